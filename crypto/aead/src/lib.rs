@@ -106,6 +106,15 @@ pub fn get_encryption_key(password: &str, salt: &str) -> Result<LessSafeKey> {
     Ok(LessSafeKey::new(key))
 }
 
+/// Get the key for [`encrypt`] and [`decrypt`] from a secret that is already
+/// uniformly random, so unlike a password it needs no key stretching
+pub fn get_encryption_key_from_secret(secret: &[u8; 32]) -> LessSafeKey {
+    LessSafeKey::new(
+        UnboundKey::new(&ring::aead::CHACHA20_POLY1305, secret)
+            .expect("ChaCha20-Poly1305 keys are 32 bytes"),
+    )
+}
+
 /// Generates a B64-encoded random salt string of the recommended 16 byte length
 pub fn random_salt() -> String {
     SaltString::generate(OsRng).to_string()
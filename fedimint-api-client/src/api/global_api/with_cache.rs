@@ -33,6 +33,7 @@
 use fedimint_core::session_outcome::{
     AcceptedItem, SessionOutcome, SessionStatus, SessionStatusV2,
 };
+use fedimint_core::setup_code::ModuleSetupParams;
 use fedimint_core::task::{MaybeSend, MaybeSync};
 use fedimint_core::transaction::{SerdeTransaction, Transaction, TransactionSubmissionOutcome};
 use fedimint_core::util::SafeUrl;
@@ -383,6 +384,7 @@ async fn set_local_params(
         federation_name: Option<String>,
         disable_base_fees: Option<bool>,
         enabled_modules: Option<BTreeSet<ModuleKind>>,
+        module_params: Option<ModuleSetupParams>,
         auth: ApiAuth,
     ) -> FederationResult<String> {
         self.request_admin(
@@ -392,6 +394,7 @@ async fn set_local_params(
                 federation_name,
                 disable_base_fees,
                 enabled_modules,
+                module_params,
             }),
             auth,
         )
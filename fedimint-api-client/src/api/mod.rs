@@ -29,6 +29,7 @@
 };
 use fedimint_core::net::api_announcement::SignedApiAnnouncement;
 use fedimint_core::session_outcome::{SessionOutcome, SessionStatus};
+use fedimint_core::setup_code::ModuleSetupParams;
 use fedimint_core::task::{MaybeSend, MaybeSync};
 use fedimint_core::transaction::{Transaction, TransactionSubmissionOutcome};
 use fedimint_core::util::backoff_util::api_networking_backoff;
@@ -498,6 +499,7 @@ async fn set_local_params(
         federation_name: Option<String>,
         disable_base_fees: Option<bool>,
         enabled_modules: Option<BTreeSet<ModuleKind>>,
+        module_params: Option<ModuleSetupParams>,
         auth: ApiAuth,
     ) -> FederationResult<String>;
 
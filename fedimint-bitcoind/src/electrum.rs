@@ -0,0 +1,799 @@
+use std::cmp::{max, min};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context as _, ensure};
+use bitcoin::consensus::encode::{deserialize, serialize};
+use bitcoin::hashes::{Hash as _, HashEngine as _};
+use bitcoin::merkle_tree::PartialMerkleTree;
+use bitcoin::{Amount, Block, BlockHash, ScriptBuf, Transaction, TxMerkleNode, Txid};
+use electrum_client::{Batch, Client, ElectrumApi, Param};
+use fedimint_core::task::block_in_place;
+use fedimint_core::txoproof::TxOutProof;
+use fedimint_core::util::SafeUrl;
+use fedimint_core::{Feerate, apply, async_trait_maybe_send};
+use fedimint_logging::LOG_BITCOIND_ELECTRUM;
+use tracing::debug;
+
+use crate::{BlockchainInfo, IBitcoindRpc, format_err};
+
+/// Maximum number of transactions we return for a script, same as for esplora
+const MAX_TX_HISTORY: usize = 1000;
+
+/// Maximum number of requests we send to the server in a single batch
+const BATCH_SIZE: usize = 100;
+
+/// Maximum number of block hashes we remember the height of, see
+/// [`ElectrumClient::get_block`]
+const MAX_CACHED_BLOCK_HEIGHTS: usize = 1024;
+
+/// Bitcoin backend speaking the Electrum protocol, as served by electrs or
+/// Fulcrum
+pub struct ElectrumClient {
+    /// The `tcp://host:port` or `ssl://host:port` address of the server
+    url: String,
+    /// Connected on first use, so creating a client never blocks on the
+    /// network
+    client: Mutex<Option<Arc<Client>>>,
+    /// The Electrum protocol can only look up blocks by height, so we remember
+    /// the heights of the block hashes we handed out
+    block_heights: Mutex<HashMap<BlockHash, usize>>,
+}
+
+impl fmt::Debug for ElectrumClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ElectrumClient").finish_non_exhaustive()
+    }
+}
+
+impl ElectrumClient {
+    /// Creates a client for an Electrum server at `tcp://host:port` or
+    /// `ssl://host:port`, connecting lazily on the first request
+    pub fn new(url: &SafeUrl) -> anyhow::Result<Self> {
+        let host = url.host_str().context("Electrum url is missing a host")?;
+        let port = url.port().context("Electrum url is missing a port")?;
+
+        Ok(Self {
+            url: format!("{}://{host}:{port}", url.scheme()),
+            client: Mutex::new(None),
+            block_heights: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn client(&self) -> anyhow::Result<Arc<Client>> {
+        let mut client = self.client.lock().expect("poisoned");
+
+        if let Some(client) = client.as_ref() {
+            return Ok(client.clone());
+        }
+
+        debug!(target: LOG_BITCOIND_ELECTRUM, url = %self.url, "Connecting to Electrum server");
+
+        let connected = Arc::new(block_in_place(|| Client::new(&self.url))?);
+
+        *client = Some(connected.clone());
+
+        Ok(connected)
+    }
+
+    pub fn get_block_count(&self) -> anyhow::Result<u64> {
+        Ok(u64::try_from(self.tip_height()?)? + 1)
+    }
+
+    pub fn get_block_hash(&self, height: u64) -> anyhow::Result<BlockHash> {
+        let height = usize::try_from(height)?;
+        let client = self.client()?;
+        let block_hash = block_in_place(|| client.block_header(height))?.block_hash();
+
+        let mut block_heights = self.block_heights.lock().expect("poisoned");
+
+        if MAX_CACHED_BLOCK_HEIGHTS <= block_heights.len() {
+            block_heights.clear();
+        }
+
+        block_heights.insert(block_hash, height);
+
+        Ok(block_hash)
+    }
+
+    /// Assembles a block from its header and transactions, since the Electrum
+    /// protocol does not serve full blocks. Only blocks whose hash was
+    /// returned by [`Self::get_block_hash`] can be fetched.
+    pub fn get_block(&self, block_hash: &BlockHash) -> anyhow::Result<Block> {
+        let height = self
+            .block_heights
+            .lock()
+            .expect("poisoned")
+            .get(block_hash)
+            .copied()
+            .context("Unknown block hash, Electrum servers can only look up blocks by height")?;
+
+        let client = self.client()?;
+        let header = block_in_place(|| client.block_header(height))?;
+
+        ensure!(
+            header.block_hash() == *block_hash,
+            "Block {block_hash} is no longer part of the chain"
+        );
+
+        let tx_count = self.get_block_tx_count(height)?;
+
+        debug!(target: LOG_BITCOIND_ELECTRUM, %block_hash, height, tx_count, "Fetching block transactions");
+
+        let mut txids = Vec::with_capacity(tx_count);
+
+        for positions in (0..tx_count).collect::<Vec<_>>().chunks(BATCH_SIZE) {
+            let mut batch = Batch::default();
+
+            for pos in positions {
+                batch.raw(
+                    "blockchain.transaction.id_from_pos".to_string(),
+                    vec![Param::Usize(height), Param::Usize(*pos)],
+                );
+            }
+
+            for txid in block_in_place(|| client.batch_call(&batch))? {
+                txids.push(
+                    txid.as_str()
+                        .context("Electrum server returned an invalid txid")?
+                        .parse::<Txid>()?,
+                );
+            }
+        }
+
+        let mut txdata = Vec::with_capacity(tx_count);
+
+        for txids in txids.chunks(BATCH_SIZE) {
+            txdata.extend(block_in_place(|| client.batch_transaction_get(txids))?);
+        }
+
+        let block = Block { header, txdata };
+
+        ensure!(
+            block.check_merkle_root() && block.check_witness_commitment(),
+            "Transactions of block {block_hash} don't match its header"
+        );
+
+        Ok(block)
+    }
+
+    pub fn get_feerate(&self) -> anyhow::Result<Option<Feerate>> {
+        let client = self.client()?;
+
+        // The server returns BTC per kvB or -1 if it has no estimate
+        let btc_per_kvb = block_in_place(|| client.estimate_fee(1))?;
+
+        if btc_per_kvb <= 0.0 {
+            return Ok(None);
+        }
+
+        Ok(Some(Feerate {
+            sats_per_kvb: Amount::from_btc(btc_per_kvb)?.to_sat(),
+        }))
+    }
+
+    pub fn submit_transaction(&self, transaction: &Transaction) -> anyhow::Result<Txid> {
+        let client = self.client()?;
+
+        Ok(block_in_place(|| {
+            client.transaction_broadcast(transaction)
+        })?)
+    }
+
+    fn tip_height(&self) -> anyhow::Result<usize> {
+        let client = self.client()?;
+        let tip = block_in_place(|| client.block_headers_subscribe())?;
+
+        // We only care about the current tip, so we drop the notifications the
+        // subscription queues up for every new block
+        while block_in_place(|| client.block_headers_pop())?.is_some() {}
+
+        Ok(tip.height)
+    }
+
+    /// The number of transactions in the block at `height`. The merkle branch
+    /// of the coinbase tells us the depth of the merkle tree, which bounds the
+    /// position of the last transaction we then binary search for.
+    fn get_block_tx_count(&self, height: usize) -> anyhow::Result<usize> {
+        let client = self.client()?;
+        let depth = block_in_place(|| client.txid_from_pos_with_merkle(height, 0))?
+            .merkle
+            .len();
+
+        ensure!(depth < 32, "Merkle branch is deeper than any block");
+
+        if depth == 0 {
+            return Ok(1);
+        }
+
+        // The last transaction is in the right half of the tree
+        let mut last: usize = 1 << (depth - 1);
+        let mut upper = (1 << depth) - 1;
+
+        while last < upper {
+            let mid = last + (upper - last).div_ceil(2);
+
+            match block_in_place(|| client.txid_from_pos(height, mid)) {
+                Ok(_) => last = mid,
+                Err(electrum_client::Error::Protocol(_)) => upper = mid - 1,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(last + 1)
+    }
+
+    /// The height of the block that confirmed `txid`. The Electrum protocol
+    /// only indexes transactions by script, so we look it up in the history
+    /// of the transaction's first output.
+    fn get_tx_height(&self, txid: &Txid) -> anyhow::Result<Option<usize>> {
+        let client = self.client()?;
+        let transaction = block_in_place(|| client.transaction_get(txid))?;
+
+        let script = &transaction
+            .output
+            .first()
+            .context("Transaction has no outputs")?
+            .script_pubkey;
+
+        let history = block_in_place(|| client.script_get_history(script))?;
+
+        // Unconfirmed transactions have a height of zero or below
+        Ok(history
+            .into_iter()
+            .find(|entry| entry.tx_hash == *txid)
+            .and_then(|entry| usize::try_from(entry.height).ok())
+            .filter(|height| 0 < *height))
+    }
+}
+
+#[apply(async_trait_maybe_send!)]
+impl IBitcoindRpc for ElectrumClient {
+    async fn get_tx_block_height(&self, txid: &Txid) -> anyhow::Result<Option<u64>> {
+        Ok(self.get_tx_height(txid)?.map(|height| height as u64))
+    }
+
+    async fn watch_script_history(&self, _: &ScriptBuf) -> anyhow::Result<()> {
+        // no watching needed, the server indexes the history of all scripts
+        Ok(())
+    }
+
+    async fn get_script_history(&self, script: &ScriptBuf) -> anyhow::Result<Vec<Transaction>> {
+        let client = self.client()?;
+        let history = block_in_place(|| client.script_get_history(script))?;
+
+        if MAX_TX_HISTORY < history.len() {
+            return Err(format_err!(
+                "Script history exceeds maximum limit of {MAX_TX_HISTORY}"
+            ));
+        }
+
+        let txids = history
+            .into_iter()
+            .map(|entry| entry.tx_hash)
+            .collect::<Vec<_>>();
+
+        let mut transactions = Vec::with_capacity(txids.len());
+
+        for txids in txids.chunks(BATCH_SIZE) {
+            transactions.extend(block_in_place(|| client.batch_transaction_get(txids))?);
+        }
+
+        Ok(transactions)
+    }
+
+    async fn get_txout_proof(&self, txid: Txid) -> anyhow::Result<TxOutProof> {
+        let height = self
+            .get_tx_height(&txid)?
+            .context("Transaction is not confirmed")?;
+
+        let client = self.client()?;
+        let merkle = block_in_place(|| client.transaction_get_merkle(&txid, height))?;
+        let block_header = block_in_place(|| client.block_header(height))?;
+        let merkle_proof = partial_merkle_tree(txid, merkle.pos, &merkle.merkle)?;
+
+        let mut matches = vec![];
+        let mut indexes = vec![];
+        let merkle_root = merkle_proof.extract_matches(&mut matches, &mut indexes)?;
+
+        ensure!(
+            merkle_root == block_header.merkle_root && matches == [txid],
+            "Electrum server returned an invalid merkle proof for {txid}"
+        );
+
+        Ok(TxOutProof {
+            block_header,
+            merkle_proof,
+        })
+    }
+
+    async fn get_info(&self) -> anyhow::Result<BlockchainInfo> {
+        Ok(BlockchainInfo {
+            block_height: self.tip_height()? as u64,
+            synced: true,
+        })
+    }
+}
+
+/// Builds a [`PartialMerkleTree`] proving the inclusion of `txid` at position
+/// `pos` of a block from the merkle branch returned by
+/// `blockchain.transaction.get_merkle`.
+///
+/// The branch lacks the number of transactions in the block, which determines
+/// the shape of the tree. Wherever a node of our path is paired with itself it
+/// is the last one of its level and everywhere else its sibling exists, so we
+/// pick the smallest transaction count satisfying these constraints.
+fn partial_merkle_tree(
+    txid: Txid,
+    pos: usize,
+    branch: &[[u8; 32]],
+) -> anyhow::Result<PartialMerkleTree> {
+    let depth = branch.len();
+
+    // Also keeps the shifts below from overflowing on a malicious branch
+    ensure!(depth < 32, "Merkle branch is deeper than any block");
+
+    ensure!(
+        pos >> depth == 0,
+        "Transaction position exceeds merkle branch"
+    );
+
+    // The hex encoding of the branch is byte reversed, just like txids
+    let siblings = branch
+        .iter()
+        .map(|bytes| {
+            let mut bytes = *bytes;
+            bytes.reverse();
+            TxMerkleNode::from_byte_array(bytes)
+        })
+        .collect::<Vec<_>>();
+
+    let mut min_tx_count = if depth == 0 {
+        1
+    } else {
+        max(pos + 1, (1 << (depth - 1)) + 1)
+    };
+    let mut max_tx_count = 1 << depth;
+    let mut node = TxMerkleNode::from_raw_hash(txid.to_raw_hash());
+
+    for (level, sibling) in siblings.iter().enumerate() {
+        let index = pos >> level;
+        let leaves_up_to_node = (index + 1) << level;
+
+        let mut engine = TxMerkleNode::engine();
+
+        if index.is_multiple_of(2) {
+            if *sibling == node {
+                max_tx_count = min(max_tx_count, leaves_up_to_node);
+            } else {
+                min_tx_count = max(min_tx_count, leaves_up_to_node + 1);
+            }
+
+            engine.input(node.as_byte_array());
+            engine.input(sibling.as_byte_array());
+        } else {
+            engine.input(sibling.as_byte_array());
+            engine.input(node.as_byte_array());
+        }
+
+        node = TxMerkleNode::from_engine(engine);
+    }
+
+    ensure!(
+        min_tx_count <= max_tx_count,
+        "Merkle branch is inconsistent with any transaction count"
+    );
+
+    let path = MerklePath {
+        tx_count: min_tx_count,
+        pos,
+        txid,
+        siblings,
+    };
+
+    let mut bits = vec![];
+    let mut hashes = vec![];
+
+    path.traverse(depth, 0, &mut bits, &mut hashes);
+
+    let mut flags = vec![0u8; bits.len().div_ceil(8)];
+
+    for (i, bit) in bits.into_iter().enumerate() {
+        if bit {
+            flags[i / 8] |= 1 << (i % 8);
+        }
+    }
+
+    let mut encoded = serialize(&u32::try_from(min_tx_count)?);
+    encoded.extend(serialize(&hashes));
+    encoded.extend(serialize(&flags));
+
+    Ok(deserialize(&encoded)?)
+}
+
+/// The path from a transaction to the merkle root, see [`partial_merkle_tree`]
+struct MerklePath {
+    tx_count: usize,
+    pos: usize,
+    txid: Txid,
+    /// Sibling of the node on the path at every level, starting at the leaves
+    siblings: Vec<TxMerkleNode>,
+}
+
+impl MerklePath {
+    /// Depth first traversal in the order of bitcoind's `TraverseAndBuild`
+    fn traverse(
+        &self,
+        level: usize,
+        index: usize,
+        bits: &mut Vec<bool>,
+        hashes: &mut Vec<TxMerkleNode>,
+    ) {
+        if index != self.pos >> level {
+            bits.push(false);
+            hashes.push(self.siblings[level]);
+            return;
+        }
+
+        bits.push(true);
+
+        if level == 0 {
+            hashes.push(TxMerkleNode::from_raw_hash(self.txid.to_raw_hash()));
+            return;
+        }
+
+        self.traverse(level - 1, index * 2, bits, hashes);
+
+        if index * 2 + 1 < self.width(level - 1) {
+            self.traverse(level - 1, index * 2 + 1, bits, hashes);
+        }
+    }
+
+    fn width(&self, level: usize) -> usize {
+        (self.tx_count + (1 << level) - 1) >> level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead as _, BufReader, Write as _};
+    use std::net::TcpListener;
+
+    use bitcoin::absolute::LockTime;
+    use bitcoin::block::{Header, Version};
+    use bitcoin::consensus::encode::serialize_hex;
+    use bitcoin::hashes::{Hash as _, HashEngine as _};
+    use bitcoin::hex::DisplayHex as _;
+    use bitcoin::transaction::Version as TxVersion;
+    use bitcoin::{
+        Block, BlockHash, CompactTarget, OutPoint, ScriptBuf, Transaction, TxIn, TxMerkleNode,
+        TxOut, Txid,
+    };
+    use electrum_client::ToElectrumScriptHash as _;
+    use fedimint_core::util::SafeUrl;
+    use serde_json::{Value, json};
+
+    use super::{ElectrumClient, partial_merkle_tree};
+    use crate::IBitcoindRpc;
+
+    /// Txids in a block of `tx_count` transactions
+    fn txids(tx_count: usize) -> Vec<Txid> {
+        (0..tx_count)
+            .map(|i| Txid::hash(&i.to_le_bytes()))
+            .collect()
+    }
+
+    /// Merkle branch of the transaction at `pos` as served by Electrum servers
+    fn merkle_branch(txids: &[Txid], pos: usize) -> Vec<TxMerkleNode> {
+        let mut level = txids
+            .iter()
+            .map(|txid| TxMerkleNode::from_raw_hash(txid.to_raw_hash()))
+            .collect::<Vec<_>>();
+        let mut index = pos;
+        let mut branch = vec![];
+
+        while 1 < level.len() {
+            branch.push(*level.get(index ^ 1).unwrap_or(&level[index]));
+
+            level = level
+                .chunks(2)
+                .map(|pair| {
+                    let mut engine = TxMerkleNode::engine();
+                    engine.input(pair[0].as_byte_array());
+                    engine.input(pair.get(1).unwrap_or(&pair[0]).as_byte_array());
+                    TxMerkleNode::from_engine(engine)
+                })
+                .collect();
+            index /= 2;
+        }
+
+        branch
+    }
+
+    fn electrum_branch(branch: &[TxMerkleNode]) -> Vec<[u8; 32]> {
+        branch
+            .iter()
+            .map(|node| {
+                let mut bytes = node.to_byte_array();
+                bytes.reverse();
+                bytes
+            })
+            .collect()
+    }
+
+    #[test]
+    fn partial_merkle_tree_proves_inclusion_for_all_positions() {
+        for tx_count in 1..=33 {
+            let txids = txids(tx_count);
+            let merkle_root = bitcoin::merkle_tree::calculate_root(
+                txids
+                    .iter()
+                    .map(|txid| TxMerkleNode::from_raw_hash(txid.to_raw_hash())),
+            )
+            .unwrap();
+
+            for (pos, txid) in txids.iter().enumerate() {
+                let branch = electrum_branch(&merkle_branch(&txids, pos));
+                let proof = partial_merkle_tree(*txid, pos, &branch).unwrap();
+
+                let mut matches = vec![];
+                let mut indexes = vec![];
+
+                assert_eq!(
+                    proof.extract_matches(&mut matches, &mut indexes),
+                    Ok(merkle_root),
+                    "tx_count={tx_count} pos={pos}"
+                );
+                assert_eq!(matches, vec![*txid]);
+            }
+        }
+    }
+
+    #[test]
+    fn partial_merkle_tree_rejects_invalid_branch() {
+        let txids = txids(4);
+        let branch = electrum_branch(&merkle_branch(&txids, 1));
+
+        assert!(partial_merkle_tree(txids[1], 4, &branch).is_err());
+    }
+
+    #[test]
+    fn partial_merkle_tree_rejects_too_deep_branch() {
+        let txid = txids(1)[0];
+
+        assert!(partial_merkle_tree(txid, 0, &[[0; 32]; 32]).is_err());
+        assert!(partial_merkle_tree(txid, usize::MAX, &[[0; 32]; 64]).is_err());
+    }
+
+    /// Minimal Electrum server serving a fixed chain
+    struct MockElectrumServer {
+        blocks: Vec<Block>,
+    }
+
+    impl MockElectrumServer {
+        fn new(tx_counts: &[usize]) -> Self {
+            let mut blocks: Vec<Block> = vec![];
+
+            for (height, tx_count) in tx_counts.iter().enumerate() {
+                let txdata = (0..*tx_count)
+                    .map(|i| Transaction {
+                        version: TxVersion::TWO,
+                        lock_time: LockTime::ZERO,
+                        input: vec![TxIn {
+                            previous_output: OutPoint {
+                                txid: Txid::hash(&[height as u8, i as u8]),
+                                vout: 0,
+                            },
+                            ..Default::default()
+                        }],
+                        output: vec![TxOut {
+                            value: bitcoin::Amount::from_sat(1000),
+                            script_pubkey: ScriptBuf::from_bytes(vec![height as u8, i as u8]),
+                        }],
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut block = Block {
+                    header: Header {
+                        version: Version::TWO,
+                        prev_blockhash: blocks
+                            .last()
+                            .map_or(BlockHash::all_zeros(), Block::block_hash),
+                        merkle_root: TxMerkleNode::all_zeros(),
+                        time: 0,
+                        bits: CompactTarget::from_consensus(0x207f_ffff),
+                        nonce: 0,
+                    },
+                    txdata,
+                };
+
+                block.header.merkle_root = block.compute_merkle_root().unwrap();
+                blocks.push(block);
+            }
+
+            Self { blocks }
+        }
+
+        fn find_tx(&self, txid: &str) -> Option<(usize, usize, &Transaction)> {
+            self.blocks.iter().enumerate().find_map(|(height, block)| {
+                block
+                    .txdata
+                    .iter()
+                    .enumerate()
+                    .find(|(_, tx)| tx.compute_txid().to_string() == txid)
+                    .map(|(pos, tx)| (height, pos, tx))
+            })
+        }
+
+        fn branch_hex(&self, height: usize, pos: usize) -> Vec<String> {
+            let txids = self.blocks[height]
+                .txdata
+                .iter()
+                .map(Transaction::compute_txid)
+                .collect::<Vec<_>>();
+
+            merkle_branch(&txids, pos)
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        }
+
+        fn handle(&self, method: &str, params: &[Value]) -> Result<Value, Value> {
+            let not_found = || json!({"code": 1, "message": "not found"});
+            let usize_param = |i: usize| params[i].as_u64().unwrap() as usize;
+            let str_param = |i: usize| params[i].as_str().unwrap().to_string();
+
+            match method {
+                "blockchain.headers.subscribe" => {
+                    let height = self.blocks.len() - 1;
+                    Ok(json!({
+                        "height": height,
+                        "hex": serialize_hex(&self.blocks[height].header),
+                    }))
+                }
+                "blockchain.block.header" => self
+                    .blocks
+                    .get(usize_param(0))
+                    .map(|block| json!(serialize_hex(&block.header)))
+                    .ok_or_else(not_found),
+                "blockchain.transaction.id_from_pos" => {
+                    let (height, pos) = (usize_param(0), usize_param(1));
+                    let tx = self.blocks[height].txdata.get(pos).ok_or_else(not_found)?;
+
+                    if params.get(2).and_then(Value::as_bool).unwrap_or(false) {
+                        Ok(json!({
+                            "tx_hash": tx.compute_txid().to_string(),
+                            "merkle": self.branch_hex(height, pos),
+                        }))
+                    } else {
+                        Ok(json!(tx.compute_txid().to_string()))
+                    }
+                }
+                "blockchain.transaction.get" => self
+                    .find_tx(&str_param(0))
+                    .map(|(.., tx)| json!(serialize_hex(tx)))
+                    .ok_or_else(not_found),
+                "blockchain.transaction.get_merkle" => {
+                    let (height, pos, _) = self.find_tx(&str_param(0)).ok_or_else(not_found)?;
+
+                    Ok(json!({
+                        "block_height": height,
+                        "pos": pos,
+                        "merkle": self.branch_hex(height, pos),
+                    }))
+                }
+                "blockchain.scripthash.get_history" => {
+                    let scripthash = str_param(0);
+                    let history = self
+                        .blocks
+                        .iter()
+                        .enumerate()
+                        .flat_map(|(height, block)| {
+                            block.txdata.iter().map(move |tx| (height, tx))
+                        })
+                        .filter(|(_, tx)| {
+                            tx.output.iter().any(|out| {
+                                out.script_pubkey.to_electrum_scripthash()[..].to_lower_hex_string() == scripthash
+                            })
+                        })
+                        .map(|(height, tx)| {
+                            json!({"height": height, "tx_hash": tx.compute_txid().to_string()})
+                        })
+                        .collect::<Vec<_>>();
+
+                    Ok(json!(history))
+                }
+                "blockchain.estimatefee" => Ok(json!(0.000_123_45)),
+                _ => Err(json!({"code": -32601, "message": "unknown method"})),
+            }
+        }
+
+        /// Serves newline delimited JSON-RPC requests on a local port
+        fn spawn(self) -> SafeUrl {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let port = listener.local_addr().unwrap().port();
+
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let mut stream = stream.unwrap();
+                    let reader = BufReader::new(stream.try_clone().unwrap());
+
+                    for line in reader.lines() {
+                        let Ok(line) = line else { break };
+                        let request: Value = serde_json::from_str(&line).unwrap();
+                        let params = request["params"].as_array().cloned().unwrap_or_default();
+
+                        let response =
+                            match self.handle(request["method"].as_str().unwrap(), &params) {
+                                Ok(result) => {
+                                    json!({"jsonrpc": "2.0", "id": request["id"], "result": result})
+                                }
+                                Err(error) => {
+                                    json!({"jsonrpc": "2.0", "id": request["id"], "error": error})
+                                }
+                            };
+
+                        writeln!(stream, "{response}").unwrap();
+                    }
+                }
+            });
+
+            SafeUrl::parse(&format!("tcp://127.0.0.1:{port}")).unwrap()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn fetches_blocks_and_proofs_from_electrum_server() {
+        let server = MockElectrumServer::new(&[1, 5, 2]);
+        let block = server.blocks[1].clone();
+        let tx = block.txdata[4].clone();
+        let client = ElectrumClient::new(&server.spawn()).unwrap();
+
+        assert_eq!(client.get_block_count().unwrap(), 3);
+        assert_eq!(client.get_info().await.unwrap().block_height, 2);
+
+        let block_hash = client.get_block_hash(1).unwrap();
+        assert_eq!(block_hash, block.block_hash());
+        assert_eq!(client.get_block(&block_hash).unwrap(), block);
+
+        assert!(client.get_block(&BlockHash::all_zeros()).is_err());
+
+        assert_eq!(
+            client
+                .get_tx_block_height(&tx.compute_txid())
+                .await
+                .unwrap(),
+            Some(1)
+        );
+
+        let proof = client.get_txout_proof(tx.compute_txid()).await.unwrap();
+        assert_eq!(proof.block(), block.block_hash());
+        assert!(proof.contains_tx(tx.compute_txid()));
+
+        assert_eq!(
+            client
+                .get_script_history(&tx.output[0].script_pubkey)
+                .await
+                .unwrap(),
+            vec![tx]
+        );
+
+        assert_eq!(client.get_feerate().unwrap().unwrap().sats_per_kvb, 12_345);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn connects_on_first_request() {
+        // Reserve a port nothing listens on
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let url = SafeUrl::parse(&format!("tcp://127.0.0.1:{port}")).unwrap();
+
+        let client = ElectrumClient::new(&url).unwrap();
+
+        assert!(client.get_info().await.is_err());
+    }
+}
@@ -13,13 +13,15 @@
 use anyhow::{Result, format_err};
 use bitcoin::{ScriptBuf, Transaction, Txid};
 use esplora_client::{AsyncClient, Builder};
-use fedimint_core::envs::FM_FORCE_BITCOIN_RPC_URL_ENV;
+use fedimint_core::envs::{BitcoinRpcConfig, FM_FORCE_BITCOIN_RPC_URL_ENV};
 use fedimint_core::txoproof::TxOutProof;
 use fedimint_core::util::SafeUrl;
 use fedimint_core::{apply, async_trait_maybe_send};
 
 #[cfg(feature = "bitcoincore")]
 pub mod bitcoincore;
+#[cfg(feature = "electrum")]
+pub mod electrum;
 
 #[derive(Debug, Clone)]
 pub struct BlockchainInfo {
@@ -37,6 +39,24 @@ pub fn create_esplora_rpc(url: &SafeUrl) -> Result<DynBitcoindRpc> {
     Ok(EsploraClient::new(&url)?.into_dyn())
 }
 
+/// Creates a client for the backend `config.kind`, falling back to esplora for
+/// kinds clients can't talk to directly
+pub fn create_bitcoind_rpc(config: &BitcoinRpcConfig) -> Result<DynBitcoindRpc> {
+    match config.kind.as_str() {
+        #[cfg(feature = "electrum")]
+        "electrum" => {
+            let url = env::var(FM_FORCE_BITCOIN_RPC_URL_ENV)
+                .ok()
+                .map(|s| SafeUrl::parse(&s))
+                .transpose()?
+                .unwrap_or_else(|| config.url.clone());
+
+            Ok(electrum::ElectrumClient::new(&url)?.into_dyn())
+        }
+        _ => create_esplora_rpc(&config.url),
+    }
+}
+
 pub type DynBitcoindRpc = Arc<dyn IBitcoindRpc + Send + Sync>;
 
 /// Trait that allows interacting with the Bitcoin blockchain
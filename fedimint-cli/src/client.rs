@@ -1,5 +1,7 @@
 use std::collections::BTreeMap;
 use std::ffi;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
@@ -13,10 +15,14 @@
 use fedimint_core::config::{ClientModuleConfig, FederationId};
 use fedimint_core::core::{ModuleInstanceId, ModuleKind, OperationId};
 use fedimint_core::encoding::Encodable;
+use fedimint_core::util::SafeUrl;
 use fedimint_core::{Amount, BitcoinAmountOrAll, TieredCounts, TieredMulti};
 use fedimint_ln_client::cli::LnInvoiceResponse;
 use fedimint_ln_client::{LightningClientModule, LnReceiveState, OutgoingLightningPayment};
 use fedimint_logging::LOG_CLIENT;
+use fedimint_mint_client::fountain::{
+    DEFAULT_MAX_FRAGMENT_LENGTH, OOBNotesFrameEncoder, decode_oob_notes_frames,
+};
 use fedimint_mint_client::{
     MintClientModule, OOBNotes, SelectNotesWithAtleastAmount, SelectNotesWithExactAmount,
 };
@@ -24,13 +30,15 @@
 use futures::StreamExt;
 use itertools::Itertools;
 use lightning_invoice::{Bolt11InvoiceDescription, Description};
+use qrcode::QrCode;
+use qrcode::render::svg;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use time::OffsetDateTime;
 use time::format_description::well_known::iso8601;
 use tracing::{debug, info, warn};
 
-use crate::metadata_from_clap_cli;
+use crate::{metadata_from_clap_cli, receive};
 
 #[derive(Debug, Clone)]
 pub enum ModuleSelector {
@@ -82,7 +90,12 @@ pub enum ClientCmd {
     Info,
     /// Reissue notes received from a third party to avoid double spends
     Reissue {
-        oob_notes: OOBNotes,
+        #[clap(required_unless_present = "frames")]
+        oob_notes: Option<OOBNotes>,
+        /// Animated QR code frames (e.g. from `spend --frames`) to reassemble
+        /// the notes from, can be specified multiple times
+        #[clap(long = "frame", conflicts_with = "oob_notes")]
+        frames: Vec<String>,
         #[arg(long = "no-wait", action = clap::ArgAction::SetFalse)]
         wait: bool,
     },
@@ -102,6 +115,21 @@ pub enum ClientCmd {
         /// belongs to should be included in the serialized notes
         #[clap(long)]
         include_invite: bool,
+        /// Additionally split the notes into frames of an animated QR code,
+        /// for notes too large to fit into a single one
+        #[clap(long)]
+        frames: bool,
+        /// Number of frames to emit, defaults to twice the minimum number of
+        /// frames needed to reassemble the notes
+        #[clap(long, requires = "frames")]
+        frame_count: Option<usize>,
+        /// Maximum number of payload bytes per frame, defaults to
+        /// `DEFAULT_MAX_FRAGMENT_LENGTH`
+        #[clap(long, requires = "frames")]
+        max_fragment_length: Option<NonZeroUsize>,
+        /// Also render the frames as SVG QR codes
+        #[clap(long, requires = "frames")]
+        qr: bool,
     },
     /// Splits a string containing multiple e-cash notes (e.g. from the `spend`
     /// command) into ones that contain exactly one.
@@ -157,12 +185,40 @@ pub enum ClientCmd {
     /// Wait for deposit on previously generated address
     #[clap(hide = true)]
     AwaitDeposit { operation_id: OperationId },
+    /// Create a BIP-21 payment URI that can be paid either on-chain or via
+    /// the included Lightning invoice
+    Receive {
+        #[clap(long)]
+        amount: Amount,
+        #[clap(long, default_value = "")]
+        description: String,
+        /// Expiry of the Lightning invoice in seconds
+        #[clap(long, default_value_t = 60 * 60 * 24)]
+        expiry_secs: u32,
+        /// Gateway to generate the Lightning invoice, otherwise a random
+        /// online gateway is selected
+        #[clap(long)]
+        gateway: Option<SafeUrl>,
+    },
+    /// Wait for a payment URI created by `receive` to be paid
+    AwaitReceive { operation_id: OperationId },
     /// Withdraw funds from the federation
     Withdraw {
+        /// Amount to withdraw, repeat together with `--address` to pay several
+        /// recipients in one peg-out transaction
+        #[clap(long, required_unless_present = "csv")]
+        amount: Vec<BitcoinAmountOrAll>,
+        #[clap(long, required_unless_present = "csv")]
+        address: Vec<bitcoin::Address<NetworkUnchecked>>,
+        /// File with one `address,amount` recipient per line, all paid in one
+        /// peg-out transaction. Empty lines and lines starting with `#` are
+        /// ignored.
+        #[clap(long, conflicts_with_all = ["amount", "address"])]
+        csv: Option<PathBuf>,
+        /// Pay the withdrawal with the federation's next peg-out batch, which
+        /// is cheaper but takes longer
         #[clap(long)]
-        amount: BitcoinAmountOrAll,
-        #[clap(long)]
-        address: bitcoin::Address<NetworkUnchecked>,
+        batched: bool,
     },
     /// Upload the (encrypted) snapshot of mint notes to federation
     Backup {
@@ -210,7 +266,15 @@ pub async fn handle_command(
 ) -> anyhow::Result<serde_json::Value> {
     match command {
         ClientCmd::Info => get_note_summary(&client).await,
-        ClientCmd::Reissue { oob_notes, wait } => {
+        ClientCmd::Reissue {
+            oob_notes,
+            frames,
+            wait,
+        } => {
+            let oob_notes = match oob_notes {
+                Some(oob_notes) => oob_notes,
+                None => decode_oob_notes_frames(frames.iter().map(String::as_str))?,
+            };
             let amount = oob_notes.total_amount();
 
             let mint = client.get_first_module::<MintClientModule>()?;
@@ -239,6 +303,10 @@ pub async fn handle_command(
             allow_overpay,
             timeout,
             include_invite,
+            frames,
+            frame_count,
+            max_fragment_length,
+            qr,
         } => {
             warn!(
                 target: LOG_CLIENT,
@@ -281,8 +349,39 @@ pub async fn handle_command(
             };
             info!(target: LOG_CLIENT, "Spend e-cash operation: {}", operation.fmt_short());
 
+            if !frames {
+                return Ok(json!({
+                    "notes": notes,
+                }));
+            }
+
+            let mut encoder = OOBNotesFrameEncoder::new(
+                &notes,
+                max_fragment_length.map_or(DEFAULT_MAX_FRAGMENT_LENGTH, NonZeroUsize::get),
+            );
+            let frames = encoder.next_frames(frame_count.unwrap_or(2 * encoder.min_frame_count()));
+
+            if !qr {
+                return Ok(json!({
+                    "notes": notes,
+                    "frames": frames,
+                }));
+            }
+
+            // Uppercase frames can use the more compact alphanumeric QR mode
+            let qr_frames = frames
+                .iter()
+                .map(|frame| {
+                    Ok(QrCode::new(frame.to_ascii_uppercase())?
+                        .render::<svg::Color>()
+                        .build())
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
             Ok(json!({
                 "notes": notes,
+                "frames": frames,
+                "qr_frames": qr_frames,
             }))
         }
         ClientCmd::Split { oob_notes } => {
@@ -470,6 +569,27 @@ pub async fn handle_command(
 
             Ok(serde_json::to_value(()).unwrap())
         }
+        ClientCmd::Receive {
+            amount,
+            description,
+            expiry_secs,
+            gateway,
+        } => {
+            let description =
+                fedimint_lnv2_client::common::Bolt11InvoiceDescription::Direct(description);
+            let (operation_id, meta) =
+                receive::receive(&client, amount, expiry_secs, description, gateway).await?;
+
+            Ok(json!({
+                "operation_id": operation_id,
+                "uri": meta.uri,
+                "address": meta.address,
+                "invoice": meta.invoice,
+            }))
+        }
+        ClientCmd::AwaitReceive { operation_id } => {
+            Ok(serde_json::to_value(receive::await_receive(&client, operation_id).await?).unwrap())
+        }
 
         ClientCmd::Backup { metadata } => {
             let metadata = metadata_from_clap_cli(metadata)?;
@@ -525,35 +645,103 @@ struct OperationOutput {
                 "operations": operations,
             }))
         }
-        ClientCmd::Withdraw { amount, address } => {
+        ClientCmd::Withdraw {
+            amount,
+            address,
+            csv,
+            batched,
+        } => {
             let wallet_module = client.get_first_module::<WalletClientModule>()?;
-            let address = address.require_network(wallet_module.get_network())?;
-            let (amount, fees) = match amount {
-                // If the amount is "all", then we need to subtract the fees from
-                // the amount we are withdrawing
-                BitcoinAmountOrAll::All => {
-                    let balance =
-                        bitcoin::Amount::from_sat(client.get_balance_for_btc().await?.msats / 1000);
-                    let fees = wallet_module.get_withdraw_fees(&address, balance).await?;
-                    let amount = balance.checked_sub(fees.amount());
-                    if amount.is_none() {
-                        bail!("Not enough funds to pay fees");
-                    }
-                    (amount.unwrap(), fees)
+            let recipients = if let Some(path) = csv {
+                parse_withdraw_recipients(
+                    &tokio::fs::read_to_string(&path)
+                        .await
+                        .with_context(|| format!("Failed to read {}", path.display()))?,
+                )?
+            } else {
+                if address.len() != amount.len() {
+                    bail!("Every --address needs exactly one --amount");
                 }
-                BitcoinAmountOrAll::Amount(amount) => (
-                    amount,
-                    wallet_module.get_withdraw_fees(&address, amount).await?,
-                ),
+                address.into_iter().zip(amount).collect()
             };
-            let absolute_fees = fees.amount();
 
-            info!(
-                target: LOG_CLIENT,
-                "Attempting withdraw with fees: {fees:?}"
-            );
+            let (operation_id, absolute_fees) = if let [(address, amount)] = recipients.as_slice() {
+                let address = address
+                    .clone()
+                    .require_network(wallet_module.get_network())?;
+                let amount = *amount;
+                let get_fees = async |amount| {
+                    if batched {
+                        wallet_module
+                            .get_batched_withdraw_fees(&address, amount)
+                            .await
+                    } else {
+                        wallet_module.get_withdraw_fees(&address, amount).await
+                    }
+                };
+                let (amount, fees) = match amount {
+                    // If the amount is "all", then we need to subtract the fees from
+                    // the amount we are withdrawing
+                    BitcoinAmountOrAll::All => {
+                        let balance = bitcoin::Amount::from_sat(
+                            client.get_balance_for_btc().await?.msats / 1000,
+                        );
+                        let fees = get_fees(balance).await?;
+                        let amount = balance.checked_sub(fees.amount());
+                        if amount.is_none() {
+                            bail!("Not enough funds to pay fees");
+                        }
+                        (amount.unwrap(), fees)
+                    }
+                    BitcoinAmountOrAll::Amount(amount) => (amount, get_fees(amount).await?),
+                };
+                let absolute_fees = fees.amount();
+
+                info!(
+                    target: LOG_CLIENT,
+                    "Attempting withdraw with fees: {fees:?}"
+                );
+
+                let operation_id = if batched {
+                    wallet_module
+                        .withdraw_batched(&address, amount, fees, ())
+                        .await?
+                } else {
+                    wallet_module.withdraw(&address, amount, fees, ()).await?
+                };
+
+                (operation_id, absolute_fees)
+            } else {
+                if batched {
+                    bail!("Withdrawals to several recipients can't be batched");
+                }
+
+                let recipients = recipients
+                    .into_iter()
+                    .map(|(address, amount)| {
+                        let BitcoinAmountOrAll::Amount(amount) = amount else {
+                            bail!("Withdrawals to several recipients can't withdraw all funds");
+                        };
+                        Ok((
+                            address.require_network(wallet_module.get_network())?,
+                            amount,
+                        ))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+
+                let fees = wallet_module.get_multi_withdraw_fees(&recipients).await?;
+
+                info!(
+                    target: LOG_CLIENT,
+                    recipients = recipients.len(),
+                    "Attempting withdraw with fees: {fees:?}"
+                );
 
-            let operation_id = wallet_module.withdraw(&address, amount, fees, ()).await?;
+                (
+                    wallet_module.withdraw_multi(&recipients, fees, ()).await?,
+                    fees.amount(),
+                )
+            };
 
             let mut updates = wallet_module
                 .subscribe_withdraw_updates(operation_id)
@@ -573,7 +761,7 @@ struct OperationOutput {
                     WithdrawState::Failed(e) => {
                         bail!("Withdraw failed: {e}");
                     }
-                    WithdrawState::Created => {}
+                    WithdrawState::Created | WithdrawState::AwaitingBatch => {}
                 }
             }
 
@@ -677,3 +865,31 @@ pub(crate) fn time_to_iso8601(time: &SystemTime) -> String {
     .format(&iso8601::Iso8601::<ISO8601_CONFIG>)
     .expect("Couldn't format OffsetDateTime as ISO8601")
 }
+
+/// Parses withdrawal recipients from lines of `address,amount`, skipping empty
+/// lines and lines starting with `#`
+fn parse_withdraw_recipients(
+    csv: &str,
+) -> anyhow::Result<Vec<(bitcoin::Address<NetworkUnchecked>, BitcoinAmountOrAll)>> {
+    csv.lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_number, line)| {
+            let (address, amount) = line
+                .split_once(',')
+                .with_context(|| format!("Line {line_number}: expected `address,amount`"))?;
+
+            Ok((
+                address
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("Line {line_number}: invalid address"))?,
+                amount
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("Line {line_number}: invalid amount"))?,
+            ))
+        })
+        .collect()
+}
@@ -11,6 +11,7 @@
 
 mod client;
 pub mod envs;
+pub mod receive;
 mod utils;
 
 use core::fmt;
@@ -45,8 +46,8 @@
 use fedimint_core::db::{Database, DatabaseValue, IDatabaseTransactionOpsCoreTyped as _};
 use fedimint_core::encoding::Decodable;
 use fedimint_core::invite_code::InviteCode;
-use fedimint_core::module::{ApiAuth, ApiRequestErased};
-use fedimint_core::setup_code::PeerSetupCode;
+use fedimint_core::module::{AmountUnit, ApiAuth, ApiRequestErased};
+use fedimint_core::setup_code::{ModuleSetupParams, PeerSetupCode};
 use fedimint_core::transaction::Transaction;
 use fedimint_core::util::{SafeUrl, backoff_util, handle_version_hash_command, retry};
 use fedimint_core::{
@@ -455,6 +456,18 @@ enum SetupAdminCmd {
         name: String,
         #[clap(long)]
         federation_name: Option<String>,
+        /// Non-bitcoin units the mint generates keys for, only set by the
+        /// guardian setting the federation name
+        #[clap(long, value_delimiter = ',', requires = "federation_name")]
+        mint_custom_units: Vec<u64>,
+        /// Number of consensus blocks the wallet collects batched peg-outs
+        /// for, only set by the guardian setting the federation name
+        #[clap(long, requires = "federation_name")]
+        wallet_peg_out_batch_window: Option<u32>,
+        /// Use a taproot peg-in descriptor, only set by the guardian setting
+        /// the federation name
+        #[clap(long, requires = "federation_name")]
+        wallet_taproot_peg_in: bool,
     },
     AddPeer {
         info: String,
@@ -704,7 +717,7 @@ pub fn with_module<T>(mut self, r#gen: T) -> Self
 
     pub fn with_default_modules(self) -> Self {
         self.with_module(LightningClientInit::default())
-            .with_module(MintClientInit)
+            .with_module(MintClientInit::default())
             .with_module(WalletClientInit::default())
             .with_module(MetaClientInit)
             .with_module(fedimint_lnv2_client::LightningClientInit::default())
@@ -1480,13 +1493,27 @@ async fn handle_admin_setup_command(
             SetupAdminCmd::SetLocalParams {
                 name,
                 federation_name,
+                mint_custom_units,
+                wallet_peg_out_batch_window,
+                wallet_taproot_peg_in,
             } => {
+                let module_params = federation_name.is_some().then(|| ModuleSetupParams {
+                    mint_custom_units: mint_custom_units
+                        .iter()
+                        .copied()
+                        .map(AmountUnit::new_custom)
+                        .collect(),
+                    wallet_peg_out_batch_window: *wallet_peg_out_batch_window,
+                    wallet_taproot_peg_in: *wallet_taproot_peg_in,
+                });
+
                 let info = client
                     .set_local_params(
                         name.clone(),
                         federation_name.clone(),
                         None,
                         None,
+                        module_params,
                         cli.auth()?,
                     )
                     .await?;
@@ -0,0 +1,275 @@
+//! Unified receive combining an on-chain deposit address and a Lightning
+//! invoice into a single BIP-21 payment URI.
+//!
+//! Both payment methods are backed by their own module operations. A third
+//! operation of type [`UNIFIED_RECEIVE_OPERATION_TYPE`] ties them together and
+//! records which of the two got paid first. The other one is marked as
+//! superseded by an operation of type
+//! [`UNIFIED_RECEIVE_SUPERSEDED_OPERATION_TYPE`]. The superseded module
+//! operation keeps running, so funds sent to it anyway are still claimed.
+
+use std::pin::pin;
+
+use anyhow::{Context, bail};
+use bitcoin::Denomination;
+use bitcoin::address::NetworkUnchecked;
+use fedimint_client::ClientHandleArc;
+use fedimint_client::oplog::OperationLog;
+use fedimint_core::Amount;
+use fedimint_core::core::OperationId;
+use fedimint_core::util::SafeUrl;
+use fedimint_lnv2_client::common::Bolt11InvoiceDescription;
+use fedimint_lnv2_client::{FinalReceiveOperationState, LightningClientModule};
+use fedimint_wallet_client::{DepositStateV2, WalletClientModule};
+use futures::StreamExt;
+use futures::future::{Either, select};
+use lightning_invoice::Bolt11Invoice;
+use serde::{Deserialize, Serialize};
+
+#[cfg(test)]
+mod tests;
+
+/// Operation type of the operation linking a deposit and a Lightning receive
+pub const UNIFIED_RECEIVE_OPERATION_TYPE: &str = "unified-receive";
+
+/// Operation type of the operation marking the payment method of a unified
+/// receive that wasn't paid first as superseded
+pub const UNIFIED_RECEIVE_SUPERSEDED_OPERATION_TYPE: &str = "unified-receive-superseded";
+
+/// Meta of the unified receive operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnifiedReceiveMeta {
+    pub amount: Amount,
+    pub address: bitcoin::Address<NetworkUnchecked>,
+    pub invoice: Bolt11Invoice,
+    pub uri: String,
+    pub deposit_operation_id: OperationId,
+    pub lightning_operation_id: OperationId,
+}
+
+/// Extra meta attached to both module operations of a unified receive so they
+/// can be traced back to it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnifiedReceiveLink {
+    pub unified_receive: OperationId,
+}
+
+/// Meta of the operation marking a module operation as superseded
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct UnifiedReceiveSupersededMeta {
+    pub unified_receive: OperationId,
+    pub superseded: OperationId,
+}
+
+/// Outcome of a unified receive, cached as the outcome of the unified receive
+/// operation
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnifiedReceiveOutcome {
+    /// The Lightning invoice was paid first, the deposit operation is
+    /// superseded
+    Lightning {
+        operation_id: OperationId,
+        superseded: OperationId,
+    },
+    /// The deposit was claimed first, the Lightning operation is superseded
+    Deposit {
+        operation_id: OperationId,
+        #[serde(with = "bitcoin::amount::serde::as_sat")]
+        btc_deposited: bitcoin::Amount,
+        btc_out_point: bitcoin::OutPoint,
+        superseded: OperationId,
+    },
+    /// Neither payment method succeeded
+    Failed(String),
+}
+
+impl UnifiedReceiveOutcome {
+    /// The operation of the payment method that wasn't paid first, if any was
+    fn superseded(&self) -> Option<OperationId> {
+        match self {
+            UnifiedReceiveOutcome::Lightning { superseded, .. }
+            | UnifiedReceiveOutcome::Deposit { superseded, .. } => Some(*superseded),
+            UnifiedReceiveOutcome::Failed(_) => None,
+        }
+    }
+}
+
+/// Allocates a deposit address and a Lightning invoice for the same
+/// `amount` and returns the id of the unified receive operation together
+/// with its meta, which contains the BIP-21 URI to share with the payer.
+///
+/// Peg-in fees are deducted from on-chain deposits, so the amount credited
+/// for the deposit can be slightly lower than `amount`.
+pub async fn receive(
+    client: &ClientHandleArc,
+    amount: Amount,
+    expiry_secs: u32,
+    description: Bolt11InvoiceDescription,
+    gateway: Option<SafeUrl>,
+) -> anyhow::Result<(OperationId, UnifiedReceiveMeta)> {
+    let operation_id = OperationId::new_random();
+    let link = UnifiedReceiveLink {
+        unified_receive: operation_id,
+    };
+
+    let (deposit_operation_id, address, _) = client
+        .get_first_module::<WalletClientModule>()?
+        .safe_allocate_deposit_address(link.clone())
+        .await?;
+
+    let (invoice, lightning_operation_id) = client
+        .get_first_module::<LightningClientModule>()?
+        .receive(
+            amount,
+            expiry_secs,
+            description,
+            gateway,
+            serde_json::to_value(&link).expect("Serialization can't fail"),
+        )
+        .await?;
+
+    let meta = UnifiedReceiveMeta {
+        amount,
+        uri: bip21_uri(&address, amount, &invoice),
+        address: address.into_unchecked(),
+        invoice,
+        deposit_operation_id,
+        lightning_operation_id,
+    };
+
+    let mut dbtx = client.db().begin_transaction().await;
+    client
+        .operation_log()
+        .add_operation_log_entry_dbtx(
+            &mut dbtx.to_ref_nc(),
+            operation_id,
+            UNIFIED_RECEIVE_OPERATION_TYPE,
+            meta.clone(),
+        )
+        .await;
+    dbtx.commit_tx_result().await?;
+
+    Ok((operation_id, meta))
+}
+
+/// Waits for either payment method of a unified receive to be paid and
+/// records which one it was, marking the other one as superseded.
+pub async fn await_receive(
+    client: &ClientHandleArc,
+    operation_id: OperationId,
+) -> anyhow::Result<UnifiedReceiveOutcome> {
+    let operation = client
+        .operation_log()
+        .get_operation(operation_id)
+        .await
+        .context("Operation not found")?;
+
+    if operation.operation_module_kind() != UNIFIED_RECEIVE_OPERATION_TYPE {
+        bail!("Operation is not a unified receive operation");
+    }
+
+    if let Some(outcome) = operation.outcome::<UnifiedReceiveOutcome>() {
+        return Ok(outcome);
+    }
+
+    let meta = operation.meta::<UnifiedReceiveMeta>();
+
+    let lightning = client.get_first_module::<LightningClientModule>()?;
+    let lightning_claimed = async {
+        lightning
+            .await_final_receive_operation_state(meta.lightning_operation_id)
+            .await
+            .map(|state| state == FinalReceiveOperationState::Claimed)
+    };
+
+    let wallet = client.get_first_module::<WalletClientModule>()?;
+    let mut deposit_updates = wallet
+        .subscribe_deposit(meta.deposit_operation_id)
+        .await?
+        .into_stream();
+    let deposit_claimed = async {
+        while let Some(update) = deposit_updates.next().await {
+            match update {
+                DepositStateV2::Claimed {
+                    btc_deposited,
+                    btc_out_point,
+                } => return Some((btc_deposited, btc_out_point)),
+                DepositStateV2::Failed(_) => return None,
+                _ => {}
+            }
+        }
+
+        None
+    };
+
+    let lightning_outcome = UnifiedReceiveOutcome::Lightning {
+        operation_id: meta.lightning_operation_id,
+        superseded: meta.deposit_operation_id,
+    };
+    let deposit_outcome = |(btc_deposited, btc_out_point)| UnifiedReceiveOutcome::Deposit {
+        operation_id: meta.deposit_operation_id,
+        btc_deposited,
+        btc_out_point,
+        superseded: meta.lightning_operation_id,
+    };
+
+    // If one payment method fails we keep waiting for the other one
+    let outcome = match select(pin!(lightning_claimed), pin!(deposit_claimed)).await {
+        Either::Left((claimed, deposit_claimed)) => {
+            if claimed? {
+                lightning_outcome
+            } else {
+                deposit_claimed.await.map_or_else(
+                    || UnifiedReceiveOutcome::Failed("Neither payment was received".to_string()),
+                    deposit_outcome,
+                )
+            }
+        }
+        Either::Right((deposit, lightning_claimed)) => match deposit {
+            Some(deposit) => deposit_outcome(deposit),
+            None if lightning_claimed.await? => lightning_outcome,
+            None => UnifiedReceiveOutcome::Failed("Neither payment was received".to_string()),
+        },
+    };
+
+    let mut dbtx = client.db().begin_transaction().await;
+
+    // Another caller might have recorded the outcome while we were waiting
+    if let Some(outcome) = OperationLog::get_operation_dbtx(&mut dbtx.to_ref_nc(), operation_id)
+        .await
+        .and_then(|operation| operation.outcome::<UnifiedReceiveOutcome>())
+    {
+        return Ok(outcome);
+    }
+
+    if let Some(superseded) = outcome.superseded() {
+        client
+            .operation_log()
+            .add_operation_log_entry_dbtx(
+                &mut dbtx.to_ref_nc(),
+                OperationId::new_random(),
+                UNIFIED_RECEIVE_SUPERSEDED_OPERATION_TYPE,
+                UnifiedReceiveSupersededMeta {
+                    unified_receive: operation_id,
+                    superseded,
+                },
+            )
+            .await;
+    }
+
+    OperationLog::set_operation_outcome_dbtx(&mut dbtx.to_ref_nc(), operation_id, &outcome).await;
+    dbtx.commit_tx_result().await?;
+
+    Ok(outcome)
+}
+/// Builds a BIP-21 URI paying `amount` to `address` with a `lightning=`
+/// fallback to `invoice`
+fn bip21_uri(address: &bitcoin::Address, amount: Amount, invoice: &Bolt11Invoice) -> String {
+    let amount = bitcoin::Amount::from_sat(amount.msats / 1000);
+
+    format!(
+        "bitcoin:{address}?amount={}&lightning={invoice}",
+        amount.to_string_in(Denomination::Bitcoin)
+    )
+}
@@ -0,0 +1,83 @@
+use bitcoin::hashes::{Hash as _, sha256};
+use bitcoin::secp256k1::{Secp256k1, SecretKey};
+use fedimint_core::Amount;
+use fedimint_core::core::OperationId;
+use lightning_invoice::{Bolt11Invoice, Currency, InvoiceBuilder, PaymentSecret};
+
+use super::{UnifiedReceiveOutcome, bip21_uri};
+
+fn invoice(amount: Amount) -> Bolt11Invoice {
+    let secp = Secp256k1::new();
+    let sk = SecretKey::from_slice(&[1; 32]).unwrap();
+
+    InvoiceBuilder::new(Currency::Regtest)
+        .description(String::new())
+        .payment_hash(sha256::Hash::hash(&[0; 32]))
+        .current_timestamp()
+        .min_final_cltv_expiry_delta(0)
+        .payment_secret(PaymentSecret([0; 32]))
+        .amount_milli_satoshis(amount.msats)
+        .build_signed(|m| secp.sign_ecdsa_recoverable(m, &sk))
+        .unwrap()
+}
+
+fn address() -> bitcoin::Address {
+    let secp = Secp256k1::new();
+    let sk = SecretKey::from_slice(&[2; 32]).unwrap();
+
+    bitcoin::Address::p2wpkh(
+        &bitcoin::CompressedPublicKey(sk.public_key(&secp)),
+        bitcoin::Network::Regtest,
+    )
+}
+
+#[test]
+fn bip21_uri_contains_amount_in_btc_and_invoice() {
+    let address = address();
+    let invoice = invoice(Amount::from_sats(1_000));
+
+    assert_eq!(
+        bip21_uri(&address, Amount::from_sats(1_000), &invoice),
+        format!("bitcoin:{address}?amount=0.00001&lightning={invoice}")
+    );
+}
+
+#[test]
+fn bip21_uri_rounds_amount_down_to_sats() {
+    let address = address();
+    let invoice = invoice(Amount::from_msats(123_456_789));
+
+    assert_eq!(
+        bip21_uri(&address, Amount::from_msats(123_456_789), &invoice),
+        format!("bitcoin:{address}?amount=0.00123456&lightning={invoice}")
+    );
+}
+
+#[test]
+fn only_paid_receives_supersede_an_operation() {
+    let operation_id = OperationId::new_random();
+    let superseded = OperationId::new_random();
+
+    assert_eq!(
+        UnifiedReceiveOutcome::Lightning {
+            operation_id,
+            superseded,
+        }
+        .superseded(),
+        Some(superseded)
+    );
+    assert_eq!(
+        UnifiedReceiveOutcome::Deposit {
+            operation_id,
+            btc_deposited: bitcoin::Amount::from_sat(1_000),
+            btc_out_point: bitcoin::OutPoint::null(),
+            superseded,
+        }
+        .superseded(),
+        Some(superseded)
+    );
+    assert_eq!(
+        UnifiedReceiveOutcome::Failed("expired".to_string()).superseded(),
+        None
+    );
+}
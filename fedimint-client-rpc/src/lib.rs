@@ -183,7 +183,7 @@ fn remove_rpc_handle(&self, request_id: u64) -> Option<AbortHandle> {
 
     async fn client_builder() -> Result<fedimint_client::ClientBuilder, anyhow::Error> {
         let mut builder = fedimint_client::Client::builder().await?;
-        builder.with_module(MintClientInit);
+        builder.with_module(MintClientInit::default());
         builder.with_module(LightningClientInit::default());
         builder.with_module(WalletClientInit(None));
         builder.with_module(MetaClientInit);
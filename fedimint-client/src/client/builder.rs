@@ -1366,7 +1366,7 @@ pub fn config(&self) -> &ClientConfig {
     /// let preview = Client::builder().await
     ///     // Mount the modules the client should support:
     ///     // .with_module(LightningClientInit)
-    ///     // .with_module(MintClientInit)
+    ///     // .with_module(MintClientInit::default())
     ///     // .with_module(WalletClientInit::default())
     ///      .expect("Error building client")
     ///      .preview(connectors, &invite_code).await?;
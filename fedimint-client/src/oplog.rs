@@ -182,11 +182,23 @@ pub async fn set_operation_outcome(
         operation_id: OperationId,
         outcome: &(impl Serialize + Debug),
     ) -> anyhow::Result<()> {
+        let mut dbtx = db.begin_transaction().await;
+        Self::set_operation_outcome_dbtx(&mut dbtx.to_ref_nc(), operation_id, outcome).await;
+        dbtx.commit_tx_result().await?;
+
+        Ok(())
+    }
+
+    /// Sets the outcome of an operation as part of `dbtx`
+    pub async fn set_operation_outcome_dbtx(
+        dbtx: &mut DatabaseTransaction<'_>,
+        operation_id: OperationId,
+        outcome: &(impl Serialize + Debug),
+    ) {
         let outcome_json =
             JsonStringed(serde_json::to_value(outcome).expect("Outcome is not serializable"));
 
-        let mut dbtx = db.begin_transaction().await;
-        let mut operation = Self::get_operation_dbtx(&mut dbtx.to_ref_nc(), operation_id)
+        let mut operation = Self::get_operation_dbtx(dbtx, operation_id)
             .await
             .expect("Operation exists");
         operation.set_outcome(OperationOutcome {
@@ -195,9 +207,6 @@ pub async fn set_operation_outcome(
         });
         dbtx.insert_entry(&OperationLogKey { operation_id }, &operation)
             .await;
-        dbtx.commit_tx_result().await?;
-
-        Ok(())
     }
 
     /// Returns an a [`UpdateStreamOrOutcome`] enum that can be converted into
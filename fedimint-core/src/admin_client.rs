@@ -5,6 +5,7 @@
 
 use crate::core::ModuleKind;
 use crate::encoding::{Decodable, Encodable};
+use crate::setup_code::ModuleSetupParams;
 
 /// The state of the server returned via APIs
 #[derive(Debug, Clone, Default, Serialize, Deserialize, Eq, PartialEq, Encodable, Decodable)]
@@ -52,6 +53,9 @@ pub struct SetLocalParamsRequest {
     /// Modules enabled by the leader (if None, all available modules are
     /// enabled)
     pub enabled_modules: Option<BTreeSet<ModuleKind>>,
+    /// Module settings, set by the leader
+    #[serde(default)]
+    pub module_params: Option<ModuleSetupParams>,
 }
 
 /// Archive of all the guardian config files that can be used to recover a lost
@@ -6,6 +6,7 @@
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
+use super::AmountUnit;
 use crate::db::{
     DatabaseKey, DatabaseLookup, DatabaseRecord, DatabaseTransaction,
     IDatabaseTransactionOpsCoreTyped,
@@ -18,14 +19,22 @@ pub struct Audit {
 }
 
 impl Audit {
+    /// Net assets of the federation in [`AmountUnit::BITCOIN`]
     pub fn net_assets(&self) -> Option<AuditItem> {
         Some(AuditItem {
             name: "Net assets (sats)".to_string(),
             milli_sat: calculate_net_assets(self.items.iter())?,
             module_instance_id: None,
+            unit: AmountUnit::BITCOIN,
         })
     }
 
+    /// Net assets of the federation in every non-bitcoin [`AmountUnit`] any
+    /// module reported items for
+    pub fn custom_unit_net_assets(&self) -> Option<BTreeMap<AmountUnit, i64>> {
+        calculate_custom_unit_net_assets(self.items.iter())
+    }
+
     pub async fn add_items<KP, F>(
         &mut self,
         dbtx: &mut DatabaseTransaction<'_>,
@@ -47,6 +56,44 @@ pub async fn add_items<KP, F>(
                     name,
                     milli_sat,
                     module_instance_id: Some(module_instance_id),
+                    unit: AmountUnit::BITCOIN,
+                }
+            })
+            .collect::<Vec<AuditItem>>()
+            .await;
+        self.items.append(&mut new_items);
+    }
+
+    /// Like [`Self::add_items`], but for modules tracking liabilities in more
+    /// than one [`AmountUnit`]
+    ///
+    /// Only [`AmountUnit::BITCOIN`] items count towards [`Self::net_assets`],
+    /// other units are balanced separately.
+    pub async fn add_unit_items<KP, F>(
+        &mut self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        module_instance_id: ModuleInstanceId,
+        key_prefix: &KP,
+        to_unit_milli_sat: F,
+    ) where
+        KP: DatabaseLookup + 'static + MaybeSend + MaybeSync,
+        KP::Record: DatabaseKey,
+        F: Fn(
+            KP::Record,
+            <<KP as DatabaseLookup>::Record as DatabaseRecord>::Value,
+        ) -> (AmountUnit, i64),
+    {
+        let mut new_items = dbtx
+            .find_by_prefix(key_prefix)
+            .await
+            .map(|(key, value)| {
+                let name = format!("{key:?}");
+                let (unit, milli_sat) = to_unit_milli_sat(key, value);
+                AuditItem {
+                    name,
+                    milli_sat,
+                    module_instance_id: Some(module_instance_id),
+                    unit,
                 }
             })
             .collect::<Vec<AuditItem>>()
@@ -73,12 +120,20 @@ pub struct AuditItem {
     pub name: String,
     pub milli_sat: i64,
     pub module_instance_id: Option<ModuleInstanceId>,
+    pub unit: AmountUnit,
 }
 
 impl Display for AuditItem {
     fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
         let sats = (self.milli_sat as f64) / 1000.0;
-        formatter.write_fmt(format_args!("{:>+15.3}|{}", sats, self.name))
+        if self.unit.is_bitcoin() {
+            formatter.write_fmt(format_args!("{:>+15.3}|{}", sats, self.name))
+        } else {
+            formatter.write_fmt(format_args!(
+                "{:>+15.3}|{} ({:?})",
+                sats, self.name, self.unit
+            ))
+        }
     }
 }
 
@@ -86,6 +141,10 @@ fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
 pub struct AuditSummary {
     pub net_assets: i64,
     pub module_summaries: BTreeMap<ModuleInstanceId, ModuleSummary>,
+    /// Net assets per non-bitcoin [`AmountUnit`], these are not included in
+    /// `net_assets`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom_unit_net_assets: BTreeMap<AmountUnit, i64>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
@@ -110,6 +169,9 @@ pub fn from_audit(
                 audit.items.iter().chain(&empty_module_placeholders),
                 module_instance_id_to_kind,
             ),
+            custom_unit_net_assets: audit
+                .custom_unit_net_assets()
+                .expect("We'd have crashed already if there was an overflow"),
         }
     }
 }
@@ -145,10 +207,24 @@ fn generate_module_summaries<'a>(
 
 fn calculate_net_assets<'a>(items: impl Iterator<Item = &'a AuditItem>) -> Option<i64> {
     items
+        .filter(|item| item.unit.is_bitcoin())
         .map(|item| item.milli_sat)
         .try_fold(0i64, i64::checked_add)
 }
 
+fn calculate_custom_unit_net_assets<'a>(
+    items: impl Iterator<Item = &'a AuditItem>,
+) -> Option<BTreeMap<AmountUnit, i64>> {
+    items.filter(|item| !item.unit.is_bitcoin()).try_fold(
+        BTreeMap::new(),
+        |mut net_assets, item| {
+            let unit_net_assets = net_assets.entry(item.unit).or_insert(0i64);
+            *unit_net_assets = unit_net_assets.checked_add(item.milli_sat)?;
+            Some(net_assets)
+        },
+    )
+}
+
 // Adding a placeholder ensures that a ModuleSummary exists even if the module
 // does not have any AuditItems (e.g. from a lack of activity, db compaction,
 // etc), which is useful for downstream consumers of AuditSummaries.
@@ -157,6 +233,7 @@ fn create_empty_module_placeholder(module_instance_id: ModuleInstanceId) -> Audi
         name: "Module placeholder".to_string(),
         milli_sat: 0,
         module_instance_id: Some(module_instance_id),
+        unit: AmountUnit::BITCOIN,
     }
 }
 
@@ -168,36 +245,43 @@ fn creates_audit_summary_from_audit() {
                 name: "ContractKey(...)".to_string(),
                 milli_sat: -101_000,
                 module_instance_id: Some(0),
+                unit: AmountUnit::BITCOIN,
             },
             AuditItem {
                 name: "IssuanceTotal".to_string(),
                 milli_sat: -50_100_000,
                 module_instance_id: Some(1),
+                unit: AmountUnit::BITCOIN,
             },
             AuditItem {
                 name: "Redemption(...)".to_string(),
                 milli_sat: 101_000,
                 module_instance_id: Some(1),
+                unit: AmountUnit::BITCOIN,
             },
             AuditItem {
                 name: "RedemptionTotal".to_string(),
                 milli_sat: 100_000,
                 module_instance_id: Some(1),
+                unit: AmountUnit::BITCOIN,
             },
             AuditItem {
                 name: "UTXOKey(...)".to_string(),
                 milli_sat: 20_000_000,
                 module_instance_id: Some(2),
+                unit: AmountUnit::BITCOIN,
             },
             AuditItem {
                 name: "UTXOKey(...)".to_string(),
                 milli_sat: 10_000_000,
                 module_instance_id: Some(2),
+                unit: AmountUnit::BITCOIN,
             },
             AuditItem {
                 name: "UTXOKey(...)".to_string(),
                 milli_sat: 20_000_000,
                 module_instance_id: Some(2),
+                unit: AmountUnit::BITCOIN,
             },
         ],
     };
@@ -235,6 +319,7 @@ fn creates_audit_summary_from_audit() {
                 },
             ),
         ]),
+        custom_unit_net_assets: BTreeMap::new(),
     };
 
     assert_eq!(audit_summary, expected_audit_summary);
@@ -275,7 +360,44 @@ fn audit_summary_includes_placeholders() {
                 },
             ),
         ]),
+        custom_unit_net_assets: BTreeMap::new(),
     };
 
     assert_eq!(audit_summary, expected_audit_summary);
 }
+
+#[test]
+fn audit_summary_separates_custom_units() {
+    let custom_unit = AmountUnit::new_custom(1);
+    let audit = Audit {
+        items: vec![
+            AuditItem {
+                name: "IssuanceTotal".to_string(),
+                milli_sat: -1_000,
+                module_instance_id: Some(0),
+                unit: AmountUnit::BITCOIN,
+            },
+            AuditItem {
+                name: "UnitIssuanceTotal(AmountUnit(1))".to_string(),
+                milli_sat: -5_000,
+                module_instance_id: Some(0),
+                unit: custom_unit,
+            },
+            AuditItem {
+                name: "UnitRedemptionTotal(AmountUnit(1))".to_string(),
+                milli_sat: 2_000,
+                module_instance_id: Some(0),
+                unit: custom_unit,
+            },
+        ],
+    };
+
+    let audit_summary = AuditSummary::from_audit(&audit, &HashMap::from([(0, "mint".to_string())]));
+
+    assert_eq!(audit_summary.net_assets, -1_000);
+    assert_eq!(audit_summary.module_summaries[&0].net_assets, -1_000);
+    assert_eq!(
+        audit_summary.custom_unit_net_assets,
+        BTreeMap::from([(custom_unit, -3_000)])
+    );
+}
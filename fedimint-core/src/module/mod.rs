@@ -93,6 +93,11 @@ pub fn new_custom(unit: u64) -> Self {
     pub const fn bitcoin() -> Self {
         Self::BITCOIN
     }
+
+    /// The numeric id of the unit, e.g. for use in key derivation paths
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
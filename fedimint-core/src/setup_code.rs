@@ -1,9 +1,10 @@
 use std::collections::BTreeSet;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::core::ModuleKind;
 use crate::encoding::{Decodable, Encodable};
+use crate::module::AmountUnit;
 use crate::util::SafeUrl;
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Encodable, Decodable, Serialize)]
@@ -20,6 +21,34 @@ pub struct PeerSetupCode {
     /// Modules enabled by the leader (if None, all available modules are
     /// enabled)
     pub enabled_modules: Option<BTreeSet<ModuleKind>>,
+    /// Module settings, set by the leader
+    pub module_params: Option<ModuleSetupParams>,
+}
+
+/// Module settings the leader chooses during setup, all guardians generate
+/// their module configs from the same settings
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Encodable,
+    Decodable,
+    Serialize,
+    Deserialize,
+)]
+pub struct ModuleSetupParams {
+    /// Non-bitcoin units the mint generates keys for
+    pub mint_custom_units: BTreeSet<AmountUnit>,
+    /// Number of consensus blocks the wallet collects batched peg-outs for,
+    /// peg-outs are not batched if unset
+    pub wallet_peg_out_batch_window: Option<u32>,
+    /// Whether the wallet uses a taproot instead of a segwit v0 peg-in
+    /// descriptor
+    pub wallet_taproot_peg_in: bool,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Encodable, Decodable, Serialize)]
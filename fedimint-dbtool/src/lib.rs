@@ -147,7 +147,7 @@ pub fn with_default_modules_inits(self) -> Self {
             .with_server_module_init(fedimint_lnv2_server::LightningInit)
             .with_server_module_init(MetaInit)
             .with_client_module_init(WalletClientInit::default())
-            .with_client_module_init(MintClientInit)
+            .with_client_module_init(MintClientInit::default())
             .with_client_module_init(LightningClientInit::default())
             .with_client_module_init(fedimint_lnv2_client::LightningClientInit::default())
             .with_client_module_init(MetaClientInit)
@@ -56,6 +56,11 @@ pub fn new(message: &[u8], max_fragment_length: usize) -> Self {
         }
     }
 
+    /// Returns the number of fragments the message was split into
+    pub fn fragment_count(&self) -> usize {
+        self.fragments.len()
+    }
+
     /// Returns the next fragment to be emitted by the fountain encoder.
     /// After all fragments of the original message have been emitted once,
     /// the fountain encoder will emit the result of xoring together the
@@ -25,6 +25,12 @@ pub fn new(encodable: impl Encodable, max_fragment_length: usize) -> Self {
         }
     }
 
+    /// The number of fragments the payload was split into, which is the
+    /// minimum number of fragments a decoder has to receive
+    pub fn fragment_count(&self) -> usize {
+        self.encoder.fragment_count()
+    }
+
     /// Fragments never repeat, so this can be called indefinitely
     pub fn next_fragment(&mut self) -> Fragment {
         self.encoder.next_fragment()
@@ -156,7 +156,7 @@ pub async fn build_client(
         .await?
         .with_iroh_enable_dht(false)
         .with_iroh_enable_dht(false);
-    client_builder.with_module(MintClientInit);
+    client_builder.with_module(MintClientInit::default());
     client_builder.with_module(LightningClientInit::default());
     client_builder.with_module(WalletClientInit::default());
     let client_secret = Client::load_or_generate_client_secret(&db).await?;
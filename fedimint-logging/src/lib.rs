@@ -63,6 +63,7 @@
 pub const LOG_LIGHTNING: &str = "fm::gw::lightning";
 pub const LOG_BITCOIND_ESPLORA: &str = "fm::bitcoind::esplora";
 pub const LOG_BITCOIND_CORE: &str = "fm::bitcoind::bitcoincore";
+pub const LOG_BITCOIND_ELECTRUM: &str = "fm::bitcoind::electrum";
 pub const LOG_BITCOIND: &str = "fm::bitcoind";
 pub const LOG_BITCOIN: &str = "fm::bitcoin";
 
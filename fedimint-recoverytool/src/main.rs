@@ -6,20 +6,22 @@
 use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
 
-use anyhow::{Context, anyhow};
-use bitcoin::OutPoint;
+use anyhow::{Context, anyhow, ensure};
+use bitcoin::address::NetworkUnchecked;
 use bitcoin::network::Network;
+use bitcoin::psbt::Psbt;
 use bitcoin::secp256k1::{PublicKey, SECP256K1, SecretKey};
+use bitcoin::{Address, OutPoint};
 use clap::{ArgGroup, Parser, Subcommand};
 use fedimint_core::core::ModuleInstanceId;
 use fedimint_core::db::{Database, IDatabaseTransactionOpsCoreTyped};
 use fedimint_core::epoch::ConsensusItem;
-use fedimint_core::fedimint_build_code_version_env;
 use fedimint_core::module::CommonModuleInit;
 use fedimint_core::module::registry::{ModuleDecoderRegistry, ModuleRegistry};
 use fedimint_core::session_outcome::SignedSessionOutcome;
 use fedimint_core::transaction::Transaction;
 use fedimint_core::util::handle_version_hash_command;
+use fedimint_core::{Feerate, fedimint_build_code_version_env};
 use fedimint_logging::TracingSetup;
 use fedimint_rocksdb::RocksDbReadOnly;
 use fedimint_server::config::ServerConfig;
@@ -33,11 +35,12 @@
     PegInDescriptor, SpendableUTXO, WalletCommonInit, WalletInput,
 };
 use fedimint_wallet_server::db::{UTXOKey, UTXOPrefixKey};
-use fedimint_wallet_server::{Wallet, nonce_from_idx};
+use fedimint_wallet_server::{RecoveryWallet, Wallet, nonce_from_idx};
 use futures::stream::StreamExt;
 use hex::FromHex;
+use miniscript::psbt::PsbtExt as _;
 use miniscript::{Descriptor, MiniscriptKey, TranslatePk, Translator};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tracing::info;
 
 use crate::envs::FM_PASSWORD_ENV;
@@ -69,7 +72,17 @@ struct RecoveryTool {
     #[arg(long, default_value = "bitcoin", requires = "descriptor")]
     network: Network,
     #[command(subcommand)]
-    strategy: TweakSource,
+    command: Command,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum Command {
+    #[command(flatten)]
+    Tweaks(TweakSource),
+    /// Sweep the on-chain wallet to a single address with a PSBT that a
+    /// threshold of guardians signs offline
+    #[command(subcommand)]
+    Sweep(SweepCommand),
 }
 
 #[derive(Debug, Clone, Subcommand)]
@@ -99,6 +112,72 @@ enum TweakSource {
     },
 }
 
+#[derive(Debug, Clone, Subcommand)]
+enum SweepCommand {
+    /// Create an unsigned PSBT spending all given UTXOs to the destination
+    /// address
+    #[command(group(
+        ArgGroup::new("utxosource")
+            .required(true)
+            .args(["db", "utxos"]),
+    ))]
+    Create {
+        /// Extract UTXOs from a database without module partitioning
+        #[arg(long, requires = "db")]
+        legacy: bool,
+        /// Path to database to sweep all confirmed UTXOs from
+        #[arg(long)]
+        db: Option<PathBuf>,
+        /// JSON file listing the UTXOs to sweep as objects with an `outpoint`,
+        /// a hex encoded `tweak` and an `amount_sat`
+        #[arg(long)]
+        utxos: Option<PathBuf>,
+        /// Address receiving the swept funds
+        #[arg(long)]
+        address: Address<NetworkUnchecked>,
+        /// Fee rate in sats/vB
+        #[arg(long)]
+        fee_rate: u64,
+    },
+    /// Add this guardian's signatures to a sweep PSBT after checking that it
+    /// pays the agreed address and fee
+    Sign {
+        /// Base64 encoded PSBT
+        psbt: Psbt,
+        /// Address the swept funds have to be paid to
+        #[arg(long)]
+        address: Address<NetworkUnchecked>,
+        /// Maximum fee in sats the sweep may pay
+        #[arg(long)]
+        max_fee_sat: u64,
+    },
+    /// Combine the sweep PSBTs signed by individual guardians and finalize the
+    /// transaction once a threshold of guardians signed
+    Combine {
+        /// Base64 encoded PSBTs
+        #[arg(required = true)]
+        psbts: Vec<Psbt>,
+    },
+}
+
+/// A UTXO to sweep, as listed in the file passed to `sweep create --utxos`
+#[derive(Debug, Deserialize)]
+struct SweepUtxo {
+    outpoint: OutPoint,
+    #[serde(with = "::fedimint_core::encoding::as_hex")]
+    tweak: [u8; 33],
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    amount_sat: bitcoin::Amount,
+}
+
+/// The combined sweep PSBT, and the transaction ready to be broadcast once it
+/// could be finalized
+#[derive(Debug, Serialize)]
+struct SweepResult {
+    psbt: String,
+    transaction: Option<String>,
+}
+
 fn tweak_parser(hex: &str) -> anyhow::Result<[u8; 33]> {
     <Vec<u8> as FromHex>::from_hex(hex)?
         .try_into()
@@ -159,14 +238,125 @@ async fn main() -> anyhow::Result<()> {
         panic!("Either config or descriptor need to be provided by clap");
     };
 
-    process_and_print_tweak_source(
-        &opts.strategy,
-        &base_descriptor,
-        &base_key,
-        network,
-        wallet_module_id,
-    )
-    .await;
+    match opts.command {
+        Command::Tweaks(tweak_source) => {
+            process_and_print_tweak_source(
+                &tweak_source,
+                &base_descriptor,
+                &base_key,
+                network,
+                wallet_module_id,
+            )
+            .await;
+        }
+        Command::Sweep(sweep_command) => {
+            let wallet = RecoveryWallet::new(base_descriptor, base_key);
+
+            process_and_print_sweep_command(sweep_command, &wallet, network, wallet_module_id)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn process_and_print_sweep_command(
+    sweep_command: SweepCommand,
+    wallet: &RecoveryWallet,
+    network: Network,
+    wallet_module_id: ModuleInstanceId,
+) -> anyhow::Result<()> {
+    match sweep_command {
+        SweepCommand::Create {
+            legacy,
+            db,
+            utxos,
+            address,
+            fee_rate,
+        } => {
+            let address = address
+                .require_network(network)
+                .context("Address is for a different network")?;
+
+            let utxos = sweep_utxos(db, legacy, utxos, wallet_module_id).await?;
+
+            let fee_rate = Feerate {
+                sats_per_kvb: fee_rate.checked_mul(1000).context("Fee rate is too high")?,
+            };
+
+            let psbt = wallet.create_sweep_psbt(&utxos, address.script_pubkey(), fee_rate)?;
+
+            info!(
+                inputs = utxos.len(),
+                txid = %psbt.unsigned_tx.compute_txid(),
+                "Created sweep PSBT"
+            );
+
+            println!("{psbt}");
+        }
+        SweepCommand::Sign {
+            mut psbt,
+            address,
+            max_fee_sat,
+        } => {
+            let address = address
+                .require_network(network)
+                .context("Address is for a different network")?;
+
+            wallet.sign_sweep_psbt(
+                &mut psbt,
+                &address.script_pubkey(),
+                bitcoin::Amount::from_sat(max_fee_sat),
+            )?;
+
+            info!(
+                txid = %psbt.unsigned_tx.compute_txid(),
+                %address,
+                amount = %psbt.unsigned_tx.output[0].value,
+                fee = %psbt.fee()?,
+                "Signed sweep PSBT"
+            );
+
+            println!("{psbt}");
+        }
+        SweepCommand::Combine { psbts } => {
+            let mut psbts = psbts.into_iter();
+            let mut psbt = psbts.next().expect("clap requires at least one PSBT");
+
+            for other in psbts {
+                ensure!(
+                    psbt.unsigned_tx == other.unsigned_tx,
+                    "PSBTs sweep different transactions"
+                );
+
+                psbt.combine(other)?;
+            }
+
+            let mut finalized = psbt.clone();
+
+            let transaction = match finalized.finalize_mut(SECP256K1) {
+                Ok(()) => Some(bitcoin::consensus::encode::serialize_hex(
+                    &finalized.extract_tx()?,
+                )),
+                Err(errors) => {
+                    info!(
+                        errors = errors.len(),
+                        "Sweep PSBT lacks a threshold of signatures"
+                    );
+                    None
+                }
+            };
+
+            serde_json::to_writer(
+                std::io::stdout().lock(),
+                &SweepResult {
+                    psbt: psbt.to_string(),
+                    transaction,
+                },
+            )
+            .expect("Could not encode to stdout");
+        }
+    }
 
     Ok(())
 }
@@ -279,6 +469,62 @@ async fn process_and_print_tweak_source(
     }
 }
 
+/// The UTXOs to sweep, either all confirmed UTXOs in the wallet `db` or the
+/// ones listed in the `utxos` file
+async fn sweep_utxos(
+    db: Option<PathBuf>,
+    legacy: bool,
+    utxos: Option<PathBuf>,
+    wallet_module_id: ModuleInstanceId,
+) -> anyhow::Result<Vec<(UTXOKey, SpendableUTXO)>> {
+    let utxos = match (db, utxos) {
+        (Some(db), None) => {
+            let db = get_db(&db, ModuleRegistry::default()).await;
+
+            let db = if legacy {
+                db
+            } else {
+                db.with_prefix_module_id(wallet_module_id).0
+            };
+
+            db.begin_transaction_nc()
+                .await
+                .find_by_prefix(&UTXOPrefixKey)
+                .await
+                .collect::<Vec<_>>()
+                .await
+        }
+        (None, Some(utxos)) => {
+            let utxos: Vec<SweepUtxo> = serde_json::from_reader(
+                std::fs::File::open(&utxos).context("Could not open UTXO file")?,
+            )
+            .context("Could not parse UTXO file")?;
+
+            utxos
+                .into_iter()
+                .map(
+                    |SweepUtxo {
+                         outpoint,
+                         tweak,
+                         amount_sat,
+                     }| {
+                        (
+                            UTXOKey(outpoint),
+                            SpendableUTXO {
+                                tweak,
+                                amount: amount_sat,
+                            },
+                        )
+                    },
+                )
+                .collect()
+        }
+        _ => panic!("Either db or utxos need to be provided by clap"),
+    };
+
+    Ok(utxos)
+}
+
 fn input_tweaks_and_peg_out_count(
     transactions: impl Iterator<Item = Transaction>,
     wallet_module_id: ModuleInstanceId,
@@ -413,3 +659,36 @@ fn parses_valid_length_tweaks() {
     );
     assert!(tweak_parser(bad_length_tweak_hex.as_str()).is_err());
 }
+
+#[test]
+fn injects_secret_key_into_taproot_descriptor() {
+    use std::collections::BTreeMap;
+
+    use fedimint_core::PeerId;
+    use fedimint_wallet_server::common::config::PegInDescriptorKind;
+
+    let secret_keys = (0..4u8)
+        .map(|peer| SecretKey::from_slice(&[peer + 1; 32]).expect("Valid secret key"))
+        .collect::<Vec<_>>();
+    let pubkeys = (0..)
+        .map(PeerId::from)
+        .zip(&secret_keys)
+        .map(|(peer, secret_key)| {
+            (
+                peer,
+                CompressedPublicKey::new(secret_key.public_key(SECP256K1)),
+            )
+        })
+        .collect::<BTreeMap<_, _>>();
+    let descriptor = PegInDescriptorKind::Taproot.descriptor(&pubkeys, 3);
+    let tweak = [2; 33];
+
+    let tweaked = tweak_descriptor(&descriptor, &secret_keys[1], &tweak, Network::Regtest);
+    let secret =
+        bitcoin::key::PrivateKey::new(secret_keys[1].tweak(&tweak, SECP256K1), Network::Regtest);
+
+    let tweaked = tweaked.to_string();
+    assert!(tweaked.starts_with("tr("));
+    assert!(tweaked.contains("multi_a(3,"));
+    assert!(tweaked.contains(&secret.to_wif()));
+}
@@ -66,7 +66,7 @@ pub async fn new(
         for (federation_id, db) in load_federation_client_databases(&db).await {
             let mut client_builder = Client::builder().await?;
             client_builder.with_module(LightningClientInit::default());
-            client_builder.with_module(MintClientInit);
+            client_builder.with_module(MintClientInit::default());
             let client = client_builder
                 .open(
                     connectors.clone(),
@@ -145,7 +145,7 @@ async fn join_federation_static(
             .map_err(RecurringPaymentError::JoiningFederationFailed)?;
 
         client_builder.with_module(LightningClientInit::default());
-        client_builder.with_module(MintClientInit);
+        client_builder.with_module(MintClientInit::default());
 
         let client = client_builder
             .preview(connectors, invite_code)
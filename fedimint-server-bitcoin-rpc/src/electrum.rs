@@ -0,0 +1,84 @@
+use std::sync::OnceLock;
+
+use bitcoin::{BlockHash, Transaction};
+use fedimint_core::envs::BitcoinRpcConfig;
+use fedimint_core::util::{FmtCompactAnyhow as _, SafeUrl};
+use fedimint_core::{ChainId, Feerate};
+use fedimint_logging::{LOG_BITCOIND_ELECTRUM, LOG_SERVER};
+use fedimint_server_core::bitcoin_rpc::IServerBitcoinRpc;
+use tracing::info;
+
+#[derive(Debug)]
+pub struct ElectrumClient {
+    client: fedimint_bitcoind::electrum::ElectrumClient,
+    url: SafeUrl,
+    cached_chain_id: OnceLock<ChainId>,
+}
+
+impl ElectrumClient {
+    pub fn new(url: &SafeUrl) -> anyhow::Result<Self> {
+        info!(
+            target: LOG_SERVER,
+            %url,
+            "Initializing bitcoin electrum backend"
+        );
+
+        Ok(Self {
+            client: fedimint_bitcoind::electrum::ElectrumClient::new(url)?,
+            url: url.clone(),
+            cached_chain_id: OnceLock::new(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl IServerBitcoinRpc for ElectrumClient {
+    fn get_bitcoin_rpc_config(&self) -> BitcoinRpcConfig {
+        BitcoinRpcConfig {
+            kind: "electrum".to_string(),
+            url: self.url.clone(),
+        }
+    }
+
+    fn get_url(&self) -> SafeUrl {
+        self.url.clone()
+    }
+
+    async fn get_block_count(&self) -> anyhow::Result<u64> {
+        self.client.get_block_count()
+    }
+
+    async fn get_block_hash(&self, height: u64) -> anyhow::Result<BlockHash> {
+        self.client.get_block_hash(height)
+    }
+
+    async fn get_block(&self, block_hash: &BlockHash) -> anyhow::Result<bitcoin::Block> {
+        self.client.get_block(block_hash)
+    }
+
+    async fn get_feerate(&self) -> anyhow::Result<Option<Feerate>> {
+        self.client.get_feerate()
+    }
+
+    async fn submit_transaction(&self, transaction: Transaction) {
+        // Electrum servers relay the error message of bitcoind, which for
+        // transactions already submitted is harmless, so we only log it
+        if let Err(err) = self.client.submit_transaction(&transaction) {
+            info!(target: LOG_BITCOIND_ELECTRUM, err = %err.fmt_compact_anyhow(), "Error broadcasting transaction");
+        }
+    }
+
+    async fn get_sync_progress(&self) -> anyhow::Result<Option<f64>> {
+        Ok(None)
+    }
+
+    async fn get_chain_id(&self) -> anyhow::Result<ChainId> {
+        if let Some(chain_id) = self.cached_chain_id.get() {
+            return Ok(*chain_id);
+        }
+
+        let chain_id = ChainId::new(self.get_block_hash(1).await?);
+        let _ = self.cached_chain_id.set(chain_id);
+        Ok(chain_id)
+    }
+}
@@ -1,4 +1,5 @@
 pub mod bitcoind;
+pub mod electrum;
 pub mod esplora;
 
 use anyhow::Result;
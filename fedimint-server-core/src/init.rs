@@ -15,8 +15,8 @@
 use fedimint_core::core::{ModuleInstanceId, ModuleKind};
 use fedimint_core::db::{Database, DatabaseVersion};
 use fedimint_core::module::{
-    CommonModuleInit, CoreConsensusVersion, IDynCommonModuleInit, ModuleConsensusVersion,
-    ModuleInit, SupportedModuleApiVersions,
+    AmountUnit, CommonModuleInit, CoreConsensusVersion, IDynCommonModuleInit,
+    ModuleConsensusVersion, ModuleInit, SupportedModuleApiVersions,
 };
 use fedimint_core::task::TaskGroup;
 use fedimint_core::{NumPeers, PeerId, apply, async_trait_maybe_send, dyn_newtype_define};
@@ -33,12 +33,20 @@
 ///
 /// This replaces the per-module GenParams approach with a unified struct
 /// containing all the information modules need for DKG/config generation.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct ConfigGenModuleArgs {
     /// Bitcoin network for the federation
     pub network: Network,
     /// Whether to disable base fees for this federation
     pub disable_base_fees: bool,
+    /// Non-bitcoin units the mint generates keys for
+    pub mint_custom_units: BTreeSet<AmountUnit>,
+    /// Number of consensus blocks the wallet collects batched peg-outs for,
+    /// peg-outs are not batched if unset
+    pub wallet_peg_out_batch_window: Option<u32>,
+    /// Whether the wallet uses a taproot instead of a segwit v0 peg-in
+    /// descriptor
+    pub wallet_taproot_peg_in: bool,
 }
 
 /// Interface for Module Generation
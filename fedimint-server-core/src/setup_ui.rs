@@ -5,6 +5,7 @@
 use async_trait::async_trait;
 use fedimint_core::core::ModuleKind;
 use fedimint_core::module::ApiAuth;
+use fedimint_core::setup_code::ModuleSetupParams;
 
 pub type DynSetupApi = Arc<dyn ISetupApi + Send + Sync + 'static>;
 
@@ -34,6 +35,7 @@ async fn set_local_parameters(
         federation_name: Option<String>,
         disable_base_fees: Option<bool>,
         enabled_modules: Option<BTreeSet<ModuleKind>>,
+        module_params: Option<ModuleSetupParams>,
     ) -> Result<String>;
 
     /// Add peer connection info
@@ -20,9 +20,9 @@
 use fedimint_ui_common::auth::UserAuth;
 use fedimint_ui_common::{LOGIN_ROUTE, ROOT_ROUTE, UiState, dashboard_layout, login_form_response};
 use maud::html;
-use {fedimint_lnv2_server, fedimint_meta_server, fedimint_wallet_server};
+use {fedimint_lnv2_server, fedimint_meta_server, fedimint_mint_server, fedimint_wallet_server};
 
-use crate::dashboard::modules::{lnv2, meta, wallet};
+use crate::dashboard::modules::{lnv2, meta, mint, wallet};
 use crate::{
     CHANGE_PASSWORD_ROUTE, DOWNLOAD_BACKUP_ROUTE, EXPLORER_IDX_ROUTE, EXPLORER_ROUTE, LoginInput,
     METRICS_ROUTE, login_submit_response,
@@ -226,6 +226,15 @@ async fn dashboard_view(
             }
         }
 
+        // Conditionally add Mint UI if the module is available
+        @if let Some(mint_module) = state.api.get_module::<fedimint_mint_server::Mint>() {
+            div class="row gy-4 mt-2" {
+                div class="col-12" {
+                    (mint::render(mint_module, &state.api).await)
+                }
+            }
+        }
+
         // Conditionally add Wallet UI if the module is available
         @if let Some(wallet_module) = state.api.get_module::<fedimint_wallet_server::Wallet>() {
             div class="row gy-4 mt-2" {
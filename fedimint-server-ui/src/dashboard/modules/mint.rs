@@ -0,0 +1,253 @@
+use std::collections::BTreeMap;
+
+use fedimint_core::Amount;
+use fedimint_core::epoch::ConsensusItem;
+use fedimint_core::module::AmountUnit;
+use fedimint_core::session_outcome::SessionStatusV2;
+use fedimint_mint_server::common::{MintInput, MintOutput};
+use fedimint_server_core::dashboard_ui::DynDashboardApi;
+use maud::{Markup, html};
+use tokio::sync::Mutex;
+
+/// Number of completed sessions the issuance and redemption rates are averaged
+/// over
+const RATE_SESSIONS: u64 = 10;
+
+/// E-cash issued and redeemed in the recent sessions of a unit
+#[derive(Default)]
+struct RecentActivity {
+    issued: Amount,
+    redeemed: Amount,
+}
+
+/// Number of notes of a denomination issued and redeemed over the lifetime of
+/// the federation
+#[derive(Default, Clone, Copy)]
+struct NoteCounts {
+    issued: u64,
+    redeemed: u64,
+}
+
+/// Note counts per denomination of all sessions scanned so far, so a dashboard
+/// refresh only has to scan the sessions completed since the previous one
+struct DenominationStats {
+    scanned_sessions: u64,
+    notes: BTreeMap<(AmountUnit, Amount), NoteCounts>,
+}
+
+static DENOMINATION_STATS: Mutex<DenominationStats> = Mutex::const_new(DenominationStats {
+    scanned_sessions: 0,
+    notes: BTreeMap::new(),
+});
+
+/// Notes issued and redeemed by the transactions of a session
+#[derive(Default)]
+struct SessionNotes {
+    issued: Vec<(AmountUnit, Amount)>,
+    redeemed: Vec<(AmountUnit, Amount)>,
+}
+
+fn format_unit(unit: AmountUnit) -> String {
+    if unit.is_bitcoin() {
+        "Bitcoin (msat)".to_string()
+    } else {
+        format!("Custom unit {}", unit.as_u64())
+    }
+}
+
+/// Number of sessions that are complete, the last session is still in progress
+async fn completed_sessions(api: &DynDashboardApi) -> u64 {
+    api.session_count().await.saturating_sub(1)
+}
+
+async fn session_notes(api: &DynDashboardApi, session_idx: u64) -> SessionNotes {
+    let mut notes = SessionNotes::default();
+
+    let SessionStatusV2::Complete(signed_session_outcome) =
+        api.get_session_status(session_idx).await
+    else {
+        return notes;
+    };
+
+    for accepted_item in signed_session_outcome.session_outcome.items {
+        let ConsensusItem::Transaction(tx) = accepted_item.item else {
+            continue;
+        };
+
+        // Every mint input and output holds a single note, so its amount is the
+        // denomination of the note
+        for input in &tx.inputs {
+            if let Some(Ok((amount, unit, _))) = input
+                .as_any()
+                .downcast_ref::<MintInput>()
+                .map(MintInput::ensure_known)
+            {
+                notes.redeemed.push((unit, amount));
+            }
+        }
+
+        for output in &tx.outputs {
+            if let Some(Ok((amount, unit, _))) = output
+                .as_any()
+                .downcast_ref::<MintOutput>()
+                .map(MintOutput::ensure_known)
+            {
+                notes.issued.push((unit, amount));
+            }
+        }
+    }
+
+    notes
+}
+
+/// Sums up the e-cash issued and redeemed in the last [`RATE_SESSIONS`]
+/// completed sessions, returning the number of sessions scanned
+async fn recent_activity(api: &DynDashboardApi) -> (u64, BTreeMap<AmountUnit, RecentActivity>) {
+    let mut activity = BTreeMap::<AmountUnit, RecentActivity>::new();
+
+    let completed_sessions = completed_sessions(api).await;
+    let first_session = completed_sessions.saturating_sub(RATE_SESSIONS);
+
+    for session_idx in first_session..completed_sessions {
+        let notes = session_notes(api, session_idx).await;
+
+        for (unit, amount) in notes.redeemed {
+            activity.entry(unit).or_default().redeemed += amount;
+        }
+
+        for (unit, amount) in notes.issued {
+            activity.entry(unit).or_default().issued += amount;
+        }
+    }
+
+    (completed_sessions - first_session, activity)
+}
+
+/// Counts the notes issued and redeemed per denomination over all completed
+/// sessions, scanning only the sessions completed since the last call
+async fn denomination_stats(api: &DynDashboardApi) -> BTreeMap<(AmountUnit, Amount), NoteCounts> {
+    let mut stats = DENOMINATION_STATS.lock().await;
+
+    let completed_sessions = completed_sessions(api).await;
+
+    for session_idx in stats.scanned_sessions..completed_sessions {
+        let notes = session_notes(api, session_idx).await;
+
+        for tier in notes.redeemed {
+            stats.notes.entry(tier).or_default().redeemed += 1;
+        }
+
+        for tier in notes.issued {
+            stats.notes.entry(tier).or_default().issued += 1;
+        }
+
+        stats.scanned_sessions = session_idx + 1;
+    }
+
+    stats.notes.clone()
+}
+
+// Function to render the Mint module UI section
+pub async fn render(mint: &fedimint_mint_server::Mint, api: &DynDashboardApi) -> Markup {
+    let outstanding = mint.outstanding_ecash_ui().await;
+    let denomination_stats = denomination_stats(api).await;
+    let denominations = mint.denominations_ui();
+    let fee_consensus = mint.fee_consensus_ui();
+    let (session_count, activity) = recent_activity(api).await;
+
+    html! {
+        div class="card h-100" {
+            div class="card-header dashboard-header" { "Mint" }
+            div class="card-body" {
+                // Outstanding e-cash and recent activity per unit
+                div class="mb-4" {
+                    table class="table" {
+                        thead {
+                            tr {
+                                th { "Unit" }
+                                th { "Outstanding E-Cash" }
+                                th { "Issued per Session" }
+                                th { "Redeemed per Session" }
+                            }
+                        }
+                        tbody {
+                            @for unit in denominations.keys() {
+                                @let recent = activity.get(unit);
+                                tr {
+                                    td { (format_unit(*unit)) }
+                                    td { (outstanding.get(unit).copied().unwrap_or_default().msats) }
+                                    td { (recent.map_or(0, |a| a.issued.msats) / session_count.max(1)) }
+                                    td { (recent.map_or(0, |a| a.redeemed.msats) / session_count.max(1)) }
+                                }
+                            }
+                        }
+                    }
+                    p class="text-muted small" {
+                        "Rates are averaged over the last " (session_count) " completed sessions."
+                    }
+                }
+
+                // Configuration
+                div class="mb-4" {
+                    h5 { "Configuration" }
+                    table class="table table-sm" {
+                        thead {
+                            tr {
+                                th { "Unit" }
+                                th { "Base Fee (msat)" }
+                                th { "Relative Fee (ppm)" }
+                                th { "Denominations" }
+                            }
+                        }
+                        tbody {
+                            @for (unit, tiers) in &denominations {
+                                tr {
+                                    td { (format_unit(*unit)) }
+                                    @if let Some(fees) = fee_consensus.get(unit) {
+                                        td { (fees.base().msats) }
+                                        td { (fees.parts_per_million()) }
+                                    } @else {
+                                        td { "-" }
+                                        td { "-" }
+                                    }
+                                    td { (tiers.len()) " (" (tiers.first().copied().unwrap_or_default().msats) " to " (tiers.last().copied().unwrap_or_default().msats) ")" }
+                                }
+                            }
+                        }
+                    }
+                }
+
+                // Notes per denomination
+                @if !denomination_stats.is_empty() {
+                    div class="mb-4" {
+                        h5 { "Notes per Denomination" }
+                        div class="table-responsive" {
+                            table class="table table-sm" {
+                                thead {
+                                    tr {
+                                        th { "Unit" }
+                                        th { "Denomination" }
+                                        th { "Issued" }
+                                        th { "Redeemed" }
+                                        th { "Outstanding" }
+                                    }
+                                }
+                                tbody {
+                                    @for ((unit, denomination), counts) in &denomination_stats {
+                                        tr {
+                                            td { (format_unit(*unit)) }
+                                            td { (denomination.msats) }
+                                            td { (counts.issued) }
+                                            td { (counts.redeemed) }
+                                            td { (counts.issued.saturating_sub(counts.redeemed)) }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
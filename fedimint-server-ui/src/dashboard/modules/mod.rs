@@ -1,3 +1,4 @@
 pub mod lnv2;
 pub mod meta;
+pub mod mint;
 pub mod wallet;
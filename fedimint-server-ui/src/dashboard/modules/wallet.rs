@@ -1,11 +1,14 @@
+use fedimint_wallet_server::ChainTipStatus;
 use maud::{Markup, html};
 
 // Function to render the Wallet module UI section
 pub async fn render(wallet: &fedimint_wallet_server::Wallet) -> Markup {
     let network = wallet.network_ui();
     let consensus_block_count = wallet.consensus_block_count_ui().await;
+    let chain_tip_status = wallet.chain_tip_status_ui();
     let consensus_fee_rate = wallet.consensus_feerate_ui().await;
     let wallet_summary = wallet.get_wallet_summary_ui().await;
+    let consolidations = wallet.consolidation_history_ui().await;
     let total_spendable = wallet_summary.total_spendable_balance().to_sat();
     let total_unsigned_change = wallet_summary.total_unsigned_change_balance().to_sat();
     let total_unconfirmed_change = wallet_summary.total_unconfirmed_change_balance().to_sat();
@@ -26,6 +29,26 @@ pub async fn render(wallet: &fedimint_wallet_server::Wallet) -> Markup {
                         th { "Consensus Block Count" }
                         td { (consensus_block_count) }
                     }
+                    tr {
+                        th { "Chain Tip" }
+                        td {
+                            @match chain_tip_status {
+                                ChainTipStatus::Unverified => {
+                                    span class="badge bg-secondary" { "Not verified yet" }
+                                }
+                                ChainTipStatus::InSync { height, block_hash } => {
+                                    span class="badge bg-success" { "In sync" }
+                                    " at height " (height) " (" code { (block_hash) } ")"
+                                }
+                                ChainTipStatus::Diverged { height, consensus_block_hash, local_block_hash } => {
+                                    span class="badge bg-danger" { "Diverged" }
+                                    " at height " (height)
+                                    div class="small" { "Consensus: " code { (consensus_block_hash) } }
+                                    div class="small" { "Local: " code { (local_block_hash) } }
+                                }
+                            }
+                        }
+                    }
                     tr {
                         th { "Consensus Fee Rate" }
                         td { (consensus_fee_rate.sats_per_kvb) " sats/kvB" }
@@ -54,6 +77,10 @@ pub async fn render(wallet: &fedimint_wallet_server::Wallet) -> Markup {
                         th { "Unconfirmed Outgoing Amount" }
                         td { (total_unconfirmed_outgoing) " sats" }
                     }
+                    tr {
+                        th { "Fee Reserve" }
+                        td { (wallet_summary.fee_reserve.to_sat()) " sats" }
+                    }
                 }
 
                 // Collapsible info section
@@ -154,6 +181,86 @@ pub async fn render(wallet: &fedimint_wallet_server::Wallet) -> Markup {
                         }
                     }
 
+                    // CPFP Fee Bumps Table
+                    @if !wallet_summary.cpfp_transactions.is_empty() {
+                        div class="mb-4" {
+                            h5 { "CPFP Fee Bumps" }
+                            div class="table-responsive" {
+                                table class="table table-sm" {
+                                    thead {
+                                        tr {
+                                            th { "Stuck Transaction" }
+                                            th { "Child Transaction" }
+                                            th { "Fees (sats)" }
+                                            th { "Status" }
+                                        }
+                                    }
+                                    tbody {
+                                        @for cpfp in &wallet_summary.cpfp_transactions {
+                                            tr {
+                                                td {
+                                                    a href={ "https://mempool.space/tx/" (cpfp.parent_txid) } class="btn btn-sm btn-outline-primary" target="_blank" {
+                                                        "mempool.space"
+                                                    }
+                                                }
+                                                td {
+                                                    a href={ "https://mempool.space/tx/" (cpfp.child_txid) } class="btn btn-sm btn-outline-primary" target="_blank" {
+                                                        "mempool.space"
+                                                    }
+                                                }
+                                                td { (cpfp.fee.to_sat()) }
+                                                td {
+                                                    @if cpfp.signed {
+                                                        span class="badge bg-info" { "Unconfirmed" }
+                                                    } @else {
+                                                        span class="badge bg-secondary" { "Awaiting signatures" }
+                                                    }
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    // UTXO Consolidations Table
+                    @if !consolidations.is_empty() {
+                        div class="mb-4" {
+                            h5 { "UTXO Consolidations" }
+                            div class="table-responsive" {
+                                table class="table table-sm" {
+                                    thead {
+                                        tr {
+                                            th { "Block Count" }
+                                            th { "Swept UTXOs" }
+                                            th { "Amount (sats)" }
+                                            th { "Fees (sats)" }
+                                            th { "Fee Rate (sats/kvB)" }
+                                            th { "Transaction" }
+                                        }
+                                    }
+                                    tbody {
+                                        @for (txid, consolidation) in &consolidations {
+                                            tr {
+                                                td { (consolidation.block_count) }
+                                                td { (consolidation.inputs) }
+                                                td { (consolidation.amount.to_sat()) }
+                                                td { (consolidation.fees.amount().to_sat()) }
+                                                td { (consolidation.fees.fee_rate.sats_per_kvb) }
+                                                td {
+                                                    a href={ "https://mempool.space/tx/" (txid) } class="btn btn-sm btn-outline-primary" target="_blank" {
+                                                        "mempool.space"
+                                                    }
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+
                     // Spendable UTXOs Table
                     @if !wallet_summary.spendable_utxos.is_empty() {
                         div class="mb-4" {
@@ -1,5 +1,6 @@
 use std::collections::BTreeSet;
 
+use anyhow::Context as _;
 use axum::Router;
 use axum::extract::State;
 use axum::response::{Html, IntoResponse, Redirect};
@@ -7,7 +8,8 @@
 use axum_extra::extract::Form;
 use axum_extra::extract::cookie::CookieJar;
 use fedimint_core::core::ModuleKind;
-use fedimint_core::module::ApiAuth;
+use fedimint_core::module::{AmountUnit, ApiAuth};
+use fedimint_core::setup_code::ModuleSetupParams;
 use fedimint_server_core::setup_ui::DynSetupApi;
 use fedimint_ui_common::assets::WithStaticRoutesExt;
 use fedimint_ui_common::auth::UserAuth;
@@ -35,6 +37,12 @@ pub(crate) struct SetupInput {
     pub enable_base_fees: bool,
     #[serde(default)] // list of enabled module kinds
     pub enabled_modules: Vec<String>,
+    #[serde(default)] // comma separated custom unit ids
+    pub mint_custom_units: String,
+    #[serde(default)] // empty if peg-outs are not batched
+    pub wallet_peg_out_batch_window: String,
+    #[serde(default)] // will not be sent if disabled
+    pub wallet_taproot_peg_in: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -191,6 +199,41 @@ async fn setup_form(State(state): State<UiState<DynSetupApi>>) -> impl IntoRespo
                                 }
                             }
                         }
+
+                        div class="accordion-item" {
+                            h2 class="accordion-header" {
+                                button class="accordion-button collapsed" type="button"
+                                    data-bs-toggle="collapse" data-bs-target="#moduleSettingsConfig"
+                                    aria-expanded="false" aria-controls="moduleSettingsConfig" {
+                                    "Advanced: Configure Module Settings"
+                                }
+                            }
+                            div id="moduleSettingsConfig" class="accordion-collapse collapse" data-bs-parent="#modulesAccordion" {
+                                div class="accordion-body" {
+                                    label class="form-label" for="mint_custom_units" {
+                                        "Custom mint units (comma separated unit ids)"
+                                    }
+                                    input type="text" class="form-control" id="mint_custom_units" name="mint_custom_units" placeholder="e.g. 1,2";
+
+                                    label class="form-label mt-2" for="wallet_peg_out_batch_window" {
+                                        "Peg-out batch window (blocks, leave empty to disable batching)"
+                                    }
+                                    input type="number" min="1" class="form-control" id="wallet_peg_out_batch_window" name="wallet_peg_out_batch_window" placeholder="e.g. 6";
+
+                                    div class="form-check mt-2" {
+                                        input type="checkbox" class="form-check-input" id="wallet_taproot_peg_in" name="wallet_taproot_peg_in" value="true";
+
+                                        label class="form-check-label" for="wallet_taproot_peg_in" {
+                                            "Use taproot peg-in addresses"
+                                        }
+                                    }
+
+                                    div class="alert alert-warning mt-2 mb-0" style="font-size: 0.875rem;" {
+                                        "Only modify this if you know what you are doing. These settings cannot be changed later."
+                                    }
+                                }
+                            }
+                        }
                     }
                 }
             }
@@ -234,17 +277,35 @@ async fn setup_submit(
         None
     };
 
-    match state
-        .api
-        .set_local_parameters(
-            ApiAuth(input.password),
-            input.name,
-            federation_name,
-            disable_base_fees,
-            enabled_modules,
+    let module_params = if input.is_lead {
+        parse_module_params(
+            &input.mint_custom_units,
+            &input.wallet_peg_out_batch_window,
+            input.wallet_taproot_peg_in,
         )
-        .await
-    {
+        .map(Some)
+    } else {
+        Ok(None)
+    };
+
+    let result = match module_params {
+        Ok(module_params) => {
+            state
+                .api
+                .set_local_parameters(
+                    ApiAuth(input.password),
+                    input.name,
+                    federation_name,
+                    disable_base_fees,
+                    enabled_modules,
+                    module_params,
+                )
+                .await
+        }
+        Err(e) => Err(e),
+    };
+
+    match result {
         Ok(_) => Redirect::to(LOGIN_ROUTE).into_response(),
         Err(e) => {
             let content = html! {
@@ -259,6 +320,39 @@ async fn setup_submit(
     }
 }
 
+/// Parses the module settings the leader entered into the setup form
+fn parse_module_params(
+    mint_custom_units: &str,
+    wallet_peg_out_batch_window: &str,
+    wallet_taproot_peg_in: bool,
+) -> anyhow::Result<ModuleSetupParams> {
+    let mint_custom_units = mint_custom_units
+        .split(',')
+        .map(str::trim)
+        .filter(|unit| !unit.is_empty())
+        .map(|unit| {
+            unit.parse::<u64>()
+                .map(AmountUnit::new_custom)
+                .with_context(|| format!("Invalid custom mint unit {unit}"))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    let wallet_peg_out_batch_window = match wallet_peg_out_batch_window.trim() {
+        "" => None,
+        window => Some(
+            window
+                .parse::<u32>()
+                .with_context(|| format!("Invalid peg-out batch window {window}"))?,
+        ),
+    };
+
+    Ok(ModuleSetupParams {
+        mint_custom_units,
+        wallet_peg_out_batch_window,
+        wallet_taproot_peg_in,
+    })
+}
+
 // GET handler for the /login route (display the login form)
 async fn login_form(State(state): State<UiState<DynSetupApi>>) -> impl IntoResponse {
     if state.api.setup_code().await.is_none() {
@@ -8,7 +8,7 @@
 use fedimint_core::encoding::Encodable as _;
 use fedimint_core::net::peers::{DynP2PConnections, Recipient};
 use fedimint_core::{NumPeers, PeerId};
-use fedimint_server_core::config::{g2, scalar};
+use fedimint_server_core::config::{eval_poly_scalar, g2, scalar};
 use group::ff::Field;
 use rand::rngs::OsRng;
 use tracing::trace;
@@ -186,15 +186,6 @@ pub async fn run_dkg_g2(
     }
 }
 
-fn eval_poly_scalar(coefficients: &[Scalar], x: &Scalar) -> Scalar {
-    coefficients
-        .iter()
-        .copied()
-        .rev()
-        .reduce(|acc, coefficient| acc * x + coefficient)
-        .expect("We have at least one coefficient")
-}
-
 enum DkgStepG2 {
     Broadcast(DkgMessageG2),
     Messages(Vec<(PeerId, DkgMessageG2)>),
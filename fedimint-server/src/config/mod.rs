@@ -17,7 +17,7 @@
     SupportedApiVersionsSummary, SupportedCoreApiVersions,
 };
 use fedimint_core::net::peers::{DynP2PConnections, Recipient};
-use fedimint_core::setup_code::{PeerEndpoints, PeerSetupCode};
+use fedimint_core::setup_code::{ModuleSetupParams, PeerEndpoints, PeerSetupCode};
 use fedimint_core::task::sleep;
 use fedimint_core::util::SafeUrl;
 use fedimint_core::{NumPeersExt, PeerId, secp256k1, timing};
@@ -246,6 +246,8 @@ pub struct ConfigGenParams {
     pub disable_base_fees: bool,
     /// Modules enabled by the leader during setup
     pub enabled_modules: BTreeSet<ModuleKind>,
+    /// Module settings chosen by the leader during setup
+    pub module_params: ModuleSetupParams,
     /// Bitcoin network for this federation
     pub network: bitcoin::Network,
 }
@@ -495,6 +497,9 @@ pub fn trusted_dealer_gen(
         let args = ConfigGenModuleArgs {
             network: peer0.network,
             disable_base_fees: peer0.disable_base_fees,
+            mint_custom_units: peer0.module_params.mint_custom_units.clone(),
+            wallet_peg_out_batch_window: peer0.module_params.wallet_peg_out_batch_window,
+            wallet_taproot_peg_in: peer0.module_params.wallet_taproot_peg_in,
         };
 
         // Use legacy module ordering for backwards compatibility tests
@@ -649,6 +654,9 @@ pub async fn distributed_gen(
         let args = ConfigGenModuleArgs {
             network: params.network,
             disable_base_fees: params.disable_base_fees,
+            mint_custom_units: params.module_params.mint_custom_units.clone(),
+            wallet_peg_out_batch_window: params.module_params.wallet_peg_out_batch_window,
+            wallet_taproot_peg_in: params.module_params.wallet_taproot_peg_in,
         };
 
         // Use legacy module ordering for backwards compatibility tests
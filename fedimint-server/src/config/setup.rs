@@ -23,7 +23,7 @@
     ApiAuth, ApiEndpoint, ApiEndpointContext, ApiError, ApiRequestErased, ApiVersion, api_endpoint,
 };
 use fedimint_core::net::auth::check_auth;
-use fedimint_core::setup_code::PeerEndpoints;
+use fedimint_core::setup_code::{ModuleSetupParams, PeerEndpoints};
 use fedimint_core::{PeerId, base32};
 use fedimint_server_core::setup_ui::ISetupApi;
 use iroh::SecretKey;
@@ -67,6 +67,8 @@ pub struct LocalParams {
     /// Modules enabled by the leader (if None, all available modules are
     /// enabled)
     enabled_modules: Option<BTreeSet<ModuleKind>>,
+    /// Module settings, set by the leader
+    module_params: Option<ModuleSetupParams>,
 }
 
 impl LocalParams {
@@ -77,6 +79,7 @@ pub fn setup_code(&self) -> PeerSetupCode {
             federation_name: self.federation_name.clone(),
             disable_base_fees: self.disable_base_fees,
             enabled_modules: self.enabled_modules.clone(),
+            module_params: self.module_params.clone(),
         }
     }
 }
@@ -158,6 +161,7 @@ async fn set_local_parameters(
         federation_name: Option<String>,
         disable_base_fees: Option<bool>,
         enabled_modules: Option<BTreeSet<ModuleKind>>,
+        module_params: Option<ModuleSetupParams>,
     ) -> anyhow::Result<String> {
         if let Some(existing_local_parameters) = self.state.lock().await.local_params.clone()
             && existing_local_parameters.auth == auth
@@ -165,6 +169,7 @@ async fn set_local_parameters(
             && existing_local_parameters.federation_name == federation_name
             && existing_local_parameters.disable_base_fees == disable_base_fees
             && existing_local_parameters.enabled_modules == enabled_modules
+            && existing_local_parameters.module_params == module_params
         {
             return Ok(base32::encode_prefixed(
                 FEDIMINT_PREFIX,
@@ -185,6 +190,20 @@ async fn set_local_parameters(
             ensure!(!federation_name.is_empty(), "The federation name is empty");
         }
 
+        if let Some(module_params) = module_params.as_ref() {
+            ensure!(
+                module_params
+                    .mint_custom_units
+                    .iter()
+                    .all(|unit| !unit.is_bitcoin()),
+                "The bitcoin unit is not a custom mint unit"
+            );
+            ensure!(
+                module_params.wallet_peg_out_batch_window != Some(0),
+                "The peg-out batch window must be at least one block"
+            );
+        }
+
         let mut state = self.state.lock().await;
 
         ensure!(
@@ -220,6 +239,7 @@ async fn set_local_parameters(
                 federation_name,
                 disable_base_fees,
                 enabled_modules,
+                module_params,
             }
         } else {
             let (tls_cert, tls_key) =
@@ -248,6 +268,7 @@ async fn set_local_parameters(
                 federation_name,
                 disable_base_fees,
                 enabled_modules,
+                module_params,
             }
         };
 
@@ -316,6 +337,18 @@ async fn add_peer_setup_code(&self, info: String) -> anyhow::Result<String> {
             );
         }
 
+        if state
+            .setup_codes
+            .iter()
+            .chain(once(&local_params.setup_code()))
+            .any(|info| info.module_params.is_some())
+        {
+            ensure!(
+                info.module_params.is_none(),
+                "Module settings have already been configured by another guardian"
+            );
+        }
+
         state.setup_codes.insert(info.clone());
 
         Ok(info.name)
@@ -356,6 +389,12 @@ async fn start_dkg(&self) -> anyhow::Result<()> {
             .find_map(|info| info.enabled_modules.clone())
             .unwrap_or_else(|| self.settings.available_modules.clone());
 
+        let module_params = state
+            .setup_codes
+            .iter()
+            .find_map(|info| info.module_params.clone())
+            .unwrap_or_default();
+
         let our_id = state
             .setup_codes
             .iter()
@@ -378,6 +417,7 @@ async fn start_dkg(&self) -> anyhow::Result<()> {
             )]),
             disable_base_fees,
             enabled_modules,
+            module_params,
             network: self.settings.network,
         };
 
@@ -432,7 +472,7 @@ pub fn server_endpoints() -> Vec<ApiEndpoint<SetupApi>> {
                     .request_auth()
                     .ok_or(ApiError::bad_request("Missing password".to_string()))?;
 
-                 config.set_local_parameters(auth, request.name, request.federation_name, request.disable_base_fees, request.enabled_modules)
+                 config.set_local_parameters(auth, request.name, request.federation_name, request.disable_base_fees, request.enabled_modules, request.module_params)
                     .await
                     .map_err(|e| ApiError::bad_request(e.to_string()))
             }
@@ -4,7 +4,7 @@
 use fedimint_core::PeerId;
 use fedimint_core::core::ModuleKind;
 use fedimint_core::module::ApiAuth;
-use fedimint_core::setup_code::{PeerEndpoints, PeerSetupCode};
+use fedimint_core::setup_code::{ModuleSetupParams, PeerEndpoints, PeerSetupCode};
 use fedimint_server::config::ConfigGenParams;
 use fedimint_server::core::ServerModuleInitRegistry;
 use fedimint_server::net::p2p_connector::gen_cert_and_key;
@@ -19,6 +19,7 @@ pub fn local_config_gen_params(
     peers: &[PeerId],
     base_port: u16,
     enable_mint_fees: bool,
+    module_params: &ModuleSetupParams,
     registry: &ServerModuleInitRegistry,
 ) -> anyhow::Result<HashMap<PeerId, ConfigGenParams>> {
     let enabled_modules: BTreeSet<ModuleKind> =
@@ -60,6 +61,7 @@ pub fn local_config_gen_params(
                 federation_name: None,
                 disable_base_fees: Some(!enable_mint_fees),
                 enabled_modules: None,
+                module_params: Some(module_params.clone()),
             };
             (*peer, params)
         })
@@ -78,6 +80,7 @@ pub fn local_config_gen_params(
                 meta: BTreeMap::new(),
                 disable_base_fees: !enable_mint_fees,
                 enabled_modules: enabled_modules.clone(),
+                module_params: module_params.clone(),
                 network: bitcoin::Network::Regtest,
             };
             Ok((*peer, params))
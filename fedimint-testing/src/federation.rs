@@ -18,6 +18,7 @@
 use fedimint_core::module::{ApiAuth, ApiRequestErased};
 use fedimint_core::net::peers::IP2PConnections;
 use fedimint_core::rustls::install_crypto_provider;
+use fedimint_core::setup_code::ModuleSetupParams;
 use fedimint_core::task::{TaskGroup, block_in_place, sleep_in_test};
 use fedimint_gateway_common::ConnectFedPayload;
 use fedimint_gateway_server::{Gateway, IAdminGateway};
@@ -184,6 +185,7 @@ pub struct FederationTestBuilder {
     client_init: ClientModuleInitRegistry,
     bitcoin_rpc_connection: DynServerBitcoinRpc,
     enable_mint_fees: bool,
+    module_params: ModuleSetupParams,
 }
 
 impl FederationTestBuilder {
@@ -206,6 +208,7 @@ pub fn new(
             client_init,
             bitcoin_rpc_connection,
             enable_mint_fees: true,
+            module_params: ModuleSetupParams::default(),
         }
     }
 
@@ -239,6 +242,11 @@ pub fn disable_mint_fees(mut self) -> FederationTestBuilder {
         self
     }
 
+    pub fn module_params(mut self, module_params: ModuleSetupParams) -> FederationTestBuilder {
+        self.module_params = module_params;
+        self
+    }
+
     #[allow(clippy::too_many_lines)]
     pub async fn build(self) -> FederationTest {
         install_crypto_provider().await;
@@ -252,6 +260,7 @@ pub async fn build(self) -> FederationTest {
             &peers,
             self.base_port,
             self.enable_mint_fees,
+            &self.module_params,
             &self.server_init,
         )
         .expect("Generates local config");
@@ -33,7 +33,7 @@ async fn make_client_builder() -> Result<(fedimint_client::ClientBuilder, Databa
     let mem_database = MemDatabase::default();
     let mut builder = fedimint_client::Client::builder().await?;
     builder.with_module(LightningClientInit::default());
-    builder.with_module(MintClientInit);
+    builder.with_module(MintClientInit::default());
     builder.with_module(WalletClientInit::default());
 
     Ok((builder, mem_database.into()))
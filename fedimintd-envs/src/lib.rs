@@ -28,6 +28,8 @@
 
 pub const FM_ESPLORA_URL_ENV: &str = "FM_ESPLORA_URL";
 
+pub const FM_ELECTRUM_URL_ENV: &str = "FM_ELECTRUM_URL";
+
 pub const FM_ENABLE_IROH_ENV: &str = "FM_ENABLE_IROH";
 
 pub const FM_DB_CHECKPOINT_RETENTION_ENV: &str = "FM_DB_CHECKPOINT_RETENTION";
@@ -38,6 +38,7 @@
 use fedimint_server::net::api::ApiSecrets;
 use fedimint_server_bitcoin_rpc::BitcoindClientWithFallback;
 use fedimint_server_bitcoin_rpc::bitcoind::BitcoindClient;
+use fedimint_server_bitcoin_rpc::electrum::ElectrumClient;
 use fedimint_server_bitcoin_rpc::esplora::EsploraClient;
 use fedimint_server_core::bitcoin_rpc::IServerBitcoinRpc;
 use fedimint_unknown_server::UnknownInit;
@@ -47,7 +48,7 @@
     FM_BIND_TOKIO_CONSOLE_ENV, FM_BIND_UI_ENV, FM_BITCOIN_NETWORK_ENV, FM_BITCOIND_PASSWORD_ENV,
     FM_BITCOIND_URL_ENV, FM_BITCOIND_URL_PASSWORD_FILE_ENV, FM_BITCOIND_USERNAME_ENV,
     FM_DATA_DIR_ENV, FM_DB_CHECKPOINT_RETENTION_ENV, FM_DISABLE_META_MODULE_ENV,
-    FM_ENABLE_IROH_ENV, FM_ESPLORA_URL_ENV, FM_FORCE_API_SECRETS_ENV,
+    FM_ELECTRUM_URL_ENV, FM_ENABLE_IROH_ENV, FM_ESPLORA_URL_ENV, FM_FORCE_API_SECRETS_ENV,
     FM_IROH_API_MAX_CONNECTIONS_ENV, FM_IROH_API_MAX_REQUESTS_PER_CONNECTION_ENV, FM_P2P_URL_ENV,
 };
 use futures::FutureExt as _;
@@ -76,7 +77,7 @@
         ArgGroup::new("bitcoin_rpc")
             .required(true)
             .multiple(true)
-            .args(["bitcoind_url", "esplora_url"])
+            .args(["bitcoind_url", "esplora_url", "electrum_url"])
     )
 )]
 struct ServerOpts {
@@ -116,6 +117,12 @@ struct ServerOpts {
     #[arg(long, env = FM_ESPLORA_URL_ENV)]
     esplora_url: Option<SafeUrl>,
 
+    /// Electrum server URL, e.g. <ssl://electrum.blockstream.info:50002>
+    ///
+    /// Used instead of bitcoind or esplora, e.g. with electrs or Fulcrum
+    #[arg(long, env = FM_ELECTRUM_URL_ENV, conflicts_with_all = ["bitcoind_url", "esplora_url"])]
+    electrum_url: Option<SafeUrl>,
+
     /// Address we bind to for p2p consensus communication
     ///
     /// Should be `0.0.0.0:8173` most of the time, as p2p connectivity is public
@@ -353,7 +360,15 @@ pub async fn run(
             .unwrap()
             .into_dyn()
         }
-        _ => unreachable!("ArgGroup already enforced XOR relation"),
+        // ArgGroup requires a bitcoin rpc and `electrum_url` conflicts with the others
+        (None, None) => ElectrumClient::new(
+            server_opts
+                .electrum_url
+                .as_ref()
+                .expect("ArgGroup already enforced a bitcoin rpc"),
+        )
+        .unwrap()
+        .into_dyn(),
     };
 
     root_task_group.install_kill_handler();
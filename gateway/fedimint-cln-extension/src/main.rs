@@ -0,0 +1,970 @@
+//! Core Lightning plugin that exposes the node to the fedimint gateway.
+//!
+//! The plugin serves the `GatewayCln` gRPC service defined in
+//! `fedimint-lightning`. Node operations are translated into calls to
+//! `lightningd`'s JSON-RPC interface, while the `htlc_accepted` hook holds all
+//! incoming HTLCs until the connected gateway decides to settle, cancel or
+//! forward them.
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bitcoin::hashes::{Hash, sha256};
+use bitcoin::secp256k1::{Secp256k1, SecretKey};
+use cln_plugin::options::{ConfigOption, DefaultStringConfigOption};
+use cln_plugin::{Builder, Plugin};
+use cln_rpc::ClnRpc;
+use fedimint_lightning::cln::proto::complete_htlc_request::Action;
+use fedimint_lightning::cln::proto::gateway_cln_server::{GatewayCln, GatewayClnServer};
+use fedimint_lightning::cln::proto::{self, create_invoice_request};
+use hex::{FromHex, ToHex};
+use lightning_invoice::{Bolt11Invoice, Currency, InvoiceBuilder, PaymentSecret};
+use rand::Rng;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use tokio::sync::{Mutex, mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+use tracing::{info, warn};
+
+const LISTEN_OPTION: DefaultStringConfigOption = ConfigOption::new_str_with_default(
+    "fm-gateway-listen",
+    "127.0.0.1:3301",
+    "Address the gRPC server for the fedimint gateway listens on",
+);
+
+/// Onion failure code `temporary_node_failure` returned for cancelled HTLCs
+const TEMPORARY_NODE_FAILURE: &str = "2002";
+
+/// The minimum CLTV delta of the final hop for invoices built by the plugin,
+/// matching the default of `lightningd`
+const MIN_FINAL_CLTV_EXPIRY_DELTA: u64 = 18;
+
+const HTLC_STREAM_SIZE: usize = 100;
+
+type HtlcStreamSender = mpsc::Sender<Result<proto::InterceptHtlcRequest, Status>>;
+
+/// Identifies an HTLC by the short channel id of its incoming channel and its
+/// id within that channel.
+type HtlcKey = (u64, u64);
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let Some(plugin) = Builder::new(tokio::io::stdin(), tokio::io::stdout())
+        .option(LISTEN_OPTION)
+        .hook("htlc_accepted", on_htlc_accepted)
+        .configure()
+        .await?
+    else {
+        return Ok(());
+    };
+
+    let listen = SocketAddr::from_str(&plugin.option(&LISTEN_OPTION)?)?;
+
+    let configuration = plugin.configuration();
+
+    let extension =
+        ClnExtension::new(Path::new(&configuration.lightning_dir).join(&configuration.rpc_file));
+
+    let plugin = plugin.start(extension.clone()).await?;
+
+    info!(%listen, "Starting gRPC server for the fedimint gateway");
+
+    tokio::select! {
+        result = Server::builder()
+            .add_service(GatewayClnServer::new(extension))
+            .serve(listen) => result?,
+        result = plugin.join() => result?,
+    }
+
+    Ok(())
+}
+
+#[derive(Clone)]
+struct ClnExtension {
+    rpc_file: PathBuf,
+    /// The stream of intercepted HTLCs opened by the gateway, if it is
+    /// connected
+    htlc_stream: Arc<Mutex<Option<HtlcStreamSender>>>,
+    /// HTLCs held in the `htlc_accepted` hook until the gateway completes
+    /// them
+    pending_htlcs: Arc<Mutex<BTreeMap<HtlcKey, oneshot::Sender<Action>>>>,
+}
+
+impl ClnExtension {
+    fn new(rpc_file: PathBuf) -> Self {
+        Self {
+            rpc_file,
+            htlc_stream: Arc::new(Mutex::new(None)),
+            pending_htlcs: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    async fn call<R: DeserializeOwned + std::fmt::Debug>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<R, Status> {
+        let mut rpc = ClnRpc::new(&self.rpc_file)
+            .await
+            .map_err(|e| Status::unavailable(format!("Failed to connect to lightningd: {e}")))?;
+
+        rpc.call_raw(method, &params)
+            .await
+            .map_err(|e| Status::internal(format!("{method} failed: {e}")))
+    }
+
+    /// Streams the HTLC to the gateway and waits for its decision. Returns
+    /// `None` if no gateway is connected or the gateway disconnects before
+    /// completing the HTLC.
+    async fn intercept(&self, htlc: proto::InterceptHtlcRequest) -> Option<Action> {
+        let key = (htlc.incoming_chan_id, htlc.htlc_id);
+
+        let sender = self.htlc_stream.lock().await.clone()?;
+
+        let (outcome_sender, outcome_receiver) = oneshot::channel();
+
+        self.pending_htlcs.lock().await.insert(key, outcome_sender);
+
+        if sender.send(Ok(htlc)).await.is_err() {
+            self.pending_htlcs.lock().await.remove(&key);
+
+            return None;
+        }
+
+        outcome_receiver.await.ok()
+    }
+
+    async fn network(&self) -> Result<String, Status> {
+        Ok(self
+            .call::<GetinfoResponse>("getinfo", json!({}))
+            .await?
+            .network)
+    }
+
+    /// Builds an invoice for a payment hash unknown to `lightningd` and lets
+    /// the node sign it. The HTLCs paying the invoice are streamed to the
+    /// gateway, which holds the preimage.
+    async fn create_intercepted_invoice(
+        &self,
+        payment_hash: sha256::Hash,
+        amount_msat: u64,
+        expiry_secs: u32,
+        description: create_invoice_request::Description,
+    ) -> Result<String, Status> {
+        let currency = match self.network().await?.as_str() {
+            "bitcoin" => Currency::Bitcoin,
+            "testnet" | "testnet4" => Currency::BitcoinTestnet,
+            "signet" => Currency::Signet,
+            "regtest" => Currency::Regtest,
+            network => return Err(Status::internal(format!("Unknown network {network}"))),
+        };
+
+        let builder = InvoiceBuilder::new(currency)
+            .payment_hash(payment_hash)
+            .payment_secret(PaymentSecret(rand::thread_rng().r#gen()))
+            .current_timestamp()
+            .min_final_cltv_expiry_delta(MIN_FINAL_CLTV_EXPIRY_DELTA)
+            .expiry_time(Duration::from_secs(u64::from(expiry_secs)))
+            .basic_mpp();
+
+        let builder = if amount_msat == 0 {
+            builder
+        } else {
+            builder.amount_milli_satoshis(amount_msat)
+        };
+
+        // The invoice is signed with an ephemeral key first since `signinvoice`
+        // replaces the signature with one of the node
+        let ephemeral_key = SecretKey::new(&mut rand::thread_rng());
+
+        let sign = |hash: &bitcoin::secp256k1::Message| {
+            Secp256k1::new().sign_ecdsa_recoverable(hash, &ephemeral_key)
+        };
+
+        let invoice = match description {
+            create_invoice_request::Description::Direct(description) => {
+                builder.description(description).build_signed(sign)
+            }
+            create_invoice_request::Description::Hash(hash) => builder
+                .description_hash(
+                    sha256::Hash::from_slice(&hash)
+                        .map_err(|_| Status::invalid_argument("Invalid description hash"))?,
+                )
+                .build_signed(sign),
+        }
+        .map_err(|e| Status::internal(format!("Failed to build invoice: {e:?}")))?;
+
+        let response: SigninvoiceResponse = self
+            .call("signinvoice", json!({ "invstring": invoice.to_string() }))
+            .await?;
+
+        Ok(response.bolt11)
+    }
+
+    /// Waits for a payment that has been attempted before to complete.
+    async fn wait_for_payment(&self, payment_hash: sha256::Hash) -> Result<Vec<u8>, Status> {
+        loop {
+            let response: ListpaysResponse = self
+                .call(
+                    "listpays",
+                    json!({ "payment_hash": payment_hash.to_string() }),
+                )
+                .await?;
+
+            if let Some(preimage) = response.pays.iter().find_map(|pay| pay.preimage.as_deref()) {
+                return Vec::from_hex(preimage)
+                    .map_err(|_| Status::internal("lightningd returned an invalid preimage"));
+            }
+
+            if !response.pays.iter().any(|pay| pay.status == "pending") {
+                return Err(Status::aborted("Payment failed"));
+            }
+
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    async fn peer_channels(&self, peer: Option<String>) -> Result<Vec<PeerChannel>, Status> {
+        let params = match peer {
+            Some(peer) => json!({ "id": peer }),
+            None => json!({}),
+        };
+
+        let response: ListpeerchannelsResponse = self.call("listpeerchannels", params).await?;
+
+        Ok(response.channels)
+    }
+}
+
+async fn on_htlc_accepted(plugin: Plugin<ClnExtension>, payload: Value) -> anyhow::Result<Value> {
+    let HtlcAccepted { htlc, onion } = serde_json::from_value(payload)?;
+
+    let request = proto::InterceptHtlcRequest {
+        payment_hash: Vec::from_hex(&htlc.payment_hash)?,
+        amount_msat: htlc.amount_msat,
+        expiry: htlc.cltv_expiry,
+        incoming_chan_id: parse_short_channel_id(&htlc.short_channel_id)?,
+        short_channel_id: onion
+            .short_channel_id
+            .as_deref()
+            .map(parse_short_channel_id)
+            .transpose()?,
+        htlc_id: htlc.id,
+    };
+
+    let response = match plugin.state().intercept(request).await {
+        Some(Action::Settle(settle)) => json!({
+            "result": "resolve",
+            "payment_key": settle.preimage.encode_hex::<String>(),
+        }),
+        Some(Action::Cancel(_)) => json!({
+            "result": "fail",
+            "failure_message": TEMPORARY_NODE_FAILURE,
+        }),
+        // HTLCs the gateway is not responsible for are handled by lightningd as if
+        // the plugin was not installed
+        Some(Action::Forward(_)) | None => json!({ "result": "continue" }),
+    };
+
+    Ok(response)
+}
+
+#[tonic::async_trait]
+impl GatewayCln for ClnExtension {
+    type RouteHtlcsStream = ReceiverStream<Result<proto::InterceptHtlcRequest, Status>>;
+
+    async fn get_node_info(
+        &self,
+        _request: Request<proto::GetNodeInfoRequest>,
+    ) -> Result<Response<proto::GetNodeInfoResponse>, Status> {
+        let info: GetinfoResponse = self.call("getinfo", json!({})).await?;
+
+        Ok(Response::new(proto::GetNodeInfoResponse {
+            pub_key: decode_hex(&info.id)?,
+            alias: info.alias.unwrap_or_default(),
+            network: info.network,
+            block_height: info.blockheight,
+            synced_to_chain: info.warning_bitcoind_sync.is_none()
+                && info.warning_lightningd_sync.is_none(),
+        }))
+    }
+
+    async fn get_route_hints(
+        &self,
+        request: Request<proto::GetRouteHintsRequest>,
+    ) -> Result<Response<proto::GetRouteHintsResponse>, Status> {
+        let num_route_hints = usize::try_from(request.into_inner().num_route_hints)
+            .map_err(|_| Status::invalid_argument("Too many route hints"))?;
+
+        let mut channels = self
+            .peer_channels(None)
+            .await?
+            .into_iter()
+            .filter(|channel| channel.state == "CHANNELD_NORMAL" && channel.peer_connected)
+            .collect::<Vec<_>>();
+
+        // Prefer the channels with the most inbound liquidity
+        channels.sort_by_key(|channel| std::cmp::Reverse(channel.receivable_msat.unwrap_or(0)));
+
+        let route_hints = channels
+            .into_iter()
+            .filter_map(|channel| {
+                let policy = channel.updates?.remote?;
+
+                Some(proto::RouteHint {
+                    hops: vec![proto::RouteHintHop {
+                        src_node_id: Vec::from_hex(&channel.peer_id).ok()?,
+                        short_channel_id: parse_short_channel_id(
+                            channel.short_channel_id.as_deref()?,
+                        )
+                        .ok()?,
+                        base_msat: policy.fee_base_msat,
+                        proportional_millionths: policy.fee_proportional_millionths,
+                        cltv_expiry_delta: policy.cltv_expiry_delta,
+                        htlc_minimum_msat: Some(policy.htlc_minimum_msat),
+                        htlc_maximum_msat: Some(policy.htlc_maximum_msat),
+                    }],
+                })
+            })
+            .take(num_route_hints)
+            .collect();
+
+        Ok(Response::new(proto::GetRouteHintsResponse { route_hints }))
+    }
+
+    async fn pay_invoice(
+        &self,
+        request: Request<proto::PayInvoiceRequest>,
+    ) -> Result<Response<proto::PayInvoiceResponse>, Status> {
+        let request = request.into_inner();
+
+        let invoice = Bolt11Invoice::from_str(&request.invoice)
+            .map_err(|e| Status::invalid_argument(format!("Invalid invoice: {e}")))?;
+
+        let mut params = json!({
+            "bolt11": request.invoice,
+            "maxfee": request.max_fee_msat,
+            "maxdelay": request.max_delay,
+        });
+
+        if let Some(amount_msat) = request.amount_msat {
+            params["amount_msat"] = json!(amount_msat);
+        }
+
+        // If the invoice has been paid before or the payment is still in flight,
+        // lightningd rejects the call and we wait for the earlier attempt instead
+        let preimage = match self.call::<PayResponse>("pay", params).await {
+            Ok(response) => decode_hex(&response.payment_preimage)?,
+            Err(status) => {
+                warn!(%status, "Pay call failed, checking for earlier payment attempts");
+
+                self.wait_for_payment(*invoice.payment_hash()).await?
+            }
+        };
+
+        Ok(Response::new(proto::PayInvoiceResponse { preimage }))
+    }
+
+    async fn route_htlcs(
+        &self,
+        _request: Request<proto::RouteHtlcsRequest>,
+    ) -> Result<Response<Self::RouteHtlcsStream>, Status> {
+        let mut htlc_stream = self.htlc_stream.lock().await;
+
+        if htlc_stream
+            .as_ref()
+            .is_some_and(|sender| !sender.is_closed())
+        {
+            return Err(Status::already_exists("A gateway is already routing HTLCs"));
+        }
+
+        let (sender, receiver) = mpsc::channel(HTLC_STREAM_SIZE);
+
+        *htlc_stream = Some(sender.clone());
+
+        let pending_htlcs = self.pending_htlcs.clone();
+
+        tokio::spawn(async move {
+            sender.closed().await;
+
+            // Dropping the senders resumes all HTLCs held for the disconnected gateway
+            pending_htlcs.lock().await.clear();
+
+            info!("Gateway stopped routing HTLCs");
+        });
+
+        info!("Gateway started routing HTLCs");
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    async fn complete_htlc(
+        &self,
+        request: Request<proto::CompleteHtlcRequest>,
+    ) -> Result<Response<proto::CompleteHtlcResponse>, Status> {
+        let request = request.into_inner();
+
+        let action = request
+            .action
+            .ok_or_else(|| Status::invalid_argument("Missing action"))?;
+
+        let outcome_sender = self
+            .pending_htlcs
+            .lock()
+            .await
+            .remove(&(request.incoming_chan_id, request.htlc_id))
+            .ok_or_else(|| Status::not_found("HTLC is not held by the plugin"))?;
+
+        outcome_sender
+            .send(action)
+            .map_err(|_| Status::aborted("HTLC has already been released"))?;
+
+        Ok(Response::new(proto::CompleteHtlcResponse {}))
+    }
+
+    async fn create_invoice(
+        &self,
+        request: Request<proto::CreateInvoiceRequest>,
+    ) -> Result<Response<proto::CreateInvoiceResponse>, Status> {
+        let request = request.into_inner();
+
+        let description = request
+            .description
+            .unwrap_or(create_invoice_request::Description::Direct(String::new()));
+
+        let invoice = match (request.payment_hash, description) {
+            (Some(payment_hash), description) => {
+                let payment_hash = sha256::Hash::from_slice(&payment_hash)
+                    .map_err(|_| Status::invalid_argument("Invalid payment hash"))?;
+
+                self.create_intercepted_invoice(
+                    payment_hash,
+                    request.amount_msat,
+                    request.expiry_secs,
+                    description,
+                )
+                .await?
+            }
+            (None, create_invoice_request::Description::Direct(description)) => {
+                let amount_msat = if request.amount_msat == 0 {
+                    json!("any")
+                } else {
+                    json!(request.amount_msat)
+                };
+
+                let label = format!("fedimint-gateway-{}", rand::thread_rng().r#gen::<u64>());
+
+                let response: InvoiceResponse = self
+                    .call(
+                        "invoice",
+                        json!({
+                            "amount_msat": amount_msat,
+                            "label": label,
+                            "description": description,
+                            "expiry": request.expiry_secs,
+                        }),
+                    )
+                    .await?;
+
+                response.bolt11
+            }
+            (None, create_invoice_request::Description::Hash(_)) => {
+                return Err(Status::invalid_argument(
+                    "lightningd cannot create invoices with a description hash",
+                ));
+            }
+        };
+
+        Ok(Response::new(proto::CreateInvoiceResponse { invoice }))
+    }
+
+    async fn get_ln_onchain_address(
+        &self,
+        _request: Request<proto::GetLnOnchainAddressRequest>,
+    ) -> Result<Response<proto::GetLnOnchainAddressResponse>, Status> {
+        let response: NewaddrResponse = self
+            .call("newaddr", json!({ "addresstype": "p2tr" }))
+            .await?;
+
+        let address = response
+            .p2tr
+            .ok_or_else(|| Status::internal("lightningd did not return a taproot address"))?;
+
+        Ok(Response::new(proto::GetLnOnchainAddressResponse {
+            address,
+        }))
+    }
+
+    async fn send_onchain(
+        &self,
+        request: Request<proto::SendOnchainRequest>,
+    ) -> Result<Response<proto::SendOnchainResponse>, Status> {
+        let request = request.into_inner();
+
+        let satoshi = match request.amount_sats {
+            Some(amount_sats) => json!(amount_sats),
+            None => json!("all"),
+        };
+
+        let response: WithdrawResponse = self
+            .call(
+                "withdraw",
+                json!({
+                    "destination": request.address,
+                    "satoshi": satoshi,
+                    "feerate": format!("{}perkb", request.fee_rate_sats_per_vbyte * 1000),
+                }),
+            )
+            .await?;
+
+        Ok(Response::new(proto::SendOnchainResponse {
+            txid: response.txid,
+        }))
+    }
+
+    async fn open_channel(
+        &self,
+        request: Request<proto::OpenChannelRequest>,
+    ) -> Result<Response<proto::OpenChannelResponse>, Status> {
+        let request = request.into_inner();
+
+        let peer = request.pubkey.encode_hex::<String>();
+
+        let _: Value = self
+            .call(
+                "connect",
+                json!({ "id": format!("{peer}@{}", request.host) }),
+            )
+            .await?;
+
+        let response: FundchannelResponse = self
+            .call(
+                "fundchannel",
+                json!({
+                    "id": peer,
+                    "amount": request.channel_size_sats,
+                    "push_msat": request.push_amount_sats * 1000,
+                }),
+            )
+            .await?;
+
+        Ok(Response::new(proto::OpenChannelResponse {
+            funding_txid: response.txid,
+        }))
+    }
+
+    async fn close_channels_with_peer(
+        &self,
+        request: Request<proto::CloseChannelsWithPeerRequest>,
+    ) -> Result<Response<proto::CloseChannelsWithPeerResponse>, Status> {
+        let request = request.into_inner();
+
+        let channels = self
+            .peer_channels(Some(request.pubkey.encode_hex()))
+            .await?
+            .into_iter()
+            .filter(|channel| is_open(&channel.state));
+
+        let mut num_channels_closed = 0;
+
+        for channel in channels {
+            let Some(channel_id) = channel.channel_id else {
+                continue;
+            };
+
+            let mut params = json!({ "id": channel_id });
+
+            // Close unilaterally if the peer has not agreed on a mutual close
+            // within a second
+            if request.force {
+                params["unilateraltimeout"] = json!(1);
+            }
+
+            if let Some(sats_per_vbyte) = request.sats_per_vbyte {
+                let feerate = format!("{}perkb", sats_per_vbyte * 1000);
+
+                params["feerange"] = json!([feerate, feerate]);
+            }
+
+            let _: Value = self.call("close", params).await?;
+
+            num_channels_closed += 1;
+        }
+
+        Ok(Response::new(proto::CloseChannelsWithPeerResponse {
+            num_channels_closed,
+        }))
+    }
+
+    async fn list_channels(
+        &self,
+        _request: Request<proto::ListChannelsRequest>,
+    ) -> Result<Response<proto::ListChannelsResponse>, Status> {
+        let channels = self
+            .peer_channels(None)
+            .await?
+            .into_iter()
+            .filter(|channel| is_open(&channel.state))
+            .map(|channel| {
+                let total_msat = channel.total_msat.unwrap_or(0);
+                let to_us_msat = channel.to_us_msat.unwrap_or(0);
+
+                Ok(proto::ChannelInfo {
+                    remote_pubkey: decode_hex(&channel.peer_id)?,
+                    channel_size_sats: total_msat / 1000,
+                    outbound_liquidity_sats: to_us_msat / 1000,
+                    inbound_liquidity_sats: total_msat.saturating_sub(to_us_msat) / 1000,
+                    is_active: channel.state == "CHANNELD_NORMAL" && channel.peer_connected,
+                    funding_outpoint: channel
+                        .funding_txid
+                        .zip(channel.funding_outnum)
+                        .map(|(txid, vout)| format!("{txid}:{vout}")),
+                    remote_node_alias: None,
+                })
+            })
+            .collect::<Result<Vec<_>, Status>>()?;
+
+        Ok(Response::new(proto::ListChannelsResponse { channels }))
+    }
+
+    async fn get_balances(
+        &self,
+        _request: Request<proto::GetBalancesRequest>,
+    ) -> Result<Response<proto::GetBalancesResponse>, Status> {
+        let funds: ListfundsResponse = self.call("listfunds", json!({})).await?;
+
+        let onchain_balance_msats = funds
+            .outputs
+            .iter()
+            .filter(|output| output.status == "confirmed" && !output.reserved)
+            .map(|output| output.amount_msat)
+            .sum::<u64>();
+
+        let channels = funds
+            .channels
+            .iter()
+            .filter(|channel| channel.state == "CHANNELD_NORMAL");
+
+        let (lightning_balance_msats, inbound_lightning_liquidity_msats) =
+            channels.fold((0, 0), |(outbound, inbound), channel| {
+                (
+                    outbound + channel.our_amount_msat,
+                    inbound + channel.amount_msat.saturating_sub(channel.our_amount_msat),
+                )
+            });
+
+        Ok(Response::new(proto::GetBalancesResponse {
+            onchain_balance_sats: onchain_balance_msats / 1000,
+            lightning_balance_msats,
+            inbound_lightning_liquidity_msats,
+        }))
+    }
+
+    async fn get_invoice(
+        &self,
+        request: Request<proto::GetInvoiceRequest>,
+    ) -> Result<Response<proto::GetInvoiceResponse>, Status> {
+        let payment_hash = request.into_inner().payment_hash.encode_hex::<String>();
+
+        let response: ListinvoicesResponse = self
+            .call("listinvoices", json!({ "payment_hash": payment_hash }))
+            .await?;
+
+        let invoice = response
+            .invoices
+            .into_iter()
+            .next()
+            .map(|invoice| {
+                let created_at_secs = invoice
+                    .bolt11
+                    .as_deref()
+                    .and_then(|bolt11| Bolt11Invoice::from_str(bolt11).ok())
+                    .map_or(0, |bolt11| bolt11.duration_since_epoch().as_secs());
+
+                Ok::<_, Status>(proto::Invoice {
+                    preimage: invoice
+                        .payment_preimage
+                        .as_deref()
+                        .map(decode_hex)
+                        .transpose()?,
+                    payment_hash: decode_hex(&invoice.payment_hash)?,
+                    amount_msat: invoice
+                        .amount_received_msat
+                        .or(invoice.amount_msat)
+                        .unwrap_or(0),
+                    created_at_secs,
+                    status: invoice_status(&invoice.status).into(),
+                })
+            })
+            .transpose()?;
+
+        Ok(Response::new(proto::GetInvoiceResponse { invoice }))
+    }
+
+    async fn list_transactions(
+        &self,
+        request: Request<proto::ListTransactionsRequest>,
+    ) -> Result<Response<proto::ListTransactionsResponse>, Status> {
+        let proto::ListTransactionsRequest {
+            start_secs,
+            end_secs,
+        } = request.into_inner();
+
+        let in_range =
+            |timestamp_secs: u64| start_secs <= timestamp_secs && timestamp_secs < end_secs;
+
+        let pays: ListpaysResponse = self.call("listpays", json!({})).await?;
+
+        let outgoing = pays
+            .pays
+            .into_iter()
+            .filter(|pay| in_range(pay.created_at))
+            .map(|pay| {
+                Ok::<_, Status>(proto::Payment {
+                    payment_hash: Some(decode_hex(&pay.payment_hash)?),
+                    preimage: pay.preimage.as_deref().map(decode_hex).transpose()?,
+                    amount_msat: pay.amount_msat.unwrap_or(0),
+                    direction: proto::PaymentDirection::Outbound.into(),
+                    status: match pay.status.as_str() {
+                        "complete" => proto::PaymentStatus::Succeeded,
+                        "failed" => proto::PaymentStatus::Failed,
+                        _ => proto::PaymentStatus::Pending,
+                    }
+                    .into(),
+                    timestamp_secs: pay.created_at,
+                })
+            });
+
+        let invoices: ListinvoicesResponse = self.call("listinvoices", json!({})).await?;
+
+        let incoming = invoices.invoices.into_iter().filter_map(|invoice| {
+            let paid_at = invoice.paid_at.filter(|paid_at| in_range(*paid_at))?;
+
+            Some(Ok::<_, Status>(proto::Payment {
+                payment_hash: Some(decode_hex(&invoice.payment_hash).ok()?),
+                preimage: invoice
+                    .payment_preimage
+                    .as_deref()
+                    .and_then(|preimage| decode_hex(preimage).ok()),
+                amount_msat: invoice.amount_received_msat.unwrap_or(0),
+                direction: proto::PaymentDirection::Inbound.into(),
+                status: proto::PaymentStatus::Succeeded.into(),
+                timestamp_secs: paid_at,
+            }))
+        });
+
+        let transactions = outgoing
+            .chain(incoming)
+            .collect::<Result<Vec<_>, Status>>()?;
+
+        Ok(Response::new(proto::ListTransactionsResponse {
+            transactions,
+        }))
+    }
+}
+
+/// Parses a short channel id in the `BLOCKxTXxOUTPUT` format used by
+/// `lightningd` into its integer representation.
+fn parse_short_channel_id(short_channel_id: &str) -> anyhow::Result<u64> {
+    let parts = short_channel_id
+        .split('x')
+        .map(u64::from_str)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let [block, tx, output] = parts[..] else {
+        anyhow::bail!("Invalid short channel id {short_channel_id}");
+    };
+
+    anyhow::ensure!(
+        block < 1 << 24 && tx < 1 << 24 && output < 1 << 16,
+        "Invalid short channel id {short_channel_id}"
+    );
+
+    Ok((block << 40) | (tx << 16) | output)
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>, Status> {
+    Vec::from_hex(hex).map_err(|_| Status::internal("lightningd returned invalid hex"))
+}
+
+fn is_open(state: &str) -> bool {
+    matches!(
+        state,
+        "CHANNELD_AWAITING_LOCKIN" | "DUALOPEND_AWAITING_LOCKIN" | "CHANNELD_NORMAL"
+    )
+}
+
+fn invoice_status(status: &str) -> proto::PaymentStatus {
+    match status {
+        "paid" => proto::PaymentStatus::Succeeded,
+        "expired" => proto::PaymentStatus::Failed,
+        _ => proto::PaymentStatus::Pending,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct HtlcAccepted {
+    htlc: AcceptedHtlc,
+    onion: AcceptedHtlcOnion,
+}
+
+#[derive(Debug, Deserialize)]
+struct AcceptedHtlc {
+    short_channel_id: String,
+    id: u64,
+    amount_msat: u64,
+    cltv_expiry: u32,
+    payment_hash: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AcceptedHtlcOnion {
+    /// Only set if the HTLC is to be forwarded
+    short_channel_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GetinfoResponse {
+    id: String,
+    alias: Option<String>,
+    network: String,
+    blockheight: u32,
+    warning_bitcoind_sync: Option<String>,
+    warning_lightningd_sync: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SigninvoiceResponse {
+    bolt11: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct InvoiceResponse {
+    bolt11: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PayResponse {
+    payment_preimage: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListpaysResponse {
+    pays: Vec<Pay>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Pay {
+    payment_hash: String,
+    status: String,
+    created_at: u64,
+    preimage: Option<String>,
+    amount_msat: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListinvoicesResponse {
+    invoices: Vec<Invoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Invoice {
+    payment_hash: String,
+    status: String,
+    bolt11: Option<String>,
+    amount_msat: Option<u64>,
+    amount_received_msat: Option<u64>,
+    payment_preimage: Option<String>,
+    paid_at: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListpeerchannelsResponse {
+    channels: Vec<PeerChannel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PeerChannel {
+    peer_id: String,
+    peer_connected: bool,
+    state: String,
+    channel_id: Option<String>,
+    short_channel_id: Option<String>,
+    funding_txid: Option<String>,
+    funding_outnum: Option<u32>,
+    total_msat: Option<u64>,
+    to_us_msat: Option<u64>,
+    receivable_msat: Option<u64>,
+    updates: Option<ChannelUpdates>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChannelUpdates {
+    remote: Option<ChannelPolicy>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChannelPolicy {
+    htlc_minimum_msat: u64,
+    htlc_maximum_msat: u64,
+    cltv_expiry_delta: u32,
+    fee_base_msat: u32,
+    fee_proportional_millionths: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListfundsResponse {
+    outputs: Vec<FundsOutput>,
+    channels: Vec<FundsChannel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FundsOutput {
+    amount_msat: u64,
+    status: String,
+    reserved: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct FundsChannel {
+    state: String,
+    amount_msat: u64,
+    our_amount_msat: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct NewaddrResponse {
+    p2tr: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WithdrawResponse {
+    txid: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FundchannelResponse {
+    txid: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_short_channel_id;
+
+    #[test]
+    fn parses_short_channel_ids() {
+        assert_eq!(
+            parse_short_channel_id("103x1x0").unwrap(),
+            (103 << 40) | (1 << 16)
+        );
+        assert!(parse_short_channel_id("103x1").is_err());
+        assert!(parse_short_channel_id("103x1x70000").is_err());
+    }
+}
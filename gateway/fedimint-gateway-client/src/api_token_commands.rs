@@ -0,0 +1,68 @@
+use clap::Subcommand;
+use fedimint_core::util::SafeUrl;
+use fedimint_gateway_client::{create_api_token, list_api_tokens, revoke_api_token};
+use fedimint_gateway_common::{ApiScope, CreateApiTokenPayload, RevokeApiTokenPayload};
+use fedimint_ln_common::client::GatewayApi;
+
+use crate::print_response;
+
+#[derive(Subcommand)]
+pub enum ApiTokenCommands {
+    /// Create an API token that can be used instead of the gateway password
+    /// to call the endpoints that require one of its scopes. The token is only
+    /// shown once.
+    Create {
+        /// Unique name of the token
+        #[clap(long)]
+        name: String,
+
+        /// Scope granted to the token, can be repeated
+        #[clap(long = "scope", value_enum, required = true)]
+        scopes: Vec<ApiScope>,
+
+        /// Number of seconds after which the token expires. The token never
+        /// expires if not set.
+        #[clap(long)]
+        expiry_secs: Option<u64>,
+    },
+    /// List the API tokens without their secrets.
+    List,
+    /// Revoke an API token.
+    Revoke {
+        #[clap(long)]
+        name: String,
+    },
+}
+
+impl ApiTokenCommands {
+    pub async fn handle(self, client: &GatewayApi, base_url: &SafeUrl) -> anyhow::Result<()> {
+        match self {
+            Self::Create {
+                name,
+                scopes,
+                expiry_secs,
+            } => {
+                let response = create_api_token(
+                    client,
+                    base_url,
+                    CreateApiTokenPayload {
+                        name,
+                        scopes: scopes.into_iter().collect(),
+                        expiry_secs,
+                    },
+                )
+                .await?;
+                print_response(response);
+            }
+            Self::List => {
+                let response = list_api_tokens(client, base_url).await?;
+                print_response(response);
+            }
+            Self::Revoke { name } => {
+                revoke_api_token(client, base_url, RevokeApiTokenPayload { name }).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
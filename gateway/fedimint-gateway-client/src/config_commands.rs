@@ -2,9 +2,15 @@
 use fedimint_core::Amount;
 use fedimint_core::config::FederationId;
 use fedimint_core::util::SafeUrl;
-use fedimint_gateway_client::{get_config, get_info, set_fees, set_mnemonic};
-use fedimint_gateway_common::{ConfigPayload, SetFeesPayload, SetMnemonicPayload};
+use fedimint_gateway_client::{
+    get_config, get_info, set_federation_limits, set_fee_policy, set_fees, set_mnemonic,
+};
+use fedimint_gateway_common::{
+    ConfigPayload, FeePolicy, SetFederationLimitsPayload, SetFeePolicyPayload, SetFeesPayload,
+    SetMnemonicPayload,
+};
 use fedimint_ln_common::client::GatewayApi;
+use fedimint_lnv2_common::gateway_api::PaymentFee;
 
 use crate::print_response;
 
@@ -20,7 +26,8 @@ pub enum ConfigCommands {
         #[clap(long)]
         federation_id: Option<FederationId>,
     },
-    /// Set the gateway's lightning or transaction fees
+    /// Set the gateway's lightning or transaction fees, this removes the fee
+    /// policy of the federations
     SetFees {
         #[clap(long)]
         federation_id: Option<FederationId>,
@@ -37,6 +44,46 @@ pub enum ConfigCommands {
         #[clap(long)]
         tx_ppm: Option<u64>,
     },
+    /// Let the gateway adjust a federation's fees between the given bounds
+    /// depending on its liquidity. Fees are given as `<base msats>,<ppm>`.
+    SetFeePolicy {
+        #[clap(long)]
+        federation_id: FederationId,
+
+        #[clap(long, required_unless_present = "remove")]
+        min_ln_fee: Option<PaymentFee>,
+
+        #[clap(long, required_unless_present = "remove")]
+        max_ln_fee: Option<PaymentFee>,
+
+        #[clap(long, required_unless_present = "remove")]
+        min_tx_fee: Option<PaymentFee>,
+
+        #[clap(long, required_unless_present = "remove")]
+        max_tx_fee: Option<PaymentFee>,
+
+        /// Remove the fee policy, the current fees are kept
+        #[clap(long, conflicts_with_all = ["min_ln_fee", "max_ln_fee", "min_tx_fee", "max_tx_fee"])]
+        remove: bool,
+    },
+    /// Set the gateway's liquidity limits for a federation, limits that are
+    /// not provided are removed
+    SetLimits {
+        #[clap(long)]
+        federation_id: FederationId,
+
+        /// Maximum ecash balance the gateway holds in the federation
+        #[clap(long)]
+        max_balance: Option<Amount>,
+
+        /// Maximum amount of a single payment sent or received
+        #[clap(long)]
+        max_payment: Option<Amount>,
+
+        /// Maximum volume sent and received over the last 24 hours
+        #[clap(long)]
+        max_daily_volume: Option<Amount>,
+    },
     /// Instructs the gateway to create a new mnemonic or set it to the provided
     /// mnemonic
     SetMnemonic {
@@ -87,6 +134,57 @@ pub async fn handle(self, client: &GatewayApi, base_url: &SafeUrl) -> anyhow::Re
                 )
                 .await?;
             }
+            Self::SetFeePolicy {
+                federation_id,
+                min_ln_fee,
+                max_ln_fee,
+                min_tx_fee,
+                max_tx_fee,
+                remove: _,
+            } => {
+                let policy = match (min_ln_fee, max_ln_fee, min_tx_fee, max_tx_fee) {
+                    (
+                        Some(min_lightning_fee),
+                        Some(max_lightning_fee),
+                        Some(min_transaction_fee),
+                        Some(max_transaction_fee),
+                    ) => Some(FeePolicy {
+                        min_lightning_fee,
+                        max_lightning_fee,
+                        min_transaction_fee,
+                        max_transaction_fee,
+                    }),
+                    _ => None,
+                };
+
+                set_fee_policy(
+                    client,
+                    base_url,
+                    SetFeePolicyPayload {
+                        federation_id,
+                        policy,
+                    },
+                )
+                .await?;
+            }
+            Self::SetLimits {
+                federation_id,
+                max_balance,
+                max_payment,
+                max_daily_volume,
+            } => {
+                set_federation_limits(
+                    client,
+                    base_url,
+                    SetFederationLimitsPayload {
+                        federation_id,
+                        max_balance,
+                        max_payment,
+                        max_daily_volume,
+                    },
+                )
+                .await?;
+            }
             Self::SetMnemonic { words } => {
                 set_mnemonic(client, base_url, SetMnemonicPayload { words }).await?;
             }
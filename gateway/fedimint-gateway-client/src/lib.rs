@@ -3,23 +3,29 @@
 use fedimint_connectors::ServerResult;
 use fedimint_core::util::SafeUrl;
 use fedimint_gateway_common::{
-    ADDRESS_ENDPOINT, ADDRESS_RECHECK_ENDPOINT, BACKUP_ENDPOINT, BackupPayload,
+    ADDRESS_ENDPOINT, ADDRESS_RECHECK_ENDPOINT, ApiTokenInfo, BACKUP_ENDPOINT, BackupPayload,
     CLOSE_CHANNELS_WITH_PEER_ENDPOINT, CONFIGURATION_ENDPOINT, CONNECT_FED_ENDPOINT,
-    CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT, CREATE_BOLT12_OFFER_FOR_OPERATOR_ENDPOINT,
-    ChannelInfo, CloseChannelsWithPeerRequest, CloseChannelsWithPeerResponse, ConfigPayload,
-    ConnectFedPayload, CreateInvoiceForOperatorPayload, CreateOfferPayload, CreateOfferResponse,
-    DepositAddressPayload, DepositAddressRecheckPayload, FederationInfo, GATEWAY_INFO_ENDPOINT,
-    GET_BALANCES_ENDPOINT, GET_INVOICE_ENDPOINT, GET_LN_ONCHAIN_ADDRESS_ENDPOINT, GatewayBalances,
-    GatewayFedConfig, GatewayInfo, GetInvoiceRequest, GetInvoiceResponse, LEAVE_FED_ENDPOINT,
-    LIST_CHANNELS_ENDPOINT, LIST_TRANSACTIONS_ENDPOINT, LeaveFedPayload, ListTransactionsPayload,
-    ListTransactionsResponse, MNEMONIC_ENDPOINT, MnemonicResponse, OPEN_CHANNEL_ENDPOINT,
-    OpenChannelRequest, PAY_INVOICE_FOR_OPERATOR_ENDPOINT, PAY_OFFER_FOR_OPERATOR_ENDPOINT,
-    PAYMENT_LOG_ENDPOINT, PAYMENT_SUMMARY_ENDPOINT, PayInvoiceForOperatorPayload, PayOfferPayload,
-    PayOfferResponse, PaymentLogPayload, PaymentLogResponse, PaymentSummaryPayload,
-    PaymentSummaryResponse, RECEIVE_ECASH_ENDPOINT, ReceiveEcashPayload, ReceiveEcashResponse,
-    SEND_ONCHAIN_ENDPOINT, SET_FEES_ENDPOINT, SPEND_ECASH_ENDPOINT, STOP_ENDPOINT,
-    SendOnchainRequest, SetFeesPayload, SetMnemonicPayload, SpendEcashPayload, SpendEcashResponse,
-    WITHDRAW_ENDPOINT, WithdrawPayload, WithdrawResponse,
+    CREATE_API_TOKEN_ENDPOINT, CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT,
+    CREATE_BOLT12_OFFER_FOR_OPERATOR_ENDPOINT, ChannelInfo, CloseChannelsWithPeerRequest,
+    CloseChannelsWithPeerResponse, ConfigPayload, ConnectFedPayload, CreateApiTokenPayload,
+    CreateApiTokenResponse, CreateInvoiceForOperatorPayload, CreateOfferPayload,
+    CreateOfferResponse, DepositAddressPayload, DepositAddressRecheckPayload, FederationInfo,
+    GATEWAY_INFO_ENDPOINT, GET_BALANCES_ENDPOINT, GET_INVOICE_ENDPOINT,
+    GET_LN_ONCHAIN_ADDRESS_ENDPOINT, GatewayBalances, GatewayFedConfig, GatewayInfo,
+    GetInvoiceRequest, GetInvoiceResponse, LEAVE_FED_ENDPOINT, LIST_API_TOKENS_ENDPOINT,
+    LIST_CHANNELS_ENDPOINT, LIST_TRANSACTIONS_ENDPOINT, LIST_WEBHOOKS_ENDPOINT, LeaveFedPayload,
+    ListTransactionsPayload, ListTransactionsResponse, MNEMONIC_ENDPOINT, MnemonicResponse,
+    OPEN_CHANNEL_ENDPOINT, OpenChannelRequest, PAY_INVOICE_FOR_OPERATOR_ENDPOINT,
+    PAY_OFFER_FOR_OPERATOR_ENDPOINT, PAYMENT_LOG_ENDPOINT, PAYMENT_SUMMARY_ENDPOINT,
+    PayInvoiceForOperatorPayload, PayOfferPayload, PayOfferResponse, PaymentLogPayload,
+    PaymentLogResponse, PaymentSummaryPayload, PaymentSummaryResponse, RECEIVE_ECASH_ENDPOINT,
+    REGISTER_WEBHOOK_ENDPOINT, REMOVE_WEBHOOK_ENDPOINT, REVOKE_API_TOKEN_ENDPOINT,
+    ReceiveEcashPayload, ReceiveEcashResponse, RegisterWebhookPayload, RemoveWebhookPayload,
+    RevokeApiTokenPayload, SEND_ONCHAIN_ENDPOINT, SET_FEDERATION_LIMITS_ENDPOINT,
+    SET_FEE_POLICY_ENDPOINT, SET_FEES_ENDPOINT, SPEND_ECASH_ENDPOINT, STOP_ENDPOINT,
+    SendOnchainRequest, SetFederationLimitsPayload, SetFeePolicyPayload, SetFeesPayload,
+    SetMnemonicPayload, SpendEcashPayload, SpendEcashResponse, WITHDRAW_ENDPOINT, WebhookInfo,
+    WithdrawPayload, WithdrawResponse,
 };
 use fedimint_ln_common::Method;
 use fedimint_ln_common::client::GatewayApi;
@@ -106,6 +112,114 @@ pub async fn set_fees(
         .await
 }
 
+pub async fn set_fee_policy(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: SetFeePolicyPayload,
+) -> ServerResult<()> {
+    client
+        .request(
+            base_url,
+            Method::POST,
+            SET_FEE_POLICY_ENDPOINT,
+            Some(payload),
+        )
+        .await
+}
+
+pub async fn set_federation_limits(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: SetFederationLimitsPayload,
+) -> ServerResult<()> {
+    client
+        .request(
+            base_url,
+            Method::POST,
+            SET_FEDERATION_LIMITS_ENDPOINT,
+            Some(payload),
+        )
+        .await
+}
+
+pub async fn create_api_token(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: CreateApiTokenPayload,
+) -> ServerResult<CreateApiTokenResponse> {
+    client
+        .request(
+            base_url,
+            Method::POST,
+            CREATE_API_TOKEN_ENDPOINT,
+            Some(payload),
+        )
+        .await
+}
+
+pub async fn list_api_tokens(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+) -> ServerResult<Vec<ApiTokenInfo>> {
+    client
+        .request::<(), Vec<ApiTokenInfo>>(base_url, Method::GET, LIST_API_TOKENS_ENDPOINT, None)
+        .await
+}
+
+pub async fn revoke_api_token(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: RevokeApiTokenPayload,
+) -> ServerResult<()> {
+    client
+        .request(
+            base_url,
+            Method::POST,
+            REVOKE_API_TOKEN_ENDPOINT,
+            Some(payload),
+        )
+        .await
+}
+
+pub async fn register_webhook(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: RegisterWebhookPayload,
+) -> ServerResult<WebhookInfo> {
+    client
+        .request(
+            base_url,
+            Method::POST,
+            REGISTER_WEBHOOK_ENDPOINT,
+            Some(payload),
+        )
+        .await
+}
+
+pub async fn list_webhooks(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+) -> ServerResult<Vec<WebhookInfo>> {
+    client
+        .request::<(), Vec<WebhookInfo>>(base_url, Method::GET, LIST_WEBHOOKS_ENDPOINT, None)
+        .await
+}
+
+pub async fn remove_webhook(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: RemoveWebhookPayload,
+) -> ServerResult<()> {
+    client
+        .request(
+            base_url,
+            Method::POST,
+            REMOVE_WEBHOOK_ENDPOINT,
+            Some(payload),
+        )
+        .await
+}
+
 pub async fn create_invoice_for_self(
     client: &GatewayApi,
     base_url: &SafeUrl,
@@ -1,11 +1,14 @@
 #![deny(clippy::pedantic, clippy::nursery)]
 
+mod api_token_commands;
 mod config_commands;
 mod ecash_commands;
 mod general_commands;
 mod lightning_commands;
 mod onchain_commands;
+mod webhook_commands;
 
+use api_token_commands::ApiTokenCommands;
 use clap::{CommandFactory, Parser, Subcommand};
 use config_commands::ConfigCommands;
 use ecash_commands::EcashCommands;
@@ -17,6 +20,7 @@
 use lightning_commands::LightningCommands;
 use onchain_commands::OnchainCommands;
 use serde::Serialize;
+use webhook_commands::WebhookCommands;
 
 #[derive(Parser)]
 #[command(version)]
@@ -29,7 +33,7 @@ struct Cli {
     #[command(subcommand)]
     command: Commands,
 
-    /// Password for authenticated requests to the gateway
+    /// Password or API token for authenticated requests to the gateway
     #[clap(long)]
     rpcpassword: Option<String>,
 }
@@ -46,6 +50,10 @@ enum Commands {
     Onchain(OnchainCommands),
     #[command(subcommand)]
     Cfg(ConfigCommands),
+    #[command(subcommand)]
+    Webhook(WebhookCommands),
+    #[command(subcommand)]
+    Token(ApiTokenCommands),
     Completion {
         shell: clap_complete::Shell,
     },
@@ -86,6 +94,12 @@ async fn run() -> anyhow::Result<()> {
         Commands::Ecash(ecash_command) => ecash_command.handle(&client, &cli.address).await?,
         Commands::Onchain(onchain_command) => onchain_command.handle(&client, &cli.address).await?,
         Commands::Cfg(config_commands) => config_commands.handle(&client, &cli.address).await?,
+        Commands::Webhook(webhook_commands) => {
+            webhook_commands.handle(&client, &cli.address).await?;
+        }
+        Commands::Token(api_token_commands) => {
+            api_token_commands.handle(&client, &cli.address).await?;
+        }
         Commands::Completion { shell } => {
             clap_complete::generate(
                 shell,
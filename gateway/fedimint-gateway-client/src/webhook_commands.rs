@@ -0,0 +1,71 @@
+use clap::Subcommand;
+use fedimint_core::util::SafeUrl;
+use fedimint_eventlog::EventKind;
+use fedimint_gateway_client::{list_webhooks, register_webhook, remove_webhook};
+use fedimint_gateway_common::{RegisterWebhookPayload, RemoveWebhookPayload};
+use fedimint_ln_common::client::GatewayApi;
+
+use crate::print_response;
+
+#[derive(Subcommand)]
+pub enum WebhookCommands {
+    /// Register a webhook that receives the events logged by the federation
+    /// clients of the gateway from now on.
+    Register {
+        /// The url the events are POSTed to. Has to use https unless it points
+        /// to localhost.
+        #[clap(long)]
+        url: SafeUrl,
+
+        /// Secret used to sign the events together with the timestamp in the
+        /// `X-Fedimint-Timestamp` header, the signature is sent in the
+        /// `X-Fedimint-Signature` header.
+        #[clap(long)]
+        secret: String,
+
+        /// Only deliver events of this kind, can be repeated. All events are
+        /// delivered if not set.
+        #[clap(long = "event-kind")]
+        event_kinds: Vec<EventKind>,
+    },
+    /// List the registered webhooks.
+    List,
+    /// Remove a webhook.
+    Remove {
+        #[clap(long)]
+        webhook_id: u64,
+    },
+}
+
+impl WebhookCommands {
+    pub async fn handle(self, client: &GatewayApi, base_url: &SafeUrl) -> anyhow::Result<()> {
+        match self {
+            Self::Register {
+                url,
+                secret,
+                event_kinds,
+            } => {
+                let response = register_webhook(
+                    client,
+                    base_url,
+                    RegisterWebhookPayload {
+                        url,
+                        secret,
+                        event_kinds,
+                    },
+                )
+                .await?;
+                print_response(response);
+            }
+            Self::List => {
+                let response = list_webhooks(client, base_url).await?;
+                print_response(response);
+            }
+            Self::Remove { webhook_id } => {
+                remove_webhook(client, base_url, RemoveWebhookPayload { webhook_id }).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -10,6 +10,10 @@
 /// Necessary for LND configuration.
 pub const FM_LND_MACAROON_ENV: &str = "FM_LND_MACAROON";
 
+/// Environment variable that specifies the URL of the gRPC server exposed by
+/// the `gateway-cln-extension` plugin. Necessary for CLN configuration.
+pub const FM_CLN_EXTENSION_ADDR_ENV: &str = "FM_CLN_EXTENSION_ADDR";
+
 /// Environment variable the specifies the port that the LDK Node should use.
 /// Necessary for LDK configuration.
 pub const FM_PORT_LDK: &str = "FM_PORT_LDK";
@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::time::{Duration, SystemTime};
 
@@ -6,9 +6,10 @@
 use bitcoin::hashes::sha256;
 use bitcoin::secp256k1::PublicKey;
 use bitcoin::{Address, Network, OutPoint};
-use clap::Subcommand;
+use clap::{Subcommand, ValueEnum};
 use envs::{
-    FM_LDK_ALIAS_ENV, FM_LND_MACAROON_ENV, FM_LND_RPC_ADDR_ENV, FM_LND_TLS_CERT_ENV, FM_PORT_LDK,
+    FM_CLN_EXTENSION_ADDR_ENV, FM_LDK_ALIAS_ENV, FM_LND_MACAROON_ENV, FM_LND_RPC_ADDR_ENV,
+    FM_LND_TLS_CERT_ENV, FM_PORT_LDK,
 };
 use fedimint_core::config::{FederationId, JsonClientConfig};
 use fedimint_core::core::OperationId;
@@ -32,6 +33,7 @@
 pub const BACKUP_ENDPOINT: &str = "/backup";
 pub const CONFIGURATION_ENDPOINT: &str = "/config";
 pub const CONNECT_FED_ENDPOINT: &str = "/connect_fed";
+pub const CREATE_API_TOKEN_ENDPOINT: &str = "/create_api_token";
 pub const CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT: &str = "/create_bolt11_invoice_for_operator";
 pub const CREATE_BOLT12_OFFER_FOR_OPERATOR_ENDPOINT: &str = "/create_bolt12_offer_for_operator";
 pub const GATEWAY_INFO_ENDPOINT: &str = "/info";
@@ -39,8 +41,10 @@
 pub const GET_INVOICE_ENDPOINT: &str = "/get_invoice";
 pub const GET_LN_ONCHAIN_ADDRESS_ENDPOINT: &str = "/get_ln_onchain_address";
 pub const LEAVE_FED_ENDPOINT: &str = "/leave_fed";
+pub const LIST_API_TOKENS_ENDPOINT: &str = "/list_api_tokens";
 pub const LIST_CHANNELS_ENDPOINT: &str = "/list_channels";
 pub const LIST_TRANSACTIONS_ENDPOINT: &str = "/list_transactions";
+pub const LIST_WEBHOOKS_ENDPOINT: &str = "/list_webhooks";
 pub const MNEMONIC_ENDPOINT: &str = "/mnemonic";
 pub const OPEN_CHANNEL_ENDPOINT: &str = "/open_channel";
 pub const CLOSE_CHANNELS_WITH_PEER_ENDPOINT: &str = "/close_channels_with_peer";
@@ -49,6 +53,11 @@
 pub const PAYMENT_LOG_ENDPOINT: &str = "/payment_log";
 pub const PAYMENT_SUMMARY_ENDPOINT: &str = "/payment_summary";
 pub const RECEIVE_ECASH_ENDPOINT: &str = "/receive_ecash";
+pub const REGISTER_WEBHOOK_ENDPOINT: &str = "/register_webhook";
+pub const REMOVE_WEBHOOK_ENDPOINT: &str = "/remove_webhook";
+pub const REVOKE_API_TOKEN_ENDPOINT: &str = "/revoke_api_token";
+pub const SET_FEDERATION_LIMITS_ENDPOINT: &str = "/set_federation_limits";
+pub const SET_FEE_POLICY_ENDPOINT: &str = "/set_fee_policy";
 pub const SET_FEES_ENDPOINT: &str = "/set_fees";
 pub const STOP_ENDPOINT: &str = "/stop";
 pub const SEND_ONCHAIN_ENDPOINT: &str = "/send_onchain";
@@ -157,6 +166,64 @@ pub struct FederationInfo {
     pub balance_msat: Amount,
     pub config: FederationConfig,
     pub last_backup_time: Option<SystemTime>,
+    #[serde(default)]
+    pub limits: FederationLimits,
+    /// Volume sent and received through the federation over the last 24 hours
+    #[serde(default)]
+    pub daily_volume: Amount,
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerState,
+    /// If set, the fees of the federation are recomputed periodically within
+    /// the bounds of the policy
+    #[serde(default)]
+    pub fee_policy: Option<FeePolicy>,
+}
+
+/// Operator-set limits on the liquidity the gateway exposes to a single
+/// federation. Limits that are not set are not enforced.
+#[derive(
+    Debug, Clone, Copy, Default, Eq, PartialEq, Encodable, Decodable, Serialize, Deserialize,
+)]
+pub struct FederationLimits {
+    /// Maximum ecash balance the gateway accepts to hold in the federation
+    pub max_balance: Option<Amount>,
+    /// Maximum amount of a single payment sent or received for the federation
+    pub max_payment: Option<Amount>,
+    /// Maximum volume sent and received for the federation over the last 24
+    /// hours
+    pub max_daily_volume: Option<Amount>,
+}
+
+/// Operator-set bounds for the fees the gateway computes for a federation from
+/// its current liquidity. The lightning fee rises from its minimum to its
+/// maximum as the outbound liquidity of the lightning node is used up, the
+/// transaction fee as the ecash balance shrinks relative to the recent volume
+/// of the federation.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Encodable, Decodable, Serialize, Deserialize)]
+pub struct FeePolicy {
+    pub min_lightning_fee: PaymentFee,
+    pub max_lightning_fee: PaymentFee,
+    pub min_transaction_fee: PaymentFee,
+    pub max_transaction_fee: PaymentFee,
+}
+
+/// While the circuit breaker of a federation is open, the gateway does not
+/// route any payments for it.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitBreakerState {
+    #[default]
+    Closed,
+    Open {
+        opened_at: SystemTime,
+        reason: String,
+    },
+}
+
+impl CircuitBreakerState {
+    pub fn is_open(&self) -> bool {
+        matches!(self, CircuitBreakerState::Open { .. })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -187,6 +254,108 @@ pub struct SetFeesPayload {
     pub transaction_parts_per_million: Option<u64>,
 }
 
+/// Replaces the fee policy of a federation. If no policy is set, the fees of
+/// the federation stay at their current value until they are changed with
+/// `SetFeesPayload`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SetFeePolicyPayload {
+    pub federation_id: FederationId,
+    pub policy: Option<FeePolicy>,
+}
+
+/// Replaces the limits of a federation, limits that are not set are removed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SetFederationLimitsPayload {
+    pub federation_id: FederationId,
+    pub max_balance: Option<Amount>,
+    pub max_payment: Option<Amount>,
+    pub max_daily_volume: Option<Amount>,
+}
+
+/// Permission required to call an authenticated endpoint of the gateway. API
+/// tokens are granted a set of scopes, while the gateway password grants all
+/// of them.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    Encodable,
+    Decodable,
+    ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum ApiScope {
+    /// Query the state of the gateway, its balances and payments
+    ReadOnly,
+    /// Create invoices, offers and deposit addresses
+    Invoice,
+    /// Pay invoices and offers and spend ecash
+    Payments,
+    /// Manage the onchain wallet and the channels of the lightning node
+    Onchain,
+    /// Manage federations, the configuration and the API tokens of the gateway
+    Admin,
+}
+
+impl ApiScope {
+    /// Returns true if a token with this scope may call an endpoint that
+    /// requires `required`
+    pub fn grants(self, required: ApiScope) -> bool {
+        self == ApiScope::Admin || self == required
+    }
+}
+
+impl fmt::Display for ApiScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scope = match self {
+            ApiScope::ReadOnly => "read-only",
+            ApiScope::Invoice => "invoice",
+            ApiScope::Payments => "payments",
+            ApiScope::Onchain => "onchain",
+            ApiScope::Admin => "admin",
+        };
+        write!(f, "{scope}")
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateApiTokenPayload {
+    /// Unique name of the token
+    pub name: String,
+    pub scopes: BTreeSet<ApiScope>,
+    /// The token never expires if not set
+    pub expiry_secs: Option<u64>,
+}
+
+/// Contains the secret token, which cannot be retrieved again after it has
+/// been created.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateApiTokenResponse {
+    pub name: String,
+    pub token: String,
+    pub expires_at: Option<SystemTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RevokeApiTokenPayload {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+pub struct ApiTokenInfo {
+    pub name: String,
+    pub scopes: BTreeSet<ApiScope>,
+    pub created_at: SystemTime,
+    pub expires_at: Option<SystemTime>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CreateInvoiceForOperatorPayload {
     pub amount_msats: u64,
@@ -282,6 +451,47 @@ pub struct PaymentLogPayload {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PaymentLogResponse(pub Vec<PersistedLogEntry>);
 
+/// HTTP header containing the hex encoded HMAC-SHA256, keyed with the secret
+/// of the webhook, of the value of [`WEBHOOK_TIMESTAMP_HEADER`] followed by a
+/// `.` and the request body.
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Fedimint-Signature";
+
+/// HTTP header containing the unix time in seconds at which the request was
+/// sent. It is covered by the signature, so receivers can reject replayed
+/// requests by rejecting old timestamps.
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "X-Fedimint-Timestamp";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RegisterWebhookPayload {
+    pub url: SafeUrl,
+    /// Secret used to sign the requests sent to the webhook
+    pub secret: String,
+    /// Kinds of the events sent to the webhook, all events are sent if empty
+    pub event_kinds: Vec<EventKind>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RemoveWebhookPayload {
+    pub webhook_id: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+pub struct WebhookInfo {
+    pub webhook_id: u64,
+    pub url: SafeUrl,
+    pub event_kinds: Vec<EventKind>,
+}
+
+/// Body of the requests sent to webhooks. Every event is delivered at least
+/// once, so receivers should deduplicate events by the federation id and the
+/// id of the event.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebhookEvent {
+    pub webhook_id: u64,
+    pub federation_id: FederationId,
+    pub event: PersistedLogEntry,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PaymentSummaryResponse {
     pub outgoing: PaymentStats,
@@ -481,6 +691,12 @@ pub enum LightningMode {
         #[arg(long = "lnd-macaroon", env = FM_LND_MACAROON_ENV)]
         lnd_macaroon: String,
     },
+    #[clap(name = "cln")]
+    Cln {
+        /// URL of the gRPC server exposed by the CLN extension plugin
+        #[arg(long = "cln-extension-addr", env = FM_CLN_EXTENSION_ADDR_ENV)]
+        cln_extension_addr: SafeUrl,
+    },
     #[clap(name = "ldk")]
     Ldk {
         /// LDK lightning server port
@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::str::FromStr;
 use std::time::SystemTime;
 
@@ -12,11 +12,15 @@
 use fedimint_core::encoding::{Decodable, Encodable};
 use fedimint_core::invite_code::InviteCode;
 use fedimint_core::module::registry::ModuleDecoderRegistry;
+use fedimint_core::util::SafeUrl;
 use fedimint_core::{Amount, impl_db_lookup, impl_db_record, push_db_pair_items, secp256k1};
+use fedimint_eventlog::{EventKind, EventLogId};
 use fedimint_gateway_common::envs::FM_GATEWAY_IROH_SECRET_KEY_OVERRIDE_ENV;
-use fedimint_gateway_common::{ConnectorType, FederationConfig, RegisteredProtocol};
+use fedimint_gateway_common::{
+    ApiScope, ConnectorType, FederationConfig, FederationLimits, FeePolicy, RegisteredProtocol,
+};
 use fedimint_ln_common::serde_routing_fees;
-use fedimint_lnv2_common::contracts::{IncomingContract, PaymentImage};
+use fedimint_lnv2_common::contracts::{AmountlessIncomingContract, IncomingContract, PaymentImage};
 use fedimint_lnv2_common::gateway_api::PaymentFee;
 use futures::{FutureExt, StreamExt};
 use lightning_invoice::RoutingFees;
@@ -80,6 +84,19 @@ async fn load_registered_incoming_contract(
         payment_image: PaymentImage,
     ) -> Option<RegisteredIncomingContract>;
 
+    /// Saves the template of an amountless incoming contract, returning the
+    /// previous template with the same payment image if it existed.
+    async fn save_registered_amountless_incoming_contract(
+        &mut self,
+        federation_id: FederationId,
+        contract: AmountlessIncomingContract,
+    ) -> Option<RegisteredAmountlessIncomingContract>;
+
+    async fn load_registered_amountless_incoming_contract(
+        &mut self,
+        payment_image: PaymentImage,
+    ) -> Option<RegisteredAmountlessIncomingContract>;
+
     /// Reads and serializes structures from the gateway's database for the
     /// purpose for serializing to JSON for inspection.
     async fn dump_database(
@@ -106,6 +123,87 @@ async fn save_federation_backup_record(
         federation_id: FederationId,
         backup_time: Option<SystemTime>,
     );
+
+    /// Returns the operator-set limits of a federation
+    async fn load_federation_limits(&mut self, federation_id: FederationId) -> FederationLimits;
+
+    /// Saves the operator-set limits of a federation
+    async fn save_federation_limits(
+        &mut self,
+        federation_id: FederationId,
+        limits: FederationLimits,
+    );
+
+    /// Removes the limits and the volume records of a federation
+    async fn remove_federation_limits(&mut self, federation_id: FederationId);
+
+    /// Adds `amount` to the volume of the federation in the hour of `now` and
+    /// removes volume records older than a day.
+    async fn record_federation_volume(
+        &mut self,
+        federation_id: FederationId,
+        amount: Amount,
+        now: SystemTime,
+    );
+
+    /// Subtracts `amount` from the volume recorded at `recorded_at`, used when
+    /// a payment whose volume was recorded is not made after all
+    async fn release_federation_volume(
+        &mut self,
+        federation_id: FederationId,
+        amount: Amount,
+        recorded_at: SystemTime,
+    );
+
+    /// Returns the volume of the federation over the 24 hours preceding `now`
+    async fn load_federation_daily_volume(
+        &mut self,
+        federation_id: FederationId,
+        now: SystemTime,
+    ) -> Amount;
+
+    /// Saves a new webhook and returns its id
+    async fn save_new_webhook(&mut self, webhook: &Webhook) -> u64;
+
+    async fn load_webhooks(&mut self) -> BTreeMap<u64, Webhook>;
+
+    async fn load_webhook(&mut self, webhook_id: u64) -> Option<Webhook>;
+
+    /// Removes the webhook along with its delivery cursors. Returns false if
+    /// the webhook does not exist.
+    async fn remove_webhook(&mut self, webhook_id: u64) -> bool;
+
+    /// Returns the position in the event log of the federation from which on
+    /// events have not been delivered to the webhook yet
+    async fn load_webhook_cursor(
+        &mut self,
+        webhook_id: u64,
+        federation_id: FederationId,
+    ) -> Option<EventLogId>;
+
+    async fn save_webhook_cursor(
+        &mut self,
+        webhook_id: u64,
+        federation_id: FederationId,
+        cursor: EventLogId,
+    );
+
+    async fn load_fee_policy(&mut self, federation_id: FederationId) -> Option<FeePolicy>;
+
+    async fn load_fee_policies(&mut self) -> BTreeMap<FederationId, FeePolicy>;
+
+    /// Replaces the fee policy of the federation, the policy is removed if
+    /// `None` is given
+    async fn save_fee_policy(&mut self, federation_id: FederationId, policy: Option<FeePolicy>);
+
+    /// Saves a new API token. Returns false if a token with the same name
+    /// already exists.
+    async fn save_new_api_token(&mut self, name: String, token: &ApiToken) -> bool;
+
+    async fn load_api_tokens(&mut self) -> BTreeMap<String, ApiToken>;
+
+    /// Returns false if the token does not exist
+    async fn remove_api_token(&mut self, name: String) -> bool;
 }
 
 impl<Cap: Send> GatewayDbtxNcExt for DatabaseTransaction<'_, Cap> {
@@ -204,6 +302,29 @@ async fn load_registered_incoming_contract(
             .await
     }
 
+    async fn save_registered_amountless_incoming_contract(
+        &mut self,
+        federation_id: FederationId,
+        contract: AmountlessIncomingContract,
+    ) -> Option<RegisteredAmountlessIncomingContract> {
+        self.insert_entry(
+            &RegisteredAmountlessIncomingContractKey(contract.payment_image()),
+            &RegisteredAmountlessIncomingContract {
+                federation_id,
+                contract,
+            },
+        )
+        .await
+    }
+
+    async fn load_registered_amountless_incoming_contract(
+        &mut self,
+        payment_image: PaymentImage,
+    ) -> Option<RegisteredAmountlessIncomingContract> {
+        self.get_value(&RegisteredAmountlessIncomingContractKey(payment_image))
+            .await
+    }
+
     async fn dump_database(
         &mut self,
         prefix_names: Vec<String>,
@@ -281,6 +402,210 @@ async fn save_federation_backup_record(
         self.insert_entry(&FederationBackupKey { federation_id }, &backup_time)
             .await;
     }
+
+    async fn load_federation_limits(&mut self, federation_id: FederationId) -> FederationLimits {
+        self.get_value(&FederationLimitsKey { federation_id })
+            .await
+            .unwrap_or_default()
+    }
+
+    async fn save_federation_limits(
+        &mut self,
+        federation_id: FederationId,
+        limits: FederationLimits,
+    ) {
+        self.insert_entry(&FederationLimitsKey { federation_id }, &limits)
+            .await;
+    }
+
+    async fn remove_federation_limits(&mut self, federation_id: FederationId) {
+        self.remove_entry(&FederationLimitsKey { federation_id })
+            .await;
+        self.remove_by_prefix(&FederationVolumePrefix { federation_id })
+            .await;
+    }
+
+    async fn record_federation_volume(
+        &mut self,
+        federation_id: FederationId,
+        amount: Amount,
+        now: SystemTime,
+    ) {
+        let hour = hours_since_epoch(now);
+
+        let expired_keys = self
+            .find_by_prefix(&FederationVolumePrefix { federation_id })
+            .await
+            .map(|(key, _)| key)
+            .filter(|key| std::future::ready(key.hour + VOLUME_WINDOW_HOURS <= hour))
+            .collect::<Vec<FederationVolumeKey>>()
+            .await;
+
+        for key in expired_keys {
+            self.remove_entry(&key).await;
+        }
+
+        let key = FederationVolumeKey {
+            federation_id,
+            hour,
+        };
+        let volume = self.get_value(&key).await.unwrap_or(Amount::ZERO);
+        self.insert_entry(&key, &(volume + amount)).await;
+    }
+
+    async fn release_federation_volume(
+        &mut self,
+        federation_id: FederationId,
+        amount: Amount,
+        recorded_at: SystemTime,
+    ) {
+        let key = FederationVolumeKey {
+            federation_id,
+            hour: hours_since_epoch(recorded_at),
+        };
+
+        // The record is gone if it expired in the meantime
+        if let Some(volume) = self.get_value(&key).await {
+            self.insert_entry(&key, &volume.saturating_sub(amount))
+                .await;
+        }
+    }
+
+    async fn load_federation_daily_volume(
+        &mut self,
+        federation_id: FederationId,
+        now: SystemTime,
+    ) -> Amount {
+        let hour = hours_since_epoch(now);
+
+        self.find_by_prefix(&FederationVolumePrefix { federation_id })
+            .await
+            .filter(|(key, _)| std::future::ready(hour < key.hour + VOLUME_WINDOW_HOURS))
+            .fold(
+                Amount::ZERO,
+                |total, (_, volume)| async move { total + volume },
+            )
+            .await
+    }
+
+    async fn save_new_webhook(&mut self, webhook: &Webhook) -> u64 {
+        // Ids are never reused, such that a receiver can't mistake the events of a
+        // new webhook for those of a removed one
+        let webhook_id = self.get_value(&NextWebhookIdKey).await.unwrap_or(0);
+
+        self.insert_entry(&NextWebhookIdKey, &(webhook_id + 1))
+            .await;
+        self.insert_new_entry(&WebhookKey { webhook_id }, webhook)
+            .await;
+
+        webhook_id
+    }
+
+    async fn load_webhooks(&mut self) -> BTreeMap<u64, Webhook> {
+        self.find_by_prefix(&WebhookPrefix)
+            .await
+            .map(|(key, webhook)| (key.webhook_id, webhook))
+            .collect::<BTreeMap<u64, Webhook>>()
+            .await
+    }
+
+    async fn load_webhook(&mut self, webhook_id: u64) -> Option<Webhook> {
+        self.get_value(&WebhookKey { webhook_id }).await
+    }
+
+    async fn remove_webhook(&mut self, webhook_id: u64) -> bool {
+        self.remove_by_prefix(&WebhookCursorPrefix { webhook_id })
+            .await;
+
+        self.remove_entry(&WebhookKey { webhook_id })
+            .await
+            .is_some()
+    }
+
+    async fn load_webhook_cursor(
+        &mut self,
+        webhook_id: u64,
+        federation_id: FederationId,
+    ) -> Option<EventLogId> {
+        self.get_value(&WebhookCursorKey {
+            webhook_id,
+            federation_id,
+        })
+        .await
+    }
+
+    async fn save_webhook_cursor(
+        &mut self,
+        webhook_id: u64,
+        federation_id: FederationId,
+        cursor: EventLogId,
+    ) {
+        self.insert_entry(
+            &WebhookCursorKey {
+                webhook_id,
+                federation_id,
+            },
+            &cursor,
+        )
+        .await;
+    }
+
+    async fn load_fee_policy(&mut self, federation_id: FederationId) -> Option<FeePolicy> {
+        self.get_value(&FeePolicyKey { federation_id }).await
+    }
+
+    async fn load_fee_policies(&mut self) -> BTreeMap<FederationId, FeePolicy> {
+        self.find_by_prefix(&FeePolicyPrefix)
+            .await
+            .map(|(key, policy)| (key.federation_id, policy))
+            .collect::<BTreeMap<FederationId, FeePolicy>>()
+            .await
+    }
+
+    async fn save_fee_policy(&mut self, federation_id: FederationId, policy: Option<FeePolicy>) {
+        match policy {
+            Some(policy) => {
+                self.insert_entry(&FeePolicyKey { federation_id }, &policy)
+                    .await;
+            }
+            None => {
+                self.remove_entry(&FeePolicyKey { federation_id }).await;
+            }
+        }
+    }
+
+    async fn save_new_api_token(&mut self, name: String, token: &ApiToken) -> bool {
+        let key = ApiTokenKey { name };
+        if self.get_value(&key).await.is_some() {
+            return false;
+        }
+
+        self.insert_new_entry(&key, token).await;
+        true
+    }
+
+    async fn load_api_tokens(&mut self) -> BTreeMap<String, ApiToken> {
+        self.find_by_prefix(&ApiTokenPrefix)
+            .await
+            .map(|(key, token)| (key.name, token))
+            .collect::<BTreeMap<String, ApiToken>>()
+            .await
+    }
+
+    async fn remove_api_token(&mut self, name: String) -> bool {
+        self.remove_entry(&ApiTokenKey { name }).await.is_some()
+    }
+}
+
+/// Number of hourly volume records that make up the daily volume of a
+/// federation
+const VOLUME_WINDOW_HOURS: u64 = 24;
+
+fn hours_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / (60 * 60)
 }
 
 #[repr(u8)]
@@ -294,6 +619,14 @@ enum DbKeyPrefix {
     ClientDatabase = 0x10,
     Iroh = 0x11,
     FederationBackup = 0x12,
+    RegisteredAmountlessIncomingContract = 0x13,
+    FederationLimits = 0x14,
+    FederationVolume = 0x15,
+    Webhook = 0x16,
+    WebhookCursor = 0x17,
+    ApiToken = 0x18,
+    FeePolicy = 0x19,
+    NextWebhookId = 0x1A,
 }
 
 impl std::fmt::Display for DbKeyPrefix {
@@ -508,6 +841,143 @@ pub struct FederationBackupKey {
     query_prefix = FederationBackupPrefix,
 );
 
+#[derive(Debug, Encodable, Decodable)]
+pub struct FederationLimitsKey {
+    federation_id: FederationId,
+}
+
+impl_db_record!(
+    key = FederationLimitsKey,
+    value = FederationLimits,
+    db_prefix = DbKeyPrefix::FederationLimits,
+);
+
+/// Volume sent and received for a federation within one hour since the unix
+/// epoch
+#[derive(Debug, Encodable, Decodable)]
+pub struct FederationVolumeKey {
+    federation_id: FederationId,
+    hour: u64,
+}
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct FederationVolumePrefix {
+    federation_id: FederationId,
+}
+
+impl_db_record!(
+    key = FederationVolumeKey,
+    value = Amount,
+    db_prefix = DbKeyPrefix::FederationVolume,
+);
+
+impl_db_lookup!(
+    key = FederationVolumeKey,
+    query_prefix = FederationVolumePrefix,
+);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct WebhookKey {
+    webhook_id: u64,
+}
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct WebhookPrefix;
+
+/// Endpoint registered by the operator that the gateway sends the events of
+/// all federation clients to
+#[derive(Debug, Clone, Eq, PartialEq, Encodable, Decodable, Serialize, Deserialize)]
+pub struct Webhook {
+    pub url: SafeUrl,
+    pub secret: String,
+    pub event_kinds: Vec<EventKind>,
+}
+
+impl_db_record!(
+    key = WebhookKey,
+    value = Webhook,
+    db_prefix = DbKeyPrefix::Webhook,
+);
+
+impl_db_lookup!(key = WebhookKey, query_prefix = WebhookPrefix);
+
+/// Id assigned to the next webhook that is registered
+#[derive(Debug, Encodable, Decodable)]
+struct NextWebhookIdKey;
+
+impl_db_record!(
+    key = NextWebhookIdKey,
+    value = u64,
+    db_prefix = DbKeyPrefix::NextWebhookId,
+);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct WebhookCursorKey {
+    webhook_id: u64,
+    federation_id: FederationId,
+}
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct WebhookCursorPrefix {
+    webhook_id: u64,
+}
+
+impl_db_record!(
+    key = WebhookCursorKey,
+    value = EventLogId,
+    db_prefix = DbKeyPrefix::WebhookCursor,
+);
+
+impl_db_lookup!(key = WebhookCursorKey, query_prefix = WebhookCursorPrefix);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct ApiTokenKey {
+    name: String,
+}
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct ApiTokenPrefix;
+
+/// Named token that grants access to the endpoints of the admin API that
+/// require one of its scopes. Only the hash of the token is stored.
+#[derive(Debug, Clone, Eq, PartialEq, Encodable, Decodable, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub token_hash: sha256::Hash,
+    pub scopes: BTreeSet<ApiScope>,
+    pub created_at: SystemTime,
+    pub expires_at: Option<SystemTime>,
+}
+
+impl ApiToken {
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+impl_db_record!(
+    key = ApiTokenKey,
+    value = ApiToken,
+    db_prefix = DbKeyPrefix::ApiToken,
+);
+
+impl_db_lookup!(key = ApiTokenKey, query_prefix = ApiTokenPrefix);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct FeePolicyKey {
+    federation_id: FederationId,
+}
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct FeePolicyPrefix;
+
+impl_db_record!(
+    key = FeePolicyKey,
+    value = FeePolicy,
+    db_prefix = DbKeyPrefix::FeePolicy,
+);
+
+impl_db_lookup!(key = FeePolicyKey, query_prefix = FeePolicyPrefix);
+
 pub fn get_gatewayd_database_migrations() -> BTreeMap<DatabaseVersion, GeneralDbMigrationFn> {
     let mut migrations: BTreeMap<DatabaseVersion, GeneralDbMigrationFn> = BTreeMap::new();
     migrations.insert(
@@ -768,5 +1238,25 @@ pub struct RegisteredIncomingContract {
     db_prefix = DbKeyPrefix::RegisteredIncomingContract,
 );
 
+#[derive(Debug, Encodable, Decodable)]
+struct RegisteredAmountlessIncomingContractKey(pub PaymentImage);
+
+/// Template of an incoming contract for an amountless invoice. The contract
+/// itself is only created once the payment arrives and its amount is known.
+#[derive(Debug, Encodable, Decodable)]
+pub struct RegisteredAmountlessIncomingContract {
+    pub federation_id: FederationId,
+    pub contract: AmountlessIncomingContract,
+}
+
+impl_db_record!(
+    key = RegisteredAmountlessIncomingContractKey,
+    value = RegisteredAmountlessIncomingContract,
+    db_prefix = DbKeyPrefix::RegisteredAmountlessIncomingContract,
+);
+
 #[cfg(test)]
 mod migration_tests;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,65 @@
+use std::time::{Duration, SystemTime};
+
+use fedimint_core::config::FederationId;
+use fedimint_core::db::Database;
+use fedimint_core::db::mem_impl::MemDatabase;
+use fedimint_core::module::registry::ModuleDecoderRegistry;
+use fedimint_core::{Amount, sats};
+
+use super::GatewayDbtxNcExt;
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+#[tokio::test(flavor = "multi_thread")]
+async fn daily_volume_rolls_over_hourly() {
+    let db = Database::new(MemDatabase::new(), ModuleDecoderRegistry::default());
+    let mut dbtx = db.begin_transaction_nc().await;
+
+    let federation_id = FederationId::dummy();
+    let start = SystemTime::UNIX_EPOCH + 1000 * HOUR;
+
+    dbtx.record_federation_volume(federation_id, sats(1), start)
+        .await;
+    dbtx.record_federation_volume(federation_id, sats(2), start + HOUR)
+        .await;
+
+    for (elapsed, volume) in [
+        (Duration::ZERO, sats(1)),
+        (HOUR, sats(3)),
+        // The first record counts until the end of the hour a day after it
+        (24 * HOUR - Duration::from_secs(1), sats(3)),
+        (24 * HOUR, sats(2)),
+        (25 * HOUR, Amount::ZERO),
+    ] {
+        assert_eq!(
+            dbtx.load_federation_daily_volume(federation_id, start + elapsed)
+                .await,
+            volume
+        );
+    }
+
+    // Releasing volume only affects the hour it was recorded in
+    dbtx.release_federation_volume(federation_id, sats(1), start + HOUR)
+        .await;
+
+    assert_eq!(
+        dbtx.load_federation_daily_volume(federation_id, start + HOUR)
+            .await,
+        sats(2)
+    );
+
+    // Recording new volume removes the records that expired
+    dbtx.record_federation_volume(federation_id, sats(4), start + 48 * HOUR)
+        .await;
+
+    assert_eq!(
+        dbtx.load_federation_daily_volume(federation_id, start + HOUR)
+            .await,
+        Amount::ZERO
+    );
+    assert_eq!(
+        dbtx.load_federation_daily_volume(federation_id, start + 48 * HOUR)
+            .await,
+        sats(4)
+    );
+}
@@ -52,6 +52,10 @@ pub enum PublicGatewayError {
     FederationNotConnected(#[from] FederationNotConnected),
     #[error("Failed to receive ecash: {failure_reason}")]
     ReceiveEcashError { failure_reason: String },
+    #[error("Federation limit exceeded: {0}")]
+    FederationLimitExceeded(String),
+    #[error("Circuit breaker is open: {0}")]
+    CircuitBreakerOpen(String),
     #[error("Unexpected Error: {}", OptStacktrace(.0))]
     Unexpected(#[from] anyhow::Error),
 }
@@ -70,6 +74,14 @@ fn into_response(self) -> Response {
                 "Failed to receive ecash".to_string(),
                 StatusCode::INTERNAL_SERVER_ERROR,
             ),
+            PublicGatewayError::FederationLimitExceeded(_) => (
+                "Payment exceeds the limits of the gateway for this federation".to_string(),
+                StatusCode::BAD_REQUEST,
+            ),
+            PublicGatewayError::CircuitBreakerOpen(_) => (
+                "Gateway is temporarily not routing payments for this federation".to_string(),
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
             PublicGatewayError::Lightning(_) => (
                 "Lightning Network operation failed".to_string(),
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -2,16 +2,20 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use fedimint_client::ClientHandle;
+use fedimint_core::Amount;
+use fedimint_core::core::ModuleKind;
 use fedimint_eventlog::{
-    DBTransactionEventLogExt, Event, EventKind, EventLogId, PersistedLogEntry,
+    DBTransactionEventLogExt, Event, EventKind, EventLogId, EventPersistence, PersistedLogEntry,
 };
 use fedimint_gwv2_client::events::{
     CompleteLightningPaymentSucceeded, IncomingPaymentFailed, IncomingPaymentStarted,
     IncomingPaymentSucceeded, OutgoingPaymentFailed, OutgoingPaymentStarted,
     OutgoingPaymentSucceeded,
 };
+use fedimint_lnv2_common::gateway_api::PaymentFee;
 use fedimint_mint_client::event::{OOBNotesReissued, OOBNotesSpent};
 use fedimint_wallet_client::events::{DepositConfirmed, WithdrawRequest};
+use serde::{Deserialize, Serialize};
 
 pub const ALL_GATEWAY_EVENTS: [EventKind; 11] = [
     OutgoingPaymentStarted::KIND,
@@ -27,6 +31,27 @@
     DepositConfirmed::KIND,
 ];
 
+/// Logged to the event log of a federation when its fee policy changes the
+/// fees the gateway advertises for it, along with the liquidity the new fees
+/// were computed from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeesUpdated {
+    pub previous_lightning_fee: PaymentFee,
+    pub previous_transaction_fee: PaymentFee,
+    pub lightning_fee: PaymentFee,
+    pub transaction_fee: PaymentFee,
+    pub outbound_liquidity: Amount,
+    pub inbound_liquidity: Amount,
+    pub ecash_balance: Amount,
+    pub daily_volume: Amount,
+}
+
+impl Event for FeesUpdated {
+    const MODULE: Option<ModuleKind> = None;
+    const KIND: EventKind = EventKind::from_static("gateway-fees-updated");
+    const PERSISTENCE: EventPersistence = EventPersistence::Persistent;
+}
+
 /// Searches through the event log for all events that occurred within the
 /// specified time bounds.
 ///
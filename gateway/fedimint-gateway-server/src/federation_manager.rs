@@ -1,14 +1,14 @@
 use std::collections::BTreeMap;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::SystemTime;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 use bitcoin::secp256k1::Keypair;
 use fedimint_client::ClientHandleArc;
 use fedimint_core::config::{FederationId, FederationIdPrefix, JsonClientConfig};
 use fedimint_core::db::{Committable, DatabaseTransaction, NonCommittable};
 use fedimint_core::util::{FmtCompactAnyhow as _, Spanned};
-use fedimint_gateway_common::FederationInfo;
+use fedimint_gateway_common::{CircuitBreakerState, FederationInfo};
 use fedimint_gateway_server_db::GatewayDbtxNcExt as _;
 use fedimint_gw_client::GatewayClientModule;
 use fedimint_gwv2_client::GatewayClientModuleV2;
@@ -23,6 +23,70 @@
 /// An SCID of 0 is considered invalid by LND's HTLC interceptor.
 const INITIAL_INDEX: u64 = 1;
 
+/// Number of failed payments within `CIRCUIT_BREAKER_FAILURE_WINDOW` that opens
+/// the circuit breaker of a federation.
+const CIRCUIT_BREAKER_FAILURE_THRESHOLD: usize = 5;
+
+const CIRCUIT_BREAKER_FAILURE_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// How long the circuit breaker of a federation stays open after a burst of
+/// failed payments.
+const CIRCUIT_BREAKER_COOLDOWN: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Default)]
+struct CircuitBreaker {
+    /// Times of the failed payments within the failure window
+    failures: Vec<SystemTime>,
+
+    /// Time the circuit breaker was opened by a burst of failed payments
+    tripped_at: Option<SystemTime>,
+
+    /// Time the guardians of the federation were first found unreachable, set
+    /// until they can be reached again
+    unreachable_since: Option<SystemTime>,
+}
+
+impl CircuitBreaker {
+    fn state(&self, now: SystemTime) -> CircuitBreakerState {
+        if let Some(opened_at) = self.unreachable_since {
+            return CircuitBreakerState::Open {
+                opened_at,
+                reason: "The guardians of the federation are unreachable".to_string(),
+            };
+        }
+
+        match self.tripped_at {
+            Some(opened_at) if now < opened_at + CIRCUIT_BREAKER_COOLDOWN => {
+                CircuitBreakerState::Open {
+                    opened_at,
+                    reason: format!(
+                        "{CIRCUIT_BREAKER_FAILURE_THRESHOLD} payments failed within {} minutes",
+                        CIRCUIT_BREAKER_FAILURE_WINDOW.as_secs() / 60
+                    ),
+                }
+            }
+            _ => CircuitBreakerState::Closed,
+        }
+    }
+
+    /// Records a failed payment and returns true if this opened the circuit
+    /// breaker.
+    fn record_failure(&mut self, now: SystemTime) -> bool {
+        self.failures
+            .retain(|failure| now < *failure + CIRCUIT_BREAKER_FAILURE_WINDOW);
+        self.failures.push(now);
+
+        if self.failures.len() < CIRCUIT_BREAKER_FAILURE_THRESHOLD {
+            return false;
+        }
+
+        self.failures.clear();
+        self.tripped_at = Some(now);
+
+        true
+    }
+}
+
 // TODO: Add support for client lookup by payment hash (for LNv2).
 #[derive(Debug)]
 pub struct FederationManager {
@@ -39,6 +103,10 @@ pub struct FederationManager {
     /// federation, this value is incremented and assigned to the federation
     /// as the `federation_index`
     next_index: AtomicU64,
+
+    /// Map of `FederationId` -> `CircuitBreaker`. Only kept in memory, so all
+    /// circuit breakers are closed when the gateway restarts.
+    circuit_breakers: Mutex<BTreeMap<FederationId, CircuitBreaker>>,
 }
 
 impl FederationManager {
@@ -47,6 +115,7 @@ pub fn new() -> Self {
             clients: BTreeMap::new(),
             index_to_federation: BTreeMap::new(),
             next_index: AtomicU64::new(INITIAL_INDEX),
+            circuit_breakers: Mutex::new(BTreeMap::new()),
         }
     }
 
@@ -86,6 +155,11 @@ async fn remove_client(&mut self, federation_id: FederationId) -> AdminResult<()
         self.index_to_federation
             .retain(|_, fid| *fid != federation_id);
 
+        self.circuit_breakers
+            .lock()
+            .expect("Lock is not poisoned")
+            .remove(&federation_id);
+
         match Arc::into_inner(client) {
             Some(client) => {
                 client.shutdown().await;
@@ -199,6 +273,71 @@ pub fn client(&self, federation_id: &FederationId) -> Option<&Spanned<ClientHand
         self.clients.get(federation_id)
     }
 
+    /// Returns the clients of all connected federations.
+    pub fn clients(&self) -> Vec<(FederationId, Spanned<ClientHandleArc>)> {
+        self.clients
+            .iter()
+            .map(|(federation_id, client)| (*federation_id, client.clone()))
+            .collect()
+    }
+
+    pub fn circuit_breaker_state(&self, federation_id: FederationId) -> CircuitBreakerState {
+        self.circuit_breakers
+            .lock()
+            .expect("Lock is not poisoned")
+            .get(&federation_id)
+            .map_or(CircuitBreakerState::Closed, |breaker| {
+                breaker.state(fedimint_core::time::now())
+            })
+    }
+
+    /// Records a failed payment for the federation, a burst of failed payments
+    /// opens its circuit breaker.
+    pub fn record_payment_failure(&self, federation_id: FederationId) {
+        let tripped = self
+            .circuit_breakers
+            .lock()
+            .expect("Lock is not poisoned")
+            .entry(federation_id)
+            .or_default()
+            .record_failure(fedimint_core::time::now());
+
+        if tripped {
+            warn!(
+                target: LOG_GATEWAY,
+                federation_id = %federation_id,
+                "Opened circuit breaker after a burst of failed payments"
+            );
+        }
+    }
+
+    /// Opens the circuit breaker of the federation while its guardians are
+    /// unreachable and closes it again once they can be reached.
+    pub fn set_guardians_reachable(&self, federation_id: FederationId, reachable: bool) {
+        let mut circuit_breakers = self.circuit_breakers.lock().expect("Lock is not poisoned");
+        let breaker = circuit_breakers.entry(federation_id).or_default();
+
+        match (reachable, breaker.unreachable_since) {
+            (false, None) => {
+                warn!(
+                    target: LOG_GATEWAY,
+                    federation_id = %federation_id,
+                    "Opened circuit breaker since guardians are unreachable"
+                );
+                breaker.unreachable_since = Some(fedimint_core::time::now());
+            }
+            (true, Some(_)) => {
+                info!(
+                    target: LOG_GATEWAY,
+                    federation_id = %federation_id,
+                    "Guardians are reachable again"
+                );
+                breaker.unreachable_since = None;
+            }
+            _ => {}
+        }
+    }
+
     pub async fn federation_info(
         &self,
         federation_id: FederationId,
@@ -219,6 +358,11 @@ pub async fn federation_info(
                 let last_backup_time = dbtx.load_backup_record(federation_id).await.ok_or(FederationNotConnected {
                     federation_id_prefix: federation_id.to_prefix(),
                 })?;
+                let limits = dbtx.load_federation_limits(federation_id).await;
+                let daily_volume = dbtx
+                    .load_federation_daily_volume(federation_id, fedimint_core::time::now())
+                    .await;
+                let fee_policy = dbtx.load_fee_policy(federation_id).await;
 
                 Ok(FederationInfo {
                     federation_id,
@@ -226,6 +370,10 @@ pub async fn federation_info(
                     balance_msat,
                     config,
                     last_backup_time,
+                    limits,
+                    daily_volume,
+                    circuit_breaker: self.circuit_breaker_state(federation_id),
+                    fee_policy,
                 })
             })
             .await
@@ -264,6 +412,11 @@ pub async fn federation_info_all_federations(
                 .load_backup_record(*federation_id)
                 .await
                 .unwrap_or_default();
+            let limits = dbtx.load_federation_limits(*federation_id).await;
+            let daily_volume = dbtx
+                .load_federation_daily_volume(*federation_id, fedimint_core::time::now())
+                .await;
+            let fee_policy = dbtx.load_fee_policy(*federation_id).await;
             if let Some(config) = config {
                 federation_infos.push(FederationInfo {
                     federation_id: *federation_id,
@@ -271,6 +424,10 @@ pub async fn federation_info_all_federations(
                     balance_msat,
                     config,
                     last_backup_time,
+                    limits,
+                    daily_volume,
+                    circuit_breaker: self.circuit_breaker_state(*federation_id),
+                    fee_policy,
                 });
             }
         }
@@ -349,3 +506,80 @@ pub fn pop_next_index(&self) -> AdminResult<u64> {
         Ok(next_index)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use fedimint_gateway_common::CircuitBreakerState;
+
+    use super::{
+        CIRCUIT_BREAKER_COOLDOWN, CIRCUIT_BREAKER_FAILURE_THRESHOLD,
+        CIRCUIT_BREAKER_FAILURE_WINDOW, CircuitBreaker,
+    };
+
+    #[test]
+    fn burst_of_failures_opens_circuit_breaker_until_cooldown() {
+        let mut breaker = CircuitBreaker::default();
+        let start = SystemTime::UNIX_EPOCH;
+
+        for _ in 1..CIRCUIT_BREAKER_FAILURE_THRESHOLD {
+            assert!(!breaker.record_failure(start));
+        }
+
+        assert_eq!(breaker.state(start), CircuitBreakerState::Closed);
+
+        let opened_at = start + Duration::from_secs(1);
+
+        assert!(breaker.record_failure(opened_at));
+
+        assert_eq!(
+            breaker.state(opened_at + CIRCUIT_BREAKER_COOLDOWN - Duration::from_secs(1)),
+            CircuitBreakerState::Open {
+                opened_at,
+                reason: "5 payments failed within 10 minutes".to_string(),
+            }
+        );
+
+        assert_eq!(
+            breaker.state(opened_at + CIRCUIT_BREAKER_COOLDOWN),
+            CircuitBreakerState::Closed
+        );
+
+        // The failures that opened the circuit breaker don't count again
+        assert!(!breaker.record_failure(opened_at + CIRCUIT_BREAKER_COOLDOWN));
+    }
+
+    #[test]
+    fn failures_spread_over_more_than_the_window_do_not_open_circuit_breaker() {
+        let mut breaker = CircuitBreaker::default();
+        let interval =
+            CIRCUIT_BREAKER_FAILURE_WINDOW / (CIRCUIT_BREAKER_FAILURE_THRESHOLD - 1) as u32;
+
+        for i in 0..2 * CIRCUIT_BREAKER_FAILURE_THRESHOLD {
+            let now = SystemTime::UNIX_EPOCH + interval * i as u32;
+
+            assert!(!breaker.record_failure(now));
+            assert_eq!(breaker.state(now), CircuitBreakerState::Closed);
+        }
+    }
+
+    #[test]
+    fn unreachable_guardians_open_circuit_breaker_until_reachable() {
+        let mut breaker = CircuitBreaker::default();
+        let opened_at = SystemTime::UNIX_EPOCH;
+
+        breaker.unreachable_since = Some(opened_at);
+
+        // Unlike a burst of failures this does not expire after the cooldown
+        assert!(
+            breaker
+                .state(opened_at + 2 * CIRCUIT_BREAKER_COOLDOWN)
+                .is_open()
+        );
+
+        breaker.unreachable_since = None;
+
+        assert_eq!(breaker.state(opened_at), CircuitBreakerState::Closed);
+    }
+}
@@ -0,0 +1,288 @@
+use std::time::Duration;
+
+use fedimint_core::Amount;
+use fedimint_core::util::FmtCompactAnyhow as _;
+use fedimint_gateway_common::SetFeePolicyPayload;
+use fedimint_gateway_server_db::GatewayDbtxNcExt as _;
+use fedimint_lnv2_common::gateway_api::PaymentFee;
+use fedimint_logging::LOG_GATEWAY;
+use tracing::{info, warn};
+
+use crate::error::{AdminGatewayError, FederationNotConnected};
+use crate::events::FeesUpdated;
+use crate::{AdminResult, Gateway, check_fee_limits};
+
+/// How often the fees of federations with a fee policy are recomputed
+const FEE_POLICY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Computed fees only change in steps of a tenth of the range of the policy, so
+/// that small changes in liquidity do not change the advertised fees.
+const FEE_STEPS: u64 = 10;
+
+impl Gateway {
+    /// Replaces the fee policy of a federation and immediately applies it
+    pub async fn handle_set_fee_policy_msg(
+        &self,
+        SetFeePolicyPayload {
+            federation_id,
+            policy,
+        }: SetFeePolicyPayload,
+    ) -> AdminResult<()> {
+        let client = self
+            .federation_manager
+            .read()
+            .await
+            .client(&federation_id)
+            .cloned()
+            .ok_or(FederationNotConnected {
+                federation_id_prefix: federation_id.to_prefix(),
+            })?;
+
+        if let Some(policy) = policy {
+            if !is_valid_range(policy.min_lightning_fee, policy.max_lightning_fee)
+                || !is_valid_range(policy.min_transaction_fee, policy.max_transaction_fee)
+            {
+                return Err(AdminGatewayError::GatewayConfigurationError(
+                    "Minimum fees of the fee policy exceed its maximum fees".to_string(),
+                ));
+            }
+
+            check_fee_limits(
+                client.value(),
+                policy.max_lightning_fee,
+                policy.max_transaction_fee,
+            )
+            .await?;
+        }
+
+        let mut dbtx = self.gateway_db.begin_transaction().await;
+        dbtx.save_fee_policy(federation_id, policy).await;
+        dbtx.commit_tx().await;
+
+        info!(target: LOG_GATEWAY, %federation_id, ?policy, "Updated fee policy");
+
+        if policy.is_some() {
+            self.update_dynamic_fees().await;
+        }
+
+        Ok(())
+    }
+
+    /// Spawns a background task that recomputes the fees of all federations
+    /// with a fee policy every `FEE_POLICY_INTERVAL`.
+    pub(crate) fn spawn_fee_policy_task(&self) {
+        let self_copy = self.clone();
+        self.task_group
+            .spawn_cancellable_silent("update dynamic fees", async move {
+                let mut interval = tokio::time::interval(FEE_POLICY_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    self_copy.update_dynamic_fees().await;
+                }
+            });
+    }
+
+    /// Recomputes the fees of all federations with a fee policy from the
+    /// liquidity of the lightning node, the ecash balance and the volume of the
+    /// federation over the last 24 hours. Fee changes are logged to the event
+    /// log of the federation.
+    async fn update_dynamic_fees(&self) {
+        let policies = self
+            .gateway_db
+            .begin_transaction_nc()
+            .await
+            .load_fee_policies()
+            .await;
+
+        if policies.is_empty() {
+            return;
+        }
+
+        let channels = match self.get_lightning_context().await {
+            Ok(context) => context.lnrpc.list_channels().await,
+            Err(err) => Err(err),
+        };
+        let channels = match channels {
+            Ok(channels) => channels.channels,
+            Err(err) => {
+                warn!(target: LOG_GATEWAY, %err, "Failed to list channels, not updating fees");
+                return;
+            }
+        };
+
+        let (outbound_liquidity, inbound_liquidity) =
+            channels.iter().filter(|channel| channel.is_active).fold(
+                (Amount::ZERO, Amount::ZERO),
+                |(outbound, inbound), channel| {
+                    (
+                        outbound + Amount::from_sats(channel.outbound_liquidity_sats),
+                        inbound + Amount::from_sats(channel.inbound_liquidity_sats),
+                    )
+                },
+            );
+
+        let federation_manager = self.federation_manager.read().await;
+
+        for (federation_id, policy) in policies {
+            let Some(client) = federation_manager.client(&federation_id) else {
+                continue;
+            };
+
+            let ecash_balance = match client.value().get_balance_for_btc().await {
+                Ok(balance) => balance,
+                Err(err) => {
+                    warn!(target: LOG_GATEWAY, %federation_id, err = %err.fmt_compact_anyhow(), "Failed to get ecash balance, not updating fees");
+                    continue;
+                }
+            };
+
+            let mut dbtx = self.gateway_db.begin_transaction().await;
+            let Some(mut config) = dbtx.load_federation_config(federation_id).await else {
+                continue;
+            };
+            let daily_volume = dbtx
+                .load_federation_daily_volume(federation_id, fedimint_core::time::now())
+                .await;
+
+            // The lightning fee rises as the outbound liquidity that sending payments
+            // needs is used up, the transaction fee as the ecash that receiving payments
+            // needs is used up by the recent volume.
+            let lightning_fee = interpolate(
+                policy.min_lightning_fee,
+                policy.max_lightning_fee,
+                scarcity_step(inbound_liquidity, outbound_liquidity, FEE_STEPS),
+            );
+            let transaction_fee = interpolate(
+                policy.min_transaction_fee,
+                policy.max_transaction_fee,
+                scarcity_step(daily_volume, ecash_balance, 0),
+            );
+
+            if config.lightning_fee == lightning_fee && config.transaction_fee == transaction_fee {
+                continue;
+            }
+
+            let event = FeesUpdated {
+                previous_lightning_fee: config.lightning_fee,
+                previous_transaction_fee: config.transaction_fee,
+                lightning_fee,
+                transaction_fee,
+                outbound_liquidity,
+                inbound_liquidity,
+                ecash_balance,
+                daily_volume,
+            };
+
+            config.lightning_fee = lightning_fee;
+            config.transaction_fee = transaction_fee;
+            dbtx.save_federation_config(&config).await;
+            dbtx.commit_tx().await;
+
+            client.value().log_event(None, event).await;
+
+            info!(target: LOG_GATEWAY, %federation_id, %lightning_fee, %transaction_fee, "Updated dynamic fees");
+        }
+    }
+}
+
+/// Returns true if neither the base fee nor the proportional fee of `min`
+/// exceeds the one of `max`
+fn is_valid_range(min: PaymentFee, max: PaymentFee) -> bool {
+    min.base <= max.base && min.parts_per_million <= max.parts_per_million
+}
+
+/// Returns the share of `used` in `used + available` in steps of
+/// `1 / FEE_STEPS`, or `empty` if both are zero
+fn scarcity_step(used: Amount, available: Amount, empty: u64) -> u64 {
+    let total = u128::from(used.msats) + u128::from(available.msats);
+    if total == 0 {
+        return empty;
+    }
+
+    let step = (u128::from(used.msats) * u128::from(FEE_STEPS) + total / 2) / total;
+
+    step as u64
+}
+
+/// Returns the fee `step / FEE_STEPS` of the way from `min` to `max`
+fn interpolate(min: PaymentFee, max: PaymentFee, step: u64) -> PaymentFee {
+    let interpolate = |min: u64, max: u64| min + max.saturating_sub(min) * step / FEE_STEPS;
+
+    PaymentFee {
+        base: Amount::from_msats(interpolate(min.base.msats, max.base.msats)),
+        parts_per_million: interpolate(min.parts_per_million, max.parts_per_million),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN_FEE: PaymentFee = PaymentFee {
+        base: Amount::from_msats(1000),
+        parts_per_million: 100,
+    };
+
+    const MAX_FEE: PaymentFee = PaymentFee {
+        base: Amount::from_msats(3000),
+        parts_per_million: 1100,
+    };
+
+    #[test]
+    fn scarcity_step_rounds_to_the_nearest_step() {
+        assert_eq!(scarcity_step(Amount::ZERO, Amount::from_sats(1000), 0), 0);
+        assert_eq!(
+            scarcity_step(Amount::from_sats(1000), Amount::ZERO, 0),
+            FEE_STEPS
+        );
+        assert_eq!(
+            scarcity_step(Amount::from_sats(500), Amount::from_sats(500), 0),
+            5
+        );
+        assert_eq!(
+            scarcity_step(Amount::from_msats(45), Amount::from_msats(55), 0),
+            5
+        );
+        assert_eq!(
+            scarcity_step(Amount::from_msats(449), Amount::from_msats(551), 0),
+            4
+        );
+        assert_eq!(
+            scarcity_step(
+                Amount::from_msats(u64::MAX),
+                Amount::from_msats(u64::MAX),
+                0
+            ),
+            5
+        );
+    }
+
+    #[test]
+    fn scarcity_step_without_liquidity_is_empty() {
+        assert_eq!(scarcity_step(Amount::ZERO, Amount::ZERO, 0), 0);
+        assert_eq!(
+            scarcity_step(Amount::ZERO, Amount::ZERO, FEE_STEPS),
+            FEE_STEPS
+        );
+    }
+
+    #[test]
+    fn interpolate_between_min_and_max_fee() {
+        assert_eq!(interpolate(MIN_FEE, MAX_FEE, 0), MIN_FEE);
+        assert_eq!(interpolate(MIN_FEE, MAX_FEE, FEE_STEPS), MAX_FEE);
+        assert_eq!(
+            interpolate(MIN_FEE, MAX_FEE, 3),
+            PaymentFee {
+                base: Amount::from_msats(1600),
+                parts_per_million: 400,
+            }
+        );
+    }
+
+    #[test]
+    fn lightning_fee_is_max_without_channels() {
+        let step = scarcity_step(Amount::ZERO, Amount::ZERO, FEE_STEPS);
+
+        assert_eq!(interpolate(MIN_FEE, MAX_FEE, step), MAX_FEE);
+    }
+}
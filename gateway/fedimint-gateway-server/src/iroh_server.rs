@@ -1,4 +1,4 @@
-use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::collections::{BTreeMap, HashMap};
 use std::pin::Pin;
 use std::sync::Arc;
 
@@ -9,7 +9,7 @@
 use fedimint_core::module::{FEDIMINT_GATEWAY_ALPN, IrohGatewayRequest, IrohGatewayResponse};
 use fedimint_core::net::iroh::build_iroh_endpoint;
 use fedimint_core::task::TaskGroup;
-use fedimint_gateway_common::STOP_ENDPOINT;
+use fedimint_gateway_common::{ApiScope, STOP_ENDPOINT};
 use fedimint_logging::LOG_GATEWAY;
 use iroh::endpoint::Incoming;
 use reqwest::StatusCode;
@@ -82,33 +82,33 @@ fn make_post_handler<P, F, Fut>(f: F) -> PostHandler
 
 /// Helper struct for registering handlers that are called by the Iroh
 /// `Endpoint`. GET handlers and POST handlers are registered separately, since
-/// they contain different function signatures. If a route is authenticated, it
-/// is also stored in `authenticated_routes` which is checked when the specific
-/// handler is called.
+/// they contain different function signatures. If a route is authenticated,
+/// the scope it requires is also stored in `route_scopes` which is checked when
+/// the specific handler is called.
 pub struct Handlers {
     get_handlers: BTreeMap<String, GetHandler>,
     post_handlers: BTreeMap<String, PostHandler>,
-    authenticated_routes: BTreeSet<String>,
+    route_scopes: BTreeMap<String, ApiScope>,
 }
 
 impl Handlers {
     pub fn new() -> Self {
-        let mut authenticated_routes = BTreeSet::new();
-        authenticated_routes.insert(STOP_ENDPOINT.to_string());
+        let mut route_scopes = BTreeMap::new();
+        route_scopes.insert(STOP_ENDPOINT.to_string(), ApiScope::Admin);
         Handlers {
             get_handlers: BTreeMap::new(),
             post_handlers: BTreeMap::new(),
-            authenticated_routes,
+            route_scopes,
         }
     }
 
-    pub fn add_handler<F, Fut>(&mut self, route: &str, f: F, is_authenticated: bool)
+    pub fn add_handler<F, Fut>(&mut self, route: &str, f: F, scope: Option<ApiScope>)
     where
         F: Fn(Extension<Arc<Gateway>>) -> Fut + Clone + Send + Sync + 'static,
         Fut: Future<Output = Result<Json<serde_json::Value>, GatewayError>> + Send + 'static,
     {
-        if is_authenticated {
-            self.authenticated_routes.insert(route.to_string());
+        if let Some(scope) = scope {
+            self.route_scopes.insert(route.to_string(), scope);
         }
         self.get_handlers
             .insert(route.to_string(), make_get_handler(f));
@@ -118,14 +118,18 @@ pub fn get_handler(&self, route: &str) -> Option<&GetHandler> {
         self.get_handlers.get(route)
     }
 
-    pub fn add_handler_with_payload<P, F, Fut>(&mut self, route: &str, f: F, is_authenticated: bool)
-    where
+    pub fn add_handler_with_payload<P, F, Fut>(
+        &mut self,
+        route: &str,
+        f: F,
+        scope: Option<ApiScope>,
+    ) where
         P: DeserializeOwned + Send + 'static,
         F: Fn(Extension<Arc<Gateway>>, Json<P>) -> Fut + Clone + Send + Sync + 'static,
         Fut: Future<Output = Result<Json<serde_json::Value>, GatewayError>> + Send + 'static,
     {
-        if is_authenticated {
-            self.authenticated_routes.insert(route.to_string());
+        if let Some(scope) = scope {
+            self.route_scopes.insert(route.to_string(), scope);
         }
 
         self.post_handlers
@@ -136,8 +140,10 @@ pub fn get_handler_with_payload(&self, route: &str) -> Option<&PostHandler> {
         self.post_handlers.get(route)
     }
 
-    pub fn is_authenticated(&self, route: &str) -> bool {
-        self.authenticated_routes.contains(route)
+    /// Returns the scope required to call the route, or `None` if the route
+    /// is public
+    pub fn required_scope(&self, route: &str) -> Option<ApiScope> {
+        self.route_scopes.get(route).copied()
     }
 }
 
@@ -222,13 +228,16 @@ async fn handle_incoming_iroh_request(
 /// if the authentication is incorrect. Then it will lookup the specific handler
 /// in `Handlers`, execute it, and return the function's JSON along with an HTTP
 /// status code.
-async fn handle_request(
+pub(crate) async fn handle_request(
     request: &IrohGatewayRequest,
     gateway: Arc<Gateway>,
     handlers: Arc<Handlers>,
     task_group: TaskGroup,
 ) -> anyhow::Result<(StatusCode, Json<serde_json::Value>)> {
-    if handlers.is_authenticated(&request.route) && iroh_verify_password(&gateway, request).is_err()
+    if let Some(scope) = handlers.required_scope(&request.route)
+        && iroh_verify_password(&gateway, request, scope)
+            .await
+            .is_err()
     {
         return Ok((StatusCode::UNAUTHORIZED, Json(json!(()))));
     }
@@ -285,14 +294,15 @@ async fn handle_request(
     Ok((status, body))
 }
 
-/// Verifies if the supplied password in the Iroh request matches the gateway's
-/// password
-fn iroh_verify_password(
+/// Verifies if the supplied password in the Iroh request is the gateway's
+/// password or an API token that grants `scope`
+async fn iroh_verify_password(
     gateway: &Arc<Gateway>,
     request: &IrohGatewayRequest,
+    scope: ApiScope,
 ) -> anyhow::Result<()> {
     if let Some(password) = request.password.as_ref()
-        && bcrypt::verify(password, &gateway.bcrypt_password_hash.to_string())?
+        && gateway.authenticate(password, scope).await
     {
         return Ok(());
     }
@@ -20,9 +20,11 @@
 mod error;
 mod events;
 mod federation_manager;
+mod fee_policy;
 mod iroh_server;
 pub mod rpc_server;
 mod types;
+mod webhooks;
 
 use std::collections::{BTreeMap, BTreeSet};
 use std::env;
@@ -30,11 +32,11 @@
 use std::net::SocketAddr;
 use std::str::FromStr;
 use std::sync::Arc;
-use std::time::{Duration, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, anyhow, ensure};
 use async_trait::async_trait;
-use bitcoin::hashes::sha256;
+use bitcoin::hashes::{Hash as _, sha256};
 use bitcoin::{Address, Network, Txid, secp256k1};
 use clap::Parser;
 use client::GatewayClientBuilder;
@@ -52,7 +54,9 @@
 use fedimint_client::{Client, ClientHandleArc};
 use fedimint_core::config::FederationId;
 use fedimint_core::core::OperationId;
-use fedimint_core::db::{Committable, Database, DatabaseTransaction, apply_migrations};
+use fedimint_core::db::{
+    AutocommitError, Committable, Database, DatabaseTransaction, apply_migrations,
+};
 use fedimint_core::envs::is_env_var_set;
 use fedimint_core::invite_code::InviteCode;
 use fedimint_core::module::CommonModuleInit;
@@ -60,7 +64,7 @@
 use fedimint_core::rustls::install_crypto_provider;
 use fedimint_core::secp256k1::PublicKey;
 use fedimint_core::secp256k1::schnorr::Signature;
-use fedimint_core::task::{TaskGroup, TaskHandle, TaskShutdownToken, sleep};
+use fedimint_core::task::{TaskGroup, TaskHandle, TaskShutdownToken, sleep, timeout};
 use fedimint_core::time::duration_since_epoch;
 use fedimint_core::util::backoff_util::fibonacci_max_one_hour;
 use fedimint_core::util::{FmtCompact, FmtCompactAnyhow, SafeUrl, Spanned, retry};
@@ -69,20 +73,24 @@
 };
 use fedimint_eventlog::{DBTransactionEventLogExt, EventLogId, StructuredPaymentEvents};
 use fedimint_gateway_common::{
-    BackupPayload, ChainSource, CloseChannelsWithPeerRequest, CloseChannelsWithPeerResponse,
-    ConnectFedPayload, ConnectorType, CreateInvoiceForOperatorPayload, CreateOfferPayload,
-    CreateOfferResponse, DepositAddressPayload, DepositAddressRecheckPayload,
-    FederationBalanceInfo, FederationConfig, FederationInfo, GatewayBalances, GatewayFedConfig,
-    GatewayInfo, GetInvoiceRequest, GetInvoiceResponse, LeaveFedPayload, LightningInfo,
-    LightningMode, ListTransactionsPayload, ListTransactionsResponse, MnemonicResponse,
-    OpenChannelRequest, PayInvoiceForOperatorPayload, PayOfferPayload, PayOfferResponse,
-    PaymentLogPayload, PaymentLogResponse, PaymentStats, PaymentSummaryPayload,
-    PaymentSummaryResponse, ReceiveEcashPayload, ReceiveEcashResponse, RegisteredProtocol,
-    SendOnchainRequest, SetFeesPayload, SetMnemonicPayload, SpendEcashPayload, SpendEcashResponse,
-    V1_API_ENDPOINT, WithdrawPayload, WithdrawPreviewPayload, WithdrawPreviewResponse,
-    WithdrawResponse,
+    ApiScope, ApiTokenInfo, BackupPayload, ChainSource, CircuitBreakerState,
+    CloseChannelsWithPeerRequest, CloseChannelsWithPeerResponse, ConnectFedPayload, ConnectorType,
+    CreateApiTokenPayload, CreateApiTokenResponse, CreateInvoiceForOperatorPayload,
+    CreateOfferPayload, CreateOfferResponse, DepositAddressPayload, DepositAddressRecheckPayload,
+    FederationBalanceInfo, FederationConfig, FederationInfo, FederationLimits, GatewayBalances,
+    GatewayFedConfig, GatewayInfo, GetInvoiceRequest, GetInvoiceResponse, LeaveFedPayload,
+    LightningInfo, LightningMode, ListTransactionsPayload, ListTransactionsResponse,
+    MnemonicResponse, OpenChannelRequest, PayInvoiceForOperatorPayload, PayOfferPayload,
+    PayOfferResponse, PaymentDirection, PaymentLogPayload, PaymentLogResponse, PaymentStats,
+    PaymentSummaryPayload, PaymentSummaryResponse, ReceiveEcashPayload, ReceiveEcashResponse,
+    RegisteredProtocol, RevokeApiTokenPayload, SendOnchainRequest, SetFederationLimitsPayload,
+    SetFeesPayload, SetMnemonicPayload, SpendEcashPayload, SpendEcashResponse, V1_API_ENDPOINT,
+    WithdrawPayload, WithdrawPreviewPayload, WithdrawPreviewResponse, WithdrawResponse,
+};
+use fedimint_gateway_server_db::{
+    ApiToken, GatewayDbtxNcExt as _, RegisteredAmountlessIncomingContract,
+    get_gatewayd_database_migrations,
 };
-use fedimint_gateway_server_db::{GatewayDbtxNcExt as _, get_gatewayd_database_migrations};
 pub use fedimint_gateway_ui::IAdminGateway;
 use fedimint_gw_client::events::compute_lnv1_stats;
 use fedimint_gw_client::pay::{OutgoingPaymentError, OutgoingPaymentErrorType};
@@ -92,25 +100,29 @@
 };
 use fedimint_gwv2_client::events::compute_lnv2_stats;
 use fedimint_gwv2_client::{
-    EXPIRATION_DELTA_MINIMUM_V2, FinalReceiveState, GatewayClientModuleV2, IGatewayClientV2,
+    EXPIRATION_DELTA_MINIMUM_V2, FederationUnreachable, FinalReceiveState, GatewayClientModuleV2,
+    IGatewayClientV2,
 };
+use fedimint_lightning::cln::GatewayClnClient;
 use fedimint_lightning::lnd::GatewayLndClient;
 use fedimint_lightning::{
     CreateInvoiceRequest, ILnRpcClient, InterceptPaymentRequest, InterceptPaymentResponse,
     InvoiceDescription, LightningContext, LightningRpcError, PayInvoiceResponse, PaymentAction,
     RouteHtlcStream, ldk,
 };
+use fedimint_ln_client::incoming::IncomingSmError;
 use fedimint_ln_client::pay::PaymentData;
 use fedimint_ln_common::LightningCommonInit;
 use fedimint_ln_common::config::LightningClientConfig;
 use fedimint_ln_common::contracts::outgoing::OutgoingContractAccount;
 use fedimint_ln_common::contracts::{IdentifiableContract, Preimage};
-use fedimint_lnv2_common::Bolt11InvoiceDescription;
 use fedimint_lnv2_common::contracts::{IncomingContract, PaymentImage};
 use fedimint_lnv2_common::gateway_api::{
-    CreateBolt11InvoicePayload, PaymentFee, RoutingInfo, SendPaymentPayload,
+    CreateAmountlessBolt11InvoicePayload, CreateBolt11InvoicePayload, PaymentFee, RoutingInfo,
+    SendPaymentPayload,
 };
 use fedimint_lnv2_common::lnurl::VerifyResponse;
+use fedimint_lnv2_common::{Bolt11InvoiceDescription, LightningInvoice};
 use fedimint_logging::LOG_GATEWAY;
 use fedimint_mint_client::{
     MintClientInit, MintClientModule, SelectNotesWithAtleastAmount, SelectNotesWithExactAmount,
@@ -118,6 +130,7 @@
 use fedimint_wallet_client::{PegOutFees, WalletClientInit, WalletClientModule, WithdrawState};
 use futures::stream::StreamExt;
 use lightning_invoice::{Bolt11Invoice, RoutingFees};
+use rand::Rng as _;
 use rand::rngs::OsRng;
 use tokio::sync::RwLock;
 use tracing::{debug, info, info_span, warn};
@@ -491,7 +504,7 @@ pub async fn new_with_default_modules(
         // Gateway module will be attached when the federation clients are created
         // because the LN RPC will be injected with `GatewayClientGen`.
         let mut registry = ClientModuleInitRegistry::new();
-        registry.attach(MintClientInit);
+        registry.attach(MintClientInit::default());
         registry.attach(WalletClientInit::new(dyn_bitcoin_rpc));
 
         let client_builder =
@@ -651,6 +664,9 @@ pub async fn run(
         self.load_clients().await?;
         self.start_gateway(runtime, mnemonic_receiver.resubscribe());
         self.spawn_backup_task();
+        self.spawn_guardian_health_task();
+        self.spawn_webhook_tasks().await;
+        self.spawn_fee_policy_task();
         // start metrics server
         fedimint_metrics::spawn_api_server(self.metrics_listen, self.task_group.clone()).await?;
         // start webserver last to avoid handling requests before fully initialized
@@ -680,6 +696,50 @@ fn spawn_backup_task(&self) {
             });
     }
 
+    /// Spawns a background task that checks every `GUARDIAN_HEALTH_INTERVAL`
+    /// if the guardians of each federation are reachable and opens the circuit
+    /// breaker of federations whose guardians are not.
+    fn spawn_guardian_health_task(&self) {
+        let self_copy = self.clone();
+        self.task_group
+            .spawn_cancellable_silent("check guardian health", async move {
+                const GUARDIAN_HEALTH_INTERVAL: Duration = Duration::from_secs(60);
+                let mut interval = tokio::time::interval(GUARDIAN_HEALTH_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    self_copy.check_guardian_health().await;
+                }
+            });
+    }
+
+    /// Requests the session count from the guardians of every federation, which
+    /// requires a threshold of guardians to respond.
+    async fn check_guardian_health(&self) {
+        const GUARDIAN_HEALTH_TIMEOUT: Duration = Duration::from_secs(30);
+
+        // Do not hold the lock while waiting for the guardians to respond
+        let clients = self.federation_manager.read().await.clients();
+
+        let results = futures::future::join_all(clients.into_iter().map(
+            |(federation_id, client)| async move {
+                let reachable = timeout(
+                    GUARDIAN_HEALTH_TIMEOUT,
+                    client.value().api().session_count(),
+                )
+                .await
+                .is_ok_and(|session_count| session_count.is_ok());
+
+                (federation_id, reachable)
+            },
+        ))
+        .await;
+
+        let federation_manager = self.federation_manager.read().await;
+        for (federation_id, reachable) in results {
+            federation_manager.set_guardians_reachable(federation_id, reachable);
+        }
+    }
+
     /// Loops through all federations and checks their last save backup time. If
     /// the last saved backup time is past the threshold time, backup the
     /// federation.
@@ -821,7 +881,10 @@ async fn route_lightning_payments<'a>(
             .await;
         info!(target: LOG_GATEWAY, "Gateway is running");
 
-        if matches!(self.lightning_mode, LightningMode::Lnd { .. }) {
+        if matches!(
+            self.lightning_mode,
+            LightningMode::Lnd { .. } | LightningMode::Cln { .. }
+        ) {
             // Re-register the gateway with all federations after connecting to the
             // lightning node
             let mut dbtx = self.gateway_db.begin_transaction_nc().await;
@@ -942,6 +1005,21 @@ async fn try_handle_lightning_payment_lnv2(
             )
             .await?;
 
+        let federation_id = client.federation_id();
+        let amount = Amount::from_msats(htlc_request.amount_msat);
+
+        let reserved_at = match self
+            .reserve_federation_volume(federation_id, amount, PaymentDirection::Inbound)
+            .await
+        {
+            Ok(reserved_at) => reserved_at,
+            Err(err) => {
+                warn!(target: LOG_GATEWAY, err = %err.fmt_compact(), "Rejecting incoming lightning payment");
+                Self::cancel_lightning_payment(htlc_request, lightning_context).await;
+                return Ok(());
+            }
+        };
+
         if let Err(err) = client
             .get_first_module::<GatewayClientModuleV2>()
             .expect("Must have client module")
@@ -955,17 +1033,9 @@ async fn try_handle_lightning_payment_lnv2(
             .await
         {
             warn!(target: LOG_GATEWAY, err = %err.fmt_compact_anyhow(), "Error relaying incoming lightning payment");
-
-            let outcome = InterceptPaymentResponse {
-                action: PaymentAction::Cancel,
-                payment_hash: htlc_request.payment_hash,
-                incoming_chan_id: htlc_request.incoming_chan_id,
-                htlc_id: htlc_request.htlc_id,
-            };
-
-            if let Err(err) = lightning_context.lnrpc.complete_htlc(outcome).await {
-                warn!(target: LOG_GATEWAY, err = %err.fmt_compact(), "Error sending HTLC response to lightning node");
-            }
+            self.release_federation_volume(federation_id, amount, reserved_at)
+                .await;
+            Self::cancel_lightning_payment(htlc_request, lightning_context).await;
         }
 
         Ok(())
@@ -993,7 +1063,20 @@ async fn try_handle_lightning_payment_ln_legacy(
             return Err(PublicGatewayError::LNv1(LNv1Error::IncomingPayment("Incoming payment has a last hop short channel id that does not map to a known federation".to_string())));
         };
 
-        client
+        let federation_id = client.value().federation_id();
+        let amount = Amount::from_msats(htlc_request.amount_msat);
+
+        // A rejected payment is forwarded to the short channel id of the federation,
+        // which does not exist on the lightning node, so it is failed back to the
+        // previous hop.
+        let reserved_at = self
+            .reserve_federation_volume(federation_id, amount, PaymentDirection::Inbound)
+            .await
+            .inspect_err(|err| {
+                warn!(target: LOG_GATEWAY, err = %err.fmt_compact(), "Rejecting incoming lightning payment");
+            })?;
+
+        let result = client
             .borrow()
             .with(|client| async {
                 let htlc = htlc_request.clone().try_into();
@@ -1009,9 +1092,17 @@ async fn try_handle_lightning_payment_ln_legacy(
                                 })?;
                         match lnv1.gateway_handle_intercepted_htlc(htlc).await {
                             Ok(_) => Ok(()),
-                            Err(e) => Err(PublicGatewayError::LNv1(LNv1Error::IncomingPayment(
-                                format!("Error intercepting lightning payment {e:?}"),
-                            ))),
+                            Err(e) => {
+                                if let Some(IncomingSmError::FetchContractError { .. }) =
+                                    e.downcast_ref::<IncomingSmError>()
+                                {
+                                    self.record_payment_failure(federation_id).await;
+                                }
+
+                                Err(PublicGatewayError::LNv1(LNv1Error::IncomingPayment(
+                                    format!("Error intercepting lightning payment {e:?}"),
+                                )))
+                            }
                         }
                     }
                     _ => Err(PublicGatewayError::LNv1(LNv1Error::IncomingPayment(
@@ -1019,7 +1110,14 @@ async fn try_handle_lightning_payment_ln_legacy(
                     ))),
                 }
             })
-            .await
+            .await;
+
+        if result.is_err() {
+            self.release_federation_volume(federation_id, amount, reserved_at)
+                .await;
+        }
+
+        result
     }
 
     /// Forwards a lightning payment to the next hop like a normal lightning
@@ -1041,6 +1139,150 @@ async fn forward_lightning_payment(
         }
     }
 
+    /// Fails an incoming lightning payment back to the previous hop.
+    async fn cancel_lightning_payment(
+        htlc_request: &InterceptPaymentRequest,
+        lightning_context: &LightningContext,
+    ) {
+        let outcome = InterceptPaymentResponse {
+            action: PaymentAction::Cancel,
+            payment_hash: htlc_request.payment_hash,
+            incoming_chan_id: htlc_request.incoming_chan_id,
+            htlc_id: htlc_request.htlc_id,
+        };
+
+        if let Err(err) = lightning_context.lnrpc.complete_htlc(outcome).await {
+            warn!(target: LOG_GATEWAY, err = %err.fmt_compact(), "Error sending HTLC response to lightning node");
+        }
+    }
+
+    /// Checks that the circuit breaker of the federation is closed and that
+    /// routing a payment of `amount` stays within the limits the operator has
+    /// set for the federation. Outbound payments are paid to the gateway in
+    /// ecash and therefore count towards its balance limit.
+    async fn check_federation_limits(
+        &self,
+        federation_id: FederationId,
+        amount: Amount,
+        direction: PaymentDirection,
+    ) -> Result<()> {
+        if let CircuitBreakerState::Open { reason, .. } = self
+            .federation_manager
+            .read()
+            .await
+            .circuit_breaker_state(federation_id)
+        {
+            return Err(PublicGatewayError::CircuitBreakerOpen(reason));
+        }
+
+        let mut dbtx = self.gateway_db.begin_transaction_nc().await;
+        let limits = dbtx.load_federation_limits(federation_id).await;
+        let daily_volume = dbtx
+            .load_federation_daily_volume(federation_id, fedimint_core::time::now())
+            .await;
+
+        check_volume_limits(&limits, amount, daily_volume)?;
+
+        if let Some(max_balance) = limits.max_balance
+            && direction == PaymentDirection::Outbound
+        {
+            let balance = self
+                .select_client(federation_id)
+                .await?
+                .value()
+                .get_balance_for_btc()
+                .await?;
+
+            if max_balance < balance + amount {
+                return Err(PublicGatewayError::FederationLimitExceeded(format!(
+                    "Payment of {amount} would raise the balance of {balance} above the maximum balance of {max_balance}"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the limits of the federation like
+    /// [`Self::check_federation_limits`] and adds `amount` to its daily volume.
+    /// The volume limit is checked again in the same database transaction that
+    /// records the volume, such that concurrent payments can't exceed it
+    /// together. Returns the time the volume was recorded at, which is needed
+    /// to release it again if the payment fails.
+    async fn reserve_federation_volume(
+        &self,
+        federation_id: FederationId,
+        amount: Amount,
+        direction: PaymentDirection,
+    ) -> Result<SystemTime> {
+        self.check_federation_limits(federation_id, amount, direction)
+            .await?;
+
+        let now = fedimint_core::time::now();
+
+        self.gateway_db
+            .autocommit(
+                |dbtx, _| {
+                    Box::pin(async move {
+                        let limits = dbtx.load_federation_limits(federation_id).await;
+                        let daily_volume =
+                            dbtx.load_federation_daily_volume(federation_id, now).await;
+
+                        check_volume_limits(&limits, amount, daily_volume)?;
+
+                        dbtx.record_federation_volume(federation_id, amount, now)
+                            .await;
+
+                        Ok::<(), PublicGatewayError>(())
+                    })
+                },
+                None,
+            )
+            .await
+            .map_err(|e| match e {
+                AutocommitError::ClosureError { error, .. } => error,
+                AutocommitError::CommitFailed { last_error, .. } => {
+                    PublicGatewayError::Unexpected(last_error.into())
+                }
+            })?;
+
+        Ok(now)
+    }
+
+    /// Removes the volume reserved for a payment that failed from the daily
+    /// volume of the federation.
+    async fn release_federation_volume(
+        &self,
+        federation_id: FederationId,
+        amount: Amount,
+        reserved_at: SystemTime,
+    ) {
+        let mut dbtx = self.gateway_db.begin_transaction().await;
+        dbtx.release_federation_volume(federation_id, amount, reserved_at)
+            .await;
+
+        if let Err(err) = dbtx.commit_tx_result().await {
+            warn!(
+                target: LOG_GATEWAY,
+                federation_id = %federation_id,
+                err = %err.fmt_compact(),
+                "Failed to release the volume of a failed payment"
+            );
+        }
+    }
+
+    /// Records a payment that failed on the side of the federation, which
+    /// counts towards opening its circuit breaker. Payments failing on the
+    /// lightning side or due to invalid requests are not recorded, since they
+    /// say nothing about the health of the federation and could be triggered
+    /// by anyone.
+    async fn record_payment_failure(&self, federation_id: FederationId) {
+        self.federation_manager
+            .read()
+            .await
+            .record_payment_failure(federation_id);
+    }
+
     /// Helper function for atomically changing the Gateway's internal state.
     async fn set_gateway_state(&self, state: GatewayState) {
         let mut lock = self.state.write().await;
@@ -1108,6 +1350,28 @@ async fn handle_pay_invoice_msg(
         };
 
         debug!(target: LOG_GATEWAY, "Handling pay invoice message");
+        let federation_id = payload.federation_id;
+        let amount = payload.payment_data.amount().unwrap_or(Amount::ZERO);
+        let reserved_at = self
+            .reserve_federation_volume(federation_id, amount, PaymentDirection::Outbound)
+            .await?;
+
+        let result = self.pay_invoice_v1(payload).await;
+
+        if result.is_err() {
+            self.release_federation_volume(federation_id, amount, reserved_at)
+                .await;
+        }
+
+        result
+    }
+
+    /// Pays an outgoing LN invoice on behalf of a Fedimint client using the
+    /// LNv1 protocol.
+    async fn pay_invoice_v1(
+        &self,
+        payload: fedimint_ln_client::pay::PayInvoicePayload,
+    ) -> Result<Preimage> {
         let client = self.select_client(payload.federation_id).await?;
         let contract_id = payload.contract_id;
         let gateway_module = &client
@@ -1422,8 +1686,11 @@ async fn load_clients(&self) -> AdminResult<()> {
     /// has successfully connected to the Lightning node, so that it can
     /// include route hints in the registration.
     fn register_clients_timer(&self) {
-        // Only spawn background registration thread if gateway is LND
-        if matches!(self.lightning_mode, LightningMode::Lnd { .. }) {
+        // Only spawn background registration thread if gateway is LND or CLN
+        if matches!(
+            self.lightning_mode,
+            LightningMode::Lnd { .. } | LightningMode::Cln { .. }
+        ) {
             info!(target: LOG_GATEWAY, "Spawning register task...");
             let gateway = self.clone();
             let register_task_group = self.task_group.make_subgroup();
@@ -1531,7 +1798,10 @@ pub async fn get_lightning_context(
     /// Iterates through all of the federations the gateway is registered with
     /// and requests to remove the registration record.
     pub async fn unannounce_from_all_federations(&self) {
-        if matches!(self.lightning_mode, LightningMode::Lnd { .. }) {
+        if matches!(
+            self.lightning_mode,
+            LightningMode::Lnd { .. } | LightningMode::Cln { .. }
+        ) {
             for registration in self.registrations.values() {
                 self.federation_manager
                     .read()
@@ -1557,6 +1827,9 @@ async fn create_lightning_client(
                 lnd_macaroon,
                 None,
             )),
+            LightningMode::Cln { cln_extension_addr } => {
+                Box::new(GatewayClnClient::new(cln_extension_addr))
+            }
             LightningMode::Ldk {
                 lightning_port,
                 alias,
@@ -1718,6 +1991,7 @@ async fn handle_leave_federation(
             .await?;
 
         dbtx.remove_federation_config(payload.federation_id).await;
+        dbtx.remove_federation_limits(payload.federation_id).await;
         dbtx.commit_tx().await;
         Ok(federation_info)
     }
@@ -1801,11 +2075,17 @@ async fn handle_connect_federation(
             }),
             config: federation_config.clone(),
             last_backup_time: None,
+            limits: FederationLimits::default(),
+            daily_volume: Amount::ZERO,
+            circuit_breaker: CircuitBreakerState::Closed,
+            fee_policy: None,
         };
 
         Self::check_federation_network(&client, self.network).await?;
-        if matches!(self.lightning_mode, LightningMode::Lnd { .. })
-            && let Ok(lnv1) = client.get_first_module::<GatewayClientModule>()
+        if matches!(
+            self.lightning_mode,
+            LightningMode::Lnd { .. } | LightningMode::Cln { .. }
+        ) && let Ok(lnv1) = client.get_first_module::<GatewayClientModule>()
         {
             for registration in self.registrations.values() {
                 lnv1.try_register_with_federation(
@@ -1835,6 +2115,8 @@ async fn handle_connect_federation(
         dbtx.save_federation_config(&federation_config).await;
         dbtx.save_federation_backup_record(federation_id, None)
             .await;
+        dbtx.save_federation_limits(federation_id, FederationLimits::default())
+            .await;
         dbtx.commit_tx().await;
         debug!(
             target: LOG_GATEWAY,
@@ -1847,7 +2129,9 @@ async fn handle_connect_federation(
     }
 
     /// Handles a request to change the lightning or transaction fees for all
-    /// federations or a federation specified by the `FederationId`.
+    /// federations or a federation specified by the `FederationId`. The fee
+    /// policies of the federations are removed, since they would overwrite the
+    /// new fees.
     async fn handle_set_fees_msg(
         &self,
         SetFeesPayload {
@@ -1869,6 +2153,8 @@ async fn handle_set_fees_msg(
             dbtx.load_federation_configs().await
         };
 
+        let fee_policies = dbtx.load_fee_policies().await;
+
         let federation_manager = self.federation_manager.read().await;
 
         for (federation_id, config) in &mut fed_configs {
@@ -1896,37 +2182,24 @@ async fn handle_set_fees_msg(
                     .ok_or(FederationNotConnected {
                         federation_id_prefix: federation_id.to_prefix(),
                     })?;
-            let client_config = client.value().config().await;
-            let contains_lnv2 = client_config
-                .modules
-                .values()
-                .any(|m| fedimint_lnv2_common::LightningCommonInit::KIND == m.kind);
-
-            // Check if the lightning fee + transaction fee is higher than the send limit
-            let send_fees = lightning_fee + transaction_fee;
-            if contains_lnv2 && send_fees.gt(&PaymentFee::SEND_FEE_LIMIT) {
-                return Err(AdminGatewayError::GatewayConfigurationError(format!(
-                    "Total Send fees exceeded {}",
-                    PaymentFee::SEND_FEE_LIMIT
-                )));
-            }
-
-            // Check if the transaction fee is higher than the receive limit
-            if contains_lnv2 && transaction_fee.gt(&PaymentFee::RECEIVE_FEE_LIMIT) {
-                return Err(AdminGatewayError::GatewayConfigurationError(format!(
-                    "Transaction fees exceeded RECEIVE LIMIT {}",
-                    PaymentFee::RECEIVE_FEE_LIMIT
-                )));
-            }
+            check_fee_limits(client.value(), lightning_fee, transaction_fee).await?;
 
             config.lightning_fee = lightning_fee;
             config.transaction_fee = transaction_fee;
             dbtx.save_federation_config(config).await;
+
+            if fee_policies.contains_key(federation_id) {
+                dbtx.save_fee_policy(*federation_id, None).await;
+                info!(target: LOG_GATEWAY, %federation_id, "Removed fee policy, fees were set manually");
+            }
         }
 
         dbtx.commit_tx().await;
 
-        if matches!(self.lightning_mode, LightningMode::Lnd { .. }) {
+        if matches!(
+            self.lightning_mode,
+            LightningMode::Lnd { .. } | LightningMode::Cln { .. }
+        ) {
             let register_task_group = TaskGroup::new();
 
             self.register_federations(&fed_configs, &register_task_group)
@@ -1936,6 +2209,134 @@ async fn handle_set_fees_msg(
         Ok(())
     }
 
+    /// Handles a request to replace the liquidity limits of a federation.
+    async fn handle_set_federation_limits_msg(
+        &self,
+        SetFederationLimitsPayload {
+            federation_id,
+            max_balance,
+            max_payment,
+            max_daily_volume,
+        }: SetFederationLimitsPayload,
+    ) -> AdminResult<()> {
+        if !self
+            .federation_manager
+            .read()
+            .await
+            .has_federation(federation_id)
+        {
+            return Err(AdminGatewayError::FederationNotConnected(
+                FederationNotConnected {
+                    federation_id_prefix: federation_id.to_prefix(),
+                },
+            ));
+        }
+
+        let mut dbtx = self.gateway_db.begin_transaction().await;
+        dbtx.save_federation_limits(
+            federation_id,
+            FederationLimits {
+                max_balance,
+                max_payment,
+                max_daily_volume,
+            },
+        )
+        .await;
+        dbtx.commit_tx().await;
+
+        info!(target: LOG_GATEWAY, federation_id = %federation_id, "Updated federation limits");
+
+        Ok(())
+    }
+
+    /// Creates a new API token. Only the hash of the token is stored, so the
+    /// returned token cannot be retrieved again.
+    async fn handle_create_api_token_msg(
+        &self,
+        CreateApiTokenPayload {
+            name,
+            scopes,
+            expiry_secs,
+        }: CreateApiTokenPayload,
+    ) -> AdminResult<CreateApiTokenResponse> {
+        if name.trim().is_empty() {
+            return Err(AdminGatewayError::Unexpected(anyhow!(
+                "API token name must not be empty"
+            )));
+        }
+
+        if scopes.is_empty() {
+            return Err(AdminGatewayError::Unexpected(anyhow!(
+                "API token must have at least one scope"
+            )));
+        }
+
+        let token = hex::encode(OsRng.r#gen::<[u8; 32]>());
+        let created_at = fedimint_core::time::now();
+        let expires_at = expiry_secs.map(|secs| created_at + Duration::from_secs(secs));
+
+        let mut dbtx = self.gateway_db.begin_transaction().await;
+        if !dbtx
+            .save_new_api_token(
+                name.clone(),
+                &ApiToken {
+                    token_hash: sha256::Hash::hash(token.as_bytes()),
+                    scopes,
+                    created_at,
+                    expires_at,
+                },
+            )
+            .await
+        {
+            return Err(AdminGatewayError::Unexpected(anyhow!(
+                "API token {name} already exists"
+            )));
+        }
+        dbtx.commit_tx().await;
+
+        info!(target: LOG_GATEWAY, %name, "Created API token");
+
+        Ok(CreateApiTokenResponse {
+            name,
+            token,
+            expires_at,
+        })
+    }
+
+    async fn handle_list_api_tokens_msg(&self) -> AdminResult<Vec<ApiTokenInfo>> {
+        Ok(self
+            .gateway_db
+            .begin_transaction_nc()
+            .await
+            .load_api_tokens()
+            .await
+            .into_iter()
+            .map(|(name, token)| ApiTokenInfo {
+                name,
+                scopes: token.scopes,
+                created_at: token.created_at,
+                expires_at: token.expires_at,
+            })
+            .collect())
+    }
+
+    async fn handle_revoke_api_token_msg(
+        &self,
+        RevokeApiTokenPayload { name }: RevokeApiTokenPayload,
+    ) -> AdminResult<()> {
+        let mut dbtx = self.gateway_db.begin_transaction().await;
+        if !dbtx.remove_api_token(name.clone()).await {
+            return Err(AdminGatewayError::Unexpected(anyhow!(
+                "API token {name} does not exist"
+            )));
+        }
+        dbtx.commit_tx().await;
+
+        info!(target: LOG_GATEWAY, %name, "Revoked API token");
+
+        Ok(())
+    }
+
     /// Handles an authenticated request for the gateway's mnemonic. This also
     /// returns a vector of federations that are not using the mnemonic
     /// backup strategy.
@@ -2324,7 +2725,7 @@ async fn handle_withdraw_msg(&self, payload: WithdrawPayload) -> AdminResult<Wit
                 WithdrawState::Failed(e) => {
                     return Err(AdminGatewayError::WithdrawError { failure_reason: e });
                 }
-                WithdrawState::Created => {}
+                WithdrawState::Created | WithdrawState::AwaitingBatch => {}
             }
         }
 
@@ -2497,6 +2898,93 @@ async fn is_configured(&self) -> bool {
     }
 }
 
+/// Returns an error if the fees exceed the limits that LNv2 clients accept,
+/// which only apply to federations that support LNv2.
+async fn check_fee_limits(
+    client: &ClientHandleArc,
+    lightning_fee: PaymentFee,
+    transaction_fee: PaymentFee,
+) -> AdminResult<()> {
+    let client_config = client.config().await;
+    let contains_lnv2 = client_config
+        .modules
+        .values()
+        .any(|m| fedimint_lnv2_common::LightningCommonInit::KIND == m.kind);
+
+    // Check if the lightning fee + transaction fee is higher than the send limit
+    let send_fees = lightning_fee + transaction_fee;
+    if contains_lnv2 && send_fees.gt(&PaymentFee::SEND_FEE_LIMIT) {
+        return Err(AdminGatewayError::GatewayConfigurationError(format!(
+            "Total Send fees exceeded {}",
+            PaymentFee::SEND_FEE_LIMIT
+        )));
+    }
+
+    // Check if the transaction fee is higher than the receive limit
+    if contains_lnv2 && transaction_fee.gt(&PaymentFee::RECEIVE_FEE_LIMIT) {
+        return Err(AdminGatewayError::GatewayConfigurationError(format!(
+            "Transaction fees exceeded RECEIVE LIMIT {}",
+            PaymentFee::RECEIVE_FEE_LIMIT
+        )));
+    }
+
+    Ok(())
+}
+
+/// Returns an error if a payment of `amount` exceeds the maximum payment or,
+/// together with the `daily_volume` already routed, the maximum daily volume of
+/// the federation.
+fn check_volume_limits(
+    limits: &FederationLimits,
+    amount: Amount,
+    daily_volume: Amount,
+) -> Result<()> {
+    if let Some(max_payment) = limits.max_payment
+        && max_payment < amount
+    {
+        return Err(PublicGatewayError::FederationLimitExceeded(format!(
+            "Payment of {amount} exceeds the maximum payment of {max_payment}"
+        )));
+    }
+
+    if let Some(max_daily_volume) = limits.max_daily_volume
+        && max_daily_volume < daily_volume + amount
+    {
+        return Err(PublicGatewayError::FederationLimitExceeded(format!(
+            "Payment of {amount} exceeds the remaining daily volume of {}",
+            max_daily_volume.saturating_sub(daily_volume)
+        )));
+    }
+
+    Ok(())
+}
+
+impl Gateway {
+    /// Returns true if the bearer token is an API token that grants `scope` and
+    /// has not expired, or the gateway password, which grants all scopes.
+    pub(crate) async fn authenticate(&self, bearer_token: &str, scope: ApiScope) -> bool {
+        let token_hash = sha256::Hash::hash(bearer_token.as_bytes());
+        let now = fedimint_core::time::now();
+
+        let token_grants_scope = self
+            .gateway_db
+            .begin_transaction_nc()
+            .await
+            .load_api_tokens()
+            .await
+            .into_values()
+            .any(|token| {
+                token.token_hash == token_hash
+                    && !token.is_expired(now)
+                    && token.scopes.iter().any(|granted| granted.grants(scope))
+            });
+
+        token_grants_scope
+            || bcrypt::verify(bearer_token, &self.bcrypt_password_hash.to_string())
+                .expect("Bcrypt hash is valid since we just stringified it")
+    }
+}
+
 // LNv2 Gateway implementation
 impl Gateway {
     /// Retrieves the `PublicKey` of the Gateway module for a given federation
@@ -2556,17 +3044,37 @@ pub async fn routing_info_v2(
 
     /// Instructs this gateway to pay a Lightning network invoice via the LNv2
     /// protocol.
-    async fn send_payment_v2(
+    pub async fn send_payment_v2(
         &self,
         payload: SendPaymentPayload,
     ) -> Result<std::result::Result<[u8; 32], Signature>> {
-        self.select_client(payload.federation_id)
-            .await?
+        let federation_id = payload.federation_id;
+        let amount = payload.contract.amount;
+        let client = self.select_client(federation_id).await?;
+        let reserved_at = self
+            .reserve_federation_volume(federation_id, amount, PaymentDirection::Outbound)
+            .await?;
+
+        let result = client
             .value()
             .get_first_module::<GatewayClientModuleV2>()
             .expect("Must have client module")
             .send_payment(payload)
-            .await
+            .await;
+
+        if let Err(err) = &result
+            && err.downcast_ref::<FederationUnreachable>().is_some()
+        {
+            self.record_payment_failure(federation_id).await;
+        }
+
+        // The payment was cancelled if the gateway returns a forfeit signature
+        if !matches!(result, Ok(Ok(_))) {
+            self.release_federation_volume(federation_id, amount, reserved_at)
+                .await;
+        }
+
+        result
             .map_err(LNv2Error::OutgoingPayment)
             .map_err(PublicGatewayError::LNv2)
     }
@@ -2618,6 +3126,13 @@ async fn create_bolt11_invoice_v2(
             )));
         }
 
+        self.check_federation_limits(
+            payload.federation_id,
+            payload.amount,
+            PaymentDirection::Inbound,
+        )
+        .await?;
+
         let payment_hash = match payload.contract.commitment.payment_image {
             PaymentImage::Hash(payment_hash) => payment_hash,
             PaymentImage::Point(..) => {
@@ -2661,6 +3176,78 @@ async fn create_bolt11_invoice_v2(
         Ok(invoice)
     }
 
+    /// For the LNv2 protocol, this will create an amountless invoice by
+    /// fetching it from the connected Lightning node, then save the contract
+    /// template so that the incoming contract can be created once the payment
+    /// arrives and its amount is known.
+    async fn create_amountless_bolt11_invoice_v2(
+        &self,
+        payload: CreateAmountlessBolt11InvoicePayload,
+    ) -> Result<Bolt11Invoice> {
+        let payment_info = self.routing_info_v2(&payload.federation_id).await?.ok_or(
+            LNv2Error::IncomingPayment(format!(
+                "Federation {} does not exist",
+                payload.federation_id
+            )),
+        )?;
+
+        if payload.contract.refund_pk != payment_info.module_public_key {
+            return Err(PublicGatewayError::LNv2(LNv2Error::IncomingPayment(
+                "The incoming contract is keyed to another gateway".to_string(),
+            )));
+        }
+
+        if payload.contract.expiration <= duration_since_epoch().as_secs() {
+            return Err(PublicGatewayError::LNv2(LNv2Error::IncomingPayment(
+                "The contract has already expired".to_string(),
+            )));
+        }
+
+        // The amount is only known once the payment arrives, at which point the
+        // limits are checked again
+        self.check_federation_limits(
+            payload.federation_id,
+            Amount::ZERO,
+            PaymentDirection::Inbound,
+        )
+        .await?;
+
+        let PaymentImage::Hash(payment_hash) = payload.contract.payment_image() else {
+            return Err(PublicGatewayError::LNv2(LNv2Error::IncomingPayment(
+                "PaymentImage is not a payment hash".to_string(),
+            )));
+        };
+
+        let invoice = self
+            .create_invoice_via_lnrpc_v2(
+                payment_hash,
+                Amount::ZERO,
+                payload.description.clone(),
+                payload.expiry_secs,
+            )
+            .await?;
+
+        let mut dbtx = self.gateway_db.begin_transaction().await;
+
+        if dbtx
+            .save_registered_amountless_incoming_contract(payload.federation_id, payload.contract)
+            .await
+            .is_some()
+        {
+            return Err(PublicGatewayError::LNv2(LNv2Error::IncomingPayment(
+                "PaymentHash is already registered".to_string(),
+            )));
+        }
+
+        dbtx.commit_tx_result().await.map_err(|_| {
+            PublicGatewayError::LNv2(LNv2Error::IncomingPayment(
+                "Payment hash is already registered".to_string(),
+            ))
+        })?;
+
+        Ok(invoice)
+    }
+
     /// Retrieves a BOLT11 invoice from the connected Lightning node with a
     /// specific `payment_hash`.
     pub async fn create_invoice_via_lnrpc_v2(
@@ -2754,20 +3341,34 @@ pub async fn verify_bolt11_preimage_v2(
     /// Retrieves the persisted `CreateInvoicePayload` from the database
     /// specified by the `payment_hash` and the `ClientHandleArc` specified
     /// by the payload's `federation_id`.
+    ///
+    /// If the payment hash belongs to an amountless invoice the incoming
+    /// contract is created from its template for the amount of the payment.
     pub async fn get_registered_incoming_contract_and_client_v2(
         &self,
         payment_image: PaymentImage,
         amount_msats: u64,
     ) -> Result<(IncomingContract, ClientHandleArc)> {
-        let registered_incoming_contract = self
-            .gateway_db
-            .begin_transaction_nc()
-            .await
-            .load_registered_incoming_contract(payment_image)
+        let mut dbtx = self.gateway_db.begin_transaction_nc().await;
+
+        let Some(registered_incoming_contract) = dbtx
+            .load_registered_incoming_contract(payment_image.clone())
             .await
-            .ok_or(PublicGatewayError::LNv2(LNv2Error::IncomingPayment(
-                "No corresponding decryption contract available".to_string(),
-            )))?;
+        else {
+            let registered_amountless_contract = dbtx
+                .load_registered_amountless_incoming_contract(payment_image)
+                .await
+                .ok_or(PublicGatewayError::LNv2(LNv2Error::IncomingPayment(
+                    "No corresponding decryption contract available".to_string(),
+                )))?;
+
+            return self
+                .create_amountless_incoming_contract_and_client_v2(
+                    registered_amountless_contract,
+                    amount_msats,
+                )
+                .await;
+        };
 
         if registered_incoming_contract.incoming_amount_msats != amount_msats {
             return Err(PublicGatewayError::LNv2(LNv2Error::IncomingPayment(
@@ -2783,6 +3384,41 @@ pub async fn get_registered_incoming_contract_and_client_v2(
 
         Ok((registered_incoming_contract.contract, client))
     }
+
+    /// Creates the incoming contract for a payment of `amount_msats` to an
+    /// amountless invoice, deducting the gateway's receive fee.
+    async fn create_amountless_incoming_contract_and_client_v2(
+        &self,
+        registered_contract: RegisteredAmountlessIncomingContract,
+        amount_msats: u64,
+    ) -> Result<(IncomingContract, ClientHandleArc)> {
+        let receive_fee = self
+            .routing_info_v2(&registered_contract.federation_id)
+            .await?
+            .ok_or(PublicGatewayError::LNv2(LNv2Error::IncomingPayment(
+                format!(
+                    "Federation {} does not exist",
+                    registered_contract.federation_id
+                ),
+            )))?
+            .receive_fee;
+
+        let client = self
+            .select_client(registered_contract.federation_id)
+            .await?
+            .into_value();
+
+        let contract = client
+            .get_first_module::<GatewayClientModuleV2>()
+            .expect("Must have client module")
+            .create_amountless_incoming_contract(
+                &registered_contract.contract,
+                receive_fee.subtract_from(amount_msats),
+            )
+            .map_err(|e| PublicGatewayError::LNv2(LNv2Error::IncomingPayment(e.to_string())))?;
+
+        Ok((contract, client))
+    }
 }
 
 #[async_trait]
@@ -2814,15 +3450,14 @@ async fn complete_htlc(&self, htlc_response: InterceptPaymentResponse) {
     async fn is_direct_swap(
         &self,
         invoice: &Bolt11Invoice,
+        amount: Amount,
     ) -> anyhow::Result<Option<(IncomingContract, ClientHandleArc)>> {
         let lightning_context = self.get_lightning_context().await?;
         if lightning_context.lightning_public_key == invoice.get_payee_pub_key() {
             let (contract, client) = self
                 .get_registered_incoming_contract_and_client_v2(
                     PaymentImage::Hash(*invoice.payment_hash()),
-                    invoice
-                        .amount_milli_satoshis()
-                        .expect("The amount invoice has been previously checked"),
+                    amount.msats,
                 )
                 .await?;
             Ok(Some((contract, client)))
@@ -2833,16 +3468,28 @@ async fn is_direct_swap(
 
     async fn pay(
         &self,
-        invoice: Bolt11Invoice,
+        invoice: LightningInvoice,
         max_delay: u64,
         max_fee: Amount,
     ) -> std::result::Result<[u8; 32], LightningRpcError> {
         let lightning_context = self.get_lightning_context().await?;
-        lightning_context
-            .lnrpc
-            .pay(invoice, max_delay, max_fee)
-            .await
-            .map(|response| response.preimage.0)
+
+        let response = match invoice {
+            LightningInvoice::Bolt11(invoice) => {
+                lightning_context
+                    .lnrpc
+                    .pay(invoice, max_delay, max_fee)
+                    .await?
+            }
+            LightningInvoice::Bolt11Amountless(invoice, amount) => {
+                lightning_context
+                    .lnrpc
+                    .pay_amountless(invoice, amount, max_delay, max_fee)
+                    .await?
+            }
+        };
+
+        Ok(response.preimage.0)
     }
 
     async fn min_contract_amount(
@@ -2,40 +2,49 @@
 use std::sync::Arc;
 
 use anyhow::anyhow;
-use axum::extract::{Path, Query, Request};
+use axum::extract::{Path, Query, Request, State};
 use axum::http::{StatusCode, header};
 use axum::middleware::{self, Next};
 use axum::response::IntoResponse;
-use axum::routing::{get, post};
+use axum::routing::{MethodRouter, get, post};
 use axum::{Extension, Json, Router};
 use bitcoin::hashes::sha256;
 use fedimint_core::config::FederationId;
+use fedimint_core::module::IrohGatewayRequest;
 use fedimint_core::task::TaskGroup;
 use fedimint_core::util::FmtCompact;
 use fedimint_gateway_common::{
-    ADDRESS_ENDPOINT, ADDRESS_RECHECK_ENDPOINT, BACKUP_ENDPOINT, BackupPayload,
+    ADDRESS_ENDPOINT, ADDRESS_RECHECK_ENDPOINT, ApiScope, BACKUP_ENDPOINT, BackupPayload,
     CLOSE_CHANNELS_WITH_PEER_ENDPOINT, CONFIGURATION_ENDPOINT, CONNECT_FED_ENDPOINT,
-    CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT, CREATE_BOLT12_OFFER_FOR_OPERATOR_ENDPOINT,
-    CloseChannelsWithPeerRequest, ConfigPayload, ConnectFedPayload,
-    CreateInvoiceForOperatorPayload, CreateOfferPayload, DepositAddressPayload,
-    DepositAddressRecheckPayload, GATEWAY_INFO_ENDPOINT, GET_BALANCES_ENDPOINT,
-    GET_INVOICE_ENDPOINT, GET_LN_ONCHAIN_ADDRESS_ENDPOINT, GetInvoiceRequest, LEAVE_FED_ENDPOINT,
-    LIST_CHANNELS_ENDPOINT, LIST_TRANSACTIONS_ENDPOINT, LeaveFedPayload, ListTransactionsPayload,
+    CREATE_API_TOKEN_ENDPOINT, CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT,
+    CREATE_BOLT12_OFFER_FOR_OPERATOR_ENDPOINT, CloseChannelsWithPeerRequest, ConfigPayload,
+    ConnectFedPayload, CreateApiTokenPayload, CreateInvoiceForOperatorPayload, CreateOfferPayload,
+    DepositAddressPayload, DepositAddressRecheckPayload, GATEWAY_INFO_ENDPOINT,
+    GET_BALANCES_ENDPOINT, GET_INVOICE_ENDPOINT, GET_LN_ONCHAIN_ADDRESS_ENDPOINT,
+    GetInvoiceRequest, LEAVE_FED_ENDPOINT, LIST_API_TOKENS_ENDPOINT, LIST_CHANNELS_ENDPOINT,
+    LIST_TRANSACTIONS_ENDPOINT, LIST_WEBHOOKS_ENDPOINT, LeaveFedPayload, ListTransactionsPayload,
     MNEMONIC_ENDPOINT, OPEN_CHANNEL_ENDPOINT, OpenChannelRequest,
     PAY_INVOICE_FOR_OPERATOR_ENDPOINT, PAY_OFFER_FOR_OPERATOR_ENDPOINT, PAYMENT_LOG_ENDPOINT,
     PAYMENT_SUMMARY_ENDPOINT, PayInvoiceForOperatorPayload, PayOfferPayload, PaymentLogPayload,
-    PaymentSummaryPayload, RECEIVE_ECASH_ENDPOINT, ReceiveEcashPayload, SEND_ONCHAIN_ENDPOINT,
-    SET_FEES_ENDPOINT, SPEND_ECASH_ENDPOINT, STOP_ENDPOINT, SendOnchainRequest, SetFeesPayload,
-    SetMnemonicPayload, SpendEcashPayload, V1_API_ENDPOINT, WITHDRAW_ENDPOINT, WithdrawPayload,
+    PaymentSummaryPayload, RECEIVE_ECASH_ENDPOINT, REGISTER_WEBHOOK_ENDPOINT,
+    REMOVE_WEBHOOK_ENDPOINT, REVOKE_API_TOKEN_ENDPOINT, ReceiveEcashPayload,
+    RegisterWebhookPayload, RemoveWebhookPayload, RevokeApiTokenPayload, SEND_ONCHAIN_ENDPOINT,
+    SET_FEDERATION_LIMITS_ENDPOINT, SET_FEE_POLICY_ENDPOINT, SET_FEES_ENDPOINT,
+    SPEND_ECASH_ENDPOINT, STOP_ENDPOINT, SendOnchainRequest, SetFederationLimitsPayload,
+    SetFeePolicyPayload, SetFeesPayload, SetMnemonicPayload, SpendEcashPayload, V1_API_ENDPOINT,
+    WITHDRAW_ENDPOINT, WithdrawPayload,
 };
 use fedimint_gateway_ui::IAdminGateway;
 use fedimint_ln_common::gateway_endpoint_constants::{
     GET_GATEWAY_ID_ENDPOINT, PAY_INVOICE_ENDPOINT,
 };
 use fedimint_lnv2_common::endpoint_constants::{
-    CREATE_BOLT11_INVOICE_ENDPOINT, ROUTING_INFO_ENDPOINT, SEND_PAYMENT_ENDPOINT,
+    CREATE_AMOUNTLESS_BOLT11_INVOICE_ENDPOINT, CREATE_BOLT11_INVOICE_ENDPOINT,
+    ROUTING_INFO_ENDPOINT, SEND_PAYMENT_ENDPOINT,
+};
+use fedimint_lnv2_common::gateway_api::{
+    CreateAmountlessBolt11InvoicePayload, CreateBolt11InvoicePayload, SendPaymentPayload,
 };
-use fedimint_lnv2_common::gateway_api::{CreateBolt11InvoicePayload, SendPaymentPayload};
 use fedimint_logging::LOG_GATEWAY;
 use hex::ToHex;
 use serde::de::DeserializeOwned;
@@ -45,7 +54,7 @@
 use tracing::{info, instrument, warn};
 
 use crate::error::{GatewayError, LnurlError};
-use crate::iroh_server::{Handlers, start_iroh_endpoint};
+use crate::iroh_server::{Handlers, handle_request, start_iroh_endpoint};
 use crate::{Gateway, GatewayState};
 
 /// Creates the webserver's routes and spawns the webserver in a separate task.
@@ -96,6 +105,23 @@ pub async fn run_webserver(
     Ok(())
 }
 
+/// Handles `request` like the Iroh endpoint does and returns the status code of
+/// the response. Useful for tests, not meant for external use
+#[doc(hidden)]
+pub async fn handle_iroh_request(
+    gateway: Arc<Gateway>,
+    request: &IrohGatewayRequest,
+) -> anyhow::Result<StatusCode> {
+    let task_group = gateway.task_group.clone();
+    let mut handlers = Handlers::new();
+    // Registering the routes also registers their Iroh handlers
+    let _ = routes(gateway.clone(), task_group.clone(), &mut handlers);
+
+    let (status, _) = handle_request(request, gateway, Arc::new(handlers), task_group).await?;
+
+    Ok(status)
+}
+
 /// Extracts the Bearer token from the Authorization header of the request.
 fn extract_bearer_token(request: &Request) -> Result<String, StatusCode> {
     let headers = request.headers();
@@ -139,17 +165,16 @@ async fn not_configured_middleware(
 }
 
 /// Middleware to authenticate an incoming request. Routes that are
-/// authenticated with this middleware always require a Bearer token to be
-/// supplied in the Authorization header.
+/// authenticated with this middleware always require a Bearer token that grants
+/// the scope of the route to be supplied in the Authorization header.
 async fn auth_middleware(
+    State(scope): State<ApiScope>,
     Extension(gateway): Extension<Arc<Gateway>>,
     request: Request,
     next: Next,
 ) -> Result<impl IntoResponse, StatusCode> {
     let token = extract_bearer_token(&request)?;
-    if bcrypt::verify(token, &gateway.bcrypt_password_hash.to_string())
-        .expect("Bcrypt hash is valid since we just stringified it")
-    {
+    if gateway.authenticate(&token, scope).await {
         return Ok(next.run(request).await);
     }
 
@@ -162,15 +187,15 @@ fn register_get_handler<F, Fut>(
     handlers: &mut Handlers,
     route: &str,
     func: F,
-    is_authenticated: bool,
+    scope: Option<ApiScope>,
     router: Router,
 ) -> Router
 where
     F: Fn(Extension<Arc<Gateway>>) -> Fut + Clone + Send + Sync + 'static,
     Fut: Future<Output = Result<Json<serde_json::Value>, GatewayError>> + Send + 'static,
 {
-    handlers.add_handler(route, func.clone(), is_authenticated);
-    router.route(route, get(func))
+    handlers.add_handler(route, func.clone(), scope);
+    router.route(route, with_scope(get(func), scope))
 }
 
 /// Registers a POST API handler for both the HTTP server and the Iroh
@@ -179,7 +204,7 @@ fn register_post_handler<P, F, Fut>(
     handlers: &mut Handlers,
     route: &str,
     func: F,
-    is_authenticated: bool,
+    scope: Option<ApiScope>,
     router: Router,
 ) -> Router
 where
@@ -187,19 +212,30 @@ fn register_post_handler<P, F, Fut>(
     F: Fn(Extension<Arc<Gateway>>, Json<P>) -> Fut + Clone + Send + Sync + 'static,
     Fut: Future<Output = Result<Json<serde_json::Value>, GatewayError>> + Send + 'static,
 {
-    handlers.add_handler_with_payload(route, func.clone(), is_authenticated);
-    router.route(route, post(func))
+    handlers.add_handler_with_payload(route, func.clone(), scope);
+    router.route(route, with_scope(post(func), scope))
+}
+
+/// Requires requests to the route to be authenticated with a token that grants
+/// `scope`, the route is public if no scope is given.
+fn with_scope(method_router: MethodRouter, scope: Option<ApiScope>) -> MethodRouter {
+    match scope {
+        Some(scope) => {
+            method_router.route_layer(middleware::from_fn_with_state(scope, auth_middleware))
+        }
+        None => method_router,
+    }
 }
 
 /// Public routes that are used in the LNv1 protocol
 fn lnv1_routes(handlers: &mut Handlers) -> Router {
     let router = Router::new();
-    let router = register_post_handler(handlers, PAY_INVOICE_ENDPOINT, pay_invoice, false, router);
+    let router = register_post_handler(handlers, PAY_INVOICE_ENDPOINT, pay_invoice, None, router);
     register_get_handler(
         handlers,
         GET_GATEWAY_ID_ENDPOINT,
         get_gateway_id,
-        false,
+        None,
         router,
     )
 }
@@ -211,21 +247,28 @@ fn lnv2_routes(handlers: &mut Handlers) -> Router {
         handlers,
         ROUTING_INFO_ENDPOINT,
         routing_info_v2,
-        false,
+        None,
         router,
     );
     let router = register_post_handler(
         handlers,
         SEND_PAYMENT_ENDPOINT,
         pay_bolt11_invoice_v2,
-        false,
+        None,
         router,
     );
     let router = register_post_handler(
         handlers,
         CREATE_BOLT11_INVOICE_ENDPOINT,
         create_bolt11_invoice_v2,
-        false,
+        None,
+        router,
+    );
+    let router = register_post_handler(
+        handlers,
+        CREATE_AMOUNTLESS_BOLT11_INVOICE_ENDPOINT,
+        create_amountless_bolt11_invoice_v2,
+        None,
         router,
     );
     // Verify endpoint does not have the same signature, it is handled separately
@@ -243,200 +286,261 @@ fn routes(gateway: Arc<Gateway>, task_group: TaskGroup, handlers: &mut Handlers)
         handlers,
         RECEIVE_ECASH_ENDPOINT,
         receive_ecash,
-        false,
+        None,
         Router::new(),
     );
     public_routes = public_routes.merge(lnv1_routes(handlers));
     public_routes = public_routes.merge(lnv2_routes(handlers));
 
-    // Authenticated routes used for gateway administration
-    let is_authenticated = true;
+    // Authenticated routes used for gateway administration, each route requires
+    // a bearer token that grants its scope
     let authenticated_routes = Router::new();
     let authenticated_routes = register_post_handler(
         handlers,
         ADDRESS_ENDPOINT,
         address,
-        is_authenticated,
+        Some(ApiScope::Invoice),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         WITHDRAW_ENDPOINT,
         withdraw,
-        is_authenticated,
+        Some(ApiScope::Onchain),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         CONNECT_FED_ENDPOINT,
         connect_fed,
-        is_authenticated,
+        Some(ApiScope::Admin),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         LEAVE_FED_ENDPOINT,
         leave_fed,
-        is_authenticated,
+        Some(ApiScope::Admin),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         BACKUP_ENDPOINT,
         backup,
-        is_authenticated,
+        Some(ApiScope::Admin),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT,
         create_invoice_for_operator,
-        is_authenticated,
+        Some(ApiScope::Invoice),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         CREATE_BOLT12_OFFER_FOR_OPERATOR_ENDPOINT,
         create_offer_for_operator,
-        is_authenticated,
+        Some(ApiScope::Invoice),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         PAY_INVOICE_FOR_OPERATOR_ENDPOINT,
         pay_invoice_operator,
-        is_authenticated,
+        Some(ApiScope::Payments),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         PAY_OFFER_FOR_OPERATOR_ENDPOINT,
         pay_offer_operator,
-        is_authenticated,
+        Some(ApiScope::Payments),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         GET_INVOICE_ENDPOINT,
         get_invoice,
-        is_authenticated,
+        Some(ApiScope::ReadOnly),
         authenticated_routes,
     );
     let authenticated_routes = register_get_handler(
         handlers,
         GET_LN_ONCHAIN_ADDRESS_ENDPOINT,
         get_ln_onchain_address,
-        is_authenticated,
+        Some(ApiScope::Onchain),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         OPEN_CHANNEL_ENDPOINT,
         open_channel,
-        is_authenticated,
+        Some(ApiScope::Onchain),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         CLOSE_CHANNELS_WITH_PEER_ENDPOINT,
         close_channels_with_peer,
-        is_authenticated,
+        Some(ApiScope::Onchain),
         authenticated_routes,
     );
     let authenticated_routes = register_get_handler(
         handlers,
         LIST_CHANNELS_ENDPOINT,
         list_channels,
-        is_authenticated,
+        Some(ApiScope::ReadOnly),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         LIST_TRANSACTIONS_ENDPOINT,
         list_transactions,
-        is_authenticated,
+        Some(ApiScope::ReadOnly),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         SEND_ONCHAIN_ENDPOINT,
         send_onchain,
-        is_authenticated,
+        Some(ApiScope::Onchain),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         ADDRESS_RECHECK_ENDPOINT,
         recheck_address,
-        is_authenticated,
+        Some(ApiScope::Invoice),
         authenticated_routes,
     );
     let authenticated_routes = register_get_handler(
         handlers,
         GET_BALANCES_ENDPOINT,
         get_balances,
-        is_authenticated,
+        Some(ApiScope::ReadOnly),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         SPEND_ECASH_ENDPOINT,
         spend_ecash,
-        is_authenticated,
+        Some(ApiScope::Payments),
         authenticated_routes,
     );
     let authenticated_routes = register_get_handler(
         handlers,
         MNEMONIC_ENDPOINT,
         mnemonic,
-        is_authenticated,
+        Some(ApiScope::Admin),
         authenticated_routes,
     );
     // Stop does not have the same function signature, it is handled separately
-    let authenticated_routes = authenticated_routes.route(STOP_ENDPOINT, get(stop));
+    let authenticated_routes = authenticated_routes.route(
+        STOP_ENDPOINT,
+        get(stop).route_layer(middleware::from_fn_with_state(
+            ApiScope::Admin,
+            auth_middleware,
+        )),
+    );
     let authenticated_routes = register_post_handler(
         handlers,
         PAYMENT_LOG_ENDPOINT,
         payment_log,
-        is_authenticated,
+        Some(ApiScope::ReadOnly),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         PAYMENT_SUMMARY_ENDPOINT,
         payment_summary,
-        is_authenticated,
+        Some(ApiScope::ReadOnly),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         SET_FEES_ENDPOINT,
         set_fees,
-        is_authenticated,
+        Some(ApiScope::Admin),
+        authenticated_routes,
+    );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        SET_FEE_POLICY_ENDPOINT,
+        set_fee_policy,
+        Some(ApiScope::Admin),
+        authenticated_routes,
+    );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        SET_FEDERATION_LIMITS_ENDPOINT,
+        set_federation_limits,
+        Some(ApiScope::Admin),
+        authenticated_routes,
+    );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        REGISTER_WEBHOOK_ENDPOINT,
+        register_webhook,
+        Some(ApiScope::Admin),
+        authenticated_routes,
+    );
+    let authenticated_routes = register_get_handler(
+        handlers,
+        LIST_WEBHOOKS_ENDPOINT,
+        list_webhooks,
+        Some(ApiScope::Admin),
+        authenticated_routes,
+    );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        REMOVE_WEBHOOK_ENDPOINT,
+        remove_webhook,
+        Some(ApiScope::Admin),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         CONFIGURATION_ENDPOINT,
         configuration,
-        is_authenticated,
+        Some(ApiScope::ReadOnly),
         authenticated_routes,
     );
     let authenticated_routes = register_get_handler(
         handlers,
         GATEWAY_INFO_ENDPOINT,
         info,
-        is_authenticated,
+        Some(ApiScope::ReadOnly),
         authenticated_routes,
     );
     let authenticated_routes = register_post_handler(
         handlers,
         MNEMONIC_ENDPOINT,
         set_mnemonic,
-        is_authenticated,
+        Some(ApiScope::Admin),
+        authenticated_routes,
+    );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        CREATE_API_TOKEN_ENDPOINT,
+        create_api_token,
+        Some(ApiScope::Admin),
+        authenticated_routes,
+    );
+    let authenticated_routes = register_get_handler(
+        handlers,
+        LIST_API_TOKENS_ENDPOINT,
+        list_api_tokens,
+        Some(ApiScope::Admin),
+        authenticated_routes,
+    );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        REVOKE_API_TOKEN_ENDPOINT,
+        revoke_api_token,
+        Some(ApiScope::Admin),
         authenticated_routes,
     );
-    let authenticated_routes = authenticated_routes.layer(middleware::from_fn(auth_middleware));
 
     Router::new()
         .merge(public_routes)
@@ -556,6 +660,76 @@ async fn set_fees(
     Ok(Json(json!(())))
 }
 
+#[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
+async fn set_fee_policy(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<SetFeePolicyPayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    gateway.handle_set_fee_policy_msg(payload).await?;
+    Ok(Json(json!(())))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
+async fn set_federation_limits(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<SetFederationLimitsPayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    gateway.handle_set_federation_limits_msg(payload).await?;
+    Ok(Json(json!(())))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err)]
+async fn create_api_token(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<CreateApiTokenPayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let token = gateway.handle_create_api_token_msg(payload).await?;
+    Ok(Json(json!(token)))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err)]
+async fn list_api_tokens(
+    Extension(gateway): Extension<Arc<Gateway>>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let tokens = gateway.handle_list_api_tokens_msg().await?;
+    Ok(Json(json!(tokens)))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err)]
+async fn revoke_api_token(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<RevokeApiTokenPayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    gateway.handle_revoke_api_token_msg(payload).await?;
+    Ok(Json(json!(())))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err)]
+async fn register_webhook(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<RegisterWebhookPayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let webhook = gateway.handle_register_webhook_msg(payload).await?;
+    Ok(Json(json!(webhook)))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err)]
+async fn list_webhooks(
+    Extension(gateway): Extension<Arc<Gateway>>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let webhooks = gateway.handle_list_webhooks_msg().await?;
+    Ok(Json(json!(webhooks)))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err)]
+async fn remove_webhook(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<RemoveWebhookPayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    gateway.handle_remove_webhook_msg(payload).await?;
+    Ok(Json(json!(())))
+}
+
 #[instrument(target = LOG_GATEWAY, skip_all, err)]
 async fn get_ln_onchain_address(
     Extension(gateway): Extension<Arc<Gateway>>,
@@ -650,6 +824,15 @@ async fn create_bolt11_invoice_v2(
     Ok(Json(json!(invoice)))
 }
 
+#[instrument(target = LOG_GATEWAY, skip_all, err)]
+async fn create_amountless_bolt11_invoice_v2(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<CreateAmountlessBolt11InvoicePayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let invoice = gateway.create_amountless_bolt11_invoice_v2(payload).await?;
+    Ok(Json(json!(invoice)))
+}
+
 pub(crate) async fn verify_bolt11_preimage_v2_get(
     Extension(gateway): Extension<Arc<Gateway>>,
     Path(payment_hash): Path<sha256::Hash>,
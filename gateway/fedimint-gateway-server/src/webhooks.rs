@@ -0,0 +1,394 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, ensure};
+use bitcoin::hashes::{Hash as _, HashEngine as _, Hmac, HmacEngine, sha256};
+use fedimint_client::ClientHandleArc;
+use fedimint_core::config::FederationId;
+use fedimint_core::task::sleep;
+use fedimint_core::time::duration_since_epoch;
+use fedimint_core::util::backoff_util::custom_backoff;
+use fedimint_core::util::{FmtCompactAnyhow as _, SafeUrl};
+use fedimint_eventlog::{DBTransactionEventLogExt as _, EventLogId, PersistedLogEntry};
+use fedimint_gateway_common::{
+    RegisterWebhookPayload, RemoveWebhookPayload, WEBHOOK_SIGNATURE_HEADER,
+    WEBHOOK_TIMESTAMP_HEADER, WebhookEvent, WebhookInfo,
+};
+use fedimint_gateway_server_db::{GatewayDbtxNcExt as _, Webhook};
+use fedimint_logging::LOG_GATEWAY;
+use tracing::{debug, info, warn};
+
+use crate::error::AdminGatewayError;
+use crate::{AdminResult, Gateway};
+
+/// Maximum number of events read from the event log of a federation at once
+const WEBHOOK_BATCH_SIZE: u64 = 100;
+
+/// How long a webhook task waits before checking the event logs again once
+/// all events have been delivered
+const WEBHOOK_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+const WEBHOOK_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+impl Gateway {
+    /// Registers a new webhook that receives all events the federation clients
+    /// log from now on.
+    pub async fn handle_register_webhook_msg(
+        &self,
+        RegisterWebhookPayload {
+            url,
+            secret,
+            event_kinds,
+        }: RegisterWebhookPayload,
+    ) -> AdminResult<WebhookInfo> {
+        validate_webhook_url(&url).map_err(AdminGatewayError::Unexpected)?;
+
+        if secret.is_empty() {
+            return Err(AdminGatewayError::Unexpected(anyhow!(
+                "Webhook secret must not be empty"
+            )));
+        }
+
+        let webhook = Webhook {
+            url,
+            secret,
+            event_kinds,
+        };
+
+        // Only events logged after the registration are delivered. Federations that
+        // are connected later have no cursor, so all of their events are delivered.
+        let clients = self.federation_manager.read().await.clients();
+        let mut dbtx = self.gateway_db.begin_transaction().await;
+        let webhook_id = dbtx.save_new_webhook(&webhook).await;
+        for (federation_id, client) in clients {
+            let next_event = client
+                .value()
+                .db()
+                .begin_transaction_nc()
+                .await
+                .get_next_event_log_id()
+                .await;
+            dbtx.save_webhook_cursor(webhook_id, federation_id, next_event)
+                .await;
+        }
+        dbtx.commit_tx_result()
+            .await
+            .map_err(|e| AdminGatewayError::Unexpected(e.into()))?;
+
+        info!(target: LOG_GATEWAY, %webhook_id, url = %webhook.url, "Registered webhook");
+
+        self.spawn_webhook_task(webhook_id);
+
+        Ok(WebhookInfo {
+            webhook_id,
+            url: webhook.url,
+            event_kinds: webhook.event_kinds,
+        })
+    }
+
+    /// Lists the registered webhooks without their secrets
+    pub async fn handle_list_webhooks_msg(&self) -> AdminResult<Vec<WebhookInfo>> {
+        Ok(self
+            .gateway_db
+            .begin_transaction_nc()
+            .await
+            .load_webhooks()
+            .await
+            .into_iter()
+            .map(|(webhook_id, webhook)| WebhookInfo {
+                webhook_id,
+                url: webhook.url,
+                event_kinds: webhook.event_kinds,
+            })
+            .collect())
+    }
+
+    /// Removes a webhook, its task stops delivering events once it notices
+    /// that the webhook is gone.
+    pub async fn handle_remove_webhook_msg(
+        &self,
+        RemoveWebhookPayload { webhook_id }: RemoveWebhookPayload,
+    ) -> AdminResult<()> {
+        let mut dbtx = self.gateway_db.begin_transaction().await;
+        if !dbtx.remove_webhook(webhook_id).await {
+            return Err(AdminGatewayError::Unexpected(anyhow!(
+                "Webhook {webhook_id} does not exist"
+            )));
+        }
+        dbtx.commit_tx_result()
+            .await
+            .map_err(|e| AdminGatewayError::Unexpected(e.into()))?;
+
+        info!(target: LOG_GATEWAY, %webhook_id, "Removed webhook");
+
+        Ok(())
+    }
+
+    /// Spawns the delivery tasks of all registered webhooks
+    pub(crate) async fn spawn_webhook_tasks(&self) {
+        let webhooks = self
+            .gateway_db
+            .begin_transaction_nc()
+            .await
+            .load_webhooks()
+            .await;
+
+        for webhook_id in webhooks.into_keys() {
+            self.spawn_webhook_task(webhook_id);
+        }
+    }
+
+    fn spawn_webhook_task(&self, webhook_id: u64) {
+        let gateway = self.clone();
+        self.task_group
+            .spawn_cancellable_silent(format!("webhook {webhook_id}"), async move {
+                gateway.run_webhook(webhook_id).await;
+            });
+    }
+
+    /// Delivers the events of all federation clients to the webhook in order
+    /// until the webhook is removed. The cursor of a federation is only
+    /// advanced after its events have been delivered, so no event is lost
+    /// when the gateway restarts.
+    async fn run_webhook(&self, webhook_id: u64) {
+        let http_client = reqwest::Client::new();
+
+        while let Some(webhook) = self
+            .gateway_db
+            .begin_transaction_nc()
+            .await
+            .load_webhook(webhook_id)
+            .await
+        {
+            let clients = self.federation_manager.read().await.clients();
+
+            let mut delivered_all = true;
+            for (federation_id, client) in clients {
+                match self
+                    .deliver_webhook_batch(
+                        &http_client,
+                        webhook_id,
+                        &webhook,
+                        federation_id,
+                        client.value(),
+                    )
+                    .await
+                {
+                    Ok(has_more) => delivered_all &= !has_more,
+                    Err(err) => {
+                        debug!(target: LOG_GATEWAY, %webhook_id, err = %err.fmt_compact_anyhow(), "Stopped delivering events");
+                        break;
+                    }
+                }
+            }
+
+            if delivered_all {
+                sleep(WEBHOOK_POLL_INTERVAL).await;
+            }
+        }
+
+        info!(target: LOG_GATEWAY, %webhook_id, "Webhook was removed, stopped delivering events");
+    }
+
+    /// Delivers the next batch of events of a federation to the webhook.
+    /// Returns true if there might be more events to deliver and an error if
+    /// the webhook was removed while delivering.
+    async fn deliver_webhook_batch(
+        &self,
+        http_client: &reqwest::Client,
+        webhook_id: u64,
+        webhook: &Webhook,
+        federation_id: FederationId,
+        client: &ClientHandleArc,
+    ) -> anyhow::Result<bool> {
+        let cursor = self
+            .gateway_db
+            .begin_transaction_nc()
+            .await
+            .load_webhook_cursor(webhook_id, federation_id)
+            .await
+            .unwrap_or(EventLogId::LOG_START);
+
+        let events = client.get_event_log(Some(cursor), WEBHOOK_BATCH_SIZE).await;
+
+        let Some(last_event) = events.last() else {
+            return Ok(false);
+        };
+        let next_cursor = last_event.id().saturating_add(1);
+
+        for event in &events {
+            if !webhook.event_kinds.is_empty()
+                && !webhook.event_kinds.contains(&event.as_raw().kind)
+            {
+                continue;
+            }
+
+            self.deliver_webhook_event(http_client, webhook_id, webhook, federation_id, event)
+                .await?;
+
+            self.save_webhook_cursor(webhook_id, federation_id, event.id().saturating_add(1))
+                .await?;
+        }
+
+        self.save_webhook_cursor(webhook_id, federation_id, next_cursor)
+            .await?;
+
+        Ok(events.len() as u64 == WEBHOOK_BATCH_SIZE)
+    }
+
+    /// Sends the event to the webhook, retrying with backoff until it is
+    /// accepted or the webhook is removed.
+    async fn deliver_webhook_event(
+        &self,
+        http_client: &reqwest::Client,
+        webhook_id: u64,
+        webhook: &Webhook,
+        federation_id: FederationId,
+        event: &PersistedLogEntry,
+    ) -> anyhow::Result<()> {
+        let body = serde_json::to_vec(&WebhookEvent {
+            webhook_id,
+            federation_id,
+            event: event.clone(),
+        })?;
+
+        let mut backoff =
+            custom_backoff(Duration::from_secs(1), Duration::from_secs(10 * 60), None);
+
+        loop {
+            ensure!(
+                self.gateway_db
+                    .begin_transaction_nc()
+                    .await
+                    .load_webhook(webhook_id)
+                    .await
+                    .is_some(),
+                "Webhook {webhook_id} was removed"
+            );
+
+            // Every attempt is signed with a fresh timestamp, such that receivers
+            // rejecting old timestamps still accept retries
+            let timestamp = duration_since_epoch().as_secs();
+            let signature = sign_webhook_body(&webhook.secret, timestamp, &body);
+
+            let result = http_client
+                .post(webhook.url.clone().to_unsafe())
+                .timeout(WEBHOOK_REQUEST_TIMEOUT)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(WEBHOOK_TIMESTAMP_HEADER, timestamp)
+                .header(WEBHOOK_SIGNATURE_HEADER, signature)
+                .body(body.clone())
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status);
+
+            match result {
+                Ok(_) => return Ok(()),
+                Err(err) => {
+                    warn!(target: LOG_GATEWAY, %webhook_id, event_id = %event.id(), %err, "Failed to deliver event to webhook, retrying");
+                }
+            }
+
+            sleep(backoff.next().unwrap_or(Duration::from_secs(10 * 60))).await;
+        }
+    }
+
+    async fn save_webhook_cursor(
+        &self,
+        webhook_id: u64,
+        federation_id: FederationId,
+        cursor: EventLogId,
+    ) -> anyhow::Result<()> {
+        let mut dbtx = self.gateway_db.begin_transaction().await;
+
+        // Do not recreate the cursor of a webhook that was removed meanwhile
+        ensure!(
+            dbtx.load_webhook(webhook_id).await.is_some(),
+            "Webhook {webhook_id} was removed"
+        );
+
+        dbtx.save_webhook_cursor(webhook_id, federation_id, cursor)
+            .await;
+        dbtx.commit_tx_result().await?;
+
+        Ok(())
+    }
+}
+
+/// Webhooks receive payment details, so they have to be served over HTTPS
+/// unless they run on the same machine as the gateway.
+fn validate_webhook_url(url: &SafeUrl) -> anyhow::Result<()> {
+    match url.scheme() {
+        "https" => Ok(()),
+        "http" if matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]")) => Ok(()),
+        scheme => Err(anyhow!(
+            "Webhook url must use https, got {scheme} for {url}"
+        )),
+    }
+}
+
+/// Returns the hex encoded HMAC-SHA256 of the timestamp, a `.` and the body,
+/// keyed with the secret of the webhook
+fn sign_webhook_body(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    let mut engine = HmacEngine::<sha256::Hash>::new(secret.as_bytes());
+    engine.input(timestamp.to_string().as_bytes());
+    engine.input(b".");
+    engine.input(body);
+    Hmac::from_engine(engine).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use fedimint_core::util::SafeUrl;
+
+    use super::{sign_webhook_body, validate_webhook_url};
+
+    #[test]
+    fn webhook_url_must_use_https_unless_local() {
+        for url in [
+            "https://example.com/webhook",
+            "https://127.0.0.1/webhook",
+            "http://localhost:8080/webhook",
+            "http://127.0.0.1:8080/webhook",
+            "http://[::1]:8080/webhook",
+        ] {
+            assert!(
+                validate_webhook_url(&SafeUrl::from_str(url).expect("Valid url")).is_ok(),
+                "{url} should be accepted"
+            );
+        }
+
+        for url in [
+            "http://example.com/webhook",
+            "http://192.168.1.1/webhook",
+            "http://localhost.example.com/webhook",
+            "ws://localhost/webhook",
+        ] {
+            assert!(
+                validate_webhook_url(&SafeUrl::from_str(url).expect("Valid url")).is_err(),
+                "{url} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn webhook_signature_covers_timestamp_and_body() {
+        let body = br#"{"a":1}"#;
+
+        // HMAC-SHA256 of `1700000000.{"a":1}` keyed with `secret`
+        assert_eq!(
+            sign_webhook_body("secret", 1_700_000_000, body),
+            "49f24e537407743fa4a0242bb63b94b9a47ee99cbbe071ccd8a22550ae411686"
+        );
+
+        assert_ne!(
+            sign_webhook_body("secret", 1_700_000_001, body),
+            sign_webhook_body("secret", 1_700_000_000, body)
+        );
+        assert_ne!(
+            sign_webhook_body("other secret", 1_700_000_000, body),
+            sign_webhook_body("secret", 1_700_000_000, body)
+        );
+    }
+}
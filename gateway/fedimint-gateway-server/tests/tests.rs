@@ -2,10 +2,13 @@
 //!
 //! This crate contains integration tests for the gateway API
 //! and business logic.
+use std::collections::BTreeSet;
 use std::sync::Arc;
 use std::time::Duration;
 
 use assert_matches::assert_matches;
+use axum::body::Bytes;
+use axum::http::{HeaderMap, StatusCode};
 use bitcoin::hashes::{Hash, sha256};
 use fedimint_client::ClientHandleArc;
 use fedimint_client::transaction::{
@@ -13,18 +16,23 @@
 };
 use fedimint_client_module::module::OutPointRange;
 use fedimint_core::config::FederationId;
-use fedimint_core::core::{IntoDynInstance, OperationId};
+use fedimint_core::core::{IntoDynInstance, ModuleKind, OperationId};
 use fedimint_core::encoding::Encodable;
-use fedimint_core::module::{AmountUnit, Amounts};
+use fedimint_core::module::{AmountUnit, Amounts, IrohGatewayRequest};
 use fedimint_core::task::sleep_in_test;
 use fedimint_core::time::now;
-use fedimint_core::util::{NextOrPending, backoff_util, retry};
-use fedimint_core::{Amount, OutPoint, msats, sats, secp256k1};
+use fedimint_core::util::{NextOrPending, SafeUrl, backoff_util, retry};
+use fedimint_core::{Amount, OutPoint, TransactionId, msats, sats, secp256k1};
 use fedimint_dummy_client::{DummyClientInit, DummyClientModule};
 use fedimint_dummy_server::DummyInit;
-use fedimint_eventlog::Event;
-use fedimint_gateway_common::{PaymentLogPayload, SetFeesPayload};
+use fedimint_eventlog::{Event, EventKind, EventPersistence};
+use fedimint_gateway_common::{
+    ApiScope, CreateApiTokenPayload, LIST_API_TOKENS_ENDPOINT, PaymentLogPayload,
+    RegisterWebhookPayload, RegisteredProtocol, RevokeApiTokenPayload, SetFederationLimitsPayload,
+    SetFeesPayload, WEBHOOK_SIGNATURE_HEADER, WEBHOOK_TIMESTAMP_HEADER, WebhookEvent,
+};
 use fedimint_gateway_server::Gateway;
+use fedimint_gateway_server::rpc_server::{handle_iroh_request, run_webserver};
 use fedimint_gateway_ui::IAdminGateway;
 use fedimint_gw_client::pay::{
     OutgoingContractError, OutgoingPaymentError, OutgoingPaymentErrorType,
@@ -49,19 +57,21 @@
 use fedimint_ln_common::contracts::{EncryptedPreimage, FundedContract, Preimage, PreimageKey};
 use fedimint_ln_common::{LightningGateway, LightningInput, LightningOutput, PrunedInvoice};
 use fedimint_ln_server::LightningInit;
+use fedimint_lnv2_common::LightningInvoice;
 use fedimint_lnv2_common::contracts::{IncomingContract, OutgoingContract, PaymentImage};
-use fedimint_lnv2_common::gateway_api::PaymentFee;
+use fedimint_lnv2_common::gateway_api::{PaymentFee, SendPaymentPayload};
 use fedimint_logging::LOG_TEST;
 use fedimint_testing::btc::BitcoinTest;
 use fedimint_testing::db::BYTE_33;
 use fedimint_testing::federation::FederationTest;
-use fedimint_testing::fixtures::Fixtures;
+use fedimint_testing::fixtures::{DEFAULT_GATEWAY_PASSWORD, Fixtures};
 use fedimint_testing::ln::FakeLightningTest;
 use fedimint_unknown_server::UnknownInit;
 use futures::Future;
 use itertools::Itertools;
 use lightning_invoice::{Bolt11Invoice, Bolt11InvoiceDescription, Description, RoutingFees};
 use secp256k1::{Keypair, PublicKey};
+use serde::{Deserialize, Serialize};
 use tpe::G1Affine;
 use tracing::info;
 
@@ -1133,6 +1143,128 @@ async fn lnv2_malleated_incoming_contract_is_rejected() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn lnv2_payment_exceeding_federation_limit_is_rejected() -> anyhow::Result<()> {
+    let fixtures = fixtures();
+    let fed = fixtures.new_fed_degraded().await;
+
+    let gateway = fixtures.new_gateway().await;
+
+    fed.connect_gateway(&gateway).await;
+
+    gateway
+        .handle_set_federation_limits_msg(SetFederationLimitsPayload {
+            federation_id: fed.id(),
+            max_balance: None,
+            max_payment: Some(sats(500)),
+            max_daily_volume: None,
+        })
+        .await?;
+
+    let invoice = FakeLightningTest::new().invoice(sats(1000), None)?;
+    let keypair = Keypair::new(secp256k1::SECP256K1, &mut rand::thread_rng());
+
+    let contract = OutgoingContract {
+        payment_image: PaymentImage::Hash(*invoice.payment_hash()),
+        amount: sats(1000),
+        expiration: u64::MAX,
+        claim_pk: keypair.public_key(),
+        refund_pk: keypair.public_key(),
+        ephemeral_pk: keypair.public_key(),
+    };
+
+    // The limits are checked before the contract is looked up in the federation,
+    // so the contract does not need to be funded
+    let error = gateway
+        .send_payment_v2(SendPaymentPayload {
+            federation_id: fed.id(),
+            outpoint: OutPoint {
+                txid: TransactionId::all_zeros(),
+                out_idx: 0,
+            },
+            auth: keypair.sign_schnorr(contract.forfeit_message()),
+            contract,
+            invoice: LightningInvoice::Bolt11(invoice),
+        })
+        .await
+        .expect_err("Payment exceeds the maximum payment of the federation");
+
+    assert!(
+        error.to_string().contains("exceeds the maximum payment"),
+        "Unexpected error: {error}"
+    );
+
+    Ok(())
+}
+
+/// Event logged by the test to check the delivery to webhooks
+#[derive(Debug, Serialize, Deserialize)]
+struct WebhookTestEvent {
+    n: u64,
+}
+
+impl Event for WebhookTestEvent {
+    const MODULE: Option<ModuleKind> = None;
+    const KIND: EventKind = EventKind::from_static("webhook-test");
+    const PERSISTENCE: EventPersistence = EventPersistence::Persistent;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn webhook_receives_signed_events_once() -> anyhow::Result<()> {
+    let fixtures = fixtures();
+    let fed = fixtures.new_fed_degraded().await;
+    let gateway = fixtures.new_gateway().await;
+    fed.connect_gateway(&gateway).await;
+
+    let client = gateway.select_client(fed.id()).await?.into_value();
+
+    // Receive the webhook requests on a local server
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    let router = axum::Router::new().route(
+        "/",
+        axum::routing::post(move |headers: HeaderMap, body: Bytes| async move {
+            sender.send((headers, body)).expect("Test is still running");
+        }),
+    );
+    tokio::spawn(async move { axum::serve(listener, router).await });
+
+    let webhook = gateway
+        .handle_register_webhook_msg(RegisterWebhookPayload {
+            url: SafeUrl::parse(&format!("http://127.0.0.1:{port}/"))?,
+            secret: "secret".to_string(),
+            event_kinds: vec![WebhookTestEvent::KIND],
+        })
+        .await?;
+
+    for n in 1..=2 {
+        client.log_event(None, WebhookTestEvent { n }).await;
+
+        let (headers, body) = tokio::time::timeout(Duration::from_secs(60), receiver.recv())
+            .await?
+            .expect("Webhook server is still running");
+
+        assert!(headers.contains_key(WEBHOOK_TIMESTAMP_HEADER));
+        assert!(headers.contains_key(WEBHOOK_SIGNATURE_HEADER));
+
+        let event: WebhookEvent = serde_json::from_slice(&body)?;
+        assert_eq!(event.webhook_id, webhook.webhook_id);
+        assert_eq!(event.federation_id, fed.id());
+
+        // The cursor is advanced after the delivery, so the first event is not
+        // delivered again before the second one
+        let event = event
+            .event
+            .as_raw()
+            .to_event::<WebhookTestEvent>()
+            .expect("Only test events are delivered");
+        assert_eq!(event.n, n);
+    }
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn gateway_read_payment_log() -> anyhow::Result<()> {
     let fixtures = fixtures();
@@ -1322,3 +1454,89 @@ async fn gateway_read_payment_log() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+async fn create_api_token(
+    gateway: &Gateway,
+    name: &str,
+    scope: ApiScope,
+    expiry_secs: Option<u64>,
+) -> anyhow::Result<String> {
+    let response = gateway
+        .handle_create_api_token_msg(CreateApiTokenPayload {
+            name: name.to_string(),
+            scopes: BTreeSet::from([scope]),
+            expiry_secs,
+        })
+        .await?;
+
+    Ok(response.token)
+}
+
+/// Returns the status of a request to an endpoint that requires the admin scope
+/// over HTTP and over Iroh
+async fn list_api_tokens_statuses(
+    gateway: &Arc<Gateway>,
+    password: Option<&str>,
+) -> anyhow::Result<(StatusCode, StatusCode)> {
+    let api = gateway.handle_get_info().await?.registrations[&RegisteredProtocol::Http]
+        .0
+        .clone();
+
+    let mut request =
+        reqwest::Client::new().get(format!("{}{LIST_API_TOKENS_ENDPOINT}", api.to_unsafe()));
+    if let Some(password) = password {
+        request = request.bearer_auth(password);
+    }
+    let http_status = request.send().await?.status();
+
+    let iroh_status = handle_iroh_request(
+        gateway.clone(),
+        &IrohGatewayRequest {
+            route: LIST_API_TOKENS_ENDPOINT.to_string(),
+            params: None,
+            password: password.map(ToString::to_string),
+        },
+    )
+    .await?;
+
+    Ok((http_status, iroh_status))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_api_tokens_are_unauthorized() -> anyhow::Result<()> {
+    let gateway = Arc::new(fixtures().new_gateway().await);
+    let (_mnemonic_sender, mnemonic_receiver) = tokio::sync::broadcast::channel(1);
+    run_webserver(gateway.clone(), mnemonic_receiver).await?;
+
+    let admin = create_api_token(&gateway, "admin", ApiScope::Admin, None).await?;
+    let read_only = create_api_token(&gateway, "read-only", ApiScope::ReadOnly, None).await?;
+    let expired = create_api_token(&gateway, "expired", ApiScope::Admin, Some(0)).await?;
+    let revoked = create_api_token(&gateway, "revoked", ApiScope::Admin, None).await?;
+    gateway
+        .handle_revoke_api_token_msg(RevokeApiTokenPayload {
+            name: "revoked".to_string(),
+        })
+        .await?;
+
+    for password in [Some(DEFAULT_GATEWAY_PASSWORD), Some(admin.as_str())] {
+        assert_eq!(
+            list_api_tokens_statuses(&gateway, password).await?,
+            (StatusCode::OK, StatusCode::OK)
+        );
+    }
+
+    for password in [
+        None,
+        Some("invalid"),
+        Some(read_only.as_str()),
+        Some(expired.as_str()),
+        Some(revoked.as_str()),
+    ] {
+        assert_eq!(
+            list_api_tokens_statuses(&gateway, password).await?,
+            (StatusCode::UNAUTHORIZED, StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,224 @@
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::time::SystemTime;
+
+use axum::Form;
+use axum::extract::State;
+use axum::response::{Html, IntoResponse};
+use fedimint_gateway_common::{ApiScope, CreateApiTokenPayload, RevokeApiTokenPayload};
+use fedimint_logging::LOG_GATEWAY_UI;
+use fedimint_ui_common::UiState;
+use fedimint_ui_common::auth::UserAuth;
+use maud::{Markup, html};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{
+    CREATE_API_TOKEN_ROUTE, DynGatewayApi, REVOKE_API_TOKEN_ROUTE, redirect_error, redirect_success,
+};
+
+/// Every scope is a separate checkbox, the expiry is entered in days and the
+/// token never expires if it is left empty
+#[derive(Deserialize)]
+pub struct CreateApiTokenForm {
+    pub name: String,
+    #[serde(default)]
+    pub read_only: bool,
+    #[serde(default)]
+    pub invoice: bool,
+    #[serde(default)]
+    pub payments: bool,
+    #[serde(default)]
+    pub onchain: bool,
+    #[serde(default)]
+    pub admin: bool,
+    pub expiry_days: String,
+}
+
+pub async fn render<E>(api: &DynGatewayApi<E>) -> Markup
+where
+    E: std::fmt::Display,
+{
+    debug!(target: LOG_GATEWAY_UI, "Listing API tokens...");
+    let tokens = api.handle_list_api_tokens_msg().await;
+
+    html! {
+        div class="card h-100" {
+            div class="card-header dashboard-header" { "API Tokens" }
+
+            div class="card-body" {
+                @match tokens {
+                    Ok(tokens) if tokens.is_empty() => {
+                        div class="alert alert-secondary" {
+                            "No API tokens. The gateway password grants access to all endpoints."
+                        }
+                    }
+                    Ok(tokens) => {
+                        table class="table table-sm" {
+                            thead {
+                                tr {
+                                    th { "Name" }
+                                    th { "Scopes" }
+                                    th { "Expires" }
+                                    th {}
+                                }
+                            }
+                            tbody {
+                                @for token in tokens {
+                                    tr {
+                                        td class="align-middle fw-bold" { (token.name) }
+                                        td class="align-middle" {
+                                            @for scope in &token.scopes {
+                                                span class="badge bg-secondary me-1" { (scope) }
+                                            }
+                                        }
+                                        td class="align-middle" {
+                                            (token.expires_at.map_or("Never".to_string(), format_time))
+                                        }
+                                        td class="text-end" {
+                                            form action=(REVOKE_API_TOKEN_ROUTE) method="post" {
+                                                input type="hidden" name="name" value=(token.name);
+                                                button type="submit"
+                                                    class="btn btn-sm btn-outline-danger"
+                                                    onclick="return confirm('Are you sure you want to revoke this API token?');"
+                                                {
+                                                    "Revoke"
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        div class="alert alert-danger" {
+                            "Failed to list API tokens: " (err)
+                        }
+                    }
+                }
+
+                form hx-post=(CREATE_API_TOKEN_ROUTE)
+                     hx-target="#api-token-result"
+                     hx-swap="innerHTML"
+                {
+                    div class="mb-2" {
+                        label class="form-label" for="api-token-name" { "Name" }
+                        input type="text" class="form-control form-control-sm"
+                            id="api-token-name" name="name" required;
+                    }
+
+                    div class="mb-2" {
+                        @for (name, scope) in [
+                            ("read_only", ApiScope::ReadOnly),
+                            ("invoice", ApiScope::Invoice),
+                            ("payments", ApiScope::Payments),
+                            ("onchain", ApiScope::Onchain),
+                            ("admin", ApiScope::Admin),
+                        ] {
+                            div class="form-check form-check-inline" {
+                                input type="checkbox" class="form-check-input"
+                                    id=(format!("api-token-scope-{name}")) name=(name) value="true";
+                                label class="form-check-label" for=(format!("api-token-scope-{name}")) {
+                                    (scope)
+                                }
+                            }
+                        }
+                    }
+
+                    div class="mb-2" {
+                        label class="form-label" for="api-token-expiry" { "Expiry (days)" }
+                        input type="number" min="1" class="form-control form-control-sm"
+                            id="api-token-expiry" name="expiry_days" placeholder="Never";
+                    }
+
+                    button type="submit" class="btn btn-sm btn-primary" { "Create Token" }
+                }
+
+                div id="api-token-result" {}
+            }
+        }
+    }
+}
+
+pub async fn create_api_token_handler<E: Display>(
+    State(state): State<UiState<DynGatewayApi<E>>>,
+    _auth: UserAuth,
+    Form(form): Form<CreateApiTokenForm>,
+) -> impl IntoResponse {
+    let scopes = [
+        (form.read_only, ApiScope::ReadOnly),
+        (form.invoice, ApiScope::Invoice),
+        (form.payments, ApiScope::Payments),
+        (form.onchain, ApiScope::Onchain),
+        (form.admin, ApiScope::Admin),
+    ]
+    .into_iter()
+    .filter_map(|(selected, scope)| selected.then_some(scope))
+    .collect::<BTreeSet<ApiScope>>();
+
+    let expiry_days = form.expiry_days.trim();
+    let expiry_secs = if expiry_days.is_empty() {
+        None
+    } else {
+        match expiry_days.parse::<u64>() {
+            Ok(days) => Some(days * 24 * 60 * 60),
+            Err(_) => {
+                return Html(
+                    html! {
+                        div class="alert alert-danger mt-2" {
+                            "Invalid expiry: " (expiry_days)
+                        }
+                    }
+                    .into_string(),
+                );
+            }
+        }
+    };
+
+    let markup = match state
+        .api
+        .handle_create_api_token_msg(CreateApiTokenPayload {
+            name: form.name,
+            scopes,
+            expiry_secs,
+        })
+        .await
+    {
+        Ok(response) => html! {
+            div class="alert alert-success mt-2" {
+                p { "Created API token " strong { (response.name) } ". It will not be shown again:" }
+                input type="text"
+                    readonly
+                    class="form-control font-monospace"
+                    value=(response.token)
+                    onclick="copyToClipboard(this)";
+                small class="text-muted" { "Click to copy" }
+            }
+        },
+        Err(err) => html! {
+            div class="alert alert-danger mt-2" {
+                "Failed to create API token: " (err)
+            }
+        },
+    };
+
+    Html(markup.into_string())
+}
+
+pub async fn revoke_api_token_handler<E: Display>(
+    State(state): State<UiState<DynGatewayApi<E>>>,
+    _auth: UserAuth,
+    Form(payload): Form<RevokeApiTokenPayload>,
+) -> impl IntoResponse {
+    let name = payload.name.clone();
+    match state.api.handle_revoke_api_token_msg(payload).await {
+        Ok(()) => redirect_success(format!("Revoked API token {name}")).into_response(),
+        Err(err) => redirect_error(format!("Failed to revoke API token: {err}")).into_response(),
+    }
+}
+
+fn format_time(time: SystemTime) -> String {
+    let time: chrono::DateTime<chrono::Utc> = time.into();
+    time.format("%Y-%m-%d %H:%M UTC").to_string()
+}
@@ -10,8 +10,9 @@
 use fedimint_core::config::FederationId;
 use fedimint_core::{Amount, BitcoinAmountOrAll};
 use fedimint_gateway_common::{
-    DepositAddressPayload, FederationInfo, LeaveFedPayload, ReceiveEcashPayload, SetFeesPayload,
-    SpendEcashPayload, WithdrawPayload, WithdrawPreviewPayload,
+    CircuitBreakerState, DepositAddressPayload, FederationInfo, LeaveFedPayload,
+    ReceiveEcashPayload, SetFederationLimitsPayload, SetFeesPayload, SpendEcashPayload,
+    WithdrawPayload, WithdrawPreviewPayload,
 };
 use fedimint_mint_client::OOBNotes;
 use fedimint_ui_common::UiState;
@@ -23,10 +24,20 @@
 use serde::Deserialize;
 
 use crate::{
-    DEPOSIT_ADDRESS_ROUTE, DynGatewayApi, RECEIVE_ECASH_ROUTE, SET_FEES_ROUTE, SPEND_ECASH_ROUTE,
-    WITHDRAW_CONFIRM_ROUTE, WITHDRAW_PREVIEW_ROUTE, redirect_error, redirect_success,
+    DEPOSIT_ADDRESS_ROUTE, DynGatewayApi, RECEIVE_ECASH_ROUTE, SET_FEDERATION_LIMITS_ROUTE,
+    SET_FEES_ROUTE, SPEND_ECASH_ROUTE, WITHDRAW_CONFIRM_ROUTE, WITHDRAW_PREVIEW_ROUTE,
+    redirect_error, redirect_success,
 };
 
+/// Limits are entered in msats, empty fields remove the limit
+#[derive(Deserialize)]
+pub struct SetFederationLimitsForm {
+    pub federation_id: FederationId,
+    pub max_balance: String,
+    pub max_payment: String,
+    pub max_daily_volume: String,
+}
+
 #[derive(Deserialize)]
 pub struct ReceiveEcashForm {
     pub notes: String,
@@ -106,6 +117,11 @@ pub fn render(fed: &FederationInfo) -> Markup {
                         div class="alert alert-secondary py-1 px-2 small" {
                             "Last Backup: " strong { (last_backup_str) }
                         }
+                        @if let CircuitBreakerState::Open { opened_at, reason } = &fed.circuit_breaker {
+                            div class="alert alert-warning py-1 px-2 small" {
+                                "Circuit breaker opened " (time_ago(*opened_at)) ": " strong { (reason) }
+                            }
+                        }
 
                         // --- TABS ---
                         ul class="nav nav-tabs" role="tablist" {
@@ -118,6 +134,15 @@ pub fn render(fed: &FederationInfo) -> Markup {
                                     role="tab"
                                 { "Fees" }
                             }
+                            li class="nav-item" role="presentation" {
+                                button class="nav-link"
+                                    id={(format!("limits-tab-{}", fed.federation_id))}
+                                    data-bs-toggle="tab"
+                                    data-bs-target={(format!("#limits-tab-pane-{}", fed.federation_id))}
+                                    type="button"
+                                    role="tab"
+                                { "Limits" }
+                            }
                             li class="nav-item" role="presentation" {
                                 button class="nav-link"
                                     id={(format!("deposit-tab-{}", fed.federation_id))}
@@ -282,6 +307,68 @@ pub fn render(fed: &FederationInfo) -> Markup {
                                 }
                             }
 
+                            // ──────────────────────────────────────────
+                            //   TAB: LIMITS
+                            // ──────────────────────────────────────────
+                            div class="tab-pane fade"
+                                id={(format!("limits-tab-pane-{}", fed.federation_id))}
+                                role="tabpanel"
+                                aria-labelledby={(format!("limits-tab-{}", fed.federation_id))} {
+
+                                div class="alert alert-secondary py-1 px-2 small" {
+                                    "Volume (24h): " strong { (fed.daily_volume) }
+                                }
+
+                                form method="post" action={(SET_FEDERATION_LIMITS_ROUTE)} {
+                                    input type="hidden" name="federation_id" value=(fed.federation_id.to_string());
+                                    table class="table table-sm mb-2" {
+                                        tbody {
+                                            tr {
+                                                th {
+                                                    "Max Balance "
+                                                    span class="text-muted" data-bs-toggle="tooltip" title="Maximum ecash balance in millisatoshis the gateway holds in this federation, outgoing payments that would exceed it are rejected" { "ⓘ" }
+                                                }
+                                                td {
+                                                    input type="number"
+                                                        class="form-control form-control-sm"
+                                                        name="max_balance"
+                                                        placeholder="No limit"
+                                                        value=[fed.limits.max_balance.map(|amount| amount.msats)];
+                                                }
+                                            }
+                                            tr {
+                                                th {
+                                                    "Max Payment "
+                                                    span class="text-muted" data-bs-toggle="tooltip" title="Maximum amount in millisatoshis of a single payment sent or received for this federation" { "ⓘ" }
+                                                }
+                                                td {
+                                                    input type="number"
+                                                        class="form-control form-control-sm"
+                                                        name="max_payment"
+                                                        placeholder="No limit"
+                                                        value=[fed.limits.max_payment.map(|amount| amount.msats)];
+                                                }
+                                            }
+                                            tr {
+                                                th {
+                                                    "Max Daily Volume "
+                                                    span class="text-muted" data-bs-toggle="tooltip" title="Maximum volume in millisatoshis sent and received for this federation over the last 24 hours" { "ⓘ" }
+                                                }
+                                                td {
+                                                    input type="number"
+                                                        class="form-control form-control-sm"
+                                                        name="max_daily_volume"
+                                                        placeholder="No limit"
+                                                        value=[fed.limits.max_daily_volume.map(|amount| amount.msats)];
+                                                }
+                                            }
+                                        }
+                                    }
+
+                                    button type="submit" class="btn btn-sm btn-primary" { "Save Limits" }
+                                }
+                            }
+
                             // ──────────────────────────────────────────
                             //   TAB: DEPOSIT
                             // ──────────────────────────────────────────
@@ -493,6 +580,45 @@ pub async fn set_fees_handler<E: Display>(
     }
 }
 
+pub async fn set_federation_limits_handler<E: Display>(
+    State(state): State<UiState<DynGatewayApi<E>>>,
+    _auth: UserAuth,
+    Form(form): Form<SetFederationLimitsForm>,
+) -> impl IntoResponse {
+    fn parse_limit(limit: &str) -> Result<Option<Amount>, String> {
+        let limit = limit.trim();
+        if limit.is_empty() {
+            return Ok(None);
+        }
+
+        limit
+            .parse::<u64>()
+            .map(|msats| Some(Amount::from_msats(msats)))
+            .map_err(|_| format!("Invalid limit: {limit}"))
+    }
+
+    let payload = match (
+        parse_limit(&form.max_balance),
+        parse_limit(&form.max_payment),
+        parse_limit(&form.max_daily_volume),
+    ) {
+        (Ok(max_balance), Ok(max_payment), Ok(max_daily_volume)) => SetFederationLimitsPayload {
+            federation_id: form.federation_id,
+            max_balance,
+            max_payment,
+            max_daily_volume,
+        },
+        (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => {
+            return redirect_error(format!("Failed to update limits: {err}")).into_response();
+        }
+    };
+
+    match state.api.handle_set_federation_limits_msg(payload).await {
+        Ok(_) => redirect_success("Successfully set limits".to_string()).into_response(),
+        Err(err) => redirect_error(format!("Failed to update limits: {err}")).into_response(),
+    }
+}
+
 pub async fn deposit_address_handler<E: Display>(
     State(state): State<UiState<DynGatewayApi<E>>>,
     _auth: UserAuth,
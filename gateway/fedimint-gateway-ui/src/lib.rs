@@ -1,3 +1,4 @@
+mod api_tokens;
 mod bitcoin;
 mod connect_fed;
 mod federation;
@@ -22,14 +23,15 @@
 use fedimint_core::secp256k1::serde::Deserialize;
 use fedimint_core::task::TaskGroup;
 use fedimint_gateway_common::{
-    ChainSource, CloseChannelsWithPeerRequest, CloseChannelsWithPeerResponse, ConnectFedPayload,
+    ApiTokenInfo, ChainSource, CloseChannelsWithPeerRequest, CloseChannelsWithPeerResponse,
+    ConnectFedPayload, CreateApiTokenPayload, CreateApiTokenResponse,
     CreateInvoiceForOperatorPayload, DepositAddressPayload, FederationInfo, GatewayBalances,
     GatewayInfo, LeaveFedPayload, LightningMode, ListTransactionsPayload, ListTransactionsResponse,
     MnemonicResponse, OpenChannelRequest, PayInvoiceForOperatorPayload, PaymentLogPayload,
     PaymentLogResponse, PaymentSummaryPayload, PaymentSummaryResponse, ReceiveEcashPayload,
-    ReceiveEcashResponse, SendOnchainRequest, SetFeesPayload, SetMnemonicPayload,
-    SpendEcashPayload, SpendEcashResponse, WithdrawPayload, WithdrawPreviewPayload,
-    WithdrawPreviewResponse, WithdrawResponse,
+    ReceiveEcashResponse, RevokeApiTokenPayload, SendOnchainRequest, SetFederationLimitsPayload,
+    SetFeesPayload, SetMnemonicPayload, SpendEcashPayload, SpendEcashResponse, WithdrawPayload,
+    WithdrawPreviewPayload, WithdrawPreviewResponse, WithdrawResponse,
 };
 use fedimint_ln_common::contracts::Preimage;
 use fedimint_logging::LOG_GATEWAY_UI;
@@ -43,10 +45,12 @@
 use maud::html;
 use tracing::debug;
 
+use crate::api_tokens::{create_api_token_handler, revoke_api_token_handler};
 use crate::connect_fed::connect_federation_handler;
 use crate::federation::{
-    deposit_address_handler, leave_federation_handler, receive_ecash_handler, set_fees_handler,
-    spend_ecash_handler, withdraw_confirm_handler, withdraw_preview_handler,
+    deposit_address_handler, leave_federation_handler, receive_ecash_handler,
+    set_federation_limits_handler, set_fees_handler, spend_ecash_handler, withdraw_confirm_handler,
+    withdraw_preview_handler,
 };
 use crate::lightning::{
     channels_fragment_handler, close_channel_handler, create_bolt11_invoice_handler,
@@ -65,6 +69,9 @@
 pub(crate) const LEAVE_FEDERATION_ROUTE: &str = "/ui/federations/{id}/leave";
 pub(crate) const CONNECT_FEDERATION_ROUTE: &str = "/ui/federations/join";
 pub(crate) const SET_FEES_ROUTE: &str = "/ui/federation/set-fees";
+pub(crate) const SET_FEDERATION_LIMITS_ROUTE: &str = "/ui/federation/set-limits";
+pub(crate) const CREATE_API_TOKEN_ROUTE: &str = "/ui/api-tokens/create";
+pub(crate) const REVOKE_API_TOKEN_ROUTE: &str = "/ui/api-tokens/revoke";
 pub(crate) const SEND_ONCHAIN_ROUTE: &str = "/ui/wallet/send";
 pub(crate) const WALLET_FRAGMENT_ROUTE: &str = "/ui/wallet/fragment";
 pub(crate) const LN_ONCHAIN_ADDRESS_ROUTE: &str = "/ui/wallet/receive";
@@ -137,6 +144,23 @@ async fn handle_connect_federation(
 
     async fn handle_set_fees_msg(&self, payload: SetFeesPayload) -> Result<(), Self::Error>;
 
+    async fn handle_set_federation_limits_msg(
+        &self,
+        payload: SetFederationLimitsPayload,
+    ) -> Result<(), Self::Error>;
+
+    async fn handle_create_api_token_msg(
+        &self,
+        payload: CreateApiTokenPayload,
+    ) -> Result<CreateApiTokenResponse, Self::Error>;
+
+    async fn handle_list_api_tokens_msg(&self) -> Result<Vec<ApiTokenInfo>, Self::Error>;
+
+    async fn handle_revoke_api_token_msg(
+        &self,
+        payload: RevokeApiTokenPayload,
+    ) -> Result<(), Self::Error>;
+
     async fn handle_mnemonic_msg(&self) -> Result<MnemonicResponse, Self::Error>;
 
     async fn handle_open_channel_msg(
@@ -347,6 +371,12 @@ async fn dashboard_view<E>(
             }
         }
 
+        div class="row gy-4 mt-2" {
+            div class="col-md-12" {
+                (api_tokens::render(&state.api).await)
+            }
+        }
+
         div class="row gy-4 mt-2" {
             div class="col-md-12" {
                 (connect_fed::render())
@@ -392,6 +422,12 @@ pub fn router<E: Display + Send + Sync + std::fmt::Debug + 'static>(
         .route(LEAVE_FEDERATION_ROUTE, post(leave_federation_handler))
         .route(CONNECT_FEDERATION_ROUTE, post(connect_federation_handler))
         .route(SET_FEES_ROUTE, post(set_fees_handler))
+        .route(
+            SET_FEDERATION_LIMITS_ROUTE,
+            post(set_federation_limits_handler),
+        )
+        .route(CREATE_API_TOKEN_ROUTE, post(create_api_token_handler))
+        .route(REVOKE_API_TOKEN_ROUTE, post(revoke_api_token_handler))
         .route(SEND_ONCHAIN_ROUTE, post(send_onchain_handler))
         .route(
             LN_ONCHAIN_ADDRESS_ROUTE,
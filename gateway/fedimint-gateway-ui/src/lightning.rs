@@ -224,6 +224,43 @@ pub async fn render<E>(gateway_info: &GatewayInfo, api: &DynGatewayApi<E>) -> Ma
                                     }
                                 }
                             }
+                            LightningMode::Cln { cln_extension_addr } => {
+                                div id="node-type" class="alert alert-info" {
+                                    "Node Type: " strong { "External CLN" }
+                                }
+                                table class="table table-sm mb-0" {
+                                    tbody {
+                                        tr {
+                                            th { "Extension Address" }
+                                            td { (cln_extension_addr) }
+                                        }
+                                        tr {
+                                            th { "Network" }
+                                            td { (network) }
+                                        }
+                                        tr {
+                                            th { "Block Height" }
+                                            td { (block_height) }
+                                        }
+                                        tr {
+                                            th { "Status" }
+                                            td { (status_badge) }
+                                        }
+                                        @if let Some(a) = alias {
+                                            tr {
+                                                th { "Alias" }
+                                                td { (a) }
+                                            }
+                                        }
+                                        @if let Some(pk) = pubkey {
+                                            tr {
+                                                th { "Public Key" }
+                                                td { (pk) }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
                             LightningMode::Ldk { lightning_port, .. } => {
                                 div id="node-type" class="alert alert-info" {
                                     "Node Type: " strong { "Internal LDK" }
@@ -0,0 +1,13 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // The workspace `prost` is pinned by `tonic_lnd`, so the generated code uses
+    // the version `tonic-prost` encodes with
+    let mut config = tonic_prost_build::Config::new();
+    config.prost_path("::tonic_prost::prost");
+
+    tonic_prost_build::configure().compile_with_config(
+        config,
+        &["proto/cln_extension.proto"],
+        &["proto"],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,649 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use bitcoin::OutPoint;
+use bitcoin::hashes::{Hash, sha256};
+use fedimint_core::task::{TaskGroup, sleep};
+use fedimint_core::util::{FmtCompact, SafeUrl};
+use fedimint_core::{Amount, BitcoinAmountOrAll, secp256k1};
+use fedimint_gateway_common::{
+    ListTransactionsResponse, PaymentDetails, PaymentDirection, PaymentKind, PaymentStatus,
+};
+use fedimint_ln_common::contracts::Preimage;
+use fedimint_ln_common::route_hints::{RouteHint, RouteHintHop};
+use fedimint_logging::LOG_LIGHTNING;
+use hex::ToHex;
+use lightning_invoice::Bolt11Invoice;
+use secp256k1::PublicKey;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::Channel;
+use tracing::{debug, info, trace, warn};
+
+use self::proto::gateway_cln_client::GatewayClnClient as ClnExtensionClient;
+use super::{
+    ChannelInfo, ILnRpcClient, LightningRpcError, ListChannelsResponse, MAX_LIGHTNING_RETRIES,
+    RouteHtlcStream,
+};
+use crate::{
+    CloseChannelsWithPeerRequest, CloseChannelsWithPeerResponse, CreateInvoiceRequest,
+    CreateInvoiceResponse, GetBalancesResponse, GetInvoiceRequest, GetInvoiceResponse,
+    GetLnOnchainAddressResponse, GetNodeInfoResponse, GetRouteHintsResponse,
+    InterceptPaymentRequest, InterceptPaymentResponse, InvoiceDescription, OpenChannelRequest,
+    OpenChannelResponse, PayInvoiceResponse, PaymentAction, SendOnchainRequest,
+    SendOnchainResponse,
+};
+
+/// Messages and services of the gRPC interface exposed by the
+/// `gateway-cln-extension` plugin.
+pub mod proto {
+    tonic::include_proto!("fedimint_cln");
+}
+
+/// Connects the gateway to a Core Lightning node through the
+/// `gateway-cln-extension` plugin, which intercepts the HTLCs accepted by the
+/// node via the `htlc_accepted` hook and streams them to the gateway.
+#[derive(Clone)]
+pub struct GatewayClnClient {
+    address: SafeUrl,
+}
+
+impl GatewayClnClient {
+    pub fn new(address: SafeUrl) -> Self {
+        info!(
+            target: LOG_LIGHTNING,
+            address = %address,
+            "Gateway configured to connect to CLN extension",
+        );
+        GatewayClnClient { address }
+    }
+
+    async fn connect(&self) -> Result<ClnExtensionClient<Channel>, LightningRpcError> {
+        let mut retries = 0;
+        let client = loop {
+            if retries >= MAX_LIGHTNING_RETRIES {
+                return Err(LightningRpcError::FailedToConnect);
+            }
+
+            retries += 1;
+
+            match ClnExtensionClient::connect(self.address.to_string()).await {
+                Ok(client) => break client,
+                Err(err) => {
+                    debug!(target: LOG_LIGHTNING, err = %err.fmt_compact(), "Couldn't connect to CLN extension, retrying in 1 second...");
+                    sleep(Duration::from_secs(1)).await;
+                }
+            }
+        };
+
+        Ok(client)
+    }
+
+    /// Spawns a background task that forwards the HTLCs intercepted by the CLN
+    /// extension to the gateway.
+    async fn spawn_interceptor(
+        &self,
+        task_group: &TaskGroup,
+        gateway_sender: mpsc::Sender<InterceptPaymentRequest>,
+    ) -> Result<(), LightningRpcError> {
+        let mut client = self.connect().await?;
+
+        // Verify that the extension is reachable before attempting to spawn a new
+        // thread that will intercept HTLCs.
+        let mut htlc_stream = client
+            .route_htlcs(proto::RouteHtlcsRequest {})
+            .await
+            .map_err(|status| LightningRpcError::FailedToRouteHtlcs {
+                failure_reason: format!("Failed to subscribe to HTLC stream {status:?}"),
+            })?
+            .into_inner();
+
+        task_group.spawn("CLN HTLC Subscription", |handle| async move {
+            debug!(target: LOG_LIGHTNING, "CLN HTLC Subscription: starting to process stream");
+
+            while let Some(htlc) = tokio::select! {
+                () = handle.make_shutdown_rx() => {
+                    info!(target: LOG_LIGHTNING, "CLN HTLC Subscription task received shutdown signal");
+                    None
+                }
+                htlc_message = htlc_stream.message() => {
+                    match htlc_message {
+                        Ok(htlc) => htlc,
+                        Err(err) => {
+                            warn!(target: LOG_LIGHTNING, err = %err.fmt_compact(), "Error received over HTLC stream");
+                            None
+                        }
+                }}
+            } {
+                trace!(target: LOG_LIGHTNING, ?htlc, "CLN Handling HTLC");
+
+                let Ok(payment_hash) = sha256::Hash::from_slice(&htlc.payment_hash) else {
+                    warn!(target: LOG_LIGHTNING, "Cannot route HTLC with invalid payment hash");
+                    continue;
+                };
+
+                // Forward all HTLCs to gatewayd, gatewayd will filter them based on the
+                // payment hash and scid. HTLCs that are dropped here are resumed by the
+                // extension once the stream closes.
+                let intercept = InterceptPaymentRequest {
+                    payment_hash,
+                    amount_msat: htlc.amount_msat,
+                    expiry: htlc.expiry,
+                    incoming_chan_id: htlc.incoming_chan_id,
+                    short_channel_id: htlc.short_channel_id,
+                    htlc_id: htlc.htlc_id,
+                };
+
+                if let Err(err) = gateway_sender.send(intercept).await {
+                    warn!(target: LOG_LIGHTNING, err = %err.fmt_compact(), "Failed to send HTLC to gatewayd for processing");
+                    break;
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn pay_invoice(
+        &self,
+        invoice: Bolt11Invoice,
+        amount: Option<Amount>,
+        max_delay: u64,
+        max_fee: Amount,
+    ) -> Result<PayInvoiceResponse, LightningRpcError> {
+        let response = self
+            .connect()
+            .await?
+            .pay_invoice(proto::PayInvoiceRequest {
+                invoice: invoice.to_string(),
+                amount_msat: amount.map(|amount| amount.msats),
+                max_delay,
+                max_fee_msat: max_fee.msats,
+            })
+            .await
+            .map_err(|status| LightningRpcError::FailedPayment {
+                failure_reason: format!("Failed to pay invoice {status:?}"),
+            })?
+            .into_inner();
+
+        let preimage =
+            response
+                .preimage
+                .try_into()
+                .map_err(|_| LightningRpcError::FailedPayment {
+                    failure_reason: "CLN extension returned an invalid preimage".to_string(),
+                })?;
+
+        Ok(PayInvoiceResponse {
+            preimage: Preimage(preimage),
+        })
+    }
+}
+
+impl fmt::Debug for GatewayClnClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ClnClient")
+    }
+}
+
+#[async_trait]
+impl ILnRpcClient for GatewayClnClient {
+    async fn info(&self) -> Result<GetNodeInfoResponse, LightningRpcError> {
+        let info = self
+            .connect()
+            .await?
+            .get_node_info(proto::GetNodeInfoRequest {})
+            .await
+            .map_err(|status| LightningRpcError::FailedToGetNodeInfo {
+                failure_reason: format!("Failed to get node info {status:?}"),
+            })?
+            .into_inner();
+
+        let pub_key = PublicKey::from_slice(&info.pub_key).map_err(|e| {
+            LightningRpcError::FailedToGetNodeInfo {
+                failure_reason: format!("Failed to parse public key {e:?}"),
+            }
+        })?;
+
+        Ok(GetNodeInfoResponse {
+            pub_key,
+            alias: info.alias,
+            network: info.network,
+            block_height: info.block_height,
+            synced_to_chain: info.synced_to_chain,
+        })
+    }
+
+    async fn routehints(
+        &self,
+        num_route_hints: usize,
+    ) -> Result<GetRouteHintsResponse, LightningRpcError> {
+        let response = self
+            .connect()
+            .await?
+            .get_route_hints(proto::GetRouteHintsRequest {
+                num_route_hints: num_route_hints as u64,
+            })
+            .await
+            .map_err(|status| LightningRpcError::FailedToGetRouteHints {
+                failure_reason: format!("Failed to get route hints {status:?}"),
+            })?
+            .into_inner();
+
+        let route_hints = response
+            .route_hints
+            .into_iter()
+            .map(|route_hint| {
+                let hops = route_hint
+                    .hops
+                    .into_iter()
+                    .map(|hop| {
+                        Ok(RouteHintHop {
+                            src_node_id: PublicKey::from_slice(&hop.src_node_id)?,
+                            short_channel_id: hop.short_channel_id,
+                            base_msat: hop.base_msat,
+                            proportional_millionths: hop.proportional_millionths,
+                            cltv_expiry_delta: hop.cltv_expiry_delta as u16,
+                            htlc_minimum_msat: hop.htlc_minimum_msat,
+                            htlc_maximum_msat: hop.htlc_maximum_msat,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, secp256k1::Error>>()?;
+
+                Ok(RouteHint(hops))
+            })
+            .collect::<Result<Vec<_>, secp256k1::Error>>()
+            .map_err(|e| LightningRpcError::FailedToGetRouteHints {
+                failure_reason: format!("Failed to parse route hint {e:?}"),
+            })?;
+
+        Ok(GetRouteHintsResponse { route_hints })
+    }
+
+    async fn pay(
+        &self,
+        invoice: Bolt11Invoice,
+        max_delay: u64,
+        max_fee: Amount,
+    ) -> Result<PayInvoiceResponse, LightningRpcError> {
+        self.pay_invoice(invoice, None, max_delay, max_fee).await
+    }
+
+    async fn pay_amountless(
+        &self,
+        invoice: Bolt11Invoice,
+        amount: Amount,
+        max_delay: u64,
+        max_fee: Amount,
+    ) -> Result<PayInvoiceResponse, LightningRpcError> {
+        self.pay_invoice(invoice, Some(amount), max_delay, max_fee)
+            .await
+    }
+
+    async fn route_htlcs<'a>(
+        self: Box<Self>,
+        task_group: &TaskGroup,
+    ) -> Result<(RouteHtlcStream<'a>, Arc<dyn ILnRpcClient>), LightningRpcError> {
+        const CHANNEL_SIZE: usize = 100;
+
+        // Channel to send intercepted htlc to the gateway for processing
+        let (gateway_sender, gateway_receiver) =
+            mpsc::channel::<InterceptPaymentRequest>(CHANNEL_SIZE);
+
+        self.spawn_interceptor(task_group, gateway_sender).await?;
+
+        Ok((
+            Box::pin(ReceiverStream::new(gateway_receiver)),
+            Arc::new(*self),
+        ))
+    }
+
+    async fn complete_htlc(&self, htlc: InterceptPaymentResponse) -> Result<(), LightningRpcError> {
+        let InterceptPaymentResponse {
+            incoming_chan_id,
+            htlc_id,
+            payment_hash,
+            action,
+        } = htlc;
+
+        let action = match action {
+            PaymentAction::Settle(preimage) => {
+                proto::complete_htlc_request::Action::Settle(proto::complete_htlc_request::Settle {
+                    preimage: preimage.0.to_vec(),
+                })
+            }
+            PaymentAction::Cancel => proto::complete_htlc_request::Action::Cancel(
+                proto::complete_htlc_request::Cancel {},
+            ),
+            PaymentAction::Forward => proto::complete_htlc_request::Action::Forward(
+                proto::complete_htlc_request::Forward {},
+            ),
+        };
+
+        self.connect()
+            .await?
+            .complete_htlc(proto::CompleteHtlcRequest {
+                incoming_chan_id,
+                htlc_id,
+                payment_hash: payment_hash.to_byte_array().to_vec(),
+                action: Some(action),
+            })
+            .await
+            .map_err(|status| LightningRpcError::FailedToCompleteHtlc {
+                failure_reason: format!("Failed to complete HTLC {status:?}"),
+            })?;
+
+        Ok(())
+    }
+
+    async fn create_invoice(
+        &self,
+        create_invoice_request: CreateInvoiceRequest,
+    ) -> Result<CreateInvoiceResponse, LightningRpcError> {
+        let description = match create_invoice_request
+            .description
+            .unwrap_or(InvoiceDescription::Direct(String::new()))
+        {
+            InvoiceDescription::Direct(description) => {
+                proto::create_invoice_request::Description::Direct(description)
+            }
+            InvoiceDescription::Hash(hash) => {
+                proto::create_invoice_request::Description::Hash(hash.to_byte_array().to_vec())
+            }
+        };
+
+        let response = self
+            .connect()
+            .await?
+            .create_invoice(proto::CreateInvoiceRequest {
+                payment_hash: create_invoice_request
+                    .payment_hash
+                    .map(|payment_hash| payment_hash.to_byte_array().to_vec()),
+                amount_msat: create_invoice_request.amount_msat,
+                expiry_secs: create_invoice_request.expiry_secs,
+                description: Some(description),
+            })
+            .await
+            .map_err(|status| LightningRpcError::FailedToGetInvoice {
+                failure_reason: format!("Failed to create invoice {status:?}"),
+            })?
+            .into_inner();
+
+        Ok(CreateInvoiceResponse {
+            invoice: response.invoice,
+        })
+    }
+
+    async fn get_ln_onchain_address(
+        &self,
+    ) -> Result<GetLnOnchainAddressResponse, LightningRpcError> {
+        let response = self
+            .connect()
+            .await?
+            .get_ln_onchain_address(proto::GetLnOnchainAddressRequest {})
+            .await
+            .map_err(|status| LightningRpcError::FailedToGetLnOnchainAddress {
+                failure_reason: format!("Failed to get funding address {status:?}"),
+            })?
+            .into_inner();
+
+        Ok(GetLnOnchainAddressResponse {
+            address: response.address,
+        })
+    }
+
+    async fn send_onchain(
+        &self,
+        SendOnchainRequest {
+            address,
+            amount,
+            fee_rate_sats_per_vbyte,
+        }: SendOnchainRequest,
+    ) -> Result<SendOnchainResponse, LightningRpcError> {
+        let amount_sats = match amount {
+            BitcoinAmountOrAll::All => None,
+            BitcoinAmountOrAll::Amount(amount) => Some(amount.to_sat()),
+        };
+
+        let response = self
+            .connect()
+            .await?
+            .send_onchain(proto::SendOnchainRequest {
+                address: address.assume_checked().to_string(),
+                amount_sats,
+                fee_rate_sats_per_vbyte,
+            })
+            .await
+            .map_err(|status| LightningRpcError::FailedToWithdrawOnchain {
+                failure_reason: format!("Failed to withdraw funds on-chain {status:?}"),
+            })?
+            .into_inner();
+
+        Ok(SendOnchainResponse {
+            txid: response.txid,
+        })
+    }
+
+    async fn open_channel(
+        &self,
+        OpenChannelRequest {
+            pubkey,
+            host,
+            channel_size_sats,
+            push_amount_sats,
+        }: OpenChannelRequest,
+    ) -> Result<OpenChannelResponse, LightningRpcError> {
+        let response = self
+            .connect()
+            .await?
+            .open_channel(proto::OpenChannelRequest {
+                pubkey: pubkey.serialize().to_vec(),
+                host,
+                channel_size_sats,
+                push_amount_sats,
+            })
+            .await
+            .map_err(|status| LightningRpcError::FailedToOpenChannel {
+                failure_reason: format!("Failed to open channel {status:?}"),
+            })?
+            .into_inner();
+
+        Ok(OpenChannelResponse {
+            funding_txid: response.funding_txid,
+        })
+    }
+
+    async fn close_channels_with_peer(
+        &self,
+        CloseChannelsWithPeerRequest {
+            pubkey,
+            force,
+            sats_per_vbyte,
+        }: CloseChannelsWithPeerRequest,
+    ) -> Result<CloseChannelsWithPeerResponse, LightningRpcError> {
+        let response = self
+            .connect()
+            .await?
+            .close_channels_with_peer(proto::CloseChannelsWithPeerRequest {
+                pubkey: pubkey.serialize().to_vec(),
+                force,
+                sats_per_vbyte,
+            })
+            .await
+            .map_err(|status| LightningRpcError::FailedToCloseChannelsWithPeer {
+                failure_reason: format!("Failed to close channels {status:?}"),
+            })?
+            .into_inner();
+
+        Ok(CloseChannelsWithPeerResponse {
+            num_channels_closed: response.num_channels_closed,
+        })
+    }
+
+    async fn list_channels(&self) -> Result<ListChannelsResponse, LightningRpcError> {
+        let response = self
+            .connect()
+            .await?
+            .list_channels(proto::ListChannelsRequest {})
+            .await
+            .map_err(|status| LightningRpcError::FailedToListChannels {
+                failure_reason: format!("Failed to list channels {status:?}"),
+            })?
+            .into_inner();
+
+        let channels = response
+            .channels
+            .into_iter()
+            .map(|channel| {
+                Ok(ChannelInfo {
+                    remote_pubkey: PublicKey::from_slice(&channel.remote_pubkey).map_err(|e| {
+                        LightningRpcError::FailedToListChannels {
+                            failure_reason: format!("Failed to parse public key {e:?}"),
+                        }
+                    })?,
+                    channel_size_sats: channel.channel_size_sats,
+                    outbound_liquidity_sats: channel.outbound_liquidity_sats,
+                    inbound_liquidity_sats: channel.inbound_liquidity_sats,
+                    is_active: channel.is_active,
+                    funding_outpoint: channel
+                        .funding_outpoint
+                        .and_then(|outpoint| outpoint.parse::<OutPoint>().ok()),
+                    remote_node_alias: channel.remote_node_alias,
+                })
+            })
+            .collect::<Result<Vec<_>, LightningRpcError>>()?;
+
+        Ok(ListChannelsResponse { channels })
+    }
+
+    async fn get_balances(&self) -> Result<GetBalancesResponse, LightningRpcError> {
+        let response = self
+            .connect()
+            .await?
+            .get_balances(proto::GetBalancesRequest {})
+            .await
+            .map_err(|status| LightningRpcError::FailedToGetBalances {
+                failure_reason: format!("Failed to get balances {status:?}"),
+            })?
+            .into_inner();
+
+        Ok(GetBalancesResponse {
+            onchain_balance_sats: response.onchain_balance_sats,
+            lightning_balance_msats: response.lightning_balance_msats,
+            inbound_lightning_liquidity_msats: response.inbound_lightning_liquidity_msats,
+        })
+    }
+
+    async fn get_invoice(
+        &self,
+        get_invoice_request: GetInvoiceRequest,
+    ) -> Result<Option<GetInvoiceResponse>, LightningRpcError> {
+        let response = self
+            .connect()
+            .await?
+            .get_invoice(proto::GetInvoiceRequest {
+                payment_hash: get_invoice_request.payment_hash.to_byte_array().to_vec(),
+            })
+            .await
+            .map_err(|status| LightningRpcError::FailedToGetInvoice {
+                failure_reason: format!("Failed to get invoice {status:?}"),
+            })?
+            .into_inner();
+
+        Ok(response.invoice.map(|invoice| GetInvoiceResponse {
+            preimage: invoice
+                .preimage
+                .as_ref()
+                .map(|preimage| preimage.encode_hex()),
+            payment_hash: sha256::Hash::from_slice(&invoice.payment_hash).ok(),
+            amount: Amount::from_msats(invoice.amount_msat),
+            created_at: UNIX_EPOCH + Duration::from_secs(invoice.created_at_secs),
+            status: payment_status_from_proto(invoice.status()),
+        }))
+    }
+
+    async fn list_transactions(
+        &self,
+        start_secs: u64,
+        end_secs: u64,
+    ) -> Result<ListTransactionsResponse, LightningRpcError> {
+        let response = self
+            .connect()
+            .await?
+            .list_transactions(proto::ListTransactionsRequest {
+                start_secs,
+                end_secs,
+            })
+            .await
+            .map_err(|status| LightningRpcError::FailedToListTransactions {
+                failure_reason: format!("Failed to list transactions {status:?}"),
+            })?
+            .into_inner();
+
+        let mut transactions = response
+            .transactions
+            .into_iter()
+            .map(|payment| PaymentDetails {
+                payment_hash: payment
+                    .payment_hash
+                    .as_deref()
+                    .and_then(|payment_hash| sha256::Hash::from_slice(payment_hash).ok()),
+                preimage: payment.preimage.as_ref().map(ToHex::encode_hex),
+                payment_kind: PaymentKind::Bolt11,
+                amount: Amount::from_msats(payment.amount_msat),
+                direction: match payment.direction() {
+                    proto::PaymentDirection::Outbound => PaymentDirection::Outbound,
+                    proto::PaymentDirection::Inbound => PaymentDirection::Inbound,
+                },
+                status: payment_status_from_proto(payment.status()),
+                timestamp_secs: payment.timestamp_secs,
+            })
+            .collect::<Vec<_>>();
+
+        transactions.sort_by_key(|payment| payment.timestamp_secs);
+
+        Ok(ListTransactionsResponse { transactions })
+    }
+
+    fn create_offer(
+        &self,
+        _amount: Option<Amount>,
+        _description: Option<String>,
+        _expiry_secs: Option<u32>,
+        _quantity: Option<u64>,
+    ) -> Result<String, LightningRpcError> {
+        Err(LightningRpcError::Bolt12Error {
+            failure_reason: "The CLN backend does not support Bolt12 yet".to_string(),
+        })
+    }
+
+    async fn pay_offer(
+        &self,
+        _offer: String,
+        _quantity: Option<u64>,
+        _amount: Option<Amount>,
+        _payer_note: Option<String>,
+    ) -> Result<Preimage, LightningRpcError> {
+        Err(LightningRpcError::Bolt12Error {
+            failure_reason: "The CLN backend does not support Bolt12 yet".to_string(),
+        })
+    }
+
+    fn sync_wallet(&self) -> Result<(), LightningRpcError> {
+        // There is nothing explicit needed to do for syncing a CLN node
+        Ok(())
+    }
+}
+
+fn payment_status_from_proto(status: proto::PaymentStatus) -> PaymentStatus {
+    match status {
+        proto::PaymentStatus::Pending => PaymentStatus::Pending,
+        proto::PaymentStatus::Succeeded => PaymentStatus::Succeeded,
+        proto::PaymentStatus::Failed => PaymentStatus::Failed,
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,341 @@
+use std::sync::{Arc, Mutex};
+
+use bitcoin::hashes::{Hash, sha256};
+use fedimint_core::secp256k1::{PublicKey, Secp256k1, SecretKey};
+use fedimint_core::task::TaskGroup;
+use fedimint_core::util::SafeUrl;
+use fedimint_gateway_common::GetInvoiceRequest;
+use fedimint_ln_common::contracts::Preimage;
+use futures::StreamExt;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::Server;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+
+use super::GatewayClnClient;
+use super::proto::gateway_cln_server::{GatewayCln, GatewayClnServer};
+use super::proto::{self, complete_htlc_request};
+use crate::{ILnRpcClient, InterceptPaymentResponse, PaymentAction};
+
+type HtlcSender = mpsc::Sender<Result<proto::InterceptHtlcRequest, Status>>;
+
+const PAYMENT_HASH: [u8; 32] = [42; 32];
+
+fn remote_pubkey() -> PublicKey {
+    SecretKey::from_slice(&[1; 32])
+        .expect("Valid secret key")
+        .public_key(&Secp256k1::new())
+}
+
+/// Mock of the `gateway-cln-extension` plugin that intercepts a single HTLC
+/// and records how the gateway completes it.
+#[derive(Clone, Default)]
+struct MockClnExtension {
+    htlc_senders: Arc<Mutex<Vec<HtlcSender>>>,
+    completed_htlcs: Arc<Mutex<Vec<proto::CompleteHtlcRequest>>>,
+}
+
+#[tonic::async_trait]
+impl GatewayCln for MockClnExtension {
+    type RouteHtlcsStream = ReceiverStream<Result<proto::InterceptHtlcRequest, Status>>;
+
+    async fn get_node_info(
+        &self,
+        _request: Request<proto::GetNodeInfoRequest>,
+    ) -> Result<Response<proto::GetNodeInfoResponse>, Status> {
+        Ok(Response::new(proto::GetNodeInfoResponse {
+            pub_key: remote_pubkey().serialize().to_vec(),
+            alias: "mock-cln".to_string(),
+            network: "regtest".to_string(),
+            block_height: 100,
+            synced_to_chain: true,
+        }))
+    }
+
+    async fn get_route_hints(
+        &self,
+        _request: Request<proto::GetRouteHintsRequest>,
+    ) -> Result<Response<proto::GetRouteHintsResponse>, Status> {
+        Err(Status::unimplemented("get_route_hints"))
+    }
+
+    async fn pay_invoice(
+        &self,
+        _request: Request<proto::PayInvoiceRequest>,
+    ) -> Result<Response<proto::PayInvoiceResponse>, Status> {
+        Err(Status::unimplemented("pay_invoice"))
+    }
+
+    async fn route_htlcs(
+        &self,
+        _request: Request<proto::RouteHtlcsRequest>,
+    ) -> Result<Response<Self::RouteHtlcsStream>, Status> {
+        let (sender, receiver) = mpsc::channel(1);
+
+        sender
+            .send(Ok(proto::InterceptHtlcRequest {
+                payment_hash: PAYMENT_HASH.to_vec(),
+                amount_msat: 1_000,
+                expiry: 500,
+                incoming_chan_id: 1,
+                short_channel_id: Some(2),
+                htlc_id: 3,
+            }))
+            .await
+            .expect("Receiver is not dropped");
+
+        // Keep the stream open for the lifetime of the mock like the extension does
+        self.htlc_senders
+            .lock()
+            .expect("Lock is not poisoned")
+            .push(sender);
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    async fn complete_htlc(
+        &self,
+        request: Request<proto::CompleteHtlcRequest>,
+    ) -> Result<Response<proto::CompleteHtlcResponse>, Status> {
+        self.completed_htlcs
+            .lock()
+            .expect("Lock is not poisoned")
+            .push(request.into_inner());
+
+        Ok(Response::new(proto::CompleteHtlcResponse {}))
+    }
+
+    async fn create_invoice(
+        &self,
+        _request: Request<proto::CreateInvoiceRequest>,
+    ) -> Result<Response<proto::CreateInvoiceResponse>, Status> {
+        Err(Status::unimplemented("create_invoice"))
+    }
+
+    async fn get_ln_onchain_address(
+        &self,
+        _request: Request<proto::GetLnOnchainAddressRequest>,
+    ) -> Result<Response<proto::GetLnOnchainAddressResponse>, Status> {
+        Err(Status::unimplemented("get_ln_onchain_address"))
+    }
+
+    async fn send_onchain(
+        &self,
+        _request: Request<proto::SendOnchainRequest>,
+    ) -> Result<Response<proto::SendOnchainResponse>, Status> {
+        Err(Status::unimplemented("send_onchain"))
+    }
+
+    async fn open_channel(
+        &self,
+        _request: Request<proto::OpenChannelRequest>,
+    ) -> Result<Response<proto::OpenChannelResponse>, Status> {
+        Err(Status::unimplemented("open_channel"))
+    }
+
+    async fn close_channels_with_peer(
+        &self,
+        _request: Request<proto::CloseChannelsWithPeerRequest>,
+    ) -> Result<Response<proto::CloseChannelsWithPeerResponse>, Status> {
+        Err(Status::unimplemented("close_channels_with_peer"))
+    }
+
+    async fn list_channels(
+        &self,
+        _request: Request<proto::ListChannelsRequest>,
+    ) -> Result<Response<proto::ListChannelsResponse>, Status> {
+        Ok(Response::new(proto::ListChannelsResponse {
+            channels: vec![proto::ChannelInfo {
+                remote_pubkey: remote_pubkey().serialize().to_vec(),
+                channel_size_sats: 100_000,
+                outbound_liquidity_sats: 60_000,
+                inbound_liquidity_sats: 40_000,
+                is_active: true,
+                funding_outpoint: Some(format!("{}:0", "11".repeat(32))),
+                remote_node_alias: None,
+            }],
+        }))
+    }
+
+    async fn get_balances(
+        &self,
+        _request: Request<proto::GetBalancesRequest>,
+    ) -> Result<Response<proto::GetBalancesResponse>, Status> {
+        Ok(Response::new(proto::GetBalancesResponse {
+            onchain_balance_sats: 50_000,
+            lightning_balance_msats: 60_000_000,
+            inbound_lightning_liquidity_msats: 40_000_000,
+        }))
+    }
+
+    async fn get_invoice(
+        &self,
+        _request: Request<proto::GetInvoiceRequest>,
+    ) -> Result<Response<proto::GetInvoiceResponse>, Status> {
+        Ok(Response::new(proto::GetInvoiceResponse { invoice: None }))
+    }
+
+    async fn list_transactions(
+        &self,
+        _request: Request<proto::ListTransactionsRequest>,
+    ) -> Result<Response<proto::ListTransactionsResponse>, Status> {
+        Ok(Response::new(proto::ListTransactionsResponse {
+            transactions: vec![
+                proto::Payment {
+                    payment_hash: Some(PAYMENT_HASH.to_vec()),
+                    preimage: None,
+                    amount_msat: 2_000,
+                    direction: proto::PaymentDirection::Inbound.into(),
+                    status: proto::PaymentStatus::Pending.into(),
+                    timestamp_secs: 20,
+                },
+                proto::Payment {
+                    payment_hash: None,
+                    preimage: Some(vec![1; 32]),
+                    amount_msat: 1_000,
+                    direction: proto::PaymentDirection::Outbound.into(),
+                    status: proto::PaymentStatus::Succeeded.into(),
+                    timestamp_secs: 10,
+                },
+            ],
+        }))
+    }
+}
+
+async fn start_mock_extension(mock: MockClnExtension, task_group: &TaskGroup) -> GatewayClnClient {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Failed to bind mock extension");
+
+    let address = SafeUrl::parse(&format!(
+        "http://{}",
+        listener.local_addr().expect("Listener has an address")
+    ))
+    .expect("Valid url");
+
+    task_group.spawn_cancellable("mock cln extension", async move {
+        Server::builder()
+            .add_service(GatewayClnServer::new(mock))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .expect("Mock extension failed");
+    });
+
+    GatewayClnClient::new(address)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn routes_and_completes_intercepted_htlcs() {
+    let task_group = TaskGroup::new();
+    let mock = MockClnExtension::default();
+    let client = start_mock_extension(mock.clone(), &task_group).await;
+
+    let (mut stream, client) = Box::new(client)
+        .route_htlcs(&task_group)
+        .await
+        .expect("Failed to route HTLCs");
+
+    let htlc = stream.next().await.expect("HTLC is intercepted");
+
+    assert_eq!(
+        htlc.payment_hash,
+        sha256::Hash::from_byte_array(PAYMENT_HASH)
+    );
+    assert_eq!(htlc.amount_msat, 1_000);
+    assert_eq!(htlc.expiry, 500);
+    assert_eq!(htlc.incoming_chan_id, 1);
+    assert_eq!(htlc.short_channel_id, Some(2));
+    assert_eq!(htlc.htlc_id, 3);
+
+    client
+        .complete_htlc(InterceptPaymentResponse {
+            incoming_chan_id: htlc.incoming_chan_id,
+            htlc_id: htlc.htlc_id,
+            payment_hash: htlc.payment_hash,
+            action: PaymentAction::Settle(Preimage([7; 32])),
+        })
+        .await
+        .expect("Failed to complete HTLC");
+
+    let completed_htlcs = mock
+        .completed_htlcs
+        .lock()
+        .expect("Lock is not poisoned")
+        .clone();
+
+    assert_eq!(completed_htlcs.len(), 1);
+    assert_eq!(completed_htlcs[0].incoming_chan_id, 1);
+    assert_eq!(completed_htlcs[0].htlc_id, 3);
+    assert_eq!(
+        completed_htlcs[0].action,
+        Some(complete_htlc_request::Action::Settle(
+            complete_htlc_request::Settle {
+                preimage: vec![7; 32],
+            }
+        ))
+    );
+
+    task_group
+        .shutdown_join_all(None)
+        .await
+        .expect("Failed to shut down");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn maps_node_state_from_extension() {
+    let task_group = TaskGroup::new();
+    let client = start_mock_extension(MockClnExtension::default(), &task_group).await;
+
+    let info = client.info().await.expect("Failed to get node info");
+
+    assert_eq!(info.pub_key, remote_pubkey());
+    assert_eq!(info.network, "regtest");
+    assert!(info.synced_to_chain);
+
+    let channels = client
+        .list_channels()
+        .await
+        .expect("Failed to list channels")
+        .channels;
+
+    assert_eq!(channels.len(), 1);
+    assert_eq!(channels[0].remote_pubkey, remote_pubkey());
+    assert_eq!(channels[0].outbound_liquidity_sats, 60_000);
+    assert!(channels[0].funding_outpoint.is_some());
+
+    let balances = client.get_balances().await.expect("Failed to get balances");
+
+    assert_eq!(balances.onchain_balance_sats, 50_000);
+    assert_eq!(balances.lightning_balance_msats, 60_000_000);
+
+    let transactions = client
+        .list_transactions(0, 100)
+        .await
+        .expect("Failed to list transactions")
+        .transactions;
+
+    // Transactions are sorted by their timestamp
+    assert_eq!(transactions.len(), 2);
+    assert_eq!(transactions[0].timestamp_secs, 10);
+    assert_eq!(transactions[0].preimage, Some("01".repeat(32)));
+    assert_eq!(
+        transactions[1].payment_hash,
+        Some(sha256::Hash::from_byte_array(PAYMENT_HASH))
+    );
+
+    let invoice = client
+        .get_invoice(GetInvoiceRequest {
+            payment_hash: sha256::Hash::from_byte_array(PAYMENT_HASH),
+        })
+        .await
+        .expect("Failed to get invoice");
+
+    assert!(invoice.is_none());
+
+    task_group
+        .shutdown_join_all(None)
+        .await
+        .expect("Failed to shut down");
+}
@@ -264,6 +264,88 @@ async fn handle_next_event(
             warn!(err = %err.fmt_compact(), "LDK could not mark event handled");
         }
     }
+
+    /// Pays `invoice`, using `amount` if the invoice is amountless.
+    async fn pay_bolt11(
+        &self,
+        invoice: Bolt11Invoice,
+        amount: Option<Amount>,
+        max_delay: u64,
+        max_fee: Amount,
+    ) -> Result<PayInvoiceResponse, LightningRpcError> {
+        let payment_id = PaymentId(*invoice.payment_hash().as_byte_array());
+
+        // Lock by the payment hash to prevent multiple simultaneous calls with the same
+        // invoice from executing. This prevents `ldk-node::Bolt11Payment::send()` from
+        // being called multiple times with the same invoice. This is important because
+        // `ldk-node::Bolt11Payment::send()` is not idempotent, but this function must
+        // be idempotent.
+        let _payment_lock_guard = self
+            .outbound_lightning_payment_lock_pool
+            .async_lock(payment_id)
+            .await;
+
+        // If a payment is not known to the node we can initiate it, and if it is known
+        // we can skip calling `ldk-node::Bolt11Payment::send()` and wait for the
+        // payment to complete. The lock guard above guarantees that this block is only
+        // executed once at a time for a given payment hash, ensuring that there is no
+        // race condition between checking if a payment is known and initiating a new
+        // payment if it isn't.
+        if self.node.payment(&payment_id).is_none() {
+            let sending_parameters = Some(SendingParameters {
+                max_total_routing_fee_msat: Some(Some(max_fee.msats)),
+                max_total_cltv_expiry_delta: Some(max_delay as u32),
+                max_path_count: None,
+                max_channel_saturation_power_of_half: None,
+            });
+
+            let bolt11_payment = self.node.bolt11_payment();
+
+            assert_eq!(
+                match amount {
+                    Some(amount) =>
+                        bolt11_payment
+                            .send_using_amount(&invoice, amount.msats, sending_parameters,),
+                    None => bolt11_payment.send(&invoice, sending_parameters),
+                }
+                // TODO: Investigate whether all error types returned by `Bolt11Payment::send()`
+                // result in idempotency.
+                .map_err(|e| LightningRpcError::FailedPayment {
+                    failure_reason: format!("LDK payment failed to initialize: {e:?}"),
+                })?,
+                payment_id
+            );
+        }
+
+        // TODO: Find a way to avoid looping/polling to know when a payment is
+        // completed. `ldk-node` provides `PaymentSuccessful` and `PaymentFailed`
+        // events, but interacting with the node event queue here isn't
+        // straightforward.
+        loop {
+            if let Some(payment_details) = self.node.payment(&payment_id) {
+                match payment_details.status {
+                    PaymentStatus::Pending => {}
+                    PaymentStatus::Succeeded => {
+                        if let PaymentKind::Bolt11 {
+                            preimage: Some(preimage),
+                            ..
+                        } = payment_details.kind
+                        {
+                            return Ok(PayInvoiceResponse {
+                                preimage: Preimage(preimage.0),
+                            });
+                        }
+                    }
+                    PaymentStatus::Failed => {
+                        return Err(LightningRpcError::FailedPayment {
+                            failure_reason: "LDK payment failed".to_string(),
+                        });
+                    }
+                }
+            }
+            fedimint_core::runtime::sleep(Duration::from_millis(100)).await;
+        }
+    }
 }
 
 impl Drop for GatewayLdkClient {
@@ -326,74 +408,18 @@ async fn pay(
         max_delay: u64,
         max_fee: Amount,
     ) -> Result<PayInvoiceResponse, LightningRpcError> {
-        let payment_id = PaymentId(*invoice.payment_hash().as_byte_array());
-
-        // Lock by the payment hash to prevent multiple simultaneous calls with the same
-        // invoice from executing. This prevents `ldk-node::Bolt11Payment::send()` from
-        // being called multiple times with the same invoice. This is important because
-        // `ldk-node::Bolt11Payment::send()` is not idempotent, but this function must
-        // be idempotent.
-        let _payment_lock_guard = self
-            .outbound_lightning_payment_lock_pool
-            .async_lock(payment_id)
-            .await;
-
-        // If a payment is not known to the node we can initiate it, and if it is known
-        // we can skip calling `ldk-node::Bolt11Payment::send()` and wait for the
-        // payment to complete. The lock guard above guarantees that this block is only
-        // executed once at a time for a given payment hash, ensuring that there is no
-        // race condition between checking if a payment is known and initiating a new
-        // payment if it isn't.
-        if self.node.payment(&payment_id).is_none() {
-            assert_eq!(
-                self.node
-                    .bolt11_payment()
-                    .send(
-                        &invoice,
-                        Some(SendingParameters {
-                            max_total_routing_fee_msat: Some(Some(max_fee.msats)),
-                            max_total_cltv_expiry_delta: Some(max_delay as u32),
-                            max_path_count: None,
-                            max_channel_saturation_power_of_half: None,
-                        }),
-                    )
-                    // TODO: Investigate whether all error types returned by `Bolt11Payment::send()`
-                    // result in idempotency.
-                    .map_err(|e| LightningRpcError::FailedPayment {
-                        failure_reason: format!("LDK payment failed to initialize: {e:?}"),
-                    })?,
-                payment_id
-            );
-        }
+        self.pay_bolt11(invoice, None, max_delay, max_fee).await
+    }
 
-        // TODO: Find a way to avoid looping/polling to know when a payment is
-        // completed. `ldk-node` provides `PaymentSuccessful` and `PaymentFailed`
-        // events, but interacting with the node event queue here isn't
-        // straightforward.
-        loop {
-            if let Some(payment_details) = self.node.payment(&payment_id) {
-                match payment_details.status {
-                    PaymentStatus::Pending => {}
-                    PaymentStatus::Succeeded => {
-                        if let PaymentKind::Bolt11 {
-                            preimage: Some(preimage),
-                            ..
-                        } = payment_details.kind
-                        {
-                            return Ok(PayInvoiceResponse {
-                                preimage: Preimage(preimage.0),
-                            });
-                        }
-                    }
-                    PaymentStatus::Failed => {
-                        return Err(LightningRpcError::FailedPayment {
-                            failure_reason: "LDK payment failed".to_string(),
-                        });
-                    }
-                }
-            }
-            fedimint_core::runtime::sleep(Duration::from_millis(100)).await;
-        }
+    async fn pay_amountless(
+        &self,
+        invoice: Bolt11Invoice,
+        amount: Amount,
+        max_delay: u64,
+        max_fee: Amount,
+    ) -> Result<PayInvoiceResponse, LightningRpcError> {
+        self.pay_bolt11(invoice, Some(amount), max_delay, max_fee)
+            .await
     }
 
     async fn route_htlcs<'a>(
@@ -475,15 +501,24 @@ async fn create_invoice(
             None => Bolt11InvoiceDescription::Direct(Description::empty()),
         };
 
-        let invoice = match payment_hash_or {
-            Some(payment_hash) => self.node.bolt11_payment().receive_for_hash(
-                create_invoice_request.amount_msat,
+        // A zero amount requests an amountless invoice
+        let bolt11_payment = self.node.bolt11_payment();
+        let invoice = match (payment_hash_or, create_invoice_request.amount_msat) {
+            (Some(payment_hash), 0) => bolt11_payment.receive_variable_amount_for_hash(
+                &description,
+                create_invoice_request.expiry_secs,
+                payment_hash,
+            ),
+            (Some(payment_hash), amount_msat) => bolt11_payment.receive_for_hash(
+                amount_msat,
                 &description,
                 create_invoice_request.expiry_secs,
                 payment_hash,
             ),
-            None => self.node.bolt11_payment().receive(
-                create_invoice_request.amount_msat,
+            (None, 0) => bolt11_payment
+                .receive_variable_amount(&description, create_invoice_request.expiry_secs),
+            (None, amount_msat) => bolt11_payment.receive(
+                amount_msat,
                 &description,
                 create_invoice_request.expiry_secs,
             ),
@@ -724,11 +759,7 @@ async fn get_invoice(
                 GetInvoiceResponse {
                     preimage: preimage.map(|p| p.to_string()),
                     payment_hash,
-                    amount: Amount::from_msats(
-                        details
-                            .amount_msat
-                            .expect("amountless invoices are not supported"),
-                    ),
+                    amount: details.amount_msat.map_or(Amount::ZERO, Amount::from_msats),
                     created_at: UNIX_EPOCH + Duration::from_secs(details.latest_update_timestamp),
                     status,
                 }
@@ -769,11 +800,7 @@ async fn list_transactions(
                     payment_hash,
                     preimage: preimage.map(|p| p.to_string()),
                     payment_kind,
-                    amount: Amount::from_msats(
-                        details
-                            .amount_msat
-                            .expect("amountless invoices are not supported"),
-                    ),
+                    amount: details.amount_msat.map_or(Amount::ZERO, Amount::from_msats),
                     direction,
                     status,
                     timestamp_secs: details.latest_update_timestamp,
@@ -1,3 +1,4 @@
+pub mod cln;
 pub mod ldk;
 pub mod lnd;
 
@@ -133,6 +134,22 @@ async fn pay(
         .await
     }
 
+    /// Attempts to pay `amount` to an amountless invoice using the lightning
+    /// node, waiting for the payment to complete and returning the preimage.
+    ///
+    /// The same caller and implementor restrictions as for
+    /// [`ILnRpcClient::pay`] apply.
+    async fn pay_amountless(
+        &self,
+        invoice: Bolt11Invoice,
+        amount: Amount,
+        max_delay: u64,
+        max_fee: Amount,
+    ) -> Result<PayInvoiceResponse, LightningRpcError> {
+        self.pay_private(PrunedInvoice::new(&invoice, amount), max_delay, max_fee)
+            .await
+    }
+
     /// Attempts to pay an invoice using the lightning node, waiting for the
     /// payment to complete and returning the preimage.
     ///
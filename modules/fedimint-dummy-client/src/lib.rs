@@ -316,15 +316,20 @@ impl DummyClientModule {
     /// The dummy server accepts any public key, so this can be used to create
     /// funds out of thin air that get converted to e-cash as change.
     pub fn create_input(&self, amount: Amount) -> ClientInputBundle {
+        self.create_unit_input(amount, AmountUnit::BITCOIN)
+    }
+
+    /// Like [`Self::create_input`], but creates funds of `unit`
+    pub fn create_unit_input(&self, amount: Amount, unit: AmountUnit) -> ClientInputBundle {
         let keypair = Keypair::new(&Secp256k1::new(), &mut rand::rngs::OsRng);
 
         let client_input = ClientInput {
             input: DummyInput {
                 amount,
-                unit: AmountUnit::BITCOIN,
+                unit,
                 pub_key: keypair.public_key(),
             },
-            amounts: Amounts::new_bitcoin(amount),
+            amounts: Amounts::new_custom(unit, amount),
             keys: vec![keypair],
         };
 
@@ -215,7 +215,7 @@ async fn process_input<'a, 'b, 'c>(
 
         Ok(InputMeta {
             amount: TransactionItemAmounts {
-                amounts: Amounts::new_bitcoin(input.amount),
+                amounts: Amounts::new_custom(input.unit, input.amount),
                 fees: Amounts::ZERO,
             },
             pub_key: input.pub_key,
@@ -232,7 +232,7 @@ async fn process_output<'a, 'b>(
             .await;
 
         Ok(TransactionItemAmounts {
-            amounts: Amounts::new_bitcoin(output.amount),
+            amounts: Amounts::new_custom(output.unit, output.amount),
             fees: Amounts::ZERO,
         })
     }
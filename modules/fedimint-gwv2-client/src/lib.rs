@@ -37,10 +37,11 @@
 use fedimint_core::{Amount, PeerId, apply, async_trait_maybe_send, secp256k1};
 use fedimint_lightning::{InterceptPaymentResponse, LightningRpcError};
 use fedimint_lnv2_common::config::LightningClientConfig;
-use fedimint_lnv2_common::contracts::{IncomingContract, PaymentImage};
+use fedimint_lnv2_common::contracts::{AmountlessIncomingContract, IncomingContract, PaymentImage};
 use fedimint_lnv2_common::gateway_api::SendPaymentPayload;
 use fedimint_lnv2_common::{
-    LightningCommonInit, LightningInvoice, LightningModuleTypes, LightningOutput, LightningOutputV0,
+    LightningCommonInit, LightningInvoice, LightningModuleTypes, LightningOutput,
+    LightningOutputV0, MINIMUM_INCOMING_CONTRACT_AMOUNT,
 };
 use futures::StreamExt;
 use lightning_invoice::Bolt11Invoice;
@@ -48,6 +49,7 @@
 use secp256k1::schnorr::Signature;
 use send_sm::{SendSMState, SendStateMachine};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 use tpe::{AggregatePublicKey, PublicKeyShare};
 use tracing::{info, warn};
 
@@ -238,6 +240,12 @@ fn operation_id(&self) -> OperationId {
     }
 }
 
+/// Returned by [`GatewayClientModuleV2::send_payment`] if the federation can
+/// not be reached to confirm the outgoing contract.
+#[derive(Debug, Error)]
+#[error("The gateway can not reach the federation")]
+pub struct FederationUnreachable;
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, Decodable, Encodable)]
 pub enum FinalReceiveState {
     Rejected,
@@ -292,7 +300,7 @@ pub async fn send_payment(
             .module_api
             .outgoing_contract_expiration(payload.outpoint)
             .await
-            .map_err(|_| anyhow!("The gateway can not reach the federation"))?
+            .map_err(|_| FederationUnreachable)?
             .ok_or(anyhow!("The outgoing contract has not yet been confirmed"))?;
 
         ensure!(
@@ -300,14 +308,13 @@ pub async fn send_payment(
             "Contract Id returned by the federation does not match contract in request"
         );
 
-        let (payment_hash, amount) = match &payload.invoice {
-            LightningInvoice::Bolt11(invoice) => (
-                invoice.payment_hash(),
-                invoice
-                    .amount_milli_satoshis()
-                    .ok_or(anyhow!("Invoice is missing amount"))?,
-            ),
-        };
+        let payment_hash = payload.invoice.bolt11().payment_hash();
+
+        let amount = payload
+            .invoice
+            .amount()
+            .ok_or(anyhow!("Invoice is missing amount"))?
+            .msats;
 
         ensure!(
             PaymentImage::Hash(*payment_hash) == payload.contract.payment_image,
@@ -547,6 +554,26 @@ pub async fn relay_direct_swap(
         Ok(self.await_receive(operation_id).await)
     }
 
+    /// Creates the incoming contract for a payment of an amountless invoice
+    /// once the amount of the contract is known.
+    pub fn create_amountless_incoming_contract(
+        &self,
+        contract: &AmountlessIncomingContract,
+        contract_amount: Amount,
+    ) -> anyhow::Result<IncomingContract> {
+        ensure!(
+            contract.refund_pk == self.keypair.public_key(),
+            "The incoming contract is keyed to another gateway"
+        );
+
+        ensure!(
+            contract_amount >= MINIMUM_INCOMING_CONTRACT_AMOUNT,
+            "The incoming contract amount is below the minimum"
+        );
+
+        Ok(contract.contract(self.cfg.tpe_agg_pk, contract_amount))
+    }
+
     pub async fn await_receive(&self, operation_id: OperationId) -> FinalReceiveState {
         let mut stream = self.notifier.subscribe(operation_id).await;
 
@@ -626,12 +653,14 @@ pub trait IGatewayClientV2: Debug + Send + Sync {
     async fn is_direct_swap(
         &self,
         invoice: &Bolt11Invoice,
+        amount: Amount,
     ) -> anyhow::Result<Option<(IncomingContract, ClientHandleArc)>>;
 
-    /// Initiates a payment over the Lightning network.
+    /// Initiates a payment over the Lightning network. For amountless invoices
+    /// the amount chosen by the payer is paid.
     async fn pay(
         &self,
-        invoice: Bolt11Invoice,
+        invoice: LightningInvoice,
         max_delay: u64,
         max_fee: Amount,
     ) -> Result<[u8; 32], LightningRpcError>;
@@ -156,12 +156,14 @@ async fn send_payment(
         invoice: LightningInvoice,
         contract: OutgoingContract,
     ) -> Result<PaymentResponse, Cancelled> {
-        let LightningInvoice::Bolt11(invoice) = invoice;
+        let amount = invoice
+            .amount()
+            .expect("The invoice amount has been previously checked");
 
         // The following two checks may fail in edge cases since they have inherent
         // timing assumptions. Therefore, they may only be checked after we have created
         // the state machine such that we can cancel the contract.
-        if invoice.is_expired() {
+        if invoice.bolt11().is_expired() {
             return Err(Cancelled::InvoiceExpired);
         }
 
@@ -177,10 +179,10 @@ async fn send_payment(
         // the LNv1 protocol and if the gateway supports the target federation.
         // If it does, we can fund an LNv1 incoming contract to satisfy the LNv2
         // outgoing payment.
-        if let Some(client) = context.gateway.is_lnv1_invoice(&invoice).await {
+        if let Some(client) = context.gateway.is_lnv1_invoice(invoice.bolt11()).await {
             let final_state = context
                 .gateway
-                .relay_lnv1_swap(client.value(), &invoice)
+                .relay_lnv1_swap(client.value(), invoice.bolt11())
                 .await;
             return match final_state {
                 Ok(final_receive_state) => match final_receive_state {
@@ -198,7 +200,7 @@ async fn send_payment(
 
         match context
             .gateway
-            .is_direct_swap(&invoice)
+            .is_direct_swap(invoice.bolt11(), amount)
             .await
             .map_err(|e| Cancelled::RegistrationError(e.to_string()))?
         {
@@ -206,12 +208,7 @@ async fn send_payment(
                 match client
                     .get_first_module::<GatewayClientModuleV2>()
                     .expect("Must have client module")
-                    .relay_direct_swap(
-                        contract,
-                        invoice
-                            .amount_milli_satoshis()
-                            .expect("amountless invoices are not supported"),
-                    )
+                    .relay_direct_swap(contract, amount.msats)
                     .await
                 {
                     Ok(final_receive_state) => match final_receive_state {
@@ -1243,6 +1243,7 @@ fn record_funded_contract_metric(updated_contract_account: &ContractAccount) {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeSet;
     use std::time::Duration;
 
     use assert_matches::assert_matches;
@@ -1328,6 +1329,9 @@ fn build_configs() -> (Vec<LightningConfig>, LightningClientConfig) {
         let args = fedimint_server_core::ConfigGenModuleArgs {
             network: Network::Regtest,
             disable_base_fees: false,
+            mint_custom_units: BTreeSet::new(),
+            wallet_peg_out_batch_window: None,
+            wallet_taproot_peg_in: false,
         };
         let server_cfg = ServerModuleInit::trusted_dealer_gen(&LightningInit, &peers, &args);
 
@@ -9,7 +9,7 @@
 use serde_json::Value;
 
 use crate::api::LightningFederationApi;
-use crate::{Bolt11InvoiceDescription, LightningClientModule};
+use crate::{Bolt11InvoiceDescription, GatewaySelectionPolicy, LightningClientModule};
 
 #[derive(Parser, Serialize)]
 enum Opts {
@@ -17,6 +17,9 @@ enum Opts {
     /// route with, otherwise a gateway will be selected automatically.
     Send {
         invoice: Bolt11Invoice,
+        /// Amount to pay, required for amountless invoices only
+        #[arg(long)]
+        amount: Option<Amount>,
         #[arg(long)]
         gateway: Option<SafeUrl>,
     },
@@ -26,9 +29,14 @@ enum Opts {
     /// generate the invoice, otherwise a gateway will be selected
     /// automatically.
     Receive {
-        amount: Amount,
+        #[arg(required_unless_present = "amountless_trusting_gateway")]
+        amount: Option<Amount>,
         #[arg(long)]
         gateway: Option<SafeUrl>,
+        /// Request an amountless invoice. The gateway learns the preimage and
+        /// has to be trusted to fund the incoming contract.
+        #[arg(long, conflicts_with = "amount")]
+        amountless_trusting_gateway: bool,
     },
     /// Await the final state of the receive operation.
     AwaitReceive { operation_id: OperationId },
@@ -70,6 +78,13 @@ enum GatewaysOpts {
     Add { gateway: SafeUrl },
     /// Remove a vetted gateway.
     Remove { gateway: SafeUrl },
+    /// List the scores of the gateways we have interacted with.
+    Scores,
+    /// Show or set the policy used to select a gateway: cheapest,
+    /// most-reliable or weighted.
+    Policy {
+        policy: Option<GatewaySelectionPolicy>,
+    },
 }
 
 pub(crate) async fn handle_cli_command(
@@ -79,25 +94,48 @@ pub(crate) async fn handle_cli_command(
     let opts = Opts::parse_from(iter::once(&ffi::OsString::from("lnv2")).chain(args.iter()));
 
     let value = match opts {
-        Opts::Send { gateway, invoice } => {
-            json(lightning.send(invoice, gateway, Value::Null).await?)
-        }
-        Opts::AwaitSend { operation_id } => json(
+        Opts::Send {
+            invoice,
+            amount,
+            gateway,
+        } => json(
             lightning
-                .await_final_send_operation_state(operation_id)
+                .send(invoice, amount, gateway, Value::Null)
                 .await?,
         ),
-        Opts::Receive { amount, gateway } => json(
+        Opts::AwaitSend { operation_id } => json(
             lightning
-                .receive(
-                    amount,
-                    3600,
-                    Bolt11InvoiceDescription::Direct(String::new()),
-                    gateway,
-                    Value::Null,
-                )
+                .await_final_send_operation_state(operation_id)
                 .await?,
         ),
+        Opts::Receive {
+            amount,
+            gateway,
+            amountless_trusting_gateway,
+        } => {
+            let description = Bolt11InvoiceDescription::Direct(String::new());
+
+            if amountless_trusting_gateway {
+                json(
+                    lightning
+                        .receive_amountless_trusting_gateway(
+                            3600,
+                            description,
+                            gateway,
+                            Value::Null,
+                        )
+                        .await?,
+                )
+            } else {
+                let amount = amount.expect("Clap requires an amount without the amountless flag");
+
+                json(
+                    lightning
+                        .receive(amount, 3600, description, gateway, Value::Null)
+                        .await?,
+                )
+            }
+        }
         Opts::AwaitReceive { operation_id } => json(
             lightning
                 .await_final_receive_operation_state(operation_id)
@@ -130,6 +168,14 @@ pub(crate) async fn handle_cli_command(
 
                 json(lightning.module_api.remove_gateway(auth, gateway).await?)
             }
+            GatewaysOpts::Scores => json(lightning.gateway_scores().await),
+            GatewaysOpts::Policy { policy } => {
+                if let Some(policy) = policy {
+                    lightning.set_gateway_selection_policy(policy).await;
+                }
+
+                json(lightning.gateway_selection_policy().await)
+            }
         },
     };
 
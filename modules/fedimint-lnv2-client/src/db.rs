@@ -1,14 +1,21 @@
+use bitcoin::hashes::sha256;
+use fedimint_core::core::OperationId;
 use fedimint_core::encoding::{Decodable, Encodable};
-use fedimint_core::impl_db_record;
 use fedimint_core::secp256k1::PublicKey;
 use fedimint_core::util::SafeUrl;
+use fedimint_core::{impl_db_lookup, impl_db_record};
 use strum::EnumIter;
 
+use crate::scoring::{GatewayScore, GatewaySelectionPolicy};
+
 #[repr(u8)]
 #[derive(Clone, EnumIter, Debug)]
 pub enum DbKeyPrefix {
     Gateway = 0x41,
     IncomingContractStreamIndex = 0x42,
+    AmountlessReceive = 0x43,
+    GatewayScore = 0x44,
+    GatewaySelectionPolicy = 0x45,
     #[allow(dead_code)]
     /// Prefixes between 0xb0..=0xcf shall all be considered allocated for
     /// historical and future external use
@@ -38,3 +45,37 @@ pub enum DbKeyPrefix {
     value = u64,
     db_prefix = DbKeyPrefix::IncomingContractStreamIndex
 );
+
+/// Maps the payment hash of an amountless invoice to its receive operation
+/// until the gateway has funded the incoming contract.
+#[derive(Debug, Encodable, Decodable)]
+pub struct AmountlessReceiveKey(pub sha256::Hash);
+
+impl_db_record!(
+    key = AmountlessReceiveKey,
+    value = OperationId,
+    db_prefix = DbKeyPrefix::AmountlessReceive
+);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct GatewayScoreKey(pub SafeUrl);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct GatewayScorePrefix;
+
+impl_db_record!(
+    key = GatewayScoreKey,
+    value = GatewayScore,
+    db_prefix = DbKeyPrefix::GatewayScore
+);
+
+impl_db_lookup!(key = GatewayScoreKey, query_prefix = GatewayScorePrefix);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct GatewaySelectionPolicyKey;
+
+impl_db_record!(
+    key = GatewaySelectionPolicyKey,
+    value = GatewaySelectionPolicy,
+    db_prefix = DbKeyPrefix::GatewaySelectionPolicy
+);
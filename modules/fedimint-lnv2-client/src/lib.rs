@@ -5,6 +5,7 @@
 #![allow(clippy::must_use_candidate)]
 
 pub use fedimint_lnv2_common as common;
+pub use scoring::{GatewayScore, GatewaySelectionPolicy};
 
 mod api;
 #[cfg(feature = "cli")]
@@ -12,15 +13,20 @@
 mod db;
 pub mod events;
 mod receive_sm;
+mod scoring;
 mod send_sm;
 
 use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_stream::stream;
 use bitcoin::hashes::{Hash, sha256};
 use bitcoin::secp256k1;
-use db::{DbKeyPrefix, GatewayKey, IncomingContractStreamIndexKey};
+use db::{
+    AmountlessReceiveKey, DbKeyPrefix, GatewayKey, GatewayScorePrefix, GatewaySelectionPolicyKey,
+    IncomingContractStreamIndexKey,
+};
 use fedimint_api_client::api::DynModuleApi;
 use fedimint_client_module::module::init::{ClientModuleInit, ClientModuleInitArgs};
 use fedimint_client_module::module::recovery::NoModuleBackup;
@@ -39,13 +45,15 @@
     Amounts, ApiAuth, ApiVersion, CommonModuleInit, ModuleCommon, ModuleInit, MultiApiVersion,
 };
 use fedimint_core::secp256k1::SECP256K1;
-use fedimint_core::task::TaskGroup;
-use fedimint_core::time::duration_since_epoch;
+use fedimint_core::task::{TaskGroup, timeout};
+use fedimint_core::time::{duration_since_epoch, now};
 use fedimint_core::util::SafeUrl;
 use fedimint_core::{Amount, PeerId, apply, async_trait_maybe_send};
 use fedimint_derive_secret::{ChildId, DerivableSecret};
 use fedimint_lnv2_common::config::LightningClientConfig;
-use fedimint_lnv2_common::contracts::{IncomingContract, OutgoingContract, PaymentImage};
+use fedimint_lnv2_common::contracts::{
+    AmountlessIncomingContract, IncomingContract, OutgoingContract, PaymentImage,
+};
 use fedimint_lnv2_common::gateway_api::{
     GatewayConnection, PaymentFee, RealGatewayConnection, RoutingInfo,
 };
@@ -54,7 +62,9 @@
     LightningModuleTypes, LightningOutput, LightningOutputV0, MINIMUM_INCOMING_CONTRACT_AMOUNT,
     lnurl, tweak,
 };
+use fedimint_logging::LOG_CLIENT_MODULE_LNV2;
 use futures::StreamExt;
+use futures::future::join_all;
 use lightning_invoice::{Bolt11Invoice, Currency};
 use secp256k1::{Keypair, PublicKey, Scalar, SecretKey, ecdh};
 use serde::{Deserialize, Serialize};
@@ -67,6 +77,7 @@
 use crate::api::LightningFederationApi;
 use crate::events::SendPaymentEvent;
 use crate::receive_sm::{ReceiveSMCommon, ReceiveSMState, ReceiveStateMachine};
+use crate::scoring::{REFERENCE_AMOUNT, update_gateway_score};
 use crate::send_sm::{SendSMCommon, SendSMState, SendStateMachine};
 
 /// Number of blocks until outgoing lightning contracts times out and user
@@ -76,12 +87,18 @@
 /// A two hour buffer in case either the client or gateway go offline
 const CONTRACT_CONFIRMATION_BUFFER: u64 = 12;
 
+/// How long we wait for a gateway's routing info before treating the request
+/// as failed, such that a single unresponsive gateway can't stall gateway
+/// selection
+const ROUTING_INFO_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LightningOperationMeta {
     Send(SendOperationMeta),
     Receive(ReceiveOperationMeta),
     LnurlReceive(LnurlReceiveOperationMeta),
+    AmountlessReceive(AmountlessReceiveOperationMeta),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -96,11 +113,9 @@ pub struct SendOperationMeta {
 impl SendOperationMeta {
     /// Calculate the absolute fee paid to the gateway on success.
     pub fn gateway_fee(&self) -> Amount {
-        match &self.invoice {
-            LightningInvoice::Bolt11(invoice) => self.contract.amount.saturating_sub(
-                Amount::from_msats(invoice.amount_milli_satoshis().expect("Invoice has amount")),
-            ),
-        }
+        self.contract
+            .amount
+            .saturating_sub(self.invoice.amount().expect("Invoice has amount"))
     }
 }
 
@@ -115,12 +130,10 @@ pub struct ReceiveOperationMeta {
 impl ReceiveOperationMeta {
     /// Calculate the absolute fee paid to the gateway on success.
     pub fn gateway_fee(&self) -> Amount {
-        match &self.invoice {
-            LightningInvoice::Bolt11(invoice) => {
-                Amount::from_msats(invoice.amount_milli_satoshis().expect("Invoice has amount"))
-                    .saturating_sub(self.contract.commitment.amount)
-            }
-        }
+        self.invoice
+            .amount()
+            .expect("Invoice has amount")
+            .saturating_sub(self.contract.commitment.amount)
     }
 }
 
@@ -130,6 +143,17 @@ pub struct LnurlReceiveOperationMeta {
     pub custom_meta: Value,
 }
 
+/// The meta of a receive operation for an amountless invoice. The incoming
+/// contract is only created by the gateway once the invoice is paid, hence
+/// the operation has no state machine until the contract is confirmed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AmountlessReceiveOperationMeta {
+    pub gateway: SafeUrl,
+    pub invoice: LightningInvoice,
+    pub expiration: u64,
+    pub custom_meta: Value,
+}
+
 #[cfg_attr(doc, aquamarine::aquamarine)]
 /// The state of an operation sending a payment over lightning.
 ///
@@ -441,8 +465,9 @@ async fn update_gateway_map(&self) {
 
     /// Selects an available gateway by querying the federation's registered
     /// gateways, checking if one of them match the invoice's payee public
-    /// key, then queries the gateway for `RoutingInfo` to determine if it is
-    /// online.
+    /// key, then queries the gateways for `RoutingInfo` to determine which
+    /// are online. Of those, the gateway ranked highest by the configured
+    /// [`GatewaySelectionPolicy`] is selected.
     pub async fn select_gateway(
         &self,
         invoice: Option<Bolt11Invoice>,
@@ -457,7 +482,7 @@ pub async fn select_gateway(
             return Err(SelectGatewayError::NoGatewaysAvailable);
         }
 
-        if let Some(invoice) = invoice
+        if let Some(invoice) = &invoice
             && let Some(gateway) = self
                 .client_ctx
                 .module_db()
@@ -466,18 +491,123 @@ pub async fn select_gateway(
                 .get_value(&GatewayKey(invoice.recover_payee_pub_key()))
                 .await
                 .filter(|gateway| gateways.contains(gateway))
-            && let Ok(Some(routing_info)) = self.routing_info(&gateway).await
+            && let Some(routing_info) = self.scored_routing_info(&gateway).await
         {
             return Ok((gateway, routing_info));
         }
 
-        for gateway in gateways {
-            if let Ok(Some(routing_info)) = self.routing_info(&gateway).await {
-                return Ok((gateway, routing_info));
+        let policy = self.gateway_selection_policy().await;
+
+        // We compare the fees for the amount of the invoice when sending and for a
+        // reference amount when receiving
+        let amount = invoice
+            .as_ref()
+            .and_then(Bolt11Invoice::amount_milli_satoshis)
+            .map_or(REFERENCE_AMOUNT, Amount::from_msats);
+
+        let responses = join_all(gateways.into_iter().map(|gateway| async move {
+            let routing_info = self.scored_routing_info(&gateway).await;
+
+            (gateway, routing_info)
+        }))
+        .await;
+
+        let scores = self.gateway_scores().await;
+
+        responses
+            .into_iter()
+            .filter_map(|(gateway, routing_info)| {
+                let routing_info = routing_info?;
+
+                let fee = match &invoice {
+                    Some(invoice) => routing_info.send_parameters(invoice).0.fee(amount.msats),
+                    None => routing_info.receive_fee.fee(amount.msats),
+                };
+
+                let rank = policy.rank(
+                    &scores.get(&gateway).cloned().unwrap_or_default(),
+                    fee,
+                    amount,
+                );
+
+                Some((rank, gateway, routing_info))
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, gateway, routing_info)| (gateway, routing_info))
+            .ok_or(SelectGatewayError::GatewaysUnresponsive)
+    }
+
+    /// Requests the `RoutingInfo` of a gateway and records its fees and
+    /// latency, or its failure to respond, in the gateway's score.
+    async fn scored_routing_info(&self, gateway: &SafeUrl) -> Option<RoutingInfo> {
+        let start = now();
+
+        let routing_info = timeout(ROUTING_INFO_TIMEOUT, self.routing_info(gateway))
+            .await
+            .ok()
+            .and_then(Result::ok);
+
+        let latency = now().duration_since(start).unwrap_or_default();
+
+        let mut dbtx = self.client_ctx.module_db().begin_transaction().await;
+
+        match &routing_info {
+            Some(Some(routing_info)) => {
+                update_gateway_score(&mut dbtx.to_ref_nc(), gateway, |score| {
+                    score.record_routing_info(routing_info, latency);
+                })
+                .await;
+            }
+            // The gateway is online but not connected to our federation
+            Some(None) => {}
+            // The request failed or timed out
+            None => {
+                update_gateway_score(&mut dbtx.to_ref_nc(), gateway, GatewayScore::record_failure)
+                    .await;
             }
         }
 
-        Err(SelectGatewayError::GatewaysUnresponsive)
+        if let Err(e) = dbtx.commit_tx_result().await {
+            warn!(
+                target: LOG_CLIENT_MODULE_LNV2,
+                "Failed to commit the updated gateway score to the database: {e}"
+            );
+        }
+
+        routing_info.flatten()
+    }
+
+    /// Returns the scores of all gateways we have interacted with.
+    pub async fn gateway_scores(&self) -> BTreeMap<SafeUrl, GatewayScore> {
+        self.client_ctx
+            .module_db()
+            .begin_transaction_nc()
+            .await
+            .find_by_prefix(&GatewayScorePrefix)
+            .await
+            .map(|(key, score)| (key.0, score))
+            .collect()
+            .await
+    }
+
+    /// Returns the policy used to rank the gateways during gateway selection.
+    pub async fn gateway_selection_policy(&self) -> GatewaySelectionPolicy {
+        self.client_ctx
+            .module_db()
+            .begin_transaction_nc()
+            .await
+            .get_value(&GatewaySelectionPolicyKey)
+            .await
+            .unwrap_or_default()
+    }
+
+    /// Sets the policy used to rank the gateways during gateway selection.
+    pub async fn set_gateway_selection_policy(&self, policy: GatewaySelectionPolicy) {
+        let mut dbtx = self.client_ctx.module_db().begin_transaction().await;
+
+        dbtx.insert_entry(&GatewaySelectionPolicyKey, &policy).await;
+
+        dbtx.commit_tx().await;
     }
 
     /// Sends a request to each peer for their registered gateway list and
@@ -525,18 +655,31 @@ pub async fn routing_info(
     /// one guardian we trust it to set a reasonable fee and only enforce a
     /// rather high limit.
     ///
+    /// The amount has to be specified if and only if the invoice does not
+    /// contain one, in which case the gateway pays the amountless invoice with
+    /// the given amount.
+    ///
     /// The absolute fee for a payment can be calculated from the operation meta
     /// to be shown to the user in the transaction history.
     #[allow(clippy::too_many_lines)]
     pub async fn send(
         &self,
         invoice: Bolt11Invoice,
+        amount: Option<Amount>,
         gateway: Option<SafeUrl>,
         custom_meta: Value,
     ) -> Result<OperationId, SendPaymentError> {
-        let amount = invoice
-            .amount_milli_satoshis()
-            .ok_or(SendPaymentError::InvoiceMissingAmount)?;
+        let lightning_invoice = match (invoice.amount_milli_satoshis(), amount) {
+            (Some(..), None) => LightningInvoice::Bolt11(invoice.clone()),
+            (None, Some(amount)) => LightningInvoice::Bolt11Amountless(invoice.clone(), amount),
+            (Some(..), Some(..)) => return Err(SendPaymentError::InvoiceHasAmount),
+            (None, None) => return Err(SendPaymentError::InvoiceMissingAmount),
+        };
+
+        let amount = lightning_invoice
+            .amount()
+            .expect("Amount is set for both invoice variants")
+            .msats;
 
         if invoice.is_expired() {
             return Err(SendPaymentError::InvoiceExpired);
@@ -598,7 +741,7 @@ pub async fn send(
 
         let contract_clone = contract.clone();
         let gateway_api_clone = gateway_api.clone();
-        let invoice_clone = invoice.clone();
+        let invoice_clone = lightning_invoice.clone();
 
         let client_output = ClientOutput::<LightningOutput> {
             output: LightningOutput::V0(LightningOutputV0::Outgoing(contract.clone())),
@@ -613,7 +756,7 @@ pub async fn send(
                         outpoint: range.into_iter().next().unwrap(),
                         contract: contract_clone.clone(),
                         gateway_api: Some(gateway_api_clone.clone()),
-                        invoice: Some(LightningInvoice::Bolt11(invoice_clone.clone())),
+                        invoice: Some(invoice_clone.clone()),
                         refund_keypair,
                     },
                     state: SendSMState::Funding,
@@ -637,7 +780,7 @@ pub async fn send(
                         change_outpoint_range,
                         gateway: gateway_api.clone(),
                         contract: contract.clone(),
-                        invoice: LightningInvoice::Bolt11(invoice.clone()),
+                        invoice: lightning_invoice.clone(),
                         custom_meta: custom_meta.clone(),
                     })
                 },
@@ -825,6 +968,112 @@ pub async fn receive(
         Ok((invoice, operation_id))
     }
 
+    /// Request an amountless invoice whose incoming contract is created by the
+    /// gateway. The contract is locked to our lnurl public key such that the
+    /// task receiving lnurl payments picks it up once it has been funded.
+    ///
+    /// Unlike [`Self::receive`] this is not trustless: the gateway needs the
+    /// contract's encryption seed to create it once the amount is known and
+    /// can therefore derive the preimage. A malicious gateway could settle
+    /// the payment without ever funding the contract, so only use this with a
+    /// gateway you trust with the amount being received.
+    pub async fn receive_amountless_trusting_gateway(
+        &self,
+        expiry_secs: u32,
+        description: Bolt11InvoiceDescription,
+        gateway: Option<SafeUrl>,
+        custom_meta: Value,
+    ) -> Result<(Bolt11Invoice, OperationId), ReceiveError> {
+        let recipient_static_pk = self.lnurl_keypair.public_key();
+
+        let (ephemeral_tweak, ephemeral_pk) = tweak::generate(recipient_static_pk);
+
+        let (gateway, routing_info) = match gateway {
+            Some(gateway) => (
+                gateway.clone(),
+                self.routing_info(&gateway)
+                    .await
+                    .map_err(|e| ReceiveError::FailedToConnectToGateway(e.to_string()))?
+                    .ok_or(ReceiveError::FederationNotSupported)?,
+            ),
+            None => self
+                .select_gateway(None)
+                .await
+                .map_err(ReceiveError::SelectGateway)?,
+        };
+
+        if !routing_info.receive_fee.le(&PaymentFee::RECEIVE_FEE_LIMIT) {
+            return Err(ReceiveError::GatewayFeeExceedsLimit);
+        }
+
+        let contract = AmountlessIncomingContract {
+            encryption_seed: ephemeral_tweak
+                .consensus_hash::<sha256::Hash>()
+                .to_byte_array(),
+            expiration: duration_since_epoch()
+                .as_secs()
+                .saturating_add(u64::from(expiry_secs)),
+            claim_pk: recipient_static_pk
+                .mul_tweak(
+                    secp256k1::SECP256K1,
+                    &Scalar::from_be_bytes(ephemeral_tweak).expect("Within curve order"),
+                )
+                .expect("Tweak is valid"),
+            refund_pk: routing_info.module_public_key,
+            ephemeral_pk,
+        };
+
+        let invoice = self
+            .gateway_conn
+            .amountless_bolt11_invoice(
+                gateway.clone(),
+                self.federation_id,
+                contract.clone(),
+                description,
+                expiry_secs,
+            )
+            .await
+            .map_err(|e| ReceiveError::FailedToConnectToGateway(e.to_string()))?;
+
+        if PaymentImage::Hash(*invoice.payment_hash()) != contract.payment_image() {
+            return Err(ReceiveError::InvalidInvoice);
+        }
+
+        if invoice.amount_milli_satoshis().is_some() {
+            return Err(ReceiveError::IncorrectInvoiceAmount);
+        }
+
+        let operation_id = OperationId::from_encodable(&invoice);
+
+        let mut dbtx = self.client_ctx.module_db().begin_transaction().await;
+
+        dbtx.insert_entry(
+            &AmountlessReceiveKey(*invoice.payment_hash()),
+            &operation_id,
+        )
+        .await;
+
+        self.client_ctx
+            .manual_operation_start_dbtx(
+                &mut dbtx.to_ref_nc(),
+                operation_id,
+                LightningCommonInit::KIND.as_str(),
+                LightningOperationMeta::AmountlessReceive(AmountlessReceiveOperationMeta {
+                    gateway,
+                    invoice: LightningInvoice::Bolt11(invoice.clone()),
+                    expiration: contract.expiration,
+                    custom_meta,
+                }),
+                vec![],
+            )
+            .await
+            .expect("The operation id is derived from a fresh invoice");
+
+        dbtx.commit_tx().await;
+
+        Ok((invoice, operation_id))
+    }
+
     /// Create an incoming contract locked to a public key derived from the
     /// recipient's static module public key and fetches the corresponding
     /// invoice.
@@ -954,6 +1203,48 @@ async fn receive_incoming_contract(
         Some(operation_id)
     }
 
+    // Start the state machine of an amountless receive operation once the gateway
+    // has funded its incoming contract.
+    async fn receive_amountless_contract(
+        &self,
+        operation_id: OperationId,
+        payment_hash: sha256::Hash,
+        contract: IncomingContract,
+    ) {
+        let mut dbtx = self.client_ctx.module_db().begin_transaction().await;
+
+        dbtx.remove_entry(&AmountlessReceiveKey(payment_hash)).await;
+
+        if let Some((claim_keypair, agg_decryption_key)) =
+            self.recover_contract_keys(self.lnurl_keypair.secret_key(), &contract)
+        {
+            let receive_sm = LightningClientStateMachines::Receive(ReceiveStateMachine {
+                common: ReceiveSMCommon {
+                    operation_id,
+                    contract,
+                    claim_keypair,
+                    agg_decryption_key,
+                },
+                state: ReceiveSMState::Pending,
+            });
+
+            self.client_ctx
+                .add_state_machines_dbtx(
+                    &mut dbtx.to_ref_nc(),
+                    vec![self.client_ctx.make_dyn_state(receive_sm)],
+                )
+                .await
+                .ok();
+        } else {
+            warn!(
+                ?operation_id,
+                "Incoming contract for amountless invoice is not locked to us"
+            );
+        }
+
+        dbtx.commit_tx().await;
+    }
+
     fn recover_contract_keys(
         &self,
         sk: SecretKey,
@@ -995,10 +1286,31 @@ pub async fn subscribe_receive_operation_state_updates(
         let mut stream = self.notifier.subscribe(operation_id).await;
         let client_ctx = self.client_ctx.clone();
 
+        // An amountless receive has no state machine until the gateway has funded
+        // the incoming contract, so we have to expire it ourselves.
+        let mut amountless_expiration = match operation.meta::<LightningOperationMeta>() {
+            LightningOperationMeta::AmountlessReceive(meta) => Some(meta.expiration),
+            _ => None,
+        };
+
         Ok(self.client_ctx.outcome_or_updates(operation, operation_id, move || {
             stream! {
                 loop {
-                    if let Some(LightningClientStateMachines::Receive(state)) = stream.next().await {
+                    let next = match amountless_expiration.take() {
+                        Some(expiration) => {
+                            let remaining = expiration.saturating_sub(duration_since_epoch().as_secs());
+
+                            if let Ok(next) = timeout(Duration::from_secs(remaining), stream.next()).await {
+                                next
+                            } else {
+                                yield ReceiveOperationState::Expired;
+                                return;
+                            }
+                        }
+                        None => stream.next().await,
+                    };
+
+                    if let Some(LightningClientStateMachines::Receive(state)) = next {
                         match state.state {
                             ReceiveSMState::Pending => yield ReceiveOperationState::Pending,
                             ReceiveSMState::Claiming(out_points) => {
@@ -1110,6 +1422,25 @@ async fn receive_lnurl(&self, custom_meta: Value) {
             .await;
 
         for contract in &contracts {
+            if let PaymentImage::Hash(payment_hash) = contract.commitment.payment_image
+                && let Some(operation_id) = self
+                    .client_ctx
+                    .module_db()
+                    .begin_transaction_nc()
+                    .await
+                    .get_value(&AmountlessReceiveKey(payment_hash))
+                    .await
+            {
+                self.receive_amountless_contract(operation_id, payment_hash, contract.clone())
+                    .await;
+
+                self.await_final_receive_operation_state(operation_id)
+                    .await
+                    .ok();
+
+                continue;
+            }
+
             if let Some(operation_id) = self
                 .receive_incoming_contract(
                     self.lnurl_keypair.secret_key(),
@@ -1148,6 +1479,8 @@ pub enum SelectGatewayError {
 pub enum SendPaymentError {
     #[error("Invoice is missing an amount")]
     InvoiceMissingAmount,
+    #[error("Amount can only be specified for amountless invoices")]
+    InvoiceHasAmount,
     #[error("Invoice has expired")]
     InvoiceExpired,
     #[error("A payment for this invoice is already in progress")]
@@ -12,9 +12,10 @@
 use tpe::AggregateDecryptionKey;
 use tracing::instrument;
 
-use crate::LightningClientContext;
 use crate::api::LightningFederationApi;
 use crate::events::ReceivePaymentEvent;
+use crate::scoring::{GatewayScore, update_gateway_score};
+use crate::{LightningClientContext, LightningOperationMeta};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
 pub struct ReceiveStateMachine {
@@ -142,6 +143,30 @@ async fn transition_incoming_contract(
             )
             .await;
 
+        // Payments to an lnurl may be received via any gateway, so we only credit
+        // the gateway that has created the invoice
+        let gateway = context
+            .client_ctx
+            .get_operation(old_state.common.operation_id)
+            .await
+            .ok()
+            .and_then(
+                |operation| match operation.meta::<LightningOperationMeta>() {
+                    LightningOperationMeta::Receive(meta) => Some(meta.gateway),
+                    LightningOperationMeta::AmountlessReceive(meta) => Some(meta.gateway),
+                    _ => None,
+                },
+            );
+
+        if let Some(gateway) = gateway {
+            update_gateway_score(
+                &mut dbtx.module_tx(),
+                &gateway,
+                GatewayScore::record_success,
+            )
+            .await;
+        }
+
         old_state.update(ReceiveSMState::Claiming(change_range.into_iter().collect()))
     }
 }
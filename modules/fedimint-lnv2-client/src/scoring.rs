@@ -0,0 +1,200 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use fedimint_core::Amount;
+use fedimint_core::db::{DatabaseTransaction, IDatabaseTransactionOpsCoreTyped};
+use fedimint_core::encoding::{Decodable, Encodable};
+use fedimint_core::util::SafeUrl;
+use fedimint_lnv2_common::gateway_api::{PaymentFee, RoutingInfo};
+use serde::{Deserialize, Serialize};
+
+use crate::db::GatewayScoreKey;
+
+/// The amount used to compare the fees of gateways if the amount of the
+/// payment is not known yet, for example when generating an lnurl.
+pub const REFERENCE_AMOUNT: Amount = Amount::from_sats(100_000);
+
+/// The policy used to rank the responsive gateways during gateway selection.
+#[derive(
+    Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize, Encodable, Decodable,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum GatewaySelectionPolicy {
+    /// Select the gateway charging the lowest fee for the payment.
+    Cheapest,
+    /// Select the gateway with the highest share of successful payments.
+    MostReliable,
+    /// Select the gateway with the best balance of reliability, fee and
+    /// latency.
+    #[default]
+    Weighted,
+}
+
+impl GatewaySelectionPolicy {
+    /// Ranks a gateway charging `fee` for a payment of `amount`; the gateway
+    /// with the highest rank is selected.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn rank(self, score: &GatewayScore, fee: Amount, amount: Amount) -> f64 {
+        let relative_fee = fee.msats as f64 / amount.msats.max(1) as f64;
+
+        match self {
+            GatewaySelectionPolicy::Cheapest => -relative_fee,
+            GatewaySelectionPolicy::MostReliable => score.reliability(),
+            GatewaySelectionPolicy::Weighted => {
+                // A fee of one percent or a latency of one second halves the rank
+                let latency_secs = score.latency_ms.unwrap_or(0) as f64 / 1000.0;
+
+                score.reliability() / (1.0 + 100.0 * relative_fee) / (1.0 + latency_secs)
+            }
+        }
+    }
+}
+
+impl fmt::Display for GatewaySelectionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GatewaySelectionPolicy::Cheapest => write!(f, "cheapest"),
+            GatewaySelectionPolicy::MostReliable => write!(f, "most_reliable"),
+            GatewaySelectionPolicy::Weighted => write!(f, "weighted"),
+        }
+    }
+}
+
+impl FromStr for GatewaySelectionPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "cheapest" => Ok(GatewaySelectionPolicy::Cheapest),
+            "most_reliable" => Ok(GatewaySelectionPolicy::MostReliable),
+            "weighted" => Ok(GatewaySelectionPolicy::Weighted),
+            _ => Err(anyhow::anyhow!("Unknown gateway selection policy: {s}")),
+        }
+    }
+}
+
+/// Statistics about a vetted gateway collected from the outcomes of our
+/// payments and the `RoutingInfo` it returned.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize, Encodable, Decodable)]
+pub struct GatewayScore {
+    /// The number of payments the gateway has completed.
+    pub successes: u64,
+    /// The number of payments the gateway has failed and of requests it has
+    /// not responded to.
+    pub failures: u64,
+    /// The default send fee of the latest `RoutingInfo`.
+    pub send_fee: Option<PaymentFee>,
+    /// The receive fee of the latest `RoutingInfo`.
+    pub receive_fee: Option<PaymentFee>,
+    /// The moving average of the time the gateway takes to respond with its
+    /// `RoutingInfo` in milliseconds.
+    pub latency_ms: Option<u64>,
+}
+
+impl GatewayScore {
+    pub fn record_routing_info(&mut self, routing_info: &RoutingInfo, latency: Duration) {
+        self.send_fee = Some(routing_info.send_fee_default);
+        self.receive_fee = Some(routing_info.receive_fee);
+
+        let latency_ms = u64::try_from(latency.as_millis()).unwrap_or(u64::MAX);
+
+        // The latest measurement is weighted with one quarter
+        self.latency_ms = Some(match self.latency_ms {
+            Some(average) => average.saturating_mul(3).saturating_add(latency_ms) / 4,
+            None => latency_ms,
+        });
+    }
+
+    pub fn record_success(&mut self) {
+        self.successes = self.successes.saturating_add(1);
+    }
+
+    pub fn record_failure(&mut self) {
+        self.failures = self.failures.saturating_add(1);
+    }
+
+    /// The share of successful payments, starting at one half for gateways we
+    /// have not used yet.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn reliability(&self) -> f64 {
+        (self.successes as f64 + 1.0) / (self.successes as f64 + self.failures as f64 + 2.0)
+    }
+}
+
+pub(crate) async fn update_gateway_score(
+    dbtx: &mut DatabaseTransaction<'_>,
+    gateway: &SafeUrl,
+    update: impl FnOnce(&mut GatewayScore),
+) {
+    let key = GatewayScoreKey(gateway.clone());
+
+    let mut score = dbtx.get_value(&key).await.unwrap_or_default();
+
+    update(&mut score);
+
+    dbtx.insert_entry(&key, &score).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unused_gateway_is_neutral() {
+        let score = GatewayScore::default();
+
+        assert!((score.reliability() - 0.5).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn weighted_policy_prefers_reliable_gateways() {
+        let mut reliable = GatewayScore::default();
+        let mut unreliable = GatewayScore::default();
+
+        for _ in 0..10 {
+            reliable.record_success();
+            unreliable.record_failure();
+        }
+
+        let fee = Amount::from_sats(100);
+
+        assert!(
+            GatewaySelectionPolicy::Weighted.rank(&reliable, fee, REFERENCE_AMOUNT)
+                > GatewaySelectionPolicy::Weighted.rank(&unreliable, fee, REFERENCE_AMOUNT)
+        );
+    }
+
+    #[test]
+    fn cheapest_policy_ignores_reliability() {
+        let mut reliable = GatewayScore::default();
+
+        reliable.record_success();
+
+        assert!(
+            GatewaySelectionPolicy::Cheapest.rank(
+                &GatewayScore::default(),
+                Amount::from_sats(10),
+                REFERENCE_AMOUNT
+            ) > GatewaySelectionPolicy::Cheapest.rank(
+                &reliable,
+                Amount::from_sats(20),
+                REFERENCE_AMOUNT
+            )
+        );
+    }
+
+    #[test]
+    fn policy_roundtrips_through_string() {
+        for policy in [
+            GatewaySelectionPolicy::Cheapest,
+            GatewaySelectionPolicy::MostReliable,
+            GatewaySelectionPolicy::Weighted,
+        ] {
+            assert_eq!(
+                policy.to_string().parse::<GatewaySelectionPolicy>().ok(),
+                Some(policy)
+            );
+        }
+    }
+}
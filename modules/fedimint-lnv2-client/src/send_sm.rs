@@ -20,6 +20,7 @@
 
 use crate::api::LightningFederationApi;
 use crate::events::{SendPaymentStatus, SendPaymentUpdateEvent};
+use crate::scoring::update_gateway_score;
 use crate::{LightningClientContext, LightningInvoice};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
@@ -59,15 +60,25 @@ pub enum SendSMState {
 async fn send_update_event(
     context: LightningClientContext,
     dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
-    operation_id: OperationId,
+    common: &SendSMCommon,
     status: SendPaymentStatus,
 ) {
+    // A refund means the gateway has either failed the payment or did not
+    // respond until the contract expired
+    if let Some(gateway_api) = &common.gateway_api {
+        update_gateway_score(&mut dbtx.module_tx(), gateway_api, |score| match &status {
+            SendPaymentStatus::Success(..) => score.record_success(),
+            SendPaymentStatus::Refunded => score.record_failure(),
+        })
+        .await;
+    }
+
     context
         .client_ctx
         .log_event(
             &mut dbtx.module_tx(),
             SendPaymentUpdateEvent {
-                operation_id,
+                operation_id: common.operation_id,
                 status,
             },
         )
@@ -228,7 +239,7 @@ async fn transition_gateway_send_payment(
                 send_update_event(
                     context,
                     dbtx,
-                    old_state.common.operation_id,
+                    &old_state.common,
                     SendPaymentStatus::Success(preimage),
                 )
                 .await;
@@ -257,7 +268,7 @@ async fn transition_gateway_send_payment(
                 send_update_event(
                     context,
                     dbtx,
-                    old_state.common.operation_id,
+                    &old_state.common,
                     SendPaymentStatus::Refunded,
                 )
                 .await;
@@ -298,7 +309,7 @@ async fn transition_preimage(
             send_update_event(
                 context,
                 dbtx,
-                old_state.common.operation_id,
+                &old_state.common,
                 SendPaymentStatus::Success(preimage),
             )
             .await;
@@ -323,7 +334,7 @@ async fn transition_preimage(
         send_update_event(
             context,
             dbtx,
-            old_state.common.operation_id,
+            &old_state.common,
             SendPaymentStatus::Refunded,
         )
         .await;
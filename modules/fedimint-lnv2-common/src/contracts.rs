@@ -1,4 +1,4 @@
-use bitcoin::hashes::sha256;
+use bitcoin::hashes::{Hash as _, sha256};
 use bitcoin::secp256k1;
 use fedimint_core::Amount;
 use fedimint_core::encoding::{Decodable, Encodable};
@@ -126,6 +126,47 @@ pub fn create_decryption_key_share(&self, sk: &SecretKeyShare) -> DecryptionKeyS
     }
 }
 
+/// The terms of an [`IncomingContract`] for an amountless invoice. The gateway
+/// creates the contract once it intercepts the payment and knows its amount.
+///
+/// Since the gateway encrypts the preimage itself it learns the preimage from
+/// the encryption seed, hence the recipient has to trust the gateway to fund
+/// the contract before it settles the payment.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct AmountlessIncomingContract {
+    pub encryption_seed: [u8; 32],
+    pub expiration: u64,
+    pub claim_pk: PublicKey,
+    pub refund_pk: PublicKey,
+    pub ephemeral_pk: PublicKey,
+}
+
+impl AmountlessIncomingContract {
+    pub fn preimage(&self) -> [u8; 32] {
+        self.encryption_seed
+            .consensus_hash::<sha256::Hash>()
+            .to_byte_array()
+    }
+
+    pub fn payment_image(&self) -> PaymentImage {
+        PaymentImage::Hash(self.preimage().consensus_hash())
+    }
+
+    pub fn contract(&self, agg_pk: AggregatePublicKey, amount: Amount) -> IncomingContract {
+        IncomingContract::new(
+            agg_pk,
+            self.encryption_seed,
+            self.preimage(),
+            self.payment_image(),
+            amount,
+            self.expiration,
+            self.claim_pk,
+            self.refund_pk,
+            self.ephemeral_pk,
+        )
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
 pub struct OutgoingContract {
     pub payment_image: PaymentImage,
@@ -10,6 +10,7 @@
 pub const REMOVE_GATEWAY_ENDPOINT: &str = "remove_gateway";
 
 // Gateway endpoints
+pub const CREATE_AMOUNTLESS_BOLT11_INVOICE_ENDPOINT: &str = "/create_amountless_bolt11_invoice";
 pub const CREATE_BOLT11_INVOICE_ENDPOINT: &str = "/create_bolt11_invoice";
 pub const VERIFY_BOLT11_PREIMAGE_ENDPOINT: &str = "/verify_bolt11_preimage";
 pub const ROUTING_INFO_ENDPOINT: &str = "/routing_info";
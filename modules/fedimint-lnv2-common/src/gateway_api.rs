@@ -13,9 +13,10 @@
 use reqwest::Method;
 use serde::{Deserialize, Serialize};
 
-use crate::contracts::{IncomingContract, OutgoingContract};
+use crate::contracts::{AmountlessIncomingContract, IncomingContract, OutgoingContract};
 use crate::endpoint_constants::{
-    CREATE_BOLT11_INVOICE_ENDPOINT, ROUTING_INFO_ENDPOINT, SEND_PAYMENT_ENDPOINT,
+    CREATE_AMOUNTLESS_BOLT11_INVOICE_ENDPOINT, CREATE_BOLT11_INVOICE_ENDPOINT,
+    ROUTING_INFO_ENDPOINT, SEND_PAYMENT_ENDPOINT,
 };
 use crate::{Bolt11InvoiceDescription, LightningInvoice};
 
@@ -37,6 +38,15 @@ async fn bolt11_invoice(
         expiry_secs: u32,
     ) -> Result<Bolt11Invoice, ServerError>;
 
+    async fn amountless_bolt11_invoice(
+        &self,
+        gateway_api: SafeUrl,
+        federation_id: FederationId,
+        contract: AmountlessIncomingContract,
+        description: Bolt11InvoiceDescription,
+        expiry_secs: u32,
+    ) -> Result<Bolt11Invoice, ServerError>;
+
     async fn send_payment(
         &self,
         gateway_api: SafeUrl,
@@ -95,6 +105,29 @@ async fn bolt11_invoice(
             .await
     }
 
+    async fn amountless_bolt11_invoice(
+        &self,
+        gateway_api: SafeUrl,
+        federation_id: FederationId,
+        contract: AmountlessIncomingContract,
+        description: Bolt11InvoiceDescription,
+        expiry_secs: u32,
+    ) -> Result<Bolt11Invoice, ServerError> {
+        self.api
+            .request(
+                &gateway_api,
+                Method::POST,
+                CREATE_AMOUNTLESS_BOLT11_INVOICE_ENDPOINT,
+                Some(CreateAmountlessBolt11InvoicePayload {
+                    federation_id,
+                    contract,
+                    description,
+                    expiry_secs,
+                }),
+            )
+            .await
+    }
+
     async fn send_payment(
         &self,
         gateway_api: SafeUrl,
@@ -130,6 +163,14 @@ pub struct CreateBolt11InvoicePayload {
     pub expiry_secs: u32,
 }
 
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CreateAmountlessBolt11InvoicePayload {
+    pub federation_id: FederationId,
+    pub contract: AmountlessIncomingContract,
+    pub description: Bolt11InvoiceDescription,
+    pub expiry_secs: u32,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct SendPaymentPayload {
     pub federation_id: FederationId,
@@ -42,6 +42,30 @@ pub enum Bolt11InvoiceDescription {
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, Decodable, Encodable)]
 pub enum LightningInvoice {
     Bolt11(Bolt11Invoice),
+    /// A BOLT11 invoice without an amount together with the amount chosen by
+    /// the sender
+    Bolt11Amountless(Bolt11Invoice, Amount),
+}
+
+impl LightningInvoice {
+    pub fn bolt11(&self) -> &Bolt11Invoice {
+        match self {
+            LightningInvoice::Bolt11(invoice) | LightningInvoice::Bolt11Amountless(invoice, _) => {
+                invoice
+            }
+        }
+    }
+
+    /// The amount to be paid to the recipient, which is only missing for an
+    /// amountless invoice not paired with an amount by the sender.
+    pub fn amount(&self) -> Option<Amount> {
+        match self {
+            LightningInvoice::Bolt11(invoice) => {
+                invoice.amount_milli_satoshis().map(Amount::from_msats)
+            }
+            LightningInvoice::Bolt11Amountless(_, amount) => Some(*amount),
+        }
+    }
 }
 
 pub const KIND: ModuleKind = ModuleKind::from_static_str("lnv2");
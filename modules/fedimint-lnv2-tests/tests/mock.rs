@@ -11,7 +11,9 @@
 use fedimint_core::util::SafeUrl;
 use fedimint_core::{Amount, OutPoint, apply, async_trait_maybe_send};
 use fedimint_ln_common::bitcoin;
-use fedimint_lnv2_common::contracts::{IncomingContract, OutgoingContract, PaymentImage};
+use fedimint_lnv2_common::contracts::{
+    AmountlessIncomingContract, IncomingContract, OutgoingContract, PaymentImage,
+};
 use fedimint_lnv2_common::gateway_api::{GatewayConnection, PaymentFee, RoutingInfo};
 use fedimint_lnv2_common::{Bolt11InvoiceDescription, LightningInvoice};
 use lightning_invoice::{
@@ -50,6 +52,21 @@ pub fn crash_invoice() -> Bolt11Invoice {
     bolt_11_invoice(GATEWAY_CRASH_PAYMENT_SECRET, Currency::Regtest)
 }
 
+pub fn payable_amountless_invoice() -> Bolt11Invoice {
+    let sk = SecretKey::new(&mut OsRng);
+    let payment_hash = sha256::Hash::hash(&MOCK_INVOICE_PREIMAGE);
+
+    InvoiceBuilder::new(Currency::Regtest)
+        .description(String::new())
+        .payment_hash(payment_hash)
+        .current_timestamp()
+        .min_final_cltv_expiry_delta(0)
+        .payment_secret(PaymentSecret(PAYABLE_PAYMENT_SECRET))
+        .expiry_time(Duration::from_secs(DEFAULT_EXPIRY_TIME))
+        .build_signed(|m| SECP256K1.sign_ecdsa_recoverable(m, &sk))
+        .expect("Invoice creation failed")
+}
+
 fn bolt_11_invoice(payment_secret: [u8; 32], currency: Currency) -> Bolt11Invoice {
     let sk = SecretKey::new(&mut OsRng);
     let payment_hash = sha256::Hash::hash(&MOCK_INVOICE_PREIMAGE);
@@ -127,6 +144,30 @@ async fn bolt11_invoice(
             .unwrap())
     }
 
+    async fn amountless_bolt11_invoice(
+        &self,
+        _gateway_api: SafeUrl,
+        _federation_id: FederationId,
+        contract: AmountlessIncomingContract,
+        _description: Bolt11InvoiceDescription,
+        expiry_time: u32,
+    ) -> Result<Bolt11Invoice, ServerError> {
+        let payment_hash = match contract.payment_image() {
+            PaymentImage::Hash(payment_hash) => payment_hash,
+            PaymentImage::Point(..) => panic!("PaymentImage is not a payment hash"),
+        };
+
+        Ok(InvoiceBuilder::new(Currency::Regtest)
+            .description(String::new())
+            .payment_hash(payment_hash)
+            .current_timestamp()
+            .min_final_cltv_expiry_delta(0)
+            .payment_secret(PaymentSecret([0; 32]))
+            .expiry_time(Duration::from_secs(expiry_time as u64))
+            .build_signed(|m| SECP256K1.sign_ecdsa_recoverable(m, &self.keypair.secret_key()))
+            .unwrap())
+    }
+
     async fn send_payment(
         &self,
         _gateway_api: SafeUrl,
@@ -136,20 +177,18 @@ async fn send_payment(
         invoice: LightningInvoice,
         _auth: Signature,
     ) -> Result<Result<[u8; 32], Signature>, ServerError> {
-        match invoice {
-            LightningInvoice::Bolt11(invoice) => {
-                if *invoice.payment_secret() == PaymentSecret(GATEWAY_CRASH_PAYMENT_SECRET) {
-                    return Err(ServerError::InvalidRequest(anyhow!(
-                        "Gateway crash payment secret"
-                    )));
-                }
-
-                if *invoice.payment_secret() == PaymentSecret(UNPAYABLE_PAYMENT_SECRET) {
-                    return Ok(Err(self.keypair.sign_schnorr(contract.forfeit_message())));
-                }
-
-                Ok(Ok(MOCK_INVOICE_PREIMAGE))
-            }
+        let invoice = invoice.bolt11();
+
+        if *invoice.payment_secret() == PaymentSecret(GATEWAY_CRASH_PAYMENT_SECRET) {
+            return Err(ServerError::InvalidRequest(anyhow!(
+                "Gateway crash payment secret"
+            )));
+        }
+
+        if *invoice.payment_secret() == PaymentSecret(UNPAYABLE_PAYMENT_SECRET) {
+            return Ok(Err(self.keypair.sign_schnorr(contract.forfeit_message())));
         }
+
+        Ok(Ok(MOCK_INVOICE_PREIMAGE))
     }
 }
@@ -2,6 +2,10 @@
 
 use std::sync::Arc;
 
+use fedimint_cli::receive::{
+    UNIFIED_RECEIVE_SUPERSEDED_OPERATION_TYPE, UnifiedReceiveOutcome, UnifiedReceiveSupersededMeta,
+    await_receive, receive,
+};
 use fedimint_client::transaction::{ClientInput, ClientInputBundle, TransactionBuilder};
 use fedimint_client_module::module::ClientModule;
 use fedimint_core::core::{IntoDynInstance, OperationId};
@@ -20,6 +24,8 @@
 use fedimint_lnv2_server::LightningInit;
 use fedimint_logging::LOG_TEST;
 use fedimint_testing::fixtures::Fixtures;
+use fedimint_wallet_client::WalletClientInit;
+use fedimint_wallet_server::WalletInit;
 use serde_json::Value;
 use tracing::warn;
 
@@ -41,6 +47,12 @@ fn fixtures() -> Fixtures {
     )
 }
 
+fn fixtures_with_wallet() -> Fixtures {
+    let fixtures = fixtures();
+    let wallet_client = WalletClientInit::new(fixtures.client_esplora_rpc());
+    fixtures.with_module(wallet_client, WalletInit)
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn can_pay_external_invoice_exactly_once() -> anyhow::Result<()> {
     let fixtures = fixtures();
@@ -58,13 +70,23 @@ async fn can_pay_external_invoice_exactly_once() -> anyhow::Result<()> {
 
     let operation_id = client
         .get_first_module::<LightningClientModule>()?
-        .send(invoice.clone(), Some(gateway_api.clone()), Value::Null)
+        .send(
+            invoice.clone(),
+            None,
+            Some(gateway_api.clone()),
+            Value::Null,
+        )
         .await?;
 
     assert_eq!(
         client
             .get_first_module::<LightningClientModule>()?
-            .send(invoice.clone(), Some(gateway_api.clone()), Value::Null)
+            .send(
+                invoice.clone(),
+                None,
+                Some(gateway_api.clone()),
+                Value::Null
+            )
             .await,
         Err(SendPaymentError::PaymentInProgress(operation_id)),
     );
@@ -85,7 +107,7 @@ async fn can_pay_external_invoice_exactly_once() -> anyhow::Result<()> {
     assert_eq!(
         client
             .get_first_module::<LightningClientModule>()?
-            .send(invoice, Some(gateway_api), Value::Null)
+            .send(invoice, None, Some(gateway_api), Value::Null)
             .await,
         Err(SendPaymentError::InvoiceAlreadyPaid(operation_id)),
     );
@@ -109,6 +131,7 @@ async fn refund_failed_payment() -> anyhow::Result<()> {
         .get_first_module::<LightningClientModule>()?
         .send(
             mock::unpayable_invoice(),
+            None,
             Some(mock::gateway()),
             Value::Null,
         )
@@ -150,7 +173,12 @@ async fn unilateral_refund_of_outgoing_contracts() -> anyhow::Result<()> {
 
     let operation_id = client
         .get_first_module::<LightningClientModule>()?
-        .send(mock::crash_invoice(), Some(mock::gateway()), Value::Null)
+        .send(
+            mock::crash_invoice(),
+            None,
+            Some(mock::gateway()),
+            Value::Null,
+        )
         .await?;
 
     let mut sub = client
@@ -184,7 +212,12 @@ async fn claiming_outgoing_contract_triggers_success() -> anyhow::Result<()> {
 
     let operation_id = client
         .get_first_module::<LightningClientModule>()?
-        .send(mock::crash_invoice(), Some(mock::gateway()), Value::Null)
+        .send(
+            mock::crash_invoice(),
+            None,
+            Some(mock::gateway()),
+            Value::Null,
+        )
         .await?;
 
     let mut sub = client
@@ -208,6 +241,9 @@ async fn claiming_outgoing_contract_triggers_success() -> anyhow::Result<()> {
         LightningOperationMeta::LnurlReceive(..) => {
             panic!("Operation Meta is a LnurlReceive variant")
         }
+        LightningOperationMeta::AmountlessReceive(..) => {
+            panic!("Operation Meta is an AmountlessReceive variant")
+        }
     };
 
     let client_input = ClientInput::<LightningInput> {
@@ -273,6 +309,97 @@ async fn receive_operation_expires() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn can_pay_amountless_invoice() -> anyhow::Result<()> {
+    let fixtures = fixtures();
+    let fed = fixtures.new_fed_degraded().await;
+    let client = fed.new_client().await;
+
+    // Give client initial balance
+    client
+        .get_first_module::<DummyClientModule>()?
+        .mock_receive(sats(10_000), AmountUnit::BITCOIN)
+        .await?;
+
+    let lightning = client.get_first_module::<LightningClientModule>()?;
+
+    assert_eq!(
+        lightning
+            .send(
+                mock::payable_amountless_invoice(),
+                None,
+                Some(mock::gateway()),
+                Value::Null
+            )
+            .await,
+        Err(SendPaymentError::InvoiceMissingAmount),
+    );
+
+    assert_eq!(
+        lightning
+            .send(
+                mock::payable_invoice(),
+                Some(sats(1000)),
+                Some(mock::gateway()),
+                Value::Null
+            )
+            .await,
+        Err(SendPaymentError::InvoiceHasAmount),
+    );
+
+    let operation_id = lightning
+        .send(
+            mock::payable_amountless_invoice(),
+            Some(sats(1000)),
+            Some(mock::gateway()),
+            Value::Null,
+        )
+        .await?;
+
+    let mut sub = lightning
+        .subscribe_send_operation_state_updates(operation_id)
+        .await?
+        .into_stream();
+
+    assert_eq!(sub.ok().await?, SendOperationState::Funding);
+    assert_eq!(sub.ok().await?, SendOperationState::Funded);
+    assert_eq!(
+        sub.ok().await?,
+        SendOperationState::Success(MOCK_INVOICE_PREIMAGE)
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn amountless_receive_operation_expires() -> anyhow::Result<()> {
+    let fixtures = fixtures();
+    let fed = fixtures.new_fed_degraded().await;
+    let client = fed.new_client().await;
+
+    let lightning = client.get_first_module::<LightningClientModule>()?;
+
+    let (invoice, operation_id) = lightning
+        .receive_amountless_trusting_gateway(
+            5, // receive operation expires in 5 seconds
+            Bolt11InvoiceDescription::Direct(String::new()),
+            Some(mock::gateway()),
+            Value::Null,
+        )
+        .await?;
+
+    assert_eq!(invoice.amount_milli_satoshis(), None);
+
+    let mut sub = lightning
+        .subscribe_receive_operation_state_updates(operation_id)
+        .await?
+        .into_stream();
+
+    assert_eq!(sub.ok().await?, ReceiveOperationState::Expired);
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn rejects_wrong_network_invoice() -> anyhow::Result<()> {
     let fixtures = fixtures();
@@ -284,6 +411,7 @@ async fn rejects_wrong_network_invoice() -> anyhow::Result<()> {
             .get_first_module::<LightningClientModule>()?
             .send(
                 mock::signet_bolt_11_invoice(),
+                None,
                 Some(mock::gateway()),
                 Value::Null
             )
@@ -297,3 +425,74 @@ async fn rejects_wrong_network_invoice() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unified_receive_supersedes_invoice_once_deposit_is_claimed() -> anyhow::Result<()> {
+    let fixtures = fixtures_with_wallet();
+    let fed = fixtures.new_fed_not_degraded().await;
+    let client = fed.new_client().await;
+    let bitcoin = fixtures.bitcoin();
+    let bitcoin = bitcoin.lock_exclusive().await;
+
+    let finality_delay = 10;
+    bitcoin.mine_blocks(finality_delay).await;
+
+    let (operation_id, meta) = receive(
+        &client,
+        Amount::from_sats(10_000),
+        3600,
+        Bolt11InvoiceDescription::Direct(String::new()),
+        Some(mock::gateway()),
+    )
+    .await?;
+
+    let address = meta.address.clone().assume_checked();
+    assert!(
+        meta.uri
+            .starts_with(&format!("bitcoin:{address}?amount=0.0001&"))
+    );
+    assert!(meta.uri.ends_with(&format!("&lightning={}", meta.invoice)));
+
+    // Nobody pays the invoice, so the deposit is received first
+    bitcoin
+        .send_and_mine_block(&address, bitcoin::Amount::from_sat(10_000))
+        .await;
+    bitcoin.mine_blocks(finality_delay).await;
+
+    let outcome = await_receive(&client, operation_id).await?;
+    match &outcome {
+        UnifiedReceiveOutcome::Deposit {
+            operation_id: deposit_operation_id,
+            superseded,
+            ..
+        } => {
+            assert_eq!(*deposit_operation_id, meta.deposit_operation_id);
+            assert_eq!(*superseded, meta.lightning_operation_id);
+        }
+        other => panic!("Unexpected outcome: {other:?}"),
+    }
+
+    // The superseded invoice is marked in its own operation log entry
+    let superseded = client
+        .operation_log()
+        .paginate_operations_rev(10, None)
+        .await
+        .into_iter()
+        .filter(|(_, entry)| {
+            entry.operation_module_kind() == UNIFIED_RECEIVE_SUPERSEDED_OPERATION_TYPE
+        })
+        .map(|(_, entry)| entry.meta::<UnifiedReceiveSupersededMeta>())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        superseded,
+        vec![UnifiedReceiveSupersededMeta {
+            unified_receive: operation_id,
+            superseded: meta.lightning_operation_id,
+        }]
+    );
+
+    // The outcome is cached
+    assert_eq!(await_receive(&client, operation_id).await?, outcome);
+
+    Ok(())
+}
@@ -1,14 +1,18 @@
 use fedimint_api_client::api::{FederationApiExt, FederationResult, IModuleFederationApi};
 use fedimint_core::bitcoin::hashes::sha256;
 use fedimint_core::module::registry::ModuleRegistry;
-use fedimint_core::module::{ApiRequestErased, SerdeModuleEncodingBase64};
+use fedimint_core::module::{ApiAuth, ApiRequestErased, SerdeModuleEncodingBase64};
 use fedimint_core::task::{MaybeSend, MaybeSync};
 use fedimint_core::{OutPoint, PeerId, apply, async_trait_maybe_send};
 use fedimint_mint_common::endpoint_constants::{
-    BLIND_NONCE_USED_ENDPOINT, NOTE_SPENT_ENDPOINT, RECOVERY_BLIND_NONCE_OUTPOINTS_ENDPOINT,
+    BLIND_NONCE_USED_ENDPOINT, KEY_EPOCH_KEYS_ENDPOINT, KEY_EPOCH_STATUS_ENDPOINT,
+    NOTE_SPENT_ENDPOINT, NOTES_SPENT_ENDPOINT, RECOVERY_BLIND_NONCE_OUTPOINTS_ENDPOINT,
     RECOVERY_COUNT_ENDPOINT, RECOVERY_SLICE_ENDPOINT, RECOVERY_SLICE_HASH_ENDPOINT,
+    ROTATE_KEY_EPOCH_ENDPOINT,
+};
+use fedimint_mint_common::{
+    BlindNonce, KeyEpochKeys, KeyEpochStatus, Nonce, NoteSpentBitmap, RecoveryItem,
 };
-use fedimint_mint_common::{BlindNonce, Nonce, RecoveryItem};
 
 #[apply(async_trait_maybe_send!)]
 pub trait MintFederationApi {
@@ -16,6 +20,23 @@ pub trait MintFederationApi {
 
     async fn check_note_spent(&self, nonce: Nonce) -> FederationResult<bool>;
 
+    /// Checks the spent status of up to
+    /// [`fedimint_mint_common::MAX_NOTES_SPENT_BATCH_SIZE`] notes at once,
+    /// requires module API version 0.2
+    async fn check_notes_spent(&self, nonces: Vec<Nonce>) -> FederationResult<NoteSpentBitmap>;
+
+    /// Returns the key epochs activated by the federation, requires module API
+    /// version 0.3
+    async fn fetch_key_epoch_status(&self) -> FederationResult<KeyEpochStatus>;
+
+    /// Returns the public keys the guardians generated for a key epoch after
+    /// zero, requires module API version 0.4
+    async fn fetch_key_epoch_keys(&self, key_epoch: u64) -> FederationResult<Option<KeyEpochKeys>>;
+
+    /// Asks our guardian to vote for generating the keys of the next key epoch
+    /// and activating it, returning the key epoch
+    async fn rotate_key_epoch(&self, auth: ApiAuth) -> FederationResult<u64>;
+
     /// Returns the total number of recovery items stored on the federation.
     async fn fetch_recovery_count(&self) -> anyhow::Result<u64>;
 
@@ -59,6 +80,35 @@ async fn check_note_spent(&self, nonce: Nonce) -> FederationResult<bool> {
         .await
     }
 
+    async fn check_notes_spent(&self, nonces: Vec<Nonce>) -> FederationResult<NoteSpentBitmap> {
+        self.request_current_consensus(
+            NOTES_SPENT_ENDPOINT.to_string(),
+            ApiRequestErased::new(nonces),
+        )
+        .await
+    }
+
+    async fn fetch_key_epoch_status(&self) -> FederationResult<KeyEpochStatus> {
+        self.request_current_consensus(
+            KEY_EPOCH_STATUS_ENDPOINT.to_string(),
+            ApiRequestErased::default(),
+        )
+        .await
+    }
+
+    async fn fetch_key_epoch_keys(&self, key_epoch: u64) -> FederationResult<Option<KeyEpochKeys>> {
+        self.request_current_consensus(
+            KEY_EPOCH_KEYS_ENDPOINT.to_string(),
+            ApiRequestErased::new(key_epoch),
+        )
+        .await
+    }
+
+    async fn rotate_key_epoch(&self, auth: ApiAuth) -> FederationResult<u64> {
+        self.request_admin(ROTATE_KEY_EPOCH_ENDPOINT, ApiRequestErased::default(), auth)
+            .await
+    }
+
     async fn fetch_recovery_count(&self) -> anyhow::Result<u64> {
         self.request_current_consensus::<u64>(
             RECOVERY_COUNT_ENDPOINT.to_string(),
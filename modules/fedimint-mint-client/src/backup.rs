@@ -104,14 +104,22 @@ pub async fn prepare_plaintext_ecash_backup(
                 )]),
                 MintClientStateMachines::Output(MintOutputStateMachine {
                     common,
-                    state: crate::output::MintOutputStates::CreatedMulti(created_state),
+                    state:
+                        crate::output::MintOutputStates::CreatedMulti(
+                            crate::output::MintOutputStatesCreatedMulti { issuance_requests },
+                        )
+                        | crate::output::MintOutputStates::CreatedMultiKeyEpoch(
+                            crate::output::MintOutputStatesCreatedMultiKeyEpoch {
+                                issuance_requests,
+                                ..
+                            },
+                        ),
                 }) => Some(
                     common
                         .out_point_range
                         .into_iter()
                         .map(|out_point| {
-                            let issuance_request = created_state
-                                .issuance_requests
+                            let issuance_request = issuance_requests
                                 .get(&out_point.out_idx)
                                 .expect("Must have corresponding out_idx");
                             (out_point, issuance_request.0, issuance_request.1)
@@ -444,6 +444,13 @@ pub fn handle_input(&mut self, input: &MintInput) {
                 self.pending_outputs.remove(&input.note.nonce);
                 self.spendable_notes.remove(&input.note.nonce);
             }
+            MintInput::V1(_) => {
+                trace!("Ignoring custom unit mint input, not covered by recovery");
+            }
+            MintInput::V2(_) => {
+                trace!("Ignoring locked mint input, locked notes are not recovered");
+            }
+            MintInput::V3(input) => self.handle_input(&input.input.clone().into()),
             MintInput::Default { variant, .. } => {
                 trace!("Ignoring future mint input variant {variant}");
             }
@@ -456,8 +463,13 @@ pub fn handle_output(
         output: &MintOutput,
         secret: &DerivableSecret,
     ) {
-        let output = match output {
-            MintOutput::V0(output) => output,
+        let (amount, blind_nonce) = match output {
+            MintOutput::V0(output) => (output.amount, output.blind_nonce),
+            MintOutput::V2(output) => (output.amount, output.blind_nonce),
+            MintOutput::V1(_) => {
+                trace!("Ignoring custom unit mint output, not covered by recovery");
+                return;
+            }
             MintOutput::Default { variant, .. } => {
                 trace!("Ignoring future mint output variant {variant}");
                 return;
@@ -465,7 +477,7 @@ pub fn handle_output(
         };
 
         if let Some((_issuance_request, note_idx, amount)) =
-            self.used_nonces.get(&output.blind_nonce.0.into())
+            self.used_nonces.get(&blind_nonce.0.into())
         {
             self.burned_total += *amount;
             self.reused_note_indices.push((*amount, *note_idx));
@@ -492,35 +504,35 @@ pub fn handle_output(
         // anything suspicious.
 
         if let Some((issuance_request, note_idx, pending_amount)) =
-            self.pending_nonces.remove(&output.blind_nonce.0.into())
+            self.pending_nonces.remove(&blind_nonce.0.into())
         {
             // the moment we see our blind nonce in the epoch history, correctly or
             // incorrectly used, we know that we must have used
             // already
             self.observe_nonce_idx_being_used(pending_amount, note_idx, secret);
 
-            if pending_amount == output.amount {
+            if pending_amount == amount {
                 self.used_nonces.insert(
-                    output.blind_nonce.0.into(),
+                    blind_nonce.0.into(),
                     (issuance_request, note_idx, pending_amount),
                 );
 
                 self.pending_outputs.insert(
                     issuance_request.nonce(),
-                    (out_point, output.amount, issuance_request),
+                    (out_point, amount, issuance_request),
                 );
             } else {
                 // put it back, incorrect amount
                 self.pending_nonces.insert(
-                    output.blind_nonce.0.into(),
+                    blind_nonce.0.into(),
                     (issuance_request, note_idx, pending_amount),
                 );
                 warn!(
                     target: LOG_CLIENT_RECOVERY_MINT,
                     output = ?out_point,
-                    blind_nonce = ?output.blind_nonce.0,
+                    blind_nonce = ?blind_nonce.0,
                     expected_amount = %pending_amount,
-                    found_amount = %output.amount,
+                    found_amount = %amount,
                     "Transaction output contains blind nonce that looks like ours but is of the wrong amount. Ignoring."
                 );
             }
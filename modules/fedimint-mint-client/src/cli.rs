@@ -1,17 +1,32 @@
+use std::time::Duration;
 use std::{ffi, iter};
 
 use anyhow::bail;
 use clap::Parser;
+use fedimint_core::Amount;
+use fedimint_core::core::OperationId;
+use fedimint_core::secp256k1::PublicKey;
+use fedimint_core::util::SafeUrl;
 use futures::StreamExt;
 use serde::Serialize;
 use serde_json::json;
 
+use crate::fountain::decode_oob_notes_frames;
+use crate::p2pk::P2pkNotes;
+use crate::payment_request::{EcashPayment, EcashPaymentRequest, EcashPaymentTarget};
 use crate::{MintClientModule, OOBNotes, ReissueExternalNotesState};
 
 #[derive(Parser, Serialize)]
 enum Opts {
     /// Reissue out of band notes
-    Reissue { notes: OOBNotes },
+    Reissue {
+        #[clap(required_unless_present = "frames")]
+        notes: Option<OOBNotes>,
+        /// Animated QR code frames to reassemble the notes from, can be
+        /// specified multiple times
+        #[clap(long = "frame", conflicts_with = "notes")]
+        frames: Vec<String>,
+    },
     /// Verifies the signatures of e-cash notes, if the online flag is specified
     /// it also checks with the mint if the notes were already spent
     Validate {
@@ -20,8 +35,130 @@ enum Opts {
         #[clap(long)]
         online: bool,
         /// E-Cash note to validate
-        oob_notes: OOBNotes,
+        #[clap(required_unless_present = "frames")]
+        oob_notes: Option<OOBNotes>,
+        /// Animated QR code frames to reassemble the notes from, can be
+        /// specified multiple times
+        #[clap(long = "frame", conflicts_with = "oob_notes")]
+        frames: Vec<String>,
+    },
+    /// Print the public key e-cash notes can be locked to for us
+    P2pkKey,
+    /// Issue e-cash notes that can only be redeemed by the owner of a public
+    /// key
+    SendP2pk {
+        amount: Amount,
+        recipient: PublicKey,
+        /// After how many seconds we can reclaim the notes if they haven't
+        /// been redeemed by the recipient. Without it the notes can't be
+        /// reclaimed.
+        #[clap(long)]
+        refund_after: Option<u64>,
+    },
+    /// Redeem e-cash notes locked to our public key
+    RedeemP2pk { notes: P2pkNotes },
+    /// Vote for rotating the mint keys as a guardian, requires admin auth
+    RotateKeyEpoch,
+    /// Create a request for an e-cash payment that the payer delivers to a
+    /// callback URL
+    RequestPayment {
+        amount: Amount,
+        /// URL the payer POSTs the e-cash to
+        #[clap(long)]
+        callback_url: SafeUrl,
+        /// Description of the payment shown to the payer
+        #[clap(long, default_value = "")]
+        memo: String,
+        /// After how many seconds the request expires
+        #[clap(long, default_value_t = 60 * 60 * 24)]
+        expiry: u64,
+    },
+    /// Pay an e-cash payment request by delivering the e-cash to its target
+    PayRequest {
+        request: EcashPaymentRequest,
+        /// After how many seconds we will try to reclaim the e-cash if it
+        /// hasn't been reissued by the recipient. Defaults to one week.
+        #[clap(long, default_value_t = 60 * 60 * 24 * 7)]
+        timeout: u64,
     },
+    /// Reissue the e-cash delivered for one of our payment requests
+    ReceivePayment {
+        request_id: OperationId,
+        notes: OOBNotes,
+    },
+}
+
+/// Returns the notes given directly or, if absent, reassembles them from
+/// animated QR code frames
+fn oob_notes_or_frames(oob_notes: Option<OOBNotes>, frames: &[String]) -> anyhow::Result<OOBNotes> {
+    match oob_notes {
+        Some(oob_notes) => Ok(oob_notes),
+        None => decode_oob_notes_frames(frames.iter().map(String::as_str)),
+    }
+}
+
+/// Waits for the reissuance of external notes started by `operation_id`,
+/// describing a failure as `action` failed
+async fn await_reissuance(
+    mint: &MintClientModule,
+    operation_id: OperationId,
+    action: &str,
+) -> anyhow::Result<()> {
+    let mut updates = mint
+        .subscribe_reissue_external_notes(operation_id)
+        .await?
+        .into_stream();
+
+    while let Some(update) = updates.next().await {
+        if let ReissueExternalNotesState::Failed(e) = update {
+            bail!("{action} failed: {e}");
+        }
+    }
+
+    Ok(())
+}
+
+async fn validate(
+    mint: &MintClientModule,
+    oob_notes: &OOBNotes,
+    online: bool,
+) -> anyhow::Result<serde_json::Value> {
+    let amount = mint.validate_notes(oob_notes)?;
+
+    if online {
+        let any_spent = mint.check_note_spent(oob_notes).await?;
+        Ok(json!({
+            "any_spent": any_spent,
+            "amount_msat": amount,
+        }))
+    } else {
+        Ok(json!({
+            "amount_msat": amount,
+        }))
+    }
+}
+
+async fn request_payment(
+    mint: &MintClientModule,
+    amount: Amount,
+    callback_url: SafeUrl,
+    memo: String,
+    expiry: u64,
+) -> anyhow::Result<serde_json::Value> {
+    let request = mint
+        .create_ecash_payment_request(
+            amount,
+            memo,
+            Duration::from_secs(expiry),
+            EcashPaymentTarget::Http { url: callback_url },
+            (),
+        )
+        .await?;
+
+    Ok(json!({
+        "request": request,
+        "operation_id": request.id,
+    }))
 }
 
 pub(crate) async fn handle_cli_command(
@@ -31,39 +168,80 @@ pub(crate) async fn handle_cli_command(
     let opts = Opts::parse_from(iter::once(&ffi::OsString::from("mint")).chain(args.iter()));
 
     match opts {
-        Opts::Reissue { notes } => {
+        Opts::Reissue { notes, frames } => {
+            let notes = oob_notes_or_frames(notes, &frames)?;
             let amount = notes.total_amount();
 
             let operation_id = mint.reissue_external_notes(notes, ()).await?;
 
-            let mut updates = mint
-                .subscribe_reissue_external_notes(operation_id)
-                .await
-                .unwrap()
-                .into_stream();
+            await_reissuance(mint, operation_id, "Reissue").await?;
+
+            Ok(serde_json::to_value(amount).expect("JSON serialization failed"))
+        }
+        Opts::P2pkKey => Ok(json!({
+            "public_key": mint.p2pk_public_key(),
+        })),
+        Opts::SendP2pk {
+            amount,
+            recipient,
+            refund_after,
+        } => {
+            let operation_id = mint
+                .send_p2pk_notes(amount, recipient, refund_after.map(Duration::from_secs), ())
+                .await?;
+
+            let notes = mint.await_p2pk_notes(operation_id).await?;
+
+            Ok(json!({
+                "notes": notes,
+            }))
+        }
+        Opts::RedeemP2pk { notes } => {
+            let amount = notes.total_amount();
+
+            let operation_id = mint.redeem_p2pk_notes(notes, ()).await?;
 
-            while let Some(update) = updates.next().await {
-                if let ReissueExternalNotesState::Failed(e) = update {
-                    bail!("Reissue failed: {e}");
-                }
-            }
+            await_reissuance(mint, operation_id, "Redeeming").await?;
 
             Ok(serde_json::to_value(amount).expect("JSON serialization failed"))
         }
-        Opts::Validate { oob_notes, online } => {
-            let amount = mint.validate_notes(&oob_notes)?;
-
-            if online {
-                let any_spent = mint.check_note_spent(&oob_notes).await?;
-                Ok(json!({
-                    "any_spent": any_spent,
-                    "amount_msat": amount,
-                }))
-            } else {
-                Ok(json!({
-                    "amount_msat": amount,
-                }))
-            }
+        Opts::RequestPayment {
+            amount,
+            callback_url,
+            memo,
+            expiry,
+        } => request_payment(mint, amount, callback_url, memo, expiry).await,
+        Opts::PayRequest { request, timeout } => {
+            let operation_id = mint
+                .pay_ecash_payment_request(request, Duration::from_secs(timeout), ())
+                .await?;
+
+            Ok(json!({
+                "operation_id": operation_id,
+            }))
+        }
+        Opts::ReceivePayment { request_id, notes } => {
+            let amount = notes.total_amount();
+
+            let operation_id = mint
+                .receive_ecash_payment(EcashPayment { request_id, notes })
+                .await?;
+
+            await_reissuance(mint, operation_id, "Receiving payment").await?;
+
+            Ok(serde_json::to_value(amount).expect("JSON serialization failed"))
+        }
+        Opts::Validate {
+            oob_notes,
+            online,
+            frames,
+        } => validate(mint, &oob_notes_or_frames(oob_notes, &frames)?, online).await,
+        Opts::RotateKeyEpoch => {
+            let key_epoch = mint.rotate_key_epoch().await?;
+
+            Ok(json!({
+                "key_epoch": key_epoch,
+            }))
         }
     }
 }
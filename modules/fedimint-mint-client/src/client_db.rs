@@ -5,10 +5,11 @@
 use fedimint_core::core::OperationId;
 use fedimint_core::db::{DatabaseRecord, DatabaseTransaction, IDatabaseTransactionOpsCore};
 use fedimint_core::encoding::{Decodable, Encodable};
+use fedimint_core::module::AmountUnit;
 use fedimint_core::module::registry::ModuleDecoderRegistry;
 use fedimint_core::{Amount, impl_db_lookup, impl_db_record};
 use fedimint_logging::LOG_CLIENT_MODULE_MINT;
-use fedimint_mint_common::Nonce;
+use fedimint_mint_common::{KeyEpochKeys, KeyEpochStatus, Nonce};
 use serde::Serialize;
 use strum_macros::EnumIter;
 use tracing::debug;
@@ -23,6 +24,11 @@
 #[derive(Clone, EnumIter, Debug)]
 pub enum DbKeyPrefix {
     Note = 0x20,
+    UnitNote = 0x21,
+    NextECashUnitNoteIndex = 0x22,
+    KeyEpochStatus = 0x23,
+    KeyEpochKeys = 0x24,
+    ReceivedEcashPaymentRequest = 0x25,
     NextECashNoteIndex = 0x2a,
     CancelledOOBSpend = 0x2b,
     RecoveryState = 0x2c,
@@ -76,6 +82,93 @@ pub struct NoteKey {
     query_prefix = NextECashNoteIndexKeyPrefix
 );
 
+/// Like [`NoteKey`], but for notes of a non-bitcoin [`AmountUnit`]
+#[derive(Debug, Clone, Encodable, Decodable, Serialize)]
+pub struct UnitNoteKey {
+    pub unit: AmountUnit,
+    pub amount: Amount,
+    pub nonce: Nonce,
+}
+
+#[derive(Debug, Clone, Encodable, Decodable)]
+pub struct UnitNoteKeyPrefix;
+
+#[derive(Debug, Clone, Encodable, Decodable)]
+pub struct UnitNoteKeyUnitPrefix(pub AmountUnit);
+
+impl_db_record!(
+    key = UnitNoteKey,
+    value = SpendableNoteUndecoded,
+    db_prefix = DbKeyPrefix::UnitNote,
+);
+impl_db_lookup!(
+    key = UnitNoteKey,
+    query_prefix = UnitNoteKeyPrefix,
+    query_prefix = UnitNoteKeyUnitPrefix,
+);
+
+/// Like [`NextECashNoteIndexKey`], but for notes of a non-bitcoin
+/// [`AmountUnit`]
+#[derive(Debug, Clone, Encodable, Decodable, Serialize)]
+pub struct NextECashUnitNoteIndexKey(pub AmountUnit, pub Amount);
+
+#[derive(Debug, Clone, Encodable, Decodable)]
+pub struct NextECashUnitNoteIndexKeyPrefix;
+
+impl_db_record!(
+    key = NextECashUnitNoteIndexKey,
+    value = u64,
+    db_prefix = DbKeyPrefix::NextECashUnitNoteIndex,
+);
+impl_db_lookup!(
+    key = NextECashUnitNoteIndexKey,
+    query_prefix = NextECashUnitNoteIndexKeyPrefix
+);
+
+/// Key epochs activated by the federation as last fetched by the key rotation
+/// task, new bitcoin notes are requested in the active one
+#[derive(Debug, Clone, Encodable, Decodable, Serialize)]
+pub struct KeyEpochStatusKey;
+
+impl_db_record!(
+    key = KeyEpochStatusKey,
+    value = KeyEpochStatus,
+    db_prefix = DbKeyPrefix::KeyEpochStatus,
+);
+
+/// Public keys of a key epoch after zero as fetched from the federation by the
+/// key rotation task
+#[derive(Debug, Clone, Encodable, Decodable, Serialize)]
+pub struct KeyEpochKeysKey(pub u64);
+
+#[derive(Debug, Clone, Encodable, Decodable)]
+pub struct KeyEpochKeysKeyPrefix;
+
+impl_db_record!(
+    key = KeyEpochKeysKey,
+    value = KeyEpochKeys,
+    db_prefix = DbKeyPrefix::KeyEpochKeys,
+);
+impl_db_lookup!(key = KeyEpochKeysKey, query_prefix = KeyEpochKeysKeyPrefix);
+
+/// Payment requests of ours that we received a payment for, the value is the
+/// id of the operation reissuing the payment
+#[derive(Debug, Clone, Encodable, Decodable, Serialize)]
+pub struct ReceivedEcashPaymentRequestKey(pub OperationId);
+
+#[derive(Debug, Clone, Encodable, Decodable)]
+pub struct ReceivedEcashPaymentRequestKeyPrefix;
+
+impl_db_record!(
+    key = ReceivedEcashPaymentRequestKey,
+    value = OperationId,
+    db_prefix = DbKeyPrefix::ReceivedEcashPaymentRequest,
+);
+impl_db_lookup!(
+    key = ReceivedEcashPaymentRequestKey,
+    query_prefix = ReceivedEcashPaymentRequestKeyPrefix
+);
+
 #[derive(Debug, Clone, Encodable, Decodable, Serialize)]
 pub struct RecoveryStateKey;
 
@@ -0,0 +1,313 @@
+//! Background consolidation of e-cash notes
+//!
+//! [`MintClientModule::consolidate_notes`] only runs as part of transactions
+//! the client makes anyway, so a wallet that mostly receives e-cash can end up
+//! holding a huge number of notes. If enabled via
+//! [`MintClientInit::with_background_consolidation`](crate::MintClientInit::with_background_consolidation),
+//! the client periodically checks its note distribution and reissues excess
+//! notes in a dedicated transaction, paying at most
+//! [`BackgroundConsolidationConfig::max_fee`] per transaction.
+
+use std::iter::repeat_n;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use fedimint_client_module::module::ClientContext;
+use fedimint_client_module::transaction::ClientInput;
+use fedimint_core::core::OperationId;
+use fedimint_core::db::{AutocommitError, DatabaseTransaction};
+use fedimint_core::module::CommonModuleInit as _;
+use fedimint_core::runtime::sleep;
+use fedimint_core::{Amount, TieredCounts};
+use fedimint_logging::LOG_CLIENT_MODULE_MINT;
+use fedimint_mint_common::config::FeeConsensus;
+use fedimint_mint_common::{MintCommonInit, MintInput};
+use tracing::{debug, warn};
+
+use crate::event::NotesConsolidated;
+use crate::{
+    DenominationStrategy, MintClientModule, MintOperationMeta, MintOperationMetaVariant,
+    SpendableNote, create_bundle_for_inputs,
+};
+
+/// Configuration of the background note consolidation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackgroundConsolidationConfig {
+    /// Minimum time between two checks of the note distribution
+    pub check_interval: Duration,
+    /// Maximum fees paid per consolidation transaction, including the fees of
+    /// spending the reissued notes later on
+    pub max_fee: Amount,
+}
+
+impl Default for BackgroundConsolidationConfig {
+    fn default() -> Self {
+        Self {
+            check_interval: Duration::from_secs(10 * 60),
+            max_fee: Amount::from_sats(10),
+        }
+    }
+}
+
+impl MintClientModule {
+    /// Runs until the client shuts down, consolidating notes whenever the
+    /// [`DenominationStrategy`] deems some of them excessive
+    pub(crate) async fn run_background_consolidation(
+        client_ctx: ClientContext<Self>,
+        config: BackgroundConsolidationConfig,
+    ) {
+        let mut balance_updates = client_ctx.self_ref().balance_update_sender.subscribe();
+
+        loop {
+            if let Err(error) = client_ctx
+                .self_ref()
+                .try_consolidate_notes_within_budget(config.max_fee)
+                .await
+            {
+                warn!(target: LOG_CLIENT_MODULE_MINT, err = %error, "Background note consolidation failed");
+            }
+
+            sleep(config.check_interval).await;
+
+            // Our note distribution only changes with our balance
+            if balance_updates.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Submits a transaction reissuing excess notes if the fees stay within
+    /// `max_fee`, returning the id of the consolidation operation
+    pub async fn try_consolidate_notes_within_budget(
+        &self,
+        max_fee: Amount,
+    ) -> anyhow::Result<Option<OperationId>> {
+        let operation_id = OperationId::new_random();
+
+        self.client_ctx
+            .module_db()
+            .autocommit(
+                |dbtx, _| {
+                    Box::pin(async {
+                        let inputs = self.select_consolidation_inputs(dbtx, max_fee).await?;
+
+                        if inputs.is_empty() {
+                            return Ok(None);
+                        }
+
+                        let note_count = inputs.len();
+                        let amount = inputs
+                            .iter()
+                            .map(|(input, _)| input.amounts.get_bitcoin())
+                            .sum::<Amount>();
+
+                        let change_range = self
+                            .client_ctx
+                            .claim_inputs(
+                                dbtx,
+                                create_bundle_for_inputs(inputs, operation_id),
+                                operation_id,
+                            )
+                            .await?;
+
+                        self.client_ctx
+                            .add_operation_log_entry_dbtx(
+                                dbtx,
+                                operation_id,
+                                MintCommonInit::KIND.as_str(),
+                                MintOperationMeta {
+                                    variant: MintOperationMetaVariant::Consolidation {
+                                        txid: change_range.txid(),
+                                        out_point_indices: change_range
+                                            .into_iter()
+                                            .map(|out_point| out_point.out_idx)
+                                            .collect(),
+                                    },
+                                    amount,
+                                    extra_meta: serde_json::Value::Null,
+                                },
+                            )
+                            .await;
+
+                        self.client_ctx
+                            .log_event(
+                                dbtx,
+                                NotesConsolidated {
+                                    operation_id,
+                                    note_count,
+                                    amount,
+                                },
+                            )
+                            .await;
+
+                        anyhow::Ok(Some(operation_id))
+                    })
+                },
+                Some(100),
+            )
+            .await
+            .map_err(|e| match e {
+                AutocommitError::ClosureError { error, .. } => error,
+                AutocommitError::CommitFailed { last_error, .. } => {
+                    anyhow!("Commit to DB failed: {last_error}")
+                }
+            })
+    }
+
+    /// Selects and removes the notes to consolidate, limited such that the
+    /// consolidation fees stay within `max_fee`
+    async fn select_consolidation_inputs(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        max_fee: Amount,
+    ) -> anyhow::Result<Vec<(ClientInput<MintInput>, SpendableNote)>> {
+        let counts = self.get_note_counts_by_denomination(dbtx).await;
+        let excessive_counts = self.denomination_strategy.notes_to_consolidate(&counts);
+        let tiers = self.cfg.tbs_pks.tiers().copied().collect::<Vec<_>>();
+
+        let within_budget = limit_to_fee_budget(
+            &excessive_counts,
+            &counts,
+            self.denomination_strategy.as_ref(),
+            &tiers,
+            &self.cfg.fee_consensus,
+            max_fee,
+        );
+
+        if within_budget.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let (selected_notes, unavailable) = self
+            .get_available_notes_by_tier_counts(dbtx, within_budget)
+            .await;
+
+        debug_assert!(
+            unavailable.is_empty(),
+            "Can't have unavailable notes on a subset of all notes: {unavailable:?}"
+        );
+
+        let mut selected_notes_decoded = vec![];
+        for (amount, note) in selected_notes.iter_items() {
+            let spendable_note_decoded = note.decode()?;
+            debug!(target: LOG_CLIENT_MODULE_MINT, %amount, %note, "Consolidating note in background");
+            Self::delete_spendable_note(&self.client_ctx, dbtx, amount, &spendable_note_decoded)
+                .await;
+            selected_notes_decoded.push((amount, spendable_note_decoded));
+        }
+
+        let sender = self.balance_update_sender.clone();
+        dbtx.on_commit(move || sender.send_replace(()));
+
+        self.create_input_from_notes(selected_notes_decoded.into_iter().collect())
+    }
+}
+
+/// Returns the largest subset of `notes`, preferring small denominations, that
+/// can be consolidated for at most `max_fee` and results in fewer notes.
+///
+/// Like [`MintClientModule::estimate_spend_all_fees`] we never spend notes that
+/// are worth less than their fee.
+pub(crate) fn limit_to_fee_budget(
+    notes: &TieredCounts,
+    current: &TieredCounts,
+    strategy: &dyn DenominationStrategy,
+    tiers: &[Amount],
+    fee_consensus: &FeeConsensus,
+    max_fee: Amount,
+) -> TieredCounts {
+    let mut candidates = notes
+        .iter()
+        .filter(|(tier, _)| fee_consensus.fee(*tier) < *tier)
+        .flat_map(|(tier, count)| repeat_n(tier, count))
+        .collect::<Vec<_>>();
+
+    // Consolidating a single note can't reduce the number of notes
+    while 1 < candidates.len() {
+        let selected = candidates
+            .iter()
+            .fold(TieredCounts::default(), |mut selected, tier| {
+                selected.inc(*tier, 1);
+                selected
+            });
+
+        let mut remaining = current.clone();
+        for tier in &candidates {
+            remaining.dec(*tier);
+        }
+
+        let input_fees = candidates
+            .iter()
+            .map(|tier| fee_consensus.fee(*tier))
+            .sum::<Amount>();
+
+        let Some(reissue_amount) = selected.total_amount().checked_sub(input_fees) else {
+            candidates.pop();
+            continue;
+        };
+
+        let reissued = strategy.denominations(reissue_amount, &remaining, tiers, fee_consensus);
+
+        let fee = selected
+            .total_amount()
+            .saturating_sub(reissued.total_amount());
+
+        if fee <= max_fee && reissued.count_items() < candidates.len() {
+            return selected;
+        }
+
+        candidates.pop();
+    }
+
+    TieredCounts::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use fedimint_core::{Amount, TieredCounts};
+    use fedimint_mint_common::config::FeeConsensus;
+
+    use super::limit_to_fee_budget;
+    use crate::MinimizeNotes;
+
+    fn limit(max_fee: Amount) -> TieredCounts {
+        let current =
+            TieredCounts::from_iter([(Amount::from_msats(64), 8), (Amount::from_msats(1024), 8)]);
+        let tiers = (0..20)
+            .map(|exp| Amount::from_msats(1 << exp))
+            .collect::<Vec<_>>();
+
+        limit_to_fee_budget(
+            &current,
+            &current,
+            &MinimizeNotes,
+            &tiers,
+            &FeeConsensus::new(0).expect("Valid fee consensus"),
+            max_fee,
+        )
+    }
+
+    #[test]
+    fn consolidation_respects_fee_budget() {
+        // Notes worth less than their fee are never consolidated, reissuing eight
+        // 1024 msat notes as four notes costs 1280 msat in fees and rounding
+        assert_eq!(
+            limit(Amount::from_msats(1_280)),
+            TieredCounts::from_iter([(Amount::from_msats(1024), 8)])
+        );
+
+        // Reissuing seven 1024 msat notes as three notes costs 1008 msat
+        assert_eq!(
+            limit(Amount::from_msats(1_279)),
+            TieredCounts::from_iter([(Amount::from_msats(1024), 7)])
+        );
+
+        // Reissuing three 1024 msat notes as two notes costs 512 msat, two notes
+        // can't be consolidated into fewer
+        assert_eq!(
+            limit(Amount::from_msats(512)),
+            TieredCounts::from_iter([(Amount::from_msats(1024), 3)])
+        );
+        assert!(limit(Amount::from_msats(511)).is_empty());
+    }
+}
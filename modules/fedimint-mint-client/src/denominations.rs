@@ -0,0 +1,243 @@
+//! Strategies deciding which e-cash denominations the client holds
+//!
+//! Every time the client receives change or reissues e-cash it has to decide
+//! which denominations to request from the mint, and when to consolidate
+//! excess notes. A wallet making many small payments benefits from holding
+//! more small notes, while a savings wallet wants as few notes as possible.
+//! The strategy can be set via [`MintClientInit`](crate::MintClientInit).
+
+use std::fmt::Debug;
+
+use fedimint_core::{Amount, Tiered, TieredCounts};
+use fedimint_mint_common::config::FeeConsensus;
+
+use crate::represent_amount;
+
+/// Defines a strategy for choosing the denominations of newly issued e-cash
+/// notes based on the notes the client already holds
+pub trait DenominationStrategy: Debug + Send + Sync {
+    /// Returns the denominations to request when issuing e-cash worth
+    /// `amount`, including the fees of later spending the notes.
+    ///
+    /// `tiers` contains all denominations issued by the mint in ascending
+    /// order. The returned notes must be worth at most `amount` and less than
+    /// the fee of a single note below it.
+    fn denominations(
+        &self,
+        amount: Amount,
+        current: &TieredCounts,
+        tiers: &[Amount],
+        fee_consensus: &FeeConsensus,
+    ) -> TieredCounts;
+
+    /// Returns how many notes of each denomination should be spent to reissue
+    /// them into fewer notes, defaults to [`default_notes_to_consolidate`]
+    fn notes_to_consolidate(&self, current: &TieredCounts) -> TieredCounts {
+        default_notes_to_consolidate(current)
+    }
+}
+
+/// Once a denomination crosses a threshold of notes, consolidates some chunk of
+/// them, bounded by the number of inputs per transaction
+pub fn default_notes_to_consolidate(current: &TieredCounts) -> TieredCounts {
+    /// At how many notes of the same denomination should we try to
+    /// consolidate
+    const MAX_NOTES_PER_TIER_TRIGGER: usize = 8;
+    /// Number of notes per tier to leave after threshold was crossed
+    const MIN_NOTES_PER_TIER: usize = 4;
+    /// Maximum number of notes to consolidate per one tx,
+    /// to limit the size of a transaction produced.
+    const MAX_NOTES_TO_CONSOLIDATE_IN_TX: usize = 20;
+    // it's fine, it's just documentation
+    #[allow(clippy::assertions_on_constants)]
+    {
+        assert!(MIN_NOTES_PER_TIER <= MAX_NOTES_PER_TIER_TRIGGER);
+    }
+
+    let should_consolidate = current
+        .iter()
+        .any(|(_, count)| MAX_NOTES_PER_TIER_TRIGGER < count);
+
+    if !should_consolidate {
+        return TieredCounts::default();
+    }
+
+    let mut max_count = MAX_NOTES_TO_CONSOLIDATE_IN_TX;
+
+    current
+        .iter()
+        .map(|(amount, count)| {
+            let take = (count.saturating_sub(MIN_NOTES_PER_TIER)).min(max_count);
+
+            max_count -= take;
+            (amount, take)
+        })
+        .collect()
+}
+
+fn tiers_of(tiers: &[Amount]) -> Tiered<()> {
+    tiers.iter().map(|tier| (*tier, ())).collect()
+}
+
+/// Tries to hold `notes_per_denomination` notes of every denomination,
+/// starting at the lowest one, and represents the rest of the amount with as
+/// few notes as possible. This is the default strategy with two notes per
+/// denomination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetNotesPerDenomination {
+    pub notes_per_denomination: u16,
+}
+
+impl TargetNotesPerDenomination {
+    pub fn new(notes_per_denomination: u16) -> Self {
+        Self {
+            notes_per_denomination,
+        }
+    }
+}
+
+impl Default for TargetNotesPerDenomination {
+    fn default() -> Self {
+        Self::new(2)
+    }
+}
+
+impl DenominationStrategy for TargetNotesPerDenomination {
+    fn denominations(
+        &self,
+        amount: Amount,
+        current: &TieredCounts,
+        tiers: &[Amount],
+        fee_consensus: &FeeConsensus,
+    ) -> TieredCounts {
+        represent_amount(
+            amount,
+            current,
+            &tiers_of(tiers),
+            self.notes_per_denomination,
+            fee_consensus,
+        )
+    }
+}
+
+/// Represents every amount with as few notes as possible, which keeps
+/// transactions and backups small but requires change for most payments. Suits
+/// wallets that mostly receive and rarely spend.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MinimizeNotes;
+
+impl DenominationStrategy for MinimizeNotes {
+    fn denominations(
+        &self,
+        amount: Amount,
+        current: &TieredCounts,
+        tiers: &[Amount],
+        fee_consensus: &FeeConsensus,
+    ) -> TieredCounts {
+        represent_amount(amount, current, &tiers_of(tiers), 0, fee_consensus)
+    }
+}
+
+/// Like [`TargetNotesPerDenomination`], but only targets denominations up to
+/// `max_denomination`, so the client holds many small notes to pay small
+/// amounts without waiting for change while larger amounts are still
+/// represented with few notes. Suits merchants taking many small payments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetSmallDenominations {
+    pub notes_per_denomination: u16,
+    pub max_denomination: Amount,
+}
+
+impl DenominationStrategy for TargetSmallDenominations {
+    fn denominations(
+        &self,
+        amount: Amount,
+        current: &TieredCounts,
+        tiers: &[Amount],
+        fee_consensus: &FeeConsensus,
+    ) -> TieredCounts {
+        // Pretend we already hold enough large notes, so they are only used to
+        // represent whatever remains after targeting the small denominations
+        let current = tiers
+            .iter()
+            .map(|tier| {
+                if *tier <= self.max_denomination {
+                    (*tier, current.get(*tier))
+                } else {
+                    (*tier, usize::from(self.notes_per_denomination))
+                }
+            })
+            .collect::<TieredCounts>();
+
+        represent_amount(
+            amount,
+            &current,
+            &tiers_of(tiers),
+            self.notes_per_denomination,
+            fee_consensus,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fedimint_core::{Amount, TieredCounts};
+    use fedimint_mint_common::config::FeeConsensus;
+
+    use super::{
+        DenominationStrategy, MinimizeNotes, TargetNotesPerDenomination, TargetSmallDenominations,
+    };
+
+    fn tiers() -> Vec<Amount> {
+        (0..10).map(|exp| Amount::from_sats(1 << exp)).collect()
+    }
+
+    #[test]
+    fn strategies_represent_amount() {
+        let amount = Amount::from_sats(1_000);
+        let current = TieredCounts::default();
+
+        let strategies: Vec<Box<dyn DenominationStrategy>> = vec![
+            Box::new(TargetNotesPerDenomination::default()),
+            Box::new(MinimizeNotes),
+            Box::new(TargetSmallDenominations {
+                notes_per_denomination: 5,
+                max_denomination: Amount::from_sats(8),
+            }),
+        ];
+
+        for strategy in strategies {
+            let denominations =
+                strategy.denominations(amount, &current, &tiers(), &FeeConsensus::zero());
+
+            assert_eq!(denominations.total_amount(), amount, "{strategy:?}");
+        }
+    }
+
+    #[test]
+    fn small_denominations_are_targeted() {
+        let denominations = TargetSmallDenominations {
+            notes_per_denomination: 5,
+            max_denomination: Amount::from_sats(2),
+        }
+        .denominations(
+            Amount::from_sats(999),
+            &TieredCounts::default(),
+            &tiers(),
+            &FeeConsensus::zero(),
+        );
+
+        assert_eq!(denominations.get(Amount::from_sats(1)), 5);
+        assert_eq!(denominations.get(Amount::from_sats(2)), 5);
+        assert_eq!(denominations.get(Amount::from_sats(4)), 0);
+
+        let minimal = MinimizeNotes.denominations(
+            Amount::from_sats(999),
+            &TieredCounts::default(),
+            &tiers(),
+            &FeeConsensus::zero(),
+        );
+
+        assert_eq!(minimal.count_items(), 8);
+    }
+}
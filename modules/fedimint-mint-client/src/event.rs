@@ -58,6 +58,88 @@ impl Event for OOBNotesSpent {
     const PERSISTENCE: EventPersistence = EventPersistence::Persistent;
 }
 
+/// Event that is emitted when excess notes are consolidated in the background
+#[derive(Serialize, Deserialize)]
+pub struct NotesConsolidated {
+    /// The operation of the consolidation transaction
+    pub operation_id: OperationId,
+
+    /// The number of notes being reissued
+    pub note_count: usize,
+
+    /// The total amount of the notes being reissued
+    pub amount: Amount,
+}
+
+impl Event for NotesConsolidated {
+    const MODULE: Option<ModuleKind> = Some(KIND);
+    const KIND: EventKind = EventKind::from_static("notes-consolidated");
+    const PERSISTENCE: EventPersistence = EventPersistence::Persistent;
+}
+
+/// Event that is emitted when notes of a deprecated key epoch are reissued to
+/// the active one
+#[derive(Serialize, Deserialize)]
+pub struct NotesReissuedToKeyEpoch {
+    /// The operation of the reissuance transaction
+    pub operation_id: OperationId,
+
+    /// The key epoch the notes are reissued to
+    pub key_epoch: u64,
+
+    /// The number of notes being reissued
+    pub note_count: usize,
+
+    /// The total amount of the notes being reissued
+    pub amount: Amount,
+}
+
+impl Event for NotesReissuedToKeyEpoch {
+    const MODULE: Option<ModuleKind> = Some(KIND);
+    const KIND: EventKind = EventKind::from_static("notes-reissued-to-key-epoch");
+    const PERSISTENCE: EventPersistence = EventPersistence::Persistent;
+}
+
+/// Event that is emitted when we delivered the payment for an e-cash payment
+/// request
+#[derive(Serialize, Deserialize)]
+pub struct EcashPaymentRequestPaid {
+    /// The operation that spent the notes
+    pub operation_id: OperationId,
+
+    /// The id of the paid request
+    pub request_id: OperationId,
+
+    /// The amount of the delivered notes
+    pub amount: Amount,
+}
+
+impl Event for EcashPaymentRequestPaid {
+    const MODULE: Option<ModuleKind> = Some(KIND);
+    const KIND: EventKind = EventKind::from_static("ecash-payment-request-paid");
+    const PERSISTENCE: EventPersistence = EventPersistence::Persistent;
+}
+
+/// Event that is emitted when a payment for one of our e-cash payment requests
+/// is received
+#[derive(Serialize, Deserialize)]
+pub struct EcashPaymentReceived {
+    /// The operation reissuing the received notes
+    pub operation_id: OperationId,
+
+    /// The id of the paid request
+    pub request_id: OperationId,
+
+    /// The amount of the received notes
+    pub amount: Amount,
+}
+
+impl Event for EcashPaymentReceived {
+    const MODULE: Option<ModuleKind> = Some(KIND);
+    const KIND: EventKind = EventKind::from_static("ecash-payment-received");
+    const PERSISTENCE: EventPersistence = EventPersistence::Persistent;
+}
+
 /// Event that is emitted when out of band ecash is reissued
 #[derive(Serialize, Deserialize)]
 pub struct OOBNotesReissued {
@@ -0,0 +1,89 @@
+//! Transfer of [`OOBNotes`] via animated QR codes
+//!
+//! Serialized e-cash quickly becomes too large to fit into a single QR code.
+//! [`OOBNotesFrameEncoder`] splits the notes into an endless sequence of
+//! fountain-encoded text frames, each small enough for one QR code. The
+//! receiver feeds scanned frames into an [`OOBNotesFrameDecoder`] in any order
+//! until it returns the notes, so missed frames don't require starting over.
+
+use anyhow::{Context as _, bail};
+use fedimint_core::base32;
+use fedimint_fountain::{FountainDecoder, FountainEncoder, Fragment};
+
+use crate::OOBNotes;
+
+/// Prefix of a base32 encoded frame of [`OOBNotes`]
+pub const OOB_NOTES_FRAME_PREFIX: &str = "fmframe";
+
+/// Default maximum number of payload bytes per frame, which results in QR codes
+/// that are still easy to scan from a phone screen
+pub const DEFAULT_MAX_FRAGMENT_LENGTH: usize = 200;
+
+/// Splits [`OOBNotes`] into fountain-encoded text frames
+pub struct OOBNotesFrameEncoder {
+    encoder: FountainEncoder,
+}
+
+impl OOBNotesFrameEncoder {
+    pub fn new(oob_notes: &OOBNotes, max_fragment_length: usize) -> Self {
+        Self {
+            encoder: FountainEncoder::new(oob_notes, max_fragment_length),
+        }
+    }
+
+    /// The minimum number of frames a receiver has to scan to reassemble the
+    /// notes
+    pub fn min_frame_count(&self) -> usize {
+        self.encoder.fragment_count()
+    }
+
+    /// Returns the next frame, frames never repeat so this can be called
+    /// indefinitely
+    ///
+    /// Frames are lowercase, but can be uppercased before rendering them as a
+    /// QR code to make use of the more compact alphanumeric mode.
+    pub fn next_frame(&mut self) -> String {
+        base32::encode_prefixed(OOB_NOTES_FRAME_PREFIX, &self.encoder.next_fragment())
+    }
+
+    /// Returns the next `count` frames
+    pub fn next_frames(&mut self, count: usize) -> Vec<String> {
+        (0..count).map(|_| self.next_frame()).collect()
+    }
+}
+
+/// Reassembles [`OOBNotes`] from frames created by an [`OOBNotesFrameEncoder`]
+#[derive(Default)]
+pub struct OOBNotesFrameDecoder {
+    decoder: FountainDecoder<OOBNotes>,
+}
+
+impl OOBNotesFrameDecoder {
+    /// Adds a scanned frame and returns the notes once enough frames were
+    /// added. A frame belonging to a different set of notes resets the
+    /// decoder.
+    pub fn add_frame(&mut self, frame: &str) -> anyhow::Result<Option<OOBNotes>> {
+        let frame: String = frame.chars().filter(|c| !c.is_whitespace()).collect();
+
+        let fragment: Fragment = base32::decode_prefixed(OOB_NOTES_FRAME_PREFIX, &frame)
+            .context("Not a valid e-cash frame")?;
+
+        Ok(self.decoder.add_fragment(&fragment))
+    }
+}
+
+/// Reassembles [`OOBNotes`] from a set of frames, failing if they are not
+/// sufficient to do so
+pub fn decode_oob_notes_frames<'a>(
+    frames: impl IntoIterator<Item = &'a str>,
+) -> anyhow::Result<OOBNotes> {
+    let mut decoder = OOBNotesFrameDecoder::default();
+
+    for frame in frames {
+        if let Some(oob_notes) = decoder.add_frame(frame)? {
+            return Ok(oob_notes);
+        }
+    }
+
+    bail!("Not enough frames to reassemble the e-cash notes")
+}
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use assert_matches::assert_matches;
 use fedimint_client_module::DynGlobalClientContext;
 use fedimint_client_module::module::OutPointRange;
@@ -5,13 +7,13 @@
 use fedimint_client_module::transaction::{ClientInput, ClientInputBundle};
 use fedimint_core::core::OperationId;
 use fedimint_core::encoding::{Decodable, Encodable};
-use fedimint_core::module::Amounts;
+use fedimint_core::module::{AmountUnit, Amounts};
 use fedimint_core::{Amount, TransactionId};
 use fedimint_logging::LOG_CLIENT_MODULE_MINT;
-use fedimint_mint_common::MintInput;
+use fedimint_mint_common::{KeyEpochKeys, MintInput};
 use tracing::{debug, warn};
 
-use crate::{MintClientContext, SpendableNote};
+use crate::{MintClientContext, SpendableNote, bitcoin_input};
 
 #[cfg_attr(doc, aquamarine::aquamarine)]
 // TODO: add retry with valid subset of e-cash notes
@@ -45,6 +47,8 @@ pub enum MintInputStates {
     RefundedBundle(MintInputStateRefundedBundle),
     /// Refunded note via multiple single-note transactions
     RefundedPerNote(MintInputStateRefundedPerNote),
+    /// Like [`Self::CreatedBundle`], but for notes of a non-bitcoin unit
+    CreatedBundleUnit(MintInputStateCreatedBundleUnit),
 }
 
 #[derive(Debug, Clone, Eq, Hash, PartialEq, Decodable, Encodable)]
@@ -78,18 +82,21 @@ impl State for MintInputStateMachine {
     #[allow(deprecated)]
     fn transitions(
         &self,
-        _context: &Self::ModuleContext,
+        context: &Self::ModuleContext,
         global_context: &DynGlobalClientContext,
     ) -> Vec<StateTransition<Self>> {
         match &self.state {
             MintInputStates::Created(_) => {
-                MintInputStateCreated::transitions(self.common, global_context)
+                MintInputStateCreated::transitions(self.common, context, global_context)
             }
             MintInputStates::CreatedBundle(_) => {
-                MintInputStateCreatedBundle::transitions(self.common, global_context)
+                MintInputStateCreatedBundle::transitions(self.common, context, global_context)
             }
             MintInputStates::RefundedBundle(_) => {
-                MintInputStateRefundedBundle::transitions(self.common, global_context)
+                MintInputStateRefundedBundle::transitions(self.common, context, global_context)
+            }
+            MintInputStates::CreatedBundleUnit(_) => {
+                MintInputStateCreatedBundleUnit::transitions(self.common, global_context)
             }
             MintInputStates::Refund(refund) => refund.transitions(global_context),
             MintInputStates::Success(_)
@@ -117,9 +124,11 @@ pub struct MintInputStateCreated {
 impl MintInputStateCreated {
     fn transitions(
         common: MintInputCommon,
+        context: &MintClientContext,
         global_context: &DynGlobalClientContext,
     ) -> Vec<StateTransition<MintInputStateMachine>> {
         let global_context = global_context.clone();
+        let key_epoch_keys = context.key_epoch_keys.borrow().clone();
         vec![StateTransition::new(
             Self::await_success(common, global_context.clone()),
             move |dbtx, result, old_state| {
@@ -128,6 +137,7 @@ fn transitions(
                     old_state,
                     dbtx,
                     global_context.clone(),
+                    key_epoch_keys.clone(),
                 ))
             },
         )]
@@ -148,6 +158,7 @@ async fn transition_success(
         old_state: MintInputStateMachine,
         dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
         global_context: DynGlobalClientContext,
+        key_epoch_keys: BTreeMap<u64, KeyEpochKeys>,
     ) -> MintInputStateMachine {
         assert_matches!(old_state.state, MintInputStates::Created(_));
 
@@ -162,7 +173,7 @@ async fn transition_success(
             Err(err) => {
                 // Transaction rejected: attempting to refund
                 debug!(target: LOG_CLIENT_MODULE_MINT, err = %err.as_str(), "Refunding mint transaction input due to transaction error");
-                Self::refund(dbtx, old_state, global_context).await
+                Self::refund(dbtx, old_state, global_context, key_epoch_keys).await
             }
         }
     }
@@ -172,6 +183,7 @@ async fn refund(
         dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
         old_state: MintInputStateMachine,
         global_context: DynGlobalClientContext,
+        key_epoch_keys: BTreeMap<u64, KeyEpochKeys>,
     ) -> MintInputStateMachine {
         let (amount, spendable_note) = match old_state.state {
             MintInputStates::Created(created) => (created.amount, created.spendable_note),
@@ -179,7 +191,7 @@ async fn refund(
         };
 
         let refund_input = ClientInput::<MintInput> {
-            input: MintInput::new_v0(amount, spendable_note.note()),
+            input: bitcoin_input(&key_epoch_keys, amount, spendable_note.note()),
             keys: vec![spendable_note.spend_key],
             amounts: Amounts::new_bitcoin(amount),
         };
@@ -211,9 +223,11 @@ pub struct MintInputStateCreatedBundle {
 impl MintInputStateCreatedBundle {
     fn transitions(
         common: MintInputCommon,
+        context: &MintClientContext,
         global_context: &DynGlobalClientContext,
     ) -> Vec<StateTransition<MintInputStateMachine>> {
         let global_context = global_context.clone();
+        let key_epoch_keys = context.key_epoch_keys.borrow().clone();
         vec![StateTransition::new(
             Self::await_success(common, global_context.clone()),
             move |dbtx, result, old_state| {
@@ -222,6 +236,7 @@ fn transitions(
                     old_state,
                     dbtx,
                     global_context.clone(),
+                    key_epoch_keys.clone(),
                 ))
             },
         )]
@@ -241,6 +256,7 @@ async fn transition_success(
         old_state: MintInputStateMachine,
         dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
         global_context: DynGlobalClientContext,
+        key_epoch_keys: BTreeMap<u64, KeyEpochKeys>,
     ) -> MintInputStateMachine {
         assert_matches!(old_state.state, MintInputStates::CreatedBundle(_));
 
@@ -255,7 +271,7 @@ async fn transition_success(
             Err(err) => {
                 // Transaction rejected: attempting to refund
                 debug!(target: LOG_CLIENT_MODULE_MINT, err = %err.as_str(), "Refunding mint transaction input due to transaction error");
-                Self::refund(dbtx, old_state, global_context).await
+                Self::refund(dbtx, old_state, global_context, key_epoch_keys).await
             }
         }
     }
@@ -264,6 +280,7 @@ async fn refund(
         dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
         old_state: MintInputStateMachine,
         global_context: DynGlobalClientContext,
+        key_epoch_keys: BTreeMap<u64, KeyEpochKeys>,
     ) -> MintInputStateMachine {
         let spendable_notes = match old_state.state {
             MintInputStates::CreatedBundle(created) => created.notes,
@@ -274,7 +291,7 @@ async fn refund(
 
         for (amount, spendable_note) in spendable_notes.clone() {
             inputs.push(ClientInput::<MintInput> {
-                input: MintInput::new_v0(amount, spendable_note.note()),
+                input: bitcoin_input(&key_epoch_keys, amount, spendable_note.note()),
                 keys: vec![spendable_note.spend_key],
                 amounts: Amounts::new_bitcoin(amount),
             });
@@ -307,6 +324,90 @@ async fn refund(
     }
 }
 
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
+pub struct MintInputStateCreatedBundleUnit {
+    pub(crate) unit: AmountUnit,
+    pub(crate) notes: Vec<(Amount, SpendableNote)>,
+}
+
+impl MintInputStateCreatedBundleUnit {
+    fn transitions(
+        common: MintInputCommon,
+        global_context: &DynGlobalClientContext,
+    ) -> Vec<StateTransition<MintInputStateMachine>> {
+        let global_context = global_context.clone();
+        vec![StateTransition::new(
+            MintInputStateCreatedBundle::await_success(common, global_context.clone()),
+            move |dbtx, result, old_state| {
+                Box::pin(Self::transition_success(
+                    result,
+                    old_state,
+                    dbtx,
+                    global_context.clone(),
+                ))
+            },
+        )]
+    }
+
+    async fn transition_success(
+        result: Result<(), String>,
+        old_state: MintInputStateMachine,
+        dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
+        global_context: DynGlobalClientContext,
+    ) -> MintInputStateMachine {
+        assert_matches!(old_state.state, MintInputStates::CreatedBundleUnit(_));
+
+        match result {
+            Ok(()) => MintInputStateMachine {
+                common: old_state.common,
+                state: MintInputStates::Success(MintInputStateSuccess {}),
+            },
+            Err(err) => {
+                debug!(target: LOG_CLIENT_MODULE_MINT, err = %err.as_str(), "Refunding mint transaction input due to transaction error");
+                Self::refund(dbtx, old_state, global_context).await
+            }
+        }
+    }
+
+    /// Unlike bitcoin bundles we don't fall back to per-note refunds, so a
+    /// single invalid note makes the refund fail
+    async fn refund(
+        dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
+        old_state: MintInputStateMachine,
+        global_context: DynGlobalClientContext,
+    ) -> MintInputStateMachine {
+        let MintInputStates::CreatedBundleUnit(created) = old_state.state else {
+            panic!("Invalid state transition")
+        };
+
+        let inputs = created
+            .notes
+            .into_iter()
+            .map(|(amount, spendable_note)| ClientInput::<MintInput> {
+                input: MintInput::new_for_unit(amount, created.unit, spendable_note.note()),
+                keys: vec![spendable_note.spend_key],
+                amounts: Amounts::new_custom(created.unit, amount),
+            })
+            .collect();
+
+        let change_range = global_context
+            .claim_inputs(
+                dbtx,
+                // We are inside an input state machine, so no need to spawn new ones
+                ClientInputBundle::new_no_sm(inputs),
+            )
+            .await
+            .expect("Cannot claim input, additional funding needed");
+
+        MintInputStateMachine {
+            common: old_state.common,
+            state: MintInputStates::Refund(MintInputStateRefund {
+                refund_txid: change_range.txid(),
+            }),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
 pub struct MintInputStateRefundedBundle {
     pub(crate) refund_txid: TransactionId,
@@ -316,9 +417,11 @@ pub struct MintInputStateRefundedBundle {
 impl MintInputStateRefundedBundle {
     fn transitions(
         common: MintInputCommon,
+        context: &MintClientContext,
         global_context: &DynGlobalClientContext,
     ) -> Vec<StateTransition<MintInputStateMachine>> {
         let global_context = global_context.clone();
+        let key_epoch_keys = context.key_epoch_keys.borrow().clone();
         vec![StateTransition::new(
             Self::await_success(common, global_context.clone()),
             move |dbtx, result, old_state| {
@@ -327,6 +430,7 @@ fn transitions(
                     old_state,
                     dbtx,
                     global_context.clone(),
+                    key_epoch_keys.clone(),
                 ))
             },
         )]
@@ -346,6 +450,7 @@ async fn transition_success(
         old_state: MintInputStateMachine,
         dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
         global_context: DynGlobalClientContext,
+        key_epoch_keys: BTreeMap<u64, KeyEpochKeys>,
     ) -> MintInputStateMachine {
         assert_matches!(old_state.state, MintInputStates::RefundedBundle(_));
 
@@ -360,7 +465,7 @@ async fn transition_success(
             Err(err) => {
                 // Transaction rejected: attempting to refund
                 debug!(target: LOG_CLIENT_MODULE_MINT, err = %err.as_str(), "Refunding mint transaction input due to transaction error on multi-note refund");
-                Self::refund(dbtx, old_state, global_context).await
+                Self::refund(dbtx, old_state, global_context, key_epoch_keys).await
             }
         }
     }
@@ -369,6 +474,7 @@ async fn refund(
         dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
         old_state: MintInputStateMachine,
         global_context: DynGlobalClientContext,
+        key_epoch_keys: BTreeMap<u64, KeyEpochKeys>,
     ) -> MintInputStateMachine {
         let spendable_notes = match old_state.state {
             MintInputStates::RefundedBundle(created) => created.spendable_notes,
@@ -378,7 +484,7 @@ async fn refund(
         let mut refund_txids = vec![];
         for (amount, spendable_note) in spendable_notes {
             let refund_input = ClientInput::<MintInput> {
-                input: MintInput::new_v0(amount, spendable_note.note()),
+                input: bitcoin_input(&key_epoch_keys, amount, spendable_note.note()),
                 keys: vec![spendable_note.spend_key],
                 amounts: Amounts::new_bitcoin(amount),
             };
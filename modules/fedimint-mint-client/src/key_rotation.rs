@@ -0,0 +1,230 @@
+//! Reissuance of e-cash notes of deprecated key epochs
+//!
+//! Federations that rotate their mint keys stop accepting notes of the
+//! previous key epoch once its deprecation window has passed. If the
+//! federation supports key rotation, the client periodically fetches the
+//! active key epoch and its keys from the federation and reissues all notes of
+//! older epochs before they become unspendable.
+
+use std::time::Duration;
+
+use anyhow::anyhow;
+use fedimint_client_module::module::ClientContext;
+use fedimint_core::core::OperationId;
+use fedimint_core::db::{AutocommitError, IDatabaseTransactionOpsCoreTyped as _};
+use fedimint_core::module::{AmountUnit, CommonModuleInit as _};
+use fedimint_core::runtime::sleep;
+use fedimint_core::time::duration_since_epoch;
+use fedimint_core::{Amount, TieredMulti};
+use fedimint_logging::LOG_CLIENT_MODULE_MINT;
+use fedimint_mint_common::MintCommonInit;
+use tracing::{debug, warn};
+
+use crate::api::MintFederationApi as _;
+use crate::client_db::{KeyEpochKeysKey, KeyEpochStatusKey};
+use crate::event::NotesReissuedToKeyEpoch;
+use crate::{
+    MintClientModule, MintOperationMeta, MintOperationMetaVariant, create_bundle_for_inputs,
+};
+
+/// Time between two checks of the active key epoch
+const KEY_ROTATION_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+impl MintClientModule {
+    /// Runs until the client shuts down, reissuing notes of deprecated key
+    /// epochs whenever the federation activates a new one
+    pub(crate) async fn run_key_rotation(client_ctx: ClientContext<Self>) {
+        loop {
+            if let Err(error) = client_ctx.self_ref().try_reissue_deprecated_notes().await {
+                warn!(target: LOG_CLIENT_MODULE_MINT, err = %error, "Reissuing notes of deprecated key epochs failed");
+            }
+
+            sleep(KEY_ROTATION_CHECK_INTERVAL).await;
+        }
+    }
+
+    /// Asks our guardian to vote for rotating the mint keys, returning the key
+    /// epoch voted for. Requires the admin auth of the guardian.
+    ///
+    /// Once a threshold of guardians voted for it they generate the keys of
+    /// the key epoch and activate it.
+    pub async fn rotate_key_epoch(&self) -> anyhow::Result<u64> {
+        let auth = self
+            .admin_auth
+            .clone()
+            .ok_or_else(|| anyhow!("Admin auth not set"))?;
+
+        Ok(self.client_ctx.module_api().rotate_key_epoch(auth).await?)
+    }
+
+    /// Fetches the active key epoch and the keys of all key epochs we don't
+    /// know yet from the federation and submits a transaction reissuing all
+    /// notes of older key epochs, returning the id of the reissuance operation
+    ///
+    /// Notes of retired key epochs can't be redeemed anymore and are left
+    /// untouched.
+    #[allow(clippy::too_many_lines)]
+    pub async fn try_reissue_deprecated_notes(&self) -> anyhow::Result<Option<OperationId>> {
+        let status = self
+            .client_ctx
+            .module_api()
+            .fetch_key_epoch_status()
+            .await?;
+
+        let mut new_key_epoch_keys = vec![];
+
+        for key_epoch in 1..=status.active {
+            if self.key_epoch_keys.borrow().contains_key(&key_epoch) {
+                continue;
+            }
+
+            let keys = self
+                .client_ctx
+                .module_api()
+                .fetch_key_epoch_keys(key_epoch)
+                .await?
+                .ok_or_else(|| anyhow!("Federation has no keys for key epoch {key_epoch}"))?;
+
+            new_key_epoch_keys.push((key_epoch, keys));
+        }
+
+        let operation_id = OperationId::new_random();
+
+        self.client_ctx
+            .module_db()
+            .autocommit(
+                |dbtx, _| {
+                    let status = status.clone();
+                    let new_key_epoch_keys = new_key_epoch_keys.clone();
+                    Box::pin(async move {
+                        for (key_epoch, keys) in &new_key_epoch_keys {
+                            dbtx.insert_entry(&KeyEpochKeysKey(*key_epoch), keys).await;
+                        }
+
+                        // The keys are fetched with the agreement of a threshold of guardians,
+                        // so we can use them to recognize our notes before the commit
+                        self.key_epoch_keys.send_modify(|key_epoch_keys| {
+                            key_epoch_keys.extend(new_key_epoch_keys);
+                        });
+
+                        dbtx.insert_entry(&KeyEpochStatusKey, &status).await;
+
+                        if status.active == 0 {
+                            return Ok(None);
+                        }
+
+                        let fee_consensus = self
+                            .cfg
+                            .fee_consensus_for_unit(AmountUnit::BITCOIN)
+                            .expect("The mint always issues bitcoin e-cash");
+
+                        let now = duration_since_epoch().as_secs();
+
+                        let mut deprecated_notes = vec![];
+                        for (amount, note) in
+                            Self::get_all_spendable_notes(dbtx).await.into_iter_items()
+                        {
+                            // Notes worth less than their fee are never spent
+                            if amount <= fee_consensus.fee(amount) {
+                                continue;
+                            }
+
+                            let note = note.decode()?;
+                            let key_epoch =
+                                self.find_key_epoch(AmountUnit::BITCOIN, amount, |key| {
+                                    note.note().verify(key)
+                                });
+
+                            match key_epoch {
+                                Some(key_epoch) if key_epoch == status.active => {}
+                                Some(key_epoch)
+                                    if status
+                                        .retirements
+                                        .get(&key_epoch)
+                                        .is_none_or(|retirement| now < *retirement) =>
+                                {
+                                    deprecated_notes.push((amount, note));
+                                }
+                                _ => {
+                                    warn!(target: LOG_CLIENT_MODULE_MINT, %amount, %note, "Holding note that can't be redeemed anymore");
+                                }
+                            }
+                        }
+
+                        if deprecated_notes.is_empty() {
+                            return Ok(None);
+                        }
+
+                        for (amount, note) in &deprecated_notes {
+                            debug!(target: LOG_CLIENT_MODULE_MINT, %amount, %note, "Reissuing note of deprecated key epoch");
+                            Self::delete_spendable_note(&self.client_ctx, dbtx, *amount, note)
+                                .await;
+                        }
+
+                        let sender = self.balance_update_sender.clone();
+                        dbtx.on_commit(move || sender.send_replace(()));
+
+                        let note_count = deprecated_notes.len();
+                        let amount = deprecated_notes
+                            .iter()
+                            .map(|(amount, _)| *amount)
+                            .sum::<Amount>();
+
+                        let inputs = self.create_input_from_notes(
+                            deprecated_notes.into_iter().collect::<TieredMulti<_>>(),
+                        )?;
+
+                        let change_range = self
+                            .client_ctx
+                            .claim_inputs(
+                                dbtx,
+                                create_bundle_for_inputs(inputs, operation_id),
+                                operation_id,
+                            )
+                            .await?;
+
+                        self.client_ctx
+                            .add_operation_log_entry_dbtx(
+                                dbtx,
+                                operation_id,
+                                MintCommonInit::KIND.as_str(),
+                                MintOperationMeta {
+                                    variant: MintOperationMetaVariant::KeyRotation {
+                                        txid: change_range.txid(),
+                                        out_point_indices: change_range
+                                            .into_iter()
+                                            .map(|out_point| out_point.out_idx)
+                                            .collect(),
+                                    },
+                                    amount,
+                                    extra_meta: serde_json::Value::Null,
+                                },
+                            )
+                            .await;
+
+                        self.client_ctx
+                            .log_event(
+                                dbtx,
+                                NotesReissuedToKeyEpoch {
+                                    operation_id,
+                                    key_epoch: status.active,
+                                    note_count,
+                                    amount,
+                                },
+                            )
+                            .await;
+
+                        anyhow::Ok(Some(operation_id))
+                    })
+                },
+                Some(100),
+            )
+            .await
+            .map_err(|e| match e {
+                AutocommitError::ClosureError { error, .. } => error,
+                AutocommitError::CommitFailed { last_error, .. } => {
+                    anyhow!("Commit to DB failed: {last_error}")
+                }
+            })
+    }
+}
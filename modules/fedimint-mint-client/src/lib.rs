@@ -13,12 +13,24 @@
 mod cli;
 /// Database keys used throughout the mint client module
 pub mod client_db;
+/// Background consolidation of e-cash notes
+pub mod consolidation;
+/// Strategies deciding which e-cash denominations the client holds
+pub mod denominations;
+/// Animated QR code transfer of out-of-band e-cash notes
+pub mod fountain;
 /// State machines for mint inputs
 mod input;
+/// Reissuance of e-cash notes of deprecated key epochs
+pub mod key_rotation;
 /// State machines for out-of-band transmitted e-cash notes
 mod oob;
 /// State machines for mint outputs
 pub mod output;
+/// E-cash notes locked to a public key
+pub mod p2pk;
+/// Receiver-initiated e-cash payments
+pub mod payment_request;
 
 pub mod event;
 
@@ -75,11 +87,14 @@
 use fedimint_core::invite_code::InviteCode;
 use fedimint_core::module::registry::{ModuleDecoderRegistry, ModuleRegistry};
 use fedimint_core::module::{
-    AmountUnit, Amounts, ApiVersion, CommonModuleInit, ModuleCommon, ModuleInit, MultiApiVersion,
+    AmountUnit, Amounts, ApiAuth, ApiVersion, CommonModuleInit, ModuleCommon, ModuleInit,
+    MultiApiVersion,
 };
 use fedimint_core::secp256k1::rand::prelude::IteratorRandom;
 use fedimint_core::secp256k1::rand::thread_rng;
-use fedimint_core::secp256k1::{All, Keypair, Secp256k1};
+use fedimint_core::secp256k1::{All, Keypair, PublicKey, Secp256k1};
+use fedimint_core::task::TaskGroup;
+use fedimint_core::time::duration_since_epoch;
 use fedimint_core::util::{BoxFuture, BoxStream, NextOrPending, SafeUrl};
 use fedimint_core::{
     Amount, OutPoint, PeerId, Tiered, TieredCounts, TieredMulti, TransactionId, apply,
@@ -88,15 +103,17 @@
 use fedimint_derive_secret::{ChildId, DerivableSecret};
 use fedimint_logging::LOG_CLIENT_MODULE_MINT;
 pub use fedimint_mint_common as common;
-use fedimint_mint_common::config::{FeeConsensus, MintClientConfig};
+use fedimint_mint_common::config::{FeeConsensus, MintClientConfig, MintUnitClientConfig};
 pub use fedimint_mint_common::*;
-use futures::future::try_join_all;
-use futures::{StreamExt, pin_mut};
+use futures::{StreamExt, TryStreamExt, pin_mut};
 use hex::ToHex;
-use input::MintInputStateCreatedBundle;
+use input::{MintInputStateCreatedBundle, MintInputStateCreatedBundleUnit};
 use itertools::Itertools as _;
 use oob::MintOOBStatesCreatedMulti;
-use output::MintOutputStatesCreatedMulti;
+use output::{
+    MintOutputStatesCreatedMulti, MintOutputStatesCreatedMultiKeyEpoch,
+    MintOutputStatesCreatedMultiUnit,
+};
 use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 use tbs::AggregatePublicKey;
@@ -105,16 +122,45 @@
 
 use crate::backup::EcashBackup;
 use crate::client_db::{
-    CancelledOOBSpendKey, CancelledOOBSpendKeyPrefix, NextECashNoteIndexKey,
-    NextECashNoteIndexKeyPrefix, NoteKey,
+    CancelledOOBSpendKey, CancelledOOBSpendKeyPrefix, KeyEpochKeysKeyPrefix, KeyEpochStatusKey,
+    NextECashNoteIndexKey, NextECashNoteIndexKeyPrefix, NextECashUnitNoteIndexKey,
+    NextECashUnitNoteIndexKeyPrefix, NoteKey, ReceivedEcashPaymentRequestKeyPrefix, UnitNoteKey,
+    UnitNoteKeyPrefix, UnitNoteKeyUnitPrefix,
+};
+pub use crate::consolidation::BackgroundConsolidationConfig;
+pub use crate::denominations::{
+    DenominationStrategy, MinimizeNotes, TargetNotesPerDenomination, TargetSmallDenominations,
 };
+use crate::fountain::{DEFAULT_MAX_FRAGMENT_LENGTH, OOBNotesFrameDecoder, OOBNotesFrameEncoder};
 use crate::input::{MintInputCommon, MintInputStateMachine, MintInputStates};
 use crate::oob::{MintOOBStateMachine, MintOOBStates};
 use crate::output::{
-    MintOutputCommon, MintOutputStateMachine, MintOutputStates, NoteIssuanceRequest,
+    MintOutputCommon, MintOutputStateMachine, MintOutputStates, MintOutputStatesCreatedLocked,
+    MintOutputStatesCreatedLockedKeyEpoch, NoteIssuanceRequest,
 };
+use crate::p2pk::P2pkNotes;
+pub use crate::payment_request::{EcashPayment, EcashPaymentRequest, EcashPaymentTarget};
+
+/// First module API version supporting
+/// [`NOTES_SPENT_ENDPOINT`](fedimint_mint_common::endpoint_constants::NOTES_SPENT_ENDPOINT)
+const VERSION_THAT_INTRODUCED_NOTES_SPENT: ApiVersion = ApiVersion::new(0, 2);
+
+/// First module API version supporting
+/// [`KEY_EPOCH_KEYS_ENDPOINT`](fedimint_mint_common::endpoint_constants::KEY_EPOCH_KEYS_ENDPOINT),
+/// federations predating it never rotate their keys
+const VERSION_THAT_INTRODUCED_KEY_ROTATION: ApiVersion = ApiVersion::new(0, 4);
+
+/// Number of concurrent requests when checking the spent status of notes one
+/// by one with federations predating [`VERSION_THAT_INTRODUCED_NOTES_SPENT`]
+const CHECK_NOTE_SPENT_PARALLELISM: usize = 16;
 
 const MINT_E_CASH_TYPE_CHILD_ID: ChildId = ChildId(0);
+/// Notes of non-bitcoin units are derived from a separate subtree (further
+/// split by unit), so their note indices can never collide with bitcoin ones
+const MINT_E_CASH_CUSTOM_UNIT_TYPE_CHILD_ID: ChildId = ChildId(1);
+/// Key that [`P2pkNotes`] sent to us and refunds of the ones we sent are
+/// locked to
+const MINT_P2PK_KEY_CHILD_ID: ChildId = ChildId(2);
 
 #[derive(Clone)]
 struct PeerSelector {
@@ -548,11 +594,66 @@ pub enum MintOperationMetaVariant {
     SpendOOB {
         requested_amount: Amount,
         oob_notes: OOBNotes,
+        /// The request the notes were delivered for, see
+        /// [`MintClientModule::pay_ecash_payment_request`]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        payment_request: Option<EcashPaymentRequest>,
+    },
+    /// Issuance of notes locked to the recipient's key, see
+    /// [`MintClientModule::send_p2pk_notes`]
+    SendP2pk { lock: P2pkLock },
+    /// Reissuance of excess notes, see
+    /// [`MintClientModule::try_consolidate_notes_within_budget`]
+    Consolidation {
+        txid: TransactionId,
+        out_point_indices: Vec<u64>,
+    },
+    /// Reissuance of notes of deprecated key epochs, see
+    /// [`MintClientModule::try_reissue_deprecated_notes`]
+    KeyRotation {
+        txid: TransactionId,
+        out_point_indices: Vec<u64>,
     },
+    /// A request for a payment we created, see
+    /// [`MintClientModule::create_ecash_payment_request`]
+    PaymentRequest { request: EcashPaymentRequest },
 }
 
 #[derive(Debug, Clone)]
-pub struct MintClientInit;
+pub struct MintClientInit {
+    /// Decides the denominations of e-cash issued to the client, defaults to
+    /// [`TargetNotesPerDenomination`]
+    pub denomination_strategy: Arc<dyn DenominationStrategy>,
+    /// Consolidates excess notes in the background if set, disabled by
+    /// default
+    pub background_consolidation: Option<BackgroundConsolidationConfig>,
+}
+
+impl MintClientInit {
+    pub fn with_denomination_strategy(
+        mut self,
+        denomination_strategy: impl DenominationStrategy + 'static,
+    ) -> Self {
+        self.denomination_strategy = Arc::new(denomination_strategy);
+        self
+    }
+
+    /// Consolidate excess notes in a background task instead of as part of
+    /// other transactions, to bound the fees spent on consolidation
+    pub fn with_background_consolidation(mut self, config: BackgroundConsolidationConfig) -> Self {
+        self.background_consolidation = Some(config);
+        self
+    }
+}
+
+impl Default for MintClientInit {
+    fn default() -> Self {
+        Self {
+            denomination_strategy: Arc::new(TargetNotesPerDenomination::default()),
+            background_consolidation: None,
+        }
+    }
+}
 
 const SLICE_SIZE: u64 = 10000;
 const PARALLEL_HASH_REQUESTS: usize = 10;
@@ -743,6 +844,51 @@ async fn dump_database(
                         "NextECashNoteIndex"
                     );
                 }
+                DbKeyPrefix::UnitNote => {
+                    push_db_pair_items!(
+                        dbtx,
+                        UnitNoteKeyPrefix,
+                        UnitNoteKey,
+                        SpendableNoteUndecoded,
+                        mint_client_items,
+                        "UnitNotes"
+                    );
+                }
+                DbKeyPrefix::NextECashUnitNoteIndex => {
+                    push_db_pair_items!(
+                        dbtx,
+                        NextECashUnitNoteIndexKeyPrefix,
+                        NextECashUnitNoteIndexKey,
+                        u64,
+                        mint_client_items,
+                        "NextECashUnitNoteIndex"
+                    );
+                }
+                DbKeyPrefix::KeyEpochStatus => {
+                    if let Some(val) = dbtx.get_value(&KeyEpochStatusKey).await {
+                        mint_client_items.insert("KeyEpochStatus".to_string(), Box::new(val));
+                    }
+                }
+                DbKeyPrefix::KeyEpochKeys => {
+                    push_db_pair_items!(
+                        dbtx,
+                        KeyEpochKeysKeyPrefix,
+                        KeyEpochKeysKey,
+                        KeyEpochKeys,
+                        mint_client_items,
+                        "KeyEpochKeys"
+                    );
+                }
+                DbKeyPrefix::ReceivedEcashPaymentRequest => {
+                    push_db_pair_items!(
+                        dbtx,
+                        ReceivedEcashPaymentRequestKeyPrefix,
+                        ReceivedEcashPaymentRequestKey,
+                        OperationId,
+                        mint_client_items,
+                        "ReceivedEcashPaymentRequest"
+                    );
+                }
                 DbKeyPrefix::CancelledOOBSpend => {
                     push_db_pair_items!(
                         dbtx,
@@ -781,14 +927,30 @@ fn supported_api_versions(&self) -> MultiApiVersion {
     }
 
     async fn init(&self, args: &ClientModuleInitArgs<Self>) -> anyhow::Result<Self::Module> {
+        let key_epoch_keys = args
+            .db()
+            .begin_transaction_nc()
+            .await
+            .find_by_prefix(&KeyEpochKeysKeyPrefix)
+            .await
+            .map(|(key, keys)| (key.0, keys))
+            .collect::<BTreeMap<_, _>>()
+            .await;
+
         Ok(MintClientModule {
             federation_id: *args.federation_id(),
             cfg: args.cfg().clone(),
+            module_api_version: *args.module_api_version(),
+            denomination_strategy: self.denomination_strategy.clone(),
+            background_consolidation: self.background_consolidation,
+            task_group: args.task_group().clone(),
             secret: args.module_root_secret().clone(),
             secp: Secp256k1::new(),
             notifier: args.notifier().clone(),
             client_ctx: args.context(),
+            admin_auth: args.admin_auth().cloned(),
             balance_update_sender: tokio::sync::watch::channel(()).0,
+            key_epoch_keys: tokio::sync::watch::channel(key_epoch_keys).0,
         })
     }
 
@@ -876,11 +1038,18 @@ fn used_db_prefixes(&self) -> Option<BTreeSet<u8>> {
 pub struct MintClientModule {
     federation_id: FederationId,
     cfg: MintClientConfig,
+    module_api_version: ApiVersion,
+    denomination_strategy: Arc<dyn DenominationStrategy>,
+    background_consolidation: Option<BackgroundConsolidationConfig>,
+    task_group: TaskGroup,
     secret: DerivableSecret,
     secp: Secp256k1<All>,
     notifier: ModuleNotifier<MintClientStateMachines>,
     pub client_ctx: ClientContext<Self>,
+    admin_auth: Option<ApiAuth>,
     balance_update_sender: tokio::sync::watch::Sender<()>,
+    /// Keys of the bitcoin key epochs after zero fetched from the federation
+    key_epoch_keys: tokio::sync::watch::Sender<BTreeMap<u64, KeyEpochKeys>>,
 }
 
 impl fmt::Debug for MintClientModule {
@@ -888,6 +1057,8 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("MintClientModule")
             .field("federation_id", &self.federation_id)
             .field("cfg", &self.cfg)
+            .field("denomination_strategy", &self.denomination_strategy)
+            .field("background_consolidation", &self.background_consolidation)
             .field("notifier", &self.notifier)
             .field("client_ctx", &self.client_ctx)
             .finish_non_exhaustive()
@@ -902,6 +1073,10 @@ pub struct MintClientContext {
     pub mint_decoder: Decoder,
     pub tbs_pks: Tiered<AggregatePublicKey>,
     pub peer_tbs_pks: BTreeMap<PeerId, Tiered<tbs::PublicKeyShare>>,
+    /// Keys of the non-bitcoin units issued by the mint
+    pub custom_units: BTreeMap<AmountUnit, MintUnitClientConfig>,
+    /// Keys of the bitcoin key epochs after zero fetched from the federation
+    pub key_epoch_keys: tokio::sync::watch::Sender<BTreeMap<u64, KeyEpochKeys>>,
     pub secret: DerivableSecret,
     // FIXME: putting a DB ref here is an antipattern, global context should become more powerful
     // but we need to consider it more carefully as its APIs will be harder to change.
@@ -926,6 +1101,34 @@ fn await_cancel_oob_payment(&self, operation_id: OperationId) -> BoxFuture<'stat
                 .await;
         })
     }
+
+    /// Keys of the bitcoin key epoch `key_epoch`, epoch zero uses the keys the
+    /// mint was set up with
+    fn key_epoch_keys(&self, key_epoch: u64) -> Option<KeyEpochKeys> {
+        if key_epoch == 0 {
+            return Some(KeyEpochKeys {
+                tbs_pks: self.tbs_pks.clone(),
+                peer_tbs_pks: self.peer_tbs_pks.clone(),
+            });
+        }
+
+        self.key_epoch_keys.borrow().get(&key_epoch).cloned()
+    }
+
+    /// Keys of all bitcoin key epochs known to us, newest first
+    fn key_epochs(&self) -> Vec<KeyEpochKeys> {
+        let mut key_epochs = self
+            .key_epoch_keys
+            .borrow()
+            .values()
+            .rev()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        key_epochs.extend(self.key_epoch_keys(0));
+
+        key_epochs
+    }
 }
 
 impl Context for MintClientContext {
@@ -947,6 +1150,8 @@ fn context(&self) -> Self::ModuleStateMachineContext {
             mint_decoder: self.decoder(),
             tbs_pks: self.cfg.tbs_pks.clone(),
             peer_tbs_pks: self.cfg.peer_tbs_pks.clone(),
+            custom_units: self.cfg.custom_units.clone(),
+            key_epoch_keys: self.key_epoch_keys.clone(),
             secret: self.secret.clone(),
             module_db: self.client_ctx.module_db().clone(),
             balance_update_sender: self.balance_update_sender.clone(),
@@ -956,21 +1161,45 @@ fn context(&self) -> Self::ModuleStateMachineContext {
     fn input_fee(
         &self,
         amount: &Amounts,
-        _input: &<Self::Common as ModuleCommon>::Input,
+        input: &<Self::Common as ModuleCommon>::Input,
     ) -> Option<Amounts> {
-        Some(Amounts::new_bitcoin(
-            self.cfg.fee_consensus.fee(amount.get_bitcoin()),
-        ))
+        let (_, unit, _) = input.ensure_known().ok()?;
+        let fee = self
+            .cfg
+            .fee_consensus_for_unit(unit)?
+            .fee(amount.get(&unit).copied().unwrap_or_default());
+
+        Some(Amounts::new_custom(unit, fee))
     }
 
     fn output_fee(
         &self,
         amount: &Amounts,
-        _output: &<Self::Common as ModuleCommon>::Output,
+        output: &<Self::Common as ModuleCommon>::Output,
     ) -> Option<Amounts> {
-        Some(Amounts::new_bitcoin(
-            self.cfg.fee_consensus.fee(amount.get_bitcoin()),
-        ))
+        let (_, unit, _) = output.ensure_known().ok()?;
+        let fee = self
+            .cfg
+            .fee_consensus_for_unit(unit)?
+            .fee(amount.get(&unit).copied().unwrap_or_default());
+
+        Some(Amounts::new_custom(unit, fee))
+    }
+
+    async fn start(&self) {
+        if let Some(config) = self.background_consolidation {
+            self.task_group.spawn_cancellable(
+                "note consolidation",
+                Self::run_background_consolidation(self.client_ctx.clone(), config),
+            );
+        }
+
+        if VERSION_THAT_INTRODUCED_KEY_ROTATION <= self.module_api_version {
+            self.task_group.spawn_cancellable(
+                "key rotation",
+                Self::run_key_rotation(self.client_ctx.clone()),
+            );
+        }
     }
 
     #[cfg(feature = "cli")]
@@ -1004,7 +1233,10 @@ async fn backup(&self) -> anyhow::Result<EcashBackup> {
     }
 
     fn supports_being_primary(&self) -> PrimaryModuleSupport {
-        PrimaryModuleSupport::selected(PrimaryModulePriority::HIGH, [AmountUnit::BITCOIN])
+        PrimaryModuleSupport::Selected {
+            priority: PrimaryModulePriority::HIGH,
+            units: self.cfg.units().collect(),
+        }
     }
 
     async fn create_final_inputs_and_outputs(
@@ -1018,12 +1250,25 @@ async fn create_final_inputs_and_outputs(
         ClientInputBundle<MintInput, MintClientStateMachines>,
         ClientOutputBundle<MintOutput, MintClientStateMachines>,
     )> {
-        let consolidation_inputs = self.consolidate_notes(dbtx).await?;
-
-        if unit != AmountUnit::BITCOIN {
-            bail!("Module can only handle Bitcoin");
+        if !unit.is_bitcoin() {
+            return self
+                .create_final_unit_inputs_and_outputs(
+                    dbtx,
+                    operation_id,
+                    unit,
+                    input_amount,
+                    output_amount,
+                )
+                .await;
         }
 
+        // The background task consolidates within its fee budget instead
+        let consolidation_inputs = if self.background_consolidation.is_some() {
+            vec![]
+        } else {
+            self.consolidate_notes(dbtx).await?
+        };
+
         input_amount += consolidation_inputs
             .iter()
             .map(|input| input.0.amounts.get_bitcoin())
@@ -1049,10 +1294,10 @@ async fn create_final_inputs_and_outputs(
             .sum();
 
         let outputs = self
-            .create_output(
+            .create_output_with_strategy(
                 dbtx,
                 operation_id,
-                2,
+                self.denomination_strategy.as_ref(),
                 input_amount.saturating_sub(output_amount),
             )
             .await;
@@ -1075,18 +1320,23 @@ async fn await_primary_module_output(
     }
 
     async fn get_balance(&self, dbtx: &mut DatabaseTransaction<'_>, unit: AmountUnit) -> Amount {
-        if unit != AmountUnit::BITCOIN {
-            return Amount::ZERO;
+        if unit.is_bitcoin() {
+            self.get_note_counts_by_denomination(dbtx).await
+        } else {
+            self.get_unit_note_counts_by_denomination(dbtx, unit).await
         }
-        self.get_note_counts_by_denomination(dbtx)
-            .await
-            .total_amount()
+        .total_amount()
     }
 
     async fn get_balances(&self, dbtx: &mut DatabaseTransaction<'_>) -> Amounts {
-        Amounts::new_bitcoin(
-            <Self as ClientModule>::get_balance(self, dbtx, AmountUnit::BITCOIN).await,
-        )
+        let mut balances = Amounts::ZERO;
+        for unit in self.cfg.units().collect::<Vec<_>>() {
+            let balance = <Self as ClientModule>::get_balance(self, dbtx, unit).await;
+            balances = balances
+                .checked_add_unit(balance, unit)
+                .expect("Balance of a single unit can't overflow");
+        }
+        balances
     }
 
     async fn subscribe_balance_changes(&self) -> BoxStream<'static, ()> {
@@ -1151,6 +1401,14 @@ async fn handle_rpc(
                     ).await?;
                     yield serde_json::to_value(result)?;
                 }
+                "encode_oob_notes_frames" => {
+                    let req: EncodeOobNotesFramesRequest = serde_json::from_value(request)?;
+                    yield serde_json::to_value(req.encode()?)?;
+                }
+                "decode_oob_notes_frames" => {
+                    let req: DecodeOobNotesFramesRequest = serde_json::from_value(request)?;
+                    yield serde_json::to_value(req.decode()?)?;
+                }
                 "validate_notes" => {
                     let req: ValidateNotesRequest = serde_json::from_value(request)?;
                     let result = self.validate_notes(&req.oob_notes)?;
@@ -1173,6 +1431,9 @@ async fn handle_rpc(
                     let value = self.await_spend_oob_refund(req.operation_id).await;
                     yield serde_json::to_value(value)?;
                 }
+                "p2pk_public_key" | "send_p2pk_notes" | "await_p2pk_notes" | "redeem_p2pk_notes" => {
+                    yield self.handle_p2pk_rpc(&method, request).await?;
+                }
                 "note_counts_by_denomination" => {
                     let mut dbtx = self.client_ctx.module_db().begin_transaction_nc().await;
                     let note_counts = self.get_note_counts_by_denomination(&mut dbtx).await;
@@ -1187,6 +1448,71 @@ async fn handle_rpc(
     }
 }
 
+#[derive(Deserialize)]
+struct EncodeOobNotesFramesRequest {
+    oob_notes: OOBNotes,
+    max_fragment_length: Option<usize>,
+    /// Number of frames to return, defaults to twice the minimum required
+    count: Option<usize>,
+}
+
+impl EncodeOobNotesFramesRequest {
+    fn encode(&self) -> anyhow::Result<Vec<String>> {
+        let max_fragment_length = self
+            .max_fragment_length
+            .unwrap_or(DEFAULT_MAX_FRAGMENT_LENGTH);
+
+        ensure!(
+            max_fragment_length != 0,
+            "max_fragment_length must not be zero"
+        );
+
+        let mut encoder = OOBNotesFrameEncoder::new(&self.oob_notes, max_fragment_length);
+        let count = self.count.unwrap_or_else(|| 2 * encoder.min_frame_count());
+
+        Ok(encoder.next_frames(count))
+    }
+}
+
+#[derive(Deserialize)]
+struct DecodeOobNotesFramesRequest {
+    frames: Vec<String>,
+}
+
+impl DecodeOobNotesFramesRequest {
+    /// Returns the notes once enough frames were given to reassemble them
+    fn decode(&self) -> anyhow::Result<Option<OOBNotes>> {
+        let mut decoder = OOBNotesFrameDecoder::default();
+
+        for frame in &self.frames {
+            if let Some(notes) = decoder.add_frame(frame)? {
+                return Ok(Some(notes));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[derive(Deserialize)]
+struct SendP2pkNotesRequest {
+    amount: Amount,
+    recipient: PublicKey,
+    refund_after: Option<Duration>,
+    extra_meta: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct AwaitP2pkNotesRequest {
+    operation_id: OperationId,
+}
+
+#[derive(Deserialize)]
+struct RedeemP2pkNotesRequest {
+    p2pk_notes: P2pkNotes,
+    extra_meta: serde_json::Value,
+}
+
 #[derive(Deserialize)]
 struct ReissueExternalNotesRequest {
     oob_notes: OOBNotes,
@@ -1311,7 +1637,6 @@ pub async fn get_available_notes_by_tier_counts(
             .await
     }
 
-    // TODO: put "notes per denomination" default into cfg
     /// Creates a mint output close to the given `amount`, issuing e-cash
     /// notes such that the client holds `notes_per_denomination` notes of each
     /// e-cash note denomination held.
@@ -1322,33 +1647,96 @@ pub async fn create_output(
         notes_per_denomination: u16,
         exact_amount: Amount,
     ) -> ClientOutputBundle<MintOutput, MintClientStateMachines> {
+        self.create_output_with_strategy(
+            dbtx,
+            operation_id,
+            &TargetNotesPerDenomination::new(notes_per_denomination),
+            exact_amount,
+        )
+        .await
+    }
+
+    /// Creates a mint output close to the given `amount`, issuing e-cash
+    /// notes of the denominations chosen by `strategy`.
+    pub async fn create_output_with_strategy(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        operation_id: OperationId,
+        strategy: &dyn DenominationStrategy,
+        exact_amount: Amount,
+    ) -> ClientOutputBundle<MintOutput, MintClientStateMachines> {
+        self.create_unit_output(
+            dbtx,
+            operation_id,
+            AmountUnit::BITCOIN,
+            strategy,
+            exact_amount,
+        )
+        .await
+        .expect("The mint always issues bitcoin e-cash")
+    }
+
+    /// Like [`Self::create_output_with_strategy`], but issues e-cash notes of
+    /// `unit`
+    pub async fn create_unit_output(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        operation_id: OperationId,
+        unit: AmountUnit,
+        strategy: &dyn DenominationStrategy,
+        exact_amount: Amount,
+    ) -> anyhow::Result<ClientOutputBundle<MintOutput, MintClientStateMachines>> {
         if exact_amount == Amount::ZERO {
-            return ClientOutputBundle::new(vec![], vec![]);
+            return Ok(ClientOutputBundle::new(vec![], vec![]));
         }
 
-        let denominations = represent_amount(
-            exact_amount,
-            &self.get_note_counts_by_denomination(dbtx).await,
-            &self.cfg.tbs_pks,
-            notes_per_denomination,
-            &self.cfg.fee_consensus,
-        );
+        let (Some(tbs_pks), Some(fee_consensus)) = (
+            self.cfg.tbs_pks_for_unit(unit),
+            self.cfg.fee_consensus_for_unit(unit),
+        ) else {
+            bail!("The mint does not issue e-cash of unit {unit:?}");
+        };
+
+        let note_counts = if unit.is_bitcoin() {
+            self.get_note_counts_by_denomination(dbtx).await
+        } else {
+            self.get_unit_note_counts_by_denomination(dbtx, unit).await
+        };
+
+        let tiers = tbs_pks.tiers().copied().collect::<Vec<_>>();
+        let denominations =
+            strategy.denominations(exact_amount, &note_counts, &tiers, fee_consensus);
+
+        // Only bitcoin notes are rotated, other units stay in key epoch zero
+        let key_epoch = if unit.is_bitcoin() {
+            Self::active_key_epoch(dbtx).await
+        } else {
+            0
+        };
 
         let mut outputs = Vec::new();
         let mut issuance_requests = Vec::new();
 
         for (amount, num) in denominations.iter() {
             for _ in 0..num {
-                let (issuance_request, blind_nonce) = self.new_ecash_note(amount, dbtx).await;
+                let (issuance_request, blind_nonce) =
+                    self.new_unit_ecash_note(unit, amount, dbtx).await;
 
                 debug!(
                     %amount,
+                    ?unit,
                     "Generated issuance request"
                 );
 
+                let output = if unit.is_bitcoin() {
+                    MintOutput::new_for_key_epoch(amount, key_epoch, blind_nonce)
+                } else {
+                    MintOutput::new_for_unit(amount, unit, blind_nonce)
+                };
+
                 outputs.push(ClientOutput {
-                    output: MintOutput::new_v0(amount, blind_nonce),
-                    amounts: Amounts::new_bitcoin(amount),
+                    output,
+                    amounts: Amounts::new_custom(unit, amount),
                 });
 
                 issuance_requests.push((amount, issuance_request));
@@ -1357,27 +1745,40 @@ pub async fn create_output(
 
         let state_generator = Arc::new(move |out_point_range: OutPointRange| {
             assert_eq!(out_point_range.count(), issuance_requests.len());
+            let issuance_requests = out_point_range
+                .into_iter()
+                .map(|out_point| out_point.out_idx)
+                .zip(issuance_requests.clone())
+                .collect();
             vec![MintClientStateMachines::Output(MintOutputStateMachine {
                 common: MintOutputCommon {
                     operation_id,
                     out_point_range,
                 },
-                state: MintOutputStates::CreatedMulti(MintOutputStatesCreatedMulti {
-                    issuance_requests: out_point_range
-                        .into_iter()
-                        .map(|out_point| out_point.out_idx)
-                        .zip(issuance_requests.clone())
-                        .collect(),
-                }),
+                state: if unit.is_bitcoin() && key_epoch == 0 {
+                    MintOutputStates::CreatedMulti(MintOutputStatesCreatedMulti {
+                        issuance_requests,
+                    })
+                } else if unit.is_bitcoin() {
+                    MintOutputStates::CreatedMultiKeyEpoch(MintOutputStatesCreatedMultiKeyEpoch {
+                        key_epoch,
+                        issuance_requests,
+                    })
+                } else {
+                    MintOutputStates::CreatedMultiUnit(MintOutputStatesCreatedMultiUnit {
+                        unit,
+                        issuance_requests,
+                    })
+                },
             })]
         });
 
-        ClientOutputBundle::new(
+        Ok(ClientOutputBundle::new(
             outputs,
             vec![ClientOutputSM {
                 state_machines: state_generator,
             }],
-        )
+        ))
     }
 
     /// Returns the number of held e-cash notes per denomination
@@ -1397,6 +1798,115 @@ pub async fn get_note_counts_by_denomination(
             .await
     }
 
+    /// Returns the number of held e-cash notes of a non-bitcoin `unit` per
+    /// denomination
+    pub async fn get_unit_note_counts_by_denomination(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        unit: AmountUnit,
+    ) -> TieredCounts {
+        dbtx.find_by_prefix(&UnitNoteKeyUnitPrefix(unit))
+            .await
+            .fold(
+                TieredCounts::default(),
+                |mut acc, (key, _note)| async move {
+                    acc.inc(key.amount, 1);
+                    acc
+                },
+            )
+            .await
+    }
+
+    /// Like [`ClientModule::create_final_inputs_and_outputs`], but for a
+    /// non-bitcoin `unit`. Notes of custom units are never consolidated.
+    async fn create_final_unit_inputs_and_outputs(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        operation_id: OperationId,
+        unit: AmountUnit,
+        mut input_amount: Amount,
+        mut output_amount: Amount,
+    ) -> anyhow::Result<(
+        ClientInputBundle<MintInput, MintClientStateMachines>,
+        ClientOutputBundle<MintOutput, MintClientStateMachines>,
+    )> {
+        let fee_consensus = self
+            .cfg
+            .fee_consensus_for_unit(unit)
+            .with_context(|| format!("The mint does not issue e-cash of unit {unit:?}"))?
+            .clone();
+
+        let inputs = self
+            .create_sufficient_unit_input(
+                dbtx,
+                unit,
+                &fee_consensus,
+                output_amount.saturating_sub(input_amount),
+            )
+            .await?;
+
+        for (input, _) in &inputs {
+            let amount = input.amounts.get(&unit).copied().unwrap_or_default();
+            input_amount += amount;
+            output_amount += fee_consensus.fee(amount);
+        }
+
+        let outputs = self
+            .create_unit_output(
+                dbtx,
+                operation_id,
+                unit,
+                self.denomination_strategy.as_ref(),
+                input_amount.saturating_sub(output_amount),
+            )
+            .await?;
+
+        Ok((
+            create_bundle_for_unit_inputs(unit, inputs, operation_id),
+            outputs,
+        ))
+    }
+
+    /// Like [`Self::create_sufficient_input`], but spends notes of a
+    /// non-bitcoin `unit`
+    async fn create_sufficient_unit_input(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        unit: AmountUnit,
+        fee_consensus: &FeeConsensus,
+        min_amount: Amount,
+    ) -> anyhow::Result<Vec<(ClientInput<MintInput>, SpendableNote)>> {
+        if min_amount == Amount::ZERO {
+            return Ok(vec![]);
+        }
+
+        let note_stream = dbtx
+            .find_by_prefix_sorted_descending(&UnitNoteKeyUnitPrefix(unit))
+            .await
+            .map(|(key, note)| (key.amount, note));
+
+        let selected_notes = SelectNotesWithAtleastAmount
+            .select_notes(note_stream, min_amount, fee_consensus.clone())
+            .await?
+            .into_iter_items()
+            .map(|(amt, snote)| Ok((amt, snote.decode()?)))
+            .collect::<anyhow::Result<TieredMulti<_>>>()?;
+
+        for (amount, note) in selected_notes.iter_items() {
+            debug!(target: LOG_CLIENT_MODULE_MINT, %amount, ?unit, %note, "Spending note as sufficient input to fund a tx");
+            Self::delete_spendable_unit_note(&self.client_ctx, dbtx, unit, amount, note).await;
+        }
+
+        let sender = self.balance_update_sender.clone();
+        dbtx.on_commit(move || sender.send_replace(()));
+
+        let inputs = self.create_input_from_unit_notes(unit, selected_notes)?;
+
+        assert!(!inputs.is_empty());
+
+        Ok(inputs)
+    }
+
     /// Returns the number of held e-cash notes per denomination
     #[deprecated(
         since = "0.5.0",
@@ -1458,13 +1968,20 @@ pub async fn await_output_finalized(
                 }
 
                 match state.state {
-                    MintOutputStates::Succeeded(_) => Some(Ok(())),
+                    MintOutputStates::Succeeded(_) | MintOutputStates::SucceededLocked(_) => {
+                        Some(Ok(()))
+                    }
                     MintOutputStates::Aborted(_) => Some(Err(anyhow!("Transaction was rejected"))),
                     MintOutputStates::Failed(failed) => Some(Err(anyhow!(
                         "Failed to finalize transaction: {}",
                         failed.error
                     ))),
-                    MintOutputStates::Created(_) | MintOutputStates::CreatedMulti(_) => None,
+                    MintOutputStates::Created(_)
+                    | MintOutputStates::CreatedMulti(_)
+                    | MintOutputStates::CreatedMultiUnit(_)
+                    | MintOutputStates::CreatedLocked(_)
+                    | MintOutputStates::CreatedMultiKeyEpoch(_)
+                    | MintOutputStates::CreatedLockedKeyEpoch(_) => None,
                 }
             });
         pin_mut!(stream);
@@ -1474,53 +1991,25 @@ pub async fn await_output_finalized(
 
     /// Provisional implementation of note consolidation
     ///
-    /// When a certain denomination crosses the threshold of notes allowed,
-    /// spend some chunk of them as inputs.
+    /// Spends the notes the [`DenominationStrategy`] deems excessive as
+    /// inputs.
     ///
     /// Return notes and the sume of their amount.
     pub async fn consolidate_notes(
         &self,
         dbtx: &mut DatabaseTransaction<'_>,
     ) -> anyhow::Result<Vec<(ClientInput<MintInput>, SpendableNote)>> {
-        /// At how many notes of the same denomination should we try to
-        /// consolidate
-        const MAX_NOTES_PER_TIER_TRIGGER: usize = 8;
-        /// Number of notes per tier to leave after threshold was crossed
-        const MIN_NOTES_PER_TIER: usize = 4;
-        /// Maximum number of notes to consolidate per one tx,
-        /// to limit the size of a transaction produced.
-        const MAX_NOTES_TO_CONSOLIDATE_IN_TX: usize = 20;
-        // it's fine, it's just documentation
-        #[allow(clippy::assertions_on_constants)]
-        {
-            assert!(MIN_NOTES_PER_TIER <= MAX_NOTES_PER_TIER_TRIGGER);
-        }
-
         let counts = self.get_note_counts_by_denomination(dbtx).await;
 
-        let should_consolidate = counts
-            .iter()
-            .any(|(_, count)| MAX_NOTES_PER_TIER_TRIGGER < count);
+        let excessive_counts = self.denomination_strategy.notes_to_consolidate(&counts);
 
-        if !should_consolidate {
+        if excessive_counts.is_empty() {
             return Ok(vec![]);
         }
 
-        let mut max_count = MAX_NOTES_TO_CONSOLIDATE_IN_TX;
-
-        let excessive_counts: TieredCounts = counts
-            .iter()
-            .map(|(amount, count)| {
-                let take = (count.saturating_sub(MIN_NOTES_PER_TIER)).min(max_count);
-
-                max_count -= take;
-                (amount, take)
-            })
-            .collect();
-
-        let (selected_notes, unavailable) = self
-            .get_available_notes_by_tier_counts(dbtx, excessive_counts)
-            .await;
+        let (selected_notes, unavailable) = self
+            .get_available_notes_by_tier_counts(dbtx, excessive_counts)
+            .await;
 
         debug_assert!(
             unavailable.is_empty(),
@@ -1552,26 +2041,44 @@ pub fn create_input_from_notes(
         &self,
         notes: TieredMulti<SpendableNote>,
     ) -> anyhow::Result<Vec<(ClientInput<MintInput>, SpendableNote)>> {
+        self.create_input_from_unit_notes(AmountUnit::BITCOIN, notes)
+    }
+
+    /// Like [`Self::create_input_from_notes`], but for notes of `unit`
+    #[allow(clippy::type_complexity)]
+    pub fn create_input_from_unit_notes(
+        &self,
+        unit: AmountUnit,
+        notes: TieredMulti<SpendableNote>,
+    ) -> anyhow::Result<Vec<(ClientInput<MintInput>, SpendableNote)>> {
+        let tbs_pks = self
+            .cfg
+            .tbs_pks_for_unit(unit)
+            .with_context(|| format!("The mint does not issue e-cash of unit {unit:?}"))?;
+
         let mut inputs_and_notes = Vec::new();
 
         for (amount, spendable_note) in notes.into_iter_items() {
-            let key = self
-                .cfg
-                .tbs_pks
-                .get(amount)
-                .ok_or(anyhow!("Invalid amount tier: {amount}"))?;
+            ensure!(
+                tbs_pks.get(amount).is_some(),
+                "Invalid amount tier: {amount}"
+            );
 
             let note = spendable_note.note();
 
-            if !note.verify(*key) {
+            let Some(key_epoch) = self.find_key_epoch(unit, amount, |key| note.verify(key)) else {
                 bail!("Invalid note");
-            }
+            };
 
             inputs_and_notes.push((
                 ClientInput {
-                    input: MintInput::new_v0(amount, note),
+                    input: if unit.is_bitcoin() {
+                        MintInput::new_for_key_epoch(key_epoch, KeyEpochInput::new_v0(amount, note))
+                    } else {
+                        MintInput::new_for_unit(amount, unit, note)
+                    },
                     keys: vec![spendable_note.spend_key],
-                    amounts: Amounts::new_bitcoin(amount),
+                    amounts: Amounts::new_custom(unit, amount),
                 },
                 spendable_note,
             ));
@@ -1580,6 +2087,38 @@ pub fn create_input_from_notes(
         Ok(inputs_and_notes)
     }
 
+    /// Finds the key epoch a note of denomination `amount` of `unit` was
+    /// issued in by checking its signature with `verify`, newest first
+    ///
+    /// Only bitcoin notes are rotated, notes of other units are always of key
+    /// epoch zero. Returns `None` if the signature is invalid in all epochs.
+    fn find_key_epoch(
+        &self,
+        unit: AmountUnit,
+        amount: Amount,
+        verify: impl Fn(AggregatePublicKey) -> bool,
+    ) -> Option<u64> {
+        if unit.is_bitcoin()
+            && let Some(key_epoch) = find_key_epoch(&self.key_epoch_keys.borrow(), amount, &verify)
+        {
+            return Some(key_epoch);
+        }
+
+        self.cfg
+            .tbs_pks_for_unit(unit)?
+            .get(amount)
+            .is_some_and(|key| verify(*key))
+            .then_some(0)
+    }
+
+    /// The bitcoin key epoch new notes are issued in, as last fetched from the
+    /// federation
+    async fn active_key_epoch(dbtx: &mut DatabaseTransaction<'_>) -> u64 {
+        dbtx.get_value(&KeyEpochStatusKey)
+            .await
+            .map_or(0, |status| status.active)
+    }
+
     async fn spend_notes_oob(
         &self,
         dbtx: &mut DatabaseTransaction<'_>,
@@ -1724,66 +2263,499 @@ pub fn new_note_secret_static(
             .child_key(ChildId(amount.msats))
     }
 
-    /// We always keep track of an incrementing index in the database and use
-    /// it as part of the derivation path for the note secret. This ensures that
-    /// we never reuse the same note secret twice.
-    async fn new_note_secret(
-        &self,
-        amount: Amount,
-        dbtx: &mut DatabaseTransaction<'_>,
-    ) -> DerivableSecret {
-        let new_idx = self.get_next_note_index(dbtx, amount).await;
-        dbtx.insert_entry(&NextECashNoteIndexKey(amount), &new_idx.next().as_u64())
-            .await;
-        Self::new_note_secret_static(&self.secret, amount, new_idx)
+    /// We always keep track of an incrementing index in the database and use
+    /// it as part of the derivation path for the note secret. This ensures that
+    /// we never reuse the same note secret twice.
+    async fn new_note_secret(
+        &self,
+        amount: Amount,
+        dbtx: &mut DatabaseTransaction<'_>,
+    ) -> DerivableSecret {
+        let new_idx = self.get_next_note_index(dbtx, amount).await;
+        dbtx.insert_entry(&NextECashNoteIndexKey(amount), &new_idx.next().as_u64())
+            .await;
+        Self::new_note_secret_static(&self.secret, amount, new_idx)
+    }
+
+    pub async fn new_ecash_note(
+        &self,
+        amount: Amount,
+        dbtx: &mut DatabaseTransaction<'_>,
+    ) -> (NoteIssuanceRequest, BlindNonce) {
+        let secret = self.new_note_secret(amount, dbtx).await;
+        NoteIssuanceRequest::new(&self.secp, &secret)
+    }
+
+    /// Like [`Self::new_note_secret_static`], but for notes of a non-bitcoin
+    /// `unit`, which have their own note index per unit and denomination
+    pub fn new_unit_note_secret_static(
+        secret: &DerivableSecret,
+        unit: AmountUnit,
+        amount: Amount,
+        note_idx: NoteIndex,
+    ) -> DerivableSecret {
+        assert_eq!(secret.level(), 2);
+        assert!(
+            !unit.is_bitcoin(),
+            "Bitcoin notes have their own derivation path"
+        );
+        debug!(?secret, ?unit, %amount, %note_idx, "Deriving new mint note");
+        secret
+            .child_key(MINT_E_CASH_CUSTOM_UNIT_TYPE_CHILD_ID)
+            .child_key(ChildId(unit.as_u64()))
+            .child_key(ChildId(note_idx.as_u64()))
+            .child_key(ChildId(amount.msats))
+    }
+
+    /// Like [`Self::new_note_secret`], but for notes of a non-bitcoin `unit`
+    async fn new_unit_note_secret(
+        &self,
+        unit: AmountUnit,
+        amount: Amount,
+        dbtx: &mut DatabaseTransaction<'_>,
+    ) -> DerivableSecret {
+        let new_idx = NoteIndex(
+            dbtx.get_value(&NextECashUnitNoteIndexKey(unit, amount))
+                .await
+                .unwrap_or(0),
+        );
+        dbtx.insert_entry(
+            &NextECashUnitNoteIndexKey(unit, amount),
+            &new_idx.next().as_u64(),
+        )
+        .await;
+        Self::new_unit_note_secret_static(&self.secret, unit, amount, new_idx)
+    }
+
+    /// Like [`Self::new_ecash_note`], but for a note of `unit`
+    pub async fn new_unit_ecash_note(
+        &self,
+        unit: AmountUnit,
+        amount: Amount,
+        dbtx: &mut DatabaseTransaction<'_>,
+    ) -> (NoteIssuanceRequest, BlindNonce) {
+        let secret = if unit.is_bitcoin() {
+            self.new_note_secret(amount, dbtx).await
+        } else {
+            self.new_unit_note_secret(unit, amount, dbtx).await
+        };
+        NoteIssuanceRequest::new(&self.secp, &secret)
+    }
+
+    /// Try to reissue e-cash notes received from a third party to receive them
+    /// in our wallet. The progress and outcome can be observed using
+    /// [`MintClientModule::subscribe_reissue_external_notes`].
+    /// Can return error of type [`ReissueExternalNotesError`]
+    pub async fn reissue_external_notes<M: Serialize + Send>(
+        &self,
+        oob_notes: OOBNotes,
+        extra_meta: M,
+    ) -> anyhow::Result<OperationId> {
+        let notes = oob_notes.notes().clone();
+        let federation_id_prefix = oob_notes.federation_id_prefix();
+
+        ensure!(
+            notes.total_amount() > Amount::ZERO,
+            "Reissuing zero-amount e-cash isn't supported"
+        );
+
+        if federation_id_prefix != self.federation_id.to_prefix() {
+            bail!(ReissueExternalNotesError::WrongFederationId);
+        }
+
+        let operation_id = reissue_external_notes_operation_id(&notes);
+
+        let amount = notes.total_amount();
+        let mint_inputs = self.create_input_from_notes(notes)?;
+
+        let tx = TransactionBuilder::new().with_inputs(
+            self.client_ctx
+                .make_dyn(create_bundle_for_inputs(mint_inputs, operation_id)),
+        );
+
+        let extra_meta = serde_json::to_value(extra_meta)
+            .expect("MintClientModule::reissue_external_notes extra_meta is serializable");
+        let operation_meta_gen = move |change_range: OutPointRange| MintOperationMeta {
+            variant: MintOperationMetaVariant::Reissuance {
+                legacy_out_point: None,
+                txid: Some(change_range.txid()),
+                out_point_indices: change_range
+                    .into_iter()
+                    .map(|out_point| out_point.out_idx)
+                    .collect(),
+            },
+            amount,
+            extra_meta: extra_meta.clone(),
+        };
+
+        self.client_ctx
+            .finalize_and_submit_transaction(
+                operation_id,
+                MintCommonInit::KIND.as_str(),
+                operation_meta_gen,
+                tx,
+            )
+            .await
+            .context(ReissueExternalNotesError::AlreadyReissued)?;
+
+        let mut dbtx = self.client_ctx.module_db().begin_transaction().await;
+
+        self.client_ctx
+            .log_event(&mut dbtx, OOBNotesReissued { amount })
+            .await;
+
+        self.client_ctx
+            .log_event(
+                &mut dbtx,
+                ReceivePaymentEvent {
+                    operation_id,
+                    amount,
+                },
+            )
+            .await;
+
+        dbtx.commit_tx().await;
+
+        Ok(operation_id)
+    }
+
+    /// Subscribe to updates on the progress of a reissue operation started with
+    /// [`MintClientModule::reissue_external_notes`].
+    pub async fn subscribe_reissue_external_notes(
+        &self,
+        operation_id: OperationId,
+    ) -> anyhow::Result<UpdateStreamOrOutcome<ReissueExternalNotesState>> {
+        let operation = self.mint_operation(operation_id).await?;
+        let (txid, out_points) = match operation.meta::<MintOperationMeta>().variant {
+            MintOperationMetaVariant::Reissuance {
+                legacy_out_point,
+                txid,
+                out_point_indices,
+            } => {
+                // Either txid or legacy_out_point will be present, so we should always
+                // have a source for the txid
+                let txid = txid
+                    .or(legacy_out_point.map(|out_point| out_point.txid))
+                    .context("Empty reissuance not permitted, this should never happen")?;
+
+                let out_points = out_point_indices
+                    .into_iter()
+                    .map(|out_idx| OutPoint { txid, out_idx })
+                    .chain(legacy_out_point)
+                    .collect::<Vec<_>>();
+
+                (txid, out_points)
+            }
+            MintOperationMetaVariant::SpendOOB { .. }
+            | MintOperationMetaVariant::SendP2pk { .. }
+            | MintOperationMetaVariant::Consolidation { .. }
+            | MintOperationMetaVariant::KeyRotation { .. }
+            | MintOperationMetaVariant::PaymentRequest { .. } => {
+                bail!("Operation is not a reissuance")
+            }
+        };
+
+        let client_ctx = self.client_ctx.clone();
+
+        Ok(self.client_ctx.outcome_or_updates(operation, operation_id, move || {
+            stream! {
+                yield ReissueExternalNotesState::Created;
+
+                match client_ctx
+                    .transaction_updates(operation_id)
+                    .await
+                    .await_tx_accepted(txid)
+                    .await
+                {
+                    Ok(()) => {
+                        yield ReissueExternalNotesState::Issuing;
+                    }
+                    Err(e) => {
+                        yield ReissueExternalNotesState::Failed(format!("Transaction not accepted {e:?}"));
+                        return;
+                    }
+                }
+
+                for out_point in out_points {
+                    if let Err(e) = client_ctx.self_ref().await_output_finalized(operation_id, out_point).await {
+                        yield ReissueExternalNotesState::Failed(e.to_string());
+                        return;
+                    }
+                }
+                yield ReissueExternalNotesState::Done;
+            }}
+        ))
+    }
+
+    /// Handles the RPC methods for sending and receiving [`P2pkNotes`]
+    async fn handle_p2pk_rpc(
+        &self,
+        method: &str,
+        request: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        match method {
+            "p2pk_public_key" => Ok(serde_json::to_value(self.p2pk_public_key())?),
+            "send_p2pk_notes" => {
+                let req: SendP2pkNotesRequest = serde_json::from_value(request)?;
+                let result = self
+                    .send_p2pk_notes(req.amount, req.recipient, req.refund_after, req.extra_meta)
+                    .await?;
+                Ok(serde_json::to_value(result)?)
+            }
+            "await_p2pk_notes" => {
+                let req: AwaitP2pkNotesRequest = serde_json::from_value(request)?;
+                let result = self.await_p2pk_notes(req.operation_id).await?;
+                Ok(serde_json::to_value(result)?)
+            }
+            "redeem_p2pk_notes" => {
+                let req: RedeemP2pkNotesRequest = serde_json::from_value(request)?;
+                let result = self
+                    .redeem_p2pk_notes(req.p2pk_notes, req.extra_meta)
+                    .await?;
+                Ok(serde_json::to_value(result)?)
+            }
+            _ => bail!("Unknown method: {method}"),
+        }
+    }
+
+    /// Our public key that [`P2pkNotes`] can be locked to
+    pub fn p2pk_public_key(&self) -> PublicKey {
+        self.p2pk_keypair().public_key()
+    }
+
+    fn p2pk_keypair(&self) -> Keypair {
+        self.secret
+            .child_key(MINT_P2PK_KEY_CHILD_ID)
+            .to_secp_key(&self.secp)
+    }
+
+    /// Issues new e-cash notes worth `amount` that can only be redeemed with a
+    /// signature of `recipient`, see [`P2pkLock`]. If `refund_after` is set we
+    /// can redeem the notes ourselves once that much time has passed, in case
+    /// the recipient never does.
+    ///
+    /// The notes to hand to the recipient can be obtained with
+    /// [`MintClientModule::await_p2pk_notes`] once they were issued.
+    pub async fn send_p2pk_notes<M: Serialize + Send>(
+        &self,
+        amount: Amount,
+        recipient: PublicKey,
+        refund_after: Option<Duration>,
+        extra_meta: M,
+    ) -> anyhow::Result<OperationId> {
+        ensure!(
+            amount > Amount::ZERO,
+            "Sending zero-amount e-cash isn't supported"
+        );
+
+        let lock = P2pkLock {
+            recipient,
+            refund: refund_after.map(|refund_after| P2pkRefund {
+                key: self.p2pk_public_key(),
+                after: (duration_since_epoch() + refund_after).as_secs(),
+            }),
+        };
+
+        let operation_id = OperationId::new_random();
+        let key_epoch =
+            Self::active_key_epoch(&mut self.client_ctx.module_db().begin_transaction_nc().await)
+                .await;
+        let output = self.create_locked_output(operation_id, lock, key_epoch, amount)?;
+        let tx =
+            TransactionBuilder::new().with_outputs(self.client_ctx.make_client_outputs(output));
+
+        let extra_meta = serde_json::to_value(extra_meta)
+            .expect("MintClientModule::send_p2pk_notes extra_meta is serializable");
+        let operation_meta_gen = move |_: OutPointRange| MintOperationMeta {
+            variant: MintOperationMetaVariant::SendP2pk { lock },
+            amount,
+            extra_meta: extra_meta.clone(),
+        };
+
+        self.client_ctx
+            .finalize_and_submit_transaction(
+                operation_id,
+                MintCommonInit::KIND.as_str(),
+                operation_meta_gen,
+                tx,
+            )
+            .await?;
+
+        Ok(operation_id)
+    }
+
+    fn create_locked_output(
+        &self,
+        operation_id: OperationId,
+        lock: P2pkLock,
+        key_epoch: u64,
+        amount: Amount,
+    ) -> anyhow::Result<ClientOutputBundle<MintOutput, MintClientStateMachines>> {
+        // Locked notes can't be used to make change, so we use as few as possible
+        let denominations = represent_amount(
+            amount,
+            &TieredCounts::default(),
+            &self.cfg.tbs_pks,
+            0,
+            &FeeConsensus::zero(),
+        );
+
+        ensure!(
+            denominations.total_amount() == amount,
+            "The amount can't be represented by the denominations of the mint"
+        );
+
+        let mut outputs = Vec::new();
+        let mut issuance_requests = Vec::new();
+
+        for (amount, num) in denominations.iter() {
+            for _ in 0..num {
+                let (issuance_request, blind_nonce) =
+                    NoteIssuanceRequest::new_locked(&self.secp, &lock);
+
+                outputs.push(ClientOutput {
+                    output: MintOutput::new_for_key_epoch(amount, key_epoch, blind_nonce),
+                    amounts: Amounts::new_bitcoin(amount),
+                });
+
+                issuance_requests.push((amount, issuance_request));
+            }
+        }
+
+        let state_generator = Arc::new(move |out_point_range: OutPointRange| {
+            assert_eq!(out_point_range.count(), issuance_requests.len());
+            vec![MintClientStateMachines::Output(MintOutputStateMachine {
+                common: MintOutputCommon {
+                    operation_id,
+                    out_point_range,
+                },
+                state: {
+                    let created = MintOutputStatesCreatedLocked {
+                        lock,
+                        issuance_requests: out_point_range
+                            .into_iter()
+                            .map(|out_point| out_point.out_idx)
+                            .zip(issuance_requests.clone())
+                            .collect(),
+                    };
+
+                    if key_epoch == 0 {
+                        MintOutputStates::CreatedLocked(created)
+                    } else {
+                        MintOutputStates::CreatedLockedKeyEpoch(
+                            MintOutputStatesCreatedLockedKeyEpoch { key_epoch, created },
+                        )
+                    }
+                },
+            })]
+        });
+
+        Ok(ClientOutputBundle::new(
+            outputs,
+            vec![ClientOutputSM {
+                state_machines: state_generator,
+            }],
+        ))
     }
 
-    pub async fn new_ecash_note(
-        &self,
-        amount: Amount,
-        dbtx: &mut DatabaseTransaction<'_>,
-    ) -> (NoteIssuanceRequest, BlindNonce) {
-        let secret = self.new_note_secret(amount, dbtx).await;
-        NoteIssuanceRequest::new(&self.secp, &secret)
+    /// Waits for the notes of a [`MintClientModule::send_p2pk_notes`]
+    /// operation to be issued and returns them
+    pub async fn await_p2pk_notes(&self, operation_id: OperationId) -> anyhow::Result<P2pkNotes> {
+        let federation_id_prefix = self.federation_id.to_prefix();
+
+        let stream = self
+            .notifier
+            .subscribe(operation_id)
+            .await
+            .filter_map(|state| async move {
+                let MintClientStateMachines::Output(state) = state else {
+                    return None;
+                };
+
+                match state.state {
+                    MintOutputStates::SucceededLocked(succeeded) => Some(Ok(P2pkNotes {
+                        federation_id_prefix,
+                        lock: succeeded.lock,
+                        notes: succeeded.notes,
+                    })),
+                    MintOutputStates::Aborted(_) => Some(Err(anyhow!("Transaction was rejected"))),
+                    MintOutputStates::Failed(failed) => Some(Err(anyhow!(
+                        "Failed to issue locked notes: {}",
+                        failed.error
+                    ))),
+                    _ => None,
+                }
+            });
+        pin_mut!(stream);
+
+        stream.next_or_pending().await
     }
 
-    /// Try to reissue e-cash notes received from a third party to receive them
-    /// in our wallet. The progress and outcome can be observed using
+    /// Redeems [`P2pkNotes`] locked to our key into our wallet, either as their
+    /// recipient or as their sender once the refund timelock expired. The
+    /// progress and outcome can be observed using
     /// [`MintClientModule::subscribe_reissue_external_notes`].
-    /// Can return error of type [`ReissueExternalNotesError`]
-    pub async fn reissue_external_notes<M: Serialize + Send>(
+    pub async fn redeem_p2pk_notes<M: Serialize + Send>(
         &self,
-        oob_notes: OOBNotes,
+        p2pk_notes: P2pkNotes,
         extra_meta: M,
     ) -> anyhow::Result<OperationId> {
-        let notes = oob_notes.notes().clone();
-        let federation_id_prefix = oob_notes.federation_id_prefix();
+        let amount = p2pk_notes.total_amount();
 
         ensure!(
-            notes.total_amount() > Amount::ZERO,
-            "Reissuing zero-amount e-cash isn't supported"
+            amount > Amount::ZERO,
+            "Redeeming zero-amount e-cash isn't supported"
         );
 
-        if federation_id_prefix != self.federation_id.to_prefix() {
+        if p2pk_notes.federation_id_prefix != self.federation_id.to_prefix() {
             bail!(ReissueExternalNotesError::WrongFederationId);
         }
 
+        let keypair = self.p2pk_keypair();
+        let lock = p2pk_notes.lock;
+
+        let spend_path = if lock.recipient == keypair.public_key() {
+            P2pkSpendPath::Recipient
+        } else if lock.spend_key(P2pkSpendPath::Refund) == Some(keypair.public_key()) {
+            P2pkSpendPath::Refund
+        } else {
+            bail!("The notes are not locked to our key");
+        };
+
         let operation_id = OperationId(
-            notes
+            p2pk_notes
                 .consensus_hash::<sha256t::Hash<OOBReissueTag>>()
                 .to_byte_array(),
         );
 
-        let amount = notes.total_amount();
-        let mint_inputs = self.create_input_from_notes(notes)?;
+        let inputs = p2pk_notes
+            .notes
+            .iter_items()
+            .map(|(amount, note)| {
+                let key_epoch = self
+                    .find_key_epoch(AmountUnit::BITCOIN, amount, |key| {
+                        note.verify_locked(&lock, key)
+                    })
+                    .unwrap_or(0);
+
+                ClientInput {
+                    input: MintInput::new_for_key_epoch(
+                        key_epoch,
+                        KeyEpochInput::new_v2(amount, AmountUnit::BITCOIN, *note, lock, spend_path),
+                    ),
+                    keys: vec![keypair],
+                    amounts: Amounts::new_bitcoin(amount),
+                }
+            })
+            .collect();
 
         let tx = TransactionBuilder::new().with_inputs(
             self.client_ctx
-                .make_dyn(create_bundle_for_inputs(mint_inputs, operation_id)),
+                .make_client_inputs(ClientInputBundle::new_no_sm(inputs)),
         );
 
         let extra_meta = serde_json::to_value(extra_meta)
-            .expect("MintClientModule::reissue_external_notes extra_meta is serializable");
+            .expect("MintClientModule::redeem_p2pk_notes extra_meta is serializable");
         let operation_meta_gen = move |change_range: OutPointRange| MintOperationMeta {
             variant: MintOperationMetaVariant::Reissuance {
                 legacy_out_point: None,
@@ -1809,10 +2781,6 @@ pub async fn reissue_external_notes<M: Serialize + Send>(
 
         let mut dbtx = self.client_ctx.module_db().begin_transaction().await;
 
-        self.client_ctx
-            .log_event(&mut dbtx, OOBNotesReissued { amount })
-            .await;
-
         self.client_ctx
             .log_event(
                 &mut dbtx,
@@ -1828,68 +2796,6 @@ pub async fn reissue_external_notes<M: Serialize + Send>(
         Ok(operation_id)
     }
 
-    /// Subscribe to updates on the progress of a reissue operation started with
-    /// [`MintClientModule::reissue_external_notes`].
-    pub async fn subscribe_reissue_external_notes(
-        &self,
-        operation_id: OperationId,
-    ) -> anyhow::Result<UpdateStreamOrOutcome<ReissueExternalNotesState>> {
-        let operation = self.mint_operation(operation_id).await?;
-        let (txid, out_points) = match operation.meta::<MintOperationMeta>().variant {
-            MintOperationMetaVariant::Reissuance {
-                legacy_out_point,
-                txid,
-                out_point_indices,
-            } => {
-                // Either txid or legacy_out_point will be present, so we should always
-                // have a source for the txid
-                let txid = txid
-                    .or(legacy_out_point.map(|out_point| out_point.txid))
-                    .context("Empty reissuance not permitted, this should never happen")?;
-
-                let out_points = out_point_indices
-                    .into_iter()
-                    .map(|out_idx| OutPoint { txid, out_idx })
-                    .chain(legacy_out_point)
-                    .collect::<Vec<_>>();
-
-                (txid, out_points)
-            }
-            MintOperationMetaVariant::SpendOOB { .. } => bail!("Operation is not a reissuance"),
-        };
-
-        let client_ctx = self.client_ctx.clone();
-
-        Ok(self.client_ctx.outcome_or_updates(operation, operation_id, move || {
-            stream! {
-                yield ReissueExternalNotesState::Created;
-
-                match client_ctx
-                    .transaction_updates(operation_id)
-                    .await
-                    .await_tx_accepted(txid)
-                    .await
-                {
-                    Ok(()) => {
-                        yield ReissueExternalNotesState::Issuing;
-                    }
-                    Err(e) => {
-                        yield ReissueExternalNotesState::Failed(format!("Transaction not accepted {e:?}"));
-                        return;
-                    }
-                }
-
-                for out_point in out_points {
-                    if let Err(e) = client_ctx.self_ref().await_output_finalized(operation_id, out_point).await {
-                        yield ReissueExternalNotesState::Failed(e.to_string());
-                        return;
-                    }
-                }
-                yield ReissueExternalNotesState::Done;
-            }}
-        ))
-    }
-
     /// Fetches and removes notes of *at least* amount `min_amount` from the
     /// wallet to be sent to the recipient out of band. These spends can be
     /// canceled by calling [`MintClientModule::try_cancel_spend_notes`] as long
@@ -1944,6 +2850,28 @@ pub async fn spend_notes_with_selector<M: Serialize + Send>(
         try_cancel_after: Duration,
         include_invite: bool,
         extra_meta: M,
+    ) -> anyhow::Result<(OperationId, OOBNotes)> {
+        self.spend_notes_with_selector_inner(
+            notes_selector,
+            requested_amount,
+            try_cancel_after,
+            include_invite,
+            None,
+            extra_meta,
+        )
+        .await
+    }
+
+    /// See [`Self::spend_notes_with_selector`], `payment_request` is the
+    /// request the notes are spent for, if any
+    async fn spend_notes_with_selector_inner<M: Serialize + Send>(
+        &self,
+        notes_selector: &impl NotesSelector,
+        requested_amount: Amount,
+        try_cancel_after: Duration,
+        include_invite: bool,
+        payment_request: Option<EcashPaymentRequest>,
+        extra_meta: M,
     ) -> anyhow::Result<(OperationId, OOBNotes)> {
         let federation_id_prefix = self.federation_id.to_prefix();
         let extra_meta = serde_json::to_value(extra_meta)
@@ -1954,6 +2882,7 @@ pub async fn spend_notes_with_selector<M: Serialize + Send>(
             .autocommit(
                 |dbtx, _| {
                     let extra_meta = extra_meta.clone();
+                    let payment_request = payment_request.clone();
                     Box::pin(async {
                         let (operation_id, states, notes) = self
                             .spend_notes_oob(
@@ -1988,6 +2917,7 @@ pub async fn spend_notes_with_selector<M: Serialize + Send>(
                                     variant: MintOperationMetaVariant::SpendOOB {
                                         requested_amount,
                                         oob_notes: oob_notes.clone(),
+                                        payment_request,
                                     },
                                     amount: oob_notes.total_amount(),
                                     extra_meta,
@@ -2198,6 +3128,7 @@ async fn try_spend_exact_notes_dbtx(
                     variant: MintOperationMetaVariant::SpendOOB {
                         requested_amount: amount,
                         oob_notes: oob_notes.clone(),
+                        payment_request: None,
                     },
                     amount: oob_notes.total_amount(),
                     extra_meta,
@@ -2235,12 +3166,16 @@ pub fn validate_notes(&self, oob_notes: &OOBNotes) -> anyhow::Result<Amount> {
         let tbs_pks = &self.cfg.tbs_pks;
 
         for (idx, (amt, snote)) in notes.iter_items().enumerate() {
-            let key = tbs_pks
-                .get(amt)
-                .ok_or_else(|| anyhow!("Note {idx} uses an invalid amount tier {amt}"))?;
+            ensure!(
+                tbs_pks.get(amt).is_some(),
+                "Note {idx} uses an invalid amount tier {amt}"
+            );
 
             let note = snote.note();
-            if !note.verify(*key) {
+            if self
+                .find_key_epoch(AmountUnit::BITCOIN, amt, |key| note.verify(key))
+                .is_none()
+            {
                 bail!("Note {idx} has an invalid federation signature");
             }
 
@@ -2259,19 +3194,60 @@ pub fn validate_notes(&self, oob_notes: &OOBNotes) -> anyhow::Result<Amount> {
     /// NOT** rely on it for receiving funds unless you really know what you are
     /// doing.
     pub async fn check_note_spent(&self, oob_notes: &OOBNotes) -> anyhow::Result<bool> {
+        let nonces = oob_notes
+            .notes()
+            .iter_items()
+            .map(|(_, note)| note.nonce())
+            .collect::<Vec<_>>();
+
+        let any_spent = self
+            .check_nonces_spent(&nonces)
+            .await?
+            .into_iter()
+            .any(|spent| spent);
+
+        Ok(any_spent)
+    }
+
+    /// Returns whether the notes with the given nonces were spent, in order
+    ///
+    /// Uses as few requests as possible if all guardians support
+    /// [`NOTES_SPENT_ENDPOINT`](fedimint_mint_common::endpoint_constants::NOTES_SPENT_ENDPOINT)
+    /// and falls back to one request per note otherwise.
+    pub async fn check_nonces_spent(&self, nonces: &[Nonce]) -> anyhow::Result<Vec<bool>> {
         use crate::api::MintFederationApi;
 
         let api_client = self.client_ctx.module_api();
-        let any_spent = try_join_all(oob_notes.notes().iter().flat_map(|(_, notes)| {
-            notes
-                .iter()
-                .map(|note| api_client.check_note_spent(note.nonce()))
-        }))
-        .await?
-        .into_iter()
-        .any(|spent| spent);
 
-        Ok(any_spent)
+        if self.module_api_version < VERSION_THAT_INTRODUCED_NOTES_SPENT {
+            return Ok(futures::stream::iter(nonces.to_vec())
+                .map(|nonce| api_client.check_note_spent(nonce))
+                .buffered(CHECK_NOTE_SPENT_PARALLELISM)
+                .try_collect::<Vec<_>>()
+                .await?);
+        }
+
+        let mut spent = Vec::with_capacity(nonces.len());
+
+        for batch in nonces.chunks(MAX_NOTES_SPENT_BATCH_SIZE) {
+            let bitmap = api_client.check_notes_spent(batch.to_vec()).await?;
+
+            ensure!(
+                bitmap.len() == batch.len() as u64,
+                "Federation returned the spent status of {} notes instead of {}",
+                bitmap.len(),
+                batch.len()
+            );
+
+            spent.extend(
+                bitmap
+                    .iter()
+                    .collect::<Option<Vec<_>>>()
+                    .context("Federation returned a malformed spent status bitmap")?,
+            );
+        }
+
+        Ok(spent)
     }
 
     /// Try to cancel a spend operation started with
@@ -2394,6 +3370,30 @@ async fn delete_spendable_note(
         .expect("Must deleted existing spendable note");
     }
 
+    async fn delete_spendable_unit_note(
+        client_ctx: &ClientContext<MintClientModule>,
+        dbtx: &mut DatabaseTransaction<'_>,
+        unit: AmountUnit,
+        amount: Amount,
+        note: &SpendableNote,
+    ) {
+        client_ctx
+            .log_event(
+                dbtx,
+                NoteSpent {
+                    nonce: note.nonce(),
+                },
+            )
+            .await;
+        dbtx.remove_entry(&UnitNoteKey {
+            unit,
+            amount,
+            nonce: note.nonce(),
+        })
+        .await
+        .expect("Must deleted existing spendable note");
+    }
+
     pub async fn advance_note_idx(&self, amount: Amount) -> anyhow::Result<DerivableSecret> {
         let db = self.client_ctx.module_db().clone();
 
@@ -2865,6 +3865,18 @@ fn engine() -> sha256::HashEngine {
     }
 }
 
+/// Id of the operation reissuing `notes` in
+/// [`MintClientModule::reissue_external_notes`]
+pub(crate) fn reissue_external_notes_operation_id(
+    notes: &TieredMulti<SpendableNote>,
+) -> OperationId {
+    OperationId(
+        notes
+            .consensus_hash::<sha256t::Hash<OOBReissueTag>>()
+            .to_byte_array(),
+    )
+}
+
 struct OOBReissueTag;
 
 impl sha256t::Tag for OOBReissueTag {
@@ -2919,6 +3931,33 @@ pub fn represent_amount<K>(
     denominations
 }
 
+/// Finds the key epoch after zero a bitcoin note of denomination `amount` was
+/// issued in by checking its signature against the keys in `key_epoch_keys`,
+/// newest first
+fn find_key_epoch(
+    key_epoch_keys: &BTreeMap<u64, KeyEpochKeys>,
+    amount: Amount,
+    verify: impl Fn(AggregatePublicKey) -> bool,
+) -> Option<u64> {
+    key_epoch_keys
+        .iter()
+        .rev()
+        .find(|(_, keys)| keys.tbs_pks.get(amount).is_some_and(|pk| verify(*pk)))
+        .map(|(key_epoch, _)| *key_epoch)
+}
+
+/// Creates the input redeeming a bitcoin note, the key epoch the note was
+/// issued in is detected by checking its signature
+pub(crate) fn bitcoin_input(
+    key_epoch_keys: &BTreeMap<u64, KeyEpochKeys>,
+    amount: Amount,
+    note: Note,
+) -> MintInput {
+    let key_epoch = find_key_epoch(key_epoch_keys, amount, |key| note.verify(key)).unwrap_or(0);
+
+    MintInput::new_for_key_epoch(key_epoch, KeyEpochInput::new_v0(amount, note))
+}
+
 pub(crate) fn create_bundle_for_inputs(
     inputs_and_notes: Vec<(ClientInput<MintInput>, SpendableNote)>,
     operation_id: OperationId,
@@ -2956,6 +3995,46 @@ pub(crate) fn create_bundle_for_inputs(
     )
 }
 
+/// Like [`create_bundle_for_inputs`], but for inputs of a non-bitcoin `unit`
+pub(crate) fn create_bundle_for_unit_inputs(
+    unit: AmountUnit,
+    inputs_and_notes: Vec<(ClientInput<MintInput>, SpendableNote)>,
+    operation_id: OperationId,
+) -> ClientInputBundle<MintInput, MintClientStateMachines> {
+    let mut inputs = Vec::new();
+    let mut notes = Vec::new();
+
+    for (input, spendable_note) in inputs_and_notes {
+        notes.push((
+            input.amounts.get(&unit).copied().unwrap_or_default(),
+            spendable_note,
+        ));
+        inputs.push(input);
+    }
+
+    let input_sm = Arc::new(move |out_point_range: OutPointRange| {
+        debug_assert_eq!(out_point_range.into_iter().count(), notes.len());
+
+        vec![MintClientStateMachines::Input(MintInputStateMachine {
+            common: MintInputCommon {
+                operation_id,
+                out_point_range,
+            },
+            state: MintInputStates::CreatedBundleUnit(MintInputStateCreatedBundleUnit {
+                unit,
+                notes: notes.clone(),
+            }),
+        })]
+    });
+
+    ClientInputBundle::new(
+        inputs,
+        vec![ClientInputSM {
+            state_machines: input_sm,
+        }],
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use std::fmt::Display;
@@ -3228,6 +4307,36 @@ fn notes_encode_decode() {
         assert!(notes_inconsistent_str.parse::<OOBNotes>().is_err());
     }
 
+    #[test]
+    fn oob_notes_frames_roundtrip() {
+        let note = SpendableNote::consensus_decode_hex("a5dd3ebacad1bc48bd8718eed5a8da1d68f91323bef2848ac4fa2e6f8eed710f3178fd4aef047cc234e6b1127086f33cc408b39818781d9521475360de6b205f3328e490a6d99d5e2553a4553207c8bd", &ModuleRegistry::default()).unwrap();
+        let notes = (1..=20)
+            .map(|sats| (Amount::from_sats(sats), note))
+            .collect::<TieredMulti<_>>();
+        let oob_notes = OOBNotes::new(FederationId::dummy().to_prefix(), notes);
+
+        let mut encoder = crate::fountain::OOBNotesFrameEncoder::new(&oob_notes, 100);
+        assert!(1 < encoder.min_frame_count());
+
+        // Skipping the first frames and uppercasing the rest, as QR code scanners
+        // might, still allows reassembling the notes
+        let frames = encoder
+            .next_frames(3 * encoder.min_frame_count())
+            .into_iter()
+            .skip(2)
+            .map(|frame| frame.to_ascii_uppercase())
+            .collect::<Vec<_>>();
+
+        let decoded =
+            crate::fountain::decode_oob_notes_frames(frames.iter().map(String::as_str)).unwrap();
+        assert_eq!(decoded, oob_notes);
+
+        assert!(
+            crate::fountain::decode_oob_notes_frames(frames.iter().take(1).map(String::as_str))
+                .is_err()
+        );
+    }
+
     #[test]
     fn spendable_note_undecoded_sanity() {
         // TODO: add more hex dumps to the loop
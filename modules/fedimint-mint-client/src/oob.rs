@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::time::SystemTime;
 
@@ -9,12 +10,12 @@
 use fedimint_core::encoding::{Decodable, Encodable};
 use fedimint_core::module::Amounts;
 use fedimint_core::{Amount, TransactionId, runtime};
-use fedimint_mint_common::MintInput;
+use fedimint_mint_common::KeyEpochKeys;
 
 use crate::input::{
     MintInputCommon, MintInputStateMachine, MintInputStateRefundedBundle, MintInputStates,
 };
-use crate::{MintClientContext, MintClientStateMachines, SpendableNote};
+use crate::{MintClientContext, MintClientStateMachines, SpendableNote, bitcoin_input};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
 pub enum MintOOBStatesV0 {
@@ -140,11 +141,18 @@ fn transitions(
     ) -> Vec<StateTransition<MintOOBStateMachine>> {
         let user_cancel_gc = global_context.clone();
         let timeout_cancel_gc = global_context.clone();
+        let user_cancel_keys = context.key_epoch_keys.borrow().clone();
+        let timeout_cancel_keys = context.key_epoch_keys.borrow().clone();
         vec![
             StateTransition::new(
                 context.await_cancel_oob_payment(operation_id),
                 move |dbtx, (), state| {
-                    Box::pin(transition_user_cancel(state, dbtx, user_cancel_gc.clone()))
+                    Box::pin(transition_user_cancel(
+                        state,
+                        dbtx,
+                        user_cancel_gc.clone(),
+                        user_cancel_keys.clone(),
+                    ))
                 },
             ),
             StateTransition::new(
@@ -154,6 +162,7 @@ fn transitions(
                         state,
                         dbtx,
                         timeout_cancel_gc.clone(),
+                        timeout_cancel_keys.clone(),
                     ))
                 },
             ),
@@ -170,6 +179,8 @@ fn transitions(
     ) -> Vec<StateTransition<MintOOBStateMachine>> {
         let user_cancel_gc = global_context.clone();
         let timeout_cancel_gc = global_context.clone();
+        let user_cancel_keys = context.key_epoch_keys.borrow().clone();
+        let timeout_cancel_keys = context.key_epoch_keys.borrow().clone();
         vec![
             StateTransition::new(
                 context.await_cancel_oob_payment(operation_id),
@@ -178,6 +189,7 @@ fn transitions(
                         state,
                         dbtx,
                         user_cancel_gc.clone(),
+                        user_cancel_keys.clone(),
                     ))
                 },
             ),
@@ -188,6 +200,7 @@ fn transitions(
                         state,
                         dbtx,
                         timeout_cancel_gc.clone(),
+                        timeout_cancel_keys.clone(),
                     ))
                 },
             ),
@@ -199,6 +212,7 @@ async fn transition_user_cancel(
     prev_state: MintOOBStateMachine,
     dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
     global_context: DynGlobalClientContext,
+    key_epoch_keys: BTreeMap<u64, KeyEpochKeys>,
 ) -> MintOOBStateMachine {
     let (amount, spendable_note) = match prev_state.state {
         MintOOBStates::Created(created) => (created.amount, created.spendable_note),
@@ -211,6 +225,7 @@ async fn transition_user_cancel(
         amount,
         spendable_note,
         global_context,
+        key_epoch_keys,
     )
     .await;
     MintOOBStateMachine {
@@ -223,6 +238,7 @@ async fn transition_user_cancel_multi(
     prev_state: MintOOBStateMachine,
     dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
     global_context: DynGlobalClientContext,
+    key_epoch_keys: BTreeMap<u64, KeyEpochKeys>,
 ) -> MintOOBStateMachine {
     let spendable_notes = match prev_state.state {
         MintOOBStates::CreatedMulti(created) => created.spendable_notes,
@@ -234,6 +250,7 @@ async fn transition_user_cancel_multi(
         prev_state.operation_id,
         spendable_notes.clone(),
         global_context,
+        key_epoch_keys,
     )
     .await;
     MintOOBStateMachine {
@@ -255,6 +272,7 @@ async fn transition_timeout_cancel(
     prev_state: MintOOBStateMachine,
     dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
     global_context: DynGlobalClientContext,
+    key_epoch_keys: BTreeMap<u64, KeyEpochKeys>,
 ) -> MintOOBStateMachine {
     let (amount, spendable_note) = match prev_state.state {
         MintOOBStates::Created(created) => (created.amount, created.spendable_note),
@@ -267,6 +285,7 @@ async fn transition_timeout_cancel(
         amount,
         spendable_note,
         global_context,
+        key_epoch_keys,
     )
     .await;
     MintOOBStateMachine {
@@ -279,6 +298,7 @@ async fn transition_timeout_cancel_multi(
     prev_state: MintOOBStateMachine,
     dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
     global_context: DynGlobalClientContext,
+    key_epoch_keys: BTreeMap<u64, KeyEpochKeys>,
 ) -> MintOOBStateMachine {
     let spendable_notes = match prev_state.state {
         MintOOBStates::CreatedMulti(created) => created.spendable_notes,
@@ -290,6 +310,7 @@ async fn transition_timeout_cancel_multi(
         prev_state.operation_id,
         spendable_notes,
         global_context,
+        key_epoch_keys,
     )
     .await;
     MintOOBStateMachine {
@@ -304,12 +325,14 @@ async fn try_cancel_oob_spend(
     amount: Amount,
     spendable_note: SpendableNote,
     global_context: DynGlobalClientContext,
+    key_epoch_keys: BTreeMap<u64, KeyEpochKeys>,
 ) -> TransactionId {
     try_cancel_oob_spend_multi(
         dbtx,
         operation_id,
         vec![(amount, spendable_note)],
         global_context,
+        key_epoch_keys,
     )
     .await
 }
@@ -319,12 +342,13 @@ async fn try_cancel_oob_spend_multi(
     operation_id: OperationId,
     spendable_notes: Vec<(Amount, SpendableNote)>,
     global_context: DynGlobalClientContext,
+    key_epoch_keys: BTreeMap<u64, KeyEpochKeys>,
 ) -> TransactionId {
     let inputs = spendable_notes
         .clone()
         .into_iter()
         .map(|(amount, spendable_note)| ClientInput {
-            input: MintInput::new_v0(amount, spendable_note.note()),
+            input: bitcoin_input(&key_epoch_keys, amount, spendable_note.note()),
             keys: vec![spendable_note.spend_key],
             amounts: Amounts::new_bitcoin(amount),
         })
@@ -15,14 +15,17 @@
 use fedimint_core::db::IDatabaseTransactionOpsCoreTyped;
 use fedimint_core::encoding::{Decodable, Encodable};
 use fedimint_core::endpoint_constants::AWAIT_OUTPUTS_OUTCOMES_ENDPOINT;
-use fedimint_core::module::ApiRequestErased;
+use fedimint_core::module::{AmountUnit, ApiRequestErased};
+use fedimint_core::secp256k1::rand::thread_rng;
 use fedimint_core::secp256k1::{Keypair, Secp256k1, Signing};
 use fedimint_core::util::FmtCompactAnyhow as _;
-use fedimint_core::{Amount, NumPeersExt, OutPoint, PeerId, Tiered, TransactionId, crit};
+use fedimint_core::{
+    Amount, NumPeersExt, OutPoint, PeerId, Tiered, TieredMulti, TransactionId, crit,
+};
 use fedimint_derive_secret::{ChildId, DerivableSecret};
 use fedimint_logging::LOG_CLIENT_MODULE_MINT;
 use fedimint_mint_common::endpoint_constants::AWAIT_OUTPUT_OUTCOME_ENDPOINT;
-use fedimint_mint_common::{BlindNonce, MintOutputOutcome, Nonce};
+use fedimint_mint_common::{BlindNonce, KeyEpochKeys, MintOutputOutcome, Nonce, Note, P2pkLock};
 use futures::future::join_all;
 use rayon::iter::{IndexedParallelIterator, IntoParallelIterator as _, ParallelIterator as _};
 use serde::{Deserialize, Serialize};
@@ -32,7 +35,7 @@
 };
 use tracing::debug;
 
-use crate::client_db::NoteKey;
+use crate::client_db::{NoteKey, UnitNoteKey};
 use crate::event::{NoteCreated, ReceivePaymentStatus, ReceivePaymentUpdateEvent};
 use crate::{MintClientContext, MintClientModule, SpendableNote};
 
@@ -73,6 +76,19 @@ pub enum MintOutputStates {
     Succeeded(MintOutputStatesSucceeded),
     /// Issuance request was created, we are waiting for blind signatures
     CreatedMulti(MintOutputStatesCreatedMulti),
+    /// Like [`Self::CreatedMulti`], but for notes of a non-bitcoin unit
+    CreatedMultiUnit(MintOutputStatesCreatedMultiUnit),
+    /// Like [`Self::CreatedMulti`], but for notes locked to a public key
+    CreatedLocked(MintOutputStatesCreatedLocked),
+    /// The locked notes were issued, instead of being added to our wallet they
+    /// are kept here to be handed to the recipient
+    SucceededLocked(MintOutputStatesSucceededLocked),
+    /// Like [`Self::CreatedMulti`], but for notes of a key epoch other than
+    /// zero
+    CreatedMultiKeyEpoch(MintOutputStatesCreatedMultiKeyEpoch),
+    /// Like [`Self::CreatedLocked`], but for notes of a key epoch other than
+    /// zero
+    CreatedLockedKeyEpoch(MintOutputStatesCreatedLockedKeyEpoch),
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
@@ -120,9 +136,22 @@ fn transitions(
             MintOutputStates::CreatedMulti(created) => {
                 created.transitions(context, global_context, self.common)
             }
+            MintOutputStates::CreatedMultiUnit(created) => {
+                created.transitions(context, global_context, self.common)
+            }
+            MintOutputStates::CreatedLocked(created) => {
+                created.transitions(context, global_context, self.common)
+            }
+            MintOutputStates::CreatedMultiKeyEpoch(created) => {
+                created.transitions(context, global_context, self.common)
+            }
+            MintOutputStates::CreatedLockedKeyEpoch(created) => {
+                created.transitions(context, global_context, self.common)
+            }
             MintOutputStates::Aborted(_)
             | MintOutputStates::Failed(_)
-            | MintOutputStates::Succeeded(_) => {
+            | MintOutputStates::Succeeded(_)
+            | MintOutputStates::SucceededLocked(_) => {
                 vec![]
             }
         }
@@ -148,7 +177,10 @@ fn transitions(
         global_context: &DynGlobalClientContext,
         common: MintOutputCommon,
     ) -> Vec<StateTransition<MintOutputStateMachine>> {
-        let tbs_pks = context.tbs_pks.clone();
+        // Notes restored from a backup may have been issued under any key epoch. The
+        // keys of a key epoch may only be fetched while we await the outcome, so we
+        // look them up whenever we verify a signature.
+        let key_epochs_context = context.clone();
         let client_ctx = context.client_ctx.clone();
         let balance_update_sender = context.balance_update_sender.clone();
 
@@ -166,7 +198,7 @@ fn transitions(
                     context.mint_decoder.clone(),
                     self.amount,
                     self.issuance_request.blinded_message(),
-                    context.peer_tbs_pks.clone(),
+                    context.clone(),
                 ),
                 move |dbtx, blinded_signature_shares, old_state| {
                     Box::pin(Self::transition_outcome_ready(
@@ -174,7 +206,11 @@ fn transitions(
                         dbtx,
                         blinded_signature_shares,
                         old_state,
-                        tbs_pks.clone(),
+                        key_epochs_context
+                            .key_epochs()
+                            .into_iter()
+                            .map(|keys| keys.tbs_pks)
+                            .collect(),
                         balance_update_sender.clone(),
                     ))
                 },
@@ -208,7 +244,7 @@ async fn await_outcome_ready(
         module_decoder: Decoder,
         amount: Amount,
         message: BlindedMessage,
-        tbs_pks: BTreeMap<PeerId, Tiered<PublicKeyShare>>,
+        context: MintClientContext,
     ) -> BTreeMap<PeerId, BlindedSignatureShare> {
         global_context
             .api()
@@ -216,15 +252,22 @@ async fn await_outcome_ready(
                 // this query collects a threshold of 2f + 1 valid blind signature shares
                 FilterMapThreshold::new(
                     move |peer, outcome| {
-                        verify_blind_share(
-                            peer,
-                            &outcome,
-                            amount,
-                            message,
-                            &module_decoder,
-                            &tbs_pks,
-                        )
-                        .map_err(ServerError::InvalidResponse)
+                        let mut result = Err(anyhow!("No key epochs"));
+                        for keys in context.key_epochs() {
+                            result = verify_blind_share(
+                                peer,
+                                &outcome,
+                                amount,
+                                message,
+                                &module_decoder,
+                                &keys.peer_tbs_pks,
+                            );
+
+                            if result.is_ok() {
+                                break;
+                            }
+                        }
+                        result.map_err(ServerError::InvalidResponse)
                     },
                     global_context.api().all_peers().to_num_peers(),
                 ),
@@ -242,7 +285,7 @@ async fn transition_outcome_ready(
         dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
         blinded_signature_shares: BTreeMap<PeerId, BlindedSignatureShare>,
         old_state: MintOutputStateMachine,
-        tbs_pks: Tiered<AggregatePublicKey>,
+        tbs_pks: Vec<Tiered<AggregatePublicKey>>,
         balance_update_sender: tokio::sync::watch::Sender<()>,
     ) -> MintOutputStateMachine {
         // we combine the shares, finalize the issuance request with the blind signature
@@ -259,23 +302,25 @@ async fn transition_outcome_ready(
                 .collect(),
         );
 
-        let amount_key = tbs_pks
-            .tier(&created.amount)
-            .expect("We obtained this amount from tbs_pks when we created the output");
-
         // this implies that the mint client config's public keys are inconsistent
-        if !tbs::verify_blinded_signature(
-            created.issuance_request.blinded_message(),
-            agg_blind_signature,
-            *amount_key,
-        ) {
+        let Some(amount_key) = tbs_pks
+            .iter()
+            .filter_map(|tbs_pks| tbs_pks.tier(&created.amount).ok())
+            .find(|amount_key| {
+                tbs::verify_blinded_signature(
+                    created.issuance_request.blinded_message(),
+                    agg_blind_signature,
+                    **amount_key,
+                )
+            })
+        else {
             return MintOutputStateMachine {
                 common: old_state.common,
                 state: MintOutputStates::Failed(MintOutputStatesFailed {
                     error: "Invalid blind signature".to_string(),
                 }),
             };
-        }
+        };
 
         let spendable_note = created.issuance_request.finalize(agg_blind_signature);
 
@@ -354,7 +399,7 @@ fn transitions(
                     global_context.clone(),
                     common,
                     context.mint_decoder.clone(),
-                    self.issuance_requests.clone(),
+                    blinded_messages(&self.issuance_requests),
                     context.peer_tbs_pks.clone(),
                 ),
                 move |dbtx, blinded_signature_shares, old_state| {
@@ -387,7 +432,12 @@ async fn transition_tx_rejected(
         dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
         old_state: MintOutputStateMachine,
     ) -> MintOutputStateMachine {
-        assert_matches!(old_state.state, MintOutputStates::CreatedMulti(_));
+        assert_matches!(
+            old_state.state,
+            MintOutputStates::CreatedMulti(_)
+                | MintOutputStates::CreatedMultiUnit(_)
+                | MintOutputStates::CreatedMultiKeyEpoch(_)
+        );
 
         client_ctx
             .log_event(
@@ -409,7 +459,7 @@ async fn await_outcome_ready(
         global_context: DynGlobalClientContext,
         common: MintOutputCommon,
         module_decoder: Decoder,
-        issuance_requests: BTreeMap<u64, (Amount, NoteIssuanceRequest)>,
+        blinded_messages: BTreeMap<u64, (Amount, BlindedMessage)>,
         tbs_pks: BTreeMap<PeerId, Tiered<PublicKeyShare>>,
     ) -> Vec<(u64, BTreeMap<PeerId, BlindedSignatureShare>)> {
         let api = global_context.api();
@@ -417,18 +467,12 @@ async fn await_outcome_ready(
 
         // Use the new efficient batch endpoint if the server supports it
         if VERSION_THAT_INTRODUCED_AWAIT_OUTPUTS_OUTCOMES <= core_api_version {
-            Self::await_outcome_ready_batch(api, common, module_decoder, issuance_requests, tbs_pks)
+            Self::await_outcome_ready_batch(api, common, module_decoder, blinded_messages, tbs_pks)
                 .await
         } else {
             // Fall back to the old sequential approach for older servers
-            Self::await_outcome_ready_legacy(
-                api,
-                common,
-                module_decoder,
-                issuance_requests,
-                tbs_pks,
-            )
-            .await
+            Self::await_outcome_ready_legacy(api, common, module_decoder, blinded_messages, tbs_pks)
+                .await
         }
     }
 
@@ -437,16 +481,16 @@ async fn await_outcome_ready_batch(
         api: &fedimint_api_client::api::DynGlobalApi,
         common: MintOutputCommon,
         module_decoder: Decoder,
-        issuance_requests: BTreeMap<u64, (Amount, NoteIssuanceRequest)>,
+        blinded_messages: BTreeMap<u64, (Amount, BlindedMessage)>,
         tbs_pks: BTreeMap<PeerId, Tiered<PublicKeyShare>>,
     ) -> Vec<(u64, BTreeMap<PeerId, BlindedSignatureShare>)> {
-        if issuance_requests.is_empty() {
+        if blinded_messages.is_empty() {
             return vec![];
         }
 
         // Use custom query strategy to collect and verify outcomes from all guardians
-        let issuance_requests_clone = issuance_requests.clone();
-        let verified_shares_per_output: BTreeMap<PeerId, Vec<Option<BlindedSignatureShare>>> = api
+        let blinded_messages_clone = blinded_messages.clone();
+        let verified_shares_per_output: BTreeMap<PeerId, Vec<BlindedSignatureShare>> = api
             .request_with_strategy_retry(
                 FilterMapThreshold::new(
                     move |peer, outcomes: Vec<Option<SerdeOutputOutcome>>| {
@@ -466,37 +510,40 @@ async fn await_outcome_ready_batch(
                             let out_idx = common.out_point_range.start_idx() + relative_idx as u64;
 
                             // We should have an issuance request for every output in the range
-                            let (amount, issuance_request) = issuance_requests_clone
+                            let (amount, blinded_message) = blinded_messages_clone
                                 .get(&out_idx)
                                 .expect("issuance_request must exist for every output in range");
 
-                            let share = if let Some(outcome) = outcome_opt {
-                                match verify_blind_share(
-                                    peer,
-                                    &outcome,
-                                    *amount,
-                                    issuance_request.blinded_message(),
-                                    &module_decoder,
-                                    &tbs_pks,
-                                ) {
-                                    Ok(share) => Some(share),
-                                    Err(err) => {
-                                        // Invalid share - reject entire response from this guardian
-                                        tracing::warn!(
-                                            target: LOG_CLIENT_MODULE_MINT,
-                                            %peer,
-                                            err = %err.fmt_compact_anyhow(),
-                                            out_point = %OutPoint { txid: common.txid(), out_idx},
-                                            "Invalid signature share from peer"
-                                        );
-                                        return Err(ServerError::InvalidResponse(err));
-                                    }
-                                }
-                            } else {
-                                None
+                            // Guardians that did not take part in the DKG of a key epoch
+                            // don't sign its outputs, so we collect the shares of the
+                            // participants instead
+                            let Some(outcome) = outcome_opt else {
+                                return Err(ServerError::InvalidResponse(anyhow::anyhow!(
+                                    "Peer {peer} did not sign output {out_idx}"
+                                )));
                             };
 
-                            verified_shares.push(share);
+                            match verify_blind_share(
+                                peer,
+                                &outcome,
+                                *amount,
+                                *blinded_message,
+                                &module_decoder,
+                                &tbs_pks,
+                            ) {
+                                Ok(share) => verified_shares.push(share),
+                                Err(err) => {
+                                    // Invalid share - reject entire response from this guardian
+                                    tracing::warn!(
+                                        target: LOG_CLIENT_MODULE_MINT,
+                                        %peer,
+                                        err = %err.fmt_compact_anyhow(),
+                                        out_point = %OutPoint { txid: common.txid(), out_idx},
+                                        "Invalid signature share from peer"
+                                    );
+                                    return Err(ServerError::InvalidResponse(err));
+                                }
+                            }
                         }
 
                         Ok(verified_shares)
@@ -512,13 +559,13 @@ async fn await_outcome_ready_batch(
         let threshold = api.all_peers().to_num_peers().threshold();
         let mut ret = vec![];
 
-        for (out_idx, (_amount, _issuance_request)) in issuance_requests {
+        for (out_idx, (_amount, _blinded_message)) in blinded_messages {
             let relative_idx = (out_idx - common.out_point_range.start_idx()) as usize;
             let mut blinded_sig_shares = BTreeMap::new();
 
             // Collect verified shares from all peers for this output
             for (peer_id, shares) in &verified_shares_per_output {
-                if let Some(Some(share)) = shares.get(relative_idx) {
+                if let Some(share) = shares.get(relative_idx) {
                     blinded_sig_shares.insert(*peer_id, *share);
                 }
             }
@@ -535,15 +582,15 @@ async fn await_outcome_ready_legacy(
         api: &fedimint_api_client::api::DynGlobalApi,
         common: MintOutputCommon,
         module_decoder: Decoder,
-        issuance_requests: BTreeMap<u64, (Amount, NoteIssuanceRequest)>,
+        blinded_messages: BTreeMap<u64, (Amount, BlindedMessage)>,
         tbs_pks: BTreeMap<PeerId, Tiered<PublicKeyShare>>,
     ) -> Vec<(u64, BTreeMap<PeerId, BlindedSignatureShare>)> {
         let mut ret = vec![];
-        let mut issuance_requests_iter = issuance_requests.into_iter();
+        let mut blinded_messages_iter = blinded_messages.into_iter();
 
         // Wait for the result of the first output only, to save server side
         // resources
-        if let Some((out_idx, (amount, issuance_request))) = issuance_requests_iter.next() {
+        if let Some((out_idx, (amount, blinded_message))) = blinded_messages_iter.next() {
             let module_decoder = module_decoder.clone();
             let tbs_pks = tbs_pks.clone();
 
@@ -555,7 +602,7 @@ async fn await_outcome_ready_legacy(
                                 peer,
                                 &outcome,
                                 amount,
-                                issuance_request.blinded_message(),
+                                blinded_message,
                                 &module_decoder,
                                 &tbs_pks,
                             )
@@ -579,7 +626,7 @@ async fn await_outcome_ready_legacy(
         // We know the tx outcomes are ready, get all of them at once
         ret.extend(
             join_all(
-                issuance_requests_iter.map(|(out_idx, (amount, issuance_request))| {
+                blinded_messages_iter.map(|(out_idx, (amount, blinded_message))| {
                     let module_decoder = module_decoder.clone();
                     let tbs_pks = tbs_pks.clone();
                     async move {
@@ -591,7 +638,7 @@ async fn await_outcome_ready_legacy(
                                             peer,
                                             &outcome,
                                             amount,
-                                            issuance_request.blinded_message(),
+                                            blinded_message,
                                             &module_decoder,
                                             &tbs_pks,
                                         )
@@ -629,8 +676,17 @@ async fn transition_outcome_ready(
         // and store the resulting note in the database
 
         let mut amount_total = Amount::ZERO;
-        let MintOutputStates::CreatedMulti(created) = old_state.state else {
-            panic!("Unexpected prior state")
+        let (unit, issuance_requests) = match old_state.state {
+            MintOutputStates::CreatedMulti(created) => {
+                (AmountUnit::BITCOIN, created.issuance_requests)
+            }
+            MintOutputStates::CreatedMultiUnit(created) => {
+                (created.unit, created.issuance_requests)
+            }
+            MintOutputStates::CreatedMultiKeyEpoch(created) => {
+                (AmountUnit::BITCOIN, created.issuance_requests)
+            }
+            _ => panic!("Unexpected prior state"),
         };
 
         let mut spendable_notes: Vec<(Amount, SpendableNote)> = vec![];
@@ -648,7 +704,7 @@ async fn transition_outcome_ready(
 
                 // this implies that the mint client config's public keys are inconsistent
                 let (amount, issuance_request) =
-                    created.issuance_requests.get(&out_idx).expect("Must have");
+                    issuance_requests.get(&out_idx).expect("Must have");
 
                 let amount_key = tbs_pks.tier(amount).expect("Must have keys for any amount");
 
@@ -673,17 +729,29 @@ async fn transition_outcome_ready(
                 .await;
 
             amount_total += amount;
-            if let Some(note) = dbtx
-                .module_tx()
-                .insert_entry(
-                    &NoteKey {
-                        amount,
-                        nonce: spendable_note.nonce(),
-                    },
-                    &spendable_note.to_undecoded(),
-                )
-                .await
-            {
+            let replaced_note = if unit.is_bitcoin() {
+                dbtx.module_tx()
+                    .insert_entry(
+                        &NoteKey {
+                            amount,
+                            nonce: spendable_note.nonce(),
+                        },
+                        &spendable_note.to_undecoded(),
+                    )
+                    .await
+            } else {
+                dbtx.module_tx()
+                    .insert_entry(
+                        &UnitNoteKey {
+                            unit,
+                            amount,
+                            nonce: spendable_note.nonce(),
+                        },
+                        &spendable_note.to_undecoded(),
+                    )
+                    .await
+            };
+            if let Some(note) = replaced_note {
                 crit!(target: LOG_CLIENT_MODULE_MINT, %note, "E-cash note was replaced in DB");
             }
         }
@@ -710,6 +778,285 @@ async fn transition_outcome_ready(
     }
 }
 
+/// See [`MintOutputStates`]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
+pub struct MintOutputStatesCreatedMultiUnit {
+    pub(crate) unit: AmountUnit,
+    pub(crate) issuance_requests: BTreeMap<u64, (Amount, NoteIssuanceRequest)>,
+}
+
+impl MintOutputStatesCreatedMultiUnit {
+    fn transitions(
+        &self,
+        context: &MintClientContext,
+        global_context: &DynGlobalClientContext,
+        common: MintOutputCommon,
+    ) -> Vec<StateTransition<MintOutputStateMachine>> {
+        let unit_cfg = context
+            .custom_units
+            .get(&self.unit)
+            .expect("We only create outputs for units of the mint config");
+        let tbs_pks = unit_cfg.tbs_pks.clone();
+        let client_ctx = context.client_ctx.clone();
+        let client_ctx_rejected = context.client_ctx.clone();
+        let balance_update_sender = context.balance_update_sender.clone();
+
+        // The state transitions are the same as for bitcoin notes, only the keys
+        // used to verify the blind signatures differ
+        vec![
+            StateTransition::new(
+                MintOutputStatesCreatedMulti::await_tx_rejected(global_context.clone(), common),
+                move |dbtx, (), state| {
+                    Box::pin(MintOutputStatesCreatedMulti::transition_tx_rejected(
+                        client_ctx_rejected.clone(),
+                        dbtx,
+                        state,
+                    ))
+                },
+            ),
+            StateTransition::new(
+                MintOutputStatesCreatedMulti::await_outcome_ready(
+                    global_context.clone(),
+                    common,
+                    context.mint_decoder.clone(),
+                    blinded_messages(&self.issuance_requests),
+                    unit_cfg.peer_tbs_pks.clone(),
+                ),
+                move |dbtx, blinded_signature_shares, old_state| {
+                    Box::pin(MintOutputStatesCreatedMulti::transition_outcome_ready(
+                        client_ctx.clone(),
+                        dbtx,
+                        blinded_signature_shares,
+                        old_state,
+                        tbs_pks.clone(),
+                        balance_update_sender.clone(),
+                    ))
+                },
+            ),
+        ]
+    }
+}
+
+/// See [`MintOutputStates`]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
+pub struct MintOutputStatesCreatedMultiKeyEpoch {
+    pub(crate) key_epoch: u64,
+    pub(crate) issuance_requests: BTreeMap<u64, (Amount, NoteIssuanceRequest)>,
+}
+
+impl MintOutputStatesCreatedMultiKeyEpoch {
+    fn transitions(
+        &self,
+        context: &MintClientContext,
+        global_context: &DynGlobalClientContext,
+        common: MintOutputCommon,
+    ) -> Vec<StateTransition<MintOutputStateMachine>> {
+        let KeyEpochKeys {
+            tbs_pks,
+            peer_tbs_pks,
+        } = context
+            .key_epoch_keys(self.key_epoch)
+            .expect("We only create outputs for key epochs we know the keys of");
+        let client_ctx = context.client_ctx.clone();
+        let client_ctx_rejected = context.client_ctx.clone();
+        let balance_update_sender = context.balance_update_sender.clone();
+
+        vec![
+            StateTransition::new(
+                MintOutputStatesCreatedMulti::await_tx_rejected(global_context.clone(), common),
+                move |dbtx, (), state| {
+                    Box::pin(MintOutputStatesCreatedMulti::transition_tx_rejected(
+                        client_ctx_rejected.clone(),
+                        dbtx,
+                        state,
+                    ))
+                },
+            ),
+            StateTransition::new(
+                MintOutputStatesCreatedMulti::await_outcome_ready(
+                    global_context.clone(),
+                    common,
+                    context.mint_decoder.clone(),
+                    blinded_messages(&self.issuance_requests),
+                    peer_tbs_pks,
+                ),
+                move |dbtx, blinded_signature_shares, old_state| {
+                    Box::pin(MintOutputStatesCreatedMulti::transition_outcome_ready(
+                        client_ctx.clone(),
+                        dbtx,
+                        blinded_signature_shares,
+                        old_state,
+                        tbs_pks.clone(),
+                        balance_update_sender.clone(),
+                    ))
+                },
+            ),
+        ]
+    }
+}
+
+/// See [`MintOutputStates`]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
+pub struct MintOutputStatesCreatedLocked {
+    pub(crate) lock: P2pkLock,
+    pub(crate) issuance_requests: BTreeMap<u64, (Amount, NoteIssuanceRequest)>,
+}
+
+impl MintOutputStatesCreatedLocked {
+    fn transitions(
+        &self,
+        context: &MintClientContext,
+        global_context: &DynGlobalClientContext,
+        common: MintOutputCommon,
+    ) -> Vec<StateTransition<MintOutputStateMachine>> {
+        self.transitions_with_keys(
+            context.tbs_pks.clone(),
+            context.peer_tbs_pks.clone(),
+            context,
+            global_context,
+            common,
+        )
+    }
+
+    fn transitions_with_keys(
+        &self,
+        tbs_pks: Tiered<AggregatePublicKey>,
+        peer_tbs_pks: BTreeMap<PeerId, Tiered<PublicKeyShare>>,
+        context: &MintClientContext,
+        global_context: &DynGlobalClientContext,
+        common: MintOutputCommon,
+    ) -> Vec<StateTransition<MintOutputStateMachine>> {
+        let blinded_messages = self
+            .issuance_requests
+            .iter()
+            .map(|(out_idx, (amount, issuance_request))| {
+                (
+                    *out_idx,
+                    (*amount, issuance_request.blinded_locked_message(&self.lock)),
+                )
+            })
+            .collect();
+
+        vec![
+            StateTransition::new(
+                MintOutputStatesCreatedMulti::await_tx_rejected(global_context.clone(), common),
+                |_dbtx, (), state| Box::pin(async move { Self::transition_tx_rejected(&state) }),
+            ),
+            StateTransition::new(
+                MintOutputStatesCreatedMulti::await_outcome_ready(
+                    global_context.clone(),
+                    common,
+                    context.mint_decoder.clone(),
+                    blinded_messages,
+                    peer_tbs_pks,
+                ),
+                move |_dbtx, blinded_signature_shares, old_state| {
+                    let tbs_pks = tbs_pks.clone();
+                    Box::pin(async move {
+                        Self::transition_outcome_ready(
+                            blinded_signature_shares,
+                            &old_state,
+                            &tbs_pks,
+                        )
+                    })
+                },
+            ),
+        ]
+    }
+
+    fn transition_tx_rejected(old_state: &MintOutputStateMachine) -> MintOutputStateMachine {
+        assert_matches!(
+            old_state.state,
+            MintOutputStates::CreatedLocked(_) | MintOutputStates::CreatedLockedKeyEpoch(_)
+        );
+
+        MintOutputStateMachine {
+            common: old_state.common,
+            state: MintOutputStates::Aborted(MintOutputStatesAborted),
+        }
+    }
+
+    fn transition_outcome_ready(
+        blinded_signature_shares: Vec<(u64, BTreeMap<PeerId, BlindedSignatureShare>)>,
+        old_state: &MintOutputStateMachine,
+        tbs_pks: &Tiered<AggregatePublicKey>,
+    ) -> MintOutputStateMachine {
+        let created = match &old_state.state {
+            MintOutputStates::CreatedLocked(created) => created,
+            MintOutputStates::CreatedLockedKeyEpoch(created) => &created.created,
+            _ => panic!("Unexpected prior state"),
+        };
+
+        let notes = blinded_signature_shares
+            .into_iter()
+            .map(|(out_idx, blinded_signature_shares)| {
+                let agg_blind_signature = aggregate_signature_shares(
+                    &blinded_signature_shares
+                        .into_iter()
+                        .map(|(peer, share)| (peer.to_usize() as u64, share))
+                        .collect(),
+                );
+
+                let (amount, issuance_request) =
+                    created.issuance_requests.get(&out_idx).expect("Must have");
+
+                let amount_key = tbs_pks.tier(amount).expect("Must have keys for any amount");
+
+                let note = issuance_request.finalize_locked(agg_blind_signature);
+
+                assert!(note.verify_locked(&created.lock, *amount_key), "We checked all signature shares in the trigger future, so the combined signature has to be valid");
+
+                (*amount, note)
+            })
+            .collect();
+
+        MintOutputStateMachine {
+            common: old_state.common,
+            state: MintOutputStates::SucceededLocked(MintOutputStatesSucceededLocked {
+                lock: created.lock,
+                notes,
+            }),
+        }
+    }
+}
+
+/// See [`MintOutputStates`]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
+pub struct MintOutputStatesCreatedLockedKeyEpoch {
+    pub(crate) key_epoch: u64,
+    pub(crate) created: MintOutputStatesCreatedLocked,
+}
+
+impl MintOutputStatesCreatedLockedKeyEpoch {
+    fn transitions(
+        &self,
+        context: &MintClientContext,
+        global_context: &DynGlobalClientContext,
+        common: MintOutputCommon,
+    ) -> Vec<StateTransition<MintOutputStateMachine>> {
+        let KeyEpochKeys {
+            tbs_pks,
+            peer_tbs_pks,
+        } = context
+            .key_epoch_keys(self.key_epoch)
+            .expect("We only create outputs for key epochs we know the keys of");
+
+        self.created
+            .transitions_with_keys(tbs_pks, peer_tbs_pks, context, global_context, common)
+    }
+}
+
+fn blinded_messages(
+    issuance_requests: &BTreeMap<u64, (Amount, NoteIssuanceRequest)>,
+) -> BTreeMap<u64, (Amount, BlindedMessage)> {
+    issuance_requests
+        .iter()
+        .map(|(out_idx, (amount, issuance_request))| {
+            (*out_idx, (*amount, issuance_request.blinded_message()))
+        })
+        .collect()
+}
+
 /// # Panics
 /// If the given `outcome` is not a [`MintOutputOutcome::V0`] outcome.
 pub fn verify_blind_share(
@@ -756,6 +1103,13 @@ pub struct MintOutputStatesSucceeded {
     pub amount: Amount,
 }
 
+/// See [`MintOutputStates`]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
+pub struct MintOutputStatesSucceededLocked {
+    pub lock: P2pkLock,
+    pub notes: TieredMulti<Note>,
+}
+
 /// Keeps the data to generate [`SpendableNote`] once the
 /// mint successfully processed the transaction signing the corresponding
 /// [`BlindNonce`].
@@ -804,6 +1158,40 @@ pub fn blinded_message(&self) -> BlindedMessage {
         blind_message(self.nonce().to_message(), self.blinding_key)
     }
 
+    /// Generate a request session for a single note locked by `lock` and
+    /// returns it plus the corresponding blinded message
+    ///
+    /// The keys are random instead of derived from our secret since the spend
+    /// key can't be used to redeem a locked note and the note is handed to the
+    /// recipient instead of being kept in our wallet, so there is nothing to
+    /// recover.
+    pub fn new_locked<C>(ctx: &Secp256k1<C>, lock: &P2pkLock) -> (NoteIssuanceRequest, BlindNonce)
+    where
+        C: Signing,
+    {
+        let cr = NoteIssuanceRequest {
+            spend_key: Keypair::new(ctx, &mut thread_rng()),
+            blinding_key: BlindingKey::random(),
+        };
+
+        let blinded_nonce = cr.blinded_locked_message(lock);
+
+        (cr, BlindNonce(blinded_nonce))
+    }
+
+    pub fn blinded_locked_message(&self, lock: &P2pkLock) -> BlindedMessage {
+        blind_message(lock.message(self.nonce()), self.blinding_key)
+    }
+
+    /// Use the blind signature to create a note locked by the [`P2pkLock`]
+    /// this request was created for
+    pub fn finalize_locked(&self, blinded_signature: BlindedSignature) -> Note {
+        Note {
+            nonce: self.nonce(),
+            signature: unblind_signature(self.blinding_key, blinded_signature),
+        }
+    }
+
     /// Use the blind signature to create spendable e-cash notes
     pub fn finalize(&self, blinded_signature: BlindedSignature) -> SpendableNote {
         SpendableNote {
@@ -0,0 +1,74 @@
+//! E-cash notes locked to a public key
+//!
+//! Unlike [`OOBNotes`](crate::OOBNotes), which can be redeemed by anyone
+//! learning about them, [`P2pkNotes`] can only be redeemed with a signature of
+//! the recipient's key, so they can be sent over insecure channels. If the
+//! [`P2pkLock`] has a refund key, the sender can reclaim notes the recipient
+//! never redeemed once the timelock expired.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::ensure;
+use fedimint_core::config::FederationIdPrefix;
+use fedimint_core::encoding::{Decodable, Encodable};
+use fedimint_core::{Amount, TieredMulti, base32};
+use fedimint_mint_common::{Note, P2pkLock};
+use serde::{Deserialize, Serialize};
+
+/// Prefix of base32 encoded [`P2pkNotes`]
+pub const P2PK_NOTES_PREFIX: &str = "fedimintp2pk";
+
+/// Bitcoin e-cash notes that were all issued under the same [`P2pkLock`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Encodable, Decodable)]
+pub struct P2pkNotes {
+    pub federation_id_prefix: FederationIdPrefix,
+    pub lock: P2pkLock,
+    pub notes: TieredMulti<Note>,
+}
+
+impl P2pkNotes {
+    /// Returns the total value of all notes in msat as `Amount`
+    pub fn total_amount(&self) -> Amount {
+        self.notes.total_amount()
+    }
+}
+
+impl FromStr for P2pkNotes {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s: String = s.chars().filter(|&c| !c.is_whitespace()).collect();
+
+        let p2pk_notes: P2pkNotes = base32::decode_prefixed(P2PK_NOTES_PREFIX, &s)?;
+
+        ensure!(!p2pk_notes.notes.is_empty(), "P2pkNotes cannot be empty");
+
+        Ok(p2pk_notes)
+    }
+}
+
+impl Display for P2pkNotes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&base32::encode_prefixed(P2PK_NOTES_PREFIX, self))
+    }
+}
+
+impl Serialize for P2pkNotes {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for P2pkNotes {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        FromStr::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,441 @@
+//! Receiver-initiated e-cash payments
+//!
+//! With [`OOBNotes`] the payer has to get the notes to the recipient somehow.
+//! An [`EcashPaymentRequest`] reverses this: the recipient creates a request
+//! for a specific amount that tells the payer where to deliver the notes, the
+//! payer spends the notes and delivers them as an [`EcashPayment`] to the
+//! request's [`EcashPaymentTarget`].
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{bail, ensure};
+use fedimint_core::config::FederationIdPrefix;
+use fedimint_core::core::OperationId;
+use fedimint_core::db::IDatabaseTransactionOpsCoreTyped as _;
+use fedimint_core::encoding::{Decodable, Encodable};
+use fedimint_core::module::CommonModuleInit as _;
+use fedimint_core::time::duration_since_epoch;
+use fedimint_core::util::SafeUrl;
+use fedimint_core::{Amount, base32};
+use fedimint_logging::LOG_CLIENT_MODULE_MINT;
+use fedimint_mint_common::MintCommonInit;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::client_db::ReceivedEcashPaymentRequestKey;
+use crate::event::{EcashPaymentReceived, EcashPaymentRequestPaid};
+use crate::{
+    MintClientModule, MintOperationMeta, MintOperationMetaVariant, OOBNotes,
+    ReissueExternalNotesState, SelectNotesWithAtleastAmount, reissue_external_notes_operation_id,
+};
+
+/// Prefix of base32 encoded [`EcashPaymentRequest`]s
+pub const ECASH_PAYMENT_REQUEST_PREFIX: &str = "fedimintreq";
+
+/// How long we wait for the target of a payment request to accept a payment
+const PAYMENT_DELIVERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A request for a payment in bitcoin e-cash, created by the recipient
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Encodable, Decodable)]
+pub struct EcashPaymentRequest {
+    /// Identifies the request, it is the id of the recipient's
+    /// [`MintOperationMetaVariant::PaymentRequest`] operation
+    pub id: OperationId,
+    pub federation_id_prefix: FederationIdPrefix,
+    pub amount: Amount,
+    /// Description of the payment shown to the payer
+    pub memo: String,
+    /// Unix timestamp in seconds after which the request must not be paid
+    pub expiry: u64,
+    pub target: EcashPaymentTarget,
+}
+
+/// Where the payer delivers the [`EcashPayment`] for a request
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Encodable, Decodable, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EcashPaymentTarget {
+    /// The payment is sent to the URL as JSON in a POST request
+    Http { url: SafeUrl },
+    #[encodable_default]
+    Default { variant: u64, bytes: Vec<u8> },
+}
+
+/// The notes paying an [`EcashPaymentRequest`], as delivered to its target
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EcashPayment {
+    /// The [`EcashPaymentRequest::id`] of the paid request
+    pub request_id: OperationId,
+    pub notes: OOBNotes,
+}
+
+impl EcashPaymentRequest {
+    pub fn is_expired(&self) -> bool {
+        self.expiry <= duration_since_epoch().as_secs()
+    }
+}
+
+impl FromStr for EcashPaymentRequest {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s: String = s.chars().filter(|&c| !c.is_whitespace()).collect();
+
+        let request: EcashPaymentRequest =
+            base32::decode_prefixed(ECASH_PAYMENT_REQUEST_PREFIX, &s)?;
+
+        ensure!(
+            request.amount > Amount::ZERO,
+            "EcashPaymentRequest amount cannot be zero"
+        );
+
+        Ok(request)
+    }
+}
+
+impl Display for EcashPaymentRequest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&base32::encode_prefixed(ECASH_PAYMENT_REQUEST_PREFIX, self))
+    }
+}
+
+impl Serialize for EcashPaymentRequest {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for EcashPaymentRequest {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        FromStr::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+impl MintClientModule {
+    /// Creates a request for a payment of `amount` that the payer delivers to
+    /// `target` and records it in the operation log
+    pub async fn create_ecash_payment_request<M: Serialize + Send>(
+        &self,
+        amount: Amount,
+        memo: String,
+        expires_in: Duration,
+        target: EcashPaymentTarget,
+        extra_meta: M,
+    ) -> anyhow::Result<EcashPaymentRequest> {
+        ensure!(
+            amount > Amount::ZERO,
+            "Requesting zero-amount e-cash isn't supported"
+        );
+
+        let request = EcashPaymentRequest {
+            id: OperationId::new_random(),
+            federation_id_prefix: self.federation_id.to_prefix(),
+            amount,
+            memo,
+            expiry: (duration_since_epoch() + expires_in).as_secs(),
+            target,
+        };
+
+        let extra_meta = serde_json::to_value(extra_meta)
+            .expect("MintClientModule::create_ecash_payment_request extra_meta is serializable");
+
+        self.client_ctx
+            .manual_operation_start(
+                request.id,
+                MintCommonInit::KIND.as_str(),
+                MintOperationMeta {
+                    variant: MintOperationMetaVariant::PaymentRequest {
+                        request: request.clone(),
+                    },
+                    amount,
+                    extra_meta,
+                },
+                vec![],
+            )
+            .await?;
+
+        Ok(request)
+    }
+
+    /// Spends notes worth at least the requested amount and delivers them to
+    /// the request's target, returning the id of the spend operation
+    ///
+    /// If the delivery fails the spend is canceled. Like for
+    /// [`MintClientModule::spend_notes_with_selector`] the client tries to
+    /// reclaim the notes after `try_cancel_after` if the recipient didn't
+    /// reissue them.
+    pub async fn pay_ecash_payment_request<M: Serialize + Send>(
+        &self,
+        request: EcashPaymentRequest,
+        try_cancel_after: Duration,
+        extra_meta: M,
+    ) -> anyhow::Result<OperationId> {
+        ensure!(
+            request.federation_id_prefix == self.federation_id.to_prefix(),
+            "The payment request is for a different federation"
+        );
+        ensure!(!request.is_expired(), "The payment request expired");
+
+        let EcashPaymentTarget::Http { url } = &request.target else {
+            bail!("Unsupported payment request target");
+        };
+
+        let (operation_id, notes) = self
+            .spend_notes_with_selector_inner(
+                &SelectNotesWithAtleastAmount,
+                request.amount,
+                try_cancel_after,
+                false,
+                Some(request.clone()),
+                extra_meta,
+            )
+            .await?;
+
+        let payment = EcashPayment {
+            request_id: request.id,
+            notes,
+        };
+
+        debug!(target: LOG_CLIENT_MODULE_MINT, %url, request_id = %request.id.fmt_short(), "Delivering e-cash payment");
+
+        if let Err(error) = deliver_payment(url, &payment).await {
+            warn!(target: LOG_CLIENT_MODULE_MINT, %url, err = %error, "Failed to deliver e-cash payment, canceling spend");
+            self.try_cancel_spend_notes(operation_id).await;
+            return Err(error);
+        }
+
+        let mut dbtx = self.client_ctx.module_db().begin_transaction().await;
+        self.client_ctx
+            .log_event(
+                &mut dbtx,
+                EcashPaymentRequestPaid {
+                    operation_id,
+                    request_id: request.id,
+                    amount: payment.notes.total_amount(),
+                },
+            )
+            .await;
+        dbtx.commit_tx().await;
+
+        Ok(operation_id)
+    }
+
+    /// Reissues the notes of a payment delivered for one of our requests,
+    /// returning the id of the reissuance operation
+    ///
+    /// Every request is only received once, unless reissuing the previous
+    /// payment for it failed.
+    pub async fn receive_ecash_payment(
+        &self,
+        payment: EcashPayment,
+    ) -> anyhow::Result<OperationId> {
+        let operation = self.mint_operation(payment.request_id).await?;
+        let MintOperationMetaVariant::PaymentRequest { request } =
+            operation.meta::<MintOperationMeta>().variant
+        else {
+            bail!("Operation is not a payment request");
+        };
+
+        ensure!(!request.is_expired(), "The payment request expired");
+
+        let amount = payment.notes.total_amount();
+        ensure!(
+            request.amount <= amount,
+            "The payment of {amount} is less than the requested {}",
+            request.amount
+        );
+
+        self.mark_ecash_payment_request_received(
+            request.id,
+            reissue_external_notes_operation_id(payment.notes.notes()),
+        )
+        .await?;
+
+        let operation_id = match self
+            .reissue_external_notes(
+                payment.notes,
+                serde_json::json!({ "payment_request": request.id }),
+            )
+            .await
+        {
+            Ok(operation_id) => operation_id,
+            Err(error) => {
+                let mut dbtx = self.client_ctx.module_db().begin_transaction().await;
+                dbtx.remove_entry(&ReceivedEcashPaymentRequestKey(request.id))
+                    .await;
+                dbtx.commit_tx().await;
+
+                return Err(error);
+            }
+        };
+
+        let mut dbtx = self.client_ctx.module_db().begin_transaction().await;
+        self.client_ctx
+            .log_event(
+                &mut dbtx,
+                EcashPaymentReceived {
+                    operation_id,
+                    request_id: request.id,
+                    amount,
+                },
+            )
+            .await;
+        dbtx.commit_tx().await;
+
+        Ok(operation_id)
+    }
+
+    /// Records that we received a payment for the request `request_id`, which
+    /// is reissued by the operation `operation_id`, failing if we already
+    /// received another payment for it that didn't fail to reissue
+    async fn mark_ecash_payment_request_received(
+        &self,
+        request_id: OperationId,
+        operation_id: OperationId,
+    ) -> anyhow::Result<()> {
+        let mut dbtx = self.client_ctx.module_db().begin_transaction().await;
+
+        if let Some(previous) = dbtx
+            .insert_entry(&ReceivedEcashPaymentRequestKey(request_id), &operation_id)
+            .await
+        {
+            let previous_failed = self
+                .client_ctx
+                .get_operation(previous)
+                .await
+                .ok()
+                .and_then(|operation| operation.outcome::<ReissueExternalNotesState>())
+                .is_some_and(|state| matches!(state, ReissueExternalNotesState::Failed(_)));
+
+            ensure!(
+                previous_failed,
+                "The payment request was already received in operation {}",
+                previous.fmt_short()
+            );
+        }
+
+        dbtx.commit_tx_result().await?;
+
+        Ok(())
+    }
+}
+
+/// POSTs `payment` as JSON to `url`
+async fn deliver_payment(url: &SafeUrl, payment: &EcashPayment) -> anyhow::Result<()> {
+    let response = reqwest::Client::new()
+        .post(url.clone().to_unsafe())
+        .timeout(PAYMENT_DELIVERY_TIMEOUT)
+        .json(payment)
+        .send()
+        .await?;
+
+    ensure!(
+        response.status().is_success(),
+        "Payment request target responded with {}",
+        response.status()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use fedimint_core::config::FederationId;
+    use fedimint_core::core::OperationId;
+    use fedimint_core::encoding::{Decodable, Encodable};
+    use fedimint_core::module::registry::ModuleRegistry;
+    use fedimint_core::time::duration_since_epoch;
+    use fedimint_core::{Amount, base32};
+
+    use super::{ECASH_PAYMENT_REQUEST_PREFIX, EcashPaymentRequest, EcashPaymentTarget};
+
+    fn request(amount: Amount, expiry: u64) -> EcashPaymentRequest {
+        EcashPaymentRequest {
+            id: OperationId([0x42; 32]),
+            federation_id_prefix: FederationId::dummy().to_prefix(),
+            amount,
+            memo: "coffee".to_string(),
+            expiry,
+            target: EcashPaymentTarget::Http {
+                url: "https://example.com/pay".parse().unwrap(),
+            },
+        }
+    }
+
+    #[test]
+    fn payment_request_roundtrip() {
+        let request = request(Amount::from_sats(1000), 1_700_000_000);
+
+        let encoded = request.to_string();
+        assert!(encoded.starts_with(ECASH_PAYMENT_REQUEST_PREFIX));
+        assert_eq!(encoded.parse::<EcashPaymentRequest>().unwrap(), request);
+
+        // Whitespace, e.g. from line wrapping, is ignored
+        let (head, tail) = encoded.split_at(encoded.len() / 2);
+        assert_eq!(
+            format!(" {head}\n{tail} ")
+                .parse::<EcashPaymentRequest>()
+                .unwrap(),
+            request
+        );
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json, serde_json::Value::String(encoded));
+        assert_eq!(
+            serde_json::from_value::<EcashPaymentRequest>(json).unwrap(),
+            request
+        );
+    }
+
+    #[test]
+    fn payment_request_rejects_invalid_strings() {
+        let zero_amount = base32::encode_prefixed(
+            ECASH_PAYMENT_REQUEST_PREFIX,
+            &request(Amount::ZERO, 1_700_000_000),
+        );
+        assert!(zero_amount.parse::<EcashPaymentRequest>().is_err());
+
+        let wrong_prefix =
+            base32::encode_prefixed("fedimint", &request(Amount::from_sats(1000), 1_700_000_000));
+        assert!(wrong_prefix.parse::<EcashPaymentRequest>().is_err());
+
+        assert!(
+            "fedimintreqnotbase32!"
+                .parse::<EcashPaymentRequest>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn payment_request_expiry() {
+        let now = duration_since_epoch().as_secs();
+
+        assert!(request(Amount::from_sats(1), now - 1).is_expired());
+        assert!(request(Amount::from_sats(1), now).is_expired());
+        assert!(!request(Amount::from_sats(1), now + 60).is_expired());
+    }
+
+    #[test]
+    fn unknown_payment_target_decodes_as_default() {
+        let unknown = EcashPaymentTarget::Default {
+            variant: 42,
+            bytes: vec![1, 2, 3],
+        };
+
+        let decoded = EcashPaymentTarget::consensus_decode_whole(
+            &unknown.consensus_encode_to_vec(),
+            &ModuleRegistry::default(),
+        )
+        .unwrap();
+
+        assert_eq!(decoded, unknown);
+    }
+}
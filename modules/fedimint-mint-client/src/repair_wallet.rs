@@ -1,7 +1,8 @@
 use std::collections::BTreeMap;
 
 use fedimint_api_client::api::DynModuleApi;
-use fedimint_core::db::IDatabaseTransactionOpsCoreTyped;
+use fedimint_core::db::{DatabaseTransaction, IDatabaseTransactionOpsCoreTyped};
+use fedimint_core::module::AmountUnit;
 use fedimint_core::util::backoff_util::aggressive_backoff;
 use fedimint_core::util::retry;
 use fedimint_core::{Amount, TieredCounts};
@@ -9,7 +10,8 @@
 
 use crate::api::MintFederationApi;
 use crate::client_db::{
-    NextECashNoteIndexKey, NextECashNoteIndexKeyPrefix, NoteKey, NoteKeyPrefix,
+    NextECashNoteIndexKey, NextECashNoteIndexKeyPrefix, NextECashUnitNoteIndexKey,
+    NextECashUnitNoteIndexKeyPrefix, NoteKey, NoteKeyPrefix, UnitNoteKey, UnitNoteKeyPrefix,
 };
 use crate::output::NoteIssuanceRequest;
 use crate::{MintClientModule, NoteIndex};
@@ -28,13 +30,17 @@ pub struct RepairSummary {
     /// from-scratch recovery, otherwise we might not be aware of unspent notes
     /// issued to us.
     pub used_indices: TieredCounts,
+    /// Like [`Self::spent_notes`], but for the notes of non-bitcoin units
+    pub spent_unit_notes: BTreeMap<AmountUnit, TieredCounts>,
+    /// Like [`Self::used_indices`], but for the notes of non-bitcoin units
+    pub used_unit_indices: BTreeMap<AmountUnit, TieredCounts>,
 }
 
 impl MintClientModule {
     /// Attempts to fix inconsistent wallet states. **Breaks privacy guarantees
     /// and is destructive!**
     ///
-    /// Invalid states that are fixable this way:
+    /// Invalid states that are fixable this way, for notes of all units:
     ///   * Already-spent e-cash being in the wallet
     ///   * E-cash nonces that would be used to issue new notes already being
     ///     used
@@ -49,52 +55,14 @@ pub async fn try_repair_wallet(&self, gap_limit: u64) -> anyhow::Result<RepairSu
         let mut dbtx = self.client_ctx.module_db().begin_transaction().await;
 
         // First check if any of our notes are already spent and remove them
-        let spent_notes: Vec<NoteKey> = dbtx
-            .find_by_prefix_sorted_descending(&NoteKeyPrefix)
-            .await
-            .map(|(key, _)| {
-                let module_api_inner = module_api.clone();
-                async move {
-                    let spent = retry("fetch e-cash spentness", aggressive_backoff(), || async {
-                        Ok(module_api_inner.check_note_spent(key.nonce).await?)
-                    })
-                    .await?;
-                    anyhow::Ok(if spent { Some(key) } else { None })
-                }
-            })
-            .buffer_unordered(CHECK_PARALLELISM)
-            .try_filter_map(|result| async move { Ok(result) })
-            .try_collect()
+        self.remove_spent_notes(&mut dbtx.to_ref_nc(), &mut summary)
             .await?;
 
-        for note_key in spent_notes {
-            summary.spent_notes.inc(note_key.amount, 1);
-            dbtx.remove_entry(&note_key).await;
-        }
-
-        let next_indices: BTreeMap<_, _> = {
-            let mut db_next_indexes = dbtx
-                .find_by_prefix_sorted_descending(&NextECashNoteIndexKeyPrefix)
-                .await
-                .map(|(key, idx)| (key.0, idx))
-                .collect::<BTreeMap<_, _>>()
-                .await;
-
-            self.cfg
-                .tbs_pks
-                .tiers()
-                .map(|&denomination| {
-                    (
-                        denomination,
-                        db_next_indexes.remove(&denomination).unwrap_or_default(),
-                    )
-                })
-                .collect()
-        };
+        let next_indices = self.next_note_indices(&mut dbtx.to_ref_nc()).await;
 
         // Next check if any of the indices for issuing new notes are already used
         let used_nonces = stream::iter(next_indices.into_iter())
-            .map(|(amount, original_next_index)| {
+            .map(|((unit, amount), original_next_index)| {
                 let module_api_inner = module_api.clone();
                 async move {
                     let mut next_index = original_next_index;
@@ -102,6 +70,7 @@ pub async fn try_repair_wallet(&self, gap_limit: u64) -> anyhow::Result<RepairSu
                         let maybe_nonce_gap = self
                             .gap_till_next_nonce_used(
                                 &module_api_inner,
+                                unit,
                                 amount,
                                 next_index,
                                 gap_limit,
@@ -117,7 +86,7 @@ pub async fn try_repair_wallet(&self, gap_limit: u64) -> anyhow::Result<RepairSu
                         } else {
                             // If the initial nonce was used but we found an unused one by now,
                             // report the used index
-                            break Some((amount, next_index));
+                            break Some((unit, amount, next_index));
                         }
                     };
 
@@ -129,22 +98,132 @@ pub async fn try_repair_wallet(&self, gap_limit: u64) -> anyhow::Result<RepairSu
             .try_collect::<Vec<_>>()
             .await?;
 
-        for (amount, next_index) in used_nonces {
-            let old_index = dbtx
-                .insert_entry(&NextECashNoteIndexKey(amount), &next_index)
-                .await
-                .unwrap_or_default();
-            summary
-                .used_indices
-                .inc(amount, (next_index - old_index) as usize);
+        for (unit, amount, next_index) in used_nonces {
+            if unit.is_bitcoin() {
+                let old_index = dbtx
+                    .insert_entry(&NextECashNoteIndexKey(amount), &next_index)
+                    .await
+                    .unwrap_or_default();
+                summary
+                    .used_indices
+                    .inc(amount, (next_index - old_index) as usize);
+            } else {
+                let old_index = dbtx
+                    .insert_entry(&NextECashUnitNoteIndexKey(unit, amount), &next_index)
+                    .await
+                    .unwrap_or_default();
+                summary
+                    .used_unit_indices
+                    .entry(unit)
+                    .or_default()
+                    .inc(amount, (next_index - old_index) as usize);
+            }
         }
 
         dbtx.commit_tx().await;
         Ok(summary)
     }
 
-    /// Checks up to `gap_limit` nonces starting from `base_index` for having
-    /// being used already.
+    /// Removes the notes of all units the federation marked as spent from the
+    /// wallet
+    async fn remove_spent_notes(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        summary: &mut RepairSummary,
+    ) -> anyhow::Result<()> {
+        let note_keys: Vec<NoteKey> = dbtx
+            .find_by_prefix_sorted_descending(&NoteKeyPrefix)
+            .await
+            .map(|(key, _)| key)
+            .collect()
+            .await;
+
+        let unit_note_keys: Vec<UnitNoteKey> = dbtx
+            .find_by_prefix(&UnitNoteKeyPrefix)
+            .await
+            .map(|(key, _)| key)
+            .collect()
+            .await;
+
+        let nonces: Vec<_> = note_keys
+            .iter()
+            .map(|key| key.nonce)
+            .chain(unit_note_keys.iter().map(|key| key.nonce))
+            .collect();
+        let spent = retry("fetch e-cash spentness", aggressive_backoff(), || {
+            self.check_nonces_spent(&nonces)
+        })
+        .await?;
+        let (spent, unit_spent) = spent.split_at(note_keys.len());
+
+        let spent_notes = note_keys
+            .into_iter()
+            .zip(spent.iter().copied())
+            .filter_map(|(key, spent)| spent.then_some(key));
+
+        for note_key in spent_notes {
+            summary.spent_notes.inc(note_key.amount, 1);
+            dbtx.remove_entry(&note_key).await;
+        }
+
+        let spent_unit_notes = unit_note_keys
+            .into_iter()
+            .zip(unit_spent.iter().copied())
+            .filter_map(|(key, spent)| spent.then_some(key));
+
+        for note_key in spent_unit_notes {
+            summary
+                .spent_unit_notes
+                .entry(note_key.unit)
+                .or_default()
+                .inc(note_key.amount, 1);
+            dbtx.remove_entry(&note_key).await;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the index of the next note to issue for every unit and
+    /// denomination of the mint
+    async fn next_note_indices(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+    ) -> BTreeMap<(AmountUnit, Amount), u64> {
+        let mut db_next_indexes = dbtx
+            .find_by_prefix_sorted_descending(&NextECashNoteIndexKeyPrefix)
+            .await
+            .map(|(key, idx)| ((AmountUnit::BITCOIN, key.0), idx))
+            .collect::<BTreeMap<_, _>>()
+            .await;
+
+        db_next_indexes.extend(
+            dbtx.find_by_prefix(&NextECashUnitNoteIndexKeyPrefix)
+                .await
+                .map(|(key, idx)| ((key.0, key.1), idx))
+                .collect::<Vec<_>>()
+                .await,
+        );
+
+        let bitcoin_tiers = self
+            .cfg
+            .tbs_pks
+            .tiers()
+            .map(|&denomination| (AmountUnit::BITCOIN, denomination));
+
+        let unit_tiers = self.cfg.custom_units.iter().flat_map(|(unit, cfg)| {
+            cfg.tbs_pks
+                .tiers()
+                .map(|&denomination| (*unit, denomination))
+        });
+
+        bitcoin_tiers
+            .chain(unit_tiers)
+            .map(|tier| (tier, db_next_indexes.remove(&tier).unwrap_or_default()))
+            .collect()
+    }
+
+    /// Checks up to `gap_limit` nonces of `unit` starting from `base_index` for
+    /// having being used already.
     ///
     /// If the nonce at `base_index` is used, returns `Some(0)`, if it's unused
     /// returns `None`. If there's an unused nonce and then a used one returns
@@ -152,13 +231,18 @@ pub async fn try_repair_wallet(&self, gap_limit: u64) -> anyhow::Result<RepairSu
     async fn gap_till_next_nonce_used(
         &self,
         module_api: &DynModuleApi,
+        unit: AmountUnit,
         amount: Amount,
         base_index: u64,
         gap_limit: u64,
     ) -> anyhow::Result<Option<u64>> {
         for gap in 0..gap_limit {
             let idx = base_index + gap;
-            let note_secret = Self::new_note_secret_static(&self.secret, amount, NoteIndex(idx));
+            let note_secret = if unit.is_bitcoin() {
+                Self::new_note_secret_static(&self.secret, amount, NoteIndex(idx))
+            } else {
+                Self::new_unit_note_secret_static(&self.secret, unit, amount, NoteIndex(idx))
+            };
             let (_, blind_nonce) = NoteIssuanceRequest::new(&self.secp, &note_secret);
             let nonce_used = retry(
                 "checking if blind nonce was already used",
@@ -1,8 +1,10 @@
 use std::collections::BTreeMap;
+use std::io::Read;
 
 use fedimint_core::core::ModuleKind;
-use fedimint_core::encoding::{Decodable, Encodable};
-use fedimint_core::module::serde_json;
+use fedimint_core::encoding::{Decodable, DecodeError, Encodable};
+use fedimint_core::module::registry::ModuleDecoderRegistry;
+use fedimint_core::module::{AmountUnit, serde_json};
 use fedimint_core::{Amount, PeerId, Tiered, plugin_types_trait_impl_config};
 use serde::{Deserialize, Serialize};
 use tbs::{AggregatePublicKey, PublicKeyShare};
@@ -18,7 +20,7 @@ pub struct MintConfig {
 #[derive(Clone, Debug, Serialize, Deserialize, Decodable, Encodable)]
 pub struct MintConfigLocal;
 
-#[derive(Clone, Debug, Serialize, Deserialize, Encodable, Decodable)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MintConfigConsensus {
     /// The set of public keys for blind-signing all peers and note
     /// denominations
@@ -27,20 +29,190 @@ pub struct MintConfigConsensus {
     pub fee_consensus: FeeConsensus,
     /// The maximum amount of change a client can request
     pub max_notes_per_denomination: u16,
+    /// Key sets and fees of the units issued in addition to
+    /// [`AmountUnit::BITCOIN`], which is configured by the fields above
+    #[serde(default)]
+    pub custom_units: BTreeMap<AmountUnit, MintUnitConfigConsensus>,
+}
+
+/// Consensus configuration of an additional [`AmountUnit`] issued by the mint
+#[derive(Clone, Debug, Serialize, Deserialize, Encodable, Decodable)]
+pub struct MintUnitConfigConsensus {
+    /// The set of public keys for blind-signing all peers and note
+    /// denominations of this unit
+    pub peer_tbs_pks: BTreeMap<PeerId, Tiered<PublicKeyShare>>,
+    /// Fees charged for ecash transactions in this unit
+    pub fee_consensus: FeeConsensus,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MintConfigPrivate {
     /// Secret keys for blind-signing ecash of varying note denominations
     pub tbs_sks: Tiered<tbs::SecretKeyShare>,
+    /// Secret keys for blind-signing ecash of the additional units
+    #[serde(default)]
+    pub custom_unit_tbs_sks: BTreeMap<AmountUnit, Tiered<tbs::SecretKeyShare>>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Encodable, Decodable, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Hash)]
 pub struct MintClientConfig {
     pub tbs_pks: Tiered<AggregatePublicKey>,
     pub fee_consensus: FeeConsensus,
     pub peer_tbs_pks: BTreeMap<PeerId, Tiered<tbs::PublicKeyShare>>,
     pub max_notes_per_denomination: u16,
+    /// Keys and fees of the units issued in addition to
+    /// [`AmountUnit::BITCOIN`]
+    #[serde(default)]
+    pub custom_units: BTreeMap<AmountUnit, MintUnitClientConfig>,
+}
+
+/// Client configuration of an additional [`AmountUnit`] issued by the mint
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Encodable, Decodable, Hash)]
+pub struct MintUnitClientConfig {
+    pub tbs_pks: Tiered<AggregatePublicKey>,
+    pub fee_consensus: FeeConsensus,
+    pub peer_tbs_pks: BTreeMap<PeerId, Tiered<tbs::PublicKeyShare>>,
+}
+
+// Custom units were added after the consensus encoding of the configs was
+// fixed. To keep the encoding, and thus the consensus hash, of configs without
+// custom units unchanged they are only appended if present.
+impl Encodable for MintConfigConsensus {
+    fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> Result<(), std::io::Error> {
+        self.peer_tbs_pks.consensus_encode(writer)?;
+        self.fee_consensus.consensus_encode(writer)?;
+        self.max_notes_per_denomination.consensus_encode(writer)?;
+        encode_custom_units(&self.custom_units, writer)
+    }
+}
+
+impl Decodable for MintConfigConsensus {
+    fn consensus_decode_partial_from_finite_reader<R: Read>(
+        r: &mut R,
+        modules: &ModuleDecoderRegistry,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self {
+            peer_tbs_pks: Decodable::consensus_decode_partial_from_finite_reader(r, modules)?,
+            fee_consensus: Decodable::consensus_decode_partial_from_finite_reader(r, modules)?,
+            max_notes_per_denomination: Decodable::consensus_decode_partial_from_finite_reader(
+                r, modules,
+            )?,
+            custom_units: decode_custom_units(r, modules)?,
+        })
+    }
+}
+
+impl Encodable for MintClientConfig {
+    fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> Result<(), std::io::Error> {
+        self.tbs_pks.consensus_encode(writer)?;
+        self.fee_consensus.consensus_encode(writer)?;
+        self.peer_tbs_pks.consensus_encode(writer)?;
+        self.max_notes_per_denomination.consensus_encode(writer)?;
+        encode_custom_units(&self.custom_units, writer)
+    }
+}
+
+impl Decodable for MintClientConfig {
+    fn consensus_decode_partial_from_finite_reader<R: Read>(
+        r: &mut R,
+        modules: &ModuleDecoderRegistry,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self {
+            tbs_pks: Decodable::consensus_decode_partial_from_finite_reader(r, modules)?,
+            fee_consensus: Decodable::consensus_decode_partial_from_finite_reader(r, modules)?,
+            peer_tbs_pks: Decodable::consensus_decode_partial_from_finite_reader(r, modules)?,
+            max_notes_per_denomination: Decodable::consensus_decode_partial_from_finite_reader(
+                r, modules,
+            )?,
+            custom_units: decode_custom_units(r, modules)?,
+        })
+    }
+}
+
+fn encode_custom_units<W, V>(
+    custom_units: &BTreeMap<AmountUnit, V>,
+    writer: &mut W,
+) -> Result<(), std::io::Error>
+where
+    W: std::io::Write,
+    V: Encodable,
+{
+    if custom_units.is_empty() {
+        return Ok(());
+    }
+
+    custom_units.consensus_encode(writer)
+}
+
+/// Decodes the custom units if there is any data left, configs are always
+/// decoded from a reader limited to their exact length.
+fn decode_custom_units<R, V>(
+    r: &mut R,
+    modules: &ModuleDecoderRegistry,
+) -> Result<BTreeMap<AmountUnit, V>, DecodeError>
+where
+    R: Read,
+    V: Decodable,
+{
+    let mut first_byte = [0u8; 1];
+
+    if r.read(&mut first_byte).map_err(DecodeError::from_err)? == 0 {
+        return Ok(BTreeMap::new());
+    }
+
+    let custom_units = BTreeMap::consensus_decode_partial_from_finite_reader(
+        &mut first_byte.as_slice().chain(r),
+        modules,
+    )?;
+
+    if custom_units.is_empty() {
+        return Err(DecodeError::from_str(
+            "Empty custom units must not be encoded",
+        ));
+    }
+
+    Ok(custom_units)
+}
+
+impl MintClientConfig {
+    /// All units the mint issues e-cash in, starting with
+    /// [`AmountUnit::BITCOIN`]
+    pub fn units(&self) -> impl Iterator<Item = AmountUnit> + '_ {
+        std::iter::once(AmountUnit::BITCOIN).chain(self.custom_units.keys().copied())
+    }
+
+    /// Aggregate public keys of the denominations of `unit`, if the mint
+    /// issues e-cash in it
+    pub fn tbs_pks_for_unit(&self, unit: AmountUnit) -> Option<&Tiered<AggregatePublicKey>> {
+        if unit.is_bitcoin() {
+            Some(&self.tbs_pks)
+        } else {
+            self.custom_units.get(&unit).map(|cfg| &cfg.tbs_pks)
+        }
+    }
+
+    /// Public key shares of all peers for the denominations of `unit`, if the
+    /// mint issues e-cash in it
+    pub fn peer_tbs_pks_for_unit(
+        &self,
+        unit: AmountUnit,
+    ) -> Option<&BTreeMap<PeerId, Tiered<tbs::PublicKeyShare>>> {
+        if unit.is_bitcoin() {
+            Some(&self.peer_tbs_pks)
+        } else {
+            self.custom_units.get(&unit).map(|cfg| &cfg.peer_tbs_pks)
+        }
+    }
+
+    /// Fees charged for e-cash transactions in `unit`, if the mint issues
+    /// e-cash in it
+    pub fn fee_consensus_for_unit(&self, unit: AmountUnit) -> Option<&FeeConsensus> {
+        if unit.is_bitcoin() {
+            Some(&self.fee_consensus)
+        } else {
+            self.custom_units.get(&unit).map(|cfg| &cfg.fee_consensus)
+        }
+    }
 }
 
 impl std::fmt::Display for MintClientConfig {
@@ -98,6 +270,16 @@ pub fn zero() -> Self {
         }
     }
 
+    /// Fee charged per input and output regardless of its amount
+    pub fn base(&self) -> Amount {
+        self.base
+    }
+
+    /// Fee charged per input and output relative to its amount
+    pub fn parts_per_million(&self) -> u64 {
+        self.parts_per_million
+    }
+
     pub fn fee(&self, amount: Amount) -> Amount {
         Amount::from_msats(self.fee_msats(amount.msats))
     }
@@ -140,3 +322,59 @@ fn test_fee_consensus() {
         Amount::from_bitcoins(100) + Amount::from_msats(100)
     );
 }
+
+#[test]
+fn test_client_config_custom_units_encoding() {
+    let mut config = MintClientConfig {
+        tbs_pks: Tiered::from_iter([]),
+        fee_consensus: FeeConsensus::zero(),
+        peer_tbs_pks: BTreeMap::new(),
+        max_notes_per_denomination: 3,
+        custom_units: BTreeMap::new(),
+    };
+
+    // Configs without custom units keep the encoding they had before custom units
+    // were introduced
+    let mut legacy_encoding = Vec::new();
+    config
+        .tbs_pks
+        .consensus_encode(&mut legacy_encoding)
+        .unwrap();
+    config
+        .fee_consensus
+        .consensus_encode(&mut legacy_encoding)
+        .unwrap();
+    config
+        .peer_tbs_pks
+        .consensus_encode(&mut legacy_encoding)
+        .unwrap();
+    3u16.consensus_encode(&mut legacy_encoding).unwrap();
+
+    assert_eq!(config.consensus_encode_to_vec(), legacy_encoding);
+    assert_eq!(
+        MintClientConfig::consensus_decode_whole(
+            &legacy_encoding,
+            &ModuleDecoderRegistry::default()
+        )
+        .unwrap(),
+        config
+    );
+
+    config.custom_units.insert(
+        AmountUnit::new_custom(1),
+        MintUnitClientConfig {
+            tbs_pks: Tiered::from_iter([]),
+            fee_consensus: FeeConsensus::zero(),
+            peer_tbs_pks: BTreeMap::new(),
+        },
+    );
+
+    assert_eq!(
+        MintClientConfig::consensus_decode_whole(
+            &config.consensus_encode_to_vec(),
+            &ModuleDecoderRegistry::default()
+        )
+        .unwrap(),
+        config
+    );
+}
@@ -1,7 +1,13 @@
 pub const AWAIT_OUTPUT_OUTCOME_ENDPOINT: &str = "await_output_outcome";
 pub const NOTE_SPENT_ENDPOINT: &str = "note_spent";
+pub const NOTES_SPENT_ENDPOINT: &str = "notes_spent";
 pub const BLIND_NONCE_USED_ENDPOINT: &str = "blind_nonce_used";
 pub const RECOVERY_COUNT_ENDPOINT: &str = "recovery_count";
 pub const RECOVERY_SLICE_ENDPOINT: &str = "recovery_slice";
 pub const RECOVERY_SLICE_HASH_ENDPOINT: &str = "recovery_slice_hash";
 pub const RECOVERY_BLIND_NONCE_OUTPOINTS_ENDPOINT: &str = "recovery_blind_nonce_outpoints";
+pub const KEY_EPOCH_STATUS_ENDPOINT: &str = "key_epoch_status";
+pub const KEY_EPOCH_KEYS_ENDPOINT: &str = "key_epoch_keys";
+pub const ROTATE_KEY_EPOCH_ENDPOINT: &str = "rotate_key_epoch";
+pub const MODULE_CONSENSUS_VERSION_ENDPOINT: &str = "module_consensus_version";
+pub const SUPPORTED_MODULE_CONSENSUS_VERSION_ENDPOINT: &str = "supported_module_consensus_version";
@@ -6,20 +6,24 @@
 #![allow(clippy::must_use_candidate)]
 
 use core::fmt;
+use std::collections::BTreeMap;
 use std::hash::Hash;
 
 use bitcoin_hashes::Hash as _;
 use bitcoin_hashes::hex::DisplayHex;
+use bls12_381::G2Affine;
 pub use common::{BackupRequest, SignedBackupRequest};
 use config::MintClientConfig;
+use fedimint_core::bitcoin::hashes::sha256;
 use fedimint_core::core::{Decoder, ModuleInstanceId, ModuleKind};
 use fedimint_core::encoding::{Decodable, Encodable};
-use fedimint_core::module::{CommonModuleInit, ModuleCommon, ModuleConsensusVersion};
+use fedimint_core::module::{AmountUnit, CommonModuleInit, ModuleCommon, ModuleConsensusVersion};
 use fedimint_core::{
-    Amount, extensible_associated_module_type, plugin_types_trait_impl_common, secp256k1,
+    Amount, PeerId, Tiered, extensible_associated_module_type, plugin_types_trait_impl_common,
+    secp256k1,
 };
 use serde::{Deserialize, Serialize};
-use tbs::BlindedSignatureShare;
+use tbs::{AggregatePublicKey, BlindedSignatureShare, PublicKeyShare};
 use thiserror::Error;
 use tracing::error;
 
@@ -28,24 +32,97 @@
 pub mod endpoint_constants;
 
 pub const KIND: ModuleKind = ModuleKind::from_static_str("mint");
-pub const MODULE_CONSENSUS_VERSION: ModuleConsensusVersion = ModuleConsensusVersion::new(2, 0);
+pub const MODULE_CONSENSUS_VERSION: ModuleConsensusVersion = ModuleConsensusVersion::new(2, 3);
+
+/// Module consensus version from which on notes of
+/// [`config::MintConfigConsensus::custom_units`] are issued and redeemed
+pub const CUSTOM_UNITS_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion =
+    ModuleConsensusVersion::new(2, 1);
+
+/// Module consensus version from which on the guardians agree on the unix time
+/// and [`P2pkLock`]ed notes are redeemed
+pub const P2PK_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion = ModuleConsensusVersion::new(2, 2);
+
+/// Module consensus version from which on the guardians generate the keys of
+/// new key epochs and notes are issued and redeemed in key epochs other than
+/// zero
+pub const KEY_ROTATION_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion =
+    ModuleConsensusVersion::new(2, 3);
+
+/// Seconds the notes of a deprecated key epoch remain redeemable after the
+/// next key epoch was activated
+pub const KEY_EPOCH_DEPRECATION_WINDOW: u64 = 30 * 24 * 60 * 60;
 
 /// By default, the maximum notes per denomination when change-making for users
 pub const DEFAULT_MAX_NOTES_PER_DENOMINATION: u16 = 3;
 
-/// The mint module currently doesn't define any consensus items and generally
-/// throws an error on encountering one. To allow old clients to still decode
-/// blocks in the future, should we decide to add consensus items, this has to
-/// be an enum with only a default variant.
+/// Maximum number of nonces that can be checked with a single request to
+/// [`endpoint_constants::NOTES_SPENT_ENDPOINT`]
+pub const MAX_NOTES_SPENT_BATCH_SIZE: usize = 1_000;
+
+/// The mint module agrees on the current unix time, which is needed to
+/// enforce the timelocks of [`P2pkLock`]ed notes, on the module consensus
+/// version and on the keys of new key epochs, which the guardians generate by
+/// a DKG run through consensus.
+/// Unknown variants are captured by the default variant so old clients can
+/// still decode blocks containing consensus items added in the future.
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, Encodable, Decodable)]
 pub enum MintConsensusItem {
+    /// Unix time in seconds as observed by the proposing guardian
+    UnixTimeVote(u64),
+    /// Highest key epoch the proposing guardian wants to activate, see
+    /// [`KeyEpochStatus`]
+    KeyEpochVote(u64),
+    /// First round of the DKG generating the keys of a key epoch
+    KeyEpochDkgCommitment(KeyEpochDkgCommitment),
+    /// Second round of the DKG generating the keys of a key epoch
+    KeyEpochDkgDealing(KeyEpochDkgDealing),
+    /// Module consensus version the proposing guardian votes to activate
+    ModuleConsensusVersion(ModuleConsensusVersion),
+    /// Proof that the proposing guardian was dealt an invalid share in the DKG
+    /// of a key epoch, aborts the attempt
+    KeyEpochDkgComplaint(KeyEpochDkgComplaint),
+    /// The proposing guardian verified all shares dealt to it in the DKG of a
+    /// key epoch, which completes once every participant confirmed
+    KeyEpochDkgConfirmation(KeyEpochDkgAttemptId),
     #[encodable_default]
     Default { variant: u64, bytes: Vec<u8> },
 }
 
 impl std::fmt::Display for MintConsensusItem {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "MintConsensusItem")
+        match self {
+            MintConsensusItem::UnixTimeVote(time) => write!(f, "Mint Unix Time Vote {time}"),
+            MintConsensusItem::KeyEpochVote(key_epoch) => {
+                write!(f, "Mint Key Epoch Vote {key_epoch}")
+            }
+            MintConsensusItem::KeyEpochDkgCommitment(commitment) => {
+                write!(
+                    f,
+                    "Mint Key Epoch {} DKG Commitment",
+                    commitment.id.key_epoch
+                )
+            }
+            MintConsensusItem::KeyEpochDkgDealing(dealing) => write!(
+                f,
+                "Mint Key Epoch {} DKG Dealing for {}",
+                dealing.id.key_epoch, dealing.amount
+            ),
+            MintConsensusItem::ModuleConsensusVersion(version) => {
+                write!(f, "Mint Module Consensus Version Vote {version:?}")
+            }
+            MintConsensusItem::KeyEpochDkgComplaint(complaint) => write!(
+                f,
+                "Mint Key Epoch {} DKG Complaint against {}",
+                complaint.id.key_epoch, complaint.dealer
+            ),
+            MintConsensusItem::KeyEpochDkgConfirmation(id) => {
+                write!(f, "Mint Key Epoch {} DKG Confirmation", id.key_epoch)
+            }
+            MintConsensusItem::Default { variant, .. } => {
+                write!(f, "Unknown MintConsensusItem (variant={variant})")
+            }
+        }
     }
 }
 
@@ -110,6 +187,162 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     }
 }
 
+/// Spent status of a list of notes, packed into a bitmap
+///
+/// Bit `i % 8` of byte `i / 8` is set iff the `i`-th note was spent.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct NoteSpentBitmap {
+    len: u64,
+    bits: Vec<u8>,
+}
+
+impl NoteSpentBitmap {
+    pub fn from_spent(spent: impl IntoIterator<Item = bool>) -> Self {
+        let mut len = 0;
+        let mut bits = Vec::new();
+
+        for spent in spent {
+            if len % 8 == 0 {
+                bits.push(0);
+            }
+
+            if spent {
+                *bits.last_mut().expect("Pushed above") |= 1 << (len % 8);
+            }
+
+            len += 1;
+        }
+
+        Self { len, bits }
+    }
+
+    /// Number of notes whose spent status is contained in the bitmap
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns if the `idx`-th note was spent, `None` if the bitmap doesn't
+    /// contain it
+    pub fn is_spent(&self, idx: u64) -> Option<bool> {
+        if self.len <= idx {
+            return None;
+        }
+
+        let byte = self.bits.get(usize::try_from(idx / 8).ok()?)?;
+
+        Some(byte & (1 << (idx % 8)) != 0)
+    }
+
+    /// Iterates over the spent status of all notes in order, `None` marking
+    /// notes missing from a malformed bitmap
+    pub fn iter(&self) -> impl Iterator<Item = Option<bool>> + '_ {
+        (0..self.len).map(|idx| self.is_spent(idx))
+    }
+}
+
+/// Key epochs activated by the federation
+///
+/// Key epoch zero uses the keys the mint was set up with. Whenever a threshold
+/// of guardians votes for a new key epoch they generate its keys by a DKG and
+/// activate it, all new [`AmountUnit::BITCOIN`] notes are issued under its
+/// keys from then on. Notes of the previous key epoch can still be redeemed
+/// until [`KEY_EPOCH_DEPRECATION_WINDOW`] seconds after the activation.
+/// Afterwards the previous key epoch is retired, its spent nonces are kept so
+/// clients can still check the notes they hold. Notes of custom units are
+/// always issued in key epoch zero and never retired.
+#[derive(
+    Debug, Clone, Default, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable,
+)]
+pub struct KeyEpochStatus {
+    /// The key epoch new notes are issued in
+    pub active: u64,
+    /// Consensus unix time after which the notes of a deprecated key epoch
+    /// can't be redeemed anymore
+    pub retirements: BTreeMap<u64, u64>,
+}
+
+/// Public keys the guardians generated for a key epoch after zero
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct KeyEpochKeys {
+    pub tbs_pks: Tiered<AggregatePublicKey>,
+    pub peer_tbs_pks: BTreeMap<PeerId, Tiered<PublicKeyShare>>,
+}
+
+/// Identifies an attempt of the DKG of a key epoch. The guardians start a new
+/// attempt with fresh polynomials and encryption keys if a participant proves
+/// it was dealt an invalid share or the attempt does not complete in time.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Eq,
+    PartialEq,
+    Hash,
+    Ord,
+    PartialOrd,
+    Deserialize,
+    Serialize,
+    Encodable,
+    Decodable,
+)]
+pub struct KeyEpochDkgAttemptId {
+    pub key_epoch: u64,
+    pub attempt: u64,
+}
+
+/// A guardian commits to the polynomials it deals per denomination in the DKG
+/// of a key epoch and announces the key the shares dealt to it have to be
+/// encrypted to. The first threshold of guardians to commit take part in the
+/// DKG, late guardians can commit until the first dealing.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct KeyEpochDkgCommitment {
+    pub id: KeyEpochDkgAttemptId,
+    pub encryption_key: secp256k1::PublicKey,
+    /// Hash of the [`KeyEpochDkgDealing::commitment`] per denomination
+    pub hashes: Tiered<sha256::Hash>,
+}
+
+/// Once a threshold of guardians committed, each participant reveals the
+/// commitment to its polynomial for a denomination together with the shares it
+/// deals to the participants, each encrypted to the recipient's
+/// [`KeyEpochDkgCommitment::encryption_key`]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct KeyEpochDkgDealing {
+    pub id: KeyEpochDkgAttemptId,
+    pub amount: Amount,
+    pub commitment: Vec<CoefficientCommitment>,
+    pub encrypted_shares: BTreeMap<PeerId, [u8; 32]>,
+}
+
+/// A participant reveals the secret of its
+/// [`KeyEpochDkgCommitment::encryption_key`] so every guardian can decrypt the
+/// share `dealer` dealt to it for `amount` and verify that it does not match
+/// the dealer's commitment. The key is only used in this attempt of the DKG,
+/// which is aborted by the complaint.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct KeyEpochDkgComplaint {
+    pub id: KeyEpochDkgAttemptId,
+    pub dealer: PeerId,
+    pub amount: Amount,
+    pub encryption_secret_key: [u8; 32],
+}
+
+/// Commitment to a coefficient of a polynomial dealt in a DKG
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize, Serialize, Encodable, Decodable)]
+pub struct CoefficientCommitment(
+    #[serde(with = "fedimint_core::bls12_381_serde::g2")] pub G2Affine,
+);
+
+impl Hash for CoefficientCommitment {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.to_compressed().hash(state);
+    }
+}
+
 /// [`Nonce`] but blinded by the user key
 ///
 /// Blinding prevents the Mint from being able to link the transaction spending
@@ -144,12 +377,165 @@ fn decoder() -> Decoder {
     }
 }
 
-extensible_associated_module_type!(MintInput, MintInputV0, UnknownMintInputVariantError);
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub enum MintInput {
+    V0(MintInputV0),
+    /// Like [`MintInputV0`], but for notes of any [`AmountUnit`]
+    V1(MintInputV1),
+    /// Redeems a note locked to a public key, see [`P2pkLock`]
+    V2(MintInputV2),
+    /// Redeems a note issued under the keys of a later key epoch
+    V3(MintInputV3),
+    #[encodable_default]
+    Default {
+        variant: u64,
+        bytes: Vec<u8>,
+    },
+}
+
+#[derive(
+    Debug,
+    thiserror::Error,
+    Clone,
+    Eq,
+    PartialEq,
+    Hash,
+    serde::Deserialize,
+    serde::Serialize,
+    fedimint_core::encoding::Encodable,
+    fedimint_core::encoding::Decodable,
+)]
+#[error("Unknown MintInput variant {variant}")]
+pub struct UnknownMintInputVariantError {
+    pub variant: u64,
+}
+
+impl std::fmt::Display for MintInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MintInput::V0(inner) => std::fmt::Display::fmt(inner, f),
+            MintInput::V1(inner) => std::fmt::Display::fmt(inner, f),
+            MintInput::V2(inner) => std::fmt::Display::fmt(inner, f),
+            MintInput::V3(inner) => std::fmt::Display::fmt(inner, f),
+            MintInput::Default { variant, .. } => {
+                write!(f, "Unknown MintInput (variant={variant})")
+            }
+        }
+    }
+}
 
 impl MintInput {
     pub fn new_v0(amount: Amount, note: Note) -> MintInput {
         MintInput::V0(MintInputV0 { amount, note })
     }
+
+    pub fn new_v1(amount: Amount, unit: AmountUnit, note: Note) -> MintInput {
+        MintInput::V1(MintInputV1 { amount, unit, note })
+    }
+
+    pub fn new_v2(
+        amount: Amount,
+        unit: AmountUnit,
+        note: Note,
+        lock: P2pkLock,
+        spend_path: P2pkSpendPath,
+    ) -> MintInput {
+        MintInput::V2(MintInputV2 {
+            amount,
+            unit,
+            note,
+            lock,
+            spend_path,
+        })
+    }
+
+    /// Creates a [`MintInputV0`] for [`AmountUnit::BITCOIN`] notes to stay
+    /// compatible with older federations and a [`MintInputV1`] otherwise
+    pub fn new_for_unit(amount: Amount, unit: AmountUnit, note: Note) -> MintInput {
+        if unit.is_bitcoin() {
+            MintInput::new_v0(amount, note)
+        } else {
+            MintInput::new_v1(amount, unit, note)
+        }
+    }
+
+    /// Wraps `input` in a [`MintInputV3`] if its note was issued in a key
+    /// epoch other than zero
+    pub fn new_for_key_epoch(key_epoch: u64, input: KeyEpochInput) -> MintInput {
+        if key_epoch == 0 {
+            input.into()
+        } else {
+            MintInput::V3(MintInputV3 { key_epoch, input })
+        }
+    }
+
+    /// The key epoch the redeemed note was issued in
+    pub fn key_epoch(&self) -> u64 {
+        match self {
+            MintInput::V3(v3) => v3.key_epoch,
+            _ => 0,
+        }
+    }
+
+    /// The input redeeming a locked note, also if it is wrapped by a
+    /// [`MintInputV3`]
+    pub fn maybe_v2_ref(&self) -> Option<&MintInputV2> {
+        match self {
+            MintInput::V2(v2)
+            | MintInput::V3(MintInputV3 {
+                input: KeyEpochInput::V2(v2),
+                ..
+            }) => Some(v2),
+            _ => None,
+        }
+    }
+
+    pub fn maybe_v0_ref(&self) -> Option<&MintInputV0> {
+        match self {
+            MintInput::V0(v0) => Some(v0),
+            _ => None,
+        }
+    }
+
+    pub fn ensure_v0_ref(&self) -> Result<&MintInputV0, UnknownMintInputVariantError> {
+        match self {
+            MintInput::V0(v0) => Ok(v0),
+            MintInput::V1(_) => Err(UnknownMintInputVariantError { variant: 1 }),
+            MintInput::V2(_) => Err(UnknownMintInputVariantError { variant: 2 }),
+            MintInput::V3(_) => Err(UnknownMintInputVariantError { variant: 3 }),
+            MintInput::Default { variant, .. } => {
+                Err(UnknownMintInputVariantError { variant: *variant })
+            }
+        }
+    }
+
+    /// Returns the amount, unit and note of any known input version
+    ///
+    /// Note that the signature of a note redeemed by a [`MintInputV2`] has to
+    /// be verified against its [`P2pkLock`], see [`Note::verify_locked`].
+    pub fn ensure_known(
+        &self,
+    ) -> Result<(Amount, AmountUnit, &Note), UnknownMintInputVariantError> {
+        match self {
+            MintInput::V0(v0) => Ok((v0.amount, AmountUnit::BITCOIN, &v0.note)),
+            MintInput::V1(v1) => Ok((v1.amount, v1.unit, &v1.note)),
+            MintInput::V2(v2) => Ok((v2.amount, v2.unit, &v2.note)),
+            MintInput::V3(v3) => match &v3.input {
+                KeyEpochInput::V0(v0) => Ok((v0.amount, AmountUnit::BITCOIN, &v0.note)),
+                KeyEpochInput::V2(v2) => Ok((v2.amount, v2.unit, &v2.note)),
+                KeyEpochInput::Default { .. } => Err(UnknownMintInputVariantError { variant: 3 }),
+            },
+            MintInput::Default { variant, .. } => {
+                Err(UnknownMintInputVariantError { variant: *variant })
+            }
+        }
+    }
+}
+
+impl From<MintInputV0> for MintInput {
+    fn from(v: MintInputV0) -> Self {
+        Self::V0(v)
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
@@ -164,7 +550,201 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
-extensible_associated_module_type!(MintOutput, MintOutputV0, UnknownMintOutputVariantError);
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct MintInputV1 {
+    pub amount: Amount,
+    pub unit: AmountUnit,
+    pub note: Note,
+}
+
+impl std::fmt::Display for MintInputV1 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Mint Note {} ({:?})", self.amount, self.unit)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct MintInputV2 {
+    pub amount: Amount,
+    pub unit: AmountUnit,
+    pub note: Note,
+    /// The lock the mint signed together with the note's nonce
+    pub lock: P2pkLock,
+    /// Selects the key the transaction has to be signed with
+    pub spend_path: P2pkSpendPath,
+}
+
+impl std::fmt::Display for MintInputV2 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Locked Mint Note {} ({:?}, {:?})",
+            self.amount, self.unit, self.spend_path
+        )
+    }
+}
+
+/// Wraps an input redeeming a bitcoin note that was issued under the keys of
+/// `key_epoch`, the other input versions redeem notes of key epoch zero
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct MintInputV3 {
+    pub key_epoch: u64,
+    pub input: KeyEpochInput,
+}
+
+/// The input versions a [`MintInputV3`] can wrap, encoded like the
+/// corresponding [`MintInput`] versions. It can't wrap a [`MintInput`] itself,
+/// which would allow nesting inputs to any depth.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub enum KeyEpochInput {
+    #[encodable(index = 0)]
+    V0(MintInputV0),
+    #[encodable(index = 2)]
+    V2(MintInputV2),
+    #[encodable_default]
+    Default { variant: u64, bytes: Vec<u8> },
+}
+
+impl KeyEpochInput {
+    pub fn new_v0(amount: Amount, note: Note) -> KeyEpochInput {
+        KeyEpochInput::V0(MintInputV0 { amount, note })
+    }
+
+    pub fn new_v2(
+        amount: Amount,
+        unit: AmountUnit,
+        note: Note,
+        lock: P2pkLock,
+        spend_path: P2pkSpendPath,
+    ) -> KeyEpochInput {
+        KeyEpochInput::V2(MintInputV2 {
+            amount,
+            unit,
+            note,
+            lock,
+            spend_path,
+        })
+    }
+}
+
+impl From<KeyEpochInput> for MintInput {
+    fn from(input: KeyEpochInput) -> Self {
+        match input {
+            KeyEpochInput::V0(v0) => MintInput::V0(v0),
+            KeyEpochInput::V2(v2) => MintInput::V2(v2),
+            KeyEpochInput::Default { variant, bytes } => MintInput::Default { variant, bytes },
+        }
+    }
+}
+
+impl std::fmt::Display for KeyEpochInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyEpochInput::V0(inner) => std::fmt::Display::fmt(inner, f),
+            KeyEpochInput::V2(inner) => std::fmt::Display::fmt(inner, f),
+            KeyEpochInput::Default { variant, .. } => {
+                write!(f, "Unknown MintInput (variant={variant})")
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for MintInputV3 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (key epoch {})", self.input, self.key_epoch)
+    }
+}
+
+/// Locks a [`Note`] to a public key whose signature is required to redeem it
+///
+/// Instead of the bare [`Nonce`] the mint signs [`P2pkLock::message`], which
+/// commits to the lock, so the note can only be redeemed through a
+/// [`MintInputV2`] carrying the very same lock. Since the message is blinded
+/// when the note is issued, locked notes are requested with regular
+/// [`MintOutput`]s and the mint only learns about the lock on redemption.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct P2pkLock {
+    /// Key that can redeem the note at any time
+    pub recipient: secp256k1::PublicKey,
+    /// Allows the sender to reclaim notes the recipient never redeemed
+    pub refund: Option<P2pkRefund>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct P2pkRefund {
+    /// Key that can redeem the note once the timelock expired
+    pub key: secp256k1::PublicKey,
+    /// Unix timestamp in seconds after which `key` can redeem the note
+    pub after: u64,
+}
+
+/// The key of a [`P2pkLock`] a [`MintInputV2`] is spent with
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub enum P2pkSpendPath {
+    Recipient,
+    Refund,
+}
+
+impl P2pkLock {
+    /// The message the mint signs for a note with the given nonce locked by
+    /// `self`
+    pub fn message(&self, nonce: Nonce) -> tbs::Message {
+        tbs::Message::from_bytes(&(nonce, *self).consensus_encode_to_vec())
+    }
+
+    /// The key that has to sign a redemption via `spend_path`, if the lock
+    /// has such a path
+    pub fn spend_key(&self, spend_path: P2pkSpendPath) -> Option<secp256k1::PublicKey> {
+        match spend_path {
+            P2pkSpendPath::Recipient => Some(self.recipient),
+            P2pkSpendPath::Refund => self.refund.map(|refund| refund.key),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub enum MintOutput {
+    V0(MintOutputV0),
+    /// Like [`MintOutputV0`], but for notes of any [`AmountUnit`]
+    V1(MintOutputV1),
+    /// Like [`MintOutputV0`], but issued under the keys of a later key epoch
+    V2(MintOutputV2),
+    #[encodable_default]
+    Default {
+        variant: u64,
+        bytes: Vec<u8>,
+    },
+}
+
+#[derive(
+    Debug,
+    thiserror::Error,
+    Clone,
+    Eq,
+    PartialEq,
+    Hash,
+    serde::Deserialize,
+    serde::Serialize,
+    fedimint_core::encoding::Encodable,
+    fedimint_core::encoding::Decodable,
+)]
+#[error("Unknown MintOutput variant {variant}")]
+pub struct UnknownMintOutputVariantError {
+    pub variant: u64,
+}
+
+impl std::fmt::Display for MintOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MintOutput::V0(inner) => std::fmt::Display::fmt(inner, f),
+            MintOutput::V1(inner) => std::fmt::Display::fmt(inner, f),
+            MintOutput::V2(inner) => std::fmt::Display::fmt(inner, f),
+            MintOutput::Default { variant, .. } => {
+                write!(f, "Unknown MintOutput (variant={variant})")
+            }
+        }
+    }
+}
 
 impl MintOutput {
     pub fn new_v0(amount: Amount, blind_nonce: BlindNonce) -> MintOutput {
@@ -173,6 +753,88 @@ pub fn new_v0(amount: Amount, blind_nonce: BlindNonce) -> MintOutput {
             blind_nonce,
         })
     }
+
+    pub fn new_v1(amount: Amount, unit: AmountUnit, blind_nonce: BlindNonce) -> MintOutput {
+        MintOutput::V1(MintOutputV1 {
+            amount,
+            unit,
+            blind_nonce,
+        })
+    }
+
+    /// Creates a [`MintOutputV0`] for [`AmountUnit::BITCOIN`] notes to stay
+    /// compatible with older federations and a [`MintOutputV1`] otherwise
+    pub fn new_for_unit(amount: Amount, unit: AmountUnit, blind_nonce: BlindNonce) -> MintOutput {
+        if unit.is_bitcoin() {
+            MintOutput::new_v0(amount, blind_nonce)
+        } else {
+            MintOutput::new_v1(amount, unit, blind_nonce)
+        }
+    }
+
+    /// Creates a [`MintOutputV0`] for key epoch zero to stay compatible with
+    /// older federations and a [`MintOutputV2`] otherwise
+    pub fn new_for_key_epoch(
+        amount: Amount,
+        key_epoch: u64,
+        blind_nonce: BlindNonce,
+    ) -> MintOutput {
+        if key_epoch == 0 {
+            MintOutput::new_v0(amount, blind_nonce)
+        } else {
+            MintOutput::V2(MintOutputV2 {
+                amount,
+                key_epoch,
+                blind_nonce,
+            })
+        }
+    }
+
+    /// The key epoch the note is issued in
+    pub fn key_epoch(&self) -> u64 {
+        match self {
+            MintOutput::V2(v2) => v2.key_epoch,
+            _ => 0,
+        }
+    }
+
+    pub fn maybe_v0_ref(&self) -> Option<&MintOutputV0> {
+        match self {
+            MintOutput::V0(v0) => Some(v0),
+            _ => None,
+        }
+    }
+
+    pub fn ensure_v0_ref(&self) -> Result<&MintOutputV0, UnknownMintOutputVariantError> {
+        match self {
+            MintOutput::V0(v0) => Ok(v0),
+            MintOutput::V1(_) => Err(UnknownMintOutputVariantError { variant: 1 }),
+            MintOutput::V2(_) => Err(UnknownMintOutputVariantError { variant: 2 }),
+            MintOutput::Default { variant, .. } => {
+                Err(UnknownMintOutputVariantError { variant: *variant })
+            }
+        }
+    }
+
+    /// Returns the amount, unit and blind nonce of any known output version
+    pub fn ensure_known(
+        &self,
+    ) -> Result<(Amount, AmountUnit, BlindNonce), UnknownMintOutputVariantError> {
+        match self {
+            MintOutput::V0(v0) => Ok((v0.amount, AmountUnit::BITCOIN, v0.blind_nonce)),
+            MintOutput::V1(v1) => Ok((v1.amount, v1.unit, v1.blind_nonce)),
+            MintOutput::V2(v2) => Ok((v2.amount, AmountUnit::BITCOIN, v2.blind_nonce)),
+            MintOutput::Default { variant, .. } => {
+                Err(UnknownMintOutputVariantError { variant: *variant })
+            }
+        }
+    }
+}
+
+impl From<MintOutputV0> for MintOutput {
+    fn from(v: MintOutputV0) -> Self {
+        Self::V0(v)
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
@@ -187,6 +849,36 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct MintOutputV1 {
+    pub amount: Amount,
+    pub unit: AmountUnit,
+    pub blind_nonce: BlindNonce,
+}
+
+impl std::fmt::Display for MintOutputV1 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Mint Note {} ({:?})", self.amount, self.unit)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct MintOutputV2 {
+    pub amount: Amount,
+    pub key_epoch: u64,
+    pub blind_nonce: BlindNonce,
+}
+
+impl std::fmt::Display for MintOutputV2 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Mint Note {} (key epoch {})",
+            self.amount, self.key_epoch
+        )
+    }
+}
+
 extensible_associated_module_type!(
     MintOutputOutcome,
     MintOutputOutcomeV0,
@@ -216,6 +908,11 @@ pub fn verify(&self, pk: tbs::AggregatePublicKey) -> bool {
         tbs::verify(self.nonce.to_message(), self.signature, pk)
     }
 
+    /// Verify the validity of a note locked by `lock` under a mint key `pk`
+    pub fn verify_locked(&self, lock: &P2pkLock, pk: tbs::AggregatePublicKey) -> bool {
+        tbs::verify(lock.message(self.nonce), self.signature, pk)
+    }
+
     /// Access the nonce as the public key to the spend key
     pub fn spend_key(&self) -> &secp256k1::PublicKey {
         &self.nonce.0
@@ -263,6 +960,22 @@ pub enum MintInputError {
     InvalidSignature,
     #[error("The mint input version is not supported by this federation")]
     UnknownInputVariant(#[from] UnknownMintInputVariantError),
+    #[error("The note is of a unit not issued by the mint: {0:?}")]
+    InvalidUnit(AmountUnit),
+    #[error("The locked note has no refund key")]
+    NoRefundKey,
+    #[error("The locked note can't be refunded before {0}")]
+    RefundTimelocked(u64),
+    #[error("The note was issued in an unknown key epoch: {0}")]
+    InvalidKeyEpoch(u64),
+    #[error("The key epoch {0} of the note was retired")]
+    RetiredKeyEpoch(u64),
+    #[error("The federation does not support custom units yet")]
+    CustomUnitsNotSupported,
+    #[error("The federation does not support locked notes yet")]
+    P2pkNotSupported,
+    #[error("The federation does not support key rotation yet")]
+    KeyRotationNotSupported,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Error, Encodable, Decodable)]
@@ -273,4 +986,74 @@ pub enum MintOutputError {
     UnknownOutputVariant(#[from] UnknownMintOutputVariantError),
     #[error("The mint output blind nonce was already used before")]
     BlindNonceAlreadyUsed,
+    #[error("The note is of a unit not issued by the mint: {0:?}")]
+    InvalidUnit(AmountUnit),
+    #[error("Notes can't be issued in key epoch {0}, it is not the active one")]
+    InactiveKeyEpoch(u64),
+    #[error("The federation does not support custom units yet")]
+    CustomUnitsNotSupported,
+    #[error("The federation does not support key rotation yet")]
+    KeyRotationNotSupported,
+}
+
+#[test]
+fn test_note_spent_bitmap() {
+    let spent = [
+        true, false, false, true, false, false, false, false, true, true,
+    ];
+    let bitmap = NoteSpentBitmap::from_spent(spent);
+
+    assert_eq!(bitmap.len(), 10);
+    assert_eq!(
+        bitmap.iter().collect::<Option<Vec<_>>>(),
+        Some(spent.to_vec())
+    );
+    assert_eq!(bitmap.is_spent(10), None);
+
+    assert!(NoteSpentBitmap::from_spent([]).is_empty());
+}
+
+#[test]
+fn test_key_epoch_input_does_not_nest() {
+    use fedimint_core::module::registry::ModuleRegistry;
+
+    let note = Note {
+        nonce: Nonce(
+            secp256k1::SecretKey::from_slice(&[1; 32])
+                .expect("Valid secret key")
+                .public_key(secp256k1::SECP256K1),
+        ),
+        signature: tbs::Signature(bls12_381::G1Affine::generator()),
+    };
+    let amount = Amount::from_msats(1);
+
+    // The wrapped input is encoded like the input version it wraps
+    assert_eq!(
+        KeyEpochInput::new_v0(amount, note).consensus_encode_to_vec(),
+        MintInput::new_v0(amount, note).consensus_encode_to_vec()
+    );
+
+    // A key epoch input wrapping another one decodes to an unknown inner input
+    let inner = MintInput::new_for_key_epoch(1, KeyEpochInput::new_v0(amount, note));
+    let MintInput::V3(inner_v3) = &inner else {
+        panic!("Input of key epoch one is wrapped");
+    };
+    let nested = MintInput::V3(MintInputV3 {
+        key_epoch: 2,
+        input: KeyEpochInput::Default {
+            variant: 3,
+            bytes: inner_v3.consensus_encode_to_vec(),
+        },
+    });
+    let decoded = MintInput::consensus_decode_whole(
+        &nested.consensus_encode_to_vec(),
+        &ModuleRegistry::default(),
+    )
+    .expect("Nested input decodes");
+
+    assert_eq!(decoded, nested);
+    assert_eq!(
+        decoded.ensure_known().unwrap_err(),
+        UnknownMintInputVariantError { variant: 3 }
+    );
 }
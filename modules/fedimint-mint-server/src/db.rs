@@ -1,6 +1,10 @@
 use fedimint_core::encoding::{Decodable, Encodable};
-use fedimint_core::{Amount, OutPoint, impl_db_lookup, impl_db_record};
-use fedimint_mint_common::{BlindNonce, MintOutputOutcome, Nonce, RecoveryItem};
+use fedimint_core::module::{AmountUnit, ModuleConsensusVersion};
+use fedimint_core::{Amount, OutPoint, PeerId, impl_db_lookup, impl_db_record};
+use fedimint_mint_common::{
+    BlindNonce, KeyEpochDkgCommitment, KeyEpochDkgDealing, KeyEpochKeys, MintOutputOutcome, Nonce,
+    RecoveryItem,
+};
 use serde::Serialize;
 use strum_macros::EnumIter;
 
@@ -14,6 +18,21 @@ pub enum DbKeyPrefix {
     BlindNonce = 0x16,
     RecoveryItem = 0x17,
     RecoveryBlindNonceOutpoint = 0x18,
+    UnixTimeVote = 0x19,
+    KeyEpochVote = 0x1a,
+    KeyEpochActivation = 0x1b,
+    KeyEpochNonce = 0x1c,
+    RetiredKeyEpoch = 0x1d,
+    ConsensusVersionVote = 0x1e,
+    CustomUnitNonce = 0x1f,
+    TargetKeyEpoch = 0x20,
+    KeyEpochDkgAttempt = 0x21,
+    KeyEpochDkgCommitment = 0x22,
+    KeyEpochDkgDealing = 0x23,
+    KeyEpochKeys = 0x24,
+    KeyEpochDkgConfirmation = 0x25,
+    KeyEpochDkgExclusion = 0x26,
+    KeyEpochDkgDealer = 0x27,
 }
 
 impl std::fmt::Display for DbKeyPrefix {
@@ -22,7 +41,8 @@ fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
     }
 }
 
-/// Index for all the spent e-cash note nonces to prevent double spends.
+/// Index for all the spent e-cash note nonces to prevent double spends, pruned
+/// once key epoch zero is retired.
 /// **Extremely safety critical!**
 #[derive(Debug, Clone, Encodable, Decodable, Eq, PartialEq, Hash, Serialize)]
 pub struct NonceKey(pub Nonce);
@@ -71,12 +91,52 @@ fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
 
 /// Represents the amounts of issued (signed) and redeemed (verified) notes for
 /// auditing
+///
+/// The `Unit*` variants track notes of non-bitcoin units, which are audited
+/// separately from the bitcoin liabilities of the federation. Notes of key
+/// epochs after zero are redeemed under
+/// [`MintAuditItemKey::KeyEpochRedemption`] since the same nonce may be
+/// redeemed once in every key epoch.
 #[derive(Debug, Clone, Encodable, Decodable, Serialize)]
 pub enum MintAuditItemKey {
     Issuance(OutPoint),
     IssuanceTotal,
     Redemption(NonceKey),
     RedemptionTotal,
+    UnitIssuance(AmountUnit, OutPoint),
+    UnitIssuanceTotal(AmountUnit),
+    UnitRedemption(AmountUnit, NonceKey),
+    UnitRedemptionTotal(AmountUnit),
+    KeyEpochRedemption(KeyEpochNonceKey),
+}
+
+impl MintAuditItemKey {
+    /// The unit of the notes this item accounts for
+    pub fn unit(&self) -> AmountUnit {
+        match self {
+            MintAuditItemKey::Issuance(_)
+            | MintAuditItemKey::IssuanceTotal
+            | MintAuditItemKey::Redemption(_)
+            | MintAuditItemKey::RedemptionTotal
+            | MintAuditItemKey::KeyEpochRedemption(_) => AmountUnit::BITCOIN,
+            MintAuditItemKey::UnitIssuance(unit, _)
+            | MintAuditItemKey::UnitIssuanceTotal(unit)
+            | MintAuditItemKey::UnitRedemption(unit, _)
+            | MintAuditItemKey::UnitRedemptionTotal(unit) => *unit,
+        }
+    }
+
+    /// Whether this item accounts for issued notes, i.e. a liability of the
+    /// federation
+    pub fn is_issuance(&self) -> bool {
+        matches!(
+            self,
+            MintAuditItemKey::Issuance(_)
+                | MintAuditItemKey::IssuanceTotal
+                | MintAuditItemKey::UnitIssuance(..)
+                | MintAuditItemKey::UnitIssuanceTotal(_)
+        )
+    }
 }
 
 #[derive(Debug, Encodable, Decodable)]
@@ -121,3 +181,301 @@ pub enum MintAuditItemKey {
     key = RecoveryBlindNonceOutpointKey,
     query_prefix = RecoveryBlindNonceOutpointKeyPrefix
 );
+
+/// Latest unix time vote of a guardian, used to enforce the timelocks of
+/// locked notes
+#[derive(Debug, Encodable, Decodable, Serialize)]
+pub struct UnixTimeVoteKey(pub PeerId);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct UnixTimeVotePrefix;
+
+impl_db_record!(
+    key = UnixTimeVoteKey,
+    value = u64,
+    db_prefix = DbKeyPrefix::UnixTimeVote,
+);
+impl_db_lookup!(key = UnixTimeVoteKey, query_prefix = UnixTimeVotePrefix);
+
+/// Highest key epoch a guardian voted to activate
+#[derive(Debug, Encodable, Decodable, Serialize)]
+pub struct KeyEpochVoteKey(pub PeerId);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeyEpochVotePrefix;
+
+impl_db_record!(
+    key = KeyEpochVoteKey,
+    value = u64,
+    db_prefix = DbKeyPrefix::KeyEpochVote,
+);
+impl_db_lookup!(key = KeyEpochVoteKey, query_prefix = KeyEpochVotePrefix);
+
+/// Consensus unix time a key epoch was activated at, the highest activated key
+/// epoch is the one new notes are issued in
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct KeyEpochActivationKey(pub u64);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeyEpochActivationPrefix;
+
+impl_db_record!(
+    key = KeyEpochActivationKey,
+    value = u64,
+    db_prefix = DbKeyPrefix::KeyEpochActivation,
+);
+impl_db_lookup!(
+    key = KeyEpochActivationKey,
+    query_prefix = KeyEpochActivationPrefix
+);
+
+/// Index for the spent nonces of notes issued in key epochs after zero, the
+/// spent nonces of key epoch zero are indexed by [`NonceKey`]. The nonces of a
+/// key epoch are pruned once it is retired.
+/// **Extremely safety critical!**
+#[derive(Debug, Clone, Encodable, Decodable, Eq, PartialEq, Hash, Serialize)]
+pub struct KeyEpochNonceKey {
+    pub key_epoch: u64,
+    pub nonce: Nonce,
+}
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeyEpochNonceKeyPrefix;
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeyEpochNonceKeyEpochPrefix(pub u64);
+
+impl_db_record!(
+    key = KeyEpochNonceKey,
+    value = (),
+    db_prefix = DbKeyPrefix::KeyEpochNonce,
+);
+impl_db_lookup!(
+    key = KeyEpochNonceKey,
+    query_prefix = KeyEpochNonceKeyPrefix,
+    query_prefix = KeyEpochNonceKeyEpochPrefix
+);
+
+/// Key epochs whose notes can't be redeemed anymore
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct RetiredKeyEpochKey(pub u64);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct RetiredKeyEpochPrefix;
+
+impl_db_record!(
+    key = RetiredKeyEpochKey,
+    value = (),
+    db_prefix = DbKeyPrefix::RetiredKeyEpoch,
+);
+impl_db_lookup!(
+    key = RetiredKeyEpochKey,
+    query_prefix = RetiredKeyEpochPrefix
+);
+
+/// Module consensus version each guardian voted to activate, see
+/// [`crate::Mint::consensus_module_consensus_version`]
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct ConsensusVersionVoteKey(pub PeerId);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct ConsensusVersionVotePrefix;
+
+impl_db_record!(
+    key = ConsensusVersionVoteKey,
+    value = ModuleConsensusVersion,
+    db_prefix = DbKeyPrefix::ConsensusVersionVote,
+);
+impl_db_lookup!(
+    key = ConsensusVersionVoteKey,
+    query_prefix = ConsensusVersionVotePrefix
+);
+
+/// Index for the spent nonces of notes of custom units, which are kept apart
+/// from [`NonceKey`] as custom units are not affected by key rotation.
+/// **Extremely safety critical!**
+#[derive(Debug, Clone, Encodable, Decodable, Eq, PartialEq, Hash, Serialize)]
+pub struct CustomUnitNonceKey(pub Nonce);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct CustomUnitNonceKeyPrefix;
+
+impl_db_record!(
+    key = CustomUnitNonceKey,
+    value = (),
+    db_prefix = DbKeyPrefix::CustomUnitNonce,
+);
+impl_db_lookup!(
+    key = CustomUnitNonceKey,
+    query_prefix = CustomUnitNonceKeyPrefix
+);
+
+/// Key epoch the guardian operator asked this guardian to vote for, see
+/// [`crate::common::endpoint_constants::ROTATE_KEY_EPOCH_ENDPOINT`]. Not part
+/// of the consensus state.
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct TargetKeyEpochKey;
+
+impl_db_record!(
+    key = TargetKeyEpochKey,
+    value = u64,
+    db_prefix = DbKeyPrefix::TargetKeyEpoch,
+);
+
+/// Current attempt of the DKG of a key epoch, kept after the activation as the
+/// guardian's key shares are derived from the dealings of the final attempt
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct KeyEpochDkgAttemptKey(pub u64);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeyEpochDkgAttemptPrefix;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Encodable, Decodable, Serialize)]
+pub struct KeyEpochDkgAttempt {
+    pub attempt: u64,
+    /// Consensus unix time of the first commitment in the attempt
+    pub started: Option<u64>,
+}
+
+impl_db_record!(
+    key = KeyEpochDkgAttemptKey,
+    value = KeyEpochDkgAttempt,
+    db_prefix = DbKeyPrefix::KeyEpochDkgAttempt,
+);
+impl_db_lookup!(
+    key = KeyEpochDkgAttemptKey,
+    query_prefix = KeyEpochDkgAttemptPrefix
+);
+
+/// First round message of a guardian in the DKG of a key epoch
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct KeyEpochDkgCommitmentKey {
+    pub key_epoch: u64,
+    pub peer: PeerId,
+}
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeyEpochDkgCommitmentPrefix;
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeyEpochDkgCommitmentKeyEpochPrefix(pub u64);
+
+impl_db_record!(
+    key = KeyEpochDkgCommitmentKey,
+    value = KeyEpochDkgCommitment,
+    db_prefix = DbKeyPrefix::KeyEpochDkgCommitment,
+);
+impl_db_lookup!(
+    key = KeyEpochDkgCommitmentKey,
+    query_prefix = KeyEpochDkgCommitmentPrefix,
+    query_prefix = KeyEpochDkgCommitmentKeyEpochPrefix
+);
+
+/// Second round message of a guardian for one denomination in the DKG of a key
+/// epoch
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct KeyEpochDkgDealingKey {
+    pub key_epoch: u64,
+    pub peer: PeerId,
+    pub amount: Amount,
+}
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeyEpochDkgDealingPrefix;
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeyEpochDkgDealingKeyEpochPrefix(pub u64);
+
+impl_db_record!(
+    key = KeyEpochDkgDealingKey,
+    value = KeyEpochDkgDealing,
+    db_prefix = DbKeyPrefix::KeyEpochDkgDealing,
+);
+impl_db_lookup!(
+    key = KeyEpochDkgDealingKey,
+    query_prefix = KeyEpochDkgDealingPrefix,
+    query_prefix = KeyEpochDkgDealingKeyEpochPrefix
+);
+
+/// Public keys generated for a key epoch after zero
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct KeyEpochKeysKey(pub u64);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeyEpochKeysPrefix;
+
+impl_db_record!(
+    key = KeyEpochKeysKey,
+    value = KeyEpochKeys,
+    db_prefix = DbKeyPrefix::KeyEpochKeys,
+);
+impl_db_lookup!(key = KeyEpochKeysKey, query_prefix = KeyEpochKeysPrefix);
+
+/// Participants of the current attempt of the DKG of a key epoch that verified
+/// the shares dealt to them
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct KeyEpochDkgConfirmationKey {
+    pub key_epoch: u64,
+    pub peer: PeerId,
+}
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeyEpochDkgConfirmationPrefix;
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeyEpochDkgConfirmationKeyEpochPrefix(pub u64);
+
+impl_db_record!(
+    key = KeyEpochDkgConfirmationKey,
+    value = (),
+    db_prefix = DbKeyPrefix::KeyEpochDkgConfirmation,
+);
+impl_db_lookup!(
+    key = KeyEpochDkgConfirmationKey,
+    query_prefix = KeyEpochDkgConfirmationPrefix,
+    query_prefix = KeyEpochDkgConfirmationKeyEpochPrefix
+);
+
+/// Guardians excluded from the remaining attempts of the DKG of a key epoch
+/// since they dealt an invalid share or stalled an attempt
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct KeyEpochDkgExclusionKey {
+    pub key_epoch: u64,
+    pub peer: PeerId,
+}
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeyEpochDkgExclusionPrefix;
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeyEpochDkgExclusionKeyEpochPrefix(pub u64);
+
+impl_db_record!(
+    key = KeyEpochDkgExclusionKey,
+    value = (),
+    db_prefix = DbKeyPrefix::KeyEpochDkgExclusion,
+);
+impl_db_lookup!(
+    key = KeyEpochDkgExclusionKey,
+    query_prefix = KeyEpochDkgExclusionPrefix,
+    query_prefix = KeyEpochDkgExclusionKeyEpochPrefix
+);
+
+/// Our polynomials and encryption key of the current attempt of the DKG of a
+/// key epoch, encrypted with a key derived from our private config. Kept after
+/// the activation as we decrypt our key shares with the encryption key.
+#[derive(Debug, Clone, Copy, Encodable, Decodable, Serialize)]
+pub struct KeyEpochDkgDealerKey(pub u64);
+
+#[derive(Debug, Encodable, Decodable)]
+pub struct KeyEpochDkgDealerPrefix;
+
+impl_db_record!(
+    key = KeyEpochDkgDealerKey,
+    value = Vec<u8>,
+    db_prefix = DbKeyPrefix::KeyEpochDkgDealer,
+);
+impl_db_lookup!(
+    key = KeyEpochDkgDealerKey,
+    query_prefix = KeyEpochDkgDealerPrefix
+);
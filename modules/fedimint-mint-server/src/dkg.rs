@@ -0,0 +1,396 @@
+//! Contribution of a guardian to the DKG generating the keys of a key epoch,
+//! see [`KeyEpochDkgCommitment`] and [`KeyEpochDkgDealing`]
+//!
+//! Every guardian that was not excluded from the DKG deals a random polynomial
+//! per denomination to all others. The key epoch's secret key is the sum of the
+//! polynomials' constant terms, so it is unknown to all guardians as long as a
+//! single participant is honest. The shares are sent through consensus,
+//! encrypted with a pad derived from the ECDH secret of the dealer's and the
+//! recipient's encryption keys.
+//!
+//! The polynomials and encryption key of every attempt are generated with
+//! fresh randomness. The guardian persists them in its database, encrypted
+//! with a key derived from its private config, before it commits to them, so
+//! it deals the same polynomials and can decrypt its key shares after a
+//! restart.
+
+use std::collections::BTreeMap;
+
+use fedimint_aead::LessSafeKey;
+use fedimint_core::encoding::{Decodable, Encodable};
+use fedimint_core::module::registry::ModuleRegistry;
+use fedimint_core::secp256k1::{Keypair, PublicKey, SECP256K1, SecretKey, ecdh};
+use fedimint_core::{Amount, PeerId, Tiered};
+use fedimint_derive_secret::{ChildId, DerivableSecret};
+use fedimint_mint_common::{
+    CoefficientCommitment, KeyEpochDkgAttemptId, KeyEpochDkgCommitment, KeyEpochDkgComplaint,
+    KeyEpochDkgDealing, KeyEpochKeys,
+};
+use fedimint_server_core::config::{eval_poly_g2, eval_poly_scalar, g2, scalar};
+use rand::rngs::OsRng;
+use tbs::{AggregatePublicKey, PublicKeyShare, SecretKeyShare};
+use threshold_crypto::ff::Field;
+use threshold_crypto::group::Curve;
+use threshold_crypto::{G2Projective, Scalar};
+
+const DKG_DEALER_SALT: &[u8] = b"fedimint-mint-key-epoch-dkg-dealer";
+
+const DKG_SHARE_SALT: &[u8] = b"fedimint-mint-key-epoch-dkg-share";
+
+/// Polynomials and encryption key of this guardian for an attempt of the DKG
+/// of a key epoch
+pub struct KeyEpochDealer {
+    id: KeyEpochDkgAttemptId,
+    encryption_key: Keypair,
+    polynomials: Tiered<Vec<Scalar>>,
+    commitments: Tiered<Vec<CoefficientCommitment>>,
+}
+
+impl std::fmt::Debug for KeyEpochDealer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyEpochDealer")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Secrets of a [`KeyEpochDealer`] as persisted in the database
+#[derive(Encodable, Decodable)]
+struct KeyEpochDealerSecrets {
+    id: KeyEpochDkgAttemptId,
+    encryption_key: [u8; 32],
+    polynomials: BTreeMap<Amount, Vec<[u8; 32]>>,
+}
+
+impl KeyEpochDealer {
+    /// Generates fresh polynomials of degree `threshold - 1` for `amounts` and
+    /// a fresh encryption key
+    pub fn new(
+        id: KeyEpochDkgAttemptId,
+        amounts: impl Iterator<Item = Amount>,
+        threshold: usize,
+    ) -> Self {
+        let polynomials = amounts
+            .map(|amount| {
+                let polynomial = (0..threshold).map(|_| Scalar::random(&mut OsRng)).collect();
+
+                (amount, polynomial)
+            })
+            .collect();
+
+        Self::from_secrets(id, Keypair::new(SECP256K1, &mut OsRng), polynomials)
+    }
+
+    fn from_secrets(
+        id: KeyEpochDkgAttemptId,
+        encryption_key: Keypair,
+        polynomials: Tiered<Vec<Scalar>>,
+    ) -> Self {
+        let commitments = polynomials
+            .iter()
+            .map(|(amount, polynomial)| {
+                let commitment = polynomial
+                    .iter()
+                    .map(|coefficient| CoefficientCommitment(g2(coefficient).to_affine()))
+                    .collect();
+
+                (amount, commitment)
+            })
+            .collect();
+
+        KeyEpochDealer {
+            id,
+            encryption_key,
+            polynomials,
+            commitments,
+        }
+    }
+
+    /// Encrypts our polynomials and encryption key to persist them, see
+    /// [`dealer_encryption_key`]
+    pub fn encrypt(&self, key: &LessSafeKey) -> Vec<u8> {
+        let secrets = KeyEpochDealerSecrets {
+            id: self.id,
+            encryption_key: self.encryption_key.secret_bytes(),
+            polynomials: self
+                .polynomials
+                .iter()
+                .map(|(amount, polynomial)| {
+                    (amount, polynomial.iter().map(Scalar::to_bytes).collect())
+                })
+                .collect(),
+        };
+
+        fedimint_aead::encrypt(secrets.consensus_encode_to_vec(), key)
+            .expect("Encryption does not fail")
+    }
+
+    /// Restores a dealer persisted with [`KeyEpochDealer::encrypt`]
+    pub fn decrypt(ciphertext: &[u8], key: &LessSafeKey) -> anyhow::Result<Self> {
+        let mut ciphertext = ciphertext.to_vec();
+
+        let secrets = KeyEpochDealerSecrets::consensus_decode_whole(
+            fedimint_aead::decrypt(&mut ciphertext, key)?,
+            &ModuleRegistry::default(),
+        )?;
+
+        let encryption_key =
+            Keypair::from_secret_key(SECP256K1, &SecretKey::from_slice(&secrets.encryption_key)?);
+
+        let mut polynomials = Tiered::default();
+
+        for (amount, coefficients) in secrets.polynomials {
+            let polynomial = coefficients
+                .iter()
+                .map(|bytes| {
+                    Option::<Scalar>::from(Scalar::from_bytes(bytes))
+                        .ok_or_else(|| anyhow::anyhow!("Invalid polynomial coefficient"))
+                })
+                .collect::<anyhow::Result<Vec<Scalar>>>()?;
+
+            polynomials.insert(amount, polynomial);
+        }
+
+        Ok(Self::from_secrets(secrets.id, encryption_key, polynomials))
+    }
+
+    pub fn id(&self) -> KeyEpochDkgAttemptId {
+        self.id
+    }
+
+    pub fn encryption_key(&self) -> &Keypair {
+        &self.encryption_key
+    }
+
+    pub fn amounts(&self) -> impl Iterator<Item = Amount> + '_ {
+        self.polynomials.tiers().copied()
+    }
+
+    pub fn commitment(&self) -> KeyEpochDkgCommitment {
+        KeyEpochDkgCommitment {
+            id: self.id,
+            encryption_key: self.encryption_key.public_key(),
+            hashes: self
+                .commitments
+                .iter()
+                .map(|(amount, commitment)| (amount, commitment.consensus_hash_sha256()))
+                .collect(),
+        }
+    }
+
+    /// Deals the shares of the polynomial for `amount` to all participants,
+    /// encrypted to the keys they announced in their commitments
+    pub fn dealing(
+        &self,
+        amount: Amount,
+        dealer: PeerId,
+        encryption_keys: &BTreeMap<PeerId, PublicKey>,
+    ) -> KeyEpochDkgDealing {
+        let polynomial = self
+            .polynomials
+            .get(amount)
+            .expect("Dealt a polynomial for every denomination");
+
+        let encrypted_shares = encryption_keys
+            .iter()
+            .map(|(&recipient, encryption_key)| {
+                let share = eval_poly_scalar(polynomial, &scalar(&recipient));
+
+                let pad = share_pad(
+                    &ecdh::SharedSecret::new(encryption_key, &self.encryption_key.secret_key()),
+                    self.id,
+                    amount,
+                    dealer,
+                    recipient,
+                );
+
+                (recipient, xor(share.to_bytes(), pad))
+            })
+            .collect();
+
+        KeyEpochDkgDealing {
+            id: self.id,
+            amount,
+            commitment: self.commitments.get(amount).cloned().unwrap_or_default(),
+            encrypted_shares,
+        }
+    }
+
+    /// Complaint revealing our encryption key of this attempt, proving that
+    /// `dealer` dealt us an invalid share for `amount`
+    pub fn complaint(&self, dealer: PeerId, amount: Amount) -> KeyEpochDkgComplaint {
+        KeyEpochDkgComplaint {
+            id: self.id,
+            dealer,
+            amount,
+            encryption_secret_key: self.encryption_key.secret_bytes(),
+        }
+    }
+}
+
+/// Key this guardian's [`KeyEpochDealer`]s are encrypted with in the database,
+/// derived from its secret key shares of key epoch zero
+pub fn dealer_encryption_key(tbs_sks: &Tiered<SecretKeyShare>) -> LessSafeKey {
+    fedimint_aead::get_encryption_key_from_secret(
+        &DerivableSecret::new_root(&tbs_sks.consensus_encode_to_vec(), DKG_DEALER_SALT)
+            .to_random_bytes(),
+    )
+}
+
+/// Public keys of a key epoch, derived from the dealings of all participants
+/// per denomination. Guardians excluded from the DKG have no key shares.
+pub fn key_epoch_keys(
+    dealings: &BTreeMap<Amount, BTreeMap<PeerId, KeyEpochDkgDealing>>,
+) -> KeyEpochKeys {
+    let mut tbs_pks = Tiered::default();
+    let mut peer_tbs_pks = BTreeMap::<PeerId, Tiered<PublicKeyShare>>::new();
+
+    for (&amount, amount_dealings) in dealings {
+        let commitment = sum_commitments(amount_dealings.values().map(|d| &d.commitment));
+
+        tbs_pks.insert(amount, AggregatePublicKey(commitment[0].to_affine()));
+
+        for peer in amount_dealings.keys() {
+            peer_tbs_pks
+                .entry(*peer)
+                .or_default()
+                .insert(amount, PublicKeyShare(eval_poly_g2(&commitment, peer)));
+        }
+    }
+
+    KeyEpochKeys {
+        tbs_pks,
+        peer_tbs_pks,
+    }
+}
+
+/// Secret key shares of `recipient` in a key epoch, the sum of the shares all
+/// participants dealt to it per denomination. Returns the first dealer and
+/// denomination with an invalid share, which the recipient complains about.
+pub fn decrypt_sec_keys(
+    encryption_key: &Keypair,
+    recipient: PeerId,
+    commitments: &BTreeMap<PeerId, KeyEpochDkgCommitment>,
+    dealings: &BTreeMap<Amount, BTreeMap<PeerId, KeyEpochDkgDealing>>,
+) -> Result<Tiered<SecretKeyShare>, (PeerId, Amount)> {
+    dealings
+        .iter()
+        .map(|(&amount, amount_dealings)| {
+            let mut sec_key = Scalar::zero();
+
+            for (&dealer, dealing) in amount_dealings {
+                sec_key += decrypt_share(
+                    encryption_key,
+                    &commitments[&dealer].encryption_key,
+                    dealing,
+                    dealer,
+                    recipient,
+                )
+                .ok_or((dealer, amount))?;
+            }
+
+            Ok((amount, SecretKeyShare(sec_key)))
+        })
+        .collect()
+}
+
+/// Verifies a complaint of `complainer`, returns whether the revealed key is
+/// the complainer's encryption key and the share `dealer` dealt to it indeed
+/// does not match the dealer's commitment
+pub fn verify_complaint(
+    complaint: &KeyEpochDkgComplaint,
+    complainer: PeerId,
+    commitments: &BTreeMap<PeerId, KeyEpochDkgCommitment>,
+    dealing: &KeyEpochDkgDealing,
+) -> bool {
+    let Ok(secret_key) = SecretKey::from_slice(&complaint.encryption_secret_key) else {
+        return false;
+    };
+
+    let encryption_key = Keypair::from_secret_key(SECP256K1, &secret_key);
+
+    let (Some(complainer_commitment), Some(dealer_commitment)) = (
+        commitments.get(&complainer),
+        commitments.get(&complaint.dealer),
+    ) else {
+        return false;
+    };
+
+    encryption_key.public_key() == complainer_commitment.encryption_key
+        && decrypt_share(
+            &encryption_key,
+            &dealer_commitment.encryption_key,
+            dealing,
+            complaint.dealer,
+            complainer,
+        )
+        .is_none()
+}
+
+/// Decrypts the share `dealer` dealt to `recipient`, returns `None` if it
+/// doesn't match the dealer's commitment
+fn decrypt_share(
+    encryption_key: &Keypair,
+    dealer_encryption_key: &PublicKey,
+    dealing: &KeyEpochDkgDealing,
+    dealer: PeerId,
+    recipient: PeerId,
+) -> Option<Scalar> {
+    let encrypted_share = dealing.encrypted_shares.get(&recipient)?;
+
+    let pad = share_pad(
+        &ecdh::SharedSecret::new(dealer_encryption_key, &encryption_key.secret_key()),
+        dealing.id,
+        dealing.amount,
+        dealer,
+        recipient,
+    );
+
+    let share = Option::<Scalar>::from(Scalar::from_bytes(&xor(*encrypted_share, pad)))?;
+
+    let commitment = sum_commitments(std::iter::once(&dealing.commitment));
+
+    (g2(&share).to_affine() == eval_poly_g2(&commitment, &recipient)).then_some(share)
+}
+
+/// Sums the commitments of all dealers into the commitment to the polynomial
+/// of the key epoch's secret key
+fn sum_commitments<'a>(
+    commitments: impl Iterator<Item = &'a Vec<CoefficientCommitment>>,
+) -> Vec<G2Projective> {
+    commitments.fold(Vec::new(), |mut sum, commitment| {
+        sum.resize(sum.len().max(commitment.len()), G2Projective::identity());
+
+        for (acc, coefficient) in sum.iter_mut().zip(commitment) {
+            *acc += coefficient.0;
+        }
+
+        sum
+    })
+}
+
+/// The pad depends on the direction of the dealing so the two shares two
+/// guardians deal each other are not encrypted with the same pad
+fn share_pad(
+    shared_secret: &ecdh::SharedSecret,
+    id: KeyEpochDkgAttemptId,
+    amount: Amount,
+    dealer: PeerId,
+    recipient: PeerId,
+) -> [u8; 32] {
+    DerivableSecret::new_root(&shared_secret.secret_bytes(), DKG_SHARE_SALT)
+        .child_key(ChildId(id.key_epoch))
+        .child_key(ChildId(id.attempt))
+        .child_key(ChildId(amount.msats))
+        .child_key(ChildId(dealer.to_usize() as u64))
+        .child_key(ChildId(recipient.to_usize() as u64))
+        .to_random_bytes()
+}
+
+fn xor(mut bytes: [u8; 32], pad: [u8; 32]) -> [u8; 32] {
+    for (byte, pad) in bytes.iter_mut().zip(pad) {
+        *byte ^= pad;
+    }
+
+    bytes
+}
@@ -5,11 +5,15 @@
 #![allow(clippy::similar_names)]
 
 pub mod db;
+mod dkg;
 mod metrics;
 
 use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::bail;
+use anyhow::{bail, ensure};
+use fedimint_api_client::api::{DynModuleApi, FederationApiExt};
 use fedimint_core::bitcoin::hashes::sha256;
 use fedimint_core::config::{
     ServerModuleConfig, ServerModuleConsensusConfig, TypedServerModuleConfig,
@@ -17,32 +21,41 @@
 };
 use fedimint_core::core::ModuleInstanceId;
 use fedimint_core::db::{
-    DatabaseTransaction, DatabaseVersion, IDatabaseTransactionOpsCore,
+    Database, DatabaseTransaction, DatabaseVersion, IDatabaseTransactionOpsCore,
     IDatabaseTransactionOpsCoreTyped,
 };
 use fedimint_core::encoding::Encodable;
+use fedimint_core::envs::is_running_in_test_env;
 use fedimint_core::module::audit::Audit;
 use fedimint_core::module::{
-    Amounts, ApiEndpoint, ApiError, ApiVersion, CORE_CONSENSUS_VERSION, CoreConsensusVersion,
-    InputMeta, ModuleConsensusVersion, ModuleInit, SerdeModuleEncodingBase64,
-    SupportedModuleApiVersions, TransactionItemAmounts, api_endpoint,
+    AmountUnit, Amounts, ApiEndpoint, ApiError, ApiRequestErased, ApiVersion,
+    CORE_CONSENSUS_VERSION, CoreConsensusVersion, InputMeta, ModuleConsensusVersion, ModuleInit,
+    SerdeModuleEncodingBase64, SupportedModuleApiVersions, TransactionItemAmounts, api_endpoint,
 };
+use fedimint_core::net::auth::check_auth;
+use fedimint_core::task::{TaskGroup, sleep};
+use fedimint_core::time::duration_since_epoch;
+use fedimint_core::util::FmtCompact as _;
 use fedimint_core::{
     Amount, InPoint, NumPeersExt, OutPoint, PeerId, Tiered, TieredMulti, apply,
-    async_trait_maybe_send, push_db_key_items, push_db_pair_items,
+    async_trait_maybe_send, push_db_key_items, push_db_pair_items, secp256k1,
 };
 use fedimint_logging::LOG_MODULE_MINT;
 pub use fedimint_mint_common as common;
 use fedimint_mint_common::config::{
     FeeConsensus, MintClientConfig, MintConfig, MintConfigConsensus, MintConfigPrivate,
+    MintUnitClientConfig, MintUnitConfigConsensus,
 };
 pub use fedimint_mint_common::{BackupRequest, SignedBackupRequest};
 use fedimint_mint_common::{
-    DEFAULT_MAX_NOTES_PER_DENOMINATION, MODULE_CONSENSUS_VERSION, MintCommonInit,
-    MintConsensusItem, MintInput, MintInputError, MintModuleTypes, MintOutput, MintOutputError,
-    MintOutputOutcome,
+    CUSTOM_UNITS_MODULE_CONSENSUS_VERSION, DEFAULT_MAX_NOTES_PER_DENOMINATION,
+    KEY_EPOCH_DEPRECATION_WINDOW, KEY_ROTATION_MODULE_CONSENSUS_VERSION, KeyEpochDkgAttemptId,
+    KeyEpochDkgCommitment, KeyEpochDkgComplaint, KeyEpochDkgDealing, KeyEpochKeys, KeyEpochStatus,
+    MAX_NOTES_SPENT_BATCH_SIZE, MODULE_CONSENSUS_VERSION, MintCommonInit, MintConsensusItem,
+    MintInput, MintInputError, MintInputV2, MintModuleTypes, MintOutput, MintOutputError,
+    MintOutputOutcome, Note, NoteSpentBitmap, P2PK_MODULE_CONSENSUS_VERSION, P2pkSpendPath,
 };
-use fedimint_server_core::config::{PeerHandleOps, eval_poly_g2};
+use fedimint_server_core::config::{PeerHandleOps, eval_poly_g2, eval_poly_scalar};
 use fedimint_server_core::migration::{
     ModuleHistoryItem, ServerModuleDbMigrationFn, ServerModuleDbMigrationFnContext,
     ServerModuleDbMigrationFnContextExt as _,
@@ -50,6 +63,7 @@
 use fedimint_server_core::{
     ConfigGenModuleArgs, ServerModule, ServerModuleInit, ServerModuleInitArgs,
 };
+use futures::future::join_all;
 use futures::{FutureExt as _, StreamExt};
 use itertools::Itertools;
 use metrics::{
@@ -65,19 +79,34 @@
 use threshold_crypto::ff::Field;
 use threshold_crypto::group::Curve;
 use threshold_crypto::{G2Projective, Scalar};
-use tracing::{debug, info, warn};
+use tokio::sync::watch;
+use tracing::{debug, info, trace, warn};
 
 use crate::common::endpoint_constants::{
-    BLIND_NONCE_USED_ENDPOINT, NOTE_SPENT_ENDPOINT, RECOVERY_BLIND_NONCE_OUTPOINTS_ENDPOINT,
-    RECOVERY_COUNT_ENDPOINT, RECOVERY_SLICE_ENDPOINT, RECOVERY_SLICE_HASH_ENDPOINT,
+    BLIND_NONCE_USED_ENDPOINT, KEY_EPOCH_KEYS_ENDPOINT, KEY_EPOCH_STATUS_ENDPOINT,
+    MODULE_CONSENSUS_VERSION_ENDPOINT, NOTE_SPENT_ENDPOINT, NOTES_SPENT_ENDPOINT,
+    RECOVERY_BLIND_NONCE_OUTPOINTS_ENDPOINT, RECOVERY_COUNT_ENDPOINT, RECOVERY_SLICE_ENDPOINT,
+    RECOVERY_SLICE_HASH_ENDPOINT, ROTATE_KEY_EPOCH_ENDPOINT,
+    SUPPORTED_MODULE_CONSENSUS_VERSION_ENDPOINT,
 };
 use crate::common::{BlindNonce, Nonce, RecoveryItem};
 use crate::db::{
-    BlindNonceKey, BlindNonceKeyPrefix, DbKeyPrefix, MintAuditItemKey, MintAuditItemKeyPrefix,
-    MintOutputOutcomeKey, MintOutputOutcomePrefix, NonceKey, NonceKeyPrefix,
-    RecoveryBlindNonceOutpointKey, RecoveryBlindNonceOutpointKeyPrefix, RecoveryItemKey,
-    RecoveryItemKeyPrefix,
+    BlindNonceKey, BlindNonceKeyPrefix, ConsensusVersionVoteKey, ConsensusVersionVotePrefix,
+    CustomUnitNonceKey, CustomUnitNonceKeyPrefix, DbKeyPrefix, KeyEpochActivationKey,
+    KeyEpochActivationPrefix, KeyEpochDkgAttempt, KeyEpochDkgAttemptKey, KeyEpochDkgAttemptPrefix,
+    KeyEpochDkgCommitmentKey, KeyEpochDkgCommitmentKeyEpochPrefix, KeyEpochDkgCommitmentPrefix,
+    KeyEpochDkgConfirmationKey, KeyEpochDkgConfirmationKeyEpochPrefix,
+    KeyEpochDkgConfirmationPrefix, KeyEpochDkgDealerKey, KeyEpochDkgDealerPrefix,
+    KeyEpochDkgDealingKey, KeyEpochDkgDealingKeyEpochPrefix, KeyEpochDkgDealingPrefix,
+    KeyEpochDkgExclusionKey, KeyEpochDkgExclusionKeyEpochPrefix, KeyEpochDkgExclusionPrefix,
+    KeyEpochKeysKey, KeyEpochKeysPrefix, KeyEpochNonceKey, KeyEpochNonceKeyEpochPrefix,
+    KeyEpochNonceKeyPrefix, KeyEpochVoteKey, KeyEpochVotePrefix, MintAuditItemKey,
+    MintAuditItemKeyPrefix, MintOutputOutcomeKey, MintOutputOutcomePrefix, NonceKey,
+    NonceKeyPrefix, RecoveryBlindNonceOutpointKey, RecoveryBlindNonceOutpointKeyPrefix,
+    RecoveryItemKey, RecoveryItemKeyPrefix, RetiredKeyEpochKey, RetiredKeyEpochPrefix,
+    TargetKeyEpochKey, UnixTimeVoteKey, UnixTimeVotePrefix,
 };
+use crate::dkg::KeyEpochDealer;
 
 #[derive(Debug, Clone)]
 pub struct MintInit;
@@ -85,6 +114,7 @@
 impl ModuleInit for MintInit {
     type Common = MintCommonInit;
 
+    #[allow(clippy::too_many_lines)]
     async fn dump_database(
         &self,
         dbtx: &mut DatabaseTransaction<'_>,
@@ -148,6 +178,146 @@ async fn dump_database(
                         "Recovery Blind Nonce Outpoints"
                     );
                 }
+                DbKeyPrefix::UnixTimeVote => {
+                    push_db_pair_items!(
+                        dbtx,
+                        UnixTimeVotePrefix,
+                        UnixTimeVoteKey,
+                        u64,
+                        mint,
+                        "Unix Time Votes"
+                    );
+                }
+                DbKeyPrefix::KeyEpochVote => {
+                    push_db_pair_items!(
+                        dbtx,
+                        KeyEpochVotePrefix,
+                        KeyEpochVoteKey,
+                        u64,
+                        mint,
+                        "Key Epoch Votes"
+                    );
+                }
+                DbKeyPrefix::KeyEpochActivation => {
+                    push_db_pair_items!(
+                        dbtx,
+                        KeyEpochActivationPrefix,
+                        KeyEpochActivationKey,
+                        u64,
+                        mint,
+                        "Key Epoch Activations"
+                    );
+                }
+                DbKeyPrefix::KeyEpochNonce => {
+                    push_db_key_items!(
+                        dbtx,
+                        KeyEpochNonceKeyPrefix,
+                        KeyEpochNonceKey,
+                        mint,
+                        "Used Coins of Later Key Epochs"
+                    );
+                }
+                DbKeyPrefix::RetiredKeyEpoch => {
+                    push_db_key_items!(
+                        dbtx,
+                        RetiredKeyEpochPrefix,
+                        RetiredKeyEpochKey,
+                        mint,
+                        "Retired Key Epochs"
+                    );
+                }
+                DbKeyPrefix::ConsensusVersionVote => {
+                    push_db_pair_items!(
+                        dbtx,
+                        ConsensusVersionVotePrefix,
+                        ConsensusVersionVoteKey,
+                        ModuleConsensusVersion,
+                        mint,
+                        "Consensus Version Votes"
+                    );
+                }
+                DbKeyPrefix::CustomUnitNonce => {
+                    push_db_key_items!(
+                        dbtx,
+                        CustomUnitNonceKeyPrefix,
+                        CustomUnitNonceKey,
+                        mint,
+                        "Used Coins of Custom Units"
+                    );
+                }
+                DbKeyPrefix::TargetKeyEpoch => {
+                    if let Some(target) = dbtx.get_value(&TargetKeyEpochKey).await {
+                        mint.insert("Target Key Epoch".to_string(), Box::new(target));
+                    }
+                }
+                DbKeyPrefix::KeyEpochDkgAttempt => {
+                    push_db_pair_items!(
+                        dbtx,
+                        KeyEpochDkgAttemptPrefix,
+                        KeyEpochDkgAttemptKey,
+                        KeyEpochDkgAttempt,
+                        mint,
+                        "Key Epoch DKG Attempts"
+                    );
+                }
+                DbKeyPrefix::KeyEpochDkgCommitment => {
+                    push_db_pair_items!(
+                        dbtx,
+                        KeyEpochDkgCommitmentPrefix,
+                        KeyEpochDkgCommitmentKey,
+                        KeyEpochDkgCommitment,
+                        mint,
+                        "Key Epoch DKG Commitments"
+                    );
+                }
+                DbKeyPrefix::KeyEpochDkgDealing => {
+                    push_db_pair_items!(
+                        dbtx,
+                        KeyEpochDkgDealingPrefix,
+                        KeyEpochDkgDealingKey,
+                        KeyEpochDkgDealing,
+                        mint,
+                        "Key Epoch DKG Dealings"
+                    );
+                }
+                DbKeyPrefix::KeyEpochKeys => {
+                    push_db_pair_items!(
+                        dbtx,
+                        KeyEpochKeysPrefix,
+                        KeyEpochKeysKey,
+                        KeyEpochKeys,
+                        mint,
+                        "Key Epoch Keys"
+                    );
+                }
+                DbKeyPrefix::KeyEpochDkgConfirmation => {
+                    push_db_key_items!(
+                        dbtx,
+                        KeyEpochDkgConfirmationPrefix,
+                        KeyEpochDkgConfirmationKey,
+                        mint,
+                        "Key Epoch DKG Confirmations"
+                    );
+                }
+                DbKeyPrefix::KeyEpochDkgExclusion => {
+                    push_db_key_items!(
+                        dbtx,
+                        KeyEpochDkgExclusionPrefix,
+                        KeyEpochDkgExclusionKey,
+                        mint,
+                        "Key Epoch DKG Exclusions"
+                    );
+                }
+                DbKeyPrefix::KeyEpochDkgDealer => {
+                    push_db_pair_items!(
+                        dbtx,
+                        KeyEpochDkgDealerPrefix,
+                        KeyEpochDkgDealerKey,
+                        Vec<u8>,
+                        mint,
+                        "Encrypted Key Epoch DKG Dealers"
+                    );
+                }
             }
         }
 
@@ -155,6 +325,10 @@ async fn dump_database(
     }
 }
 
+/// Seconds of consensus unix time after its first commitment within which an
+/// attempt of the DKG of a key epoch has to complete
+const KEY_EPOCH_DKG_TIMEOUT: u64 = 60 * 60;
+
 /// Default denomination base for ecash notes (powers of 2)
 const DEFAULT_DENOMINATION_BASE: u16 = 2;
 
@@ -169,6 +343,140 @@ fn gen_denominations() -> Vec<Amount> {
         .collect()
 }
 
+fn gen_fee_consensus(args: &ConfigGenModuleArgs) -> FeeConsensus {
+    if args.disable_base_fees {
+        FeeConsensus::zero()
+    } else {
+        FeeConsensus::new(0).expect("Relative fee is within range")
+    }
+}
+
+/// Public key shares of all peers and secret key shares of all peers for one
+/// unit
+type DealerUnitKeys = (
+    BTreeMap<PeerId, Tiered<PublicKeyShare>>,
+    BTreeMap<PeerId, Tiered<SecretKeyShare>>,
+);
+
+fn dealer_unit_keygen(peers: &[PeerId], denominations: &[Amount]) -> DealerUnitKeys {
+    let tbs_keys = denominations
+        .iter()
+        .map(|&amount| {
+            let (tbs_pk, tbs_pks, tbs_sks) =
+                dealer_keygen(peers.to_num_peers().threshold(), peers.len());
+            (amount, (tbs_pk, tbs_pks, tbs_sks))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let peer_tbs_pks = peers
+        .iter()
+        .map(|&key_peer| {
+            let keys = denominations
+                .iter()
+                .map(|amount| (*amount, tbs_keys[amount].1[key_peer.to_usize()]))
+                .collect();
+            (key_peer, keys)
+        })
+        .collect();
+
+    let peer_tbs_sks = peers
+        .iter()
+        .map(|&peer| {
+            let keys = denominations
+                .iter()
+                .map(|amount| (*amount, tbs_keys[amount].2[peer.to_usize()]))
+                .collect();
+            (peer, keys)
+        })
+        .collect();
+
+    (peer_tbs_pks, peer_tbs_sks)
+}
+
+/// Runs one DKG per denomination and returns our secret key shares together
+/// with the public key shares of all peers
+async fn distributed_unit_keygen(
+    peers: &(dyn PeerHandleOps + Send + Sync),
+    denominations: &[Amount],
+) -> anyhow::Result<(
+    Tiered<SecretKeyShare>,
+    BTreeMap<PeerId, Tiered<PublicKeyShare>>,
+)> {
+    let mut amount_keys = HashMap::new();
+
+    for amount in denominations {
+        amount_keys.insert(*amount, peers.run_dkg_g2().await?);
+    }
+
+    let tbs_sks = amount_keys
+        .iter()
+        .map(|(amount, (_, sks))| (*amount, tbs::SecretKeyShare(*sks)))
+        .collect();
+
+    let peer_tbs_pks = peers
+        .num_peers()
+        .peer_ids()
+        .map(|peer| {
+            let pks = amount_keys
+                .iter()
+                .map(|(amount, (pks, _))| (*amount, PublicKeyShare(eval_poly_g2(pks, &peer))))
+                .collect::<Tiered<_>>();
+
+            (peer, pks)
+        })
+        .collect();
+
+    Ok((tbs_sks, peer_tbs_pks))
+}
+
+/// Aggregates the public key shares of all peers into the public keys used to
+/// verify notes
+fn aggregate_pub_keys<C>(peer_tbs_pks: &BTreeMap<PeerId, Tiered<PublicKeyShare>>) -> C
+where
+    C: FromIterator<(Amount, AggregatePublicKey)>,
+{
+    // TODO: the aggregate pks should become part of the MintConfigConsensus as they
+    // can be obtained by evaluating the polynomial returned by the DKG at
+    // zero
+    TieredMulti::new_aggregate_from_tiered_iter(peer_tbs_pks.values().cloned())
+        .into_iter()
+        .map(|(amt, keys)| {
+            let keys = (0_u64..)
+                .zip(keys)
+                .take(peer_tbs_pks.to_num_peers().threshold())
+                .collect();
+
+            (amt, aggregate_public_key_shares(&keys))
+        })
+        .collect()
+}
+
+fn validate_unit_keys(
+    identity: PeerId,
+    tbs_sks: &Tiered<SecretKeyShare>,
+    peer_tbs_pks: &BTreeMap<PeerId, Tiered<PublicKeyShare>>,
+) -> anyhow::Result<()> {
+    let sks: BTreeMap<Amount, PublicKeyShare> = tbs_sks
+        .iter()
+        .map(|(amount, sk)| (amount, derive_pk_share(sk)))
+        .collect();
+    let pks: BTreeMap<Amount, PublicKeyShare> = peer_tbs_pks
+        .get(&identity)
+        .ok_or_else(|| anyhow::format_err!("No pubkey shares for our peer id"))?
+        .as_map()
+        .iter()
+        .map(|(k, v)| (*k, *v))
+        .collect();
+    if sks != pks {
+        bail!("Mint private key doesn't match pubkey share");
+    }
+    if !sks.keys().contains(&Amount::from_msats(1)) {
+        bail!("No msat 1 denomination");
+    }
+
+    Ok(())
+}
+
 #[apply(async_trait_maybe_send!)]
 impl ServerModuleInit for MintInit {
     type Module = Mint;
@@ -184,12 +492,22 @@ fn supported_api_versions(&self) -> SupportedModuleApiVersions {
                 MODULE_CONSENSUS_VERSION.major,
                 MODULE_CONSENSUS_VERSION.minor,
             ),
-            &[(0, 1)],
+            &[(0, 5)],
         )
     }
 
     async fn init(&self, args: &ServerModuleInitArgs<Self>) -> anyhow::Result<Self::Module> {
-        Ok(Mint::new(args.cfg().to_typed()?))
+        let peer_supported_consensus_version = Mint::spawn_peer_supported_consensus_version_task(
+            args.module_api().clone(),
+            args.task_group(),
+            args.our_peer_id(),
+        );
+
+        Ok(Mint::new(
+            args.cfg().to_typed()?,
+            args.db().clone(),
+            peer_supported_consensus_version,
+        ))
     }
 
     fn trusted_dealer_gen(
@@ -198,44 +516,54 @@ fn trusted_dealer_gen(
         args: &ConfigGenModuleArgs,
     ) -> BTreeMap<PeerId, ServerModuleConfig> {
         let denominations = gen_denominations();
+        let fee_consensus = gen_fee_consensus(args);
+
+        let (peer_tbs_pks, mut peer_tbs_sks) = dealer_unit_keygen(peers, &denominations);
+
+        let mut custom_unit_keys = args
+            .mint_custom_units
+            .iter()
+            .copied()
+            .map(|unit| (unit, dealer_unit_keygen(peers, &denominations)))
+            .collect::<BTreeMap<_, _>>();
 
-        let tbs_keys = denominations
+        let custom_units = custom_unit_keys
             .iter()
-            .map(|&amount| {
-                let (tbs_pk, tbs_pks, tbs_sks) =
-                    dealer_keygen(peers.to_num_peers().threshold(), peers.len());
-                (amount, (tbs_pk, tbs_pks, tbs_sks))
+            .map(|(unit, (peer_tbs_pks, _))| {
+                (
+                    *unit,
+                    MintUnitConfigConsensus {
+                        peer_tbs_pks: peer_tbs_pks.clone(),
+                        fee_consensus: fee_consensus.clone(),
+                    },
+                )
             })
-            .collect::<HashMap<_, _>>();
+            .collect::<BTreeMap<_, _>>();
 
         let mint_cfg: BTreeMap<_, MintConfig> = peers
             .iter()
             .map(|&peer| {
                 let config = MintConfig {
                     consensus: MintConfigConsensus {
-                        peer_tbs_pks: peers
-                            .iter()
-                            .map(|&key_peer| {
-                                let keys = denominations
-                                    .iter()
-                                    .map(|amount| {
-                                        (*amount, tbs_keys[amount].1[key_peer.to_usize()])
-                                    })
-                                    .collect();
-                                (key_peer, keys)
-                            })
-                            .collect(),
-                        fee_consensus: if args.disable_base_fees {
-                            FeeConsensus::zero()
-                        } else {
-                            FeeConsensus::new(0).expect("Relative fee is within range")
-                        },
+                        peer_tbs_pks: peer_tbs_pks.clone(),
+                        fee_consensus: fee_consensus.clone(),
                         max_notes_per_denomination: DEFAULT_MAX_NOTES_PER_DENOMINATION,
+                        custom_units: custom_units.clone(),
                     },
                     private: MintConfigPrivate {
-                        tbs_sks: denominations
-                            .iter()
-                            .map(|amount| (*amount, tbs_keys[amount].2[peer.to_usize()]))
+                        tbs_sks: peer_tbs_sks
+                            .remove(&peer)
+                            .expect("Generated keys for every peer"),
+                        custom_unit_tbs_sks: custom_unit_keys
+                            .iter_mut()
+                            .map(|(unit, (_, peer_tbs_sks))| {
+                                (
+                                    *unit,
+                                    peer_tbs_sks
+                                        .remove(&peer)
+                                        .expect("Generated keys for every peer"),
+                                )
+                            })
                             .collect(),
                     },
                 };
@@ -255,41 +583,38 @@ async fn distributed_gen(
         args: &ConfigGenModuleArgs,
     ) -> anyhow::Result<ServerModuleConfig> {
         let denominations = gen_denominations();
+        let fee_consensus = gen_fee_consensus(args);
+
+        let (tbs_sks, peer_tbs_pks) = distributed_unit_keygen(peers, &denominations).await?;
+
+        // All guardians have to configure the same custom units, otherwise the DKG
+        // below will not complete
+        let mut custom_unit_tbs_sks = BTreeMap::new();
+        let mut custom_units = BTreeMap::new();
 
-        let mut amount_keys = HashMap::new();
+        for unit in args.mint_custom_units.iter().copied() {
+            let (tbs_sks, peer_tbs_pks) = distributed_unit_keygen(peers, &denominations).await?;
 
-        for amount in &denominations {
-            amount_keys.insert(*amount, peers.run_dkg_g2().await?);
+            custom_unit_tbs_sks.insert(unit, tbs_sks);
+            custom_units.insert(
+                unit,
+                MintUnitConfigConsensus {
+                    peer_tbs_pks,
+                    fee_consensus: fee_consensus.clone(),
+                },
+            );
         }
 
         let server = MintConfig {
             private: MintConfigPrivate {
-                tbs_sks: amount_keys
-                    .iter()
-                    .map(|(amount, (_, sks))| (*amount, tbs::SecretKeyShare(*sks)))
-                    .collect(),
+                tbs_sks,
+                custom_unit_tbs_sks,
             },
             consensus: MintConfigConsensus {
-                peer_tbs_pks: peers
-                    .num_peers()
-                    .peer_ids()
-                    .map(|peer| {
-                        let pks = amount_keys
-                            .iter()
-                            .map(|(amount, (pks, _))| {
-                                (*amount, PublicKeyShare(eval_poly_g2(pks, &peer)))
-                            })
-                            .collect::<Tiered<_>>();
-
-                        (peer, pks)
-                    })
-                    .collect(),
-                fee_consensus: if args.disable_base_fees {
-                    FeeConsensus::zero()
-                } else {
-                    FeeConsensus::new(0).expect("Relative fee is within range")
-                },
+                peer_tbs_pks,
+                fee_consensus,
                 max_notes_per_denomination: DEFAULT_MAX_NOTES_PER_DENOMINATION,
+                custom_units,
             },
         };
 
@@ -298,26 +623,37 @@ async fn distributed_gen(
 
     fn validate_config(&self, identity: &PeerId, config: ServerModuleConfig) -> anyhow::Result<()> {
         let config = config.to_typed::<MintConfig>()?;
-        let sks: BTreeMap<Amount, PublicKeyShare> = config
-            .private
-            .tbs_sks
-            .iter()
-            .map(|(amount, sk)| (amount, derive_pk_share(sk)))
-            .collect();
-        let pks: BTreeMap<Amount, PublicKeyShare> = config
+
+        validate_unit_keys(
+            *identity,
+            &config.private.tbs_sks,
+            &config.consensus.peer_tbs_pks,
+        )?;
+
+        if config
             .consensus
-            .peer_tbs_pks
-            .get(identity)
-            .unwrap()
-            .as_map()
-            .iter()
-            .map(|(k, v)| (*k, *v))
-            .collect();
-        if sks != pks {
-            bail!("Mint private key doesn't match pubkey share");
+            .custom_units
+            .keys()
+            .any(|unit| unit.is_bitcoin())
+        {
+            bail!("Bitcoin is not a custom unit");
+        }
+
+        if !config
+            .consensus
+            .custom_units
+            .keys()
+            .eq(config.private.custom_unit_tbs_sks.keys())
+        {
+            bail!("Mint private keys don't match the configured custom units");
         }
-        if !sks.keys().contains(&Amount::from_msats(1)) {
-            bail!("No msat 1 denomination");
+
+        for (unit, unit_config) in &config.consensus.custom_units {
+            validate_unit_keys(
+                *identity,
+                &config.private.custom_unit_tbs_sks[unit],
+                &unit_config.peer_tbs_pks,
+            )?;
         }
 
         Ok(())
@@ -328,27 +664,26 @@ fn get_client_config(
         config: &ServerModuleConsensusConfig,
     ) -> anyhow::Result<MintClientConfig> {
         let config = MintConfigConsensus::from_erased(config)?;
-        // TODO: the aggregate pks should become part of the MintConfigConsensus as they
-        // can be obtained by evaluating the polynomial returned by the DKG at
-        // zero
-        let tbs_pks =
-            TieredMulti::new_aggregate_from_tiered_iter(config.peer_tbs_pks.values().cloned())
-                .into_iter()
-                .map(|(amt, keys)| {
-                    let keys = (0_u64..)
-                        .zip(keys)
-                        .take(config.peer_tbs_pks.to_num_peers().threshold())
-                        .collect();
-
-                    (amt, aggregate_public_key_shares(&keys))
-                })
-                .collect();
 
         Ok(MintClientConfig {
-            tbs_pks,
+            tbs_pks: aggregate_pub_keys(&config.peer_tbs_pks),
             fee_consensus: config.fee_consensus.clone(),
             peer_tbs_pks: config.peer_tbs_pks.clone(),
             max_notes_per_denomination: config.max_notes_per_denomination,
+            custom_units: config
+                .custom_units
+                .iter()
+                .map(|(unit, unit_config)| {
+                    (
+                        *unit,
+                        MintUnitClientConfig {
+                            tbs_pks: aggregate_pub_keys(&unit_config.peer_tbs_pks),
+                            fee_consensus: unit_config.fee_consensus.clone(),
+                            peer_tbs_pks: unit_config.peer_tbs_pks.clone(),
+                        },
+                    )
+                })
+                .collect(),
         })
     }
 
@@ -495,10 +830,10 @@ fn dealer_keygen(
     let mut rng = OsRng; // FIXME: pass rng
     let poly: Vec<Scalar> = (0..threshold).map(|_| Scalar::random(&mut rng)).collect();
 
-    let apk = (G2Projective::generator() * eval_polynomial(&poly, &Scalar::zero())).to_affine();
+    let apk = (G2Projective::generator() * eval_poly_scalar(&poly, &Scalar::zero())).to_affine();
 
     let sks: Vec<SecretKeyShare> = (0..keys)
-        .map(|idx| SecretKeyShare(eval_polynomial(&poly, &Scalar::from(idx as u64 + 1))))
+        .map(|idx| SecretKeyShare(eval_poly_scalar(&poly, &Scalar::from(idx as u64 + 1))))
         .collect();
 
     let pks = sks
@@ -509,22 +844,83 @@ fn dealer_keygen(
     (AggregatePublicKey(apk), pks, sks)
 }
 
-fn eval_polynomial(coefficients: &[Scalar], x: &Scalar) -> Scalar {
-    coefficients
-        .iter()
-        .copied()
-        .rev()
-        .reduce(|acc, coefficient| acc * x + coefficient)
-        .expect("We have at least one coefficient")
-}
-
 /// Federated mint member mint
 #[derive(Debug)]
 pub struct Mint {
     cfg: MintConfig,
+    db: Database,
+    our_peer_id: PeerId,
+    sec_key: Tiered<SecretKeyShare>,
+    pub_key: HashMap<Amount, AggregatePublicKey>,
+    custom_unit_keys: BTreeMap<AmountUnit, MintUnitKeys>,
+    /// Our contribution to the DKG of the next key epoch, cached so we don't
+    /// decrypt it again for every consensus proposal
+    key_epoch_dealer: tokio::sync::Mutex<Option<Arc<KeyEpochDealer>>>,
+    /// Our secret key shares of the key epochs after zero, cached so we don't
+    /// decrypt them again for every output
+    key_epoch_sec_keys: std::sync::Mutex<BTreeMap<u64, Tiered<SecretKeyShare>>>,
+    /// Maximum consensus version supported by *all* our peers. Used to
+    /// automatically activate new consensus versions as soon as everyone
+    /// upgrades.
+    peer_supported_consensus_version: watch::Receiver<Option<ModuleConsensusVersion>>,
+}
+
+/// Keys of a non-bitcoin unit issued by the mint
+#[derive(Debug)]
+struct MintUnitKeys {
     sec_key: Tiered<SecretKeyShare>,
     pub_key: HashMap<Amount, AggregatePublicKey>,
 }
+
+/// Amount, unit and note of an input of a version this mint understands
+///
+/// [`MintInput::V1`] is only valid for custom units, so federations without
+/// any reject it just like versions predating custom units do. Likewise only
+/// bitcoin notes are issued in key epochs other than zero.
+fn known_input(input: &MintInput) -> Result<(Amount, AmountUnit, &Note), MintInputError> {
+    match input {
+        MintInput::V1(input) if input.unit.is_bitcoin() => {
+            Err(MintInputError::InvalidUnit(input.unit))
+        }
+        input => {
+            let (amount, unit, note) = input.ensure_known()?;
+
+            if input.key_epoch() != 0 && !unit.is_bitcoin() {
+                return Err(MintInputError::InvalidUnit(unit));
+            }
+
+            Ok((amount, unit, note))
+        }
+    }
+}
+
+fn verify_note_signature(
+    input: &MintInput,
+    note: &Note,
+    amount_key: AggregatePublicKey,
+) -> Result<(), MintInputError> {
+    let valid = match input.maybe_v2_ref() {
+        Some(input) => note.verify_locked(&input.lock, amount_key),
+        None => note.verify(amount_key),
+    };
+
+    if !valid {
+        return Err(MintInputError::InvalidSignature);
+    }
+
+    Ok(())
+}
+
+/// Amount, unit and blind nonce of an output of a version this mint
+/// understands, see [`known_input`]
+fn known_output(output: &MintOutput) -> Result<(Amount, AmountUnit, BlindNonce), MintOutputError> {
+    match output {
+        MintOutput::V1(output) if output.unit.is_bitcoin() => {
+            Err(MintOutputError::InvalidUnit(output.unit))
+        }
+        output => Ok(output.ensure_known()?),
+    }
+}
 #[apply(async_trait_maybe_send!)]
 impl ServerModule for Mint {
     type Common = MintModuleTypes;
@@ -532,33 +928,163 @@ impl ServerModule for Mint {
 
     async fn consensus_proposal(
         &self,
-        _dbtx: &mut DatabaseTransaction<'_>,
+        dbtx: &mut DatabaseTransaction<'_>,
     ) -> Vec<MintConsensusItem> {
-        Vec::new()
+        let active_consensus_version = self.consensus_module_consensus_version(dbtx).await;
+        let mut items = vec![];
+
+        if P2PK_MODULE_CONSENSUS_VERSION <= active_consensus_version {
+            // We reduce the time granularity to deduplicate votes more often and not save
+            // one consensus item every second.
+            items.push(MintConsensusItem::UnixTimeVote(
+                60 * (duration_since_epoch().as_secs() / 60),
+            ));
+        }
+
+        if KEY_ROTATION_MODULE_CONSENSUS_VERSION <= active_consensus_version {
+            let target = dbtx.get_value(&TargetKeyEpochKey).await.unwrap_or(0);
+
+            let vote = dbtx
+                .get_value(&KeyEpochVoteKey(self.our_peer_id))
+                .await
+                .unwrap_or(0);
+
+            if vote < target {
+                items.push(MintConsensusItem::KeyEpochVote(target));
+            }
+
+            if let Some(id) = self.pending_key_epoch_dkg(dbtx).await {
+                items.extend(self.key_epoch_dkg_proposal(dbtx, id).await);
+            }
+        }
+
+        // Only vote if the version supported by all peers is higher than the
+        // currently active one
+        let supported_consensus_version = *self.peer_supported_consensus_version.borrow();
+
+        if let Some(version) = supported_consensus_version
+            && active_consensus_version < version
+        {
+            items.push(MintConsensusItem::ModuleConsensusVersion(version));
+        }
+
+        items
     }
 
     async fn process_consensus_item<'a, 'b>(
         &'a self,
-        _dbtx: &mut DatabaseTransaction<'b>,
-        _consensus_item: MintConsensusItem,
-        _peer_id: PeerId,
+        dbtx: &mut DatabaseTransaction<'b>,
+        consensus_item: MintConsensusItem,
+        peer_id: PeerId,
     ) -> anyhow::Result<()> {
-        bail!("Mint does not process consensus items");
+        match consensus_item {
+            MintConsensusItem::UnixTimeVote(vote) => {
+                ensure!(
+                    P2PK_MODULE_CONSENSUS_VERSION
+                        <= self.consensus_module_consensus_version(dbtx).await,
+                    "Unix time votes are not supported yet"
+                );
+
+                let current_vote = dbtx
+                    .insert_entry(&UnixTimeVoteKey(peer_id), &vote)
+                    .await
+                    .unwrap_or(0);
+
+                ensure!(current_vote < vote, "Unix time vote is redundant");
+
+                self.time_out_key_epoch_dkg(dbtx).await;
+
+                self.update_key_epochs(dbtx).await;
+
+                Ok(())
+            }
+            MintConsensusItem::KeyEpochVote(vote) => {
+                ensure!(
+                    KEY_ROTATION_MODULE_CONSENSUS_VERSION
+                        <= self.consensus_module_consensus_version(dbtx).await,
+                    "Key rotation is not supported yet"
+                );
+
+                // The activation time of the key epoch determines when the previous one is
+                // retired, so we need to agree on the time first
+                ensure!(
+                    self.consensus_unix_time(dbtx).await != 0,
+                    "Consensus unix time is not known yet"
+                );
+
+                ensure!(
+                    vote <= active_key_epoch(dbtx).await + 1,
+                    "Key epoch vote {vote} skips a key epoch"
+                );
+
+                let current_vote = dbtx
+                    .insert_entry(&KeyEpochVoteKey(peer_id), &vote)
+                    .await
+                    .unwrap_or(0);
+
+                ensure!(current_vote < vote, "Key epoch vote is redundant");
+
+                Ok(())
+            }
+            MintConsensusItem::KeyEpochDkgCommitment(commitment) => {
+                self.process_key_epoch_dkg_commitment(dbtx, commitment, peer_id)
+                    .await
+            }
+            MintConsensusItem::KeyEpochDkgDealing(dealing) => {
+                self.process_key_epoch_dkg_dealing(dbtx, dealing, peer_id)
+                    .await
+            }
+            MintConsensusItem::KeyEpochDkgComplaint(complaint) => {
+                self.process_key_epoch_dkg_complaint(dbtx, complaint, peer_id)
+                    .await
+            }
+            MintConsensusItem::KeyEpochDkgConfirmation(id) => {
+                self.process_key_epoch_dkg_confirmation(dbtx, id, peer_id)
+                    .await
+            }
+            MintConsensusItem::ModuleConsensusVersion(version) => {
+                let current_vote = dbtx
+                    .get_value(&ConsensusVersionVoteKey(peer_id))
+                    .await
+                    .unwrap_or(ModuleConsensusVersion::new(2, 0));
+
+                ensure!(
+                    version > current_vote,
+                    "Module consensus version vote is redundant"
+                );
+
+                dbtx.insert_entry(&ConsensusVersionVoteKey(peer_id), &version)
+                    .await;
+
+                assert!(
+                    self.consensus_module_consensus_version(dbtx).await <= MODULE_CONSENSUS_VERSION,
+                    "Mint module does not support new consensus version, please upgrade the module"
+                );
+
+                Ok(())
+            }
+            MintConsensusItem::Default { variant, .. } => {
+                bail!("Received mint consensus item with unknown variant {variant}")
+            }
+        }
     }
 
     fn verify_input(&self, input: &MintInput) -> Result<(), MintInputError> {
-        let input = input.ensure_v0_ref()?;
+        let (amount, unit, note) = known_input(input)?;
 
-        let amount_key = self
-            .pub_key
-            .get(&input.amount)
-            .ok_or(MintInputError::InvalidAmountTier(input.amount))?;
-
-        if !input.note.verify(*amount_key) {
-            return Err(MintInputError::InvalidSignature);
+        // The keys of later key epochs are generated at runtime and stored in the
+        // database, so notes issued in them are verified in `process_input`
+        if input.key_epoch() != 0 {
+            return Ok(());
         }
 
-        Ok(())
+        let amount_key = self
+            .pub_keys(unit)
+            .ok_or(MintInputError::InvalidUnit(unit))?
+            .get(&amount)
+            .ok_or(MintInputError::InvalidAmountTier(amount))?;
+
+        verify_note_signature(input, note, *amount_key)
     }
 
     async fn process_input<'a, 'b, 'c>(
@@ -567,107 +1093,232 @@ async fn process_input<'a, 'b, 'c>(
         input: &'b MintInput,
         _in_point: InPoint,
     ) -> Result<InputMeta, MintInputError> {
-        let input = input.ensure_v0_ref()?;
+        let (amount, unit, note) = known_input(input)?;
 
-        debug!(target: LOG_MODULE_MINT, nonce=%(input.note.nonce), "Marking note as spent");
+        if !unit.is_bitcoin()
+            && self.consensus_module_consensus_version(dbtx).await
+                < CUSTOM_UNITS_MODULE_CONSENSUS_VERSION
+        {
+            return Err(MintInputError::CustomUnitsNotSupported);
+        }
 
-        if dbtx
-            .insert_entry(&NonceKey(input.note.nonce), &())
-            .await
-            .is_some()
+        if input.maybe_v2_ref().is_some()
+            && self.consensus_module_consensus_version(dbtx).await < P2PK_MODULE_CONSENSUS_VERSION
         {
-            return Err(MintInputError::SpentCoin);
+            return Err(MintInputError::P2pkNotSupported);
         }
 
-        dbtx.insert_new_entry(
-            &MintAuditItemKey::Redemption(NonceKey(input.note.nonce)),
-            &input.amount,
-        )
-        .await;
+        let fee_consensus = self
+            .fee_consensus(unit)
+            .ok_or(MintInputError::InvalidUnit(unit))?;
 
-        let next_index = get_recovery_count(dbtx).await;
-        dbtx.insert_new_entry(
-            &RecoveryItemKey(next_index),
-            &RecoveryItem::Input {
-                nonce: input.note.nonce.consensus_hash(),
-            },
-        )
-        .await;
+        let pub_key = match input.maybe_v2_ref() {
+            Some(input) => self.locked_input_pub_key(dbtx, input).await?,
+            None => *note.spend_key(),
+        };
 
-        let amount = input.amount;
-        let fee = self.cfg.consensus.fee_consensus.fee(amount);
+        if matches!(input, MintInput::V3(_))
+            && self.consensus_module_consensus_version(dbtx).await
+                < KEY_ROTATION_MODULE_CONSENSUS_VERSION
+        {
+            return Err(MintInputError::KeyRotationNotSupported);
+        }
 
-        calculate_mint_redeemed_ecash_metrics(dbtx, amount, fee);
+        let key_epoch = input.key_epoch();
 
-        Ok(InputMeta {
-            amount: TransactionItemAmounts {
-                amounts: Amounts::new_bitcoin(amount),
-                fees: Amounts::new_bitcoin(fee),
-            },
-            pub_key: *input.note.spend_key(),
-        })
-    }
+        if key_epoch != 0 {
+            let keys = dbtx
+                .get_value(&KeyEpochKeysKey(key_epoch))
+                .await
+                .ok_or(MintInputError::InvalidKeyEpoch(key_epoch))?;
 
-    async fn process_output<'a, 'b>(
-        &'a self,
-        dbtx: &mut DatabaseTransaction<'b>,
-        output: &'a MintOutput,
-        out_point: OutPoint,
-    ) -> Result<TransactionItemAmounts, MintOutputError> {
-        let output = output.ensure_v0_ref()?;
+            let amount_key = keys
+                .tbs_pks
+                .get(amount)
+                .ok_or(MintInputError::InvalidAmountTier(amount))?;
 
-        let amount_key = self
-            .sec_key
-            .get(output.amount)
-            .ok_or(MintOutputError::InvalidAmountTier(output.amount))?;
+            verify_note_signature(input, note, *amount_key)?;
+        }
 
-        dbtx.insert_new_entry(
-            &MintOutputOutcomeKey(out_point),
-            &MintOutputOutcome::new_v0(sign_message(output.blind_nonce.0, *amount_key)),
-        )
-        .await;
+        // Only bitcoin notes are rotated into new key epochs, so notes of custom
+        // units are never retired
+        if unit.is_bitcoin()
+            && dbtx
+                .get_value(&RetiredKeyEpochKey(key_epoch))
+                .await
+                .is_some()
+        {
+            return Err(MintInputError::RetiredKeyEpoch(key_epoch));
+        }
+
+        debug!(target: LOG_MODULE_MINT, nonce=%(note.nonce), key_epoch, "Marking note as spent");
+
+        let spent = if !unit.is_bitcoin() {
+            dbtx.insert_entry(&CustomUnitNonceKey(note.nonce), &())
+                .await
+        } else if key_epoch == 0 {
+            dbtx.insert_entry(&NonceKey(note.nonce), &()).await
+        } else {
+            dbtx.insert_entry(
+                &KeyEpochNonceKey {
+                    key_epoch,
+                    nonce: note.nonce,
+                },
+                &(),
+            )
+            .await
+        };
+
+        if spent.is_some() {
+            return Err(MintInputError::SpentCoin);
+        }
+
+        let fee = fee_consensus.fee(amount);
+
+        if unit.is_bitcoin() {
+            let audit_key = if key_epoch == 0 {
+                MintAuditItemKey::Redemption(NonceKey(note.nonce))
+            } else {
+                MintAuditItemKey::KeyEpochRedemption(KeyEpochNonceKey {
+                    key_epoch,
+                    nonce: note.nonce,
+                })
+            };
+
+            dbtx.insert_new_entry(&audit_key, &amount).await;
+
+            // Recovery items of custom units would not be understood by clients
+            // predating them, so notes of custom units can't be recovered yet
+            let next_index = get_recovery_count(dbtx).await;
+            dbtx.insert_new_entry(
+                &RecoveryItemKey(next_index),
+                &RecoveryItem::Input {
+                    nonce: note.nonce.consensus_hash(),
+                },
+            )
+            .await;
+
+            calculate_mint_redeemed_ecash_metrics(dbtx, amount, fee);
+        } else {
+            dbtx.insert_new_entry(
+                &MintAuditItemKey::UnitRedemption(unit, NonceKey(note.nonce)),
+                &amount,
+            )
+            .await;
+        }
+
+        Ok(InputMeta {
+            amount: TransactionItemAmounts {
+                amounts: Amounts::new_custom(unit, amount),
+                fees: Amounts::new_custom(unit, fee),
+            },
+            pub_key,
+        })
+    }
+
+    async fn process_output<'a, 'b>(
+        &'a self,
+        dbtx: &mut DatabaseTransaction<'b>,
+        output: &'a MintOutput,
+        out_point: OutPoint,
+    ) -> Result<TransactionItemAmounts, MintOutputError> {
+        let (amount, unit, blind_nonce) = known_output(output)?;
+
+        if !unit.is_bitcoin()
+            && self.consensus_module_consensus_version(dbtx).await
+                < CUSTOM_UNITS_MODULE_CONSENSUS_VERSION
+        {
+            return Err(MintOutputError::CustomUnitsNotSupported);
+        }
+
+        if matches!(output, MintOutput::V2(_))
+            && self.consensus_module_consensus_version(dbtx).await
+                < KEY_ROTATION_MODULE_CONSENSUS_VERSION
+        {
+            return Err(MintOutputError::KeyRotationNotSupported);
+        }
+
+        ensure_active_key_epoch(dbtx, output, unit).await?;
+
+        let key_epoch = output.key_epoch();
+
+        // All key epochs use the denominations of key epoch zero
+        let amount_key = *self
+            .sec_keys(unit)
+            .ok_or(MintOutputError::InvalidUnit(unit))?
+            .get(amount)
+            .ok_or(MintOutputError::InvalidAmountTier(amount))?;
+
+        let amount_key = if key_epoch == 0 {
+            Some(amount_key)
+        } else {
+            self.key_epoch_sec_key(dbtx, key_epoch, amount).await
+        };
 
-        dbtx.insert_new_entry(&MintAuditItemKey::Issuance(out_point), &output.amount)
+        let fee = self
+            .fee_consensus(unit)
+            .expect("Fees are configured for every unit with keys")
+            .fee(amount);
+
+        // Guardians excluded from the DKG of the key epoch have no key share, all
+        // other guardians sign the note
+        if let Some(amount_key) = amount_key {
+            dbtx.insert_new_entry(
+                &MintOutputOutcomeKey(out_point),
+                &MintOutputOutcome::new_v0(sign_message(blind_nonce.0, amount_key)),
+            )
             .await;
+        } else {
+            debug!(
+                target: LOG_MODULE_MINT,
+                key_epoch,
+                %out_point,
+                "We were excluded from the DKG of the key epoch, not signing the output"
+            );
+        }
+
+        let audit_key = if unit.is_bitcoin() {
+            MintAuditItemKey::Issuance(out_point)
+        } else {
+            MintAuditItemKey::UnitIssuance(unit, out_point)
+        };
+
+        dbtx.insert_new_entry(&audit_key, &amount).await;
 
         if dbtx
-            .insert_entry(&BlindNonceKey(output.blind_nonce), &())
+            .insert_entry(&BlindNonceKey(blind_nonce), &())
             .await
             .is_some()
         {
             // TODO: make a consensus rule against this
             warn!(
                 target: LOG_MODULE_MINT,
-                denomination = %output.amount,
-                bnonce = ?output.blind_nonce,
+                denomination = %amount,
+                bnonce = ?blind_nonce,
                 "Blind nonce already used, money was burned!"
             );
         }
 
-        let next_index = get_recovery_count(dbtx).await;
-        dbtx.insert_new_entry(
-            &RecoveryItemKey(next_index),
-            &RecoveryItem::Output {
-                amount: output.amount,
-                nonce: output.blind_nonce.0.consensus_hash(),
-            },
-        )
-        .await;
-
-        dbtx.insert_new_entry(
-            &RecoveryBlindNonceOutpointKey(output.blind_nonce),
-            &out_point,
-        )
-        .await;
+        if unit.is_bitcoin() {
+            let next_index = get_recovery_count(dbtx).await;
+            dbtx.insert_new_entry(
+                &RecoveryItemKey(next_index),
+                &RecoveryItem::Output {
+                    amount,
+                    nonce: blind_nonce.0.consensus_hash(),
+                },
+            )
+            .await;
 
-        let amount = output.amount;
-        let fee = self.cfg.consensus.fee_consensus.fee(amount);
+            dbtx.insert_new_entry(&RecoveryBlindNonceOutpointKey(blind_nonce), &out_point)
+                .await;
 
-        calculate_mint_issued_ecash_metrics(dbtx, amount, fee);
+            calculate_mint_issued_ecash_metrics(dbtx, amount, fee);
+        }
 
         Ok(TransactionItemAmounts {
-            amounts: Amounts::new_bitcoin(amount),
-            fees: Amounts::new_bitcoin(fee),
+            amounts: Amounts::new_custom(unit, amount),
+            fees: Amounts::new_custom(unit, fee),
         })
     }
 
@@ -686,13 +1337,11 @@ async fn verify_output_submission<'a, 'b>(
         output: &'a MintOutput,
         _out_point: OutPoint,
     ) -> Result<(), MintOutputError> {
-        let output = output.ensure_v0_ref()?;
+        let (_, unit, blind_nonce) = known_output(output)?;
 
-        if dbtx
-            .get_value(&BlindNonceKey(output.blind_nonce))
-            .await
-            .is_some()
-        {
+        ensure_active_key_epoch(dbtx, output, unit).await?;
+
+        if dbtx.get_value(&BlindNonceKey(blind_nonce)).await.is_some() {
             return Err(MintOutputError::BlindNonceAlreadyUsed);
         }
 
@@ -705,20 +1354,18 @@ async fn audit(
         audit: &mut Audit,
         module_instance_id: ModuleInstanceId,
     ) {
-        let mut redemptions = Amount::from_sats(0);
-        let mut issuances = Amount::from_sats(0);
+        let mut redemptions = BTreeMap::<AmountUnit, Amount>::new();
+        let mut issuances = BTreeMap::<AmountUnit, Amount>::new();
         let remove_audit_keys = dbtx
             .find_by_prefix(&MintAuditItemKeyPrefix)
             .await
             .map(|(key, amount)| {
-                match key {
-                    MintAuditItemKey::Issuance(_) | MintAuditItemKey::IssuanceTotal => {
-                        issuances += amount;
-                    }
-                    MintAuditItemKey::Redemption(_) | MintAuditItemKey::RedemptionTotal => {
-                        redemptions += amount;
-                    }
-                }
+                let totals = if key.is_issuance() {
+                    &mut issuances
+                } else {
+                    &mut redemptions
+                };
+                *totals.entry(key.unit()).or_default() += amount;
                 key
             })
             .collect::<Vec<_>>()
@@ -728,28 +1375,39 @@ async fn audit(
             dbtx.remove_entry(&key).await;
         }
 
-        dbtx.insert_entry(&MintAuditItemKey::IssuanceTotal, &issuances)
-            .await;
-        dbtx.insert_entry(&MintAuditItemKey::RedemptionTotal, &redemptions)
-            .await;
+        dbtx.insert_entry(
+            &MintAuditItemKey::IssuanceTotal,
+            &issuances.remove(&AmountUnit::BITCOIN).unwrap_or_default(),
+        )
+        .await;
+        dbtx.insert_entry(
+            &MintAuditItemKey::RedemptionTotal,
+            &redemptions.remove(&AmountUnit::BITCOIN).unwrap_or_default(),
+        )
+        .await;
+
+        for (unit, amount) in issuances {
+            dbtx.insert_entry(&MintAuditItemKey::UnitIssuanceTotal(unit), &amount)
+                .await;
+        }
+        for (unit, amount) in redemptions {
+            dbtx.insert_entry(&MintAuditItemKey::UnitRedemptionTotal(unit), &amount)
+                .await;
+        }
 
         audit
-            .add_items(
-                dbtx,
-                module_instance_id,
-                &MintAuditItemKeyPrefix,
-                |k, v| match k {
-                    MintAuditItemKey::Issuance(_) | MintAuditItemKey::IssuanceTotal => {
-                        -(v.msats as i64)
-                    }
-                    MintAuditItemKey::Redemption(_) | MintAuditItemKey::RedemptionTotal => {
-                        v.msats as i64
-                    }
-                },
-            )
+            .add_unit_items(dbtx, module_instance_id, &MintAuditItemKeyPrefix, |k, v| {
+                let milli_sat = if k.is_issuance() {
+                    -(v.msats as i64)
+                } else {
+                    v.msats as i64
+                };
+                (k.unit(), milli_sat)
+            })
             .await;
     }
 
+    #[allow(clippy::too_many_lines)]
     fn api_endpoints(&self) -> Vec<ApiEndpoint<Self>> {
         vec![
             api_endpoint! {
@@ -758,7 +1416,28 @@ fn api_endpoints(&self) -> Vec<ApiEndpoint<Self>> {
                 async |_module: &Mint, context, nonce: Nonce| -> bool {
                     let db = context.db();
                     let mut dbtx = db.begin_transaction_nc().await;
-                    Ok(dbtx.get_value(&NonceKey(nonce)).await.is_some())
+                    Ok(is_nonce_spent(&mut dbtx, nonce).await)
+                }
+            },
+            api_endpoint! {
+                NOTES_SPENT_ENDPOINT,
+                ApiVersion::new(0, 2),
+                async |_module: &Mint, context, nonces: Vec<Nonce>| -> NoteSpentBitmap {
+                    if MAX_NOTES_SPENT_BATCH_SIZE < nonces.len() {
+                        return Err(ApiError::bad_request(format!(
+                            "Can check at most {MAX_NOTES_SPENT_BATCH_SIZE} notes per request"
+                        )));
+                    }
+
+                    let db = context.db();
+                    let mut dbtx = db.begin_transaction_nc().await;
+                    let mut spent = Vec::with_capacity(nonces.len());
+
+                    for nonce in nonces {
+                        spent.push(is_nonce_spent(&mut dbtx, nonce).await);
+                    }
+
+                    Ok(NoteSpentBitmap::from_spent(spent))
                 }
             },
             api_endpoint! {
@@ -814,10 +1493,218 @@ fn api_endpoints(&self) -> Vec<ApiEndpoint<Self>> {
                     Ok(result)
                 }
             },
+            api_endpoint! {
+                KEY_EPOCH_STATUS_ENDPOINT,
+                ApiVersion::new(0, 3),
+                async |_module: &Mint, context, _params: ()| -> KeyEpochStatus {
+                    let db = context.db();
+                    let mut dbtx = db.begin_transaction_nc().await;
+                    Ok(key_epoch_status(&mut dbtx).await)
+                }
+            },
+            api_endpoint! {
+                KEY_EPOCH_KEYS_ENDPOINT,
+                ApiVersion::new(0, 4),
+                async |_module: &Mint, context, key_epoch: u64| -> Option<KeyEpochKeys> {
+                    let db = context.db();
+                    let mut dbtx = db.begin_transaction_nc().await;
+                    Ok(dbtx.get_value(&KeyEpochKeysKey(key_epoch)).await)
+                }
+            },
+            api_endpoint! {
+                ROTATE_KEY_EPOCH_ENDPOINT,
+                ApiVersion::new(0, 4),
+                async |module: &Mint, context, _params: ()| -> u64 {
+                    check_auth(context)?;
+
+                    let db = context.db();
+                    let mut dbtx = db.begin_transaction().await;
+
+                    if module.consensus_module_consensus_version(&mut dbtx.to_ref_nc()).await
+                        < KEY_ROTATION_MODULE_CONSENSUS_VERSION
+                    {
+                        return Err(ApiError::bad_request(
+                            "The federation does not support key rotation yet".to_string(),
+                        ));
+                    }
+
+                    let target = active_key_epoch(&mut dbtx.to_ref_nc()).await + 1;
+
+                    dbtx.to_ref().insert_entry(&TargetKeyEpochKey, &target).await;
+                    dbtx.commit_tx_result().await?;
+
+                    Ok(target)
+                }
+            },
+            api_endpoint! {
+                MODULE_CONSENSUS_VERSION_ENDPOINT,
+                ApiVersion::new(0, 5),
+                async |module: &Mint, context, _params: ()| -> ModuleConsensusVersion {
+                    let db = context.db();
+                    let mut dbtx = db.begin_transaction_nc().await;
+                    Ok(module.consensus_module_consensus_version(&mut dbtx).await)
+                }
+            },
+            api_endpoint! {
+                SUPPORTED_MODULE_CONSENSUS_VERSION_ENDPOINT,
+                ApiVersion::new(0, 5),
+                async |_module: &Mint, _context, _params: ()| -> ModuleConsensusVersion {
+                    Ok(MODULE_CONSENSUS_VERSION)
+                }
+            },
         ]
     }
 }
 
+/// The highest key epoch activated by consensus, new notes of
+/// [`AmountUnit::BITCOIN`] are issued in it
+async fn active_key_epoch(dbtx: &mut DatabaseTransaction<'_>) -> u64 {
+    dbtx.find_by_prefix_sorted_descending(&KeyEpochActivationPrefix)
+        .await
+        .next()
+        .await
+        .map_or(0, |(key, _)| key.0)
+}
+
+async fn key_epoch_status(dbtx: &mut DatabaseTransaction<'_>) -> KeyEpochStatus {
+    let activations = dbtx
+        .find_by_prefix(&KeyEpochActivationPrefix)
+        .await
+        .map(|(key, activated)| (key.0, activated))
+        .collect::<BTreeMap<u64, u64>>()
+        .await;
+
+    KeyEpochStatus {
+        active: activations.keys().last().copied().unwrap_or(0),
+        retirements: activations
+            .into_iter()
+            .map(|(key_epoch, activated)| {
+                (
+                    key_epoch - 1,
+                    activated.saturating_add(KEY_EPOCH_DEPRECATION_WINDOW),
+                )
+            })
+            .collect(),
+    }
+}
+
+async fn key_epoch_dkg_commitments(
+    dbtx: &mut DatabaseTransaction<'_>,
+    key_epoch: u64,
+) -> BTreeMap<PeerId, KeyEpochDkgCommitment> {
+    dbtx.find_by_prefix(&KeyEpochDkgCommitmentKeyEpochPrefix(key_epoch))
+        .await
+        .map(|(key, commitment)| (key.peer, commitment))
+        .collect()
+        .await
+}
+
+/// The dealings of the running attempt of the DKG of `key_epoch` per
+/// denomination and dealer
+async fn key_epoch_dkg_dealings(
+    dbtx: &mut DatabaseTransaction<'_>,
+    key_epoch: u64,
+) -> BTreeMap<Amount, BTreeMap<PeerId, KeyEpochDkgDealing>> {
+    let mut dealings = BTreeMap::<Amount, BTreeMap<PeerId, KeyEpochDkgDealing>>::new();
+
+    for (key, dealing) in dbtx
+        .find_by_prefix(&KeyEpochDkgDealingKeyEpochPrefix(key_epoch))
+        .await
+        .collect::<Vec<_>>()
+        .await
+    {
+        dealings
+            .entry(key.amount)
+            .or_default()
+            .insert(key.peer, dealing);
+    }
+
+    dealings
+}
+
+async fn key_epoch_dkg_attempt(
+    dbtx: &mut DatabaseTransaction<'_>,
+    key_epoch: u64,
+) -> KeyEpochDkgAttempt {
+    dbtx.get_value(&KeyEpochDkgAttemptKey(key_epoch))
+        .await
+        .unwrap_or_default()
+}
+
+async fn is_excluded_from_key_epoch_dkg(
+    dbtx: &mut DatabaseTransaction<'_>,
+    key_epoch: u64,
+    peer: PeerId,
+) -> bool {
+    dbtx.get_value(&KeyEpochDkgExclusionKey { key_epoch, peer })
+        .await
+        .is_some()
+}
+
+/// Discards all messages of an attempt of the DKG, the guardians start over
+/// with fresh polynomials and encryption keys
+async fn abort_key_epoch_dkg(dbtx: &mut DatabaseTransaction<'_>, id: KeyEpochDkgAttemptId) {
+    dbtx.remove_by_prefix(&KeyEpochDkgCommitmentKeyEpochPrefix(id.key_epoch))
+        .await;
+    dbtx.remove_by_prefix(&KeyEpochDkgDealingKeyEpochPrefix(id.key_epoch))
+        .await;
+    dbtx.remove_by_prefix(&KeyEpochDkgConfirmationKeyEpochPrefix(id.key_epoch))
+        .await;
+
+    dbtx.insert_entry(
+        &KeyEpochDkgAttemptKey(id.key_epoch),
+        &KeyEpochDkgAttempt {
+            attempt: id.attempt + 1,
+            started: None,
+        },
+    )
+    .await;
+}
+
+/// Whether a note with this nonce was spent in any key epoch that was not
+/// retired yet
+async fn is_nonce_spent(dbtx: &mut DatabaseTransaction<'_>, nonce: Nonce) -> bool {
+    if dbtx.get_value(&NonceKey(nonce)).await.is_some()
+        || dbtx.get_value(&CustomUnitNonceKey(nonce)).await.is_some()
+    {
+        return true;
+    }
+
+    for key_epoch in 1..=active_key_epoch(dbtx).await {
+        if dbtx
+            .get_value(&KeyEpochNonceKey { key_epoch, nonce })
+            .await
+            .is_some()
+        {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Bitcoin notes can only be issued in the active key epoch while notes of
+/// custom units are always issued in key epoch zero
+async fn ensure_active_key_epoch(
+    dbtx: &mut DatabaseTransaction<'_>,
+    output: &MintOutput,
+    unit: AmountUnit,
+) -> Result<(), MintOutputError> {
+    let key_epoch = output.key_epoch();
+
+    let active = if unit.is_bitcoin() {
+        active_key_epoch(dbtx).await
+    } else {
+        0
+    };
+
+    if key_epoch != active {
+        return Err(MintOutputError::InactiveKeyEpoch(key_epoch));
+    }
+
+    Ok(())
+}
+
 fn calculate_mint_issued_ecash_metrics(
     dbtx: &mut DatabaseTransaction<'_>,
     amount: Amount,
@@ -879,7 +1766,12 @@ impl Mint {
     /// * If the amount tiers for secret and public keys are inconsistent
     /// * If the pub key belonging to the secret key share is not in the pub key
     ///   list.
-    pub fn new(cfg: MintConfig) -> Mint {
+    /// * If the keys of a custom unit are inconsistent in the same way
+    pub fn new(
+        cfg: MintConfig,
+        db: Database,
+        peer_supported_consensus_version: watch::Receiver<Option<ModuleConsensusVersion>>,
+    ) -> Mint {
         assert!(cfg.private.tbs_sks.tiers().count() > 0);
 
         // The amount tiers are implicitly provided by the key sets, make sure they are
@@ -916,33 +1808,925 @@ pub fn new(cfg: MintConfig) -> Mint {
                 .collect()
         );
 
-        // TODO: the aggregate pks should become part of the MintConfigConsensus as they
-        // can be obtained by evaluating the polynomial returned by the DKG at
-        // zero
-        let aggregate_pub_keys = TieredMulti::new_aggregate_from_tiered_iter(
-            cfg.consensus.peer_tbs_pks.values().cloned(),
-        )
-        .into_iter()
-        .map(|(amt, keys)| {
-            let keys = (0_u64..)
-                .zip(keys)
-                .take(cfg.consensus.peer_tbs_pks.to_num_peers().threshold())
-                .collect();
+        let custom_unit_keys = cfg
+            .consensus
+            .custom_units
+            .iter()
+            .map(|(unit, unit_cfg)| {
+                let sec_key = cfg
+                    .private
+                    .custom_unit_tbs_sks
+                    .get(unit)
+                    .expect("Secret keys for every custom unit")
+                    .clone();
+
+                assert!(
+                    unit_cfg
+                        .peer_tbs_pks
+                        .values()
+                        .all(|pk| pk.structural_eq(&sec_key))
+                );
+
+                assert_eq!(
+                    unit_cfg.peer_tbs_pks[&our_id],
+                    sec_key
+                        .iter()
+                        .map(|(amount, sk)| (amount, derive_pk_share(sk)))
+                        .collect()
+                );
+
+                let keys = MintUnitKeys {
+                    sec_key,
+                    pub_key: aggregate_pub_keys(&unit_cfg.peer_tbs_pks),
+                };
 
-            (amt, aggregate_public_key_shares(&keys))
-        })
-        .collect();
+                (*unit, keys)
+            })
+            .collect();
 
         Mint {
             cfg: cfg.clone(),
+            db,
+            our_peer_id: our_id,
             sec_key: cfg.private.tbs_sks,
-            pub_key: aggregate_pub_keys,
+            pub_key: aggregate_pub_keys(&cfg.consensus.peer_tbs_pks),
+            custom_unit_keys,
+            key_epoch_dealer: tokio::sync::Mutex::new(None),
+            key_epoch_sec_keys: std::sync::Mutex::new(BTreeMap::new()),
+            peer_supported_consensus_version,
         }
     }
 
     pub fn pub_key(&self) -> HashMap<Amount, AggregatePublicKey> {
         self.pub_key.clone()
     }
+
+    /// Get the value of all issued but not yet redeemed e-cash per unit for UI
+    /// display
+    pub async fn outstanding_ecash_ui(&self) -> BTreeMap<AmountUnit, Amount> {
+        let mut issuances = BTreeMap::<AmountUnit, Amount>::new();
+        let mut redemptions = BTreeMap::<AmountUnit, Amount>::new();
+
+        let audit_items = self
+            .db
+            .begin_transaction_nc()
+            .await
+            .find_by_prefix(&MintAuditItemKeyPrefix)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        for (key, amount) in audit_items {
+            let totals = if key.is_issuance() {
+                &mut issuances
+            } else {
+                &mut redemptions
+            };
+            *totals.entry(key.unit()).or_default() += amount;
+        }
+
+        issuances
+            .into_iter()
+            .map(|(unit, issued)| {
+                let redeemed = redemptions.get(&unit).copied().unwrap_or_default();
+                (unit, issued.saturating_sub(redeemed))
+            })
+            .collect()
+    }
+
+    /// Get the denominations issued by the mint per unit for UI display
+    pub fn denominations_ui(&self) -> BTreeMap<AmountUnit, Vec<Amount>> {
+        std::iter::once((AmountUnit::BITCOIN, &self.sec_key))
+            .chain(
+                self.custom_unit_keys
+                    .iter()
+                    .map(|(unit, keys)| (*unit, &keys.sec_key)),
+            )
+            .map(|(unit, keys)| (unit, keys.tiers().copied().collect()))
+            .collect()
+    }
+
+    /// Get the configured fees per unit for UI display
+    pub fn fee_consensus_ui(&self) -> BTreeMap<AmountUnit, FeeConsensus> {
+        self.denominations_ui()
+            .into_keys()
+            .filter_map(|unit| Some((unit, self.fee_consensus(unit)?.clone())))
+            .collect()
+    }
+
+    /// The key that has to sign the redemption of a locked note, enforcing the
+    /// timelock of the refund path against the consensus unix time
+    async fn locked_input_pub_key(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        input: &MintInputV2,
+    ) -> Result<secp256k1::PublicKey, MintInputError> {
+        match input.spend_path {
+            P2pkSpendPath::Recipient => Ok(input.lock.recipient),
+            P2pkSpendPath::Refund => {
+                let refund = input.lock.refund.ok_or(MintInputError::NoRefundKey)?;
+
+                if self.consensus_unix_time(dbtx).await < refund.after {
+                    return Err(MintInputError::RefundTimelocked(refund.after));
+                }
+
+                Ok(refund.key)
+            }
+        }
+    }
+
+    async fn consensus_unix_time(&self, dbtx: &mut DatabaseTransaction<'_>) -> u64 {
+        let num_peers = self.cfg.consensus.peer_tbs_pks.to_num_peers();
+
+        let mut times = dbtx
+            .find_by_prefix(&UnixTimeVotePrefix)
+            .await
+            .map(|entry| entry.1)
+            .collect::<Vec<u64>>()
+            .await;
+
+        times.sort_unstable();
+
+        times.reverse();
+
+        // The unix time we select guarantees that any threshold of correct peers can
+        // advance the consensus unix time and any consensus unix time has been
+        // confirmed by a threshold of peers.
+        times.get(num_peers.threshold() - 1).copied().unwrap_or(0)
+    }
+
+    fn pub_keys(&self, unit: AmountUnit) -> Option<&HashMap<Amount, AggregatePublicKey>> {
+        if unit.is_bitcoin() {
+            Some(&self.pub_key)
+        } else {
+            self.custom_unit_keys.get(&unit).map(|keys| &keys.pub_key)
+        }
+    }
+
+    fn sec_keys(&self, unit: AmountUnit) -> Option<&Tiered<SecretKeyShare>> {
+        if unit.is_bitcoin() {
+            Some(&self.sec_key)
+        } else {
+            self.custom_unit_keys.get(&unit).map(|keys| &keys.sec_key)
+        }
+    }
+
+    /// Our secret key share for `amount` in a key epoch after zero, `None` if
+    /// we were excluded from the DKG of the key epoch
+    async fn key_epoch_sec_key(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        key_epoch: u64,
+        amount: Amount,
+    ) -> Option<SecretKeyShare> {
+        let cached = self
+            .key_epoch_sec_keys
+            .lock()
+            .expect("locking failed")
+            .get(&key_epoch)
+            .map(|sec_keys| sec_keys.get(amount).copied());
+
+        if let Some(sec_key) = cached {
+            return Some(sec_key.expect("Key epochs have keys for all denominations"));
+        }
+
+        let commitments = key_epoch_dkg_commitments(dbtx, key_epoch).await;
+
+        if !commitments.contains_key(&self.our_peer_id) {
+            return None;
+        }
+
+        let id = KeyEpochDkgAttemptId {
+            key_epoch,
+            attempt: key_epoch_dkg_attempt(dbtx, key_epoch).await.attempt,
+        };
+
+        let dealer = self
+            .stored_key_epoch_dealer(dbtx, id)
+            .await
+            .expect("We persisted our dealer before we committed to it");
+
+        // We confirmed our shares before the key epoch was activated, so failing
+        // to decrypt them means our database does not match the DKG
+        let sec_keys = dkg::decrypt_sec_keys(
+            dealer.encryption_key(),
+            self.our_peer_id,
+            &commitments,
+            &key_epoch_dkg_dealings(dbtx, key_epoch).await,
+        )
+        .unwrap_or_else(|(dealer, amount)| {
+            panic!("Failed to decrypt the key share {dealer} dealt us for {amount} in key epoch {key_epoch}")
+        });
+
+        let sec_key = *sec_keys
+            .get(amount)
+            .expect("Key epochs have keys for all denominations");
+
+        self.key_epoch_sec_keys
+            .lock()
+            .expect("locking failed")
+            .insert(key_epoch, sec_keys);
+
+        Some(sec_key)
+    }
+
+    /// The key epoch the guardians generate the keys for, if a threshold of
+    /// them voted for it
+    async fn pending_key_epoch(&self, dbtx: &mut DatabaseTransaction<'_>) -> Option<u64> {
+        let num_peers = self.cfg.consensus.peer_tbs_pks.to_num_peers();
+
+        let mut votes = dbtx
+            .find_by_prefix(&KeyEpochVotePrefix)
+            .await
+            .map(|entry| entry.1)
+            .collect::<Vec<u64>>()
+            .await;
+
+        votes.sort_unstable();
+
+        votes.reverse();
+
+        // Like the consensus unix time every generated key epoch has been voted for
+        // by a threshold of peers
+        let target = votes.get(num_peers.threshold() - 1).copied().unwrap_or(0);
+
+        let next = active_key_epoch(dbtx).await + 1;
+
+        (next <= target).then_some(next)
+    }
+
+    /// The running attempt of the DKG, if there is a pending key epoch
+    async fn pending_key_epoch_dkg(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+    ) -> Option<KeyEpochDkgAttemptId> {
+        let key_epoch = self.pending_key_epoch(dbtx).await?;
+
+        Some(KeyEpochDkgAttemptId {
+            key_epoch,
+            attempt: key_epoch_dkg_attempt(dbtx, key_epoch).await.attempt,
+        })
+    }
+
+    /// Our contribution to an attempt of the DKG. It is generated with fresh
+    /// randomness and persisted before we propose our commitment to it, so we
+    /// deal the same polynomials after a restart.
+    async fn key_epoch_dealer(&self, id: KeyEpochDkgAttemptId) -> Arc<KeyEpochDealer> {
+        let mut cached = self.key_epoch_dealer.lock().await;
+
+        if let Some(dealer) = cached.as_ref().filter(|dealer| dealer.id() == id) {
+            return dealer.clone();
+        }
+
+        let mut dbtx = self.db.begin_transaction().await;
+
+        let stored = self
+            .stored_key_epoch_dealer(&mut dbtx.to_ref_nc(), id)
+            .await;
+
+        let dealer = if let Some(dealer) = stored {
+            dealer
+        } else {
+            let dealer = KeyEpochDealer::new(
+                id,
+                self.sec_key.tiers().copied(),
+                self.cfg.consensus.peer_tbs_pks.to_num_peers().threshold(),
+            );
+
+            dbtx.insert_entry(
+                &KeyEpochDkgDealerKey(id.key_epoch),
+                &dealer.encrypt(&dkg::dealer_encryption_key(&self.cfg.private.tbs_sks)),
+            )
+            .await;
+
+            dbtx.commit_tx().await;
+
+            dealer
+        };
+
+        let dealer = Arc::new(dealer);
+
+        *cached = Some(dealer.clone());
+
+        dealer
+    }
+
+    /// Our persisted contribution to an attempt of the DKG, see
+    /// [`Mint::key_epoch_dealer`]
+    async fn stored_key_epoch_dealer(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        id: KeyEpochDkgAttemptId,
+    ) -> Option<KeyEpochDealer> {
+        let ciphertext = dbtx.get_value(&KeyEpochDkgDealerKey(id.key_epoch)).await?;
+
+        let dealer = KeyEpochDealer::decrypt(
+            &ciphertext,
+            &dkg::dealer_encryption_key(&self.cfg.private.tbs_sks),
+        )
+        .expect("Our dealer is encrypted with a key derived from our private config");
+
+        (dealer.id() == id).then_some(dealer)
+    }
+
+    /// The guardians taking part in the DKG of `key_epoch`, all guardians that
+    /// were not excluded from it
+    async fn key_epoch_dkg_participants(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        key_epoch: u64,
+    ) -> BTreeSet<PeerId> {
+        let excluded = dbtx
+            .find_by_prefix(&KeyEpochDkgExclusionKeyEpochPrefix(key_epoch))
+            .await
+            .map(|(key, ())| key.peer)
+            .collect::<BTreeSet<_>>()
+            .await;
+
+        self.cfg
+            .consensus
+            .peer_tbs_pks
+            .keys()
+            .filter(|peer| !excluded.contains(peer))
+            .copied()
+            .collect()
+    }
+
+    /// Our next messages in the DKG of `key_epoch`: first our commitment, once
+    /// every participant committed our dealings for all denominations and once
+    /// every participant dealt either our confirmation or a complaint
+    async fn key_epoch_dkg_proposal(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        id: KeyEpochDkgAttemptId,
+    ) -> Vec<MintConsensusItem> {
+        let key_epoch = id.key_epoch;
+
+        if is_excluded_from_key_epoch_dkg(dbtx, key_epoch, self.our_peer_id).await {
+            return vec![];
+        }
+
+        let dealer = self.key_epoch_dealer(id).await;
+        let commitments = key_epoch_dkg_commitments(dbtx, key_epoch).await;
+        let dealings = key_epoch_dkg_dealings(dbtx, key_epoch).await;
+
+        if !commitments.contains_key(&self.our_peer_id) {
+            return vec![MintConsensusItem::KeyEpochDkgCommitment(
+                dealer.commitment(),
+            )];
+        }
+
+        let participants = self.key_epoch_dkg_participants(dbtx, key_epoch).await;
+
+        if !commitments.keys().eq(participants.iter()) {
+            return vec![];
+        }
+
+        let encryption_keys = commitments
+            .iter()
+            .map(|(peer, commitment)| (*peer, commitment.encryption_key))
+            .collect();
+
+        let our_dealings = dealer
+            .amounts()
+            .filter(|amount| {
+                !dealings
+                    .get(amount)
+                    .is_some_and(|dealings| dealings.contains_key(&self.our_peer_id))
+            })
+            .map(|amount| {
+                MintConsensusItem::KeyEpochDkgDealing(dealer.dealing(
+                    amount,
+                    self.our_peer_id,
+                    &encryption_keys,
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        if !our_dealings.is_empty() {
+            return our_dealings;
+        }
+
+        let confirmed = dbtx
+            .get_value(&KeyEpochDkgConfirmationKey {
+                key_epoch,
+                peer: self.our_peer_id,
+            })
+            .await
+            .is_some();
+
+        if confirmed || !self.is_key_epoch_dkg_dealt(&commitments, &dealings) {
+            return vec![];
+        }
+
+        match dkg::decrypt_sec_keys(
+            dealer.encryption_key(),
+            self.our_peer_id,
+            &commitments,
+            &dealings,
+        ) {
+            Ok(..) => vec![MintConsensusItem::KeyEpochDkgConfirmation(id)],
+            Err((dealer_peer, amount)) => {
+                warn!(
+                    target: LOG_MODULE_MINT,
+                    key_epoch,
+                    dealer = %dealer_peer,
+                    %amount,
+                    "Guardian dealt us an invalid key share, complaining"
+                );
+
+                vec![MintConsensusItem::KeyEpochDkgComplaint(
+                    dealer.complaint(dealer_peer, amount),
+                )]
+            }
+        }
+    }
+
+    /// Whether every participant dealt its shares for every denomination
+    fn is_key_epoch_dkg_dealt(
+        &self,
+        commitments: &BTreeMap<PeerId, KeyEpochDkgCommitment>,
+        dealings: &BTreeMap<Amount, BTreeMap<PeerId, KeyEpochDkgDealing>>,
+    ) -> bool {
+        dealings.values().map(BTreeMap::len).sum::<usize>()
+            == commitments.len() * self.sec_key.count_tiers()
+    }
+
+    /// Common checks of all DKG messages: the message belongs to the running
+    /// attempt and, unless it is a commitment, comes from a participant
+    async fn ensure_key_epoch_dkg_message(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        id: KeyEpochDkgAttemptId,
+        peer_id: PeerId,
+    ) -> anyhow::Result<BTreeMap<PeerId, KeyEpochDkgCommitment>> {
+        ensure!(
+            KEY_ROTATION_MODULE_CONSENSUS_VERSION
+                <= self.consensus_module_consensus_version(dbtx).await,
+            "Key rotation is not supported yet"
+        );
+
+        ensure!(
+            self.pending_key_epoch_dkg(dbtx).await == Some(id),
+            "No DKG is running for key epoch {} attempt {}",
+            id.key_epoch,
+            id.attempt
+        );
+
+        ensure!(
+            !is_excluded_from_key_epoch_dkg(dbtx, id.key_epoch, peer_id).await,
+            "Guardian {peer_id} was excluded from the DKG of key epoch {}",
+            id.key_epoch
+        );
+
+        Ok(key_epoch_dkg_commitments(dbtx, id.key_epoch).await)
+    }
+
+    async fn process_key_epoch_dkg_commitment(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        commitment: KeyEpochDkgCommitment,
+        peer_id: PeerId,
+    ) -> anyhow::Result<()> {
+        let key_epoch = commitment.id.key_epoch;
+
+        self.ensure_key_epoch_dkg_message(dbtx, commitment.id, peer_id)
+            .await?;
+
+        ensure!(
+            commitment.hashes.tiers().eq(self.sec_key.tiers()),
+            "DKG commitment does not match the denominations"
+        );
+
+        let key = KeyEpochDkgCommitmentKey {
+            key_epoch,
+            peer: peer_id,
+        };
+
+        ensure!(
+            dbtx.insert_entry(&key, &commitment).await.is_none(),
+            "DKG commitment is redundant"
+        );
+
+        let mut attempt = key_epoch_dkg_attempt(dbtx, key_epoch).await;
+
+        if attempt.started.is_none() {
+            attempt.started = Some(self.consensus_unix_time(dbtx).await);
+
+            dbtx.insert_entry(&KeyEpochDkgAttemptKey(key_epoch), &attempt)
+                .await;
+        }
+
+        Ok(())
+    }
+
+    async fn process_key_epoch_dkg_dealing(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        dealing: KeyEpochDkgDealing,
+        peer_id: PeerId,
+    ) -> anyhow::Result<()> {
+        let commitments = self
+            .ensure_key_epoch_dkg_message(dbtx, dealing.id, peer_id)
+            .await?;
+
+        let num_peers = self.cfg.consensus.peer_tbs_pks.to_num_peers();
+
+        let participants = self
+            .key_epoch_dkg_participants(dbtx, dealing.id.key_epoch)
+            .await;
+
+        ensure!(
+            commitments.keys().eq(participants.iter()),
+            "Not every participant committed to its DKG dealings yet"
+        );
+
+        ensure!(
+            commitments
+                .get(&peer_id)
+                .and_then(|commitment| commitment.hashes.get(dealing.amount))
+                == Some(&dealing.commitment.consensus_hash_sha256()),
+            "DKG dealing does not match the commitment"
+        );
+
+        ensure!(
+            dealing.commitment.len() == num_peers.threshold(),
+            "DKG dealing has the wrong degree"
+        );
+
+        ensure!(
+            dealing.encrypted_shares.keys().eq(commitments.keys()),
+            "DKG dealing does not contain a share for every participant"
+        );
+
+        let key = KeyEpochDkgDealingKey {
+            key_epoch: dealing.id.key_epoch,
+            peer: peer_id,
+            amount: dealing.amount,
+        };
+
+        ensure!(
+            dbtx.insert_entry(&key, &dealing).await.is_none(),
+            "DKG dealing is redundant"
+        );
+
+        Ok(())
+    }
+
+    async fn process_key_epoch_dkg_complaint(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        complaint: KeyEpochDkgComplaint,
+        peer_id: PeerId,
+    ) -> anyhow::Result<()> {
+        let key_epoch = complaint.id.key_epoch;
+
+        let commitments = self
+            .ensure_key_epoch_dkg_message(dbtx, complaint.id, peer_id)
+            .await?;
+
+        let dealing = dbtx
+            .get_value(&KeyEpochDkgDealingKey {
+                key_epoch,
+                peer: complaint.dealer,
+                amount: complaint.amount,
+            })
+            .await
+            .ok_or_else(|| anyhow::anyhow!("DKG complaint is about a missing dealing"))?;
+
+        ensure!(
+            dkg::verify_complaint(&complaint, peer_id, &commitments, &dealing),
+            "DKG complaint is invalid"
+        );
+
+        warn!(
+            target: LOG_MODULE_MINT,
+            key_epoch,
+            dealer = %complaint.dealer,
+            complainer = %peer_id,
+            "Guardian dealt an invalid key share, excluding it from the DKG"
+        );
+
+        dbtx.insert_entry(
+            &KeyEpochDkgExclusionKey {
+                key_epoch,
+                peer: complaint.dealer,
+            },
+            &(),
+        )
+        .await;
+
+        abort_key_epoch_dkg(dbtx, complaint.id).await;
+
+        Ok(())
+    }
+
+    async fn process_key_epoch_dkg_confirmation(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        id: KeyEpochDkgAttemptId,
+        peer_id: PeerId,
+    ) -> anyhow::Result<()> {
+        let key_epoch = id.key_epoch;
+
+        let commitments = self.ensure_key_epoch_dkg_message(dbtx, id, peer_id).await?;
+
+        ensure!(
+            commitments.contains_key(&peer_id),
+            "Guardian {peer_id} does not take part in the DKG"
+        );
+
+        let dealings = key_epoch_dkg_dealings(dbtx, key_epoch).await;
+
+        ensure!(
+            self.is_key_epoch_dkg_dealt(&commitments, &dealings),
+            "Not every participant dealt its shares yet"
+        );
+
+        let key = KeyEpochDkgConfirmationKey {
+            key_epoch,
+            peer: peer_id,
+        };
+
+        ensure!(
+            dbtx.insert_entry(&key, &()).await.is_none(),
+            "DKG confirmation is redundant"
+        );
+
+        let confirmations = dbtx
+            .find_by_prefix(&KeyEpochDkgConfirmationKeyEpochPrefix(key_epoch))
+            .await
+            .count()
+            .await;
+
+        if confirmations == commitments.len() {
+            self.activate_key_epoch(dbtx, key_epoch, &dealings).await;
+        }
+
+        Ok(())
+    }
+
+    /// Aborts the running attempt of the DKG if it did not complete within
+    /// [`KEY_EPOCH_DKG_TIMEOUT`], excluding the participants that stalled it
+    async fn time_out_key_epoch_dkg(&self, dbtx: &mut DatabaseTransaction<'_>) {
+        let Some(id) = self.pending_key_epoch_dkg(dbtx).await else {
+            return;
+        };
+
+        let Some(started) = key_epoch_dkg_attempt(dbtx, id.key_epoch).await.started else {
+            return;
+        };
+
+        if self.consensus_unix_time(dbtx).await < started.saturating_add(KEY_EPOCH_DKG_TIMEOUT) {
+            return;
+        }
+
+        let participants = self.key_epoch_dkg_participants(dbtx, id.key_epoch).await;
+        let commitments = key_epoch_dkg_commitments(dbtx, id.key_epoch).await;
+        let dealings = key_epoch_dkg_dealings(dbtx, id.key_epoch).await;
+
+        let confirmations = dbtx
+            .find_by_prefix(&KeyEpochDkgConfirmationKeyEpochPrefix(id.key_epoch))
+            .await
+            .map(|(key, ())| key.peer)
+            .collect::<BTreeSet<_>>()
+            .await;
+
+        // Until every participant committed we wait for the commitments, then for the
+        // dealers and afterwards for the confirmations
+        let stalling = if !commitments.keys().eq(participants.iter()) {
+            participants
+                .iter()
+                .filter(|peer| !commitments.contains_key(peer))
+                .copied()
+                .collect::<Vec<_>>()
+        } else if self.is_key_epoch_dkg_dealt(&commitments, &dealings) {
+            commitments
+                .keys()
+                .filter(|peer| !confirmations.contains(peer))
+                .copied()
+                .collect::<Vec<_>>()
+        } else {
+            commitments
+                .keys()
+                .filter(|peer| {
+                    self.sec_key.tiers().any(|amount| {
+                        !dealings
+                            .get(amount)
+                            .is_some_and(|dealings| dealings.contains_key(peer))
+                    })
+                })
+                .copied()
+                .collect()
+        };
+
+        let num_peers = self.cfg.consensus.peer_tbs_pks.to_num_peers();
+
+        warn!(
+            target: LOG_MODULE_MINT,
+            key_epoch = id.key_epoch,
+            attempt = id.attempt,
+            ?stalling,
+            "DKG of the key epoch timed out, starting a new attempt"
+        );
+
+        // Guardians may just have been offline for a while, so we only exclude them
+        // as long as a threshold of guardians remains to run the DKG
+        if num_peers.threshold() <= participants.len() - stalling.len() {
+            for peer in stalling {
+                dbtx.insert_entry(
+                    &KeyEpochDkgExclusionKey {
+                        key_epoch: id.key_epoch,
+                        peer,
+                    },
+                    &(),
+                )
+                .await;
+            }
+        }
+
+        abort_key_epoch_dkg(dbtx, id).await;
+    }
+
+    /// Completes the DKG of `key_epoch` once every participant confirmed its
+    /// shares, all new bitcoin notes are issued in the key epoch from then on
+    async fn activate_key_epoch(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        key_epoch: u64,
+        dealings: &BTreeMap<Amount, BTreeMap<PeerId, KeyEpochDkgDealing>>,
+    ) {
+        info!(target: LOG_MODULE_MINT, key_epoch, "Activating key epoch");
+
+        dbtx.insert_new_entry(&KeyEpochKeysKey(key_epoch), &dkg::key_epoch_keys(dealings))
+            .await;
+
+        let now = self.consensus_unix_time(dbtx).await;
+
+        dbtx.insert_new_entry(&KeyEpochActivationKey(key_epoch), &now)
+            .await;
+
+        // The commitments and dealings are kept to derive our key shares from
+        dbtx.remove_by_prefix(&KeyEpochDkgConfirmationKeyEpochPrefix(key_epoch))
+            .await;
+        dbtx.remove_by_prefix(&KeyEpochDkgExclusionKeyEpochPrefix(key_epoch))
+            .await;
+
+        self.update_key_epochs(dbtx).await;
+    }
+
+    /// Retires the bitcoin key epochs whose deprecation window has passed.
+    /// Notes of retired key epochs can't be redeemed anymore, so their
+    /// spent nonces are pruned and the note spent endpoints report them as
+    /// unspent.
+    async fn update_key_epochs(&self, dbtx: &mut DatabaseTransaction<'_>) {
+        let now = self.consensus_unix_time(dbtx).await;
+
+        let activations = dbtx
+            .find_by_prefix(&KeyEpochActivationPrefix)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        for (KeyEpochActivationKey(key_epoch), activated) in activations {
+            let deprecated = key_epoch - 1;
+
+            if now < activated.saturating_add(KEY_EPOCH_DEPRECATION_WINDOW)
+                || dbtx
+                    .get_value(&RetiredKeyEpochKey(deprecated))
+                    .await
+                    .is_some()
+            {
+                continue;
+            }
+
+            info!(target: LOG_MODULE_MINT, key_epoch = deprecated, "Retiring key epoch");
+
+            // We don't sign notes of the retired key epoch anymore
+            if deprecated == 0 {
+                dbtx.remove_by_prefix(&NonceKeyPrefix).await;
+            } else {
+                dbtx.remove_by_prefix(&KeyEpochNonceKeyEpochPrefix(deprecated))
+                    .await;
+                dbtx.remove_by_prefix(&KeyEpochDkgCommitmentKeyEpochPrefix(deprecated))
+                    .await;
+                dbtx.remove_by_prefix(&KeyEpochDkgDealingKeyEpochPrefix(deprecated))
+                    .await;
+                dbtx.remove_entry(&KeyEpochDkgAttemptKey(deprecated)).await;
+                dbtx.remove_entry(&KeyEpochDkgDealerKey(deprecated)).await;
+            }
+
+            dbtx.insert_new_entry(&RetiredKeyEpochKey(deprecated), &())
+                .await;
+        }
+    }
+
+    /// The module consensus version voted for by all but at most `max_evil`
+    /// guardians
+    async fn consensus_module_consensus_version(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+    ) -> ModuleConsensusVersion {
+        let num_peers = self.cfg.consensus.peer_tbs_pks.to_num_peers();
+
+        let mut versions = dbtx
+            .find_by_prefix(&ConsensusVersionVotePrefix)
+            .await
+            .map(|entry| entry.1)
+            .collect::<Vec<ModuleConsensusVersion>>()
+            .await;
+
+        while versions.len() < num_peers.total() {
+            versions.push(ModuleConsensusVersion::new(2, 0));
+        }
+
+        assert_eq!(versions.len(), num_peers.total());
+
+        versions.sort_unstable();
+
+        versions[num_peers.max_evil()]
+    }
+
+    fn spawn_peer_supported_consensus_version_task(
+        api_client: DynModuleApi,
+        task_group: &TaskGroup,
+        our_peer_id: PeerId,
+    ) -> watch::Receiver<Option<ModuleConsensusVersion>> {
+        let (sender, receiver) = watch::channel(None);
+        task_group.spawn_cancellable("fetch-peer-consensus-versions", async move {
+            loop {
+                let request_futures = api_client.all_peers().iter().filter_map(|&peer| {
+                    if peer == our_peer_id {
+                        return None;
+                    }
+
+                    let api_client_inner = api_client.clone();
+                    Some(async move {
+                        api_client_inner
+                            .request_single_peer::<ModuleConsensusVersion>(
+                                SUPPORTED_MODULE_CONSENSUS_VERSION_ENDPOINT.to_owned(),
+                                ApiRequestErased::default(),
+                                peer,
+                            )
+                            .await
+                            .inspect_err(|err| warn!(
+                                target: LOG_MODULE_MINT,
+                                %peer,
+                                err = %err.fmt_compact(),
+                                "Failed to fetch consensus version from peer"
+                            ))
+                            .ok()
+                    })
+                });
+
+                let sorted_consensus_versions = join_all(request_futures)
+                    .await
+                    .into_iter()
+                    .flatten()
+                    .chain(std::iter::once(MODULE_CONSENSUS_VERSION))
+                    .sorted()
+                    .collect::<Vec<_>>();
+
+                let all_peers_supported_version =
+                    if sorted_consensus_versions.len() == api_client.all_peers().len() {
+                        sorted_consensus_versions.first().copied()
+                    } else {
+                        trace!(
+                            target: LOG_MODULE_MINT,
+                            ?sorted_consensus_versions,
+                            "Not all peers have reported their consensus version yet"
+                        );
+                        None
+                    };
+
+                #[allow(clippy::disallowed_methods)]
+                if sender.send(all_peers_supported_version).is_err() {
+                    warn!(target: LOG_MODULE_MINT, "Failed to send consensus version to watch channel, stopping task");
+                    break;
+                }
+
+                if is_running_in_test_env() {
+                    // Even in tests we don't want to spam the federation with requests about it
+                    sleep(Duration::from_secs(5)).await;
+                } else {
+                    sleep(Duration::from_secs(600)).await;
+                }
+            }
+        });
+        receiver
+    }
+
+    fn fee_consensus(&self, unit: AmountUnit) -> Option<&FeeConsensus> {
+        if unit.is_bitcoin() {
+            Some(&self.cfg.consensus.fee_consensus)
+        } else {
+            self.cfg
+                .consensus
+                .custom_units
+                .get(&unit)
+                .map(|unit_cfg| &unit_cfg.fee_consensus)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1,16 +1,32 @@
+use std::collections::{BTreeMap, BTreeSet};
+
 use assert_matches::assert_matches;
-use fedimint_core::config::{ClientModuleConfig, ServerModuleConfig};
-use fedimint_core::db::Database;
+use fedimint_core::config::{ClientModuleConfig, ServerModuleConfig, TypedServerModuleConfig};
 use fedimint_core::db::mem_impl::MemDatabase;
-use fedimint_core::module::ModuleConsensusVersion;
+use fedimint_core::db::{Database, DatabaseTransaction, IDatabaseTransactionOpsCoreTyped};
 use fedimint_core::module::registry::ModuleRegistry;
-use fedimint_core::{Amount, BitcoinHash, InPoint, PeerId, TransactionId, secp256k1};
-use fedimint_mint_common::config::FeeConsensus;
-use fedimint_mint_common::{MintInput, Nonce, Note};
+use fedimint_core::module::{AmountUnit, Amounts, ModuleConsensusVersion};
+use fedimint_core::{Amount, BitcoinHash, InPoint, OutPoint, PeerId, TransactionId, secp256k1};
+use fedimint_mint_common::config::{FeeConsensus, MintUnitConfigConsensus};
+use fedimint_mint_common::{
+    BlindNonce, CUSTOM_UNITS_MODULE_CONSENSUS_VERSION, KEY_EPOCH_DEPRECATION_WINDOW,
+    KEY_ROTATION_MODULE_CONSENSUS_VERSION, KeyEpochInput, MintConsensusItem, MintInput,
+    MintInputError, MintOutput, MintOutputError, Nonce, Note, P2PK_MODULE_CONSENSUS_VERSION,
+    P2pkLock, P2pkRefund, P2pkSpendPath,
+};
 use fedimint_server_core::{ConfigGenModuleArgs, ServerModule, ServerModuleInit};
+use futures::StreamExt;
 use tbs::blind_message;
+use tokio::sync::watch;
 
-use crate::{Mint, MintConfig, MintConfigConsensus, MintConfigPrivate, MintInit};
+use crate::db::{
+    KeyEpochActivationKey, KeyEpochDkgAttemptKey, KeyEpochKeysKey, MintAuditItemKeyPrefix,
+    MintOutputOutcomeKey, TargetKeyEpochKey,
+};
+use crate::{
+    Mint, MintConfig, MintConfigConsensus, MintConfigPrivate, MintInit, active_key_epoch,
+    dealer_unit_keygen, gen_denominations, is_nonce_spent, key_epoch_status,
+};
 
 const MINTS: u16 = 5;
 
@@ -19,6 +35,9 @@ fn build_configs() -> (Vec<ServerModuleConfig>, ClientModuleConfig) {
     let args = ConfigGenModuleArgs {
         network: bitcoin::Network::Regtest,
         disable_base_fees: false,
+        mint_custom_units: BTreeSet::new(),
+        wallet_peg_out_batch_window: None,
+        wallet_taproot_peg_in: false,
     };
     let mint_cfg = MintInit.trusted_dealer_gen(&peers, &args);
     let client_cfg = ClientModuleConfig::from_typed(
@@ -34,30 +53,65 @@ fn build_configs() -> (Vec<ServerModuleConfig>, ClientModuleConfig) {
     (mint_cfg.into_values().collect(), client_cfg)
 }
 
+fn test_db() -> Database {
+    Database::new(MemDatabase::new(), ModuleRegistry::default())
+}
+
+fn new_mint(server_cfg: &ServerModuleConfig) -> Mint {
+    Mint::new(
+        server_cfg.to_typed().unwrap(),
+        test_db(),
+        watch::channel(None).1,
+    )
+}
+
+/// Has a threshold of guardians vote for the given module consensus version
+async fn activate_consensus_version(
+    mint: &Mint,
+    dbtx: &mut DatabaseTransaction<'_>,
+    version: ModuleConsensusVersion,
+) {
+    for peer in (0..MINTS).map(PeerId::from).take(4) {
+        mint.process_consensus_item(
+            &mut dbtx.to_ref_nc(),
+            MintConsensusItem::ModuleConsensusVersion(version),
+            peer,
+        )
+        .await
+        .expect("Vote is not redundant");
+    }
+}
+
 #[test_log::test]
 #[should_panic(expected = "Own key not found among pub keys.")]
 fn test_new_panic_without_own_pub_key() {
     let (mint_server_cfg1, _) = build_configs();
     let (mint_server_cfg2, _) = build_configs();
 
-    Mint::new(MintConfig {
-        consensus: MintConfigConsensus {
-            peer_tbs_pks: mint_server_cfg2[0]
-                .to_typed::<MintConfig>()
-                .unwrap()
-                .consensus
-                .peer_tbs_pks,
-            fee_consensus: FeeConsensus::new(1000).expect("Relative fee is within range"),
-            max_notes_per_denomination: 0,
-        },
-        private: MintConfigPrivate {
-            tbs_sks: mint_server_cfg1[0]
-                .to_typed::<MintConfig>()
-                .unwrap()
-                .private
-                .tbs_sks,
+    Mint::new(
+        MintConfig {
+            consensus: MintConfigConsensus {
+                peer_tbs_pks: mint_server_cfg2[0]
+                    .to_typed::<MintConfig>()
+                    .unwrap()
+                    .consensus
+                    .peer_tbs_pks,
+                fee_consensus: FeeConsensus::new(1000).expect("Relative fee is within range"),
+                max_notes_per_denomination: 0,
+                custom_units: BTreeMap::new(),
+            },
+            private: MintConfigPrivate {
+                tbs_sks: mint_server_cfg1[0]
+                    .to_typed::<MintConfig>()
+                    .unwrap()
+                    .private
+                    .tbs_sks,
+                custom_unit_tbs_sks: BTreeMap::new(),
+            },
         },
-    });
+        test_db(),
+        watch::channel(None).1,
+    );
 }
 
 fn issue_note(
@@ -66,7 +120,28 @@ fn issue_note(
 ) -> (secp256k1::Keypair, Note) {
     let note_key = secp256k1::Keypair::new(secp256k1::SECP256K1, &mut rand::thread_rng());
     let nonce = Nonce(note_key.public_key());
-    let message = nonce.to_message();
+    let signature = issue_signature(server_cfgs, denomination, nonce.to_message());
+
+    (note_key, Note { nonce, signature })
+}
+
+fn issue_locked_note(
+    server_cfgs: &[ServerModuleConfig],
+    denomination: Amount,
+    lock: &P2pkLock,
+) -> Note {
+    let note_key = secp256k1::Keypair::new(secp256k1::SECP256K1, &mut rand::thread_rng());
+    let nonce = Nonce(note_key.public_key());
+    let signature = issue_signature(server_cfgs, denomination, lock.message(nonce));
+
+    Note { nonce, signature }
+}
+
+fn issue_signature(
+    server_cfgs: &[ServerModuleConfig],
+    denomination: Amount,
+    message: tbs::Message,
+) -> tbs::Signature {
     let blinding_key = tbs::BlindingKey::random();
     let blind_msg = blind_message(message, blinding_key);
 
@@ -85,15 +160,14 @@ fn issue_note(
         .collect();
 
     let blind_signature = tbs::aggregate_signature_shares(&bsig_shares);
-    let signature = tbs::unblind_signature(blinding_key, blind_signature);
 
-    (note_key, Note { nonce, signature })
+    tbs::unblind_signature(blinding_key, blind_signature)
 }
 
 #[test_log::test(tokio::test)]
 async fn test_detect_double_spends() {
     let (mint_server_cfg, _) = build_configs();
-    let mint = Mint::new(mint_server_cfg[0].to_typed().unwrap());
+    let mint = new_mint(&mint_server_cfg[0]);
     let (_, tiered) = mint
         .cfg
         .consensus
@@ -132,3 +206,782 @@ async fn test_detect_double_spends() {
         Err(_)
     );
 }
+
+#[test_log::test]
+fn test_reject_unknown_units() {
+    let (mint_server_cfg, _) = build_configs();
+    let mint = new_mint(&mint_server_cfg[0]);
+    let denomination = Amount::from_msats(1);
+    let (_, note) = issue_note(&mint_server_cfg, denomination);
+
+    mint.verify_input(&MintInput::new_v0(denomination, note))
+        .expect("Bitcoin notes are valid");
+
+    // Bitcoin notes have to use the legacy input version
+    assert_matches!(
+        mint.verify_input(&MintInput::new_v1(denomination, AmountUnit::BITCOIN, note)),
+        Err(MintInputError::InvalidUnit(_))
+    );
+
+    assert_matches!(
+        mint.verify_input(&MintInput::new_v1(
+            denomination,
+            AmountUnit::new_custom(1),
+            note
+        )),
+        Err(MintInputError::InvalidUnit(_))
+    );
+}
+
+#[test_log::test(tokio::test)]
+async fn test_locked_notes() {
+    let (mint_server_cfg, _) = build_configs();
+    let mint = new_mint(&mint_server_cfg[0]);
+    let denomination = Amount::from_msats(1);
+    let recipient = secp256k1::Keypair::new(secp256k1::SECP256K1, &mut rand::thread_rng());
+    let refund = secp256k1::Keypair::new(secp256k1::SECP256K1, &mut rand::thread_rng());
+    let lock = P2pkLock {
+        recipient: recipient.public_key(),
+        refund: Some(P2pkRefund {
+            key: refund.public_key(),
+            after: 1_000,
+        }),
+    };
+    let note = issue_locked_note(&mint_server_cfg, denomination, &lock);
+
+    // A locked note can't be spent as a bearer note or under a different lock
+    assert_matches!(
+        mint.verify_input(&MintInput::new_v0(denomination, note)),
+        Err(MintInputError::InvalidSignature)
+    );
+    assert_matches!(
+        mint.verify_input(&MintInput::new_v2(
+            denomination,
+            AmountUnit::BITCOIN,
+            note,
+            P2pkLock {
+                refund: None,
+                ..lock
+            },
+            P2pkSpendPath::Recipient,
+        )),
+        Err(MintInputError::InvalidSignature)
+    );
+
+    let refund_input = MintInput::new_v2(
+        denomination,
+        AmountUnit::BITCOIN,
+        note,
+        lock,
+        P2pkSpendPath::Refund,
+    );
+    mint.verify_input(&refund_input)
+        .expect("Locked note is valid");
+
+    let db = Database::new(MemDatabase::new(), ModuleRegistry::default());
+    let mut dbtx = db.begin_transaction_nc().await;
+    let in_point = InPoint {
+        txid: TransactionId::all_zeros(),
+        in_idx: 0,
+    };
+
+    // Locked notes are only redeemed once all guardians upgraded
+    assert_matches!(
+        mint.process_input(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            &refund_input,
+            in_point,
+        )
+        .await,
+        Err(MintInputError::P2pkNotSupported)
+    );
+
+    activate_consensus_version(
+        &mint,
+        &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+        P2PK_MODULE_CONSENSUS_VERSION,
+    )
+    .await;
+
+    // The refund path is only available once a threshold of guardians agree that
+    // the timelock expired
+    assert_matches!(
+        mint.process_input(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            &refund_input,
+            in_point,
+        )
+        .await,
+        Err(MintInputError::RefundTimelocked(1_000))
+    );
+
+    for peer in (0..MINTS).map(PeerId::from).take(4) {
+        mint.process_consensus_item(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            MintConsensusItem::UnixTimeVote(1_000),
+            peer,
+        )
+        .await
+        .expect("Vote is not redundant");
+    }
+
+    let meta = mint
+        .process_input(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            &refund_input,
+            in_point,
+        )
+        .await
+        .expect("Refund of expired locked note works");
+    assert_eq!(meta.pub_key, refund.public_key());
+
+    // The note was spent by the refund
+    assert_matches!(
+        mint.process_input(
+            &mut dbtx.to_ref_with_prefix_module_id(42).0.into_nc(),
+            &MintInput::new_v2(
+                denomination,
+                AmountUnit::BITCOIN,
+                note,
+                lock,
+                P2pkSpendPath::Recipient,
+            ),
+            in_point,
+        )
+        .await,
+        Err(MintInputError::SpentCoin)
+    );
+}
+
+/// Adds a custom unit to the configs, returning configs that sign with the
+/// keys of the custom unit as well
+fn add_custom_unit(
+    server_cfgs: &[ServerModuleConfig],
+    unit: AmountUnit,
+) -> (Vec<ServerModuleConfig>, Vec<ServerModuleConfig>) {
+    let peers = (0..MINTS).map(PeerId::from).collect::<Vec<_>>();
+    let (peer_tbs_pks, peer_tbs_sks) = dealer_unit_keygen(&peers, &gen_denominations());
+
+    let configs = peers
+        .iter()
+        .zip(server_cfgs)
+        .map(|(peer, cfg)| {
+            let mut cfg = cfg.to_typed::<MintConfig>().unwrap();
+
+            cfg.consensus.custom_units.insert(
+                unit,
+                MintUnitConfigConsensus {
+                    peer_tbs_pks: peer_tbs_pks.clone(),
+                    fee_consensus: FeeConsensus::zero(),
+                },
+            );
+            cfg.private
+                .custom_unit_tbs_sks
+                .insert(unit, peer_tbs_sks[peer].clone());
+
+            cfg
+        })
+        .collect::<Vec<_>>();
+
+    let unit_signing_cfgs = configs
+        .iter()
+        .map(|cfg| {
+            let mut cfg = cfg.clone();
+            cfg.private.tbs_sks = cfg.private.custom_unit_tbs_sks[&unit].clone();
+            cfg.to_erased()
+        })
+        .collect();
+
+    (
+        configs
+            .into_iter()
+            .map(TypedServerModuleConfig::to_erased)
+            .collect(),
+        unit_signing_cfgs,
+    )
+}
+
+#[test_log::test(tokio::test)]
+async fn test_custom_units_require_consensus_version() {
+    let unit = AmountUnit::new_custom(1);
+    let (mint_server_cfg, _) = build_configs();
+    let (mint_server_cfg, unit_signing_cfg) = add_custom_unit(&mint_server_cfg, unit);
+    let mint = new_mint(&mint_server_cfg[0]);
+    let denomination = Amount::from_msats(1);
+    let (_, note) = issue_note(&unit_signing_cfg, denomination);
+
+    let input = MintInput::new_for_unit(denomination, unit, note);
+    let output = MintOutput::new_for_unit(
+        denomination,
+        unit,
+        BlindNonce(blind_message(
+            note.nonce.to_message(),
+            tbs::BlindingKey::random(),
+        )),
+    );
+
+    mint.verify_input(&input)
+        .expect("Note of the custom unit is valid");
+
+    let db = test_db();
+    let mut dbtx = db.begin_transaction_nc().await;
+    let mut module_dbtx = dbtx.to_ref_with_prefix_module_id(42).0.into_nc();
+    let in_point = InPoint {
+        txid: TransactionId::all_zeros(),
+        in_idx: 0,
+    };
+    let out_point = OutPoint {
+        txid: TransactionId::all_zeros(),
+        out_idx: 0,
+    };
+
+    // Custom units are only issued and redeemed once all guardians upgraded
+    assert_matches!(
+        mint.process_input(&mut module_dbtx.to_ref_nc(), &input, in_point)
+            .await,
+        Err(MintInputError::CustomUnitsNotSupported)
+    );
+    assert_matches!(
+        mint.process_output(&mut module_dbtx.to_ref_nc(), &output, out_point)
+            .await,
+        Err(MintOutputError::CustomUnitsNotSupported)
+    );
+
+    activate_consensus_version(
+        &mint,
+        &mut module_dbtx.to_ref_nc(),
+        CUSTOM_UNITS_MODULE_CONSENSUS_VERSION,
+    )
+    .await;
+
+    let meta = mint
+        .process_input(&mut module_dbtx.to_ref_nc(), &input, in_point)
+        .await
+        .expect("Spend of a custom unit note works");
+    assert_eq!(meta.amount.amounts, Amounts::new_custom(unit, denomination));
+
+    mint.process_output(&mut module_dbtx.to_ref_nc(), &output, out_point)
+        .await
+        .expect("Issuance of a custom unit note works");
+}
+
+/// Runs consensus among the mints, processing every item proposed by a mint
+/// on all of them, until every mint activated the given key epoch. The
+/// proposed items pass through `network`, which may drop or alter them. The
+/// unix time votes advance by ten minutes per round, so attempts of the DKG
+/// stalled by a guardian time out.
+async fn run_key_epoch_dkg(
+    mints: &[Mint],
+    key_epoch: u64,
+    network: impl Fn(PeerId, MintConsensusItem) -> Option<MintConsensusItem>,
+) {
+    for round in 0..30 {
+        let mut items = vec![];
+
+        for (peer, mint) in (0..MINTS).map(PeerId::from).zip(mints) {
+            let mut dbtx = mint.db.begin_transaction_nc().await;
+
+            for item in mint.consensus_proposal(&mut dbtx).await {
+                let item = match item {
+                    MintConsensusItem::UnixTimeVote(time) => {
+                        MintConsensusItem::UnixTimeVote(time + round * 600)
+                    }
+                    item => item,
+                };
+
+                if let Some(item) = network(peer, item) {
+                    items.push((peer, item));
+                }
+            }
+        }
+
+        for mint in mints {
+            for (peer, item) in &items {
+                // Like in consensus the changes of rejected items are discarded
+                let mut dbtx = mint.db.begin_transaction().await;
+
+                if mint
+                    .process_consensus_item(&mut dbtx.to_ref_nc(), item.clone(), *peer)
+                    .await
+                    .is_ok()
+                {
+                    dbtx.commit_tx().await;
+                }
+            }
+        }
+
+        let mut activated = true;
+
+        for mint in mints {
+            let mut dbtx = mint.db.begin_transaction_nc().await;
+            activated &= active_key_epoch(&mut dbtx).await == key_epoch;
+        }
+
+        if activated {
+            return;
+        }
+    }
+
+    panic!("Key epoch {key_epoch} was not activated");
+}
+
+/// Issues a note by having the first threshold of the given mints that hold a
+/// key share of the key epoch of the output sign it
+async fn issue_note_via_outputs(mints: &[Mint], output: impl Fn(BlindNonce) -> MintOutput) -> Note {
+    let note_key = secp256k1::Keypair::new(secp256k1::SECP256K1, &mut rand::thread_rng());
+
+    issue_nonce_via_outputs(mints, Nonce(note_key.public_key()), output).await
+}
+
+/// Like [`issue_note_via_outputs`], but signs the given nonce
+async fn issue_nonce_via_outputs(
+    mints: &[Mint],
+    nonce: Nonce,
+    output: impl Fn(BlindNonce) -> MintOutput,
+) -> Note {
+    let blinding_key = tbs::BlindingKey::random();
+    let output = output(BlindNonce(blind_message(nonce.to_message(), blinding_key)));
+    let out_point = OutPoint {
+        txid: TransactionId::all_zeros(),
+        out_idx: 0,
+    };
+
+    let mut shares = BTreeMap::new();
+
+    for mint in mints {
+        let mut dbtx = mint.db.begin_transaction_nc().await;
+
+        mint.process_output(&mut dbtx, &output, out_point)
+            .await
+            .expect("Issuance works");
+
+        if let Some(outcome) = dbtx.get_value(&MintOutputOutcomeKey(out_point)).await
+            && shares.len() < 4
+        {
+            shares.insert(
+                mint.our_peer_id.to_usize() as u64,
+                outcome.ensure_v0_ref().unwrap().0,
+            );
+        }
+    }
+
+    assert_eq!(shares.len(), 4, "A threshold of mints signed the output");
+
+    let signature = tbs::unblind_signature(blinding_key, tbs::aggregate_signature_shares(&shares));
+
+    Note { nonce, signature }
+}
+
+#[test_log::test(tokio::test)]
+async fn test_key_rotation() {
+    let unit = AmountUnit::new_custom(1);
+    let (mint_server_cfg, _) = build_configs();
+    let (mint_server_cfg, unit_signing_cfg) = add_custom_unit(&mint_server_cfg, unit);
+    let mints = mint_server_cfg.iter().map(new_mint).collect::<Vec<_>>();
+    let mint = &mints[0];
+    let denomination = Amount::from_msats(1);
+    let (_, old_note) = issue_note(&mint_server_cfg, denomination);
+    let (_, retired_note) = issue_note(&mint_server_cfg, denomination);
+    let (_, unit_note) = issue_note(&unit_signing_cfg, denomination);
+    let in_point = InPoint {
+        txid: TransactionId::all_zeros(),
+        in_idx: 0,
+    };
+    let out_point = OutPoint {
+        txid: TransactionId::all_zeros(),
+        out_idx: 0,
+    };
+    let blind_nonce = |note: Note| {
+        BlindNonce(blind_message(
+            note.nonce.to_message(),
+            tbs::BlindingKey::random(),
+        ))
+    };
+
+    for mint in &mints {
+        let mut dbtx = mint.db.begin_transaction().await;
+        activate_consensus_version(mint, &mut dbtx.to_ref_nc(), P2PK_MODULE_CONSENSUS_VERSION)
+            .await;
+        dbtx.commit_tx().await;
+    }
+
+    let mut dbtx = mint.db.begin_transaction_nc().await;
+
+    // Notes are only issued and redeemed in later key epochs once all guardians
+    // upgraded
+    assert_matches!(
+        mint.process_input(
+            &mut dbtx,
+            &MintInput::new_for_key_epoch(1, KeyEpochInput::new_v0(denomination, old_note)),
+            in_point,
+        )
+        .await,
+        Err(MintInputError::KeyRotationNotSupported)
+    );
+    assert_matches!(
+        mint.process_output(
+            &mut dbtx,
+            &MintOutput::new_for_key_epoch(denomination, 1, blind_nonce(old_note)),
+            out_point,
+        )
+        .await,
+        Err(MintOutputError::KeyRotationNotSupported)
+    );
+    assert!(
+        mint.process_consensus_item(
+            &mut dbtx,
+            MintConsensusItem::KeyEpochVote(1),
+            PeerId::from(0)
+        )
+        .await
+        .is_err()
+    );
+
+    drop(dbtx);
+
+    for mint in &mints {
+        let mut dbtx = mint.db.begin_transaction().await;
+        activate_consensus_version(
+            mint,
+            &mut dbtx.to_ref_nc(),
+            KEY_ROTATION_MODULE_CONSENSUS_VERSION,
+        )
+        .await;
+        dbtx.commit_tx().await;
+    }
+
+    // A threshold of guardian operators asks for a new key epoch, the last
+    // guardian joins the DKG anyways
+    for mint in mints.iter().take(4) {
+        let mut dbtx = mint.db.begin_transaction().await;
+        dbtx.insert_entry(&TargetKeyEpochKey, &1).await;
+        dbtx.commit_tx().await;
+    }
+
+    run_key_epoch_dkg(&mints, 1, |_, item| Some(item)).await;
+
+    let mut dbtx = mint.db.begin_transaction_nc().await;
+    let keys = dbtx.get_value(&KeyEpochKeysKey(1)).await.unwrap();
+    assert_eq!(keys.peer_tbs_pks.len(), MINTS as usize);
+
+    for other in &mints[1..] {
+        let mut other_dbtx = other.db.begin_transaction_nc().await;
+        assert_eq!(
+            other_dbtx.get_value(&KeyEpochKeysKey(1)).await,
+            Some(keys.clone())
+        );
+    }
+
+    // Notes of the new key epoch are signed with the generated keys, any
+    // threshold of the guardians can issue them
+    let new_note = issue_note_via_outputs(&mints, |blind_nonce| {
+        MintOutput::new_for_key_epoch(denomination, 1, blind_nonce)
+    })
+    .await;
+    assert!(new_note.verify(*keys.tbs_pks.get(denomination).unwrap()));
+
+    let other_note = issue_note_via_outputs(&mints[1..], |blind_nonce| {
+        MintOutput::new_for_key_epoch(denomination, 1, blind_nonce)
+    })
+    .await;
+    assert!(other_note.verify(*keys.tbs_pks.get(denomination).unwrap()));
+
+    let new_input = MintInput::new_for_key_epoch(1, KeyEpochInput::new_v0(denomination, new_note));
+    mint.process_input(&mut dbtx, &new_input, in_point)
+        .await
+        .expect("Spend of a note of the active key epoch works");
+    assert!(is_nonce_spent(&mut dbtx, new_note.nonce).await);
+
+    // Notes signed with the keys of key epoch zero are not valid in key epoch one
+    assert_matches!(
+        mint.process_input(
+            &mut dbtx,
+            &MintInput::new_for_key_epoch(1, KeyEpochInput::new_v0(denomination, old_note)),
+            in_point,
+        )
+        .await,
+        Err(MintInputError::InvalidSignature)
+    );
+
+    // New bitcoin notes are only issued in the active key epoch, notes of custom
+    // units remain in key epoch zero
+    assert_matches!(
+        mint.process_output(
+            &mut dbtx,
+            &MintOutput::new_v0(denomination, blind_nonce(old_note)),
+            out_point,
+        )
+        .await,
+        Err(MintOutputError::InactiveKeyEpoch(0))
+    );
+    mint.process_output(
+        &mut dbtx,
+        &MintOutput::new_for_unit(denomination, unit, blind_nonce(unit_note)),
+        out_point,
+    )
+    .await
+    .expect("Issuance of a custom unit note works");
+
+    // Notes of the deprecated key epoch can be redeemed until it is retired
+    mint.process_input(
+        &mut dbtx,
+        &MintInput::new_v0(denomination, old_note),
+        in_point,
+    )
+    .await
+    .expect("Spend of a note of the deprecated key epoch works");
+
+    let activated = dbtx.get_value(&KeyEpochActivationKey(1)).await.unwrap();
+
+    for peer in (0..MINTS).map(PeerId::from).take(4) {
+        mint.process_consensus_item(
+            &mut dbtx,
+            MintConsensusItem::UnixTimeVote(activated + KEY_EPOCH_DEPRECATION_WINDOW),
+            peer,
+        )
+        .await
+        .expect("Vote is not redundant");
+    }
+
+    assert_matches!(
+        mint.process_input(
+            &mut dbtx,
+            &MintInput::new_v0(denomination, retired_note),
+            in_point,
+        )
+        .await,
+        Err(MintInputError::RetiredKeyEpoch(0))
+    );
+
+    // The spent nonces of the retired key epoch are pruned, its notes are
+    // rejected regardless
+    assert!(!is_nonce_spent(&mut dbtx, old_note.nonce).await);
+    assert!(!is_nonce_spent(&mut dbtx, retired_note.nonce).await);
+    assert_matches!(
+        mint.process_input(
+            &mut dbtx,
+            &MintInput::new_v0(denomination, old_note),
+            in_point,
+        )
+        .await,
+        Err(MintInputError::RetiredKeyEpoch(0))
+    );
+
+    // The nonces of the active key epoch are kept
+    assert!(is_nonce_spent(&mut dbtx, new_note.nonce).await);
+
+    // Notes of custom units are never retired
+    assert!(!is_nonce_spent(&mut dbtx, unit_note.nonce).await);
+    mint.process_input(
+        &mut dbtx,
+        &MintInput::new_for_unit(denomination, unit, unit_note),
+        in_point,
+    )
+    .await
+    .expect("Spend of a custom unit note works after the rotation");
+    assert!(is_nonce_spent(&mut dbtx, unit_note.nonce).await);
+    assert_matches!(
+        mint.process_input(
+            &mut dbtx,
+            &MintInput::new_for_unit(denomination, unit, unit_note),
+            in_point,
+        )
+        .await,
+        Err(MintInputError::SpentCoin)
+    );
+
+    let status = key_epoch_status(&mut dbtx).await;
+    assert_eq!(status.active, 1);
+    assert_eq!(
+        status.retirements,
+        BTreeMap::from([(0, activated + KEY_EPOCH_DEPRECATION_WINDOW)])
+    );
+}
+
+/// Activates key rotation on all mints and has a threshold of them ask for key
+/// epoch one
+async fn request_key_epoch_one(mints: &[Mint]) {
+    for mint in mints {
+        let mut dbtx = mint.db.begin_transaction().await;
+        activate_consensus_version(
+            mint,
+            &mut dbtx.to_ref_nc(),
+            KEY_ROTATION_MODULE_CONSENSUS_VERSION,
+        )
+        .await;
+        dbtx.commit_tx().await;
+    }
+
+    for mint in mints.iter().take(4) {
+        let mut dbtx = mint.db.begin_transaction().await;
+        dbtx.insert_entry(&TargetKeyEpochKey, &1).await;
+        dbtx.commit_tx().await;
+    }
+}
+
+#[test_log::test(tokio::test)]
+async fn test_key_epoch_dkg_with_offline_guardian() {
+    let (mint_server_cfg, _) = build_configs();
+    let mints = mint_server_cfg.iter().map(new_mint).collect::<Vec<_>>();
+    let denomination = Amount::from_msats(1);
+    let offline = PeerId::from(MINTS - 1);
+
+    request_key_epoch_one(&mints).await;
+
+    // The first attempt of the DKG times out waiting for the offline guardian,
+    // the remaining guardians exclude it and run the DKG without it
+    run_key_epoch_dkg(&mints, 1, |peer, item| (peer != offline).then_some(item)).await;
+
+    let mut dbtx = mints[0].db.begin_transaction_nc().await;
+    let keys = dbtx.get_value(&KeyEpochKeysKey(1)).await.unwrap();
+    assert!(!keys.peer_tbs_pks.contains_key(&offline));
+    assert_eq!(keys.peer_tbs_pks.len(), MINTS as usize - 1);
+
+    // The excluded guardian processes the output but has no key share to sign it
+    let note = issue_note_via_outputs(&mints, |blind_nonce| {
+        MintOutput::new_for_key_epoch(denomination, 1, blind_nonce)
+    })
+    .await;
+    assert!(note.verify(*keys.tbs_pks.get(denomination).unwrap()));
+}
+
+#[test_log::test(tokio::test)]
+async fn test_key_epoch_dkg_complaint() {
+    let (mint_server_cfg, _) = build_configs();
+    let mints = mint_server_cfg.iter().map(new_mint).collect::<Vec<_>>();
+    let denomination = Amount::from_msats(1);
+    let cheater = PeerId::from(1);
+    let victim = PeerId::from(0);
+
+    request_key_epoch_one(&mints).await;
+
+    // The cheater deals the victim invalid shares in the first attempt, the
+    // victim's complaint aborts it and excludes the cheater from the next one
+    run_key_epoch_dkg(&mints, 1, |peer, item| match item {
+        MintConsensusItem::KeyEpochDkgDealing(mut dealing)
+            if peer == cheater && dealing.id.attempt == 0 =>
+        {
+            dealing.encrypted_shares.get_mut(&victim).unwrap()[0] ^= 1;
+
+            Some(MintConsensusItem::KeyEpochDkgDealing(dealing))
+        }
+        item => Some(item),
+    })
+    .await;
+
+    let mut dbtx = mints[0].db.begin_transaction_nc().await;
+    let keys = dbtx.get_value(&KeyEpochKeysKey(1)).await.unwrap();
+    assert!(!keys.peer_tbs_pks.contains_key(&cheater));
+    assert_eq!(
+        dbtx.get_value(&KeyEpochDkgAttemptKey(1))
+            .await
+            .unwrap()
+            .attempt,
+        1
+    );
+
+    let note = issue_note_via_outputs(&mints, |blind_nonce| {
+        MintOutput::new_for_key_epoch(denomination, 1, blind_nonce)
+    })
+    .await;
+    assert!(note.verify(*keys.tbs_pks.get(denomination).unwrap()));
+}
+
+#[test_log::test(tokio::test)]
+async fn test_redeem_nonce_in_two_key_epochs() {
+    let (mint_server_cfg, _) = build_configs();
+    let mints = mint_server_cfg.iter().map(new_mint).collect::<Vec<_>>();
+    let mint = &mints[0];
+    let denomination = Amount::from_msats(1);
+    let in_point = InPoint {
+        txid: TransactionId::all_zeros(),
+        in_idx: 0,
+    };
+
+    request_key_epoch_one(&mints).await;
+    run_key_epoch_dkg(&mints, 1, |_, item| Some(item)).await;
+
+    // A note of key epoch zero and one of key epoch one with the same nonce
+    let (_, old_note) = issue_note(&mint_server_cfg, denomination);
+    let new_note = issue_nonce_via_outputs(&mints, old_note.nonce, |blind_nonce| {
+        MintOutput::new_for_key_epoch(denomination, 1, blind_nonce)
+    })
+    .await;
+    let new_input = MintInput::new_for_key_epoch(1, KeyEpochInput::new_v0(denomination, new_note));
+
+    let mut dbtx = mint.db.begin_transaction_nc().await;
+
+    mint.process_input(
+        &mut dbtx,
+        &MintInput::new_v0(denomination, old_note),
+        in_point,
+    )
+    .await
+    .expect("Spend of a note of key epoch zero works");
+    mint.process_input(&mut dbtx, &new_input, in_point)
+        .await
+        .expect("The nonce is spent once per key epoch");
+    assert_matches!(
+        mint.process_input(&mut dbtx, &new_input, in_point).await,
+        Err(MintInputError::SpentCoin)
+    );
+
+    // Both redemptions are audited
+    let redeemed = dbtx
+        .find_by_prefix(&MintAuditItemKeyPrefix)
+        .await
+        .filter(|(key, _)| std::future::ready(!key.is_issuance()))
+        .map(|(_, amount)| amount)
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(redeemed, vec![denomination, denomination]);
+}
+
+/// The commitment a mint proposes in the DKG of key epoch one
+async fn commitment_proposal(mint: &Mint) -> MintConsensusItem {
+    let mut dbtx = mint.db.begin_transaction_nc().await;
+
+    mint.consensus_proposal(&mut dbtx)
+        .await
+        .into_iter()
+        .find(|item| matches!(item, MintConsensusItem::KeyEpochDkgCommitment(_)))
+        .expect("Mint commits to its DKG dealings")
+}
+
+#[test_log::test(tokio::test)]
+async fn test_key_epoch_dealer_survives_restart() {
+    let (mint_server_cfg, _) = build_configs();
+    let mints = mint_server_cfg.iter().map(new_mint).collect::<Vec<_>>();
+
+    request_key_epoch_one(&mints).await;
+
+    for mint in &mints[..2] {
+        let mut dbtx = mint.db.begin_transaction().await;
+
+        for item in [
+            MintConsensusItem::UnixTimeVote(1_000),
+            MintConsensusItem::KeyEpochVote(1),
+        ] {
+            for peer in (0..MINTS).map(PeerId::from).take(4) {
+                mint.process_consensus_item(&mut dbtx.to_ref_nc(), item.clone(), peer)
+                    .await
+                    .expect("Vote is not redundant");
+            }
+        }
+
+        dbtx.commit_tx().await;
+    }
+
+    let commitment = commitment_proposal(&mints[0]).await;
+
+    // A restarted mint commits to the polynomials it persisted before
+    let restarted = Mint::new(
+        mint_server_cfg[0].to_typed().unwrap(),
+        mints[0].db.clone(),
+        watch::channel(None).1,
+    );
+    assert_eq!(commitment_proposal(&restarted).await, commitment);
+
+    // Other guardians deal their own random polynomials
+    assert_ne!(commitment_proposal(&mints[1]).await, commitment);
+}
@@ -9,6 +9,7 @@
 use fedimint_core::core::OperationId;
 use fedimint_core::db::IDatabaseTransactionOpsCoreTyped;
 use fedimint_core::module::{AmountUnit, Amounts};
+use fedimint_core::setup_code::ModuleSetupParams;
 use fedimint_core::task::sleep_in_test;
 use fedimint_core::util::NextOrPending;
 use fedimint_core::{Amount, TieredMulti, sats, secp256k1};
@@ -16,7 +17,10 @@
 use fedimint_dummy_server::DummyInit;
 use fedimint_logging::LOG_TEST;
 use fedimint_mint_client::api::MintFederationApi;
-use fedimint_mint_client::client_db::{NextECashNoteIndexKey, NoteKey};
+use fedimint_mint_client::client_db::{
+    NextECashNoteIndexKey, NextECashUnitNoteIndexKey, NextECashUnitNoteIndexKeyPrefix, NoteKey,
+};
+use fedimint_mint_client::payment_request::{EcashPayment, EcashPaymentTarget};
 use fedimint_mint_client::{
     MintClientInit, MintClientModule, Note, OOBNotes, ReissueExternalNotesState,
     SelectNotesWithAtleastAmount, SelectNotesWithExactAmount, SpendOOBState,
@@ -25,6 +29,7 @@
 use fedimint_mint_common::{MintInput, MintInputV0, Nonce};
 use fedimint_mint_server::MintInit;
 use fedimint_testing::fixtures::{Fixtures, TIMEOUT};
+use fedimint_testing_core::config::API_AUTH;
 use futures::StreamExt;
 use secp256k1::Keypair;
 use serde::{Deserialize, Serialize};
@@ -33,11 +38,54 @@
 const EXPECTED_MAXIMUM_FEE: Amount = Amount::from_sats(20);
 
 fn fixtures() -> Fixtures {
-    let fixtures = Fixtures::new_primary(MintClientInit, MintInit);
+    let fixtures = Fixtures::new_primary(MintClientInit::default(), MintInit);
 
     fixtures.with_module(DummyClientInit, DummyInit)
 }
 
+/// Like [`issue_ecash`], but for e-cash of a custom `unit`. Retries until the
+/// guardians activated the module consensus version supporting custom units.
+async fn issue_unit_ecash(
+    client: &ClientHandleArc,
+    amount: Amount,
+    unit: AmountUnit,
+) -> anyhow::Result<()> {
+    let dummy_module = client.get_first_module::<DummyClientModule>()?;
+
+    loop {
+        let operation_id = OperationId::new_random();
+
+        let txid = client
+            .finalize_and_submit_transaction(
+                operation_id,
+                "Issue custom unit e-cash via dummy module",
+                |_| (),
+                TransactionBuilder::new().with_inputs(dummy_module.create_unit_input(amount, unit)),
+            )
+            .await?
+            .txid();
+
+        match client
+            .transaction_updates(operation_id)
+            .await
+            .await_tx_accepted(txid)
+            .await
+        {
+            Ok(()) => break,
+            Err(err) => {
+                info!(target: LOG_TEST, %err, "Custom units not activated yet, retrying");
+                sleep_in_test("waiting for custom units", Duration::from_secs(1)).await;
+            }
+        }
+    }
+
+    let mut balance_changes = client.subscribe_balance_changes(unit).await;
+
+    while balance_changes.ok().await? < amount {}
+
+    Ok(())
+}
+
 /// Create real e-cash by submitting a DummyInput transaction.
 /// The dummy server accepts any public key, so this creates "free money"
 /// that gets converted to e-cash as change by the mint module.
@@ -155,6 +203,49 @@ async fn sends_ecash_out_of_band() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn sends_p2pk_locked_ecash() -> anyhow::Result<()> {
+    let fed = fixtures().new_fed_degraded().await;
+    let (client1, client2) = fed.two_clients().await;
+    issue_ecash(&client1, sats(1000)).await?;
+
+    let client1_mint = client1.get_first_module::<MintClientModule>()?;
+    let client2_mint = client2.get_first_module::<MintClientModule>()?;
+
+    let op = client1_mint
+        .send_p2pk_notes(
+            sats(500),
+            client2_mint.p2pk_public_key(),
+            Some(Duration::from_secs(60 * 60 * 24)),
+            (),
+        )
+        .await?;
+    let notes = client1_mint.await_p2pk_notes(op).await?;
+    assert_eq!(notes.total_amount(), sats(500));
+
+    // The sender can't redeem the notes before the refund timelock expired
+    let op = client1_mint.redeem_p2pk_notes(notes.clone(), ()).await?;
+    let mut sub1 = client1_mint
+        .subscribe_reissue_external_notes(op)
+        .await?
+        .into_stream();
+    assert_eq!(sub1.ok().await?, ReissueExternalNotesState::Created);
+    assert_matches!(sub1.ok().await?, ReissueExternalNotesState::Failed(_));
+
+    let op = client2_mint.redeem_p2pk_notes(notes, ()).await?;
+    let mut sub2 = client2_mint
+        .subscribe_reissue_external_notes(op)
+        .await?
+        .into_stream();
+    assert_eq!(sub2.ok().await?, ReissueExternalNotesState::Created);
+    assert_eq!(sub2.ok().await?, ReissueExternalNotesState::Issuing);
+    assert_eq!(sub2.ok().await?, ReissueExternalNotesState::Done);
+
+    assert!(client2.get_balance_for_btc().await? >= sats(500).saturating_sub(EXPECTED_MAXIMUM_FEE));
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn blind_nonce_index() -> anyhow::Result<()> {
     // Give client initial balance
@@ -770,6 +861,201 @@ async fn repair_wallet() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn holds_balances_in_several_units() -> anyhow::Result<()> {
+    let unit_a = AmountUnit::new_custom(1);
+    let unit_b = AmountUnit::new_custom(2);
+
+    let fed = fixtures()
+        .new_fed_builder(0)
+        .disable_mint_fees()
+        .module_params(ModuleSetupParams {
+            mint_custom_units: [unit_a, unit_b].into(),
+            ..Default::default()
+        })
+        .build()
+        .await;
+    let client = fed.new_client().await;
+
+    issue_ecash(&client, sats(1000)).await?;
+    issue_unit_ecash(&client, sats(500), unit_a).await?;
+    issue_unit_ecash(&client, sats(250), unit_b).await?;
+
+    assert_eq!(client.get_balance_for_btc().await?, sats(1000));
+    assert_eq!(client.get_balance_for_unit(unit_a).await?, sats(500));
+    assert_eq!(client.get_balance_for_unit(unit_b).await?, sats(250));
+
+    // Repairing the wallet also repairs the notes of custom units
+    let client_mint = client.get_first_module::<MintClientModule>()?;
+
+    let mut dbtx = client_mint.db.begin_transaction().await;
+    let (index_key, next_index) = dbtx
+        .find_by_prefix(&NextECashUnitNoteIndexKeyPrefix)
+        .await
+        .next()
+        .await
+        .expect("We issued notes of custom units");
+    dbtx.insert_entry(
+        &NextECashUnitNoteIndexKey(index_key.0, index_key.1),
+        &(next_index - 1),
+    )
+    .await;
+    dbtx.commit_tx().await;
+
+    let repair_summary = client_mint
+        .try_repair_wallet(100)
+        .await
+        .expect("Repair should succeed");
+
+    assert!(repair_summary.spent_notes.is_empty());
+    assert!(repair_summary.used_indices.is_empty());
+    assert!(repair_summary.spent_unit_notes.is_empty());
+    assert_eq!(
+        repair_summary.used_unit_indices[&index_key.0].get(index_key.1),
+        1,
+        "One used index of the custom unit should be found"
+    );
+
+    assert_eq!(client.get_balance_for_btc().await?, sats(1000));
+    assert_eq!(client.get_balance_for_unit(unit_a).await?, sats(500));
+    assert_eq!(client.get_balance_for_unit(unit_b).await?, sats(250));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn receives_ecash_payment_request_once() -> anyhow::Result<()> {
+    let fed = fixtures().new_fed_degraded().await;
+    let (payer, recipient) = fed.two_clients().await;
+    issue_ecash(&payer, sats(1000)).await?;
+
+    let payer_mint = payer.get_first_module::<MintClientModule>()?;
+    let recipient_mint = recipient.get_first_module::<MintClientModule>()?;
+
+    let request = recipient_mint
+        .create_ecash_payment_request(
+            sats(250),
+            "test".to_string(),
+            Duration::from_secs(60 * 60),
+            EcashPaymentTarget::Http {
+                url: "https://example.com/pay".parse()?,
+            },
+            (),
+        )
+        .await?;
+
+    let (_, notes) = payer_mint
+        .spend_notes_with_selector(&SelectNotesWithAtleastAmount, sats(250), TIMEOUT, false, ())
+        .await?;
+    let payment = EcashPayment {
+        request_id: request.id,
+        notes,
+    };
+    let paid = payment.notes.total_amount();
+
+    let operation_id = recipient_mint
+        .receive_ecash_payment(payment.clone())
+        .await?;
+    let mut sub = recipient_mint
+        .subscribe_reissue_external_notes(operation_id)
+        .await?
+        .into_stream();
+    assert_eq!(sub.ok().await?, ReissueExternalNotesState::Created);
+    assert_eq!(sub.ok().await?, ReissueExternalNotesState::Issuing);
+    assert_eq!(sub.ok().await?, ReissueExternalNotesState::Done);
+
+    // Neither the same nor another payment is received for the request again
+    assert!(recipient_mint.receive_ecash_payment(payment).await.is_err());
+
+    let (_, notes) = payer_mint
+        .spend_notes_with_selector(&SelectNotesWithAtleastAmount, sats(250), TIMEOUT, false, ())
+        .await?;
+    assert!(
+        recipient_mint
+            .receive_ecash_payment(EcashPayment {
+                request_id: request.id,
+                notes,
+            })
+            .await
+            .is_err()
+    );
+
+    // Only the first payment was reissued
+    assert!(recipient.get_balance_for_btc().await? <= paid);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reissues_notes_of_rotated_key_epoch() -> anyhow::Result<()> {
+    let fed = fixtures()
+        .new_fed_builder(0)
+        .disable_mint_fees()
+        .build()
+        .await;
+    let (client, receiver) = fed.two_clients().await;
+    let client_mint = client.get_first_module::<MintClientModule>()?;
+
+    issue_ecash(&client, sats(1000)).await?;
+
+    // The guardians vote for the key rotation once they activated the module
+    // consensus version supporting it
+    for peer_id in fed.online_peer_ids() {
+        let admin_client = fed.new_admin_client(peer_id, API_AUTH.clone()).await;
+        let admin_mint = admin_client.get_first_module::<MintClientModule>()?;
+
+        loop {
+            match admin_mint.rotate_key_epoch().await {
+                Ok(key_epoch) => {
+                    assert_eq!(key_epoch, 1);
+                    break;
+                }
+                Err(err) => {
+                    info!(target: LOG_TEST, %err, "Key rotation not supported yet, retrying");
+                    sleep_in_test("waiting for key rotation", Duration::from_secs(1)).await;
+                }
+            }
+        }
+    }
+
+    while client_mint.api.fetch_key_epoch_status().await?.active < 1 {
+        sleep_in_test("waiting for key epoch DKG", Duration::from_secs(1)).await;
+    }
+
+    let mut balance_changes = client.subscribe_balance_changes(AmountUnit::BITCOIN).await;
+
+    assert!(
+        client_mint.try_reissue_deprecated_notes().await?.is_some(),
+        "Notes of key epoch zero should be reissued"
+    );
+
+    while balance_changes.ok().await? < sats(1000) {}
+
+    assert_eq!(client.get_balance_for_btc().await?, sats(1000));
+    assert!(
+        client_mint.try_reissue_deprecated_notes().await?.is_none(),
+        "All notes should be of the active key epoch"
+    );
+
+    // The reissued notes can be spent
+    let (_, notes) = client_mint
+        .spend_notes_with_selector(&SelectNotesWithAtleastAmount, sats(750), TIMEOUT, false, ())
+        .await?;
+    let receiver_mint = receiver.get_first_module::<MintClientModule>()?;
+    let operation_id = receiver_mint.reissue_external_notes(notes, ()).await?;
+    let mut sub = receiver_mint
+        .subscribe_reissue_external_notes(operation_id)
+        .await?
+        .into_stream();
+    assert_eq!(sub.ok().await?, ReissueExternalNotesState::Created);
+    assert_eq!(sub.ok().await?, ReissueExternalNotesState::Issuing);
+    assert_eq!(sub.ok().await?, ReissueExternalNotesState::Done);
+
+    assert_eq!(receiver.get_balance_for_btc().await?, sats(750));
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod fedimint_migration_tests {
     use std::collections::BTreeMap;
@@ -1039,6 +1325,28 @@ async fn test_server_db_migrations() -> anyhow::Result<()> {
                         // New prefix for slice-based recovery, no migration
                         // needed
                     }
+                    DbKeyPrefix::UnixTimeVote => {
+                        // New prefix for locked notes, no migration needed
+                    }
+                    DbKeyPrefix::KeyEpochVote
+                    | DbKeyPrefix::KeyEpochActivation
+                    | DbKeyPrefix::KeyEpochNonce
+                    | DbKeyPrefix::RetiredKeyEpoch
+                    | DbKeyPrefix::CustomUnitNonce
+                    | DbKeyPrefix::TargetKeyEpoch
+                    | DbKeyPrefix::KeyEpochDkgAttempt
+                    | DbKeyPrefix::KeyEpochDkgCommitment
+                    | DbKeyPrefix::KeyEpochDkgDealing
+                    | DbKeyPrefix::KeyEpochKeys
+                    | DbKeyPrefix::KeyEpochDkgConfirmation
+                    | DbKeyPrefix::KeyEpochDkgExclusion
+                    | DbKeyPrefix::KeyEpochDkgDealer => {
+                        // New prefixes for key rotation, no migration needed
+                    }
+                    DbKeyPrefix::ConsensusVersionVote => {
+                        // New prefix for consensus version votes, no migration
+                        // needed
+                    }
                 }
             }
 
@@ -1061,7 +1369,7 @@ async fn snapshot_client_db_migrations() -> anyhow::Result<()> {
     async fn test_client_db_migrations() -> anyhow::Result<()> {
         let _ = TracingSetup::default().init();
 
-        let module = DynClientModuleInit::from(MintClientInit);
+        let module = DynClientModuleInit::from(MintClientInit::default());
         validate_migrations_client::<_, _, MintClientModule>(
             module,
             "mint-client",
@@ -1126,6 +1434,20 @@ async fn test_client_db_migrations() -> anyhow::Result<()> {
                             info!("Validated RecoveryFinalized");
                         }
                         fedimint_mint_client::client_db::DbKeyPrefix::ReusedNoteIndices => {}
+                        fedimint_mint_client::client_db::DbKeyPrefix::UnitNote
+                        | fedimint_mint_client::client_db::DbKeyPrefix::NextECashUnitNoteIndex => {
+                            // New prefixes for custom units, no migration
+                            // needed
+                        }
+                        fedimint_mint_client::client_db::DbKeyPrefix::KeyEpochStatus
+                        | fedimint_mint_client::client_db::DbKeyPrefix::KeyEpochKeys => {
+                            // New prefixes for key rotation, no migration
+                            // needed
+                        }
+                        fedimint_mint_client::client_db::DbKeyPrefix::ReceivedEcashPaymentRequest => {
+                            // New prefix for payment requests, no migration
+                            // needed
+                        }
                         fedimint_mint_client::client_db::DbKeyPrefix::RecoveryStateV2 => {
                             // New prefix for slice-based recovery, no migration
                             // needed
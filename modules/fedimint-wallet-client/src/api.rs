@@ -7,12 +7,13 @@
 use fedimint_core::envs::BitcoinRpcConfig;
 use fedimint_core::module::{ApiAuth, ApiRequestErased, ModuleConsensusVersion};
 use fedimint_core::task::{MaybeSend, MaybeSync};
-use fedimint_core::{NumPeersExt, PeerId, apply, async_trait_maybe_send};
+use fedimint_core::{NumPeersExt, OutPoint, PeerId, apply, async_trait_maybe_send};
 use fedimint_wallet_common::endpoint_constants::{
-    ACTIVATE_CONSENSUS_VERSION_VOTING_ENDPOINT, BITCOIN_KIND_ENDPOINT, BITCOIN_RPC_CONFIG_ENDPOINT,
+    ACTIVATE_CONSENSUS_VERSION_VOTING_ENDPOINT, AWAIT_PEG_OUT_BATCH_ENDPOINT,
+    BATCHED_PEG_OUT_FEES_ENDPOINT, BITCOIN_KIND_ENDPOINT, BITCOIN_RPC_CONFIG_ENDPOINT,
     BLOCK_COUNT_ENDPOINT, BLOCK_COUNT_LOCAL_ENDPOINT, MODULE_CONSENSUS_VERSION_ENDPOINT,
-    PEG_OUT_FEES_ENDPOINT, RECOVERY_COUNT_ENDPOINT, RECOVERY_SLICE_ENDPOINT,
-    UTXO_CONFIRMED_ENDPOINT, WALLET_SUMMARY_ENDPOINT,
+    MULTI_PEG_OUT_FEES_ENDPOINT, PEG_OUT_FEES_ENDPOINT, RECOVERY_COUNT_ENDPOINT,
+    RECOVERY_SLICE_ENDPOINT, UTXO_CONFIRMED_ENDPOINT, WALLET_SUMMARY_ENDPOINT,
 };
 use fedimint_wallet_common::{PegOutFees, RecoveryItem, WalletSummary};
 
@@ -28,6 +29,26 @@ async fn fetch_peg_out_fees(
         amount: Amount,
     ) -> FederationResult<Option<PegOutFees>>;
 
+    /// Fetches the fees of a batched peg-out, `None` if the federation
+    /// doesn't batch peg-outs
+    async fn fetch_batched_peg_out_fees(
+        &self,
+        address: &Address,
+        amount: Amount,
+    ) -> FederationResult<Option<PegOutFees>>;
+
+    /// Fetches the fees of a peg-out paying all `recipients` in one
+    /// transaction, `None` if the federation can't create it
+    async fn fetch_multi_peg_out_fees(
+        &self,
+        recipients: &[(Address, Amount)],
+    ) -> FederationResult<Option<PegOutFees>>;
+
+    /// Waits until the batched peg-out created by the output `out_point` was
+    /// paid and returns the id of the batch transaction, `None` if the
+    /// federation didn't pay it before the request timed out
+    async fn await_peg_out_batch(&self, out_point: OutPoint) -> Option<bitcoin::Txid>;
+
     async fn fetch_bitcoin_rpc_kind(&self, peer_id: PeerId) -> FederationResult<String>;
 
     async fn fetch_bitcoin_rpc_config(&self, auth: ApiAuth) -> FederationResult<BitcoinRpcConfig>;
@@ -139,6 +160,42 @@ async fn fetch_peg_out_fees(
         .await
     }
 
+    async fn fetch_batched_peg_out_fees(
+        &self,
+        address: &Address,
+        amount: Amount,
+    ) -> FederationResult<Option<PegOutFees>> {
+        self.request_current_consensus(
+            BATCHED_PEG_OUT_FEES_ENDPOINT.to_string(),
+            ApiRequestErased::new((address, amount.to_sat())),
+        )
+        .await
+    }
+
+    async fn fetch_multi_peg_out_fees(
+        &self,
+        recipients: &[(Address, Amount)],
+    ) -> FederationResult<Option<PegOutFees>> {
+        self.request_current_consensus(
+            MULTI_PEG_OUT_FEES_ENDPOINT.to_string(),
+            ApiRequestErased::new(
+                recipients
+                    .iter()
+                    .map(|(address, amount)| (address, amount.to_sat()))
+                    .collect::<Vec<_>>(),
+            ),
+        )
+        .await
+    }
+
+    async fn await_peg_out_batch(&self, out_point: OutPoint) -> Option<bitcoin::Txid> {
+        self.request_current_consensus_retry(
+            AWAIT_PEG_OUT_BATCH_ENDPOINT.to_string(),
+            ApiRequestErased::new(out_point),
+        )
+        .await
+    }
+
     async fn fetch_bitcoin_rpc_kind(&self, peer_id: PeerId) -> FederationResult<String> {
         self.request_single_peer_federation(
             BITCOIN_KIND_ENDPOINT.to_string(),
@@ -3,7 +3,7 @@
 use std::collections::{BTreeMap, BTreeSet};
 use std::sync::{Arc, Mutex};
 
-use fedimint_bitcoind::{DynBitcoindRpc, create_esplora_rpc};
+use fedimint_bitcoind::{DynBitcoindRpc, create_bitcoind_rpc};
 use fedimint_client_module::module::ClientContext;
 use fedimint_client_module::module::init::ClientModuleRecoverArgs;
 use fedimint_client_module::module::init::recovery::{
@@ -213,7 +213,7 @@ async fn new(
         // 2. user-provided no-chain-id factory from
         //    ClientBuilder::with_bitcoind_rpc_no_chain_id
         // 3. WalletClientInit constructor
-        // 4. create from config (esplora)
+        // 4. create from config (esplora or electrum)
         let btc_rpc = if let Some(user_rpc) = args.user_bitcoind_rpc() {
             user_rpc.clone()
         } else if let Some(factory) = args.user_bitcoind_rpc_no_chain_id() {
@@ -222,12 +222,12 @@ async fn new(
             } else {
                 init.0
                     .clone()
-                    .unwrap_or(create_esplora_rpc(&rpc_config.url)?)
+                    .map_or_else(|| create_bitcoind_rpc(&rpc_config), Ok)?
             }
         } else {
             init.0
                 .clone()
-                .unwrap_or(create_esplora_rpc(&rpc_config.url)?)
+                .map_or_else(|| create_bitcoind_rpc(&rpc_config), Ok)?
         };
 
         let data = WalletClientModuleData {
@@ -312,7 +312,7 @@ async fn load_dbtx(
         // 2. user-provided no-chain-id factory from
         //    ClientBuilder::with_bitcoind_rpc_no_chain_id
         // 3. WalletClientInit constructor
-        // 4. create from config (esplora)
+        // 4. create from config (esplora or electrum)
         let btc_rpc = if let Some(user_rpc) = args.user_bitcoind_rpc() {
             user_rpc.clone()
         } else if let Some(factory) = args.user_bitcoind_rpc_no_chain_id() {
@@ -321,12 +321,12 @@ async fn load_dbtx(
             } else {
                 init.0
                     .clone()
-                    .unwrap_or(create_esplora_rpc(&rpc_config.url)?)
+                    .map_or_else(|| create_bitcoind_rpc(&rpc_config), Ok)?
             }
         } else {
             init.0
                 .clone()
-                .unwrap_or(create_esplora_rpc(&rpc_config.url)?)
+                .map_or_else(|| create_bitcoind_rpc(&rpc_config), Ok)?
         };
 
         let data = WalletClientModuleData {
@@ -34,7 +34,7 @@
 use bitcoin::{Address, Network, ScriptBuf};
 use client_db::{DbKeyPrefix, PegInTweakIndexKey, SupportsSafeDepositKey, TweakIdx};
 use fedimint_api_client::api::{DynModuleApi, FederationResult};
-use fedimint_bitcoind::{DynBitcoindRpc, create_esplora_rpc};
+use fedimint_bitcoind::{DynBitcoindRpc, create_bitcoind_rpc};
 use fedimint_client_module::module::init::{
     ClientModuleInit, ClientModuleInitArgs, ClientModuleRecoverArgs,
 };
@@ -131,6 +131,9 @@ pub enum DepositStateV2 {
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub enum WithdrawState {
     Created,
+    /// The batched peg-out was accepted and waits for the next batch
+    /// transaction
+    AwaitingBatch,
     Succeeded(bitcoin::Txid),
     Failed(String),
     // TODO: track refund
@@ -320,7 +323,7 @@ async fn init(&self, args: &ClientModuleInitArgs<Self>) -> anyhow::Result<Self::
         // 2. user-provided no-chain-id factory from
         //    ClientBuilder::with_bitcoind_rpc_no_chain_id
         // 3. WalletClientInit constructor
-        // 4. create from config (esplora)
+        // 4. create from config (esplora or electrum)
         let btc_rpc = if let Some(user_rpc) = args.user_bitcoind_rpc() {
             user_rpc.clone()
         } else if let Some(factory) = args.user_bitcoind_rpc_no_chain_id() {
@@ -329,12 +332,12 @@ async fn init(&self, args: &ClientModuleInitArgs<Self>) -> anyhow::Result<Self::
             } else {
                 self.0
                     .clone()
-                    .unwrap_or(create_esplora_rpc(&rpc_config.url)?)
+                    .map_or_else(|| create_bitcoind_rpc(&rpc_config), Ok)?
             }
         } else {
             self.0
                 .clone()
-                .unwrap_or(create_esplora_rpc(&rpc_config.url)?)
+                .map_or_else(|| create_bitcoind_rpc(&rpc_config), Ok)?
         };
 
         let module_api = args.module_api().clone();
@@ -436,6 +439,20 @@ pub enum WalletOperationMetaVariant {
         rbf: Rbf,
         change: Vec<OutPoint>,
     },
+
+    BatchedWithdraw {
+        address: Address<NetworkUnchecked>,
+        #[serde(with = "bitcoin::amount::serde::as_sat")]
+        amount: bitcoin::Amount,
+        fee: PegOutFees,
+        change: Vec<OutPoint>,
+    },
+
+    MultiWithdraw {
+        recipients: Vec<PegOutRecipient>,
+        fee: PegOutFees,
+        change: Vec<OutPoint>,
+    },
 }
 
 /// The non-resource, just plain-data parts of [`WalletClientModule`]
@@ -794,6 +811,53 @@ pub async fn get_withdraw_fees(
             .context("Federation didn't return peg-out fees")
     }
 
+    /// Fetches the fees of a withdrawal using [`Self::withdraw_batched`], fails
+    /// if the federation doesn't batch peg-outs.
+    ///
+    /// Like for [`Self::get_withdraw_fees`] the fees are only guaranteed to be
+    /// valid *right now*.
+    pub async fn get_batched_withdraw_fees(
+        &self,
+        address: &bitcoin::Address,
+        amount: bitcoin::Amount,
+    ) -> anyhow::Result<PegOutFees> {
+        ensure!(
+            self.cfg().peg_out_batching.is_some(),
+            "The federation doesn't batch peg-outs"
+        );
+
+        self.module_api
+            .fetch_batched_peg_out_fees(address, amount)
+            .await?
+            .context("Federation didn't return batched peg-out fees")
+    }
+
+    /// Fetches the fees of a withdrawal paying all `recipients` in one
+    /// transaction using [`Self::withdraw_multi`], fails if the federation
+    /// doesn't support multi-output peg-outs yet.
+    ///
+    /// Like for [`Self::get_withdraw_fees`] the fees are only guaranteed to be
+    /// valid *right now*.
+    pub async fn get_multi_withdraw_fees(
+        &self,
+        recipients: &[(bitcoin::Address, bitcoin::Amount)],
+    ) -> anyhow::Result<PegOutFees> {
+        ensure!(
+            !recipients.is_empty() && recipients.len() <= MAX_PEG_OUT_RECIPIENTS,
+            "A withdrawal has to pay between 1 and {MAX_PEG_OUT_RECIPIENTS} recipients"
+        );
+        ensure!(
+            MULTI_PEG_OUT_MODULE_CONSENSUS_VERSION
+                <= self.module_api.module_consensus_version().await?,
+            "The federation doesn't support multi-output peg-outs yet"
+        );
+
+        self.module_api
+            .fetch_multi_peg_out_fees(recipients)
+            .await?
+            .context("Federation didn't return multi peg-out fees")
+    }
+
     /// Returns a summary of the wallet's coins
     pub async fn get_wallet_summary(&self) -> anyhow::Result<WalletSummary> {
         Ok(self.module_api.fetch_wallet_summary().await?)
@@ -812,14 +876,57 @@ pub fn create_withdraw_output(
     ) -> anyhow::Result<ClientOutputBundle<WalletOutput, WalletClientStates>> {
         let output = WalletOutput::new_v0_peg_out(address, amount, fees);
 
-        let amount = output.maybe_v0_ref().expect("v0 output").amount().into();
+        Ok(Self::withdraw_output_bundle(
+            operation_id,
+            output,
+            WithdrawStates::Created,
+        ))
+    }
+
+    pub fn create_batched_withdraw_output(
+        &self,
+        operation_id: OperationId,
+        address: bitcoin::Address,
+        amount: bitcoin::Amount,
+        fees: PegOutFees,
+    ) -> anyhow::Result<ClientOutputBundle<WalletOutput, WalletClientStates>> {
+        let output = WalletOutput::new_v1_batched_peg_out(address, amount, fees);
+
+        Ok(Self::withdraw_output_bundle(
+            operation_id,
+            output,
+            WithdrawStates::Batching,
+        ))
+    }
+
+    pub fn create_multi_withdraw_output(
+        &self,
+        operation_id: OperationId,
+        recipients: Vec<(bitcoin::Address, bitcoin::Amount)>,
+        fees: PegOutFees,
+    ) -> anyhow::Result<ClientOutputBundle<WalletOutput, WalletClientStates>> {
+        let output = WalletOutput::new_v1_multi_peg_out(recipients, fees);
+
+        Ok(Self::withdraw_output_bundle(
+            operation_id,
+            output,
+            WithdrawStates::Created,
+        ))
+    }
+
+    fn withdraw_output_bundle(
+        operation_id: OperationId,
+        output: WalletOutput,
+        initial_state: fn(CreatedWithdrawState) -> WithdrawStates,
+    ) -> ClientOutputBundle<WalletOutput, WalletClientStates> {
+        let amount = output.ensure_amount().expect("known output").into();
 
         let sm_gen = move |out_point_range: OutPointRange| {
             assert_eq!(out_point_range.count(), 1);
             let out_idx = out_point_range.start_idx();
             vec![WalletClientStates::Withdraw(WithdrawStateMachine {
                 operation_id,
-                state: WithdrawStates::Created(CreatedWithdrawState {
+                state: initial_state(CreatedWithdrawState {
                     fm_outpoint: OutPoint {
                         txid: out_point_range.txid(),
                         out_idx,
@@ -828,7 +935,7 @@ pub fn create_withdraw_output(
             })]
         };
 
-        Ok(ClientOutputBundle::new(
+        ClientOutputBundle::new(
             vec![ClientOutput::<WalletOutput> {
                 output,
                 amounts: Amounts::new_bitcoin(amount),
@@ -836,7 +943,7 @@ pub fn create_withdraw_output(
             vec![ClientOutputSM::<WalletClientStates> {
                 state_machines: Arc::new(sm_gen),
             }],
-        ))
+        )
     }
 
     pub async fn peg_in(&self, req: PegInRequest) -> anyhow::Result<PegInResponse> {
@@ -872,7 +979,7 @@ pub fn create_rbf_withdraw_output(
     ) -> anyhow::Result<ClientOutputBundle<WalletOutput, WalletClientStates>> {
         let output = WalletOutput::new_v0_rbf(rbf.fees, rbf.txid);
 
-        let amount = output.maybe_v0_ref().expect("v0 output").amount().into();
+        let amount = output.ensure_amount().expect("known output").into();
 
         let sm_gen = move |out_point_range: OutPointRange| {
             assert_eq!(out_point_range.count(), 1);
@@ -1444,6 +1551,138 @@ pub async fn withdraw<M: Serialize + MaybeSend + MaybeSync>(
         }
     }
 
+    /// Attempt to withdraw a given `amount` of Bitcoin to a destination
+    /// `address` with the next peg-out batch of the federation. Batched
+    /// withdrawals share a transaction and are cheaper, but are only paid
+    /// once the federation's batching window has passed. The fees have to be
+    /// fetched using [`Self::get_batched_withdraw_fees`].
+    pub async fn withdraw_batched<M: Serialize + MaybeSend + MaybeSync>(
+        &self,
+        address: &bitcoin::Address,
+        amount: bitcoin::Amount,
+        fee: PegOutFees,
+        extra_meta: M,
+    ) -> anyhow::Result<OperationId> {
+        ensure!(
+            self.cfg().peg_out_batching.is_some(),
+            "The federation doesn't batch peg-outs"
+        );
+
+        let operation_id = OperationId(thread_rng().r#gen());
+
+        let withdraw_output =
+            self.create_batched_withdraw_output(operation_id, address.clone(), amount, fee)?;
+        let tx_builder = TransactionBuilder::new()
+            .with_outputs(self.client_ctx.make_client_outputs(withdraw_output));
+
+        let extra_meta = serde_json::to_value(extra_meta).expect("Failed to serialize extra meta");
+        self.client_ctx
+            .finalize_and_submit_transaction(
+                operation_id,
+                WalletCommonInit::KIND.as_str(),
+                {
+                    let address = address.clone();
+                    move |change_range: OutPointRange| WalletOperationMeta {
+                        variant: WalletOperationMetaVariant::BatchedWithdraw {
+                            address: address.clone().into_unchecked(),
+                            amount,
+                            fee,
+                            change: change_range.into_iter().collect(),
+                        },
+                        extra_meta: extra_meta.clone(),
+                    }
+                },
+                tx_builder,
+            )
+            .await?;
+
+        let mut dbtx = self.client_ctx.module_db().begin_transaction().await;
+
+        self.client_ctx
+            .log_event(
+                &mut dbtx,
+                SendPaymentEvent {
+                    operation_id,
+                    amount: amount + fee.amount(),
+                    fee: fee.amount(),
+                },
+            )
+            .await;
+
+        dbtx.commit_tx().await;
+
+        Ok(operation_id)
+    }
+
+    /// Attempt to withdraw to several `recipients` with a single peg-out
+    /// transaction, which is cheaper than one withdrawal per recipient. The
+    /// fees cover the whole transaction and have to be fetched using
+    /// [`Self::get_multi_withdraw_fees`].
+    pub async fn withdraw_multi<M: Serialize + MaybeSend + MaybeSync>(
+        &self,
+        recipients: &[(bitcoin::Address, bitcoin::Amount)],
+        fee: PegOutFees,
+        extra_meta: M,
+    ) -> anyhow::Result<OperationId> {
+        ensure!(
+            !recipients.is_empty() && recipients.len() <= MAX_PEG_OUT_RECIPIENTS,
+            "A withdrawal has to pay between 1 and {MAX_PEG_OUT_RECIPIENTS} recipients"
+        );
+
+        let operation_id = OperationId(thread_rng().r#gen());
+        let amount = recipients
+            .iter()
+            .map(|(_, amount)| *amount)
+            .sum::<bitcoin::Amount>();
+
+        let withdraw_output =
+            self.create_multi_withdraw_output(operation_id, recipients.to_vec(), fee)?;
+        let tx_builder = TransactionBuilder::new()
+            .with_outputs(self.client_ctx.make_client_outputs(withdraw_output));
+
+        let recipients = recipients
+            .iter()
+            .map(|(address, amount)| PegOutRecipient {
+                recipient: address.clone().into_unchecked(),
+                amount: *amount,
+            })
+            .collect::<Vec<_>>();
+
+        let extra_meta = serde_json::to_value(extra_meta).expect("Failed to serialize extra meta");
+        self.client_ctx
+            .finalize_and_submit_transaction(
+                operation_id,
+                WalletCommonInit::KIND.as_str(),
+                move |change_range: OutPointRange| WalletOperationMeta {
+                    variant: WalletOperationMetaVariant::MultiWithdraw {
+                        recipients: recipients.clone(),
+                        fee,
+                        change: change_range.into_iter().collect(),
+                    },
+                    extra_meta: extra_meta.clone(),
+                },
+                tx_builder,
+            )
+            .await?;
+
+        let mut dbtx = self.client_ctx.module_db().begin_transaction().await;
+
+        self.client_ctx
+            .log_event(
+                &mut dbtx,
+                SendPaymentEvent {
+                    operation_id,
+                    amount: amount + fee.amount(),
+                    fee: fee.amount(),
+                },
+            )
+            .await;
+
+        dbtx.commit_tx().await;
+
+        Ok(operation_id)
+    }
+
     /// Attempt to increase the fee of a onchain withdraw transaction using
     /// replace by fee (RBF).
     /// This can prevent transactions from getting stuck
@@ -1499,7 +1738,9 @@ pub async fn subscribe_withdraw_updates(
         let operation_meta = operation.meta::<WalletOperationMeta>();
 
         let (WalletOperationMetaVariant::Withdraw { change, .. }
-        | WalletOperationMetaVariant::RbfWithdraw { change, .. }) = operation_meta.variant
+        | WalletOperationMetaVariant::RbfWithdraw { change, .. }
+        | WalletOperationMetaVariant::BatchedWithdraw { change, .. }
+        | WalletOperationMetaVariant::MultiWithdraw { change, .. }) = operation_meta.variant
         else {
             bail!("Operation is not a withdraw operation");
         };
@@ -1515,6 +1756,10 @@ pub async fn subscribe_withdraw_updates(
                         Some(WithdrawStates::Created(_)) => {
                             yield WithdrawState::Created;
                         },
+                        Some(WithdrawStates::Batching(_)) => {
+                            yield WithdrawState::Created;
+                            yield WithdrawState::AwaitingBatch;
+                        },
                         Some(s) => {
                             panic!("Unexpected state {s:?}")
                         },
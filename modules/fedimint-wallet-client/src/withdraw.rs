@@ -14,6 +14,7 @@
 use tracing::warn;
 
 use crate::WalletClientContext;
+use crate::api::WalletFederationApi;
 use crate::events::{SendPaymentStatus, SendPaymentStatusEvent, WithdrawRequest};
 
 // TODO: track tx confirmations
@@ -21,6 +22,8 @@
 /// graph LR
 ///     Created --> Success
 ///     Created --> Aborted
+///     Batching --> Success
+///     Batching --> Aborted
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
 pub struct WithdrawStateMachine {
     pub(crate) operation_id: OperationId,
@@ -54,6 +57,19 @@ fn transitions(
                     },
                 )]
             }
+            WithdrawStates::Batching(batching) => {
+                vec![StateTransition::new(
+                    await_peg_out_batch(global_context.clone(), batching.clone()),
+                    move |dbtx, res, old_state| {
+                        Box::pin(transition_withdraw_processed(
+                            res,
+                            old_state,
+                            wallet_context.clone(),
+                            dbtx,
+                        ))
+                    },
+                )]
+            }
             WithdrawStates::Success(_) | WithdrawStates::Aborted(_) => {
                 vec![]
             }
@@ -100,6 +116,28 @@ async fn await_withdraw_processed(
     }
 }
 
+/// Waits until the batched peg-out was accepted and the federation paid it
+/// with a batch transaction
+async fn await_peg_out_batch(
+    global_context: DynGlobalClientContext,
+    batching: CreatedWithdrawState,
+) -> Result<Txid, String> {
+    global_context
+        .await_tx_accepted(batching.fm_outpoint.txid)
+        .await?;
+
+    // The federation only waits for the batch for a while before responding
+    loop {
+        if let Some(txid) = global_context
+            .module_api()
+            .await_peg_out_batch(batching.fm_outpoint)
+            .await
+        {
+            return Ok(txid);
+        }
+    }
+}
+
 async fn transition_withdraw_processed(
     res: Result<Txid, String>,
     old_state: WithdrawStateMachine,
@@ -108,8 +146,8 @@ async fn transition_withdraw_processed(
 ) -> WithdrawStateMachine {
     assert_matches!(
         old_state.state,
-        WithdrawStates::Created(_),
-        "Unexpected old state: got {:?}, expected Created",
+        WithdrawStates::Created(_) | WithdrawStates::Batching(_),
+        "Unexpected old state: got {:?}, expected Created or Batching",
         old_state.state
     );
 
@@ -160,6 +198,9 @@ pub enum WithdrawStates {
     Created(CreatedWithdrawState),
     Success(SuccessWithdrawState),
     Aborted(AbortedWithdrawState),
+    /// A batched peg-out waiting for the federation to pay it with the next
+    /// batch transaction
+    Batching(CreatedWithdrawState),
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Decodable, Encodable)]
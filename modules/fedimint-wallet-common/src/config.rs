@@ -1,14 +1,19 @@
 use std::collections::BTreeMap;
+use std::io::Read;
+use std::str::FromStr;
+use std::sync::Arc;
 
 use bitcoin::Network;
 use bitcoin::secp256k1::SecretKey;
 use fedimint_core::core::ModuleKind;
 use fedimint_core::encoding::btc::NetworkLegacyEncodingWrapper;
-use fedimint_core::encoding::{Decodable, Encodable};
+use fedimint_core::encoding::{Decodable, DecodeError, Encodable};
 use fedimint_core::envs::BitcoinRpcConfig;
+use fedimint_core::module::registry::ModuleDecoderRegistry;
 use fedimint_core::module::serde_json;
 use fedimint_core::{Feerate, PeerId, plugin_types_trait_impl_config};
-use miniscript::descriptor::{Wpkh, Wsh};
+use miniscript::descriptor::{TapTree, Tr, Wpkh, Wsh};
+use miniscript::{Miniscript, Terminal, Threshold};
 use serde::{Deserialize, Serialize};
 
 use crate::keys::CompressedPublicKey;
@@ -36,7 +41,7 @@ pub struct WalletConfigPrivate {
     pub peg_in_key: SecretKey,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Encodable, Decodable)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WalletConfigConsensus {
     /// Bitcoin network (e.g. testnet, bitcoin)
     pub network: NetworkLegacyEncodingWrapper,
@@ -60,9 +65,12 @@ pub struct WalletConfigConsensus {
     /// **This is only used by the client, the RPC used by the server is defined
     /// in [`WalletConfigLocal`].**
     pub client_default_bitcoin_rpc: BitcoinRpcConfig,
+    /// Batching of peg-outs, if enabled for this federation
+    #[serde(default)]
+    pub peg_out_batching: Option<PegOutBatchingConfig>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize, Encodable, Decodable)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct WalletClientConfig {
     /// The federations public peg-in-descriptor
     pub peg_in_descriptor: PegInDescriptor,
@@ -76,6 +84,112 @@ pub struct WalletClientConfig {
     /// become configurable locally and this should merely be a suggested
     /// default by the federation.*
     pub default_bitcoin_rpc: BitcoinRpcConfig,
+    /// Batching of peg-outs, if enabled for this federation
+    #[serde(default)]
+    pub peg_out_batching: Option<PegOutBatchingConfig>,
+}
+
+/// Batched peg-outs are queued by the federation instead of being paid
+/// immediately. Once the oldest queued peg-out was accepted `window` consensus
+/// blocks ago, all queued peg-outs are paid by a single transaction.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize, Encodable, Decodable)]
+pub struct PegOutBatchingConfig {
+    /// Number of consensus blocks a batch collects peg-outs for
+    pub window: u32,
+}
+
+// Peg-out batching was added after the consensus encoding of the configs was
+// fixed. To keep the encoding, and thus the consensus hash, of configs without
+// it unchanged it is only appended if enabled.
+impl Encodable for WalletConfigConsensus {
+    fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> Result<(), std::io::Error> {
+        self.network.consensus_encode(writer)?;
+        self.peg_in_descriptor.consensus_encode(writer)?;
+        self.peer_peg_in_keys.consensus_encode(writer)?;
+        self.finality_delay.consensus_encode(writer)?;
+        self.default_fee.consensus_encode(writer)?;
+        self.fee_consensus.consensus_encode(writer)?;
+        self.client_default_bitcoin_rpc.consensus_encode(writer)?;
+        encode_peg_out_batching(self.peg_out_batching.as_ref(), writer)
+    }
+}
+
+impl Decodable for WalletConfigConsensus {
+    fn consensus_decode_partial_from_finite_reader<R: Read>(
+        r: &mut R,
+        modules: &ModuleDecoderRegistry,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self {
+            network: Decodable::consensus_decode_partial_from_finite_reader(r, modules)?,
+            peg_in_descriptor: Decodable::consensus_decode_partial_from_finite_reader(r, modules)?,
+            peer_peg_in_keys: Decodable::consensus_decode_partial_from_finite_reader(r, modules)?,
+            finality_delay: Decodable::consensus_decode_partial_from_finite_reader(r, modules)?,
+            default_fee: Decodable::consensus_decode_partial_from_finite_reader(r, modules)?,
+            fee_consensus: Decodable::consensus_decode_partial_from_finite_reader(r, modules)?,
+            client_default_bitcoin_rpc: Decodable::consensus_decode_partial_from_finite_reader(
+                r, modules,
+            )?,
+            peg_out_batching: decode_peg_out_batching(r, modules)?,
+        })
+    }
+}
+
+impl Encodable for WalletClientConfig {
+    fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> Result<(), std::io::Error> {
+        self.peg_in_descriptor.consensus_encode(writer)?;
+        self.network.consensus_encode(writer)?;
+        self.finality_delay.consensus_encode(writer)?;
+        self.fee_consensus.consensus_encode(writer)?;
+        self.default_bitcoin_rpc.consensus_encode(writer)?;
+        encode_peg_out_batching(self.peg_out_batching.as_ref(), writer)
+    }
+}
+
+impl Decodable for WalletClientConfig {
+    fn consensus_decode_partial_from_finite_reader<R: Read>(
+        r: &mut R,
+        modules: &ModuleDecoderRegistry,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self {
+            peg_in_descriptor: Decodable::consensus_decode_partial_from_finite_reader(r, modules)?,
+            network: Decodable::consensus_decode_partial_from_finite_reader(r, modules)?,
+            finality_delay: Decodable::consensus_decode_partial_from_finite_reader(r, modules)?,
+            fee_consensus: Decodable::consensus_decode_partial_from_finite_reader(r, modules)?,
+            default_bitcoin_rpc: Decodable::consensus_decode_partial_from_finite_reader(
+                r, modules,
+            )?,
+            peg_out_batching: decode_peg_out_batching(r, modules)?,
+        })
+    }
+}
+
+fn encode_peg_out_batching<W: std::io::Write>(
+    peg_out_batching: Option<&PegOutBatchingConfig>,
+    writer: &mut W,
+) -> Result<(), std::io::Error> {
+    match peg_out_batching {
+        Some(peg_out_batching) => peg_out_batching.consensus_encode(writer),
+        None => Ok(()),
+    }
+}
+
+/// Decodes the peg-out batching config if there is any data left, configs are
+/// always decoded from a reader limited to their exact length.
+fn decode_peg_out_batching<R: Read>(
+    r: &mut R,
+    modules: &ModuleDecoderRegistry,
+) -> Result<Option<PegOutBatchingConfig>, DecodeError> {
+    let mut first_byte = [0u8; 1];
+
+    if r.read(&mut first_byte).map_err(DecodeError::from_err)? == 0 {
+        return Ok(None);
+    }
+
+    PegOutBatchingConfig::consensus_decode_partial_from_finite_reader(
+        &mut first_byte.as_slice().chain(r),
+        modules,
+    )
+    .map(Some)
 }
 
 impl std::fmt::Display for WalletClientConfig {
@@ -103,6 +217,72 @@ fn default() -> Self {
     }
 }
 
+/// Internal key of taproot peg-in descriptors of federations with more than
+/// one guardian. It is the NUMS point `H` from BIP-341, so nobody knows its
+/// secret key and the outputs can only be spent through the threshold script.
+const UNSPENDABLE_INTERNAL_KEY: &str =
+    "0250929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0";
+
+/// The kind of peg-in descriptor a new federation is created with. It can't
+/// be changed after the federation was set up.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PegInDescriptorKind {
+    /// P2WPKH for a single guardian, a P2WSH `sortedmulti` otherwise
+    #[default]
+    SegwitV0,
+    /// A P2TR output. With a single guardian it is spent through the key path
+    /// with the guardian's key, otherwise through a `multi_a` threshold script
+    /// behind an unspendable internal key.
+    ///
+    /// Peg-outs of federations using it are only signed once
+    /// [`crate::TAPROOT_MODULE_CONSENSUS_VERSION`] is active.
+    Taproot,
+}
+
+impl PegInDescriptorKind {
+    pub fn descriptor(
+        self,
+        pubkeys: &BTreeMap<PeerId, CompressedPublicKey>,
+        threshold: usize,
+    ) -> PegInDescriptor {
+        let single_key = (pubkeys.len() == 1).then(|| {
+            *pubkeys
+                .values()
+                .next()
+                .expect("there is exactly one pub key")
+        });
+
+        match (self, single_key) {
+            (PegInDescriptorKind::SegwitV0, Some(key)) => {
+                PegInDescriptor::Wpkh(Wpkh::new(key).expect("Our key type is always compressed"))
+            }
+            (PegInDescriptorKind::SegwitV0, None) => PegInDescriptor::Wsh(
+                Wsh::new_sortedmulti(threshold, pubkeys.values().copied().collect()).unwrap(),
+            ),
+            (PegInDescriptorKind::Taproot, Some(key)) => {
+                PegInDescriptor::Tr(Tr::new(key, None).expect("Key path descriptor is valid"))
+            }
+            (PegInDescriptorKind::Taproot, None) => {
+                let multi_a = Miniscript::from_ast(Terminal::MultiA(
+                    Threshold::new(threshold, pubkeys.values().copied().collect())
+                        .expect("Threshold is valid for the number of guardians"),
+                ))
+                .expect("multi_a is a valid tapscript");
+
+                PegInDescriptor::Tr(
+                    Tr::new(
+                        CompressedPublicKey::from_str(UNSPENDABLE_INTERNAL_KEY)
+                            .expect("Valid public key"),
+                        Some(TapTree::Leaf(Arc::new(multi_a))),
+                    )
+                    .expect("Threshold script descriptor is valid"),
+                )
+            }
+        }
+    }
+}
+
 impl WalletConfig {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
@@ -113,22 +293,10 @@ pub fn new(
         finality_delay: u32,
         client_default_bitcoin_rpc: BitcoinRpcConfig,
         fee_consensus: FeeConsensus,
+        peg_out_batching: Option<PegOutBatchingConfig>,
+        peg_in_descriptor_kind: PegInDescriptorKind,
     ) -> Self {
-        let peg_in_descriptor = if pubkeys.len() == 1 {
-            PegInDescriptor::Wpkh(
-                Wpkh::new(
-                    *pubkeys
-                        .values()
-                        .next()
-                        .expect("there is exactly one pub key"),
-                )
-                .expect("Our key type is always compressed"),
-            )
-        } else {
-            PegInDescriptor::Wsh(
-                Wsh::new_sortedmulti(threshold, pubkeys.values().copied().collect()).unwrap(),
-            )
-        };
+        let peg_in_descriptor = peg_in_descriptor_kind.descriptor(&pubkeys, threshold);
 
         Self {
             private: WalletConfigPrivate { peg_in_key: sk },
@@ -140,6 +308,7 @@ pub fn new(
                 default_fee: Feerate { sats_per_kvb: 1000 },
                 fee_consensus,
                 client_default_bitcoin_rpc,
+                peg_out_batching,
             },
         }
     }
@@ -152,3 +321,111 @@ pub fn new(
     WalletConfigConsensus,
     WalletClientConfig
 );
+
+#[test]
+fn test_client_config_peg_out_batching_encoding() {
+    let secp = bitcoin::secp256k1::Secp256k1::new();
+    let key = CompressedPublicKey {
+        key: SecretKey::from_slice(&[1; 32])
+            .expect("Valid secret key")
+            .public_key(&secp),
+    };
+
+    let mut config = WalletClientConfig {
+        peg_in_descriptor: PegInDescriptor::Wpkh(Wpkh::new(key).expect("Key is compressed")),
+        network: NetworkLegacyEncodingWrapper(Network::Regtest),
+        finality_delay: 10,
+        fee_consensus: FeeConsensus::default(),
+        default_bitcoin_rpc: BitcoinRpcConfig {
+            kind: "esplora".to_string(),
+            url: "http://127.0.0.1:50002/".parse().expect("Valid URL"),
+        },
+        peg_out_batching: None,
+    };
+
+    // Configs without peg-out batching keep the encoding they had before peg-out
+    // batching was introduced
+    let mut legacy_encoding = Vec::new();
+    config
+        .peg_in_descriptor
+        .consensus_encode(&mut legacy_encoding)
+        .unwrap();
+    config
+        .network
+        .consensus_encode(&mut legacy_encoding)
+        .unwrap();
+    10u32.consensus_encode(&mut legacy_encoding).unwrap();
+    config
+        .fee_consensus
+        .consensus_encode(&mut legacy_encoding)
+        .unwrap();
+    config
+        .default_bitcoin_rpc
+        .consensus_encode(&mut legacy_encoding)
+        .unwrap();
+
+    assert_eq!(config.consensus_encode_to_vec(), legacy_encoding);
+    assert_eq!(
+        WalletClientConfig::consensus_decode_whole(
+            &legacy_encoding,
+            &ModuleDecoderRegistry::default()
+        )
+        .unwrap(),
+        config
+    );
+
+    config.peg_out_batching = Some(PegOutBatchingConfig { window: 6 });
+
+    assert_eq!(
+        WalletClientConfig::consensus_decode_whole(
+            &config.consensus_encode_to_vec(),
+            &ModuleDecoderRegistry::default()
+        )
+        .unwrap(),
+        config
+    );
+}
+
+#[test]
+fn test_taproot_peg_in_descriptor() {
+    use crate::tweakable::Tweakable;
+
+    let secp = bitcoin::secp256k1::Secp256k1::new();
+    let pubkeys = (0..4)
+        .map(|peer| {
+            let key = SecretKey::from_slice(&[peer + 1; 32])
+                .expect("Valid secret key")
+                .public_key(&secp);
+            (PeerId::from(u16::from(peer)), CompressedPublicKey { key })
+        })
+        .collect::<BTreeMap<_, _>>();
+    let tweak = [2; 33];
+
+    for (pubkeys, threshold) in [
+        (pubkeys.clone(), 3),
+        (pubkeys.into_iter().take(1).collect(), 1),
+    ] {
+        let descriptor = PegInDescriptorKind::Taproot.descriptor(&pubkeys, threshold);
+
+        // Deposit addresses are derived from the tweaked descriptor
+        let address = descriptor
+            .tweak(&tweak, &secp)
+            .address(Network::Regtest)
+            .expect("Taproot descriptors have an address");
+        assert!(address.script_pubkey().is_p2tr());
+
+        // The descriptor survives the roundtrip through the config
+        assert_eq!(
+            PegInDescriptor::consensus_decode_whole(
+                &descriptor.consensus_encode_to_vec(),
+                &ModuleDecoderRegistry::default()
+            )
+            .unwrap(),
+            descriptor
+        );
+        assert_eq!(
+            PegInDescriptor::from_str(&descriptor.to_string()).unwrap(),
+            descriptor
+        );
+    }
+}
@@ -10,3 +10,6 @@
 pub const UTXO_CONFIRMED_ENDPOINT: &str = "utxo_confirmed";
 pub const RECOVERY_COUNT_ENDPOINT: &str = "recovery_count";
 pub const RECOVERY_SLICE_ENDPOINT: &str = "recovery_slice";
+pub const BATCHED_PEG_OUT_FEES_ENDPOINT: &str = "batched_peg_out_fees";
+pub const AWAIT_PEG_OUT_BATCH_ENDPOINT: &str = "await_peg_out_batch";
+pub const MULTI_PEG_OUT_FEES_ENDPOINT: &str = "multi_peg_out_fees";
@@ -34,13 +34,44 @@
 pub mod txoproof;
 
 pub const KIND: ModuleKind = ModuleKind::from_static_str("wallet");
-pub const MODULE_CONSENSUS_VERSION: ModuleConsensusVersion = ModuleConsensusVersion::new(2, 2);
+pub const MODULE_CONSENSUS_VERSION: ModuleConsensusVersion = ModuleConsensusVersion::new(2, 7);
 
 /// Module consensus version that introduced support for processing Bitcoin
 /// transactions that exceed the `ALEPH_BFT_UNIT_BYTE_LIMIT`.
 pub const SAFE_DEPOSIT_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion =
     ModuleConsensusVersion::new(2, 2);
 
+/// Module consensus version that introduced Schnorr peg-out signatures, which
+/// federations with a taproot peg-in descriptor need to sign peg-outs.
+pub const TAPROOT_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion =
+    ModuleConsensusVersion::new(2, 3);
+
+/// Module consensus version that replaced block count votes with votes on the
+/// block count and the hash of the chain tip, see [`BlockCountHashItem`].
+pub const BLOCK_HASH_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion =
+    ModuleConsensusVersion::new(2, 4);
+
+/// Module consensus version that introduced peg-outs paying several
+/// recipients in one transaction, see [`MultiPegOut`].
+pub const MULTI_PEG_OUT_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion =
+    ModuleConsensusVersion::new(2, 5);
+
+/// Module consensus version that introduced votes to consolidate the
+/// federation's UTXOs, see [`WalletConsensusItem::ConsolidationVote`].
+pub const CONSOLIDATION_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion =
+    ModuleConsensusVersion::new(2, 6);
+
+/// Maximum number of UTXOs a single consolidation transaction sweeps
+pub const MAX_CONSOLIDATION_INPUTS: usize = 100;
+
+/// Module consensus version that introduced child-pays-for-parent fee bumping
+/// of the federation's own transactions, see [`WalletConsensusItem::CpfpVote`].
+pub const CPFP_MODULE_CONSENSUS_VERSION: ModuleConsensusVersion = ModuleConsensusVersion::new(2, 7);
+
+/// Maximum number of recipients a [`MultiPegOut`] can pay, keeps the peg-out
+/// transaction well below the standardness weight limit
+pub const MAX_PEG_OUT_RECIPIENTS: usize = 100;
+
 /// To further mitigate the risk of a peg-out transaction getting stuck in the
 /// mempool, we multiply the feerate estimate returned from the backend by this
 /// value.
@@ -52,11 +83,23 @@
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Encodable, Decodable)]
 pub enum WalletConsensusItem {
-    BlockCount(u32), /* FIXME: use block hash instead, but needs more complicated
-                      * * verification logic */
+    /// Superseded by [`WalletConsensusItem::BlockCountHash`] once
+    /// [`BLOCK_HASH_MODULE_CONSENSUS_VERSION`] is active
+    BlockCount(u32),
     Feerate(Feerate),
     PegOutSignature(PegOutSignatureItem),
     ModuleConsensusVersion(ModuleConsensusVersion),
+    TaprootPegOutSignature(TaprootPegOutSignatureItem),
+    BlockCountHash(BlockCountHashItem),
+    /// Vote to sweep small UTXOs into a single one at the given consensus
+    /// block count, the federation consolidates once a threshold of peers
+    /// voted for the same block count
+    ConsolidationVote(u32),
+    /// Vote to bump the fee of the given unconfirmed federation transaction
+    /// by spending its change output in a child transaction, the federation
+    /// creates the child once a threshold of peers voted for the same
+    /// transaction
+    CpfpVote(Txid),
     #[encodable_default]
     Default {
         variant: u64,
@@ -87,6 +130,26 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
                     version.major, version.minor
                 )
             }
+            WalletConsensusItem::TaprootPegOutSignature(sig) => {
+                write!(
+                    f,
+                    "Wallet taproot PegOut signature for Bitcoin TxId {}",
+                    sig.txid
+                )
+            }
+            WalletConsensusItem::BlockCountHash(item) => {
+                write!(
+                    f,
+                    "Wallet Block Count {} with tip {}",
+                    item.block_count, item.block_hash
+                )
+            }
+            WalletConsensusItem::ConsolidationVote(block_count) => {
+                write!(f, "Wallet Consolidation Vote at Block Count {block_count}")
+            }
+            WalletConsensusItem::CpfpVote(txid) => {
+                write!(f, "Wallet CPFP Vote for {txid}")
+            }
             WalletConsensusItem::Default { variant, .. } => {
                 write!(f, "Unknown Wallet CI variant={variant}")
             }
@@ -100,6 +163,23 @@ pub struct PegOutSignatureItem {
     pub signature: Vec<secp256k1::ecdsa::Signature>,
 }
 
+/// Signatures of a peer for all inputs of a peg-out transaction spending
+/// outputs of a taproot peg-in descriptor
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Encodable, Decodable)]
+pub struct TaprootPegOutSignatureItem {
+    pub txid: Txid,
+    pub signature: Vec<secp256k1::schnorr::Signature>,
+}
+
+/// A block count vote together with the hash of the block at height
+/// `block_count - 1`, so peers can verify they agree on the chain and not just
+/// on its length
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Encodable, Decodable)]
+pub struct BlockCountHashItem {
+    pub block_count: u32,
+    pub block_hash: BlockHash,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Encodable, Decodable)]
 pub struct SpendableUTXO {
     #[serde(with = "::fedimint_core::encoding::as_hex")]
@@ -116,6 +196,20 @@ pub struct TxOutputSummary {
     pub amount: bitcoin::Amount,
 }
 
+/// A child transaction spending the change of an unconfirmed federation
+/// transaction to bump the fee of both (child-pays-for-parent)
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct CpfpSummary {
+    /// The stuck transaction whose change output is spent
+    pub parent_txid: Txid,
+    pub child_txid: Txid,
+    /// Fee paid by the child from the federation's fee reserve
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub fee: bitcoin::Amount,
+    /// Whether the child transaction reached threshold signatures
+    pub signed: bool,
+}
+
 /// Summary of the coins within the wallet.
 ///
 /// Coins within the wallet go from spendable, to consumed in a transaction that
@@ -140,6 +234,13 @@ pub struct WalletSummary {
     /// Change UTXOs created from peg-out transactions that have reached
     /// threshold signatures waiting for finality delay confirmations
     pub unconfirmed_change_utxos: Vec<TxOutputSummary>,
+    /// Child-pays-for-parent transactions bumping the fee of our unconfirmed
+    /// transactions
+    #[serde(default)]
+    pub cpfp_transactions: Vec<CpfpSummary>,
+    /// Fees collected by the wallet that can pay for fee bumps
+    #[serde(default, with = "bitcoin::amount::serde::as_sat")]
+    pub fee_reserve: Amount,
 }
 
 impl WalletSummary {
@@ -259,6 +360,31 @@ pub struct PegOut {
     pub fees: PegOutFees,
 }
 
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct PegOutRecipient {
+    pub recipient: Address<NetworkUnchecked>,
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub amount: bitcoin::Amount,
+}
+
+/// A peg-out paying several recipients in one transaction, the `fees` cover
+/// the whole transaction
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub struct MultiPegOut {
+    pub recipients: Vec<PegOutRecipient>,
+    pub fees: PegOutFees,
+}
+
+impl MultiPegOut {
+    /// The sum of the amounts paid to all recipients
+    pub fn amount(&self) -> bitcoin::Amount {
+        self.recipients
+            .iter()
+            .map(|recipient| recipient.amount)
+            .sum()
+    }
+}
+
 extensible_associated_module_type!(
     WalletOutputOutcome,
     WalletOutputOutcomeV0,
@@ -386,11 +512,53 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
-extensible_associated_module_type!(
-    WalletOutput,
-    WalletOutputV0,
-    UnknownWalletOutputVariantError
-);
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub enum WalletOutput {
+    V0(WalletOutputV0),
+    /// Outputs only federations with the corresponding features enabled
+    /// accept
+    V1(WalletOutputV1),
+    #[encodable_default]
+    Default {
+        variant: u64,
+        bytes: Vec<u8>,
+    },
+}
+
+#[derive(
+    Debug,
+    thiserror::Error,
+    Clone,
+    Eq,
+    PartialEq,
+    Hash,
+    serde::Deserialize,
+    serde::Serialize,
+    fedimint_core::encoding::Encodable,
+    fedimint_core::encoding::Decodable,
+)]
+#[error("Unknown WalletOutput variant {variant}")]
+pub struct UnknownWalletOutputVariantError {
+    pub variant: u64,
+}
+
+impl std::fmt::Display for WalletOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WalletOutput::V0(inner) => std::fmt::Display::fmt(inner, f),
+            WalletOutput::V1(inner) => std::fmt::Display::fmt(inner, f),
+            WalletOutput::Default { variant, .. } => {
+                write!(f, "Unknown WalletOutput (variant={variant})")
+            }
+        }
+    }
+}
+
+impl From<WalletOutputV0> for WalletOutput {
+    fn from(v: WalletOutputV0) -> Self {
+        Self::V0(v)
+    }
+}
 
 impl WalletOutput {
     pub fn new_v0_peg_out(
@@ -407,6 +575,60 @@ pub fn new_v0_peg_out(
     pub fn new_v0_rbf(fees: PegOutFees, txid: Txid) -> WalletOutput {
         WalletOutput::V0(WalletOutputV0::Rbf(Rbf { fees, txid }))
     }
+    pub fn new_v1_batched_peg_out(
+        recipient: Address,
+        amount: bitcoin::Amount,
+        fees: PegOutFees,
+    ) -> WalletOutput {
+        WalletOutput::V1(WalletOutputV1::BatchedPegOut(PegOut {
+            recipient: recipient.into_unchecked(),
+            amount,
+            fees,
+        }))
+    }
+    pub fn new_v1_multi_peg_out(
+        recipients: Vec<(Address, bitcoin::Amount)>,
+        fees: PegOutFees,
+    ) -> WalletOutput {
+        WalletOutput::V1(WalletOutputV1::MultiPegOut(MultiPegOut {
+            recipients: recipients
+                .into_iter()
+                .map(|(recipient, amount)| PegOutRecipient {
+                    recipient: recipient.into_unchecked(),
+                    amount,
+                })
+                .collect(),
+            fees,
+        }))
+    }
+
+    pub fn maybe_v0_ref(&self) -> Option<&WalletOutputV0> {
+        match self {
+            WalletOutput::V0(v0) => Some(v0),
+            _ => None,
+        }
+    }
+
+    pub fn ensure_v0_ref(&self) -> Result<&WalletOutputV0, UnknownWalletOutputVariantError> {
+        match self {
+            WalletOutput::V0(v0) => Ok(v0),
+            WalletOutput::V1(_) => Err(UnknownWalletOutputVariantError { variant: 1 }),
+            WalletOutput::Default { variant, .. } => {
+                Err(UnknownWalletOutputVariantError { variant: *variant })
+            }
+        }
+    }
+
+    /// The amount of any known output version
+    pub fn ensure_amount(&self) -> Result<Amount, UnknownWalletOutputVariantError> {
+        match self {
+            WalletOutput::V0(v0) => Ok(v0.amount()),
+            WalletOutput::V1(v1) => Ok(v1.amount()),
+            WalletOutput::Default { variant, .. } => {
+                Err(UnknownWalletOutputVariantError { variant: *variant })
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
@@ -415,6 +637,50 @@ pub enum WalletOutputV0 {
     Rbf(Rbf),
 }
 
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
+pub enum WalletOutputV1 {
+    /// A peg-out that is queued and paid together with all other peg-outs of
+    /// the same batch, only accepted by federations with
+    /// [`config::PegOutBatchingConfig`]. Its fees cover its share of the
+    /// batch transaction's weight.
+    BatchedPegOut(PegOut),
+    /// A peg-out paying several recipients in one transaction, only accepted
+    /// from [`MULTI_PEG_OUT_MODULE_CONSENSUS_VERSION`] on
+    MultiPegOut(MultiPegOut),
+}
+
+impl WalletOutputV1 {
+    pub fn amount(&self) -> Amount {
+        match self {
+            WalletOutputV1::BatchedPegOut(pegout) => pegout.amount + pegout.fees.amount(),
+            WalletOutputV1::MultiPegOut(pegout) => pegout.amount() + pegout.fees.amount(),
+        }
+    }
+}
+
+impl std::fmt::Display for WalletOutputV1 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WalletOutputV1::BatchedPegOut(pegout) => {
+                write!(
+                    f,
+                    "Wallet batched PegOut {} to {}",
+                    pegout.amount,
+                    pegout.recipient.clone().assume_checked()
+                )
+            }
+            WalletOutputV1::MultiPegOut(pegout) => {
+                write!(
+                    f,
+                    "Wallet PegOut {} to {} recipients",
+                    pegout.amount(),
+                    pegout.recipients.len()
+                )
+            }
+        }
+    }
+}
+
 /// Allows a user to bump the fees of a `PendingTransaction`
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Encodable, Decodable)]
 pub struct Rbf {
@@ -524,6 +790,16 @@ pub enum WalletOutputError {
     BelowMinRelayFee,
     #[error("The wallet output version is not supported by this federation")]
     UnknownOutputVariant(#[from] UnknownWalletOutputVariantError),
+    #[error("The federation doesn't batch peg-outs")]
+    PegOutBatchingDisabled,
+    #[error("Peg-out has {0} recipients, expected between 1 and {MAX_PEG_OUT_RECIPIENTS}")]
+    InvalidPegOutRecipientCount(u64),
+    #[error("The federation doesn't support multi-output peg-outs yet")]
+    MultiPegOutNotSupported,
+    #[error("Peg-outs paying several recipients can't be replaced by RBF")]
+    RbfMultiPegOutNotSupported,
+    #[error("Batched peg-out transactions can't be replaced by RBF")]
+    RbfPegOutBatchNotSupported,
 }
 
 // For backwards-compatibility with old clients, we use an UnknownOutputVariant
@@ -550,4 +826,6 @@ pub enum ProcessPegOutSigError {
     MissingOrMalformedChangeTweak,
     #[error("Error finalizing PSBT {0:?}")]
     ErrorFinalizingPsbt(Vec<miniscript::psbt::Error>),
+    #[error("Signature type doesn't match the peg-in descriptor")]
+    WrongSignatureType,
 }
@@ -1,4 +1,5 @@
 use bitcoin::secp256k1::ecdsa::Signature;
+use bitcoin::secp256k1::schnorr;
 use bitcoin::{BlockHash, OutPoint, TxOut, Txid};
 use fedimint_core::db::IDatabaseTransactionOpsCoreTyped;
 use fedimint_core::encoding::{Decodable, Encodable};
@@ -11,8 +12,11 @@
 use serde::Serialize;
 use strum_macros::EnumIter;
 
-use crate::common::{RecoveryItem, WalletInput};
-use crate::{PendingTransaction, SpendableUTXO, UnsignedTransaction, Wallet, WalletOutputOutcome};
+use crate::common::{BlockCountHashItem, RecoveryItem, WalletInput};
+use crate::{
+    Consolidation, Cpfp, PendingTransaction, QueuedPegOut, SpendableUTXO, UnsignedTransaction,
+    Wallet, WalletOutputOutcome,
+};
 
 #[repr(u8)]
 #[derive(Clone, EnumIter, Debug)]
@@ -35,6 +39,16 @@ pub enum DbKeyPrefix {
     // was started with fedimint 0.8 or later
     BlockHashByHeight = 0x43,
     RecoveryItem = 0x44,
+    QueuedPegOut = 0x45,
+    PegOutBatch = 0x46,
+    TaprootPegOutTxSigCi = 0x47,
+    BlockHashVote = 0x48,
+    ConsolidationVote = 0x49,
+    Consolidation = 0x4a,
+    PendingTransactionBlockCount = 0x4b,
+    CpfpVote = 0x4c,
+    Cpfp = 0x4d,
+    FeeReserve = 0x4e,
 }
 
 impl std::fmt::Display for DbKeyPrefix {
@@ -138,6 +152,24 @@ fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
     query_prefix = PegOutTxSignatureCIPrefix
 );
 
+/// Our Schnorr signatures of a peg-out transaction spending outputs of a
+/// taproot peg-in descriptor, the counterpart of [`PegOutTxSignatureCI`]
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct TaprootPegOutTxSignatureCI(pub Txid);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct TaprootPegOutTxSignatureCIPrefix;
+
+impl_db_record!(
+    key = TaprootPegOutTxSignatureCI,
+    value = Vec<schnorr::Signature>,
+    db_prefix = DbKeyPrefix::TaprootPegOutTxSigCi,
+);
+impl_db_lookup!(
+    key = TaprootPegOutTxSignatureCI,
+    query_prefix = TaprootPegOutTxSignatureCIPrefix
+);
+
 #[derive(Clone, Debug, Encodable, Decodable, Serialize)]
 pub struct PegOutBitcoinTransaction(pub fedimint_core::OutPoint);
 
@@ -148,6 +180,7 @@ fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
     key = PegOutBitcoinTransaction,
     value = WalletOutputOutcome,
     db_prefix = DbKeyPrefix::PegOutBitcoinOutPoint,
+    notify_on_modify = true,
 );
 
 impl_db_lookup!(
@@ -169,6 +202,112 @@ fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
 
 impl_db_lookup!(key = BlockCountVoteKey, query_prefix = BlockCountVotePrefix);
 
+/// The chain tips a peer voted for at or above the consensus block count, the
+/// latest of which is also recorded in [`BlockCountVoteKey`]
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct BlockHashVoteKey(pub PeerId);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct BlockHashVotePrefix;
+
+impl_db_record!(
+    key = BlockHashVoteKey,
+    value = Vec<BlockCountHashItem>,
+    db_prefix = DbKeyPrefix::BlockHashVote
+);
+
+impl_db_lookup!(key = BlockHashVoteKey, query_prefix = BlockHashVotePrefix);
+
+/// The consensus block count a peer last voted to consolidate UTXOs at
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct ConsolidationVoteKey(pub PeerId);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct ConsolidationVotePrefix;
+
+impl_db_record!(
+    key = ConsolidationVoteKey,
+    value = u32,
+    db_prefix = DbKeyPrefix::ConsolidationVote
+);
+
+impl_db_lookup!(
+    key = ConsolidationVoteKey,
+    query_prefix = ConsolidationVotePrefix
+);
+
+/// A UTXO consolidation transaction created by the federation
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct ConsolidationKey(pub Txid);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct ConsolidationPrefix;
+
+impl_db_record!(
+    key = ConsolidationKey,
+    value = Consolidation,
+    db_prefix = DbKeyPrefix::Consolidation
+);
+
+impl_db_lookup!(key = ConsolidationKey, query_prefix = ConsolidationPrefix);
+
+/// Consensus block count at which a [`PendingTransaction`] reached threshold
+/// signatures
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct PendingTransactionBlockCountKey(pub Txid);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct PendingTransactionBlockCountPrefix;
+
+impl_db_record!(
+    key = PendingTransactionBlockCountKey,
+    value = u32,
+    db_prefix = DbKeyPrefix::PendingTransactionBlockCount
+);
+
+impl_db_lookup!(
+    key = PendingTransactionBlockCountKey,
+    query_prefix = PendingTransactionBlockCountPrefix
+);
+
+/// The unconfirmed transaction a peer last voted to bump with a CPFP child
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct CpfpVoteKey(pub PeerId);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct CpfpVotePrefix;
+
+impl_db_record!(
+    key = CpfpVoteKey,
+    value = Txid,
+    db_prefix = DbKeyPrefix::CpfpVote
+);
+
+impl_db_lookup!(key = CpfpVoteKey, query_prefix = CpfpVotePrefix);
+
+/// A CPFP child transaction created by the federation, keyed by the txid of
+/// the parent it bumps
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct CpfpKey(pub Txid);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct CpfpPrefix;
+
+impl_db_record!(key = CpfpKey, value = Cpfp, db_prefix = DbKeyPrefix::Cpfp);
+
+impl_db_lookup!(key = CpfpKey, query_prefix = CpfpPrefix);
+
+/// Wallet fees collected since CPFP fee bumping was activated, minus the fees
+/// the federation paid for its own transactions since then
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct FeeReserveKey;
+
+impl_db_record!(
+    key = FeeReserveKey,
+    value = bitcoin::Amount,
+    db_prefix = DbKeyPrefix::FeeReserve
+);
+
 #[derive(Clone, Debug, Encodable, Decodable, Serialize)]
 pub struct FeeRateVoteKey(pub PeerId);
 
@@ -300,6 +439,35 @@ pub async fn migrate_to_v1(
 );
 impl_db_lookup!(key = RecoveryItemKey, query_prefix = RecoveryItemKeyPrefix);
 
+/// A batched peg-out waiting to be paid with the next batch, keyed by its
+/// output
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct QueuedPegOutKey(pub fedimint_core::OutPoint);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct QueuedPegOutPrefix;
+
+impl_db_record!(
+    key = QueuedPegOutKey,
+    value = QueuedPegOut,
+    db_prefix = DbKeyPrefix::QueuedPegOut,
+);
+impl_db_lookup!(key = QueuedPegOutKey, query_prefix = QueuedPegOutPrefix);
+
+/// The outputs of the batched peg-outs paid by a bitcoin transaction
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct PegOutBatchKey(pub Txid);
+
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct PegOutBatchPrefix;
+
+impl_db_record!(
+    key = PegOutBatchKey,
+    value = Vec<fedimint_core::OutPoint>,
+    db_prefix = DbKeyPrefix::PegOutBatch,
+);
+impl_db_lookup!(key = PegOutBatchKey, query_prefix = PegOutBatchPrefix);
+
 /// Migrate to v2, backfilling recovery items from module history
 pub async fn migrate_to_v2(
     mut ctx: ServerModuleDbMigrationFnContext<'_, Wallet>,
@@ -1,3 +1,4 @@
+use fedimint_core::Feerate;
 use fedimint_core::util::FmtCompact as _;
 use fedimint_logging::LOG_MODULE_WALLET;
 use fedimint_wallet_common::FEERATE_MULTIPLIER_DEFAULT;
@@ -5,6 +6,21 @@
 
 pub const FM_WALLET_FEERATE_MULTIPLIER_ENV: &str = "FM_WALLET_FEERATE_MULTIPLIER";
 
+/// Highest consensus fee rate in sats/kvB at which this guardian votes to
+/// consolidate the federation's UTXOs, consolidation is disabled if unset
+pub const FM_WALLET_CONSOLIDATION_MAX_FEERATE_ENV: &str = "FM_WALLET_CONSOLIDATION_MAX_FEERATE";
+
+/// Minimum number of spendable UTXOs for this guardian to vote to consolidate
+/// them
+pub const FM_WALLET_CONSOLIDATION_MIN_UTXOS_ENV: &str = "FM_WALLET_CONSOLIDATION_MIN_UTXOS";
+
+const CONSOLIDATION_MIN_UTXOS_DEFAULT: usize = 50;
+
+/// Number of blocks after which this guardian votes to bump the fee of an
+/// unconfirmed federation transaction paying less than the consensus fee rate
+/// with a CPFP child, fee bumping is disabled if unset
+pub const FM_WALLET_CPFP_MIN_AGE_ENV: &str = "FM_WALLET_CPFP_MIN_AGE";
+
 pub fn get_feerate_multiplier() -> f64 {
     if let Ok(mult) = std::env::var(FM_WALLET_FEERATE_MULTIPLIER_ENV) {
         match mult.parse::<f64>() {
@@ -21,3 +37,56 @@ pub fn get_feerate_multiplier() -> f64 {
 
     FEERATE_MULTIPLIER_DEFAULT
 }
+
+pub fn get_consolidation_max_feerate() -> Option<Feerate> {
+    let feerate = std::env::var(FM_WALLET_CONSOLIDATION_MAX_FEERATE_ENV).ok()?;
+
+    match feerate.trim().parse::<u64>() {
+        Ok(sats_per_kvb) => Some(Feerate { sats_per_kvb }),
+        Err(err) => {
+            warn!(
+                target: LOG_MODULE_WALLET,
+                err = %err.fmt_compact(),
+                %feerate,
+                "Invalid consolidation fee rate"
+            );
+            None
+        }
+    }
+}
+
+pub fn get_consolidation_min_utxos() -> usize {
+    if let Ok(min_utxos) = std::env::var(FM_WALLET_CONSOLIDATION_MIN_UTXOS_ENV) {
+        match min_utxos.trim().parse::<usize>() {
+            // Consolidating a single UTXO would only pay fees
+            Ok(min_utxos) => return min_utxos.max(2),
+            Err(err) => {
+                warn!(
+                    target: LOG_MODULE_WALLET,
+                    err = %err.fmt_compact(),
+                    %min_utxos,
+                    "Invalid consolidation minimum UTXO count"
+                );
+            }
+        }
+    }
+
+    CONSOLIDATION_MIN_UTXOS_DEFAULT
+}
+
+pub fn get_cpfp_min_age() -> Option<u32> {
+    let min_age = std::env::var(FM_WALLET_CPFP_MIN_AGE_ENV).ok()?;
+
+    match min_age.trim().parse::<u32>() {
+        Ok(min_age) => Some(min_age),
+        Err(err) => {
+            warn!(
+                target: LOG_MODULE_WALLET,
+                err = %err.fmt_compact(),
+                %min_age,
+                "Invalid CPFP minimum transaction age"
+            );
+            None
+        }
+    }
+}
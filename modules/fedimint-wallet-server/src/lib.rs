@@ -25,22 +25,35 @@
 use bitcoin::address::NetworkUnchecked;
 use bitcoin::ecdsa::Signature as EcdsaSig;
 use bitcoin::hashes::{Hash as BitcoinHash, HashEngine, Hmac, HmacEngine, sha256};
+use bitcoin::key::{Keypair, TapTweak as _};
 use bitcoin::policy::DEFAULT_MIN_RELAY_TX_FEE;
 use bitcoin::psbt::{Input, Psbt};
 use bitcoin::secp256k1::{self, All, Message, Scalar, Secp256k1, Verification};
-use bitcoin::sighash::{EcdsaSighashType, SighashCache};
+use bitcoin::sighash::{EcdsaSighashType, Prevouts, SighashCache, TapSighashType};
+use bitcoin::taproot::{LeafVersion, TapLeafHash};
 use bitcoin::{Address, BlockHash, Network, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid};
 use common::config::WalletConfigConsensus;
 use common::{
-    DEPRECATED_RBF_ERROR, PegOutFees, PegOutSignatureItem, ProcessPegOutSigError, SpendableUTXO,
-    TxOutputSummary, WalletCommonInit, WalletConsensusItem, WalletInput, WalletModuleTypes,
-    WalletOutput, WalletOutputOutcome, WalletSummary, proprietary_tweak_key,
+    BLOCK_HASH_MODULE_CONSENSUS_VERSION, BlockCountHashItem,
+    CONSOLIDATION_MODULE_CONSENSUS_VERSION, CPFP_MODULE_CONSENSUS_VERSION, CpfpSummary,
+    DEPRECATED_RBF_ERROR, MAX_CONSOLIDATION_INPUTS, PegInDescriptor, PegOut, PegOutFees,
+    PegOutSignatureItem, ProcessPegOutSigError, SpendableUTXO, TAPROOT_MODULE_CONSENSUS_VERSION,
+    TaprootPegOutSignatureItem, TxOutputSummary, WalletCommonInit, WalletConsensusItem,
+    WalletInput, WalletModuleTypes, WalletOutput, WalletOutputOutcome, WalletSummary,
+    proprietary_tweak_key,
 };
 use db::{
-    BlockHashByHeightKey, BlockHashByHeightKeyPrefix, BlockHashByHeightValue, RecoveryItemKey,
-    RecoveryItemKeyPrefix,
+    BlockHashByHeightKey, BlockHashByHeightKeyPrefix, BlockHashByHeightValue, BlockHashVoteKey,
+    BlockHashVotePrefix, ConsolidationKey, ConsolidationPrefix, ConsolidationVoteKey,
+    ConsolidationVotePrefix, CpfpKey, CpfpPrefix, CpfpVoteKey, CpfpVotePrefix, FeeReserveKey,
+    PegOutBatchKey, PegOutBatchPrefix, PendingTransactionBlockCountKey,
+    PendingTransactionBlockCountPrefix, QueuedPegOutKey, QueuedPegOutPrefix, RecoveryItemKey,
+    RecoveryItemKeyPrefix, TaprootPegOutTxSignatureCI, TaprootPegOutTxSignatureCIPrefix,
+};
+use envs::{
+    get_consolidation_max_feerate, get_consolidation_min_utxos, get_cpfp_min_age,
+    get_feerate_multiplier,
 };
-use envs::get_feerate_multiplier;
 use fedimint_api_client::api::{DynModuleApi, FederationApiExt};
 use fedimint_core::config::{
     ServerModuleConfig, ServerModuleConsensusConfig, TypedServerModuleConfig,
@@ -60,9 +73,9 @@
     SupportedModuleApiVersions, TransactionItemAmounts, api_endpoint,
 };
 use fedimint_core::net::auth::check_auth;
-use fedimint_core::task::TaskGroup;
 #[cfg(not(target_family = "wasm"))]
 use fedimint_core::task::sleep;
+use fedimint_core::task::{TaskGroup, timeout};
 use fedimint_core::util::{FmtCompact, FmtCompactAnyhow as _, backoff_util, retry};
 use fedimint_core::{
     Feerate, InPoint, NumPeersExt, OutPoint, PeerId, apply, async_trait_maybe_send,
@@ -76,19 +89,25 @@
     ConfigGenModuleArgs, ServerModule, ServerModuleInit, ServerModuleInitArgs,
 };
 pub use fedimint_wallet_common as common;
-use fedimint_wallet_common::config::{FeeConsensus, WalletClientConfig, WalletConfig};
+use fedimint_wallet_common::config::{
+    FeeConsensus, PegInDescriptorKind, PegOutBatchingConfig, WalletClientConfig, WalletConfig,
+};
 use fedimint_wallet_common::endpoint_constants::{
-    ACTIVATE_CONSENSUS_VERSION_VOTING_ENDPOINT, BITCOIN_KIND_ENDPOINT, BITCOIN_RPC_CONFIG_ENDPOINT,
+    ACTIVATE_CONSENSUS_VERSION_VOTING_ENDPOINT, AWAIT_PEG_OUT_BATCH_ENDPOINT,
+    BATCHED_PEG_OUT_FEES_ENDPOINT, BITCOIN_KIND_ENDPOINT, BITCOIN_RPC_CONFIG_ENDPOINT,
     BLOCK_COUNT_ENDPOINT, BLOCK_COUNT_LOCAL_ENDPOINT, MODULE_CONSENSUS_VERSION_ENDPOINT,
-    PEG_OUT_FEES_ENDPOINT, RECOVERY_COUNT_ENDPOINT, RECOVERY_SLICE_ENDPOINT,
-    SUPPORTED_MODULE_CONSENSUS_VERSION_ENDPOINT, UTXO_CONFIRMED_ENDPOINT, WALLET_SUMMARY_ENDPOINT,
+    MULTI_PEG_OUT_FEES_ENDPOINT, PEG_OUT_FEES_ENDPOINT, RECOVERY_COUNT_ENDPOINT,
+    RECOVERY_SLICE_ENDPOINT, SUPPORTED_MODULE_CONSENSUS_VERSION_ENDPOINT, UTXO_CONFIRMED_ENDPOINT,
+    WALLET_SUMMARY_ENDPOINT,
 };
 use fedimint_wallet_common::envs::FM_PORT_ESPLORA_ENV;
 use fedimint_wallet_common::keys::CompressedPublicKey;
 use fedimint_wallet_common::tweakable::Tweakable;
 use fedimint_wallet_common::{
-    MODULE_CONSENSUS_VERSION, Rbf, RecoveryItem, UnknownWalletInputVariantError, WalletInputError,
-    WalletOutputError, WalletOutputV0,
+    MAX_PEG_OUT_RECIPIENTS, MODULE_CONSENSUS_VERSION, MULTI_PEG_OUT_MODULE_CONSENSUS_VERSION,
+    MultiPegOut, Rbf, RecoveryItem, UnknownWalletInputVariantError,
+    UnknownWalletOutputVariantError, WalletInputError, WalletOutputError, WalletOutputV0,
+    WalletOutputV1,
 };
 use futures::future::join_all;
 use futures::{FutureExt, StreamExt};
@@ -115,7 +134,7 @@
     UTXOPrefixKey, UnsignedTransactionKey, UnsignedTransactionPrefixKey, UnspentTxOutKey,
     UnspentTxOutPrefix, migrate_to_v1, migrate_to_v2,
 };
-use crate::metrics::WALLET_BLOCK_COUNT;
+use crate::metrics::{WALLET_BLOCK_COUNT, WALLET_CHAIN_TIP_DIVERGED};
 
 mod metrics;
 
@@ -168,6 +187,16 @@ async fn dump_database(
                         "Peg Out Transaction Signatures"
                     );
                 }
+                DbKeyPrefix::TaprootPegOutTxSigCi => {
+                    push_db_pair_items!(
+                        dbtx,
+                        TaprootPegOutTxSignatureCIPrefix,
+                        TaprootPegOutTxSignatureCI,
+                        Vec<secp256k1::schnorr::Signature>,
+                        wallet,
+                        "Taproot Peg Out Transaction Signatures"
+                    );
+                }
                 DbKeyPrefix::PendingTransaction => {
                     push_db_pair_items!(
                         dbtx,
@@ -213,6 +242,64 @@ async fn dump_database(
                         "Block Count Votes"
                     );
                 }
+                DbKeyPrefix::BlockHashVote => {
+                    push_db_pair_items!(
+                        dbtx,
+                        BlockHashVotePrefix,
+                        BlockHashVoteKey,
+                        Vec<BlockCountHashItem>,
+                        wallet,
+                        "Block Hash Votes"
+                    );
+                }
+                DbKeyPrefix::ConsolidationVote => {
+                    push_db_pair_items!(
+                        dbtx,
+                        ConsolidationVotePrefix,
+                        ConsolidationVoteKey,
+                        u32,
+                        wallet,
+                        "Consolidation Votes"
+                    );
+                }
+                DbKeyPrefix::Consolidation => {
+                    push_db_pair_items!(
+                        dbtx,
+                        ConsolidationPrefix,
+                        ConsolidationKey,
+                        Consolidation,
+                        wallet,
+                        "Consolidations"
+                    );
+                }
+                DbKeyPrefix::PendingTransactionBlockCount => {
+                    push_db_pair_items!(
+                        dbtx,
+                        PendingTransactionBlockCountPrefix,
+                        PendingTransactionBlockCountKey,
+                        u32,
+                        wallet,
+                        "Pending Transaction Block Counts"
+                    );
+                }
+                DbKeyPrefix::CpfpVote => {
+                    push_db_pair_items!(
+                        dbtx,
+                        CpfpVotePrefix,
+                        CpfpVoteKey,
+                        Txid,
+                        wallet,
+                        "CPFP Votes"
+                    );
+                }
+                DbKeyPrefix::Cpfp => {
+                    push_db_pair_items!(dbtx, CpfpPrefix, CpfpKey, Cpfp, wallet, "CPFPs");
+                }
+                DbKeyPrefix::FeeReserve => {
+                    if let Some(fee_reserve) = dbtx.get_value(&FeeReserveKey).await {
+                        wallet.insert("Fee Reserve".to_string(), Box::new(fee_reserve));
+                    }
+                }
                 DbKeyPrefix::FeeRateVote => {
                     push_db_pair_items!(
                         dbtx,
@@ -273,6 +360,26 @@ async fn dump_database(
                         "Recovery Items"
                     );
                 }
+                DbKeyPrefix::QueuedPegOut => {
+                    push_db_pair_items!(
+                        dbtx,
+                        QueuedPegOutPrefix,
+                        QueuedPegOutKey,
+                        QueuedPegOut,
+                        wallet,
+                        "Queued Peg-Outs"
+                    );
+                }
+                DbKeyPrefix::PegOutBatch => {
+                    push_db_pair_items!(
+                        dbtx,
+                        PegOutBatchPrefix,
+                        PegOutBatchKey,
+                        Vec<OutPoint>,
+                        wallet,
+                        "Peg-Out Batches"
+                    );
+                }
             }
         }
 
@@ -289,6 +396,14 @@ fn default_finality_delay(network: Network) -> u32 {
     }
 }
 
+fn peg_in_descriptor_kind(args: &ConfigGenModuleArgs) -> PegInDescriptorKind {
+    if args.wallet_taproot_peg_in {
+        PegInDescriptorKind::Taproot
+    } else {
+        PegInDescriptorKind::SegwitV0
+    }
+}
+
 /// Default Bitcoin RPC config for clients
 fn default_client_bitcoin_rpc(network: Network) -> BitcoinRpcConfig {
     let url = match network {
@@ -324,7 +439,7 @@ fn supported_api_versions(&self) -> SupportedModuleApiVersions {
                 MODULE_CONSENSUS_VERSION.major,
                 MODULE_CONSENSUS_VERSION.minor,
             ),
-            &[(0, 2)],
+            &[(0, 4)],
         )
     }
 
@@ -382,6 +497,9 @@ fn trusted_dealer_gen(
                     finality_delay,
                     client_default_bitcoin_rpc.clone(),
                     FeeConsensus::default(),
+                    args.wallet_peg_out_batch_window
+                        .map(|window| PegOutBatchingConfig { window }),
+                    peg_in_descriptor_kind(args),
                 );
                 (*id, cfg)
             })
@@ -419,6 +537,9 @@ async fn distributed_gen(
             finality_delay,
             client_default_bitcoin_rpc,
             FeeConsensus::default(),
+            args.wallet_peg_out_batch_window
+                .map(|window| PegOutBatchingConfig { window }),
+            peg_in_descriptor_kind(args),
         );
 
         Ok(wallet_cfg.to_erased())
@@ -452,6 +573,7 @@ fn get_client_config(
             fee_consensus: config.fee_consensus,
             finality_delay: config.finality_delay,
             default_bitcoin_rpc: config.client_default_bitcoin_rpc,
+            peg_out_batching: config.peg_out_batching,
         })
     }
 
@@ -498,6 +620,21 @@ async fn consensus_proposal<'a>(
             .collect::<Vec<WalletConsensusItem>>()
             .await;
 
+        if self.taproot_signatures_supported(dbtx).await {
+            items.extend(
+                dbtx.find_by_prefix(&TaprootPegOutTxSignatureCIPrefix)
+                    .await
+                    .map(|(key, val)| {
+                        WalletConsensusItem::TaprootPegOutSignature(TaprootPegOutSignatureItem {
+                            txid: key.0,
+                            signature: val,
+                        })
+                    })
+                    .collect::<Vec<WalletConsensusItem>>()
+                    .await,
+            );
+        }
+
         // If we are unable to get a block count from the node we skip adding a block
         // count vote to consensus items.
         //
@@ -543,7 +680,7 @@ async fn consensus_proposal<'a>(
                 );
 
                 WALLET_BLOCK_COUNT.set(i64::from(block_count_vote));
-                items.push(WalletConsensusItem::BlockCount(block_count_vote));
+                items.extend(self.block_count_vote_item(dbtx, block_count_vote).await);
             }
             Err(err) => {
                 warn!(target: LOG_MODULE_WALLET, err = %err.fmt_compact_anyhow(), "Can't update block count");
@@ -554,6 +691,14 @@ async fn consensus_proposal<'a>(
 
         items.push(WalletConsensusItem::Feerate(fee_rate_proposal));
 
+        if let Some(consolidation_vote) = self.consolidation_vote_item(dbtx).await {
+            items.push(consolidation_vote);
+        }
+
+        if let Some(cpfp_vote) = self.cpfp_vote_item(dbtx).await {
+            items.push(cpfp_vote);
+        }
+
         // Consensus upgrade activation voting
         let manual_vote = dbtx
             .get_value(&ConsensusVersionVotingActivationKey)
@@ -593,54 +738,29 @@ async fn process_consensus_item<'a, 'b>(
 
         match consensus_item {
             WalletConsensusItem::BlockCount(block_count_vote) => {
-                let current_vote = dbtx.get_value(&BlockCountVoteKey(peer)).await.unwrap_or(0);
-
-                if block_count_vote < current_vote {
-                    warn!(target: LOG_MODULE_WALLET, ?peer, ?block_count_vote, "Block count vote is outdated");
-                }
-
+                self.process_block_count_vote(dbtx, peer, block_count_vote)
+                    .await?;
+            }
+            WalletConsensusItem::BlockCountHash(block_count_hash) => {
                 ensure!(
-                    block_count_vote > current_vote,
-                    "Block count vote is redundant"
+                    BLOCK_HASH_MODULE_CONSENSUS_VERSION
+                        <= self.consensus_module_consensus_version(dbtx).await,
+                    "Block hash votes are not supported yet"
                 );
 
-                let old_consensus_block_count = self.consensus_block_count(dbtx).await;
-
-                dbtx.insert_entry(&BlockCountVoteKey(peer), &block_count_vote)
-                    .await;
-
-                let new_consensus_block_count = self.consensus_block_count(dbtx).await;
+                // Recorded first so the sync triggered by this vote can verify the hash
+                let consensus_block_count = self.consensus_block_count(dbtx).await;
+                let mut votes = dbtx
+                    .get_value(&BlockHashVoteKey(peer))
+                    .await
+                    .unwrap_or_default();
 
-                debug!(
-                    target: LOG_MODULE_WALLET,
-                    ?peer,
-                    ?current_vote,
-                    ?block_count_vote,
-                    ?old_consensus_block_count,
-                    ?new_consensus_block_count,
-                    "Received block count vote"
-                );
+                record_block_hash_vote(&mut votes, block_count_hash.clone(), consensus_block_count);
 
-                assert!(old_consensus_block_count <= new_consensus_block_count);
+                dbtx.insert_entry(&BlockHashVoteKey(peer), &votes).await;
 
-                if new_consensus_block_count != old_consensus_block_count {
-                    // We do not sync blocks that predate the federation itself
-                    if old_consensus_block_count != 0 {
-                        self.sync_up_to_consensus_count(
-                            dbtx,
-                            old_consensus_block_count,
-                            new_consensus_block_count,
-                        )
-                        .await;
-                    } else {
-                        info!(
-                            target: LOG_MODULE_WALLET,
-                            ?old_consensus_block_count,
-                            ?new_consensus_block_count,
-                            "Not syncing up to consensus block count because we are at block 0"
-                        );
-                    }
-                }
+                self.process_block_count_vote(dbtx, peer, block_count_hash.block_count)
+                    .await?;
             }
             WalletConsensusItem::Feerate(feerate) => {
                 if Some(feerate) == dbtx.insert_entry(&FeeRateVoteKey(peer), &feerate).await {
@@ -648,38 +768,61 @@ async fn process_consensus_item<'a, 'b>(
                 }
             }
             WalletConsensusItem::PegOutSignature(peg_out_signature) => {
-                let txid = peg_out_signature.txid;
+                self.process_peg_out_signature(dbtx, peg_out_signature.txid, |psbt| {
+                    self.sign_peg_out_psbt(psbt, peer, &peg_out_signature)
+                })
+                .await?;
+            }
+            WalletConsensusItem::TaprootPegOutSignature(peg_out_signature) => {
+                ensure!(
+                    self.taproot_signatures_supported(dbtx).await,
+                    "Taproot peg-out signatures are not supported yet"
+                );
 
-                if dbtx.get_value(&PendingTransactionKey(txid)).await.is_some() {
-                    bail!("Already received a threshold of valid signatures");
-                }
+                self.process_peg_out_signature(dbtx, peg_out_signature.txid, |psbt| {
+                    self.sign_taproot_peg_out_psbt(psbt, peer, &peg_out_signature)
+                })
+                .await?;
+            }
+            WalletConsensusItem::ConsolidationVote(block_count) => {
+                ensure!(
+                    CONSOLIDATION_MODULE_CONSENSUS_VERSION
+                        <= self.consensus_module_consensus_version(dbtx).await,
+                    "Consolidation votes are not supported yet"
+                );
 
-                let mut unsigned = dbtx
-                    .get_value(&UnsignedTransactionKey(txid))
-                    .await
-                    .context("Unsigned transaction does not exist")?;
+                ensure!(
+                    block_count == self.consensus_block_count(dbtx).await,
+                    "Consolidation vote is not for the consensus block count"
+                );
 
-                self.sign_peg_out_psbt(&mut unsigned.psbt, peer, &peg_out_signature)
-                    .context("Peg out signature is invalid")?;
+                if Some(block_count)
+                    == dbtx
+                        .insert_entry(&ConsolidationVoteKey(peer), &block_count)
+                        .await
+                {
+                    bail!("Consolidation vote is redundant");
+                }
 
-                dbtx.insert_entry(&UnsignedTransactionKey(txid), &unsigned)
-                    .await;
+                self.process_consolidation_votes(dbtx, block_count).await;
+            }
+            WalletConsensusItem::CpfpVote(txid) => {
+                ensure!(
+                    CPFP_MODULE_CONSENSUS_VERSION
+                        <= self.consensus_module_consensus_version(dbtx).await,
+                    "CPFP votes are not supported yet"
+                );
 
-                if let Ok(pending_tx) = self.finalize_peg_out_psbt(unsigned) {
-                    // We were able to finalize the transaction, so we will delete the
-                    // PSBT and instead keep the extracted tx for periodic transmission
-                    // as well as to accept the change into our wallet eventually once
-                    // it confirms.
-                    dbtx.insert_new_entry(&PendingTransactionKey(txid), &pending_tx)
-                        .await;
-
-                    dbtx.remove_entry(&PegOutTxSignatureCI(txid)).await;
-                    dbtx.remove_entry(&UnsignedTransactionKey(txid)).await;
-                    let broadcast_pending = self.broadcast_pending.clone();
-                    dbtx.on_commit(move || {
-                        broadcast_pending.notify_one();
-                    });
+                ensure!(
+                    dbtx.get_value(&PendingTransactionKey(txid)).await.is_some(),
+                    "CPFP vote is not for a pending transaction"
+                );
+
+                if Some(txid) == dbtx.insert_entry(&CpfpVoteKey(peer), &txid).await {
+                    bail!("CPFP vote is redundant");
                 }
+
+                self.process_cpfp_votes(dbtx, txid).await;
             }
             WalletConsensusItem::ModuleConsensusVersion(module_consensus_version) => {
                 let current_vote = dbtx
@@ -792,6 +935,7 @@ async fn process_input<'a, 'b, 'c>(
         let fee = self.cfg.consensus.fee_consensus.peg_in_abs;
 
         calculate_pegin_metrics(dbtx, amount, fee);
+        self.credit_fee_reserve(dbtx, fee).await;
 
         Ok(InputMeta {
             amount: TransactionItemAmounts {
@@ -808,7 +952,20 @@ async fn process_output<'a, 'b>(
         output: &'a WalletOutput,
         out_point: OutPoint,
     ) -> Result<TransactionItemAmounts, WalletOutputError> {
-        let output = output.ensure_v0_ref()?;
+        let output = match output {
+            WalletOutput::V0(output) => output,
+            WalletOutput::V1(WalletOutputV1::BatchedPegOut(peg_out)) => {
+                return self.queue_batched_peg_out(dbtx, peg_out, out_point).await;
+            }
+            WalletOutput::V1(WalletOutputV1::MultiPegOut(peg_out)) => {
+                return self.process_multi_peg_out(dbtx, peg_out, out_point).await;
+            }
+            WalletOutput::Default { variant, .. } => {
+                return Err(WalletOutputError::UnknownOutputVariant(
+                    UnknownWalletOutputVariantError { variant: *variant },
+                ));
+            }
+        };
 
         // In 0.4.0 we began preventing RBF withdrawals. Once we reach EoL support
         // for 0.4.0, we can safely remove RBF withdrawal logic.
@@ -828,71 +985,15 @@ async fn process_output<'a, 'b>(
 
         let change_tweak = self.consensus_nonce(dbtx).await;
 
-        let mut tx = self.create_peg_out_tx(dbtx, output, &change_tweak).await?;
+        let tx = self.create_peg_out_tx(dbtx, output, &change_tweak).await?;
 
         let fee_rate = self.consensus_fee_rate(dbtx).await;
 
         StatelessWallet::validate_tx(&tx, output, fee_rate, self.cfg.consensus.network.0)?;
 
-        self.offline_wallet().sign_psbt(&mut tx.psbt);
-
-        let txid = tx.psbt.unsigned_tx.compute_txid();
-
-        info!(
-            target: LOG_MODULE_WALLET,
-            %txid,
-            "Signing peg out",
-        );
-
-        let sigs = tx
-            .psbt
-            .inputs
-            .iter_mut()
-            .map(|input| {
-                assert_eq!(
-                    input.partial_sigs.len(),
-                    1,
-                    "There was already more than one (our) or no signatures in input"
-                );
-
-                // TODO: don't put sig into PSBT in the first place
-                // We actually take out our own signature so everyone finalizes the tx in the
-                // same epoch.
-                let sig = std::mem::take(&mut input.partial_sigs)
-                    .into_values()
-                    .next()
-                    .expect("asserted previously");
-
-                // We drop SIGHASH_ALL, because we always use that and it is only present in the
-                // PSBT for compatibility with other tools.
-                secp256k1::ecdsa::Signature::from_der(&sig.to_vec()[..sig.to_vec().len() - 1])
-                    .expect("we serialized it ourselves that way")
-            })
-            .collect::<Vec<_>>();
-
-        // Delete used UTXOs
-        for input in &tx.psbt.unsigned_tx.input {
-            dbtx.remove_entry(&UTXOKey(input.previous_output)).await;
-        }
-
-        dbtx.insert_new_entry(&UnsignedTransactionKey(txid), &tx)
-            .await;
-
-        dbtx.insert_new_entry(&PegOutTxSignatureCI(txid), &sigs)
-            .await;
-
-        dbtx.insert_new_entry(
-            &PegOutBitcoinTransaction(out_point),
-            &WalletOutputOutcome::new_v0(txid),
-        )
-        .await;
-        let amount: fedimint_core::Amount = output.amount().into();
-        let fee = self.cfg.consensus.fee_consensus.peg_out_abs;
-        calculate_pegout_metrics(dbtx, amount, fee);
-        Ok(TransactionItemAmounts {
-            amounts: Amounts::new_bitcoin(amount),
-            fees: Amounts::new_bitcoin(fee),
-        })
+        Ok(self
+            .sign_and_record_peg_out(dbtx, tx, output.amount(), out_point)
+            .await)
     }
 
     async fn output_status(
@@ -936,6 +1037,11 @@ async fn audit(
                 },
             )
             .await;
+        audit
+            .add_items(dbtx, module_instance_id, &QueuedPegOutPrefix, |_, v| {
+                (v.peg_out.amount + v.peg_out.fees.amount()).to_sat() as i64 * -1000
+            })
+            .await;
     }
 
     fn api_endpoints(&self) -> Vec<ApiEndpoint<Self>> {
@@ -969,11 +1075,13 @@ fn api_endpoints(&self) -> Vec<ApiEndpoint<Self>> {
                     let dummy_tweak = [0; 33];
 
                     let tx = module.offline_wallet().create_tx(
-                        bitcoin::Amount::from_sat(sats),
-                        // Note: While calling `assume_checked()` is generally unwise, it's fine
-                        // here since we're only returning a fee estimate, and we would still
-                        // reject a transaction with the wrong network upon attempted peg-out.
-                        address.assume_checked().script_pubkey(),
+                        vec![TxOut {
+                            value: bitcoin::Amount::from_sat(sats),
+                            // Note: While calling `assume_checked()` is generally unwise, it's fine
+                            // here since we're only returning a fee estimate, and we would still
+                            // reject a transaction with the wrong network upon attempted peg-out.
+                            script_pubkey: address.assume_checked().script_pubkey(),
+                        }],
                         vec![],
                         module.available_utxos(&mut dbtx).await,
                         feerate,
@@ -991,6 +1099,96 @@ fn api_endpoints(&self) -> Vec<ApiEndpoint<Self>> {
                     }
                 }
             },
+            api_endpoint! {
+                MULTI_PEG_OUT_FEES_ENDPOINT,
+                ApiVersion::new(0, 4),
+                async |module: &Wallet, context, params: Vec<(Address<NetworkUnchecked>, u64)>| -> Option<PegOutFees> {
+                    if params.is_empty() || MAX_PEG_OUT_RECIPIENTS < params.len() {
+                        return Ok(None);
+                    }
+
+                    let db = context.db();
+                    let mut dbtx = db.begin_transaction_nc().await;
+
+                    if module.consensus_module_consensus_version(&mut dbtx).await
+                        < MULTI_PEG_OUT_MODULE_CONSENSUS_VERSION
+                    {
+                        return Ok(None);
+                    }
+
+                    let feerate = module.consensus_fee_rate(&mut dbtx).await;
+
+                    // Since we are only calculating the tx size we can use an arbitrary dummy nonce.
+                    let dummy_tweak = [0; 33];
+
+                    let tx = module.offline_wallet().create_tx(
+                        params
+                            .into_iter()
+                            .map(|(address, sats)| TxOut {
+                                value: bitcoin::Amount::from_sat(sats),
+                                // The network is validated upon attempted peg-out, see
+                                // `PEG_OUT_FEES_ENDPOINT`
+                                script_pubkey: address.assume_checked().script_pubkey(),
+                            })
+                            .collect(),
+                        vec![],
+                        module.available_utxos(&mut dbtx).await,
+                        feerate,
+                        &dummy_tweak,
+                        None
+                    );
+
+                    match tx {
+                        Err(error) => {
+                            warn!(target: LOG_MODULE_WALLET, "Error returning multi peg-out fees {error}");
+                            Ok(None)
+                        }
+                        Ok(tx) => Ok(Some(tx.fees))
+                    }
+                }
+            },
+            api_endpoint! {
+                BATCHED_PEG_OUT_FEES_ENDPOINT,
+                ApiVersion::new(0, 3),
+                async |module: &Wallet, context, params: (Address<NetworkUnchecked>, u64)| -> Option<PegOutFees> {
+                    if module.cfg.consensus.peg_out_batching.is_none() {
+                        return Ok(None);
+                    }
+
+                    // The amount doesn't influence the fees of a batched peg-out, it is only part
+                    // of the request to mirror the fee quote of regular peg-outs.
+                    let (address, _sats) = params;
+                    let db = context.db();
+                    let mut dbtx = db.begin_transaction_nc().await;
+
+                    Ok(Some(PegOutFees {
+                        fee_rate: module.consensus_fee_rate(&mut dbtx).await,
+                        total_weight: module
+                            .offline_wallet()
+                            .batched_peg_out_weight(&address.assume_checked().script_pubkey()),
+                    }))
+                }
+            },
+            api_endpoint! {
+                AWAIT_PEG_OUT_BATCH_ENDPOINT,
+                ApiVersion::new(0, 3),
+                async |_module: &Wallet, context, out_point: OutPoint| -> Option<bitcoin::Txid> {
+                    // Clients poll again if the batch isn't paid before the timeout
+                    let Ok(outcome) = timeout(
+                        Duration::from_secs(60),
+                        context.wait_key_exists(PegOutBitcoinTransaction(out_point)),
+                    )
+                    .await
+                    else {
+                        return Ok(None);
+                    };
+
+                    outcome
+                        .ensure_v0_ref()
+                        .map(|outcome| Some(outcome.0))
+                        .map_err(|e| ApiError::server_error(e.to_string()))
+                }
+            },
             api_endpoint! {
                 BITCOIN_KIND_ENDPOINT,
                 ApiVersion::new(0, 1),
@@ -1138,6 +1336,25 @@ fn calculate_pegout_metrics(
     });
 }
 
+/// Whether our bitcoin backend agrees with our peers on the chain, as verified
+/// whenever we sync up to a new consensus block count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainTipStatus {
+    /// No consensus block hash was verified since startup, e.g. because
+    /// [`BLOCK_HASH_MODULE_CONSENSUS_VERSION`] is not active yet
+    Unverified,
+    /// Our bitcoin backend contains the block our peers agreed on
+    InSync { height: u32, block_hash: BlockHash },
+    /// Our bitcoin backend has a different block at the agreed height, either
+    /// because of a reorg it has not caught up with yet or because it follows
+    /// a different chain. Syncing is paused until the hashes match again.
+    Diverged {
+        height: u32,
+        consensus_block_hash: BlockHash,
+        local_block_hash: BlockHash,
+    },
+}
+
 #[derive(Debug)]
 pub struct Wallet {
     cfg: WalletConfig,
@@ -1152,6 +1369,9 @@ pub struct Wallet {
     /// automatically activate new consensus versions as soon as everyone
     /// upgrades.
     peer_supported_consensus_version: watch::Receiver<Option<ModuleConsensusVersion>>,
+    /// Result of the last verification of a consensus block hash against our
+    /// bitcoin backend
+    chain_tip_status: watch::Sender<ChainTipStatus>,
 }
 
 impl Wallet {
@@ -1194,55 +1414,169 @@ pub async fn new(
             task_group: task_group.clone(),
             peer_supported_consensus_version,
             broadcast_pending,
+            chain_tip_status: watch::Sender::new(ChainTipStatus::Unverified),
         };
 
         Ok(wallet)
     }
 
-    /// Try to attach signatures to a pending peg-out tx.
-    fn sign_peg_out_psbt(
+    /// Records a peer's block count vote and syncs up to the new consensus
+    /// block count if the vote advanced it
+    async fn process_block_count_vote(
         &self,
-        psbt: &mut Psbt,
+        dbtx: &mut DatabaseTransaction<'_>,
         peer: PeerId,
-        signature: &PegOutSignatureItem,
-    ) -> Result<(), ProcessPegOutSigError> {
-        let peer_key = self
-            .cfg
-            .consensus
-            .peer_peg_in_keys
-            .get(&peer)
-            .expect("always called with valid peer id");
+        block_count_vote: u32,
+    ) -> anyhow::Result<()> {
+        let current_vote = dbtx.get_value(&BlockCountVoteKey(peer)).await.unwrap_or(0);
 
-        if psbt.inputs.len() != signature.signature.len() {
-            return Err(ProcessPegOutSigError::WrongSignatureCount(
-                psbt.inputs.len(),
-                signature.signature.len(),
-            ));
+        if block_count_vote < current_vote {
+            warn!(target: LOG_MODULE_WALLET, ?peer, ?block_count_vote, "Block count vote is outdated");
         }
 
-        let mut tx_hasher = SighashCache::new(&psbt.unsigned_tx);
-        for (idx, (input, signature)) in psbt
-            .inputs
-            .iter_mut()
-            .zip(signature.signature.iter())
-            .enumerate()
-        {
-            let tx_hash = tx_hasher
-                .p2wsh_signature_hash(
-                    idx,
-                    input
-                        .witness_script
-                        .as_ref()
-                        .expect("Missing witness script"),
-                    input.witness_utxo.as_ref().expect("Missing UTXO").value,
-                    EcdsaSighashType::All,
-                )
-                .map_err(|_| ProcessPegOutSigError::SighashError)?;
+        ensure!(
+            block_count_vote > current_vote,
+            "Block count vote is redundant"
+        );
 
-            let tweak = input
-                .proprietary
-                .get(&proprietary_tweak_key())
-                .expect("we saved it with a tweak");
+        let old_consensus_block_count = self.consensus_block_count(dbtx).await;
+
+        dbtx.insert_entry(&BlockCountVoteKey(peer), &block_count_vote)
+            .await;
+
+        let new_consensus_block_count = self.consensus_block_count(dbtx).await;
+
+        debug!(
+            target: LOG_MODULE_WALLET,
+            ?peer,
+            ?current_vote,
+            ?block_count_vote,
+            ?old_consensus_block_count,
+            ?new_consensus_block_count,
+            "Received block count vote"
+        );
+
+        assert!(old_consensus_block_count <= new_consensus_block_count);
+
+        if new_consensus_block_count != old_consensus_block_count {
+            // We do not sync blocks that predate the federation itself
+            if old_consensus_block_count != 0 {
+                self.sync_up_to_consensus_count(
+                    dbtx,
+                    old_consensus_block_count,
+                    new_consensus_block_count,
+                )
+                .await;
+            } else {
+                info!(
+                    target: LOG_MODULE_WALLET,
+                    ?old_consensus_block_count,
+                    ?new_consensus_block_count,
+                    "Not syncing up to consensus block count because we are at block 0"
+                );
+            }
+
+            self.process_peg_out_batch(dbtx, new_consensus_block_count)
+                .await;
+        }
+
+        Ok(())
+    }
+
+    /// Attaches a peer's signatures to a pending peg-out tx with `sign` and
+    /// finalizes it once we have a threshold of signatures
+    async fn process_peg_out_signature(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        txid: Txid,
+        sign: impl FnOnce(&mut Psbt) -> Result<(), ProcessPegOutSigError>,
+    ) -> anyhow::Result<()> {
+        if dbtx.get_value(&PendingTransactionKey(txid)).await.is_some() {
+            bail!("Already received a threshold of valid signatures");
+        }
+
+        let mut unsigned = dbtx
+            .get_value(&UnsignedTransactionKey(txid))
+            .await
+            .context("Unsigned transaction does not exist")?;
+
+        sign(&mut unsigned.psbt).context("Peg out signature is invalid")?;
+
+        dbtx.insert_entry(&UnsignedTransactionKey(txid), &unsigned)
+            .await;
+
+        if let Ok(pending_tx) = self.finalize_peg_out_psbt(unsigned) {
+            // We were able to finalize the transaction, so we will delete the
+            // PSBT and instead keep the extracted tx for periodic transmission
+            // as well as to accept the change into our wallet eventually once
+            // it confirms.
+            dbtx.insert_new_entry(&PendingTransactionKey(txid), &pending_tx)
+                .await;
+
+            let block_count = self.consensus_block_count(dbtx).await;
+            dbtx.insert_new_entry(&PendingTransactionBlockCountKey(txid), &block_count)
+                .await;
+
+            dbtx.remove_entry(&PegOutTxSignatureCI(txid)).await;
+            dbtx.remove_entry(&TaprootPegOutTxSignatureCI(txid)).await;
+            dbtx.remove_entry(&UnsignedTransactionKey(txid)).await;
+            let broadcast_pending = self.broadcast_pending.clone();
+            dbtx.on_commit(move || {
+                broadcast_pending.notify_one();
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Try to attach signatures to a pending peg-out tx.
+    fn sign_peg_out_psbt(
+        &self,
+        psbt: &mut Psbt,
+        peer: PeerId,
+        signature: &PegOutSignatureItem,
+    ) -> Result<(), ProcessPegOutSigError> {
+        if matches!(self.cfg.consensus.peg_in_descriptor, Descriptor::Tr(_)) {
+            return Err(ProcessPegOutSigError::WrongSignatureType);
+        }
+
+        let peer_key = self
+            .cfg
+            .consensus
+            .peer_peg_in_keys
+            .get(&peer)
+            .expect("always called with valid peer id");
+
+        if psbt.inputs.len() != signature.signature.len() {
+            return Err(ProcessPegOutSigError::WrongSignatureCount(
+                psbt.inputs.len(),
+                signature.signature.len(),
+            ));
+        }
+
+        let mut tx_hasher = SighashCache::new(&psbt.unsigned_tx);
+        for (idx, (input, signature)) in psbt
+            .inputs
+            .iter_mut()
+            .zip(signature.signature.iter())
+            .enumerate()
+        {
+            let tx_hash = tx_hasher
+                .p2wsh_signature_hash(
+                    idx,
+                    input
+                        .witness_script
+                        .as_ref()
+                        .expect("Missing witness script"),
+                    input.witness_utxo.as_ref().expect("Missing UTXO").value,
+                    EcdsaSighashType::All,
+                )
+                .map_err(|_| ProcessPegOutSigError::SighashError)?;
+
+            let tweak = input
+                .proprietary
+                .get(&proprietary_tweak_key())
+                .expect("we saved it with a tweak");
 
             let tweaked_peer_key = peer_key.tweak(tweak, &self.secp);
             self.secp
@@ -1265,6 +1599,87 @@ fn sign_peg_out_psbt(
         Ok(())
     }
 
+    /// Try to attach Schnorr signatures to a pending peg-out tx spending
+    /// outputs of a taproot peg-in descriptor.
+    fn sign_taproot_peg_out_psbt(
+        &self,
+        psbt: &mut Psbt,
+        peer: PeerId,
+        signature: &TaprootPegOutSignatureItem,
+    ) -> Result<(), ProcessPegOutSigError> {
+        if !matches!(self.cfg.consensus.peg_in_descriptor, Descriptor::Tr(_)) {
+            return Err(ProcessPegOutSigError::WrongSignatureType);
+        }
+
+        let peer_key = self
+            .cfg
+            .consensus
+            .peer_peg_in_keys
+            .get(&peer)
+            .expect("always called with valid peer id");
+
+        if psbt.inputs.len() != signature.signature.len() {
+            return Err(ProcessPegOutSigError::WrongSignatureCount(
+                psbt.inputs.len(),
+                signature.signature.len(),
+            ));
+        }
+
+        let prevouts = psbt_prevouts(psbt);
+        let mut tx_hasher = SighashCache::new(&psbt.unsigned_tx);
+        for (idx, (input, signature)) in psbt
+            .inputs
+            .iter_mut()
+            .zip(signature.signature.iter())
+            .enumerate()
+        {
+            let (tx_hash, leaf_hash) =
+                taproot_signature_hash(&mut tx_hasher, idx, input, &prevouts)?;
+
+            let tweak = input
+                .proprietary
+                .get(&proprietary_tweak_key())
+                .expect("we saved it with a tweak");
+
+            let tweaked_peer_key = peer_key.tweak(tweak, &self.secp).key.x_only_public_key().0;
+            let signing_key = match leaf_hash {
+                Some(_) => tweaked_peer_key,
+                // Only the key of a single guardian federation can spend through the key path
+                None => tweaked_peer_key
+                    .tap_tweak(&self.secp, input.tap_merkle_root)
+                    .0
+                    .to_x_only_public_key(),
+            };
+
+            self.secp
+                .verify_schnorr(
+                    signature,
+                    &Message::from_digest_slice(&tx_hash[..]).unwrap(),
+                    &signing_key,
+                )
+                .map_err(|_| ProcessPegOutSigError::InvalidSignature)?;
+
+            let signature = bitcoin::taproot::Signature {
+                signature: *signature,
+                sighash_type: TapSighashType::Default,
+            };
+
+            let duplicate = match leaf_hash {
+                Some(leaf_hash) => input
+                    .tap_script_sigs
+                    .insert((tweaked_peer_key, leaf_hash), signature)
+                    .is_some(),
+                None => input.tap_key_sig.replace(signature).is_some(),
+            };
+
+            if duplicate {
+                // Should never happen since peers only sign a PSBT once
+                return Err(ProcessPegOutSigError::DuplicateSignature);
+            }
+        }
+        Ok(())
+    }
+
     fn finalize_peg_out_psbt(
         &self,
         mut unsigned: UnsignedTransaction,
@@ -1312,124 +1727,530 @@ fn get_block_count(&self) -> anyhow::Result<u32> {
             })
     }
 
-    pub fn get_fee_rate_opt(&self) -> Feerate {
-        // `get_feerate_multiplier` is clamped and can't be negative
-        // feerate sources as clamped and can't be negative or too large
-        #[allow(clippy::cast_precision_loss)]
-        #[allow(clippy::cast_sign_loss)]
-        Feerate {
-            sats_per_kvb: ((self
-                .btc_rpc
-                .status()
-                .map_or(self.cfg.consensus.default_fee, |status| status.fee_rate)
-                .sats_per_kvb as f64
-                * get_feerate_multiplier())
-            .round()) as u64,
+    /// Our block count vote, which includes the hash of our chain tip once our
+    /// peers agreed to verify it
+    async fn block_count_vote_item(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        block_count_vote: u32,
+    ) -> Option<WalletConsensusItem> {
+        if self.consensus_module_consensus_version(dbtx).await < BLOCK_HASH_MODULE_CONSENSUS_VERSION
+        {
+            return Some(WalletConsensusItem::BlockCount(block_count_vote));
         }
-    }
 
-    pub async fn consensus_block_count(&self, dbtx: &mut DatabaseTransaction<'_>) -> u32 {
-        let peer_count = self.cfg.consensus.peer_peg_in_keys.to_num_peers().total();
+        let tip_height = block_count_vote.checked_sub(1)?;
 
-        let mut counts = dbtx
-            .find_by_prefix(&BlockCountVotePrefix)
-            .await
-            .map(|entry| entry.1)
-            .collect::<Vec<u32>>()
-            .await;
+        match self.btc_rpc.get_block_hash(u64::from(tip_height)).await {
+            Ok(block_hash) => Some(WalletConsensusItem::BlockCountHash(BlockCountHashItem {
+                block_count: block_count_vote,
+                block_hash,
+            })),
+            Err(err) => {
+                warn!(target: LOG_MODULE_WALLET, err = %err.fmt_compact_anyhow(), tip_height, "Can't get block hash for block count vote");
+                None
+            }
+        }
+    }
 
-        assert!(counts.len() <= peer_count);
+    /// Our vote to consolidate UTXOs at the current consensus block count if
+    /// the consensus fee rate is below our configured maximum and we hold
+    /// enough UTXOs
+    async fn consolidation_vote_item(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+    ) -> Option<WalletConsensusItem> {
+        let max_fee_rate = get_consolidation_max_feerate()?;
 
-        while counts.len() < peer_count {
-            counts.push(0);
+        if self.consensus_module_consensus_version(dbtx).await
+            < CONSOLIDATION_MODULE_CONSENSUS_VERSION
+        {
+            return None;
         }
 
-        counts.sort_unstable();
+        if max_fee_rate < self.consensus_fee_rate(dbtx).await {
+            return None;
+        }
 
-        counts[peer_count / 2]
-    }
+        if self.available_utxos(dbtx).await.len() < get_consolidation_min_utxos() {
+            return None;
+        }
 
-    pub async fn consensus_fee_rate(&self, dbtx: &mut DatabaseTransaction<'_>) -> Feerate {
-        let peer_count = self.cfg.consensus.peer_peg_in_keys.to_num_peers().total();
+        let block_count = self.consensus_block_count(dbtx).await;
 
-        let mut rates = dbtx
-            .find_by_prefix(&FeeRateVotePrefix)
+        if dbtx
+            .get_value(&ConsolidationVoteKey(self.our_peer_id))
             .await
-            .map(|(.., rate)| rate)
-            .collect::<Vec<_>>()
-            .await;
-
-        assert!(rates.len() <= peer_count);
-
-        while rates.len() < peer_count {
-            rates.push(self.cfg.consensus.default_fee);
+            == Some(block_count)
+        {
+            return None;
         }
 
-        rates.sort_unstable();
-
-        rates[peer_count / 2]
+        Some(WalletConsensusItem::ConsolidationVote(block_count))
     }
 
-    async fn consensus_module_consensus_version(
+    /// Sweeps our smallest UTXOs into a single one once exactly a threshold of
+    /// peers voted to consolidate at `block_count`, later votes for the same
+    /// block count don't trigger another consolidation.
+    ///
+    /// The consolidation tx is signed like a peg-out tx, so it is finalized by
+    /// the peers' [`PegOutSignatureItem`]s.
+    async fn process_consolidation_votes(
         &self,
         dbtx: &mut DatabaseTransaction<'_>,
-    ) -> ModuleConsensusVersion {
-        let num_peers = self.cfg.consensus.peer_peg_in_keys.to_num_peers();
-
-        let mut versions = dbtx
-            .find_by_prefix(&ConsensusVersionVotePrefix)
+        block_count: u32,
+    ) {
+        let votes = dbtx
+            .find_by_prefix(&ConsolidationVotePrefix)
             .await
-            .map(|entry| entry.1)
-            .collect::<Vec<ModuleConsensusVersion>>()
+            .filter(|(_, vote)| std::future::ready(*vote == block_count))
+            .count()
             .await;
 
-        while versions.len() < num_peers.total() {
-            versions.push(ModuleConsensusVersion::new(2, 0));
+        if votes
+            != self
+                .cfg
+                .consensus
+                .peer_peg_in_keys
+                .to_num_peers()
+                .threshold()
+        {
+            return;
         }
 
-        assert_eq!(versions.len(), num_peers.total());
+        let fee_rate = self.consensus_fee_rate(dbtx).await;
+        let change_tweak = self.consensus_nonce(dbtx).await;
 
-        versions.sort_unstable();
+        let fee_per_removed_utxo =
+            bitcoin::Amount::from_sat(self.cfg.consensus.fee_consensus.peg_in_abs.msats / 1000);
 
-        assert!(versions.first() <= versions.last());
+        let tx = match self.offline_wallet().create_consolidation_tx(
+            self.available_utxos(dbtx).await,
+            fee_rate,
+            fee_per_removed_utxo,
+            &change_tweak,
+        ) {
+            Ok(tx) => tx,
+            Err(err) => {
+                warn!(
+                    target: LOG_MODULE_WALLET,
+                    err = %err.fmt_compact_anyhow(),
+                    block_count,
+                    "Can't consolidate UTXOs"
+                );
+                return;
+            }
+        };
 
-        versions[num_peers.max_evil()]
-    }
+        let consolidation = Consolidation {
+            block_count,
+            inputs: tx.selected_utxos.len() as u64,
+            amount: tx.change,
+            fees: tx.fees,
+        };
 
-    pub async fn consensus_nonce(&self, dbtx: &mut DatabaseTransaction<'_>) -> [u8; 33] {
-        let nonce_idx = dbtx.get_value(&PegOutNonceKey).await.unwrap_or(0);
-        dbtx.insert_entry(&PegOutNonceKey, &(nonce_idx + 1)).await;
+        let txid = self.sign_peg_out_tx(dbtx, tx).await;
 
-        nonce_from_idx(nonce_idx)
-    }
+        self.debit_fee_reserve(dbtx, consolidation.fees.amount())
+            .await;
 
-    async fn sync_up_to_consensus_count(
-        &self,
-        dbtx: &mut DatabaseTransaction<'_>,
-        old_count: u32,
-        new_count: u32,
-    ) {
         info!(
             target: LOG_MODULE_WALLET,
-            old_count,
-            new_count,
-            blocks_to_go = new_count - old_count,
-            "New block count consensus, initiating sync",
+            %txid,
+            block_count,
+            inputs = consolidation.inputs,
+            amount = %consolidation.amount,
+            "Consolidating UTXOs",
         );
 
-        // Before we can safely call our bitcoin backend to process the new consensus
-        // count, we need to ensure we observed enough confirmations
-        self.wait_for_finality_confs_or_shutdown(new_count).await;
+        dbtx.insert_new_entry(&ConsolidationKey(txid), &consolidation)
+            .await;
+    }
 
-        for height in old_count..new_count {
-            info!(
-                target: LOG_MODULE_WALLET,
-                height,
-                "Processing block of height {height}",
-            );
+    /// Our vote to bump the oldest of our transactions that is unconfirmed for
+    /// at least our configured number of blocks and pays less than the
+    /// consensus fee rate
+    async fn cpfp_vote_item(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+    ) -> Option<WalletConsensusItem> {
+        let min_age = get_cpfp_min_age()?;
 
-            // TODO: use batching for mainnet syncing
-            trace!(block = height, "Fetching block hash");
+        if self.consensus_module_consensus_version(dbtx).await < CPFP_MODULE_CONSENSUS_VERSION {
+            return None;
+        }
+
+        let block_count = self.consensus_block_count(dbtx).await;
+        let fee_rate = self.consensus_fee_rate(dbtx).await;
+
+        // All peers pick the oldest transaction so their votes converge
+        let (txid, _) = self
+            .cpfp_candidates(dbtx, fee_rate)
+            .await
+            .into_iter()
+            .filter(|(_, since)| since.saturating_add(min_age) <= block_count)
+            .min_by_key(|(txid, since)| (*since, *txid))?;
+
+        if dbtx.get_value(&CpfpVoteKey(self.our_peer_id)).await == Some(txid) {
+            return None;
+        }
+
+        Some(WalletConsensusItem::CpfpVote(txid))
+    }
+
+    /// Our pending transactions paying less than `fee_rate` that can be bumped
+    /// with a CPFP child, together with the consensus block count at which
+    /// they reached threshold signatures
+    async fn cpfp_candidates(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        fee_rate: Feerate,
+    ) -> Vec<(Txid, u32)> {
+        let pending_transactions = dbtx
+            .find_by_prefix(&PendingTransactionPrefixKey)
+            .await
+            .map(|(key, transaction)| (key.0, transaction))
+            .collect::<BTreeMap<Txid, PendingTransaction>>()
+            .await;
+
+        let cpfps = dbtx
+            .find_by_prefix(&CpfpPrefix)
+            .await
+            .map(|(key, cpfp)| (key.0, cpfp))
+            .collect::<BTreeMap<Txid, Cpfp>>()
+            .await;
+
+        let mut candidates = vec![];
+
+        for (txid, transaction) in &pending_transactions {
+            // A child would be invalidated as soon as an RBF replacement confirms
+            let is_rbf = transaction.rbf.is_some()
+                || pending_transactions
+                    .values()
+                    .any(|other| other.rbf.as_ref().is_some_and(|rbf| rbf.txid == *txid));
+
+            // We bump every transaction at most once and never bump a child
+            let is_cpfp =
+                cpfps.contains_key(txid) || cpfps.values().any(|cpfp| cpfp.child_txid == *txid);
+
+            if is_rbf || is_cpfp || fee_rate <= transaction.fees.fee_rate {
+                continue;
+            }
+
+            // Transactions finalized before CPFP was activated have no block count
+            if let Some(since) = dbtx
+                .get_value(&PendingTransactionBlockCountKey(*txid))
+                .await
+            {
+                candidates.push((*txid, since));
+            }
+        }
+
+        candidates
+    }
+
+    /// Bumps the fee of our pending transaction `txid` with a CPFP child once
+    /// exactly a threshold of peers voted for it, paying the child's fees from
+    /// the fee reserve.
+    ///
+    /// The child tx is signed like a peg-out tx, so it is finalized by the
+    /// peers' [`PegOutSignatureItem`]s.
+    async fn process_cpfp_votes(&self, dbtx: &mut DatabaseTransaction<'_>, txid: Txid) {
+        let votes = dbtx
+            .find_by_prefix(&CpfpVotePrefix)
+            .await
+            .filter(|(_, vote)| std::future::ready(*vote == txid))
+            .count()
+            .await;
+
+        if votes
+            != self
+                .cfg
+                .consensus
+                .peer_peg_in_keys
+                .to_num_peers()
+                .threshold()
+        {
+            return;
+        }
+
+        let fee_rate = self.consensus_fee_rate(dbtx).await;
+
+        if !self
+            .cpfp_candidates(dbtx, fee_rate)
+            .await
+            .iter()
+            .any(|(candidate, _)| *candidate == txid)
+        {
+            warn!(
+                target: LOG_MODULE_WALLET,
+                %txid,
+                "Transaction can't be bumped with a CPFP child"
+            );
+            return;
+        }
+
+        let parent = dbtx
+            .get_value(&PendingTransactionKey(txid))
+            .await
+            .expect("CPFP candidates are pending");
+
+        let fee_reserve = self.fee_reserve(dbtx).await;
+        let change_tweak = self.consensus_nonce(dbtx).await;
+
+        let (child, fee) = match self.offline_wallet().create_cpfp_tx(
+            &parent,
+            fee_rate,
+            fee_reserve,
+            &change_tweak,
+        ) {
+            Ok(child_and_fee) => child_and_fee,
+            Err(err) => {
+                warn!(
+                    target: LOG_MODULE_WALLET,
+                    err = %err.fmt_compact_anyhow(),
+                    %txid,
+                    "Can't bump transaction with a CPFP child"
+                );
+                return;
+            }
+        };
+
+        let child_txid = self.sign_peg_out_tx(dbtx, child).await;
+
+        self.debit_fee_reserve(dbtx, fee).await;
+
+        // The parent's change is spent by the child, so only the child's change
+        // counts towards our assets from now on
+        dbtx.insert_entry(
+            &PendingTransactionKey(txid),
+            &PendingTransaction {
+                change: bitcoin::Amount::ZERO,
+                ..parent
+            },
+        )
+        .await;
+
+        info!(
+            target: LOG_MODULE_WALLET,
+            %txid,
+            %child_txid,
+            %fee,
+            "Bumping transaction with a CPFP child",
+        );
+
+        let block_count = self.consensus_block_count(dbtx).await;
+
+        dbtx.insert_new_entry(
+            &CpfpKey(txid),
+            &Cpfp {
+                block_count,
+                child_txid,
+                fee,
+            },
+        )
+        .await;
+    }
+
+    async fn fee_reserve(&self, dbtx: &mut DatabaseTransaction<'_>) -> bitcoin::Amount {
+        dbtx.get_value(&FeeReserveKey)
+            .await
+            .unwrap_or(bitcoin::Amount::ZERO)
+    }
+
+    /// Adds the wallet fees of a transaction to the fee reserve paying for
+    /// CPFP children
+    async fn credit_fee_reserve(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        fee: fedimint_core::Amount,
+    ) {
+        if fee == fedimint_core::Amount::ZERO
+            || self.consensus_module_consensus_version(dbtx).await < CPFP_MODULE_CONSENSUS_VERSION
+        {
+            return;
+        }
+
+        let fee_reserve =
+            self.fee_reserve(dbtx).await + bitcoin::Amount::from_sat(fee.msats / 1000);
+
+        dbtx.insert_entry(&FeeReserveKey, &fee_reserve).await;
+    }
+
+    /// Deducts fees the federation pays for its own transactions from the fee
+    /// reserve, so consolidations and CPFP children don't spend the same
+    /// collected fees twice
+    async fn debit_fee_reserve(&self, dbtx: &mut DatabaseTransaction<'_>, fee: bitcoin::Amount) {
+        let fee_reserve = self.fee_reserve(dbtx).await;
+
+        dbtx.insert_entry(
+            &FeeReserveKey,
+            &fee_reserve.checked_sub(fee).unwrap_or_default(),
+        )
+        .await;
+    }
+
+    pub fn get_fee_rate_opt(&self) -> Feerate {
+        // `get_feerate_multiplier` is clamped and can't be negative
+        // feerate sources as clamped and can't be negative or too large
+        #[allow(clippy::cast_precision_loss)]
+        #[allow(clippy::cast_sign_loss)]
+        Feerate {
+            sats_per_kvb: ((self
+                .btc_rpc
+                .status()
+                .map_or(self.cfg.consensus.default_fee, |status| status.fee_rate)
+                .sats_per_kvb as f64
+                * get_feerate_multiplier())
+            .round()) as u64,
+        }
+    }
+
+    pub async fn consensus_block_count(&self, dbtx: &mut DatabaseTransaction<'_>) -> u32 {
+        let peer_count = self.cfg.consensus.peer_peg_in_keys.to_num_peers().total();
+
+        let mut counts = dbtx
+            .find_by_prefix(&BlockCountVotePrefix)
+            .await
+            .map(|entry| entry.1)
+            .collect::<Vec<u32>>()
+            .await;
+
+        assert!(counts.len() <= peer_count);
+
+        while counts.len() < peer_count {
+            counts.push(0);
+        }
+
+        counts.sort_unstable();
+
+        counts[peer_count / 2]
+    }
+
+    /// The hash of the block at height `block_count - 1` if enough peers voted
+    /// for it to include at least one honest peer. Peers vote for their chain
+    /// tip, so only the votes they cast while at `block_count` name it.
+    pub async fn consensus_block_hash(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        block_count: u32,
+    ) -> Option<BlockHash> {
+        let votes = dbtx
+            .find_by_prefix(&BlockHashVotePrefix)
+            .await
+            .flat_map(|(.., votes)| futures::stream::iter(votes))
+            .collect::<Vec<BlockCountHashItem>>()
+            .await;
+
+        consensus_block_hash_from_votes(
+            votes,
+            block_count,
+            self.cfg
+                .consensus
+                .peer_peg_in_keys
+                .to_num_peers()
+                .max_evil(),
+        )
+    }
+
+    pub async fn consensus_fee_rate(&self, dbtx: &mut DatabaseTransaction<'_>) -> Feerate {
+        let peer_count = self.cfg.consensus.peer_peg_in_keys.to_num_peers().total();
+
+        let mut rates = dbtx
+            .find_by_prefix(&FeeRateVotePrefix)
+            .await
+            .map(|(.., rate)| rate)
+            .collect::<Vec<_>>()
+            .await;
+
+        assert!(rates.len() <= peer_count);
+
+        while rates.len() < peer_count {
+            rates.push(self.cfg.consensus.default_fee);
+        }
+
+        rates.sort_unstable();
+
+        rates[peer_count / 2]
+    }
+
+    /// Peers only accept Schnorr signatures once they agreed to support them.
+    /// All peers of a federation with a taproot peg-in descriptor support
+    /// them, as otherwise they couldn't have generated its config, so its
+    /// peg-outs never wait for the consensus version.
+    async fn taproot_signatures_supported(&self, dbtx: &mut DatabaseTransaction<'_>) -> bool {
+        matches!(self.cfg.consensus.peg_in_descriptor, Descriptor::Tr(_))
+            || TAPROOT_MODULE_CONSENSUS_VERSION
+                <= self.consensus_module_consensus_version(dbtx).await
+    }
+
+    async fn consensus_module_consensus_version(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+    ) -> ModuleConsensusVersion {
+        let num_peers = self.cfg.consensus.peer_peg_in_keys.to_num_peers();
+
+        let mut versions = dbtx
+            .find_by_prefix(&ConsensusVersionVotePrefix)
+            .await
+            .map(|entry| entry.1)
+            .collect::<Vec<ModuleConsensusVersion>>()
+            .await;
+
+        while versions.len() < num_peers.total() {
+            versions.push(ModuleConsensusVersion::new(2, 0));
+        }
+
+        assert_eq!(versions.len(), num_peers.total());
+
+        versions.sort_unstable();
+
+        assert!(versions.first() <= versions.last());
+
+        versions[num_peers.max_evil()]
+    }
+
+    pub async fn consensus_nonce(&self, dbtx: &mut DatabaseTransaction<'_>) -> [u8; 33] {
+        let nonce_idx = dbtx.get_value(&PegOutNonceKey).await.unwrap_or(0);
+        dbtx.insert_entry(&PegOutNonceKey, &(nonce_idx + 1)).await;
+
+        nonce_from_idx(nonce_idx)
+    }
+
+    async fn sync_up_to_consensus_count(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        old_count: u32,
+        new_count: u32,
+    ) {
+        info!(
+            target: LOG_MODULE_WALLET,
+            old_count,
+            new_count,
+            blocks_to_go = new_count - old_count,
+            "New block count consensus, initiating sync",
+        );
+
+        // Before we can safely call our bitcoin backend to process the new consensus
+        // count, we need to ensure we observed enough confirmations
+        self.wait_for_finality_confs_or_shutdown(new_count).await;
+
+        // Our peers might agree on the same height but a different chain, so once
+        // they vote on block hashes we only sync from a chain containing theirs
+        if let Some(block_hash) = self.consensus_block_hash(dbtx, new_count).await {
+            self.wait_for_consensus_block_hash_or_shutdown(new_count - 1, block_hash)
+                .await;
+        }
+
+        for height in old_count..new_count {
+            info!(
+                target: LOG_MODULE_WALLET,
+                height,
+                "Processing block of height {height}",
+            );
+
+            // TODO: use batching for mainnet syncing
+            trace!(block = height, "Fetching block hash");
             let block_hash = retry("get_block_hash", backoff_util::background_backoff(), || {
                 self.btc_rpc.get_block_hash(u64::from(height)) // TODO: use u64 for height everywhere
             })
@@ -1474,10 +2295,10 @@ async fn sync_up_to_consensus_count(
                     }
 
                     for (vout, tx_out) in transaction.output.iter().enumerate() {
-                        let should_track_utxo = if self.cfg.consensus.peer_peg_in_keys.len() > 1 {
-                            tx_out.script_pubkey.is_p2wsh()
-                        } else {
-                            tx_out.script_pubkey.is_p2wpkh()
+                        let should_track_utxo = match self.cfg.consensus.peg_in_descriptor {
+                            Descriptor::Tr(_) => tx_out.script_pubkey.is_p2tr(),
+                            Descriptor::Wpkh(_) => tx_out.script_pubkey.is_p2wpkh(),
+                            _ => tx_out.script_pubkey.is_p2wsh(),
                         };
 
                         if should_track_utxo {
@@ -1552,6 +2373,16 @@ async fn recognize_change_utxo(
     ) {
         self.remove_rbf_transactions(dbtx, pending_tx).await;
 
+        // The change was already spent by a CPFP child, whose change we will
+        // recognize instead
+        if dbtx
+            .get_value(&CpfpKey(pending_tx.tx.compute_txid()))
+            .await
+            .is_some()
+        {
+            return;
+        }
+
         let script_pk = self
             .cfg
             .consensus
@@ -1594,6 +2425,8 @@ async fn remove_rbf_transactions(
             all_transactions.remove(&removed.tx.compute_txid());
             dbtx.remove_entry(&PendingTransactionKey(removed.tx.compute_txid()))
                 .await;
+            dbtx.remove_entry(&PendingTransactionBlockCountKey(removed.tx.compute_txid()))
+                .await;
 
             // Search for tx that this `removed` has as RBF
             if let Some(rbf) = &removed.rbf
@@ -1621,44 +2454,455 @@ async fn block_is_known(
         dbtx.get_value(&BlockHashKey(block_hash)).await.is_some()
     }
 
-    async fn create_peg_out_tx(
+    /// Adds our signatures to a peg-out transaction, marks its inputs as spent
+    /// and stores it until a threshold of guardians signed it
+    async fn sign_peg_out_tx(
         &self,
         dbtx: &mut DatabaseTransaction<'_>,
-        output: &WalletOutputV0,
-        change_tweak: &[u8; 33],
-    ) -> Result<UnsignedTransaction, WalletOutputError> {
-        match output {
-            WalletOutputV0::PegOut(peg_out) => self.offline_wallet().create_tx(
-                peg_out.amount,
-                // Note: While calling `assume_checked()` is generally unwise, checking the
-                // network here could be a consensus-breaking change. Ignoring the network
-                // is fine here since we validate it in `process_output()`.
-                peg_out.recipient.clone().assume_checked().script_pubkey(),
-                vec![],
-                self.available_utxos(dbtx).await,
-                peg_out.fees.fee_rate,
-                change_tweak,
-                None,
-            ),
-            WalletOutputV0::Rbf(rbf) => {
-                let tx = dbtx
-                    .get_value(&PendingTransactionKey(rbf.txid))
-                    .await
-                    .ok_or(WalletOutputError::RbfTransactionIdNotFound)?;
+        mut tx: UnsignedTransaction,
+    ) -> Txid {
+        self.offline_wallet().sign_psbt(&mut tx.psbt);
 
-                self.offline_wallet().create_tx(
-                    tx.peg_out_amount,
-                    tx.destination,
-                    tx.selected_utxos,
-                    self.available_utxos(dbtx).await,
-                    tx.fees.fee_rate,
-                    change_tweak,
-                    Some(rbf.clone()),
+        let txid = tx.psbt.unsigned_tx.compute_txid();
+
+        info!(
+            target: LOG_MODULE_WALLET,
+            %txid,
+            "Signing peg out",
+        );
+
+        if matches!(self.cfg.consensus.peg_in_descriptor, Descriptor::Tr(_)) {
+            let sigs = tx
+                .psbt
+                .inputs
+                .iter_mut()
+                .map(|input| {
+                    // We take out our own signature for the same reason as below
+                    let sig = if let Some(sig) = input.tap_key_sig.take() {
+                        sig
+                    } else {
+                        assert_eq!(
+                            input.tap_script_sigs.len(),
+                            1,
+                            "There was already more than one (our) or no signatures in input"
+                        );
+
+                        std::mem::take(&mut input.tap_script_sigs)
+                            .into_values()
+                            .next()
+                            .expect("asserted previously")
+                    };
+
+                    sig.signature
+                })
+                .collect::<Vec<_>>();
+
+            self.remove_spent_utxos(dbtx, &tx).await;
+
+            dbtx.insert_new_entry(&UnsignedTransactionKey(txid), &tx)
+                .await;
+
+            dbtx.insert_new_entry(&TaprootPegOutTxSignatureCI(txid), &sigs)
+                .await;
+
+            return txid;
+        }
+
+        let sigs = tx
+            .psbt
+            .inputs
+            .iter_mut()
+            .map(|input| {
+                assert_eq!(
+                    input.partial_sigs.len(),
+                    1,
+                    "There was already more than one (our) or no signatures in input"
+                );
+
+                // TODO: don't put sig into PSBT in the first place
+                // We actually take out our own signature so everyone finalizes the tx in the
+                // same epoch.
+                let sig = std::mem::take(&mut input.partial_sigs)
+                    .into_values()
+                    .next()
+                    .expect("asserted previously");
+
+                // We drop SIGHASH_ALL, because we always use that and it is only present in the
+                // PSBT for compatibility with other tools.
+                secp256k1::ecdsa::Signature::from_der(&sig.to_vec()[..sig.to_vec().len() - 1])
+                    .expect("we serialized it ourselves that way")
+            })
+            .collect::<Vec<_>>();
+
+        self.remove_spent_utxos(dbtx, &tx).await;
+
+        dbtx.insert_new_entry(&UnsignedTransactionKey(txid), &tx)
+            .await;
+
+        dbtx.insert_new_entry(&PegOutTxSignatureCI(txid), &sigs)
+            .await;
+
+        txid
+    }
+
+    /// Deletes the UTXOs spent by a peg-out tx from our spendable UTXOs
+    async fn remove_spent_utxos(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        tx: &UnsignedTransaction,
+    ) {
+        for input in &tx.psbt.unsigned_tx.input {
+            dbtx.remove_entry(&UTXOKey(input.previous_output)).await;
+        }
+    }
+
+    /// Queues a batched peg-out to be paid with the next batch, see
+    /// [`Wallet::process_peg_out_batch`]
+    async fn queue_batched_peg_out(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        peg_out: &PegOut,
+        out_point: OutPoint,
+    ) -> Result<TransactionItemAmounts, WalletOutputError> {
+        if self.cfg.consensus.peg_out_batching.is_none() {
+            return Err(WalletOutputError::PegOutBatchingDisabled);
+        }
+
+        let fee_rate = self.consensus_fee_rate(dbtx).await;
+
+        self.offline_wallet().validate_batched_peg_out(
+            peg_out,
+            fee_rate,
+            self.cfg.consensus.network.0,
+        )?;
+
+        // Reject peg-outs the federation can't pay rather than queueing them
+        // indefinitely
+        let queued_amount = dbtx
+            .find_by_prefix(&QueuedPegOutPrefix)
+            .await
+            .map(|(_, queued)| queued.peg_out.amount + queued.peg_out.fees.amount())
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .sum::<bitcoin::Amount>();
+
+        if self.get_wallet_value(dbtx).await
+            < queued_amount + peg_out.amount + peg_out.fees.amount()
+        {
+            return Err(WalletOutputError::NotEnoughSpendableUTXO);
+        }
+
+        let block_count = self.consensus_block_count(dbtx).await;
+
+        debug!(
+            target: LOG_MODULE_WALLET,
+            %out_point,
+            amount = %peg_out.amount,
+            block_count,
+            "Queueing batched peg-out",
+        );
+
+        dbtx.insert_new_entry(
+            &QueuedPegOutKey(out_point),
+            &QueuedPegOut {
+                peg_out: peg_out.clone(),
+                block_count,
+            },
+        )
+        .await;
+
+        let amount: fedimint_core::Amount = (peg_out.amount + peg_out.fees.amount()).into();
+        let fee = self.cfg.consensus.fee_consensus.peg_out_abs;
+        calculate_pegout_metrics(dbtx, amount, fee);
+        self.credit_fee_reserve(dbtx, fee).await;
+        Ok(TransactionItemAmounts {
+            amounts: Amounts::new_bitcoin(amount),
+            fees: Amounts::new_bitcoin(fee),
+        })
+    }
+
+    /// Pays all queued batched peg-outs with a single transaction once the
+    /// oldest of them was queued for the batching window.
+    ///
+    /// If the federation can't fund the whole batch, for example because the
+    /// change of earlier peg-outs is still unconfirmed, we pay as many of the
+    /// oldest peg-outs as we can and retry the rest with the next consensus
+    /// block count.
+    async fn process_peg_out_batch(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        consensus_block_count: u32,
+    ) {
+        let Some(batching) = self.cfg.consensus.peg_out_batching else {
+            return;
+        };
+
+        let mut queued = dbtx
+            .find_by_prefix(&QueuedPegOutPrefix)
+            .await
+            .map(|(key, queued)| (key.0, queued))
+            .collect::<Vec<(OutPoint, QueuedPegOut)>>()
+            .await;
+
+        let Some(oldest) = queued.iter().map(|(_, queued)| queued.block_count).min() else {
+            return;
+        };
+
+        if consensus_block_count < oldest.saturating_add(batching.window) {
+            return;
+        }
+
+        // Ensure all peers drop the same peg-outs if the batch can't be funded
+        queued.sort_by_key(|(out_point, queued)| (queued.block_count, *out_point));
+
+        let change_tweak = self.consensus_nonce(dbtx).await;
+        let available_utxos = self.available_utxos(dbtx).await;
+
+        while !queued.is_empty() {
+            match self.create_batch_tx(&queued, available_utxos.clone(), &change_tweak) {
+                Ok(tx) => {
+                    self.pay_peg_out_batch(dbtx, queued, tx).await;
+                    return;
+                }
+                Err(err) => {
+                    warn!(
+                        target: LOG_MODULE_WALLET,
+                        err = %err.fmt_compact(),
+                        peg_outs = queued.len(),
+                        "Can't pay peg-out batch yet"
+                    );
+                    queued.pop();
+                }
+            }
+        }
+    }
+
+    /// Creates the transaction paying the `queued` peg-outs at the lowest fee
+    /// rate they paid
+    fn create_batch_tx(
+        &self,
+        queued: &[(OutPoint, QueuedPegOut)],
+        available_utxos: Vec<(UTXOKey, SpendableUTXO)>,
+        change_tweak: &[u8; 33],
+    ) -> Result<UnsignedTransaction, WalletOutputError> {
+        let peg_outs = queued
+            .iter()
+            .map(|(_, queued)| TxOut {
+                value: queued.peg_out.amount,
+                // Note: The network was validated when the peg-out was queued
+                script_pubkey: queued
+                    .peg_out
+                    .recipient
+                    .clone()
+                    .assume_checked()
+                    .script_pubkey(),
+            })
+            .collect::<Vec<_>>();
+
+        let collected_fees = queued
+            .iter()
+            .map(|(_, queued)| queued.peg_out.fees.amount())
+            .sum::<bitcoin::Amount>();
+
+        // Every queued peg-out paid at least the min relay and consensus fee rate
+        let fee_rate = queued
+            .iter()
+            .map(|(_, queued)| queued.peg_out.fees.fee_rate)
+            .min_by_key(|fee_rate| fee_rate.sats_per_kvb)
+            .expect("Batch is not empty");
+
+        self.offline_wallet().create_batch_tx(
+            peg_outs,
+            collected_fees,
+            fee_rate,
+            available_utxos,
+            change_tweak,
+        )
+    }
+
+    async fn pay_peg_out_batch(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        queued: Vec<(OutPoint, QueuedPegOut)>,
+        tx: UnsignedTransaction,
+    ) {
+        let collected_fees = queued
+            .iter()
+            .map(|(_, queued)| queued.peg_out.fees.amount())
+            .sum::<bitcoin::Amount>();
+
+        // Batches needing more inputs than they have peg-outs cost more than the
+        // peg-outs paid, the fee reserve covers the difference
+        let fees = tx
+            .selected_utxos
+            .iter()
+            .map(|(_, utxo)| utxo.amount)
+            .sum::<bitcoin::Amount>()
+            - tx.change
+            - tx.peg_out_amount;
+        if collected_fees < fees {
+            self.debit_fee_reserve(dbtx, fees - collected_fees).await;
+        }
+
+        let txid = self.sign_peg_out_tx(dbtx, tx).await;
+
+        info!(
+            target: LOG_MODULE_WALLET,
+            %txid,
+            peg_outs = queued.len(),
+            "Paying peg-out batch",
+        );
+
+        for (out_point, _) in &queued {
+            dbtx.remove_entry(&QueuedPegOutKey(*out_point)).await;
+
+            dbtx.insert_new_entry(
+                &PegOutBitcoinTransaction(*out_point),
+                &WalletOutputOutcome::new_v0(txid),
+            )
+            .await;
+        }
+
+        dbtx.insert_new_entry(
+            &PegOutBatchKey(txid),
+            &queued
+                .into_iter()
+                .map(|(out_point, _)| out_point)
+                .collect::<Vec<_>>(),
+        )
+        .await;
+    }
+
+    async fn create_peg_out_tx(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        output: &WalletOutputV0,
+        change_tweak: &[u8; 33],
+    ) -> Result<UnsignedTransaction, WalletOutputError> {
+        match output {
+            WalletOutputV0::PegOut(peg_out) => self.offline_wallet().create_tx(
+                vec![TxOut {
+                    value: peg_out.amount,
+                    // Note: While calling `assume_checked()` is generally unwise, checking the
+                    // network here could be a consensus-breaking change. Ignoring the network
+                    // is fine here since we validate it in `process_output()`.
+                    script_pubkey: peg_out.recipient.clone().assume_checked().script_pubkey(),
+                }],
+                vec![],
+                self.available_utxos(dbtx).await,
+                peg_out.fees.fee_rate,
+                change_tweak,
+                None,
+            ),
+            WalletOutputV0::Rbf(rbf) => {
+                // Replacing a batch would drop the other peg-outs it pays
+                if dbtx.get_value(&PegOutBatchKey(rbf.txid)).await.is_some() {
+                    return Err(WalletOutputError::RbfPegOutBatchNotSupported);
+                }
+
+                let tx = dbtx
+                    .get_value(&PendingTransactionKey(rbf.txid))
+                    .await
+                    .ok_or(WalletOutputError::RbfTransactionIdNotFound)?;
+
+                // Transactions paying several recipients have no single destination
+                if tx.destination.is_empty() {
+                    return Err(WalletOutputError::RbfMultiPegOutNotSupported);
+                }
+
+                self.offline_wallet().create_tx(
+                    vec![TxOut {
+                        value: tx.peg_out_amount,
+                        script_pubkey: tx.destination,
+                    }],
+                    tx.selected_utxos,
+                    self.available_utxos(dbtx).await,
+                    tx.fees.fee_rate,
+                    change_tweak,
+                    Some(rbf.clone()),
                 )
             }
         }
     }
 
+    async fn process_multi_peg_out(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        peg_out: &MultiPegOut,
+        out_point: OutPoint,
+    ) -> Result<TransactionItemAmounts, WalletOutputError> {
+        // Peers only accept multi-output peg-outs once they all agreed to support them
+        if self.consensus_module_consensus_version(dbtx).await
+            < MULTI_PEG_OUT_MODULE_CONSENSUS_VERSION
+        {
+            return Err(WalletOutputError::MultiPegOutNotSupported);
+        }
+
+        let change_tweak = self.consensus_nonce(dbtx).await;
+
+        let tx = self.offline_wallet().create_tx(
+            peg_out
+                .recipients
+                .iter()
+                .map(|recipient| TxOut {
+                    value: recipient.amount,
+                    // The network of all recipients is validated below
+                    script_pubkey: recipient.recipient.clone().assume_checked().script_pubkey(),
+                })
+                .collect(),
+            vec![],
+            self.available_utxos(dbtx).await,
+            peg_out.fees.fee_rate,
+            &change_tweak,
+            None,
+        )?;
+
+        let fee_rate = self.consensus_fee_rate(dbtx).await;
+
+        StatelessWallet::validate_multi_peg_out_tx(
+            &tx,
+            peg_out,
+            fee_rate,
+            self.cfg.consensus.network.0,
+        )?;
+
+        Ok(self
+            .sign_and_record_peg_out(
+                dbtx,
+                tx,
+                peg_out.amount() + peg_out.fees.amount(),
+                out_point,
+            )
+            .await)
+    }
+
+    /// Signs the transaction paying a peg-out and records its txid as the
+    /// outcome of the output at `out_point`
+    async fn sign_and_record_peg_out(
+        &self,
+        dbtx: &mut DatabaseTransaction<'_>,
+        tx: UnsignedTransaction,
+        amount: bitcoin::Amount,
+        out_point: OutPoint,
+    ) -> TransactionItemAmounts {
+        let txid = self.sign_peg_out_tx(dbtx, tx).await;
+
+        dbtx.insert_new_entry(
+            &PegOutBitcoinTransaction(out_point),
+            &WalletOutputOutcome::new_v0(txid),
+        )
+        .await;
+        let amount: fedimint_core::Amount = amount.into();
+        let fee = self.cfg.consensus.fee_consensus.peg_out_abs;
+        calculate_pegout_metrics(dbtx, amount, fee);
+        self.credit_fee_reserve(dbtx, fee).await;
+        TransactionItemAmounts {
+            amounts: Amounts::new_bitcoin(amount),
+            fees: Amounts::new_bitcoin(fee),
+        }
+    }
+
     async fn available_utxos(
         &self,
         dbtx: &mut DatabaseTransaction<'_>,
@@ -1689,22 +2933,28 @@ fn partition_peg_out_and_change(
             for tx in transactions {
                 let txid = tx.compute_txid();
 
-                // to identify outputs for the peg_out (idx = 0) and change (idx = 1), we lean
-                // on how the wallet constructs the transaction
-                let peg_out_output = tx
+                // to identify outputs for the peg-outs (all but the last) and change (the
+                // last), we lean on how the wallet constructs the transaction
+                let (change_output, peg_out_outputs) = tx
                     .output
-                    .first()
-                    .expect("tx must contain withdrawal output");
-
-                let change_output = tx.output.last().expect("tx must contain change output");
-
-                peg_out_txos.push(TxOutputSummary {
-                    outpoint: bitcoin::OutPoint { txid, vout: 0 },
-                    amount: peg_out_output.value,
-                });
+                    .split_last()
+                    .expect("tx must contain change output");
+
+                for (vout, peg_out_output) in peg_out_outputs.iter().enumerate() {
+                    peg_out_txos.push(TxOutputSummary {
+                        outpoint: bitcoin::OutPoint {
+                            txid,
+                            vout: vout as u32,
+                        },
+                        amount: peg_out_output.value,
+                    });
+                }
 
                 change_utxos.push(TxOutputSummary {
-                    outpoint: bitcoin::OutPoint { txid, vout: 1 },
+                    outpoint: bitcoin::OutPoint {
+                        txid,
+                        vout: peg_out_outputs.len() as u32,
+                    },
                     amount: change_output.value,
                 });
             }
@@ -1738,18 +2988,59 @@ fn partition_peg_out_and_change(
             .collect::<Vec<_>>()
             .await;
 
+        // change outputs already spent by CPFP children are not ours anymore
+        let spent_outpoints = unsigned_transactions
+            .iter()
+            .chain(&unconfirmed_transactions)
+            .flat_map(|tx| tx.input.iter().map(|input| input.previous_output))
+            .collect::<BTreeSet<_>>();
+
         let (unsigned_peg_out_txos, unsigned_change_utxos) =
             partition_peg_out_and_change(unsigned_transactions);
 
-        let (unconfirmed_peg_out_txos, unconfirmed_change_utxos) =
+        let (unconfirmed_peg_out_txos, mut unconfirmed_change_utxos) =
             partition_peg_out_and_change(unconfirmed_transactions);
 
+        unconfirmed_change_utxos.retain(|txo| !spent_outpoints.contains(&txo.outpoint));
+
+        let mut cpfp_transactions = vec![];
+
+        for (parent_txid, cpfp) in dbtx
+            .find_by_prefix(&CpfpPrefix)
+            .await
+            .map(|(key, cpfp)| (key.0, cpfp))
+            .collect::<Vec<_>>()
+            .await
+        {
+            let signed = dbtx
+                .get_value(&PendingTransactionKey(cpfp.child_txid))
+                .await
+                .is_some();
+
+            // Only report children that didn't confirm yet
+            if signed
+                || dbtx
+                    .get_value(&UnsignedTransactionKey(cpfp.child_txid))
+                    .await
+                    .is_some()
+            {
+                cpfp_transactions.push(CpfpSummary {
+                    parent_txid,
+                    child_txid: cpfp.child_txid,
+                    fee: cpfp.fee,
+                    signed,
+                });
+            }
+        }
+
         WalletSummary {
             spendable_utxos,
             unsigned_peg_out_txos,
             unsigned_change_utxos,
             unconfirmed_peg_out_txos,
             unconfirmed_change_utxos,
+            cpfp_transactions,
+            fee_reserve: self.fee_reserve(dbtx).await,
         }
     }
 
@@ -1793,6 +3084,11 @@ pub async fn consensus_block_count_ui(&self) -> u32 {
             .await
     }
 
+    /// Get the result of the last chain tip verification for UI display
+    pub fn chain_tip_status_ui(&self) -> ChainTipStatus {
+        *self.chain_tip_status.borrow()
+    }
+
     /// Get the current consensus fee rate for UI display
     pub async fn consensus_feerate_ui(&self) -> Feerate {
         self.consensus_fee_rate(&mut self.db.begin_transaction_nc().await)
@@ -1805,7 +3101,25 @@ pub async fn get_wallet_summary_ui(&self) -> WalletSummary {
             .await
     }
 
-    /// Shutdown the task group shared throughout fedimintd, giving 60 seconds
+    /// Get the UTXO consolidations, most recent first, for UI display
+    pub async fn consolidation_history_ui(&self) -> Vec<(Txid, Consolidation)> {
+        let mut consolidations = self
+            .db
+            .begin_transaction_nc()
+            .await
+            .find_by_prefix(&ConsolidationPrefix)
+            .await
+            .map(|(key, consolidation)| (key.0, consolidation))
+            .collect::<Vec<_>>()
+            .await;
+
+        consolidations
+            .sort_by_key(|(_, consolidation)| std::cmp::Reverse(consolidation.block_count));
+
+        consolidations
+    }
+
+    /// Shutdown the task group shared throughout fedimintd, giving 60 seconds
     /// for other services to gracefully shutdown.
     async fn graceful_shutdown(&self) {
         if let Err(e) = self
@@ -1856,6 +3170,71 @@ async fn wait_for_finality_confs_or_shutdown(&self, consensus_block_count: u32)
         }
     }
 
+    /// Returns once our bitcoin backend has the block with the consensus block
+    /// hash at `height`. Like [`Self::wait_for_finality_confs_or_shutdown`]
+    /// we gracefully shutdown fedimintd if that does not happen within an hour,
+    /// since we can't process blocks our peers don't agree on.
+    async fn wait_for_consensus_block_hash_or_shutdown(
+        &self,
+        height: u32,
+        consensus_block_hash: BlockHash,
+    ) {
+        let backoff = if is_running_in_test_env() {
+            // every 100ms for 60s
+            backoff_util::custom_backoff(
+                Duration::from_millis(100),
+                Duration::from_millis(100),
+                Some(10 * 60),
+            )
+        } else {
+            // every max 10s for 1 hour
+            backoff_util::fibonacci_max_one_hour()
+        };
+
+        let wait_for_consensus_block_hash = || async {
+            let local_block_hash = self.btc_rpc.get_block_hash(u64::from(height)).await?;
+
+            if local_block_hash == consensus_block_hash {
+                WALLET_CHAIN_TIP_DIVERGED.set(0);
+                self.chain_tip_status.send_replace(ChainTipStatus::InSync {
+                    height,
+                    block_hash: local_block_hash,
+                });
+
+                return Ok(());
+            }
+
+            warn!(
+                target: LOG_MODULE_WALLET,
+                height,
+                %consensus_block_hash,
+                %local_block_hash,
+                "Our bitcoin backend diverged from the consensus chain tip"
+            );
+
+            WALLET_CHAIN_TIP_DIVERGED.set(1);
+            self.chain_tip_status
+                .send_replace(ChainTipStatus::Diverged {
+                    height,
+                    consensus_block_hash,
+                    local_block_hash,
+                });
+
+            Err(anyhow::anyhow!("block hash differs from consensus"))
+        };
+
+        if retry(
+            "wait_for_consensus_block_hash",
+            backoff,
+            wait_for_consensus_block_hash,
+        )
+        .await
+        .is_err()
+        {
+            self.graceful_shutdown().await;
+        }
+    }
+
     fn spawn_peer_supported_consensus_version_task(
         api_client: DynModuleApi,
         task_group: &TaskGroup,
@@ -2001,6 +3380,17 @@ pub async fn broadcast_pending_tx(
     }
 }
 
+/// Weight of the version, input and output counts and lock time of a
+/// transaction
+/// Maximum number of block hash votes kept per peer, honest peers are only a
+/// few blocks ahead of the consensus block count
+const MAX_BLOCK_HASH_VOTES: usize = 64;
+
+const BASE_TX_WEIGHT: u64 = 16 + // version
+    12 + // up to 2**16-1 inputs
+    12 + // up to 2**16-1 outputs
+    16; // lock time
+
 struct StatelessWallet<'a> {
     descriptor: &'a Descriptor<CompressedPublicKey>,
     secret_key: &'a secp256k1::SecretKey,
@@ -2030,6 +3420,52 @@ fn validate_tx(
             return Err(WalletOutputError::PegOutUnderDustLimit);
         }
 
+        let fees = match output {
+            WalletOutputV0::PegOut(pegout) => pegout.fees,
+            WalletOutputV0::Rbf(rbf) => rbf.fees,
+        };
+
+        Self::validate_tx_fees(tx, fees, consensus_fee_rate)
+    }
+
+    /// Given a tx created from a [`MultiPegOut`], validate there will be no
+    /// issues submitting the transaction to the Bitcoin network
+    fn validate_multi_peg_out_tx(
+        tx: &UnsignedTransaction,
+        peg_out: &MultiPegOut,
+        consensus_fee_rate: Feerate,
+        network: Network,
+    ) -> Result<(), WalletOutputError> {
+        if peg_out.recipients.is_empty() || MAX_PEG_OUT_RECIPIENTS < peg_out.recipients.len() {
+            return Err(WalletOutputError::InvalidPegOutRecipientCount(
+                peg_out.recipients.len() as u64,
+            ));
+        }
+
+        for recipient in &peg_out.recipients {
+            if !recipient.recipient.is_valid_for_network(network) {
+                return Err(WalletOutputError::WrongNetwork(
+                    NetworkLegacyEncodingWrapper(network),
+                    NetworkLegacyEncodingWrapper(get_network_for_address(&recipient.recipient)),
+                ));
+            }
+
+            let destination = recipient.recipient.clone().assume_checked().script_pubkey();
+
+            if recipient.amount < destination.minimal_non_dust() {
+                return Err(WalletOutputError::PegOutUnderDustLimit);
+            }
+        }
+
+        Self::validate_tx_fees(tx, peg_out.fees, consensus_fee_rate)
+    }
+
+    /// Validates the fees a peg-out pays for its transaction
+    fn validate_tx_fees(
+        tx: &UnsignedTransaction,
+        fees: PegOutFees,
+        consensus_fee_rate: Feerate,
+    ) -> Result<(), WalletOutputError> {
         // Validate tx fee rate is above the consensus fee rate
         if tx.fees.fee_rate < consensus_fee_rate {
             return Err(WalletOutputError::PegOutFeeBelowConsensus(
@@ -2040,10 +3476,6 @@ fn validate_tx(
 
         // Validate added fees are above the min relay tx fee
         // BIP-0125 requires 1 sat/vb for RBF by default (same as normal txs)
-        let fees = match output {
-            WalletOutputV0::PegOut(pegout) => pegout.fees,
-            WalletOutputV0::Rbf(rbf) => rbf.fees,
-        };
         if fees.fee_rate.sats_per_kvb < u64::from(DEFAULT_MIN_RELAY_TX_FEE) {
             return Err(WalletOutputError::BelowMinRelayFee);
         }
@@ -2059,10 +3491,88 @@ fn validate_tx(
         Ok(())
     }
 
+    /// Validates a batched peg-out before it is queued, its transaction is
+    /// only created once the batch is complete
+    fn validate_batched_peg_out(
+        &self,
+        peg_out: &PegOut,
+        consensus_fee_rate: Feerate,
+        network: Network,
+    ) -> Result<(), WalletOutputError> {
+        if !peg_out.recipient.is_valid_for_network(network) {
+            return Err(WalletOutputError::WrongNetwork(
+                NetworkLegacyEncodingWrapper(network),
+                NetworkLegacyEncodingWrapper(get_network_for_address(&peg_out.recipient)),
+            ));
+        }
+
+        let destination = peg_out.recipient.clone().assume_checked().script_pubkey();
+
+        if peg_out.amount < destination.minimal_non_dust() {
+            return Err(WalletOutputError::PegOutUnderDustLimit);
+        }
+
+        if peg_out.fees.fee_rate < consensus_fee_rate {
+            return Err(WalletOutputError::PegOutFeeBelowConsensus(
+                peg_out.fees.fee_rate,
+                consensus_fee_rate,
+            ));
+        }
+
+        if peg_out.fees.fee_rate.sats_per_kvb < u64::from(DEFAULT_MIN_RELAY_TX_FEE) {
+            return Err(WalletOutputError::BelowMinRelayFee);
+        }
+
+        let weight = self.batched_peg_out_weight(&destination);
+
+        if peg_out.fees.total_weight != weight {
+            return Err(WalletOutputError::TxWeightIncorrect(
+                peg_out.fees.total_weight,
+                weight,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Weight a transaction output paying to `script` adds to a transaction
+    fn output_weight(script: &ScriptBuf) -> u64 {
+        (1 // script len varint, 1 byte for all addresses we accept
+            + script.len() * 4 // script len
+            + 32) as u64 // value
+    }
+
+    /// Maximum weight an input spending one of our UTXOs adds to a transaction
+    fn max_input_weight(&self) -> u64 {
+        // https://github.com/fedimint/fedimint/issues/4590
+        #[allow(deprecated)]
+        let max_input_weight = (self
+            .descriptor
+            .max_satisfaction_weight()
+            .expect("is satisfyable") +
+            128 + // TxOutHash
+            16 + // TxOutIndex
+            16) as u64; // sequence
+
+        max_input_weight
+    }
+
+    /// The weight a batched peg-out to `destination` pays fees for: the weight
+    /// of a transaction paying it alone, with one input and a change output.
+    /// A batch is never heavier than the sum of the weights of its peg-outs
+    /// unless it needs more inputs than it has peg-outs, the fee reserve pays
+    /// for those.
+    fn batched_peg_out_weight(&self, destination: &ScriptBuf) -> u64 {
+        // All change scripts derived from our descriptor have the same length
+        BASE_TX_WEIGHT
+            + Self::output_weight(destination)
+            + Self::output_weight(&self.descriptor.script_pubkey())
+            + self.max_input_weight()
+    }
+
     /// Attempts to create a tx ready to be signed from available UTXOs.
     //
-    // * `peg_out_amount`: How much the peg-out should be
-    // * `destination`: The address the user is pegging-out to
+    // * `peg_outs`: The outputs paying the recipients of the peg-out
     // * `included_utxos`: UXTOs that must be included (for RBF)
     // * `remaining_utxos`: All other spendable UXTOs
     // * `fee_rate`: How much needs to be spent on fees
@@ -2071,8 +3581,7 @@ fn validate_tx(
     #[allow(clippy::too_many_arguments)]
     fn create_tx(
         &self,
-        peg_out_amount: bitcoin::Amount,
-        destination: ScriptBuf,
+        peg_outs: Vec<TxOut>,
         mut included_utxos: Vec<(UTXOKey, SpendableUTXO)>,
         mut remaining_utxos: Vec<(UTXOKey, SpendableUTXO)>,
         mut fee_rate: Feerate,
@@ -2092,25 +3601,15 @@ fn create_tx(
         // and the maximum weight per added input which we will add every time
         // we select an input.
         let change_script = self.derive_script(change_tweak);
-        let out_weight = (destination.len() * 4 + 1 + 32
+        let peg_out_amount = peg_outs.iter().map(|peg_out| peg_out.value).sum();
+        let out_weight = peg_outs
+            .iter()
+            .map(|peg_out| Self::output_weight(&peg_out.script_pubkey))
+            .sum::<u64>()
             // Add change script weight, it's very likely to be needed if not we just overpay in fees
-            + 1 // script len varint, 1 byte for all addresses we accept
-            + change_script.len() * 4 // script len
-            + 32) as u64; // value
-        let mut total_weight = 16 + // version
-            12 + // up to 2**16-1 inputs
-            12 + // up to 2**16-1 outputs
-            out_weight + // weight of all outputs
-            16; // lock time
-        // https://github.com/fedimint/fedimint/issues/4590
-        #[allow(deprecated)]
-        let max_input_weight = (self
-            .descriptor
-            .max_satisfaction_weight()
-            .expect("is satisfyable") +
-            128 + // TxOutHash
-            16 + // TxOutIndex
-            16) as u64; // sequence
+            + Self::output_weight(&change_script);
+        let mut total_weight = BASE_TX_WEIGHT + out_weight;
+        let max_input_weight = self.max_input_weight();
 
         // Ensure deterministic ordering of UTXOs for all peers
         included_utxos.sort_by_key(|(_, utxo)| utxo.amount);
@@ -2137,25 +3636,12 @@ fn create_tx(
         // We always pay ourselves change back to ensure that we don't lose anything due
         // to dust
         let change = total_selected_value - fees - peg_out_amount;
-        let output: Vec<TxOut> = vec![
-            TxOut {
-                value: peg_out_amount,
-                script_pubkey: destination.clone(),
-            },
-            TxOut {
-                value: change,
-                script_pubkey: change_script,
-            },
-        ];
-        let mut change_out = bitcoin::psbt::Output::default();
-        change_out
-            .proprietary
-            .insert(proprietary_tweak_key(), change_tweak.to_vec());
 
         info!(
             target: LOG_MODULE_WALLET,
             inputs = selected_utxos.len(),
             input_sats = total_selected_value.to_sat(),
+            peg_outs = peg_outs.len(),
             peg_out_sats = peg_out_amount.to_sat(),
             ?total_weight,
             fees_sats = fees.to_sat(),
@@ -2164,77 +3650,22 @@ fn create_tx(
             "Creating peg-out tx",
         );
 
-        let transaction = Transaction {
-            version: bitcoin::transaction::Version(2),
-            lock_time: LockTime::ZERO,
-            input: selected_utxos
-                .iter()
-                .map(|(utxo_key, _utxo)| TxIn {
-                    previous_output: utxo_key.0,
-                    script_sig: Default::default(),
-                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
-                    witness: bitcoin::Witness::new(),
-                })
-                .collect(),
-            output,
+        // The destination is only used to replace the tx by RBF, which would drop all
+        // but one of several peg-outs, so it is left empty for them like for batches.
+        let destination = match peg_outs.as_slice() {
+            [peg_out] => peg_out.script_pubkey.clone(),
+            _ => ScriptBuf::new(),
         };
-        info!(
-            target: LOG_MODULE_WALLET,
-            txid = %transaction.compute_txid(), "Creating peg-out tx"
-        );
 
-        // FIXME: use custom data structure that guarantees more invariants and only
-        // convert to PSBT for finalization
-        let psbt = Psbt {
-            unsigned_tx: transaction,
-            version: 0,
-            xpub: Default::default(),
-            proprietary: Default::default(),
-            unknown: Default::default(),
-            inputs: selected_utxos
-                .iter()
-                .map(|(_utxo_key, utxo)| {
-                    let script_pubkey = self
-                        .descriptor
-                        .tweak(&utxo.tweak, self.secp)
-                        .script_pubkey();
-                    Input {
-                        non_witness_utxo: None,
-                        witness_utxo: Some(TxOut {
-                            value: utxo.amount,
-                            script_pubkey,
-                        }),
-                        partial_sigs: Default::default(),
-                        sighash_type: None,
-                        redeem_script: None,
-                        witness_script: Some(
-                            self.descriptor
-                                .tweak(&utxo.tweak, self.secp)
-                                .script_code()
-                                .expect("Failed to tweak descriptor"),
-                        ),
-                        bip32_derivation: Default::default(),
-                        final_script_sig: None,
-                        final_script_witness: None,
-                        ripemd160_preimages: Default::default(),
-                        sha256_preimages: Default::default(),
-                        hash160_preimages: Default::default(),
-                        hash256_preimages: Default::default(),
-                        proprietary: vec![(proprietary_tweak_key(), utxo.tweak.to_vec())]
-                            .into_iter()
-                            .collect(),
-                        tap_key_sig: Default::default(),
-                        tap_script_sigs: Default::default(),
-                        tap_scripts: Default::default(),
-                        tap_key_origins: Default::default(),
-                        tap_internal_key: Default::default(),
-                        tap_merkle_root: Default::default(),
-                        unknown: Default::default(),
-                    }
-                })
-                .collect(),
-            outputs: vec![Default::default(), change_out],
-        };
+        let psbt = self.create_psbt(
+            &selected_utxos,
+            peg_outs,
+            TxOut {
+                value: change,
+                script_pubkey: change_script,
+            },
+            change_tweak,
+        );
 
         Ok(UnsignedTransaction {
             psbt,
@@ -2251,179 +3682,1474 @@ fn create_tx(
         })
     }
 
-    fn sign_psbt(&self, psbt: &mut Psbt) {
-        let mut tx_hasher = SighashCache::new(&psbt.unsigned_tx);
-
-        for (idx, (psbt_input, _tx_input)) in psbt
-            .inputs
-            .iter_mut()
-            .zip(psbt.unsigned_tx.input.iter())
-            .enumerate()
-        {
-            let tweaked_secret = {
-                let tweak = psbt_input
-                    .proprietary
-                    .get(&proprietary_tweak_key())
-                    .expect("Malformed PSBT: expected tweak");
-
-                self.secret_key.tweak(tweak, self.secp)
-            };
+    /// Attempts to create a tx paying all `peg_outs` of a batch from available
+    /// UTXOs. The tx pays the `collected_fees` of the batched peg-outs, or the
+    /// fees for its weight at `fee_rate` if they are higher.
+    fn create_batch_tx(
+        &self,
+        peg_outs: Vec<TxOut>,
+        collected_fees: bitcoin::Amount,
+        fee_rate: Feerate,
+        mut remaining_utxos: Vec<(UTXOKey, SpendableUTXO)>,
+        change_tweak: &[u8; 33],
+    ) -> Result<UnsignedTransaction, WalletOutputError> {
+        let change_script = self.derive_script(change_tweak);
+        let peg_out_amount = peg_outs.iter().map(|peg_out| peg_out.value).sum();
+        let mut total_weight = BASE_TX_WEIGHT
+            + peg_outs
+                .iter()
+                .map(|peg_out| Self::output_weight(&peg_out.script_pubkey))
+                .sum::<u64>()
+            + Self::output_weight(&change_script);
+        let max_input_weight = self.max_input_weight();
 
-            let tx_hash = tx_hasher
-                .p2wsh_signature_hash(
-                    idx,
-                    psbt_input
-                        .witness_script
-                        .as_ref()
-                        .expect("Missing witness script"),
-                    psbt_input
-                        .witness_utxo
-                        .as_ref()
-                        .expect("Missing UTXO")
-                        .value,
-                    EcdsaSighashType::All,
-                )
-                .expect("Failed to create segwit sighash");
+        // Ensure deterministic ordering of UTXOs for all peers
+        remaining_utxos.sort_by_key(|(_, utxo)| utxo.amount);
 
-            let signature = self.secp.sign_ecdsa(
-                &Message::from_digest_slice(&tx_hash[..]).unwrap(),
-                &tweaked_secret,
-            );
+        let mut total_selected_value = bitcoin::Amount::from_sat(0);
+        let mut selected_utxos: Vec<(UTXOKey, SpendableUTXO)> = vec![];
+        let mut fees = collected_fees.max(fee_rate.calculate_fee(total_weight));
 
-            psbt_input.partial_sigs.insert(
-                bitcoin::PublicKey {
-                    compressed: true,
-                    inner: secp256k1::PublicKey::from_secret_key(self.secp, &tweaked_secret),
-                },
-                EcdsaSig::sighash_all(signature),
-            );
+        while total_selected_value < peg_out_amount + change_script.minimal_non_dust() + fees {
+            match remaining_utxos.pop() {
+                Some((utxo_key, utxo)) => {
+                    total_selected_value += utxo.amount;
+                    total_weight += max_input_weight;
+                    fees = collected_fees.max(fee_rate.calculate_fee(total_weight));
+                    selected_utxos.push((utxo_key, utxo));
+                }
+                _ => return Err(WalletOutputError::NotEnoughSpendableUTXO),
+            }
         }
-    }
 
-    fn derive_script(&self, tweak: &[u8]) -> ScriptBuf {
-        struct CompressedPublicKeyTranslator<'t, 's, Ctx: Verification> {
-            tweak: &'t [u8],
-            secp: &'s Secp256k1<Ctx>,
-        }
+        let change = total_selected_value - fees - peg_out_amount;
 
-        impl<Ctx: Verification>
-            miniscript::Translator<CompressedPublicKey, CompressedPublicKey, Infallible>
-            for CompressedPublicKeyTranslator<'_, '_, Ctx>
-        {
-            fn pk(&mut self, pk: &CompressedPublicKey) -> Result<CompressedPublicKey, Infallible> {
-                let hashed_tweak = {
-                    let mut hasher = HmacEngine::<sha256::Hash>::new(&pk.key.serialize()[..]);
-                    hasher.input(self.tweak);
-                    Hmac::from_engine(hasher).to_byte_array()
-                };
+        // The effective fee rate of the batch, rounded down
+        let fee_rate = Feerate {
+            sats_per_kvb: fees.to_sat() * 4000 / total_weight,
+        };
 
-                Ok(CompressedPublicKey {
-                    key: pk
-                        .key
-                        .add_exp_tweak(
-                            self.secp,
-                            &Scalar::from_be_bytes(hashed_tweak).expect("can't fail"),
-                        )
-                        .expect("tweaking failed"),
+        info!(
+            target: LOG_MODULE_WALLET,
+            inputs = selected_utxos.len(),
+            input_sats = total_selected_value.to_sat(),
+            peg_outs = peg_outs.len(),
+            peg_out_sats = peg_out_amount.to_sat(),
+            ?total_weight,
+            fees_sats = fees.to_sat(),
+            fee_rate = fee_rate.sats_per_kvb,
+            change_sats = change.to_sat(),
+            "Creating peg-out batch tx",
+        );
+
+        let psbt = self.create_psbt(
+            &selected_utxos,
+            peg_outs,
+            TxOut {
+                value: change,
+                script_pubkey: change_script,
+            },
+            change_tweak,
+        );
+
+        // Batch transactions can't be replaced by RBF, the only user of the
+        // destination, so it is left empty.
+        Ok(UnsignedTransaction {
+            psbt,
+            signatures: vec![],
+            change,
+            fees: PegOutFees {
+                fee_rate,
+                total_weight,
+            },
+            destination: ScriptBuf::new(),
+            selected_utxos,
+            peg_out_amount,
+            rbf: None,
+        })
+    }
+
+    /// Attempts to create a tx sweeping up to [`MAX_CONSOLIDATION_INPUTS`] of
+    /// the smallest `remaining_utxos` into a single one. UTXOs not worth the
+    /// fees of spending them are skipped.
+    ///
+    /// Unlike peg-outs the fees are paid by the federation, out of the peg-in
+    /// fees it collected. Every peg-in added one UTXO and paid
+    /// `fee_per_removed_utxo`, so as long as every consolidation pays at most
+    /// that for each UTXO it removes, the fees stay covered.
+    fn create_consolidation_tx(
+        &self,
+        mut remaining_utxos: Vec<(UTXOKey, SpendableUTXO)>,
+        fee_rate: Feerate,
+        fee_per_removed_utxo: bitcoin::Amount,
+        change_tweak: &[u8; 33],
+    ) -> anyhow::Result<UnsignedTransaction> {
+        let change_script = self.derive_script(change_tweak);
+        let max_input_weight = self.max_input_weight();
+        let input_fee = fee_rate.calculate_fee(max_input_weight);
+
+        // Ensure deterministic ordering of UTXOs for all peers
+        remaining_utxos.sort_by_key(|(utxo_key, utxo)| (utxo.amount, utxo_key.0));
+
+        let selected_utxos = remaining_utxos
+            .into_iter()
+            .filter(|(_, utxo)| input_fee < utxo.amount)
+            .take(MAX_CONSOLIDATION_INPUTS)
+            .collect::<Vec<_>>();
+
+        // Sweeping a single UTXO would only pay fees
+        ensure!(
+            2 <= selected_utxos.len(),
+            "Not enough UTXOs worth consolidating"
+        );
+
+        let removed_utxos = selected_utxos.len() as u64 - 1;
+        let total_weight = BASE_TX_WEIGHT
+            + Self::output_weight(&change_script)
+            + max_input_weight * selected_utxos.len() as u64;
+        let fees = fee_rate.calculate_fee(total_weight);
+        let total_selected_value = selected_utxos
+            .iter()
+            .map(|(_, utxo)| utxo.amount)
+            .sum::<bitcoin::Amount>();
+
+        ensure!(
+            fees <= fee_per_removed_utxo * removed_utxos,
+            "Consolidation fees {fees} exceed the collected peg-in fees"
+        );
+
+        ensure!(
+            fees + change_script.minimal_non_dust() <= total_selected_value,
+            "Consolidated amount is below the dust limit"
+        );
+
+        let change = total_selected_value - fees;
+
+        info!(
+            target: LOG_MODULE_WALLET,
+            inputs = selected_utxos.len(),
+            input_sats = total_selected_value.to_sat(),
+            ?total_weight,
+            fees_sats = fees.to_sat(),
+            fee_rate = fee_rate.sats_per_kvb,
+            change_sats = change.to_sat(),
+            "Creating consolidation tx",
+        );
+
+        let psbt = self.create_psbt(
+            &selected_utxos,
+            vec![],
+            TxOut {
+                value: change,
+                script_pubkey: change_script,
+            },
+            change_tweak,
+        );
+
+        // Consolidations only pay to ourselves and can't be replaced by RBF
+        Ok(UnsignedTransaction {
+            psbt,
+            signatures: vec![],
+            change,
+            fees: PegOutFees {
+                fee_rate,
+                total_weight,
+            },
+            destination: ScriptBuf::new(),
+            selected_utxos,
+            peg_out_amount: bitcoin::Amount::ZERO,
+            rbf: None,
+        })
+    }
+
+    /// Creates a child tx spending the change of our unconfirmed `parent` tx,
+    /// paying enough fees from the `fee_reserve` for both together to reach
+    /// `fee_rate`. Returns the child together with the fees it pays.
+    fn create_cpfp_tx(
+        &self,
+        parent: &PendingTransaction,
+        fee_rate: Feerate,
+        fee_reserve: bitcoin::Amount,
+        change_tweak: &[u8; 33],
+    ) -> anyhow::Result<(UnsignedTransaction, bitcoin::Amount)> {
+        // Our change output is always the last one, see `create_psbt`
+        let (parent_vout, parent_change) = parent
+            .tx
+            .output
+            .iter()
+            .enumerate()
+            .next_back()
+            .context("Parent tx has no outputs")?;
+
+        ensure!(
+            parent_change.script_pubkey == self.derive_script(&parent.tweak),
+            "Parent tx has no change output"
+        );
+
+        let change_script = self.derive_script(change_tweak);
+        let total_weight =
+            BASE_TX_WEIGHT + self.max_input_weight() + Self::output_weight(&change_script);
+
+        let parent_fees = parent.fees.amount();
+
+        ensure!(
+            parent_fees < fee_rate.calculate_fee(parent.fees.total_weight),
+            "Parent tx already pays the fee rate"
+        );
+
+        // The child pays the difference between the fees of both txs at the fee
+        // rate and the fees the parent pays already
+        let package_fees = fee_rate.calculate_fee(parent.fees.total_weight + total_weight);
+        let fees = package_fees - parent_fees;
+
+        ensure!(
+            fees <= fee_reserve,
+            "CPFP fees {fees} exceed the fee reserve {fee_reserve}"
+        );
+
+        ensure!(
+            fees + change_script.minimal_non_dust() <= parent_change.value,
+            "Parent change is too small to pay the CPFP fees"
+        );
+
+        let change = parent_change.value - fees;
+
+        info!(
+            target: LOG_MODULE_WALLET,
+            parent_txid = %parent.tx.compute_txid(),
+            ?total_weight,
+            fees_sats = fees.to_sat(),
+            fee_rate = fee_rate.sats_per_kvb,
+            change_sats = change.to_sat(),
+            "Creating CPFP tx",
+        );
+
+        let selected_utxos = vec![(
+            UTXOKey(bitcoin::OutPoint {
+                txid: parent.tx.compute_txid(),
+                vout: parent_vout as u32,
+            }),
+            SpendableUTXO {
+                tweak: parent.tweak,
+                amount: parent_change.value,
+            },
+        )];
+
+        let psbt = self.create_psbt(
+            &selected_utxos,
+            vec![],
+            TxOut {
+                value: change,
+                script_pubkey: change_script,
+            },
+            change_tweak,
+        );
+
+        // Like consolidations, children only pay to ourselves and can't be
+        // replaced by RBF. The fee rate is the one of the whole package.
+        let child = UnsignedTransaction {
+            psbt,
+            signatures: vec![],
+            change,
+            fees: PegOutFees {
+                fee_rate,
+                total_weight,
+            },
+            destination: ScriptBuf::new(),
+            selected_utxos,
+            peg_out_amount: bitcoin::Amount::ZERO,
+            rbf: None,
+        };
+
+        Ok((child, fees))
+    }
+
+    /// Creates a PSBT spending all `utxos` to `destination` without a change
+    /// output, see [`RecoveryWallet::create_sweep_psbt`]
+    fn create_sweep_psbt(
+        &self,
+        utxos: &[(UTXOKey, SpendableUTXO)],
+        destination: ScriptBuf,
+        fee_rate: Feerate,
+    ) -> anyhow::Result<Psbt> {
+        ensure!(!utxos.is_empty(), "No UTXOs to sweep");
+
+        let total_weight = BASE_TX_WEIGHT
+            + Self::output_weight(&destination)
+            + self.max_input_weight() * utxos.len() as u64;
+        let fees = fee_rate.calculate_fee(total_weight);
+        let total_value = utxos
+            .iter()
+            .map(|(_, utxo)| utxo.amount)
+            .sum::<bitcoin::Amount>();
+
+        ensure!(
+            fees + destination.minimal_non_dust() <= total_value,
+            "Swept amount {total_value} is too small to pay the fees {fees}"
+        );
+
+        let transaction = Transaction {
+            version: bitcoin::transaction::Version(2),
+            lock_time: LockTime::ZERO,
+            input: utxos
+                .iter()
+                .map(|(utxo_key, _utxo)| TxIn {
+                    previous_output: utxo_key.0,
+                    script_sig: Default::default(),
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: bitcoin::Witness::new(),
                 })
-            }
-            translate_hash_fail!(CompressedPublicKey, CompressedPublicKey, Infallible);
+                .collect(),
+            output: vec![TxOut {
+                value: total_value - fees,
+                script_pubkey: destination,
+            }],
+        };
+
+        info!(
+            target: LOG_MODULE_WALLET,
+            txid = %transaction.compute_txid(),
+            inputs = utxos.len(),
+            fees_sats = fees.to_sat(),
+            "Creating sweep tx",
+        );
+
+        Ok(Psbt {
+            unsigned_tx: transaction,
+            version: 0,
+            xpub: Default::default(),
+            proprietary: Default::default(),
+            unknown: Default::default(),
+            inputs: utxos
+                .iter()
+                .map(|(_utxo_key, utxo)| self.psbt_input(utxo))
+                .collect(),
+            outputs: vec![bitcoin::psbt::Output::default()],
+        })
+    }
+
+    /// Signs a PSBT we didn't create ourselves after checking that every input
+    /// spends one of our UTXOs with the scripts we expect and that it pays
+    /// everything but at most `max_fee` to `destination`
+    fn sign_sweep_psbt(
+        &self,
+        psbt: &mut Psbt,
+        destination: &ScriptBuf,
+        max_fee: bitcoin::Amount,
+    ) -> anyhow::Result<()> {
+        ensure!(
+            psbt.inputs.len() == psbt.unsigned_tx.input.len(),
+            "PSBT inputs don't match its transaction"
+        );
+
+        ensure!(
+            psbt.unsigned_tx.output.len() == 1
+                && psbt.unsigned_tx.output[0].script_pubkey == *destination,
+            "PSBT doesn't pay the destination address only"
+        );
+
+        for (idx, input) in psbt.inputs.iter().enumerate() {
+            let tweak: [u8; 33] = input
+                .proprietary
+                .get(&proprietary_tweak_key())
+                .and_then(|tweak| tweak.as_slice().try_into().ok())
+                .with_context(|| format!("Input {idx} has no tweak"))?;
+
+            let amount = input
+                .witness_utxo
+                .as_ref()
+                .with_context(|| format!("Input {idx} has no witness UTXO"))?
+                .value;
+
+            let expected = self.psbt_input(&SpendableUTXO { tweak, amount });
+
+            ensure!(
+                input.witness_utxo == expected.witness_utxo
+                    && input.witness_script == expected.witness_script
+                    && input.tap_internal_key == expected.tap_internal_key
+                    && input.tap_merkle_root == expected.tap_merkle_root
+                    && input.tap_scripts == expected.tap_scripts
+                    && input.sighash_type.is_none(),
+                "Input {idx} doesn't spend one of our UTXOs"
+            );
+        }
+
+        // The signatures commit to the amounts of the inputs, so the fee can't be
+        // misrepresented by lying about them
+        let fee = psbt
+            .fee()
+            .context("PSBT spends more than its inputs are worth")?;
+
+        ensure!(
+            fee <= max_fee,
+            "PSBT pays a fee of {fee}, more than the maximum of {max_fee}"
+        );
+
+        self.sign_psbt(psbt);
+
+        Ok(())
+    }
+
+    /// Creates the PSBT spending `selected_utxos` to the `peg_outs` followed by
+    /// our `change` output
+    fn create_psbt(
+        &self,
+        selected_utxos: &[(UTXOKey, SpendableUTXO)],
+        peg_outs: Vec<TxOut>,
+        change: TxOut,
+        change_tweak: &[u8; 33],
+    ) -> Psbt {
+        let mut outputs = vec![bitcoin::psbt::Output::default(); peg_outs.len()];
+        let mut change_out = bitcoin::psbt::Output::default();
+        change_out
+            .proprietary
+            .insert(proprietary_tweak_key(), change_tweak.to_vec());
+        outputs.push(change_out);
+
+        let transaction = Transaction {
+            version: bitcoin::transaction::Version(2),
+            lock_time: LockTime::ZERO,
+            input: selected_utxos
+                .iter()
+                .map(|(utxo_key, _utxo)| TxIn {
+                    previous_output: utxo_key.0,
+                    script_sig: Default::default(),
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: bitcoin::Witness::new(),
+                })
+                .collect(),
+            output: peg_outs
+                .into_iter()
+                .chain(std::iter::once(change))
+                .collect(),
+        };
+        info!(
+            target: LOG_MODULE_WALLET,
+            txid = %transaction.compute_txid(), "Creating peg-out tx"
+        );
+
+        // FIXME: use custom data structure that guarantees more invariants and only
+        // convert to PSBT for finalization
+        Psbt {
+            unsigned_tx: transaction,
+            version: 0,
+            xpub: Default::default(),
+            proprietary: Default::default(),
+            unknown: Default::default(),
+            inputs: selected_utxos
+                .iter()
+                .map(|(_utxo_key, utxo)| self.psbt_input(utxo))
+                .collect(),
+            outputs,
+        }
+    }
+
+    /// The PSBT input spending one of our UTXOs, carrying its tweak and scripts
+    /// so every guardian can sign it
+    fn psbt_input(&self, utxo: &SpendableUTXO) -> Input {
+        let descriptor = self.descriptor.tweak(&utxo.tweak, self.secp);
+        let mut input = Input {
+            non_witness_utxo: None,
+            witness_utxo: Some(TxOut {
+                value: utxo.amount,
+                script_pubkey: descriptor.script_pubkey(),
+            }),
+            partial_sigs: Default::default(),
+            sighash_type: None,
+            redeem_script: None,
+            witness_script: match &descriptor {
+                // Taproot inputs carry their scripts in the tap fields below
+                Descriptor::Tr(_) => None,
+                _ => Some(
+                    descriptor
+                        .script_code()
+                        .expect("Failed to tweak descriptor"),
+                ),
+            },
+            bip32_derivation: Default::default(),
+            final_script_sig: None,
+            final_script_witness: None,
+            ripemd160_preimages: Default::default(),
+            sha256_preimages: Default::default(),
+            hash160_preimages: Default::default(),
+            hash256_preimages: Default::default(),
+            proprietary: vec![(proprietary_tweak_key(), utxo.tweak.to_vec())]
+                .into_iter()
+                .collect(),
+            tap_key_sig: Default::default(),
+            tap_script_sigs: Default::default(),
+            tap_scripts: Default::default(),
+            tap_key_origins: Default::default(),
+            tap_internal_key: Default::default(),
+            tap_merkle_root: Default::default(),
+            unknown: Default::default(),
+        };
+
+        if let Descriptor::Tr(tr) = &descriptor {
+            let spend_info = tr.spend_info();
+            input.tap_internal_key = Some(spend_info.internal_key());
+            input.tap_merkle_root = spend_info.merkle_root();
+            input.tap_scripts = tr
+                .iter_scripts()
+                .map(|(_depth, script)| {
+                    let script = (script.encode(), LeafVersion::TapScript);
+                    let control_block = spend_info
+                        .control_block(&script)
+                        .expect("Script is part of the tap tree");
+                    (control_block, script)
+                })
+                .collect();
+        }
+
+        input
+    }
+
+    fn sign_psbt(&self, psbt: &mut Psbt) {
+        if matches!(self.descriptor, Descriptor::Tr(_)) {
+            self.sign_taproot_psbt(psbt);
+            return;
         }
 
-        let descriptor = self
-            .descriptor
-            .translate_pk(&mut CompressedPublicKeyTranslator {
-                tweak,
-                secp: self.secp,
-            })
-            .expect("can't fail");
+        let mut tx_hasher = SighashCache::new(&psbt.unsigned_tx);
+
+        for (idx, (psbt_input, _tx_input)) in psbt
+            .inputs
+            .iter_mut()
+            .zip(psbt.unsigned_tx.input.iter())
+            .enumerate()
+        {
+            let tweaked_secret = {
+                let tweak = psbt_input
+                    .proprietary
+                    .get(&proprietary_tweak_key())
+                    .expect("Malformed PSBT: expected tweak");
+
+                self.secret_key.tweak(tweak, self.secp)
+            };
+
+            let tx_hash = tx_hasher
+                .p2wsh_signature_hash(
+                    idx,
+                    psbt_input
+                        .witness_script
+                        .as_ref()
+                        .expect("Missing witness script"),
+                    psbt_input
+                        .witness_utxo
+                        .as_ref()
+                        .expect("Missing UTXO")
+                        .value,
+                    EcdsaSighashType::All,
+                )
+                .expect("Failed to create segwit sighash");
+
+            let signature = self.secp.sign_ecdsa(
+                &Message::from_digest_slice(&tx_hash[..]).unwrap(),
+                &tweaked_secret,
+            );
+
+            psbt_input.partial_sigs.insert(
+                bitcoin::PublicKey {
+                    compressed: true,
+                    inner: secp256k1::PublicKey::from_secret_key(self.secp, &tweaked_secret),
+                },
+                EcdsaSig::sighash_all(signature),
+            );
+        }
+    }
+
+    fn sign_taproot_psbt(&self, psbt: &mut Psbt) {
+        let prevouts = psbt_prevouts(psbt);
+        let mut tx_hasher = SighashCache::new(&psbt.unsigned_tx);
+
+        for (idx, psbt_input) in psbt.inputs.iter_mut().enumerate() {
+            let tweaked_secret = {
+                let tweak = psbt_input
+                    .proprietary
+                    .get(&proprietary_tweak_key())
+                    .expect("Malformed PSBT: expected tweak");
+
+                self.secret_key.tweak(tweak, self.secp)
+            };
+
+            let (tx_hash, leaf_hash) =
+                taproot_signature_hash(&mut tx_hasher, idx, psbt_input, &prevouts)
+                    .expect("Failed to create taproot sighash");
+
+            let keypair = Keypair::from_secret_key(self.secp, &tweaked_secret);
+            let message = Message::from_digest_slice(&tx_hash[..]).unwrap();
+
+            if let Some(leaf_hash) = leaf_hash {
+                let signature = bitcoin::taproot::Signature {
+                    signature: self.secp.sign_schnorr(&message, &keypair),
+                    sighash_type: TapSighashType::Default,
+                };
+
+                psbt_input
+                    .tap_script_sigs
+                    .insert((keypair.x_only_public_key().0, leaf_hash), signature);
+            } else {
+                let keypair = keypair
+                    .tap_tweak(self.secp, psbt_input.tap_merkle_root)
+                    .to_keypair();
+
+                psbt_input.tap_key_sig = Some(bitcoin::taproot::Signature {
+                    signature: self.secp.sign_schnorr(&message, &keypair),
+                    sighash_type: TapSighashType::Default,
+                });
+            }
+        }
+    }
+
+    fn derive_script(&self, tweak: &[u8]) -> ScriptBuf {
+        struct CompressedPublicKeyTranslator<'t, 's, Ctx: Verification> {
+            tweak: &'t [u8],
+            secp: &'s Secp256k1<Ctx>,
+        }
+
+        impl<Ctx: Verification>
+            miniscript::Translator<CompressedPublicKey, CompressedPublicKey, Infallible>
+            for CompressedPublicKeyTranslator<'_, '_, Ctx>
+        {
+            fn pk(&mut self, pk: &CompressedPublicKey) -> Result<CompressedPublicKey, Infallible> {
+                let hashed_tweak = {
+                    let mut hasher = HmacEngine::<sha256::Hash>::new(&pk.key.serialize()[..]);
+                    hasher.input(self.tweak);
+                    Hmac::from_engine(hasher).to_byte_array()
+                };
+
+                Ok(CompressedPublicKey {
+                    key: pk
+                        .key
+                        .add_exp_tweak(
+                            self.secp,
+                            &Scalar::from_be_bytes(hashed_tweak).expect("can't fail"),
+                        )
+                        .expect("tweaking failed"),
+                })
+            }
+            translate_hash_fail!(CompressedPublicKey, CompressedPublicKey, Infallible);
+        }
+
+        let descriptor = self
+            .descriptor
+            .translate_pk(&mut CompressedPublicKeyTranslator {
+                tweak,
+                secp: self.secp,
+            })
+            .expect("can't fail");
+
+        descriptor.script_pubkey()
+    }
+}
+
+/// The block hash voted for at `block_count` by the most peers, as long as more
+/// than `max_evil` of them did. Ties are broken by the smaller hash so every
+/// guardian picks the same one.
+fn consensus_block_hash_from_votes(
+    votes: impl IntoIterator<Item = BlockCountHashItem>,
+    block_count: u32,
+    max_evil: usize,
+) -> Option<BlockHash> {
+    let mut hash_votes = BTreeMap::<BlockHash, usize>::new();
+
+    for vote in votes {
+        if vote.block_count == block_count {
+            *hash_votes.entry(vote.block_hash).or_default() += 1;
+        }
+    }
+
+    hash_votes
+        .into_iter()
+        .filter(|(_, count)| max_evil < *count)
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(block_hash, _)| block_hash)
+}
+
+/// Adds `vote` to the block hash votes of a peer. Peers usually move past the
+/// consensus block count before it is reached, so we keep their votes at or
+/// above it, up to [`MAX_BLOCK_HASH_VOTES`].
+fn record_block_hash_vote(
+    votes: &mut Vec<BlockCountHashItem>,
+    vote: BlockCountHashItem,
+    consensus_block_count: u32,
+) {
+    votes.retain(|vote| consensus_block_count <= vote.block_count);
+
+    if votes.len() < MAX_BLOCK_HASH_VOTES {
+        votes.push(vote);
+    }
+}
+
+/// The outputs spent by all inputs of a PSBT, taproot sighashes commit to them
+fn psbt_prevouts(psbt: &Psbt) -> Vec<TxOut> {
+    psbt.inputs
+        .iter()
+        .map(|input| input.witness_utxo.clone().expect("Missing UTXO"))
+        .collect()
+}
+
+/// Computes the sighash of a taproot input and, if it is spent through the
+/// script path, the hash of the spent leaf
+fn taproot_signature_hash(
+    tx_hasher: &mut SighashCache<&Transaction>,
+    idx: usize,
+    input: &Input,
+    prevouts: &[TxOut],
+) -> Result<(bitcoin::TapSighash, Option<TapLeafHash>), ProcessPegOutSigError> {
+    let prevouts = Prevouts::All(prevouts);
+
+    // Our taproot descriptors have at most a single leaf, if they have one we
+    // spend through it
+    if let Some((script, leaf_version)) = input.tap_scripts.values().next() {
+        let leaf_hash = TapLeafHash::from_script(script, *leaf_version);
+        let tx_hash = tx_hasher
+            .taproot_script_spend_signature_hash(idx, &prevouts, leaf_hash, TapSighashType::Default)
+            .map_err(|_| ProcessPegOutSigError::SighashError)?;
+
+        Ok((tx_hash, Some(leaf_hash)))
+    } else {
+        let tx_hash = tx_hasher
+            .taproot_key_spend_signature_hash(idx, &prevouts, TapSighashType::Default)
+            .map_err(|_| ProcessPegOutSigError::SighashError)?;
+
+        Ok((tx_hash, None))
+    }
+}
+
+/// The federation's on-chain wallet as seen by a single guardian, used to sweep
+/// the wallet of a federation that was shut down. Any guardian creates the
+/// sweep PSBT, a threshold of guardians then signs it offline with their own
+/// key.
+pub struct RecoveryWallet {
+    descriptor: PegInDescriptor,
+    secret_key: secp256k1::SecretKey,
+}
+
+impl RecoveryWallet {
+    pub fn new(descriptor: PegInDescriptor, secret_key: secp256k1::SecretKey) -> Self {
+        Self {
+            descriptor,
+            secret_key,
+        }
+    }
+
+    fn stateless(&self) -> StatelessWallet<'_> {
+        StatelessWallet {
+            descriptor: &self.descriptor,
+            secret_key: &self.secret_key,
+            secp: secp256k1::SECP256K1,
+        }
+    }
+
+    /// Creates a PSBT spending all `utxos` to `destination`, paying fees at
+    /// `fee_rate` for the maximum weight of the signed transaction
+    pub fn create_sweep_psbt(
+        &self,
+        utxos: &[(UTXOKey, SpendableUTXO)],
+        destination: ScriptBuf,
+        fee_rate: Feerate,
+    ) -> anyhow::Result<Psbt> {
+        self.stateless()
+            .create_sweep_psbt(utxos, destination, fee_rate)
+    }
+
+    /// Adds our signatures to every input of a sweep PSBT, fails if any input
+    /// doesn't spend one of the federation's UTXOs or if it pays anything but
+    /// at most `max_fee` to an output other than `destination`
+    pub fn sign_sweep_psbt(
+        &self,
+        psbt: &mut Psbt,
+        destination: &ScriptBuf,
+        max_fee: bitcoin::Amount,
+    ) -> anyhow::Result<()> {
+        self.stateless().sign_sweep_psbt(psbt, destination, max_fee)
+    }
+}
+
+pub fn nonce_from_idx(nonce_idx: u64) -> [u8; 33] {
+    let mut nonce: [u8; 33] = [0; 33];
+    // Make it look like a compressed pubkey, has to be either 0x02 or 0x03
+    nonce[0] = 0x02;
+    nonce[1..].copy_from_slice(&nonce_idx.consensus_hash::<bitcoin::hashes::sha256::Hash>()[..]);
+
+    nonce
+}
+
+/// A batched peg-out waiting to be paid by the next batch transaction
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct QueuedPegOut {
+    pub peg_out: PegOut,
+    /// Consensus block count at the time the peg-out was queued
+    pub block_count: u32,
+}
+
+/// A transaction sweeping small UTXOs of the federation into a single one
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct Consolidation {
+    /// Consensus block count the federation voted to consolidate at
+    pub block_count: u32,
+    /// Number of UTXOs swept
+    pub inputs: u64,
+    /// Amount of the resulting UTXO
+    pub amount: bitcoin::Amount,
+    pub fees: PegOutFees,
+}
+
+/// A child tx spending the change of one of our unconfirmed transactions to
+/// bump the fee of both
+#[derive(Clone, Debug, Encodable, Decodable, Serialize)]
+pub struct Cpfp {
+    /// Consensus block count the federation voted to bump the parent at
+    pub block_count: u32,
+    pub child_txid: Txid,
+    /// Fee paid by the child from the fee reserve
+    pub fee: bitcoin::Amount,
+}
+
+/// A peg-out tx that is ready to be broadcast with a tweak for the change UTXO
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct PendingTransaction {
+    pub tx: bitcoin::Transaction,
+    pub tweak: [u8; 33],
+    pub change: bitcoin::Amount,
+    pub destination: ScriptBuf,
+    pub fees: PegOutFees,
+    pub selected_utxos: Vec<(UTXOKey, SpendableUTXO)>,
+    pub peg_out_amount: bitcoin::Amount,
+    pub rbf: Option<Rbf>,
+}
+
+impl Serialize for PendingTransaction {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.consensus_encode_to_hex())
+        } else {
+            serializer.serialize_bytes(&self.consensus_encode_to_vec())
+        }
+    }
+}
+
+/// A PSBT that is awaiting enough signatures from the federation to becoming a
+/// `PendingTransaction`
+#[derive(Clone, Debug, Eq, PartialEq, Encodable, Decodable)]
+pub struct UnsignedTransaction {
+    pub psbt: Psbt,
+    pub signatures: Vec<(PeerId, PegOutSignatureItem)>,
+    pub change: bitcoin::Amount,
+    pub fees: PegOutFees,
+    pub destination: ScriptBuf,
+    pub selected_utxos: Vec<(UTXOKey, SpendableUTXO)>,
+    pub peg_out_amount: bitcoin::Amount,
+    pub rbf: Option<Rbf>,
+}
+
+impl Serialize for UnsignedTransaction {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.consensus_encode_to_hex())
+        } else {
+            serializer.serialize_bytes(&self.consensus_encode_to_vec())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::collections::BTreeMap;
+    use std::str::FromStr;
+
+    use bitcoin::Network::{Bitcoin, Testnet};
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Address, Amount, BlockHash, OutPoint, Txid, secp256k1};
+    use fedimint_core::encoding::btc::NetworkLegacyEncodingWrapper;
+    use fedimint_core::{Feerate, PeerId};
+    use fedimint_wallet_common::config::PegInDescriptorKind;
+    use fedimint_wallet_common::{
+        BlockCountHashItem, MultiPegOut, PegOut, PegOutFees, PegOutRecipient, Rbf, WalletOutputV0,
+    };
+    use miniscript::descriptor::Wsh;
+    use miniscript::psbt::PsbtExt as _;
+
+    use crate::common::PegInDescriptor;
+    use crate::{
+        CompressedPublicKey, OsRng, PendingTransaction, RecoveryWallet, SpendableUTXO,
+        StatelessWallet, TxOut, UTXOKey, WalletOutputError, consensus_block_hash_from_votes,
+        record_block_hash_vote,
+    };
+
+    #[test]
+    fn consensus_block_hash_requires_an_honest_vote() {
+        let hash_a = BlockHash::from_byte_array([1; 32]);
+        let hash_b = BlockHash::from_byte_array([2; 32]);
+        let vote = |block_count, block_hash| BlockCountHashItem {
+            block_count,
+            block_hash,
+        };
+
+        // A single vote could come from a malicious peer
+        assert_eq!(
+            consensus_block_hash_from_votes([vote(100, hash_a)], 100, 1),
+            None
+        );
+
+        // Votes for other block counts don't count
+        assert_eq!(
+            consensus_block_hash_from_votes([vote(100, hash_a), vote(101, hash_a)], 100, 1),
+            None
+        );
+
+        assert_eq!(
+            consensus_block_hash_from_votes(
+                [vote(100, hash_a), vote(100, hash_b), vote(100, hash_b)],
+                100,
+                1
+            ),
+            Some(hash_b)
+        );
+
+        // Ties during a reorg are broken deterministically
+        assert_eq!(
+            consensus_block_hash_from_votes(
+                [
+                    vote(100, hash_b),
+                    vote(100, hash_a),
+                    vote(100, hash_b),
+                    vote(100, hash_a)
+                ],
+                100,
+                1
+            ),
+            Some(hash_a)
+        );
+    }
+
+    #[test]
+    fn block_hash_votes_are_kept_until_consensus_passes_them() {
+        let hash_a = BlockHash::from_byte_array([1; 32]);
+        let hash_b = BlockHash::from_byte_array([2; 32]);
+        let vote = |block_count, block_hash| BlockCountHashItem {
+            block_count,
+            block_hash,
+        };
+
+        // A peer voting ahead of the consensus block count keeps the vote for it
+        let mut votes = vec![];
+        record_block_hash_vote(&mut votes, vote(100, hash_a), 99);
+        record_block_hash_vote(&mut votes, vote(101, hash_b), 99);
+        assert_eq!(votes, vec![vote(100, hash_a), vote(101, hash_b)]);
+
+        let peer_votes = [votes.clone(), vec![vote(100, hash_a)]];
+        assert_eq!(
+            consensus_block_hash_from_votes(peer_votes.into_iter().flatten(), 100, 1),
+            Some(hash_a)
+        );
+
+        // Votes below the consensus block count are dropped
+        record_block_hash_vote(&mut votes, vote(102, hash_a), 101);
+        assert_eq!(votes, vec![vote(101, hash_b), vote(102, hash_a)]);
+    }
+
+    #[test]
+    fn create_tx_should_validate_amounts() {
+        let secp = secp256k1::Secp256k1::new();
+
+        let descriptor = PegInDescriptor::Wsh(
+            Wsh::new_sortedmulti(
+                3,
+                (0..4)
+                    .map(|_| secp.generate_keypair(&mut OsRng))
+                    .map(|(_, key)| CompressedPublicKey { key })
+                    .collect(),
+            )
+            .unwrap(),
+        );
+
+        let (secret_key, _) = secp.generate_keypair(&mut OsRng);
+
+        let wallet = StatelessWallet {
+            descriptor: &descriptor,
+            secret_key: &secret_key,
+            secp: &secp,
+        };
+
+        let spendable = SpendableUTXO {
+            tweak: [0; 33],
+            amount: bitcoin::Amount::from_sat(3000),
+        };
+
+        let recipient = Address::from_str("32iVBEu4dxkUQk9dJbZUiBiQdmypcEyJRf").unwrap();
+
+        let fee = Feerate { sats_per_kvb: 1000 };
+        let weight = 875;
+
+        // not enough SpendableUTXO
+        // tx fee = ceil(875 / 4) * 1 sat/vb = 219
+        // change script dust = 330
+        // spendable sats = 3000 - 219 - 330 = 2451
+        let tx = wallet.create_tx(
+            vec![TxOut {
+                value: Amount::from_sat(2452),
+                script_pubkey: recipient.clone().assume_checked().script_pubkey(),
+            }],
+            vec![],
+            vec![(UTXOKey(OutPoint::null()), spendable.clone())],
+            fee,
+            &[0; 33],
+            None,
+        );
+        assert_eq!(tx, Err(WalletOutputError::NotEnoughSpendableUTXO));
+
+        // successful tx creation
+        let mut tx = wallet
+            .create_tx(
+                vec![TxOut {
+                    value: Amount::from_sat(1000),
+                    script_pubkey: recipient.clone().assume_checked().script_pubkey(),
+                }],
+                vec![],
+                vec![(UTXOKey(OutPoint::null()), spendable)],
+                fee,
+                &[0; 33],
+                None,
+            )
+            .expect("is ok");
+
+        // peg out weight is incorrectly set to 0
+        let res = StatelessWallet::validate_tx(&tx, &rbf(fee.sats_per_kvb, 0), fee, Bitcoin);
+        assert_eq!(res, Err(WalletOutputError::TxWeightIncorrect(0, weight)));
+
+        // fee rate set below min relay fee to 0
+        let res = StatelessWallet::validate_tx(&tx, &rbf(0, weight), fee, Bitcoin);
+        assert_eq!(res, Err(WalletOutputError::BelowMinRelayFee));
+
+        // fees are okay
+        let res = StatelessWallet::validate_tx(&tx, &rbf(fee.sats_per_kvb, weight), fee, Bitcoin);
+        assert_eq!(res, Ok(()));
+
+        // tx has fee below consensus
+        tx.fees = PegOutFees::new(0, weight);
+        let res = StatelessWallet::validate_tx(&tx, &rbf(fee.sats_per_kvb, weight), fee, Bitcoin);
+        assert_eq!(
+            res,
+            Err(WalletOutputError::PegOutFeeBelowConsensus(
+                Feerate { sats_per_kvb: 0 },
+                fee
+            ))
+        );
+
+        // tx has peg-out amount under dust limit
+        tx.peg_out_amount = bitcoin::Amount::ZERO;
+        let res = StatelessWallet::validate_tx(&tx, &rbf(fee.sats_per_kvb, weight), fee, Bitcoin);
+        assert_eq!(res, Err(WalletOutputError::PegOutUnderDustLimit));
+
+        // tx is invalid for network
+        let output = WalletOutputV0::PegOut(PegOut {
+            recipient,
+            amount: bitcoin::Amount::from_sat(1000),
+            fees: PegOutFees::new(100, weight),
+        });
+        let res = StatelessWallet::validate_tx(&tx, &output, fee, Testnet);
+        assert_eq!(
+            res,
+            Err(WalletOutputError::WrongNetwork(
+                NetworkLegacyEncodingWrapper(Testnet),
+                NetworkLegacyEncodingWrapper(Bitcoin)
+            ))
+        );
+    }
+
+    #[test]
+    fn create_tx_should_pay_multiple_recipients() {
+        let secp = secp256k1::Secp256k1::new();
+
+        let descriptor = PegInDescriptor::Wsh(
+            Wsh::new_sortedmulti(
+                3,
+                (0..4)
+                    .map(|_| secp.generate_keypair(&mut OsRng))
+                    .map(|(_, key)| CompressedPublicKey { key })
+                    .collect(),
+            )
+            .unwrap(),
+        );
+
+        let (secret_key, _) = secp.generate_keypair(&mut OsRng);
+
+        let wallet = StatelessWallet {
+            descriptor: &descriptor,
+            secret_key: &secret_key,
+            secp: &secp,
+        };
+
+        let spendable = SpendableUTXO {
+            tweak: [0; 33],
+            amount: bitcoin::Amount::from_sat(10_000),
+        };
+
+        let recipient = Address::from_str("32iVBEu4dxkUQk9dJbZUiBiQdmypcEyJRf").unwrap();
+        let script = recipient.clone().assume_checked().script_pubkey();
+        let fee = Feerate { sats_per_kvb: 1000 };
+
+        // a single peg-out weighs 875, every further recipient adds its output
+        let weight = 1000;
+
+        let tx = wallet
+            .create_tx(
+                vec![
+                    TxOut {
+                        value: Amount::from_sat(1000),
+                        script_pubkey: script.clone(),
+                    },
+                    TxOut {
+                        value: Amount::from_sat(2000),
+                        script_pubkey: script,
+                    },
+                ],
+                vec![],
+                vec![(UTXOKey(OutPoint::null()), spendable)],
+                fee,
+                &[0; 33],
+                None,
+            )
+            .expect("is ok");
+
+        assert_eq!(tx.psbt.unsigned_tx.output.len(), 3);
+        assert_eq!(tx.peg_out_amount, Amount::from_sat(3000));
+        assert_eq!(tx.fees.total_weight, weight);
+        // several recipients can't be replaced by RBF
+        assert!(tx.destination.is_empty());
+
+        let multi_peg_out = |amounts: &[u64]| MultiPegOut {
+            recipients: amounts
+                .iter()
+                .map(|sats| PegOutRecipient {
+                    recipient: recipient.clone(),
+                    amount: Amount::from_sat(*sats),
+                })
+                .collect(),
+            fees: PegOutFees::new(fee.sats_per_kvb, weight),
+        };
+
+        let res = StatelessWallet::validate_multi_peg_out_tx(
+            &tx,
+            &multi_peg_out(&[1000, 2000]),
+            fee,
+            Bitcoin,
+        );
+        assert_eq!(res, Ok(()));
+
+        let res =
+            StatelessWallet::validate_multi_peg_out_tx(&tx, &multi_peg_out(&[]), fee, Bitcoin);
+        assert_eq!(res, Err(WalletOutputError::InvalidPegOutRecipientCount(0)));
+
+        let res = StatelessWallet::validate_multi_peg_out_tx(
+            &tx,
+            &multi_peg_out(&[1000, 0]),
+            fee,
+            Bitcoin,
+        );
+        assert_eq!(res, Err(WalletOutputError::PegOutUnderDustLimit));
+
+        let res = StatelessWallet::validate_multi_peg_out_tx(
+            &tx,
+            &multi_peg_out(&[1000, 2000]),
+            fee,
+            Testnet,
+        );
+        assert_eq!(
+            res,
+            Err(WalletOutputError::WrongNetwork(
+                NetworkLegacyEncodingWrapper(Testnet),
+                NetworkLegacyEncodingWrapper(Bitcoin)
+            ))
+        );
+    }
+
+    #[test]
+    fn create_consolidation_tx_should_sweep_small_utxos() {
+        let secp = secp256k1::Secp256k1::new();
+
+        let descriptor = PegInDescriptor::Wsh(
+            Wsh::new_sortedmulti(
+                3,
+                (0..4)
+                    .map(|_| secp.generate_keypair(&mut OsRng))
+                    .map(|(_, key)| CompressedPublicKey { key })
+                    .collect(),
+            )
+            .unwrap(),
+        );
+
+        let (secret_key, _) = secp.generate_keypair(&mut OsRng);
+
+        let wallet = StatelessWallet {
+            descriptor: &descriptor,
+            secret_key: &secret_key,
+            secp: &secp,
+        };
+
+        let utxos = |amounts: &[u64]| {
+            amounts
+                .iter()
+                .enumerate()
+                .map(|(vout, sats)| {
+                    (
+                        UTXOKey(OutPoint {
+                            txid: Txid::all_zeros(),
+                            vout: vout as u32,
+                        }),
+                        SpendableUTXO {
+                            tweak: [0; 33],
+                            amount: Amount::from_sat(*sats),
+                        },
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let fee = Feerate { sats_per_kvb: 1000 };
+
+        // spending an input costs ceil(525 / 4) = 132 sats, so the 100 sat UTXO is
+        // skipped
+        // tx fee = ceil((56 + 169 + 3 * 525) / 4) * 1 sat/vb = 450
+        let tx = wallet
+            .create_consolidation_tx(
+                utxos(&[5000, 100, 2000, 1000]),
+                fee,
+                Amount::from_sat(1000),
+                &[0; 33],
+            )
+            .expect("is ok");
+
+        assert_eq!(tx.selected_utxos.len(), 3);
+        assert_eq!(tx.psbt.unsigned_tx.output.len(), 1);
+        assert_eq!(tx.fees.total_weight, 1800);
+        assert_eq!(tx.change, Amount::from_sat(8000 - 450));
+        assert_eq!(tx.peg_out_amount, Amount::ZERO);
+
+        // the fees exceed the peg-in fees of the two removed UTXOs
+        assert!(
+            wallet
+                .create_consolidation_tx(
+                    utxos(&[5000, 100, 2000, 1000]),
+                    fee,
+                    Amount::from_sat(200),
+                    &[0; 33],
+                )
+                .is_err()
+        );
 
-        descriptor.script_pubkey()
+        // a single UTXO worth spending isn't consolidated
+        assert!(
+            wallet
+                .create_consolidation_tx(
+                    utxos(&[5000, 100]),
+                    fee,
+                    Amount::from_sat(1000),
+                    &[0; 33],
+                )
+                .is_err()
+        );
     }
-}
 
-pub fn nonce_from_idx(nonce_idx: u64) -> [u8; 33] {
-    let mut nonce: [u8; 33] = [0; 33];
-    // Make it look like a compressed pubkey, has to be either 0x02 or 0x03
-    nonce[0] = 0x02;
-    nonce[1..].copy_from_slice(&nonce_idx.consensus_hash::<bitcoin::hashes::sha256::Hash>()[..]);
+    #[test]
+    fn create_cpfp_tx_should_bump_package_fee_rate() {
+        let secp = secp256k1::Secp256k1::new();
 
-    nonce
-}
+        let descriptor = PegInDescriptor::Wsh(
+            Wsh::new_sortedmulti(
+                3,
+                (0..4)
+                    .map(|_| secp.generate_keypair(&mut OsRng))
+                    .map(|(_, key)| CompressedPublicKey { key })
+                    .collect(),
+            )
+            .unwrap(),
+        );
 
-/// A peg-out tx that is ready to be broadcast with a tweak for the change UTXO
-#[derive(Clone, Debug, Encodable, Decodable)]
-pub struct PendingTransaction {
-    pub tx: bitcoin::Transaction,
-    pub tweak: [u8; 33],
-    pub change: bitcoin::Amount,
-    pub destination: ScriptBuf,
-    pub fees: PegOutFees,
-    pub selected_utxos: Vec<(UTXOKey, SpendableUTXO)>,
-    pub peg_out_amount: bitcoin::Amount,
-    pub rbf: Option<Rbf>,
-}
+        let (secret_key, _) = secp.generate_keypair(&mut OsRng);
 
-impl Serialize for PendingTransaction {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        if serializer.is_human_readable() {
-            serializer.serialize_str(&self.consensus_encode_to_hex())
-        } else {
-            serializer.serialize_bytes(&self.consensus_encode_to_vec())
-        }
-    }
-}
+        let wallet = StatelessWallet {
+            descriptor: &descriptor,
+            secret_key: &secret_key,
+            secp: &secp,
+        };
 
-/// A PSBT that is awaiting enough signatures from the federation to becoming a
-/// `PendingTransaction`
-#[derive(Clone, Debug, Eq, PartialEq, Encodable, Decodable)]
-pub struct UnsignedTransaction {
-    pub psbt: Psbt,
-    pub signatures: Vec<(PeerId, PegOutSignatureItem)>,
-    pub change: bitcoin::Amount,
-    pub fees: PegOutFees,
-    pub destination: ScriptBuf,
-    pub selected_utxos: Vec<(UTXOKey, SpendableUTXO)>,
-    pub peg_out_amount: bitcoin::Amount,
-    pub rbf: Option<Rbf>,
-}
+        let parent = PendingTransaction {
+            tx: bitcoin::Transaction {
+                version: bitcoin::transaction::Version(2),
+                lock_time: bitcoin::absolute::LockTime::ZERO,
+                input: vec![],
+                output: vec![
+                    TxOut {
+                        value: Amount::from_sat(50_000),
+                        script_pubkey: wallet.derive_script(&[1; 33]),
+                    },
+                    TxOut {
+                        value: Amount::from_sat(10_000),
+                        script_pubkey: wallet.derive_script(&[0; 33]),
+                    },
+                ],
+            },
+            tweak: [0; 33],
+            change: Amount::from_sat(10_000),
+            destination: wallet.derive_script(&[1; 33]),
+            // the parent pays ceil(1000 / 4) * 1 sat/vb = 250 sats
+            fees: PegOutFees::new(1000, 1000),
+            selected_utxos: vec![],
+            peg_out_amount: Amount::from_sat(50_000),
+            rbf: None,
+        };
 
-impl Serialize for UnsignedTransaction {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        if serializer.is_human_readable() {
-            serializer.serialize_str(&self.consensus_encode_to_hex())
-        } else {
-            serializer.serialize_bytes(&self.consensus_encode_to_vec())
-        }
+        let fee_rate = Feerate { sats_per_kvb: 2000 };
+
+        // child weight = 56 + 525 + 169 = 750
+        // package fee = ceil((1000 + 750) / 4) * 2 sat/vb = 876
+        let (child, fee) = wallet
+            .create_cpfp_tx(&parent, fee_rate, Amount::from_sat(1000), &[2; 33])
+            .expect("is ok");
+
+        assert_eq!(
+            child.psbt.unsigned_tx.input[0].previous_output,
+            OutPoint {
+                txid: parent.tx.compute_txid(),
+                vout: 1,
+            }
+        );
+        assert_eq!(child.psbt.unsigned_tx.output.len(), 1);
+        assert_eq!(child.fees.total_weight, 750);
+        assert_eq!(fee, Amount::from_sat(876 - 250));
+        assert_eq!(child.change, Amount::from_sat(10_000) - fee);
+
+        // the child fees exceed the fee reserve
+        assert!(
+            wallet
+                .create_cpfp_tx(&parent, fee_rate, Amount::from_sat(500), &[2; 33])
+                .is_err()
+        );
+
+        // the parent already pays the fee rate
+        assert!(
+            wallet
+                .create_cpfp_tx(
+                    &parent,
+                    Feerate { sats_per_kvb: 1000 },
+                    Amount::from_sat(1000),
+                    &[2; 33]
+                )
+                .is_err()
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
+    #[test]
+    fn sweep_psbt_is_finalized_by_threshold_of_guardians() {
+        let secp = secp256k1::Secp256k1::new();
 
-    use std::str::FromStr;
+        let keys = (0..4)
+            .map(|_| secp.generate_keypair(&mut OsRng))
+            .collect::<Vec<_>>();
 
-    use bitcoin::Network::{Bitcoin, Testnet};
-    use bitcoin::hashes::Hash;
-    use bitcoin::{Address, Amount, OutPoint, Txid, secp256k1};
-    use fedimint_core::Feerate;
-    use fedimint_core::encoding::btc::NetworkLegacyEncodingWrapper;
-    use fedimint_wallet_common::{PegOut, PegOutFees, Rbf, WalletOutputV0};
-    use miniscript::descriptor::Wsh;
+        let descriptor = PegInDescriptor::Wsh(
+            Wsh::new_sortedmulti(
+                3,
+                keys.iter()
+                    .map(|(_, key)| CompressedPublicKey { key: *key })
+                    .collect(),
+            )
+            .unwrap(),
+        );
 
-    use crate::common::PegInDescriptor;
-    use crate::{
-        CompressedPublicKey, OsRng, SpendableUTXO, StatelessWallet, UTXOKey, WalletOutputError,
-    };
+        let guardians = keys
+            .iter()
+            .map(|(secret_key, _)| RecoveryWallet::new(descriptor.clone(), *secret_key))
+            .collect::<Vec<_>>();
+
+        let utxos = (0..2)
+            .map(|vout| {
+                (
+                    UTXOKey(OutPoint {
+                        txid: Txid::all_zeros(),
+                        vout,
+                    }),
+                    SpendableUTXO {
+                        tweak: [vout as u8 + 2; 33],
+                        amount: Amount::from_sat(10_000),
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let destination = Address::from_str("32iVBEu4dxkUQk9dJbZUiBiQdmypcEyJRf")
+            .unwrap()
+            .assume_checked()
+            .script_pubkey();
+        let max_fee = Amount::from_sat(308);
+
+        // tx fee = ceil((56 + 1 + 23 * 4 + 32 + 2 * 525) / 4) * 1 sat/vb = 308
+        let psbt = guardians[0]
+            .create_sweep_psbt(&utxos, destination.clone(), Feerate { sats_per_kvb: 1000 })
+            .expect("is ok");
+
+        assert_eq!(psbt.unsigned_tx.input.len(), 2);
+        assert_eq!(psbt.unsigned_tx.output.len(), 1);
+        assert_eq!(
+            psbt.unsigned_tx.output[0].value,
+            Amount::from_sat(20_000 - 308)
+        );
+
+        let signed = |signers: &[RecoveryWallet]| {
+            let mut psbt = psbt.clone();
+
+            for signer in signers {
+                signer
+                    .sign_sweep_psbt(&mut psbt, &destination, max_fee)
+                    .expect("is ok");
+            }
+
+            psbt
+        };
+
+        assert!(signed(&guardians[..2]).finalize_mut(&secp).is_err());
+        assert!(signed(&guardians[1..]).finalize_mut(&secp).is_ok());
+
+        // guardians refuse to sign inputs that don't spend federation UTXOs
+        let mut foreign = psbt.clone();
+        foreign.inputs[1].witness_script = foreign.inputs[0].witness_script.clone();
+        assert!(
+            guardians[0]
+                .sign_sweep_psbt(&mut foreign, &destination, max_fee)
+                .is_err()
+        );
+
+        // guardians refuse to sign sweeps to another address
+        let other_destination = Address::from_str("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy")
+            .unwrap()
+            .assume_checked()
+            .script_pubkey();
+        assert!(
+            guardians[0]
+                .sign_sweep_psbt(&mut psbt.clone(), &other_destination, max_fee)
+                .is_err()
+        );
+
+        // guardians refuse to sign sweeps paying more than the maximum fee
+        assert!(
+            guardians[0]
+                .sign_sweep_psbt(&mut psbt.clone(), &destination, Amount::from_sat(307))
+                .is_err()
+        );
+    }
 
     #[test]
-    fn create_tx_should_validate_amounts() {
+    fn create_batch_tx_should_pay_all_peg_outs() {
         let secp = secp256k1::Secp256k1::new();
 
         let descriptor = PegInDescriptor::Wsh(
@@ -2445,86 +5171,161 @@ fn create_tx_should_validate_amounts() {
             secp: &secp,
         };
 
-        let spendable = SpendableUTXO {
-            tweak: [0; 33],
-            amount: bitcoin::Amount::from_sat(3000),
-        };
-
         let recipient = Address::from_str("32iVBEu4dxkUQk9dJbZUiBiQdmypcEyJRf").unwrap();
-
+        let script = recipient.clone().assume_checked().script_pubkey();
         let fee = Feerate { sats_per_kvb: 1000 };
-        let weight = 875;
 
-        // not enough SpendableUTXO
-        // tx fee = ceil(875 / 4) * 1 sat/vb = 219
-        // change script dust = 330
-        // spendable sats = 3000 - 219 - 330 = 2451
-        let tx = wallet.create_tx(
-            Amount::from_sat(2452),
-            recipient.clone().assume_checked().script_pubkey(),
-            vec![],
-            vec![(UTXOKey(OutPoint::null()), spendable.clone())],
-            fee,
-            &[0; 33],
-            None,
+        // each batched peg-out pays for a transaction with its output, one input and
+        // a change output
+        let weight = wallet.batched_peg_out_weight(&script);
+        assert_eq!(weight, 875);
+
+        let peg_out = PegOut {
+            recipient,
+            amount: bitcoin::Amount::from_sat(1000),
+            fees: PegOutFees::new(fee.sats_per_kvb, weight),
+        };
+        assert_eq!(
+            wallet.validate_batched_peg_out(&peg_out, fee, Bitcoin),
+            Ok(())
+        );
+        assert_eq!(
+            wallet.validate_batched_peg_out(
+                &PegOut {
+                    fees: PegOutFees::new(fee.sats_per_kvb, 0),
+                    ..peg_out.clone()
+                },
+                fee,
+                Bitcoin
+            ),
+            Err(WalletOutputError::TxWeightIncorrect(0, weight))
         );
+
+        let peg_outs = vec![
+            TxOut {
+                value: peg_out.amount,
+                script_pubkey: script.clone(),
+            };
+            2
+        ];
+        let fees = peg_out.fees.amount() * 2;
+        let utxo = |sats| {
+            (
+                UTXOKey(OutPoint::null()),
+                SpendableUTXO {
+                    tweak: [0; 33],
+                    amount: bitcoin::Amount::from_sat(sats),
+                },
+            )
+        };
+
+        // not enough SpendableUTXO
+        // peg-outs = 2000, fees = 2 * 219 = 438, change script dust = 330
+        let tx = wallet.create_batch_tx(peg_outs.clone(), fees, fee, vec![utxo(2767)], &[0; 33]);
         assert_eq!(tx, Err(WalletOutputError::NotEnoughSpendableUTXO));
 
-        // successful tx creation
-        let mut tx = wallet
-            .create_tx(
-                Amount::from_sat(1000),
-                recipient.clone().assume_checked().script_pubkey(),
-                vec![],
-                vec![(UTXOKey(OutPoint::null()), spendable)],
+        let tx = wallet
+            .create_batch_tx(
+                peg_outs.clone(),
+                fees,
                 fee,
+                vec![utxo(2768), utxo(10_000)],
                 &[0; 33],
-                None,
             )
             .expect("is ok");
 
-        // peg out weight is incorrectly set to 0
-        let res = StatelessWallet::validate_tx(&tx, &rbf(fee.sats_per_kvb, 0), fee, Bitcoin);
-        assert_eq!(res, Err(WalletOutputError::TxWeightIncorrect(0, weight)));
+        // a single input is enough, so the batch pays a higher fee rate than
+        // the peg-outs did
+        assert_eq!(tx.selected_utxos.len(), 1);
+        assert_eq!(tx.psbt.unsigned_tx.output.len(), 3);
+        assert_eq!(tx.peg_out_amount, Amount::from_sat(2000));
+        assert_eq!(tx.change, Amount::from_sat(10_000 - 2000 - 438));
+        assert!(tx.fees.fee_rate > fee);
+
+        // with more inputs than peg-outs the batch still pays the fee rate for its
+        // weight of 56 + 2 * 125 + 169 + 6 * 525 = 3625
+        let tx = wallet
+            .create_batch_tx(peg_outs, fees, fee, vec![utxo(600); 6], &[0; 33])
+            .expect("is ok");
 
-        // fee rate set below min relay fee to 0
-        let res = StatelessWallet::validate_tx(&tx, &rbf(0, weight), fee, Bitcoin);
-        assert_eq!(res, Err(WalletOutputError::BelowMinRelayFee));
+        assert_eq!(tx.selected_utxos.len(), 6);
+        assert_eq!(tx.fees.total_weight, 3625);
+        assert_eq!(tx.change, Amount::from_sat(3600 - 2000 - 907));
+    }
 
-        // fees are okay
-        let res = StatelessWallet::validate_tx(&tx, &rbf(fee.sats_per_kvb, weight), fee, Bitcoin);
-        assert_eq!(res, Ok(()));
+    #[test]
+    fn taproot_peg_out_is_finalized_with_threshold_signatures() {
+        let secp = secp256k1::Secp256k1::new();
 
-        // tx has fee below consensus
-        tx.fees = PegOutFees::new(0, weight);
-        let res = StatelessWallet::validate_tx(&tx, &rbf(fee.sats_per_kvb, weight), fee, Bitcoin);
-        assert_eq!(
-            res,
-            Err(WalletOutputError::PegOutFeeBelowConsensus(
-                Feerate { sats_per_kvb: 0 },
-                fee
-            ))
-        );
+        let secret_keys = (0..4)
+            .map(|_| secp.generate_keypair(&mut OsRng).0)
+            .collect::<Vec<_>>();
 
-        // tx has peg-out amount under dust limit
-        tx.peg_out_amount = bitcoin::Amount::ZERO;
-        let res = StatelessWallet::validate_tx(&tx, &rbf(fee.sats_per_kvb, weight), fee, Bitcoin);
-        assert_eq!(res, Err(WalletOutputError::PegOutUnderDustLimit));
+        let recipient = Address::from_str("32iVBEu4dxkUQk9dJbZUiBiQdmypcEyJRf").unwrap();
+        let spendable = SpendableUTXO {
+            tweak: [0; 33],
+            amount: bitcoin::Amount::from_sat(100_000),
+        };
 
-        // tx is invalid for network
-        let output = WalletOutputV0::PegOut(PegOut {
-            recipient,
-            amount: bitcoin::Amount::from_sat(1000),
-            fees: PegOutFees::new(100, weight),
-        });
-        let res = StatelessWallet::validate_tx(&tx, &output, fee, Testnet);
-        assert_eq!(
-            res,
-            Err(WalletOutputError::WrongNetwork(
-                NetworkLegacyEncodingWrapper(Testnet),
-                NetworkLegacyEncodingWrapper(Bitcoin)
-            ))
-        );
+        // A federation of four spends through the `multi_a` script, a single guardian
+        // through the key path
+        for (guardians, threshold, witness_items) in [(4, 3, 6), (1, 1, 1)] {
+            let pubkeys = secret_keys
+                .iter()
+                .take(guardians)
+                .enumerate()
+                .map(|(peer, secret_key)| {
+                    (
+                        PeerId::from(peer as u16),
+                        CompressedPublicKey {
+                            key: secret_key.public_key(&secp),
+                        },
+                    )
+                })
+                .collect::<BTreeMap<_, _>>();
+
+            let descriptor = PegInDescriptorKind::Taproot.descriptor(&pubkeys, threshold);
+
+            let wallets = secret_keys
+                .iter()
+                .take(guardians)
+                .map(|secret_key| StatelessWallet {
+                    descriptor: &descriptor,
+                    secret_key,
+                    secp: &secp,
+                })
+                .collect::<Vec<_>>();
+
+            let tx = wallets[0]
+                .create_tx(
+                    vec![TxOut {
+                        value: Amount::from_sat(10_000),
+                        script_pubkey: recipient.clone().assume_checked().script_pubkey(),
+                    }],
+                    vec![],
+                    vec![(UTXOKey(OutPoint::null()), spendable.clone())],
+                    Feerate { sats_per_kvb: 1000 },
+                    &[0; 33],
+                    None,
+                )
+                .expect("is ok");
+
+            let mut psbt = tx.psbt;
+
+            for wallet in &wallets[..threshold - 1] {
+                wallet.sign_psbt(&mut psbt);
+            }
+            if threshold > 1 {
+                assert!(psbt.clone().finalize_mut(&secp).is_err());
+            }
+
+            wallets[threshold - 1].sign_psbt(&mut psbt);
+            psbt.finalize_mut(&secp)
+                .expect("Threshold of signatures is present");
+
+            let tx = psbt.extract_tx_unchecked_fee_rate();
+            assert_eq!(tx.input[0].witness.len(), witness_items);
+        }
     }
 
     fn rbf(sats_per_kvb: u64, total_weight: u64) -> WalletOutputV0 {
@@ -86,3 +86,13 @@
     )
     .unwrap()
 });
+pub(crate) static WALLET_CHAIN_TIP_DIVERGED: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge_with_registry!(
+        opts!(
+            "wallet_chain_tip_diverged",
+            "Whether the bitcoin backend is missing the block hash agreed on by the federation (1) or not (0)",
+        ),
+        REGISTRY
+    )
+    .unwrap()
+});
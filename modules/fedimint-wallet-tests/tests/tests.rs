@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::env;
 use std::str::FromStr;
 use std::time::Duration;
@@ -13,7 +13,8 @@
 use fedimint_core::db::mem_impl::MemDatabase;
 use fedimint_core::db::{DatabaseTransaction, IRawDatabaseExt};
 use fedimint_core::module::{AmountUnit, serde_json};
-use fedimint_core::task::{TaskGroup, sleep_in_test};
+use fedimint_core::setup_code::ModuleSetupParams;
+use fedimint_core::task::{TaskGroup, sleep_in_test, timeout};
 use fedimint_core::util::{BoxStream, NextOrPending, SafeUrl, retry};
 use fedimint_core::{Amount, BitcoinHash, Feerate, InPoint, PeerId, TransactionId, sats};
 use fedimint_dummy_client::DummyClientInit;
@@ -673,6 +674,193 @@ async fn peg_outs_must_wait_for_available_utxos() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn taproot_peg_in_and_peg_out() -> anyhow::Result<()> {
+    let fixtures = fixtures();
+    let fed = fixtures
+        .new_fed_builder(1)
+        .module_params(ModuleSetupParams {
+            wallet_taproot_peg_in: true,
+            ..Default::default()
+        })
+        .build()
+        .await;
+    let client = fed.new_client().await;
+    let bitcoin = fixtures.bitcoin();
+    let bitcoin = bitcoin.lock_exclusive().await;
+    info!("Starting test taproot_peg_in_and_peg_out");
+
+    let finality_delay = 10;
+    bitcoin.mine_blocks(finality_delay).await;
+    await_consensus_to_catch_up(&client, 1).await?;
+
+    let wallet_module = client.get_first_module::<WalletClientModule>()?;
+    let (_, address, _) = wallet_module
+        .allocate_deposit_address_expert_only(())
+        .await?;
+    assert!(address.script_pubkey().is_p2tr());
+
+    let (mut balance_sub, _) = peg_in(&client, bitcoin.as_ref(), finality_delay, &fed).await?;
+
+    // Peg-outs of a taproot federation are signed with Schnorr signatures
+    let address = bitcoin.get_new_address().await;
+    let peg_out = bsats(PEG_OUT_AMOUNT_SATS);
+    let fees = wallet_module.get_withdraw_fees(&address, peg_out).await?;
+    let op = wallet_module.withdraw(&address, peg_out, fees, ()).await?;
+
+    let balance_after_peg_out =
+        sats(PEG_IN_AMOUNT_SATS - PEG_OUT_AMOUNT_SATS - fees.amount().to_sat());
+    assert_eq!(balance_sub.ok().await?, balance_after_peg_out);
+
+    let mut sub = wallet_module
+        .subscribe_withdraw_updates(op)
+        .await?
+        .into_stream();
+    assert_eq!(sub.ok().await?, WithdrawState::Created);
+    let txid = match sub.ok().await? {
+        WithdrawState::Succeeded(txid) => txid,
+        other => panic!("Unexpected state: {other:?}"),
+    };
+
+    // The transaction is only in the mempool if it was finalized with valid
+    // signatures
+    bitcoin.get_mempool_tx_fee(&txid).await;
+    let received = bitcoin.mine_block_and_get_received(&address).await;
+    assert_eq!(received, peg_out.into());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn batched_peg_outs_share_one_transaction() -> anyhow::Result<()> {
+    const PEG_OUT_BATCH_WINDOW: u32 = 2;
+
+    let fixtures = fixtures();
+    let fed = fixtures
+        .new_fed_builder(1)
+        .module_params(ModuleSetupParams {
+            wallet_peg_out_batch_window: Some(PEG_OUT_BATCH_WINDOW),
+            ..Default::default()
+        })
+        .build()
+        .await;
+    let client = fed.new_client().await;
+    let bitcoin = fixtures.bitcoin();
+    let bitcoin = bitcoin.lock_exclusive().await;
+    info!("Starting test batched_peg_outs_share_one_transaction");
+
+    let finality_delay = 10;
+    bitcoin.mine_blocks(finality_delay).await;
+    await_consensus_to_catch_up(&client, 1).await?;
+
+    peg_in(&client, bitcoin.as_ref(), finality_delay, &fed).await?;
+
+    let wallet_module = client.get_first_module::<WalletClientModule>()?;
+    let mut peg_outs = vec![];
+    for _ in 0..2 {
+        // Note: important to use different addresses, see
+        // https://github.com/fedimint/fedimint/issues/3604
+        let address = bitcoin.get_new_address().await;
+        let fees = wallet_module
+            .get_batched_withdraw_fees(&address, bsats(PEG_OUT_AMOUNT_SATS))
+            .await?;
+        let op = wallet_module
+            .withdraw_batched(&address, bsats(PEG_OUT_AMOUNT_SATS), fees, ())
+            .await?;
+
+        let mut sub = wallet_module
+            .subscribe_withdraw_updates(op)
+            .await?
+            .into_stream();
+        assert_eq!(sub.ok().await?, WithdrawState::Created);
+        assert_eq!(sub.ok().await?, WithdrawState::AwaitingBatch);
+        peg_outs.push((address, fees, sub));
+    }
+
+    // The batch is paid once the oldest peg-out was queued for the batching
+    // window, so we mine blocks until it is
+    let mut txids = vec![];
+    for (_, _, sub) in &mut peg_outs {
+        let state = loop {
+            bitcoin.mine_blocks(1).await;
+            if let Ok(state) = timeout(Duration::from_secs(1), sub.ok()).await {
+                break state?;
+            }
+        };
+
+        match state {
+            WithdrawState::Succeeded(txid) => txids.push(txid),
+            other => panic!("Unexpected state: {other:?}"),
+        }
+    }
+
+    assert_eq!(txids[0], txids[1], "Peg-outs weren't batched");
+
+    // A single input funds the batch, so it pays exactly the fees of its peg-outs
+    let tx_fee = bitcoin.get_mempool_tx_fee(&txids[0]).await;
+    let fees = peg_outs
+        .iter()
+        .map(|(_, fees, _)| fees.amount())
+        .sum::<bitcoin::Amount>();
+    assert_eq!(tx_fee, fees.into());
+
+    let (address, _, _) = &peg_outs[0];
+    let received = bitcoin.mine_block_and_get_received(address).await;
+    assert_eq!(received, bsats(PEG_OUT_AMOUNT_SATS).into());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn multi_peg_out_pays_all_recipients_in_one_transaction() -> anyhow::Result<()> {
+    let fixtures = fixtures();
+    let fed = fixtures.new_fed_not_degraded().await;
+    let client = fed.new_client().await;
+    let bitcoin = fixtures.bitcoin();
+    let bitcoin = bitcoin.lock_exclusive().await;
+    info!("Starting test multi_peg_out_pays_all_recipients_in_one_transaction");
+
+    let finality_delay = 10;
+    bitcoin.mine_blocks(finality_delay).await;
+    await_consensus_to_catch_up(&client, 1).await?;
+
+    let (mut balance_sub, _) = peg_in(&client, bitcoin.as_ref(), finality_delay, &fed).await?;
+
+    let wallet_module = client.get_first_module::<WalletClientModule>()?;
+    let recipients = vec![
+        (bitcoin.get_new_address().await, bsats(PEG_OUT_AMOUNT_SATS)),
+        (
+            bitcoin.get_new_address().await,
+            bsats(2 * PEG_OUT_AMOUNT_SATS),
+        ),
+    ];
+    let fees = wallet_module.get_multi_withdraw_fees(&recipients).await?;
+    let op = wallet_module.withdraw_multi(&recipients, fees, ()).await?;
+
+    let balance_after_peg_out =
+        sats(PEG_IN_AMOUNT_SATS - 3 * PEG_OUT_AMOUNT_SATS - fees.amount().to_sat());
+    assert_eq!(balance_sub.ok().await?, balance_after_peg_out);
+
+    // A single operation covers all recipients
+    let mut sub = wallet_module
+        .subscribe_withdraw_updates(op)
+        .await?
+        .into_stream();
+    assert_eq!(sub.ok().await?, WithdrawState::Created);
+    let txid = match sub.ok().await? {
+        WithdrawState::Succeeded(txid) => txid,
+        other => panic!("Unexpected state: {other:?}"),
+    };
+
+    // The fee quote covers the whole transaction
+    let tx_fee = bitcoin.get_mempool_tx_fee(&txid).await;
+    assert_eq!(tx_fee, fees.amount().into());
+
+    for (address, amount) in &recipients {
+        let received = bitcoin.mine_block_and_get_received(address).await;
+        assert_eq!(received, (*amount).into());
+    }
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn peg_ins_that_are_unconfirmed_are_rejected() -> anyhow::Result<()> {
     let fixtures = fixtures();
@@ -1136,6 +1324,9 @@ fn build_wallet_server_configs() -> anyhow::Result<(
     let args = fedimint_server_core::ConfigGenModuleArgs {
         network: bitcoin::Network::Regtest,
         disable_base_fees: false,
+        mint_custom_units: BTreeSet::new(),
+        wallet_peg_out_batch_window: None,
+        wallet_taproot_peg_in: false,
     };
     let wallet_cfg =
         fedimint_server::core::ServerModuleInit::trusted_dealer_gen(&WalletInit, &peers, &args);
@@ -1609,6 +1800,21 @@ async fn test_server_db_migrations() -> anyhow::Result<()> {
                     DbKeyPrefix::RecoveryItem => {
                         // Recovery items are new and won't be in old snapshots
                     }
+                    DbKeyPrefix::QueuedPegOut
+                    | DbKeyPrefix::PegOutBatch
+                    | DbKeyPrefix::TaprootPegOutTxSigCi
+                    | DbKeyPrefix::BlockHashVote
+                    | DbKeyPrefix::ConsolidationVote
+                    | DbKeyPrefix::Consolidation
+                    | DbKeyPrefix::PendingTransactionBlockCount
+                    | DbKeyPrefix::CpfpVote
+                    | DbKeyPrefix::Cpfp
+                    | DbKeyPrefix::FeeReserve => {
+                        // Peg-out batches, taproot signatures, block hash
+                        // votes, consolidations and
+                        // CPFP fee bumping are new and won't be
+                        // in old snapshots
+                    }
                 }
             }
             Ok(())
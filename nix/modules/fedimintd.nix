@@ -177,6 +177,13 @@ let
             description = "Bitcoin node (bitcoind/electrum/esplora) address to connect to";
           };
 
+          electrumUrl = mkOption {
+            type = types.nullOr types.str;
+            default = null;
+            example = "ssl://electrum.blockstream.info:60002";
+            description = "Electrum server (electrs/Fulcrum) to connect to instead of bitcoind or esplora";
+          };
+
         };
 
         consensus.finalityDelay = mkOption {
@@ -255,10 +262,15 @@ in
 
       assertions = [
         {
-          assertion = lib.all (cfg: (cfg.bitcoin.bitcoindUrl != null || cfg.bitcoin.esploraUrl != null)) (
-            lib.mapAttrsToList (name: cfg: cfg) eachFedimintd
-          );
-          message = "Either bitcoindUrl or esploraUrl must be set for each fedimintd instance";
+          assertion = lib.all (
+            cfg:
+            (
+              cfg.bitcoin.bitcoindUrl != null
+              || cfg.bitcoin.esploraUrl != null
+              || cfg.bitcoin.electrumUrl != null
+            )
+          ) (lib.mapAttrsToList (name: cfg: cfg) eachFedimintd);
+          message = "Either bitcoindUrl, esploraUrl or electrumUrl must be set for each fedimintd instance";
         }
       ];
 
@@ -305,6 +317,7 @@ in
                 FM_BITCOIN_NETWORK = cfg.bitcoin.network;
                 FM_BITCOIND_URL = cfg.bitcoin.bitcoindUrl;
                 FM_ESPLORA_URL = cfg.bitcoin.esploraUrl;
+                FM_ELECTRUM_URL = cfg.bitcoin.electrumUrl;
                 FM_BITCOIND_URL_PASSWORD_FILE = cfg.bitcoin.bitcoindSecretFile;
                 FM_BITCOIND_USERNAME = cfg.bitcoin.bitcoindUser;
                 FM_BITCOIND_PASSWORD = cfg.bitcoin.bitcoindPassword;